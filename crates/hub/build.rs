@@ -0,0 +1,28 @@
+//! Embeds the git commit as `GIT_HASH`, reported with the crate version in
+//! the retained `status/hub` message.  A `GIT_HASH` set in the environment
+//! wins (builds without a checkout, e.g. in Docker).
+
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=GIT_HASH");
+    for path in ["../../.git/HEAD", "../../.git/refs/heads"] {
+        if std::path::Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={path}");
+        }
+    }
+    let hash = std::env::var("GIT_HASH")
+        .ok()
+        .filter(|h| !h.trim().is_empty())
+        .or_else(|| {
+            let out = Command::new("git")
+                .args(["rev-parse", "--short=8", "HEAD"])
+                .output()
+                .ok()?;
+            out.status
+                .success()
+                .then(|| String::from_utf8_lossy(&out.stdout).trim().to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=GIT_HASH={hash}");
+}
@@ -0,0 +1,17 @@
+-- Node administration: operator-facing metadata for sensor nodes, and
+-- archival of sensors belonging to decommissioned nodes.
+
+CREATE TABLE IF NOT EXISTS nodes (
+  node_id TEXT PRIMARY KEY,
+  name TEXT NOT NULL,
+
+  -- Expected telemetry cadence (informational; NULL = unknown)
+  sample_interval_sec INTEGER,
+  -- Per-node override of NODE_STALE_TIMEOUT_MIN (NULL = use default)
+  stale_timeout_min INTEGER,
+
+  decommissioned_at INTEGER     -- unix seconds, NULL = active
+);
+
+-- Archived sensors keep their history but are no longer loaded for ingest.
+ALTER TABLE sensors ADD COLUMN archived_at INTEGER;
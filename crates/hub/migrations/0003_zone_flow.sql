@@ -0,0 +1,3 @@
+-- Measured flow rate per zone (litres/minute), used to turn valve open time
+-- into water volume in usage reports.  NULL = no flow measurement.
+ALTER TABLE zones ADD COLUMN flow_lpm REAL;
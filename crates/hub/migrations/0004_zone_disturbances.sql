@@ -0,0 +1,14 @@
+-- Known disturbances per zone (probe pulled for cleaning, bed re-dug, ...).
+-- Readings inside [start_ts, end_ts) are still stored but excluded from
+-- scheduler averages and moisture analytics.  end_ts NULL = still ongoing.
+CREATE TABLE IF NOT EXISTS zone_disturbances (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  zone_id TEXT NOT NULL,
+  start_ts INTEGER NOT NULL,
+  end_ts INTEGER,
+  reason TEXT NOT NULL,
+
+  FOREIGN KEY(zone_id) REFERENCES zones(zone_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_zone_disturbances_zone_ts ON zone_disturbances(zone_id, start_ts);
@@ -0,0 +1,6 @@
+-- Small hub-owned key/value table.  `write_probe` is rewritten periodically
+-- to detect (and recover from) an unwritable database.
+CREATE TABLE IF NOT EXISTS hub_meta (
+  key TEXT PRIMARY KEY,
+  value TEXT NOT NULL
+);
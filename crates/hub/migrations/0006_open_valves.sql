@@ -0,0 +1,8 @@
+-- Valves currently open, so a hub crash doesn't lose the session: on startup
+-- any row left here is closed out (duration counted, event recorded).
+CREATE TABLE IF NOT EXISTS open_valves (
+  zone_id TEXT PRIMARY KEY,
+  opened_ts INTEGER NOT NULL,   -- unix seconds
+
+  FOREIGN KEY(zone_id) REFERENCES zones(zone_id)
+);
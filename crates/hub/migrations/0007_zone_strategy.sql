@@ -0,0 +1,2 @@
+-- Per-zone watering strategy (JSON, see strategy.rs). NULL = moisture threshold.
+ALTER TABLE zones ADD COLUMN strategy TEXT;
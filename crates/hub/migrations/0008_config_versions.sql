@@ -0,0 +1,8 @@
+-- Snapshots of the effective zone / sensor / node configuration, taken on
+-- every change, so a bad edit can be rolled back to a known-good version.
+CREATE TABLE IF NOT EXISTS config_versions (
+  version INTEGER PRIMARY KEY AUTOINCREMENT,
+  created_ts INTEGER NOT NULL,  -- unix seconds
+  reason TEXT NOT NULL,         -- what triggered the snapshot
+  snapshot TEXT NOT NULL        -- JSON ConfigSnapshot
+);
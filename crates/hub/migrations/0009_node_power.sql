@@ -0,0 +1,8 @@
+-- Battery / deep-sleep nodes: instead of the staleness timeout they are
+-- expected to wake every sample_interval_sec, and are only flagged once a
+-- wake is missed by more than the grace factor.
+
+ALTER TABLE nodes ADD COLUMN battery_powered INTEGER NOT NULL DEFAULT 0;
+-- Multiple of sample_interval_sec before a wake counts as missed
+-- (NULL = hub default)
+ALTER TABLE nodes ADD COLUMN wake_grace_factor REAL;
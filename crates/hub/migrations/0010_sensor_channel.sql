@@ -0,0 +1,3 @@
+-- ADS1115 input each sensor is wired to, pushed to its node on
+-- cfg/<node_id>/set.  NULL = derived from the local sensor id ("s1" -> 0).
+ALTER TABLE sensors ADD COLUMN channel INTEGER;
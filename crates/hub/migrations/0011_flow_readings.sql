@@ -0,0 +1,11 @@
+-- Flow-meter samples per zone, from flow/<zone_id>/reading.  Compared
+-- against the zone's own history to spot clogged emitters or filters.
+CREATE TABLE IF NOT EXISTS flow_readings (
+  ts INTEGER NOT NULL,          -- unix seconds
+  zone_id TEXT NOT NULL,
+  lpm REAL NOT NULL,            -- litres/minute
+  pressure_kpa REAL,            -- supply pressure, if the meter reports it
+
+  PRIMARY KEY (ts, zone_id),
+  FOREIGN KEY(zone_id) REFERENCES zones(zone_id)
+);
@@ -0,0 +1,18 @@
+-- Audit log of scheduler evaluations: one row per idle-zone evaluation and
+-- per pulse/soak transition, so skipped waterings can be explained later.
+-- No foreign key on zone_id: the log must not block deleting a zone.
+CREATE TABLE IF NOT EXISTS scheduler_decisions (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  ts INTEGER NOT NULL,          -- unix seconds
+  zone_id TEXT NOT NULL,
+  phase TEXT NOT NULL,          -- idle | watering | soaking
+  avg_moisture REAL,            -- averaged moisture, when it was computed
+  blocked_by TEXT,              -- guard that stopped the evaluation
+  action TEXT NOT NULL,         -- skip | wait | pulse | alert | soak_done | ...
+  detail TEXT NOT NULL DEFAULT ''
+);
+
+CREATE INDEX IF NOT EXISTS idx_scheduler_decisions_zone_ts
+  ON scheduler_decisions(zone_id, ts);
+CREATE INDEX IF NOT EXISTS idx_scheduler_decisions_ts
+  ON scheduler_decisions(ts);
@@ -0,0 +1,3 @@
+-- Zone priority: higher-priority zones are served first when a shared water
+-- budget runs low.
+ALTER TABLE zones ADD COLUMN priority INTEGER NOT NULL DEFAULT 0;
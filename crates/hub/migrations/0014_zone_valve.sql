@@ -0,0 +1,3 @@
+-- Zone valve type (JSON).  NULL = solenoid; motorized ball valves also
+-- record their close-direction pin and travel time.
+ALTER TABLE zones ADD COLUMN valve TEXT;
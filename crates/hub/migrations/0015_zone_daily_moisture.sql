@@ -0,0 +1,14 @@
+-- Daily moisture per zone, rolled up from readings just before retention
+-- pruning deletes them, so seasonal comparisons can look back past the
+-- readings retention window.  Kept even if the zone is later removed.
+CREATE TABLE IF NOT EXISTS zone_daily_moisture (
+  day TEXT NOT NULL,            -- "YYYY-MM-DD" (UTC)
+  zone_id TEXT NOT NULL,
+
+  avg_moisture REAL NOT NULL,
+  min_moisture REAL NOT NULL,
+  max_moisture REAL NOT NULL,
+  samples INTEGER NOT NULL,
+
+  PRIMARY KEY (day, zone_id)
+);
@@ -0,0 +1,3 @@
+-- Zone evaluation dependencies (JSON array of upstream zone ids).  NULL = no
+-- dependencies; a listed zone must finish its cycle for the day first.
+ALTER TABLE zones ADD COLUMN after TEXT;
@@ -0,0 +1,4 @@
+-- Per-zone sensor aggregation ("mean", "median" or "min"; NULL = mean) and
+-- per-sensor weights (0 = excluded from watering decisions).
+ALTER TABLE zones ADD COLUMN aggregation TEXT;
+ALTER TABLE sensors ADD COLUMN weight REAL NOT NULL DEFAULT 1.0;
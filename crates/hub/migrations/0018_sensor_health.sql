@@ -0,0 +1,10 @@
+-- Per-sensor implausible-reading counters.  A sensor with quarantined_ts set
+-- is left out of zone moisture until released through the API.
+-- No foreign key on sensor_id: health must not block deleting a sensor.
+CREATE TABLE IF NOT EXISTS sensor_health (
+  sensor_id TEXT PRIMARY KEY,
+  consecutive_failures INTEGER NOT NULL DEFAULT 0,
+  total_failures INTEGER NOT NULL DEFAULT 0,
+  last_failure_ts INTEGER,      -- unix seconds
+  quarantined_ts INTEGER        -- unix seconds; NULL = in service
+);
@@ -0,0 +1,11 @@
+-- Acknowledged cold-start safety review findings.  A finding is matched on
+-- its exact detail, so changed numbers need acknowledging again.
+-- No foreign key on zone_id: acknowledgements must not block deleting a zone.
+CREATE TABLE IF NOT EXISTS safety_acks (
+  zone_id TEXT NOT NULL,
+  code TEXT NOT NULL,
+  detail TEXT NOT NULL,
+  acked_ts INTEGER NOT NULL,    -- unix seconds
+
+  PRIMARY KEY (zone_id, code, detail)
+);
@@ -0,0 +1,13 @@
+-- Lifetime valve usage per zone, bumped alongside zone_daily_counters.
+-- serviced_* hold the counts at the last recorded service, so usage since
+-- then is the difference; service_due_ts is set once the configured
+-- service threshold is crossed (and the maintenance event raised).
+CREATE TABLE IF NOT EXISTS zone_odometer (
+  zone_id TEXT PRIMARY KEY,
+  actuations INTEGER NOT NULL DEFAULT 0,
+  open_sec INTEGER NOT NULL DEFAULT 0,
+  serviced_actuations INTEGER NOT NULL DEFAULT 0,
+  serviced_open_sec INTEGER NOT NULL DEFAULT 0,
+  serviced_ts INTEGER,          -- unix seconds; NULL = never serviced
+  service_due_ts INTEGER        -- unix seconds; NULL = not due
+);
@@ -0,0 +1,13 @@
+-- WARN and ERROR log records kept for post-mortems when LOG_TO_DB is set
+-- (journald on the Pi rotates them away too quickly).  Pruned after
+-- LOG_RETENTION_DAYS.
+CREATE TABLE IF NOT EXISTS logs (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  ts INTEGER NOT NULL,          -- unix seconds
+  level TEXT NOT NULL,          -- WARN | ERROR
+  target TEXT NOT NULL,         -- module that logged it
+  message TEXT NOT NULL,
+  fields TEXT NOT NULL DEFAULT '{}'  -- other structured fields, JSON object
+);
+
+CREATE INDEX IF NOT EXISTS idx_logs_ts ON logs(ts);
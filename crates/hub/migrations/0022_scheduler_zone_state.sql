@@ -0,0 +1,9 @@
+-- Scheduler state of zones mid-cycle, so a restart resumes the soak rather
+-- than dropping the zone back to idle.  Idle zones have no row.
+CREATE TABLE IF NOT EXISTS scheduler_zone_state (
+  zone_id TEXT PRIMARY KEY,
+  phase TEXT NOT NULL,              -- watering | flushing | soaking
+  started_ts INTEGER NOT NULL,      -- unix seconds
+  until_ts INTEGER NOT NULL,        -- unix seconds: pulse, flush or soak end
+  extended_sec INTEGER NOT NULL DEFAULT 0
+);
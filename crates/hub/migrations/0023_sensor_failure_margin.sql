@@ -0,0 +1,5 @@
+-- Per-sensor plausibility margin beyond the calibration endpoints, either in
+-- raw counts or as a percentage of the calibration range (NULL = the
+-- built-in 3000 counts).
+ALTER TABLE sensors ADD COLUMN failure_margin INTEGER;
+ALTER TABLE sensors ADD COLUMN failure_margin_pct REAL;
@@ -0,0 +1,3 @@
+-- Archived zones are left out of scheduling, GPIO mapping and the default
+-- zone listing, while their readings and watering history are kept.
+ALTER TABLE zones ADD COLUMN archived_at INTEGER;
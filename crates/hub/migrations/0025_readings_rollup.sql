@@ -0,0 +1,27 @@
+-- Per-sensor moisture summaries that outlive the raw readings: retention
+-- pruning rolls readings into hourly rows before deleting them, and hourly
+-- rows into daily rows once they reach the hourly retention.  Daily rows are
+-- kept forever, and kept even if the sensor is later removed.
+CREATE TABLE IF NOT EXISTS readings_hourly (
+  ts INTEGER NOT NULL,          -- start of the UTC hour, unix seconds
+  sensor_id TEXT NOT NULL,
+
+  samples INTEGER NOT NULL,
+  moisture_min REAL NOT NULL,
+  moisture_avg REAL NOT NULL,
+  moisture_max REAL NOT NULL,
+
+  PRIMARY KEY (sensor_id, ts)
+);
+
+CREATE TABLE IF NOT EXISTS readings_daily (
+  ts INTEGER NOT NULL,          -- start of the UTC day, unix seconds
+  sensor_id TEXT NOT NULL,
+
+  samples INTEGER NOT NULL,
+  moisture_min REAL NOT NULL,
+  moisture_avg REAL NOT NULL,
+  moisture_max REAL NOT NULL,
+
+  PRIMARY KEY (sensor_id, ts)
+);
@@ -0,0 +1,16 @@
+-- Zone and sensor changes, one row per changed entity per config version.
+-- Values are the entity's JSON before and after (unset for a create or a
+-- delete).  Kept forever.
+CREATE TABLE IF NOT EXISTS audit_log (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  ts INTEGER NOT NULL,
+  entity TEXT NOT NULL,         -- zone | sensor
+  entity_id TEXT NOT NULL,
+  action TEXT NOT NULL,         -- create | update | delete
+  source TEXT NOT NULL,         -- api | config_file | restore
+  actor TEXT,                   -- API token name
+  old_value TEXT,
+  new_value TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_entity ON audit_log(entity, entity_id, ts);
@@ -0,0 +1,9 @@
+-- Blackout calendar: the scheduler opens no valves on these dates.  Both
+-- ends inclusive, UTC; YYYY-MM-DD for a one-off range, MM-DD for a range
+-- that recurs every year (wrapping over the new year when end < start).
+CREATE TABLE IF NOT EXISTS blackouts (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  start TEXT NOT NULL,
+  end TEXT NOT NULL,
+  reason TEXT NOT NULL
+);
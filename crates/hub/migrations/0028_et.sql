@@ -0,0 +1,14 @@
+-- Reference evapotranspiration per closed UTC day (see `et`).
+CREATE TABLE IF NOT EXISTS et_daily (
+  date TEXT PRIMARY KEY,
+  et0_mm REAL NOT NULL,
+  rain_mm REAL NOT NULL,
+  method TEXT NOT NULL
+);
+
+-- Water-balance deficit of each `et` zone, in mm.
+CREATE TABLE IF NOT EXISTS zone_et_deficit (
+  zone_id TEXT PRIMARY KEY,
+  deficit_mm REAL NOT NULL,
+  updated_at INTEGER NOT NULL
+);
@@ -0,0 +1,9 @@
+-- What each watering event was meant to do and what ended it, so the API
+-- can show expected against actual.  NULL on events recorded before this.
+ALTER TABLE watering_events ADD COLUMN planned_sec INTEGER;  -- NULL = no planned duration
+ALTER TABLE watering_events ADD COLUMN triggered_by TEXT;    -- scheduler | manual | mqtt
+ALTER TABLE watering_events ADD COLUMN ended_by TEXT;        -- off_command | watchdog | emergency | hub_restart | restore
+
+-- Kept with the open valve so an event recovered after a crash still has them.
+ALTER TABLE open_valves ADD COLUMN triggered_by TEXT;
+ALTER TABLE open_valves ADD COLUMN planned_sec INTEGER;
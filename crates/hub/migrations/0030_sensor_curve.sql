@@ -0,0 +1,3 @@
+-- Optional calibration curve (JSON, see calibration.rs).  NULL = linear
+-- between raw_dry and raw_wet.
+ALTER TABLE sensors ADD COLUMN curve TEXT;
@@ -0,0 +1,6 @@
+-- Free-form zone details.  emitter_flow_lph is the nominal flow of the
+-- zone's emitters, used for litre estimates when flow_lpm isn't measured.
+ALTER TABLE zones ADD COLUMN notes TEXT;
+ALTER TABLE zones ADD COLUMN location TEXT;
+ALTER TABLE zones ADD COLUMN plant_type TEXT;
+ALTER TABLE zones ADD COLUMN emitter_flow_lph REAL;
@@ -0,0 +1,4 @@
+-- Sensors can be taken out of service without deleting them (e.g. a
+-- corroded probe awaiting replacement): the node stops sampling the
+-- channel and the sensor no longer counts toward zone moisture.
+ALTER TABLE sensors ADD COLUMN enabled INTEGER NOT NULL DEFAULT 1;
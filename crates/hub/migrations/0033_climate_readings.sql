@@ -0,0 +1,10 @@
+-- Air temperature and humidity from nodes with a climate sensor (DHT22 or
+-- SHT31), sent alongside their soil readings.  They feed the day's ET0.
+CREATE TABLE IF NOT EXISTS climate_readings (
+  ts INTEGER NOT NULL,          -- unix seconds
+  node_id TEXT NOT NULL,
+  air_temp_c REAL,
+  humidity_pct REAL,            -- relative humidity, 0–100
+
+  PRIMARY KEY (ts, node_id)
+);
@@ -0,0 +1,3 @@
+-- Light level from nodes with a BH1750, sent with their climate sample.
+-- Read by the peak-sun deferral (see `sunlight`).
+ALTER TABLE climate_readings ADD COLUMN lux REAL;
@@ -0,0 +1,284 @@
+//! MQTT adapters: translate readings from off-the-shelf firmware (Tasmota,
+//! ESPHome) into hub telemetry, so those devices need no reflashing.
+//!
+//! Each `[[mqtt_adapters]]` entry names a topic the device publishes on and
+//! the hub node its readings belong to.  Each reading picks a value out of
+//! the payload: `field` is a dotted path into a JSON payload (Tasmota's
+//! `tele/<topic>/SENSOR`), or is left out when the whole payload is the
+//! number (ESPHome's `<name>/sensor/<id>/state`).  The value becomes raw
+//! counts as `value × scale + offset`, rounded, and from there is handled
+//! exactly like a `tele/<node_id>/reading` message: sensor `s1` below is
+//! `tasmota-bed/s1` in `[[sensors]]`.  Readings are stamped on arrival.
+//!
+//! ```toml
+//! [[mqtt_adapters]]
+//! topic = "tele/tasmota_bed/SENSOR"
+//! node_id = "tasmota-bed"
+//! readings = [{ sensor_id = "s1", field = "ANALOG.A0", scale = 32 }]
+//!
+//! [[mqtt_adapters]]
+//! topic = "esp-pots/sensor/soil_raw/state"
+//! node_id = "esp-pots"
+//! readings = [{ sensor_id = "s1" }]
+//! ```
+//!
+//! Adapter topics are used as written: `MQTT_TOPIC_PREFIX` doesn't apply.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::mqtt::{
+    PayloadKind, Reject, RejectReason, MAX_JSON_PAYLOAD_BYTES, MAX_READINGS_PER_MESSAGE,
+};
+
+/// A device topic and the node its readings are filed under.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MqttAdapter {
+    /// Exact topic, without wildcards.
+    pub topic: String,
+    pub node_id: String,
+    pub readings: Vec<AdapterReading>,
+}
+
+/// One sensor value in an adapted payload.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AdapterReading {
+    /// Local sensor id, as a node would report it (`s1`).
+    pub sensor_id: String,
+    /// Dotted path to the value in a JSON payload (`ANALOG.A0`); unset when
+    /// the payload is the bare number.
+    #[serde(default)]
+    pub field: Option<String>,
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    #[serde(default)]
+    pub offset: f64,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+/// Check every `[[mqtt_adapters]]` entry.
+pub fn validate(adapters: &[MqttAdapter]) -> Result<(), Vec<String>> {
+    let mut errors = Vec::new();
+    let mut topics = HashSet::new();
+    for a in adapters {
+        let topic = &a.topic;
+        if topic.trim().is_empty() || topic.contains(['+', '#']) {
+            errors.push(format!(
+                "mqtt_adapters: topic '{topic}' must be an exact topic without wildcards"
+            ));
+        }
+        if !topics.insert(topic.as_str()) {
+            errors.push(format!("mqtt_adapters: topic '{topic}' is listed twice"));
+        }
+        if a.node_id.trim().is_empty() || a.node_id.contains('/') {
+            errors.push(format!(
+                "mqtt_adapters: '{topic}' needs a node_id without '/'"
+            ));
+        }
+        if a.readings.is_empty() || a.readings.len() > MAX_READINGS_PER_MESSAGE {
+            errors.push(format!(
+                "mqtt_adapters: '{topic}' needs 1–{MAX_READINGS_PER_MESSAGE} readings"
+            ));
+        }
+        let mut sensors = HashSet::new();
+        for r in &a.readings {
+            let id = &r.sensor_id;
+            if id.trim().is_empty() {
+                errors.push(format!(
+                    "mqtt_adapters: '{topic}' has a reading without sensor_id"
+                ));
+            } else if !sensors.insert(id.as_str()) {
+                errors.push(format!("mqtt_adapters: '{topic}' maps sensor '{id}' twice"));
+            }
+            if r.field
+                .as_deref()
+                .is_some_and(|f| f.split('.').any(str::is_empty))
+            {
+                errors.push(format!(
+                    "mqtt_adapters: '{topic}' sensor '{id}' has an empty field"
+                ));
+            }
+            if !r.scale.is_finite() || r.scale == 0.0 || !r.offset.is_finite() {
+                errors.push(format!(
+                    "mqtt_adapters: '{topic}' sensor '{id}' needs a finite, non-zero scale and a finite offset"
+                ));
+            }
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+impl MqttAdapter {
+    /// Translate a device payload into `tele/<node_id>/reading` JSON
+    /// stamped `ts`.
+    pub(crate) fn translate(&self, payload: &[u8], ts: i64) -> Result<Vec<u8>, Reject> {
+        let kind = PayloadKind::Telemetry;
+        if payload.len() > MAX_JSON_PAYLOAD_BYTES {
+            return Err(Reject::new(
+                kind,
+                RejectReason::TooLarge,
+                None,
+                format!("{} bytes exceeds {MAX_JSON_PAYLOAD_BYTES}", payload.len()),
+            ));
+        }
+        let doc: serde_json::Value = serde_json::from_slice(payload)
+            .map_err(|e| Reject::new(kind, RejectReason::Malformed, None, e.to_string()))?;
+        let readings = self
+            .readings
+            .iter()
+            .map(|r| {
+                let value = r
+                    .field
+                    .as_deref()
+                    .map_or(Some(&doc), |path| {
+                        path.split('.').try_fold(&doc, |v, key| v.get(key))
+                    })
+                    .ok_or_else(|| {
+                        Reject::new(
+                            kind,
+                            RejectReason::MissingField,
+                            r.field.clone(),
+                            format!("no value for sensor '{}'", r.sensor_id),
+                        )
+                    })?;
+                let number = match value {
+                    serde_json::Value::Number(n) => n.as_f64(),
+                    serde_json::Value::String(s) => s.trim().parse().ok(),
+                    _ => None,
+                }
+                .filter(|n: &f64| n.is_finite())
+                .ok_or_else(|| {
+                    Reject::new(
+                        kind,
+                        RejectReason::WrongType,
+                        r.field.clone(),
+                        format!(
+                            "expected a number for sensor '{}', got {value}",
+                            r.sensor_id
+                        ),
+                    )
+                })?;
+                let raw = (number * r.scale + r.offset).round() as i64;
+                Ok(serde_json::json!({ "sensor_id": r.sensor_id, "raw": raw }))
+            })
+            .collect::<Result<Vec<_>, Reject>>()?;
+        Ok(serde_json::json!({ "ts": ts, "readings": readings })
+            .to_string()
+            .into_bytes())
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mqtt::parse_telemetry;
+
+    fn adapter(toml: &str) -> MqttAdapter {
+        let a: MqttAdapter = toml::from_str(toml).unwrap();
+        validate(std::slice::from_ref(&a)).unwrap();
+        a
+    }
+
+    #[test]
+    fn tasmota_json_fields() {
+        let a = adapter(
+            r#"
+            topic = "tele/tasmota_bed/SENSOR"
+            node_id = "tasmota-bed"
+            readings = [
+                { sensor_id = "s1", field = "ANALOG.A0", scale = 32 },
+                { sensor_id = "s2", field = "ANALOG.A1", offset = 100 },
+            ]
+            "#,
+        );
+        let out = a
+            .translate(
+                br#"{"Time":"2024-05-01T10:00:00","ANALOG":{"A0":600,"A1":"250.4"}}"#,
+                1_700_000_000,
+            )
+            .unwrap();
+        let msg = parse_telemetry(&out).unwrap();
+        assert_eq!(msg.ts, 1_700_000_000);
+        let raws: Vec<(&str, i64)> = msg
+            .readings
+            .iter()
+            .map(|r| (r.sensor_id.as_str(), r.raw))
+            .collect();
+        assert_eq!(raws, vec![("s1", 19200), ("s2", 350)]);
+
+        let err = a.translate(br#"{"ANALOG":{"A0":600}}"#, 1).unwrap_err();
+        assert_eq!(err.reason, RejectReason::MissingField);
+        assert_eq!(err.field.as_deref(), Some("ANALOG.A1"));
+        let err = a
+            .translate(br#"{"ANALOG":{"A0":true,"A1":1}}"#, 1)
+            .unwrap_err();
+        assert_eq!(err.reason, RejectReason::WrongType);
+        assert_eq!(
+            a.translate(b"not json", 1).unwrap_err().reason,
+            RejectReason::Malformed
+        );
+    }
+
+    #[test]
+    fn esphome_bare_number() {
+        let a = adapter(
+            r#"
+            topic = "esp-pots/sensor/soil_raw/state"
+            node_id = "esp-pots"
+            readings = [{ sensor_id = "s1" }]
+            "#,
+        );
+        let msg = parse_telemetry(&a.translate(b"18123.6", 5).unwrap()).unwrap();
+        assert_eq!(msg.readings[0].sensor_id, "s1");
+        assert_eq!(msg.readings[0].raw, 18124);
+        assert_eq!(
+            a.translate(b"nan", 5).unwrap_err().reason,
+            RejectReason::Malformed
+        );
+    }
+
+    #[test]
+    fn validation() {
+        let bad: Vec<MqttAdapter> = toml::from_str::<toml::Table>(
+            r#"
+            [[a]]
+            topic = "tele/+/SENSOR"
+            node_id = "a/b"
+            readings = []
+
+            [[a]]
+            topic = "x"
+            node_id = "n"
+            readings = [{ sensor_id = "s1", field = "A..B", scale = 0 }, { sensor_id = "s1" }]
+
+            [[a]]
+            topic = "x"
+            node_id = "n"
+            readings = [{ sensor_id = "" }]
+            "#,
+        )
+        .unwrap()["a"]
+            .clone()
+            .try_into()
+            .unwrap();
+        let errs = validate(&bad).unwrap_err();
+        assert_eq!(errs.len(), 8, "{errs:?}");
+        assert!(errs.iter().any(|e| e.contains("'x' is listed twice")));
+        assert!(errs.iter().any(|e| e.contains("maps sensor 's1' twice")));
+        assert!(validate(&[]).is_ok());
+    }
+}
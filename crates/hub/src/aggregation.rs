@@ -0,0 +1,158 @@
+//! Combining a zone's sensors into the single moisture value the scheduler
+//! acts on.
+//!
+//! Each sensor is first smoothed over its own recent readings; the zone's
+//! [`Aggregation`] then combines those per-sensor values, honouring each
+//! sensor's `weight`.  Sensors with weight 0 (and sensors currently
+//! reporting implausible values) are left out, so one shaded or failing
+//! probe doesn't dominate watering decisions.
+
+use serde::{Deserialize, Serialize};
+
+/// How a zone combines its sensors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Aggregation {
+    /// Weighted mean (default).
+    #[default]
+    Mean,
+    /// Weighted median: robust against a single outlier probe.
+    Median,
+    /// Driest sensor: water until every probe is satisfied.
+    Min,
+}
+
+impl Aggregation {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Mean => "mean",
+            Self::Median => "median",
+            Self::Min => "min",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "mean" => Some(Self::Mean),
+            "median" => Some(Self::Median),
+            "min" => Some(Self::Min),
+            _ => None,
+        }
+    }
+
+    /// Combine per-sensor moisture values.  `None` when no sensor with a
+    /// positive weight is left.
+    pub fn combine(self, sensors: &[SensorMoisture]) -> Option<f32> {
+        let mut used: Vec<(f64, f64)> = sensors
+            .iter()
+            .filter(|s| s.weight > 0.0)
+            .map(|s| (f64::from(s.moisture), s.weight))
+            .collect();
+        if used.is_empty() {
+            return None;
+        }
+        let value = match self {
+            Self::Mean => {
+                let total: f64 = used.iter().map(|(_, w)| w).sum();
+                used.iter().map(|(m, w)| m * w).sum::<f64>() / total
+            }
+            Self::Median => {
+                used.sort_by(|a, b| a.0.total_cmp(&b.0));
+                let half = used.iter().map(|(_, w)| w).sum::<f64>() / 2.0;
+                let mut acc = 0.0;
+                let mut value = used[used.len() - 1].0;
+                for (i, (m, w)) in used.iter().enumerate() {
+                    acc += w;
+                    if acc > half {
+                        value = *m;
+                        break;
+                    }
+                    if acc == half {
+                        // Exactly half the weight on each side.
+                        value = (m + used.get(i + 1).map_or(*m, |n| n.0)) / 2.0;
+                        break;
+                    }
+                }
+                value
+            }
+            Self::Min => used.iter().map(|(m, _)| *m).fold(f64::INFINITY, f64::min),
+        };
+        Some(value as f32)
+    }
+}
+
+/// One sensor's smoothed moisture and its configured weight.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensorMoisture {
+    pub sensor_id: String,
+    pub weight: f64,
+    pub moisture: f32,
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s(moisture: f32, weight: f64) -> SensorMoisture {
+        SensorMoisture {
+            sensor_id: format!("s{moisture}"),
+            weight,
+            moisture,
+        }
+    }
+
+    fn approx(v: Option<f32>, expected: f32) {
+        let v = v.expect("a value");
+        assert!((v - expected).abs() < 1e-5, "got {v}, expected {expected}");
+    }
+
+    #[test]
+    fn mean_is_weighted() {
+        approx(Aggregation::Mean.combine(&[s(0.2, 1.0), s(0.5, 2.0)]), 0.4);
+        // Zero weight drops the shaded probe entirely.
+        approx(Aggregation::Mean.combine(&[s(0.1, 0.0), s(0.5, 1.0)]), 0.5);
+    }
+
+    #[test]
+    fn median_resists_one_outlier() {
+        approx(
+            Aggregation::Median.combine(&[s(0.05, 1.0), s(0.40, 1.0), s(0.42, 1.0)]),
+            0.40,
+        );
+        approx(
+            Aggregation::Median.combine(&[s(0.2, 1.0), s(0.4, 1.0)]),
+            0.3,
+        );
+        // A heavy sensor pulls the median to itself.
+        approx(
+            Aggregation::Median.combine(&[s(0.2, 1.0), s(0.3, 1.0), s(0.6, 3.0)]),
+            0.6,
+        );
+    }
+
+    #[test]
+    fn min_takes_driest_weighted_sensor() {
+        approx(
+            Aggregation::Min.combine(&[s(0.1, 0.0), s(0.3, 1.0), s(0.5, 1.0)]),
+            0.3,
+        );
+    }
+
+    #[test]
+    fn nothing_left_to_combine() {
+        assert_eq!(Aggregation::Mean.combine(&[]), None);
+        assert_eq!(Aggregation::Min.combine(&[s(0.3, 0.0)]), None);
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for a in [Aggregation::Mean, Aggregation::Median, Aggregation::Min] {
+            assert_eq!(Aggregation::parse(a.as_str()), Some(a));
+        }
+        assert_eq!(Aggregation::parse("max"), None);
+    }
+}
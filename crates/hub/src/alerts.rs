@@ -0,0 +1,269 @@
+//! Low-moisture alerts, separate from the watering thresholds: warn at
+//! 0.35 before a zone with `min_moisture = 0.30` is watered, in monitor and
+//! auto mode alike.
+//!
+//! ```toml
+//! [moisture_alerts]
+//! below = 0.35          # every zone (optional)
+//! hysteresis = 0.02     # cleared once moisture is this far above the threshold
+//! cooldown_min = 240    # at most one alert per zone this often
+//!
+//! [moisture_alerts.zones]
+//! seedlings = 0.45      # overrides `below`
+//! ```
+//!
+//! The scheduler feeds each zone's moisture, as it sees it for watering,
+//! in every tick.  Alerts and recoveries are recorded as `alert` events,
+//! which reach the dashboard and the `status/hub/heartbeat` event list.
+//! A zone that dips again within the cooldown is marked low (see
+//! `/api/status`) without a second alert.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct MoistureAlertConfig {
+    /// Threshold for every zone not listed in `zones`.
+    pub below: Option<f32>,
+    /// Per-zone thresholds.
+    pub zones: BTreeMap<String, f32>,
+    pub hysteresis: f32,
+    pub cooldown_min: i64,
+}
+
+impl Default for MoistureAlertConfig {
+    fn default() -> Self {
+        Self {
+            below: None,
+            zones: BTreeMap::new(),
+            hysteresis: 0.02,
+            cooldown_min: 240,
+        }
+    }
+}
+
+impl MoistureAlertConfig {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        let thresholds = self
+            .below
+            .map(|t| ("below".to_string(), t))
+            .into_iter()
+            .chain(self.zones.iter().map(|(z, &t)| (format!("zones.{z}"), t)));
+        for (key, t) in thresholds {
+            if !(t > 0.0 && t < 1.0) {
+                errors.push(format!(
+                    "moisture_alerts: {key} must be between 0 and 1 (exclusive), got {t}"
+                ));
+            }
+        }
+        if !(0.0..=0.5).contains(&self.hysteresis) {
+            errors.push(format!(
+                "moisture_alerts: hysteresis must be between 0 and 0.5, got {}",
+                self.hysteresis
+            ));
+        }
+        if self.cooldown_min < 0 {
+            errors.push(format!(
+                "moisture_alerts: cooldown_min must not be negative, got {}",
+                self.cooldown_min
+            ));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Alert threshold for `zone_id`, if it has one.
+    pub fn threshold(&self, zone_id: &str) -> Option<f32> {
+        self.zones.get(zone_id).copied().or(self.below)
+    }
+}
+
+/// An alert state change caused by a moisture value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Change {
+    Raised { threshold: f32 },
+    Cleared,
+}
+
+/// A zone currently below its alert threshold, listed under
+/// `moisture_alerts` in `/api/status`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LowZone {
+    pub zone_id: String,
+    pub threshold: f32,
+    pub moisture: f32,
+    /// When it went low (unix seconds).
+    pub since: i64,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ZoneAlert {
+    /// Set while the zone is below the threshold (and not yet back above
+    /// threshold + hysteresis).
+    low_since: Option<i64>,
+    moisture: f32,
+    /// This low spell was alerted, so its recovery is too.
+    alerted: bool,
+    last_alert: Option<i64>,
+}
+
+/// Alert state per zone.
+#[derive(Debug, Clone, Default)]
+pub struct MoistureAlerts {
+    cfg: MoistureAlertConfig,
+    zones: BTreeMap<String, ZoneAlert>,
+}
+
+impl MoistureAlerts {
+    pub fn new(cfg: &MoistureAlertConfig) -> Self {
+        Self {
+            cfg: cfg.clone(),
+            zones: BTreeMap::new(),
+        }
+    }
+
+    pub fn threshold(&self, zone_id: &str) -> Option<f32> {
+        self.cfg.threshold(zone_id)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.cfg.below.is_some() || !self.cfg.zones.is_empty()
+    }
+
+    /// Take a zone's current moisture.
+    pub fn observe(&mut self, zone_id: &str, moisture: f32, now_ts: i64) -> Option<Change> {
+        let threshold = self.threshold(zone_id)?;
+        let cooldown_sec = self.cfg.cooldown_min * 60;
+        let zone = self.zones.entry(zone_id.to_string()).or_default();
+        zone.moisture = moisture;
+        match zone.low_since {
+            None if moisture < threshold => {
+                zone.low_since = Some(now_ts);
+                zone.alerted = zone
+                    .last_alert
+                    .is_none_or(|last| now_ts - last >= cooldown_sec);
+                if zone.alerted {
+                    zone.last_alert = Some(now_ts);
+                    Some(Change::Raised { threshold })
+                } else {
+                    None
+                }
+            }
+            Some(_) if moisture >= threshold + self.cfg.hysteresis => {
+                zone.low_since = None;
+                std::mem::take(&mut zone.alerted).then_some(Change::Cleared)
+            }
+            _ => None,
+        }
+    }
+
+    /// Zones currently below their threshold.
+    pub fn low_zones(&self) -> Vec<LowZone> {
+        self.zones
+            .iter()
+            .filter_map(|(zone_id, z)| {
+                Some(LowZone {
+                    zone_id: zone_id.clone(),
+                    threshold: self.threshold(zone_id)?,
+                    moisture: z.moisture,
+                    since: z.low_since?,
+                })
+            })
+            .collect()
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alerts() -> MoistureAlerts {
+        MoistureAlerts::new(&MoistureAlertConfig {
+            below: Some(0.35),
+            zones: BTreeMap::from([("seedlings".to_string(), 0.45)]),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn raises_below_threshold_and_clears_above_hysteresis() {
+        let mut a = alerts();
+        assert_eq!(a.observe("lawn", 0.40, 0), None);
+        assert_eq!(
+            a.observe("lawn", 0.34, 60),
+            Some(Change::Raised { threshold: 0.35 })
+        );
+        // Still low, and within the hysteresis band: no repeats.
+        assert_eq!(a.observe("lawn", 0.33, 120), None);
+        assert_eq!(a.observe("lawn", 0.36, 180), None);
+        assert_eq!(
+            a.low_zones(),
+            vec![LowZone {
+                zone_id: "lawn".into(),
+                threshold: 0.35,
+                moisture: 0.36,
+                since: 60,
+            }]
+        );
+        assert_eq!(a.observe("lawn", 0.38, 240), Some(Change::Cleared));
+        assert!(a.low_zones().is_empty());
+    }
+
+    #[test]
+    fn cooldown_suppresses_repeat_alerts() {
+        let mut a = alerts();
+        assert!(a.observe("lawn", 0.30, 0).is_some());
+        assert_eq!(a.observe("lawn", 0.40, 60), Some(Change::Cleared));
+        // Dips again an hour later: marked low, but not alerted, so its
+        // recovery isn't either.
+        assert_eq!(a.observe("lawn", 0.30, 3600), None);
+        assert_eq!(a.low_zones().len(), 1);
+        assert_eq!(a.observe("lawn", 0.40, 3660), None);
+        // After the 240 min cooldown it alerts again.
+        assert_eq!(
+            a.observe("lawn", 0.30, 240 * 60),
+            Some(Change::Raised { threshold: 0.35 })
+        );
+    }
+
+    #[test]
+    fn per_zone_thresholds_override_the_default() {
+        let mut a = alerts();
+        assert_eq!(
+            a.observe("seedlings", 0.40, 0),
+            Some(Change::Raised { threshold: 0.45 })
+        );
+        let mut only_listed = MoistureAlerts::new(&MoistureAlertConfig {
+            zones: BTreeMap::from([("seedlings".to_string(), 0.45)]),
+            ..Default::default()
+        });
+        assert!(only_listed.is_enabled());
+        assert_eq!(only_listed.observe("lawn", 0.05, 0), None);
+        assert!(!MoistureAlerts::default().is_enabled());
+    }
+
+    #[test]
+    fn config_validation() {
+        assert!(MoistureAlertConfig::default().validate().is_ok());
+        let errs = MoistureAlertConfig {
+            below: Some(1.2),
+            zones: BTreeMap::from([("lawn".to_string(), 0.0)]),
+            hysteresis: -0.1,
+            cooldown_min: -5,
+        }
+        .validate()
+        .unwrap_err();
+        assert_eq!(errs.len(), 4, "{errs:?}");
+        assert!(errs[1].contains("zones.lawn"), "{errs:?}");
+    }
+}
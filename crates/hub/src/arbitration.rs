@@ -0,0 +1,161 @@
+//! Order in which the scheduler offers free valve slots to zones.
+//!
+//! With `max_concurrent_valves` below the number of zones, whichever zone
+//! the scheduler evaluates first takes a free slot.  Each tick zones are
+//! therefore evaluated in this order:
+//!
+//! 1. effective priority, highest first: the zone's `priority` plus one
+//!    level for every [`AGING_SEC`] it has been waiting for a slot, so a
+//!    low-priority zone can't be starved by busier ones;
+//! 2. longest wait first;
+//! 3. driest first: furthest below `min_moisture` by the latest reading
+//!    (zones without one last);
+//! 4. zone id, so the order is stable.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use tokio::time::Instant;
+
+use crate::db::ZoneConfig;
+
+/// Waiting time that raises a zone's effective priority by one.
+pub const AGING_SEC: u64 = 600;
+
+/// Zones waiting for a free valve slot, and since when.
+#[derive(Debug, Default)]
+pub struct FairQueue {
+    waiting_since: HashMap<String, Instant>,
+}
+
+impl FairQueue {
+    /// Note that `zone_id` wanted a slot and found none (keeps the earliest
+    /// time).
+    pub fn mark_waiting(&mut self, zone_id: &str, now: Instant) {
+        self.waiting_since.entry(zone_id.to_string()).or_insert(now);
+    }
+
+    /// The zone got a slot, or stopped needing one.
+    pub fn clear(&mut self, zone_id: &str) {
+        self.waiting_since.remove(zone_id);
+    }
+
+    /// Seconds `zone_id` has been waiting, if it is.
+    pub fn waited_sec(&self, zone_id: &str, now: Instant) -> Option<u64> {
+        self.waiting_since
+            .get(zone_id)
+            .map(|since| now.saturating_duration_since(*since).as_secs())
+    }
+
+    pub fn effective_priority(&self, cfg: &ZoneConfig, now: Instant) -> i64 {
+        let aged = self.waited_sec(&cfg.zone_id, now).unwrap_or(0) / AGING_SEC;
+        cfg.priority
+            .saturating_add(i64::try_from(aged).unwrap_or(i64::MAX))
+    }
+
+    /// Zones in evaluation order.  `moisture` gives a zone's latest
+    /// reading.
+    pub fn order<'a>(
+        &self,
+        zones: &'a HashMap<String, ZoneConfig>,
+        moisture: impl Fn(&str) -> Option<f32>,
+        now: Instant,
+    ) -> Vec<&'a ZoneConfig> {
+        let mut keyed: Vec<(i64, u64, Option<f32>, &ZoneConfig)> = zones
+            .values()
+            .map(|cfg| {
+                let deficit = moisture(&cfg.zone_id).map(|m| cfg.min_moisture - m);
+                (
+                    self.effective_priority(cfg, now),
+                    self.waited_sec(&cfg.zone_id, now).unwrap_or(0),
+                    deficit,
+                    cfg,
+                )
+            })
+            .collect();
+        keyed.sort_by(|a, b| {
+            b.0.cmp(&a.0)
+                .then(b.1.cmp(&a.1))
+                .then_with(|| match (a.2, b.2) {
+                    (Some(x), Some(y)) => y.partial_cmp(&x).unwrap_or(Ordering::Equal),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                })
+                .then_with(|| a.3.zone_id.cmp(&b.3.zone_id))
+        });
+        keyed.into_iter().map(|(.., cfg)| cfg).collect()
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn zone(zone_id: &str, priority: i64) -> ZoneConfig {
+        ZoneConfig {
+            name: zone_id.into(),
+            max_open_sec_per_day: 600,
+            max_pulses_per_day: 10,
+            priority,
+            ..ZoneConfig::test(zone_id)
+        }
+    }
+
+    fn zones(list: &[(&str, i64)]) -> HashMap<String, ZoneConfig> {
+        list.iter()
+            .map(|(id, p)| (id.to_string(), zone(id, *p)))
+            .collect()
+    }
+
+    fn ids(order: Vec<&ZoneConfig>) -> Vec<&str> {
+        order.into_iter().map(|z| z.zone_id.as_str()).collect()
+    }
+
+    #[test]
+    fn priority_then_dryness_then_id() {
+        let zones = zones(&[("a", 0), ("b", 0), ("c", 5), ("d", 0)]);
+        let moisture = |id: &str| match id {
+            "a" => Some(0.28),
+            "b" => Some(0.10),
+            _ => None,
+        };
+        let q = FairQueue::default();
+        assert_eq!(
+            ids(q.order(&zones, moisture, Instant::now())),
+            ["c", "b", "a", "d"]
+        );
+    }
+
+    #[test]
+    fn waiting_zones_age_past_higher_priority() {
+        let zones = zones(&[("busy", 2), ("starved", 0)]);
+        let start = Instant::now();
+        let mut q = FairQueue::default();
+        q.mark_waiting("starved", start);
+        q.mark_waiting("starved", start + Duration::from_secs(60));
+
+        let later = start + Duration::from_secs(AGING_SEC);
+        assert_eq!(ids(q.order(&zones, |_| None, later)), ["busy", "starved"]);
+        assert_eq!(q.effective_priority(&zones["starved"], later), 1);
+
+        // Two levels up it ties with `busy` and wins on waiting time.
+        let much_later = start + Duration::from_secs(2 * AGING_SEC);
+        assert_eq!(
+            ids(q.order(&zones, |_| None, much_later)),
+            ["starved", "busy"]
+        );
+
+        q.clear("starved");
+        assert_eq!(
+            ids(q.order(&zones, |_| None, much_later)),
+            ["busy", "starved"]
+        );
+        assert_eq!(q.waited_sec("starved", much_later), None);
+    }
+}
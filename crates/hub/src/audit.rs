@@ -0,0 +1,171 @@
+//! Audit log of zone and sensor changes.
+//!
+//! Every stored config version is compared with the one before it; each
+//! zone or sensor that was created, changed or deleted gets an `audit_log`
+//! row with its old and new value, where the change came from (the API,
+//! `config.toml` at startup, or a backup restore) and, for the API, the
+//! name of the token that made it.  Served newest first by
+//! `GET /api/audit`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::db::ConfigSnapshot;
+
+/// Where a configuration change came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditSource {
+    Api,
+    ConfigFile,
+    Restore,
+}
+
+impl AuditSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Api => "api",
+            Self::ConfigFile => "config_file",
+            Self::Restore => "restore",
+        }
+    }
+}
+
+/// One changed zone or sensor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    /// `zone` or `sensor`.
+    pub entity: &'static str,
+    pub entity_id: String,
+    /// Unset for a create.
+    pub old: Option<Value>,
+    /// Unset for a delete.
+    pub new: Option<Value>,
+}
+
+impl Change {
+    /// `create`, `update` or `delete`.
+    pub fn action(&self) -> &'static str {
+        match (&self.old, &self.new) {
+            (None, _) => "create",
+            (_, None) => "delete",
+            _ => "update",
+        }
+    }
+}
+
+/// A stored audit row.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub ts: i64,
+    pub entity: String,
+    pub entity_id: String,
+    pub action: String,
+    pub source: String,
+    /// Token name, for API changes made with a token.
+    pub actor: Option<String>,
+    pub old_value: Option<Value>,
+    pub new_value: Option<Value>,
+}
+
+/// Zones and sensors that differ between two snapshots.  Without an
+/// `old` snapshot everything in `new` is a create.
+pub fn diff(old: Option<&ConfigSnapshot>, new: &ConfigSnapshot) -> Vec<Change> {
+    let mut changes = diff_entities(
+        "zone",
+        old.map(|s| by_id(&s.zones, |z| &z.zone_id)),
+        by_id(&new.zones, |z| &z.zone_id),
+    );
+    changes.extend(diff_entities(
+        "sensor",
+        old.map(|s| by_id(&s.sensors, |s| &s.sensor_id)),
+        by_id(&new.sensors, |s| &s.sensor_id),
+    ));
+    changes
+}
+
+fn by_id<T: Serialize>(items: &[T], id: impl Fn(&T) -> &String) -> BTreeMap<String, Value> {
+    items
+        .iter()
+        .map(|item| {
+            (
+                id(item).clone(),
+                serde_json::to_value(item).unwrap_or(Value::Null),
+            )
+        })
+        .collect()
+}
+
+fn diff_entities(
+    entity: &'static str,
+    old: Option<BTreeMap<String, Value>>,
+    new: BTreeMap<String, Value>,
+) -> Vec<Change> {
+    let old = old.unwrap_or_default();
+    let ids: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    ids.into_iter()
+        .filter(|id| old.get(*id) != new.get(*id))
+        .map(|id| Change {
+            entity,
+            entity_id: id.clone(),
+            old: old.get(id).cloned(),
+            new: new.get(id).cloned(),
+        })
+        .collect()
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::SensorConfig;
+
+    fn sensor(sensor_id: &str, raw_dry: i64) -> SensorConfig {
+        SensorConfig {
+            sensor_id: sensor_id.into(),
+            node_id: "n1".into(),
+            zone_id: "z1".into(),
+            raw_dry,
+            raw_wet: 12000,
+            channel: None,
+            archived_at: None,
+            weight: 1.0,
+            failure_margin: None,
+            failure_margin_pct: None,
+            curve: None,
+            enabled: true,
+        }
+    }
+
+    fn snapshot(sensors: Vec<SensorConfig>) -> ConfigSnapshot {
+        ConfigSnapshot {
+            zones: Vec::new(),
+            sensors,
+            nodes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn diff_reports_creates_updates_and_deletes() {
+        let before = snapshot(vec![sensor("a", 26000), sensor("b", 26000)]);
+        let after = snapshot(vec![sensor("b", 25000), sensor("c", 26000)]);
+
+        let changes = diff(Some(&before), &after);
+        let summary: Vec<(&str, &str)> = changes
+            .iter()
+            .map(|c| (c.entity_id.as_str(), c.action()))
+            .collect();
+        assert_eq!(summary, [("a", "delete"), ("b", "update"), ("c", "create")]);
+        assert_eq!(changes[1].entity, "sensor");
+        assert_eq!(changes[1].old.as_ref().unwrap()["raw_dry"], 26000);
+        assert_eq!(changes[1].new.as_ref().unwrap()["raw_dry"], 25000);
+
+        assert!(diff(Some(&after), &after).is_empty());
+        assert_eq!(diff(None, &after).len(), 2);
+    }
+}
@@ -0,0 +1,341 @@
+//! API tokens and the role each route needs.
+//!
+//! `API_TOKEN` is the admin token.  `API_TOKENS` adds named tokens with a
+//! role, as a comma-separated list of `name:role:token`:
+//!
+//! ```text
+//! API_TOKENS=greenhouse:operator:s3cret,wall-display:viewer:0th3r
+//! ```
+//!
+//! Roles are cumulative: `viewer` reads status, readings and history;
+//! `operator` also runs the garden (water a zone now, cancel a session,
+//! record a disturbance, restart a node); `admin` may do anything,
+//! including editing zones, sensors, nodes and retention, reading and
+//! rolling back config versions, restoring backups, pushing node updates
+//! and clearing the emergency stop.  With no token configured the API is
+//! open (dev mode).
+//!
+//! `NODE_TOKENS` is separate: `node_id:token` pairs that let a node post
+//! its own telemetry to `POST /api/telemetry` and nothing else.
+//!
+//! ```text
+//! NODE_TOKENS=esp-bed:k3y1,esp-pots:k3y2
+//! ```
+
+use axum::http::Method;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Viewer,
+    Operator,
+    Admin,
+}
+
+impl Role {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "viewer" => Some(Self::Viewer),
+            "operator" => Some(Self::Operator),
+            "admin" => Some(Self::Admin),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Viewer => "viewer",
+            Self::Operator => "operator",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+/// Who made a request: the token's name and role.  Added to the request
+/// extensions by the auth middleware when tokens are configured.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Identity {
+    pub name: String,
+    pub role: Role,
+}
+
+/// Name given to the `API_TOKEN` token.
+pub const LEGACY_TOKEN_NAME: &str = "admin";
+
+/// The configured tokens.  Empty means the API is open.
+#[derive(Debug, Clone, Default)]
+pub struct ApiTokens {
+    tokens: Vec<(String, Identity)>,
+}
+
+impl ApiTokens {
+    /// Build from the `API_TOKEN` and `API_TOKENS` values; unset and empty
+    /// are the same.
+    pub fn parse(api_token: Option<&str>, api_tokens: Option<&str>) -> Result<Self, Vec<String>> {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        if let Some(token) = api_token.map(str::trim).filter(|t| !t.is_empty()) {
+            tokens.push((
+                token.to_string(),
+                Identity {
+                    name: LEGACY_TOKEN_NAME.to_string(),
+                    role: Role::Admin,
+                },
+            ));
+        }
+        for entry in api_tokens
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+        {
+            // The token itself may contain ':'.
+            let mut parts = entry.splitn(3, ':');
+            let (name, role, token) = match (parts.next(), parts.next(), parts.next()) {
+                (Some(name), Some(role), Some(token)) => (name.trim(), role, token.trim()),
+                _ => {
+                    errors.push(format!(
+                        "API_TOKENS: '{}' must be name:role:token",
+                        name_of(entry)
+                    ));
+                    continue;
+                }
+            };
+            let Some(role) = Role::parse(role) else {
+                errors.push(format!(
+                    "API_TOKENS: '{name}' has unknown role '{}' (viewer, operator or admin)",
+                    role.trim()
+                ));
+                continue;
+            };
+            if name.is_empty() || token.is_empty() {
+                errors.push(format!(
+                    "API_TOKENS: '{}' needs a name and a token",
+                    name_of(entry)
+                ));
+                continue;
+            }
+            if tokens.iter().any(|(_, id)| id.name == name) {
+                errors.push(format!("API_TOKENS: name '{name}' is used twice"));
+            } else if tokens.iter().any(|(t, _)| t == token) {
+                errors.push(format!("API_TOKENS: '{name}' reuses another token"));
+            } else {
+                tokens.push((
+                    token.to_string(),
+                    Identity {
+                        name: name.to_string(),
+                        role,
+                    },
+                ));
+            }
+        }
+        if errors.is_empty() {
+            Ok(Self { tokens })
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// No tokens: every request is allowed.
+    pub fn is_open(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// The identity of an `Authorization` header value, if it carries a
+    /// known bearer token.
+    pub fn identify(&self, authorization: &str) -> Option<&Identity> {
+        self.identify_token(authorization.strip_prefix("Bearer ")?)
+    }
+
+    /// The identity a bare token belongs to.
+    pub fn identify_token(&self, token: &str) -> Option<&Identity> {
+        let token = token.trim();
+        self.tokens
+            .iter()
+            .find(|(t, _)| t == token)
+            .map(|(_, id)| id)
+    }
+}
+
+/// Per-node telemetry tokens from `NODE_TOKENS`.  Empty means HTTP
+/// telemetry is off.
+#[derive(Debug, Clone, Default)]
+pub struct NodeTokens {
+    tokens: Vec<(String, String)>,
+}
+
+impl NodeTokens {
+    /// Build from a comma-separated list of `node_id:token`.
+    pub fn parse(node_tokens: Option<&str>) -> Result<Self, Vec<String>> {
+        let mut tokens: Vec<(String, String)> = Vec::new();
+        let mut errors = Vec::new();
+        for entry in node_tokens
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+        {
+            let Some((node_id, token)) = entry
+                .split_once(':')
+                .map(|(n, t)| (n.trim(), t.trim()))
+                .filter(|(n, t)| !n.is_empty() && !t.is_empty())
+            else {
+                errors.push(format!(
+                    "NODE_TOKENS: '{}' must be node_id:token",
+                    name_of(entry)
+                ));
+                continue;
+            };
+            if tokens.iter().any(|(_, n)| n == node_id) {
+                errors.push(format!("NODE_TOKENS: node '{node_id}' is listed twice"));
+            } else if tokens.iter().any(|(t, _)| t == token) {
+                errors.push(format!("NODE_TOKENS: '{node_id}' reuses another token"));
+            } else {
+                tokens.push((token.to_string(), node_id.to_string()));
+            }
+        }
+        if errors.is_empty() {
+            Ok(Self { tokens })
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// The node an `Authorization` header value belongs to.
+    pub fn node_for(&self, authorization: &str) -> Option<&str> {
+        let token = authorization.strip_prefix("Bearer ")?.trim();
+        self.tokens
+            .iter()
+            .find(|(t, _)| t == token)
+            .map(|(_, node_id)| node_id.as_str())
+    }
+}
+
+/// Start of an `API_TOKENS` entry for error messages, without the token.
+fn name_of(entry: &str) -> &str {
+    entry.split(':').next().unwrap_or_default().trim()
+}
+
+/// The least role allowed to make a request.  Reads need `viewer`, except
+/// for config versions and backups; writes need `admin` unless listed as
+/// day-to-day operation.
+pub fn required_role(method: &Method, path: &str) -> Role {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    if matches!(
+        segments.as_slice(),
+        ["api", "config", ..] | ["api", "backups", ..]
+    ) {
+        return Role::Admin;
+    }
+    if method == Method::GET || method == Method::HEAD {
+        return Role::Viewer;
+    }
+    match segments.as_slice() {
+        ["api", "sessions", _, "cancel"]
+        | ["api", "zones", _, "water-now"]
+        | ["api", "zones", _, "disturbances", ..]
+        | ["api", "zones", _, "odometer", "service"]
+        | ["api", "nodes", _, "restart" | "send-logs" | "ping"] => Role::Operator,
+        _ => Role::Admin,
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_tokens() {
+        let tokens = ApiTokens::parse(
+            Some("root-token"),
+            Some(" greenhouse:Operator:a:b , wall:viewer:w,"),
+        )
+        .unwrap();
+        assert!(!tokens.is_open());
+        assert_eq!(
+            tokens.identify("Bearer root-token"),
+            Some(&Identity {
+                name: LEGACY_TOKEN_NAME.into(),
+                role: Role::Admin
+            })
+        );
+        let greenhouse = tokens.identify("Bearer a:b").unwrap();
+        assert_eq!(greenhouse.name, "greenhouse");
+        assert_eq!(greenhouse.role, Role::Operator);
+        assert_eq!(tokens.identify("Bearer w").unwrap().role, Role::Viewer);
+        assert_eq!(tokens.identify("Bearer nope"), None);
+        assert_eq!(tokens.identify("w"), None);
+
+        assert!(ApiTokens::parse(Some(""), None).unwrap().is_open());
+        assert!(ApiTokens::parse(None, Some(" , ")).unwrap().is_open());
+
+        let errs = ApiTokens::parse(
+            Some("dup"),
+            Some("a:guest:x,b:viewer,c:viewer:dup,admin:viewer:y,:viewer:z"),
+        )
+        .unwrap_err();
+        assert_eq!(errs.len(), 5, "{errs:?}");
+        assert!(errs[0].contains("unknown role 'guest'"));
+        assert_eq!(errs[1], "API_TOKENS: 'b' must be name:role:token");
+        assert!(errs[2].contains("reuses another token"));
+        assert!(errs[3].contains("name 'admin' is used twice"));
+        assert!(errs[4].contains("needs a name"));
+    }
+
+    #[test]
+    fn route_roles() {
+        let cases = [
+            (Method::GET, "/api/status", Role::Viewer),
+            (Method::GET, "/api/zones/z1", Role::Viewer),
+            (Method::GET, "/metrics", Role::Viewer),
+            (Method::GET, "/api/config/versions", Role::Admin),
+            (Method::GET, "/api/backups", Role::Admin),
+            (Method::POST, "/api/sessions/3/cancel", Role::Operator),
+            (Method::POST, "/api/zones/z1/water-now", Role::Operator),
+            (Method::POST, "/api/zones/z1/disturbances", Role::Operator),
+            (
+                Method::DELETE,
+                "/api/zones/z1/disturbances/2",
+                Role::Operator,
+            ),
+            (Method::POST, "/api/emergency-stop/clear", Role::Admin),
+            (Method::POST, "/api/nodes/n1/restart", Role::Operator),
+            (Method::PUT, "/api/zones/z1", Role::Admin),
+            (Method::PUT, "/api/sensors/s1", Role::Admin),
+            (Method::POST, "/api/zones/z1/archive", Role::Admin),
+            (Method::PUT, "/api/retention", Role::Admin),
+            (Method::POST, "/api/backups/restore", Role::Admin),
+            (Method::POST, "/api/nodes/n1/ota", Role::Admin),
+            (Method::DELETE, "/api/nodes/n1/ota", Role::Admin),
+        ];
+        for (method, path, role) in cases {
+            assert_eq!(required_role(&method, path), role, "{method} {path}");
+        }
+    }
+
+    #[test]
+    fn parse_node_tokens() {
+        let tokens = NodeTokens::parse(Some(" esp-bed:k:1 , esp-pots:k2,")).unwrap();
+        assert_eq!(tokens.node_for("Bearer k:1"), Some("esp-bed"));
+        assert_eq!(tokens.node_for("Bearer k2"), Some("esp-pots"));
+        assert_eq!(tokens.node_for("Bearer nope"), None);
+        assert_eq!(tokens.node_for("k2"), None);
+        assert_eq!(NodeTokens::parse(None).unwrap().node_for("Bearer "), None);
+
+        let errs = NodeTokens::parse(Some("a:x,b,a:y,c:x,:z")).unwrap_err();
+        assert_eq!(
+            errs,
+            vec![
+                "NODE_TOKENS: 'b' must be node_id:token",
+                "NODE_TOKENS: node 'a' is listed twice",
+                "NODE_TOKENS: 'c' reuses another token",
+                "NODE_TOKENS: '' must be node_id:token",
+            ]
+        );
+    }
+}
@@ -0,0 +1,167 @@
+//! Periodic database backups to `DB_BACKUP_PATH`.
+//!
+//! `DB_BACKUP_MODE=full` (the default) rewrites the backup with
+//! `VACUUM INTO` every time.  On multi-GB databases that wears SD cards, so
+//! `incremental` instead updates the existing backup in place with only the
+//! rows that changed ([`Db::backup_incremental`]), and takes a full snapshot
+//! every `DB_BACKUP_FULL_INTERVAL_SEC` to compact it again.  A full snapshot
+//! is also taken when there is no backup yet or the schema has changed.
+
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use crate::db::Db;
+
+/// Time between full snapshots in incremental mode.
+pub const DEFAULT_FULL_INTERVAL_SEC: u64 = 86_400;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BackupMode {
+    #[default]
+    Full,
+    Incremental,
+}
+
+impl BackupMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "full" => Some(Self::Full),
+            "incremental" => Some(Self::Incremental),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Full => "full",
+            Self::Incremental => "incremental",
+        }
+    }
+}
+
+/// What a backup run did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackupKind {
+    Full,
+    Incremental,
+}
+
+impl BackupKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Full => "full",
+            Self::Incremental => "incremental",
+        }
+    }
+}
+
+/// Decides between full and incremental backups.
+#[derive(Debug, Clone)]
+pub struct BackupSchedule {
+    mode: BackupMode,
+    full_interval: Duration,
+    /// Unset until this process has taken a full snapshot, so the first
+    /// backup after a start compacts the file.
+    last_full: Option<Instant>,
+}
+
+impl BackupSchedule {
+    pub fn new(mode: BackupMode, full_interval: Duration) -> Self {
+        Self {
+            mode,
+            full_interval,
+            last_full: None,
+        }
+    }
+
+    /// The kind of backup to try at `now`.
+    pub fn next_kind(&self, now: Instant) -> BackupKind {
+        match (self.mode, self.last_full) {
+            (BackupMode::Incremental, Some(last))
+                if now.saturating_duration_since(last) < self.full_interval =>
+            {
+                BackupKind::Incremental
+            }
+            _ => BackupKind::Full,
+        }
+    }
+
+    /// Back `db` up to `dest`, incrementally when the schedule allows and
+    /// the existing backup can be updated, in full otherwise.
+    pub async fn run(&mut self, db: &Db, dest: &str) -> Result<BackupKind> {
+        let now = Instant::now();
+        if self.next_kind(now) == BackupKind::Incremental && db.backup_incremental(dest).await? {
+            return Ok(BackupKind::Incremental);
+        }
+        db.backup(dest).await?;
+        self.last_full = Some(now);
+        Ok(BackupKind::Full)
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_modes() {
+        assert_eq!(BackupMode::parse("full"), Some(BackupMode::Full));
+        assert_eq!(
+            BackupMode::parse(" Incremental "),
+            Some(BackupMode::Incremental)
+        );
+        assert_eq!(BackupMode::parse("wal"), None);
+    }
+
+    #[test]
+    fn incremental_between_full_snapshots() {
+        let interval = Duration::from_secs(3600);
+        let now = Instant::now();
+        let mut s = BackupSchedule::new(BackupMode::Incremental, interval);
+        // Nothing taken yet this run: full.
+        assert_eq!(s.next_kind(now), BackupKind::Full);
+
+        s.last_full = Some(now);
+        assert_eq!(
+            s.next_kind(now + Duration::from_secs(1800)),
+            BackupKind::Incremental
+        );
+        assert_eq!(s.next_kind(now + interval), BackupKind::Full);
+
+        let mut full = BackupSchedule::new(BackupMode::Full, interval);
+        full.last_full = Some(now);
+        assert_eq!(full.next_kind(now), BackupKind::Full);
+    }
+
+    #[tokio::test]
+    async fn run_falls_back_to_full_without_a_backup() {
+        let dir =
+            std::env::temp_dir().join(format!("irrigation_backup_mode_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = Db::connect(&format!(
+            "sqlite:{}?mode=rwc",
+            dir.join("live.db").display()
+        ))
+        .await
+        .unwrap();
+        db.migrate().await.unwrap();
+        let dest = dir.join("backup.db");
+        let dest = dest.to_str().unwrap();
+
+        let mut s = BackupSchedule::new(BackupMode::Incremental, Duration::from_secs(3600));
+        assert_eq!(s.run(&db, dest).await.unwrap(), BackupKind::Full);
+        assert_eq!(s.run(&db, dest).await.unwrap(), BackupKind::Incremental);
+
+        // The backup vanished: full again.
+        std::fs::remove_file(dest).unwrap();
+        assert_eq!(s.run(&db, dest).await.unwrap(), BackupKind::Full);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,194 @@
+//! Blackout calendar: dates on which the scheduler never opens a valve,
+//! whatever the moisture.
+//!
+//! A blackout is either a one-off date range (`"2026-07-10"` to
+//! `"2026-07-24"`) or a range that recurs every year (`"11-01"` to
+//! `"03-15"`, which wraps over the new year).  Both ends are inclusive
+//! and dates are UTC, like the maintenance windows.  Blackouts are stored
+//! in the `blackouts` table and edited through `/api/blackouts`; the
+//! scheduler skips every zone with `blocked_by = blackout` while one is
+//! active.  Valve commands from elsewhere (MQTT, the watchdog) are not
+//! affected.
+
+use serde::{Deserialize, Serialize};
+use time::{Date, Month};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Blackout {
+    pub id: i64,
+    /// `YYYY-MM-DD`, or `MM-DD` for a yearly range.
+    pub start: String,
+    /// Same form as `start`.
+    pub end: String,
+    pub reason: String,
+}
+
+/// A parsed blackout range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Span {
+    Dates {
+        start: Date,
+        end: Date,
+    },
+    /// (month, day) pairs.
+    Yearly {
+        start: (u8, u8),
+        end: (u8, u8),
+    },
+}
+
+impl Span {
+    /// Parse a `start`/`end` pair; both must be dates, or both month-days.
+    pub fn parse(start: &str, end: &str) -> Result<Self, String> {
+        match (parse_date(start), parse_date(end)) {
+            (Some(s), Some(e)) if s <= e => return Ok(Self::Dates { start: s, end: e }),
+            (Some(_), Some(_)) => return Err(format!("end {end} is before start {start}")),
+            _ => {}
+        }
+        match (parse_month_day(start), parse_month_day(end)) {
+            (Some(s), Some(e)) => Ok(Self::Yearly { start: s, end: e }),
+            _ => Err(format!(
+                "start and end must both be YYYY-MM-DD or both MM-DD, got '{start}' and '{end}'"
+            )),
+        }
+    }
+
+    pub fn contains(&self, date: Date) -> bool {
+        match *self {
+            Self::Dates { start, end } => (start..=end).contains(&date),
+            Self::Yearly { start, end } => {
+                let d = (date.month() as u8, date.day());
+                if start <= end {
+                    (start..=end).contains(&d)
+                } else {
+                    d >= start || d <= end
+                }
+            }
+        }
+    }
+}
+
+fn parse_date(s: &str) -> Option<Date> {
+    let mut parts = s.trim().split('-');
+    let (y, m, d) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() || y.len() != 4 {
+        return None;
+    }
+    let month = Month::try_from(m.parse::<u8>().ok()?).ok()?;
+    Date::from_calendar_date(y.parse().ok()?, month, d.parse().ok()?).ok()
+}
+
+fn parse_month_day(s: &str) -> Option<(u8, u8)> {
+    let (m, d) = s.trim().split_once('-')?;
+    let month = Month::try_from(m.parse::<u8>().ok()?).ok()?;
+    let day: u8 = d.parse().ok()?;
+    // 2000 is a leap year, so 02-29 is accepted.
+    Date::from_calendar_date(2000, month, day).ok()?;
+    Some((month as u8, day))
+}
+
+/// The stored blackouts, parsed, as the scheduler checks them.
+#[derive(Debug, Clone, Default)]
+pub struct Blackouts {
+    entries: Vec<(Blackout, Span)>,
+}
+
+impl Blackouts {
+    /// Rows that don't parse (only possible if edited outside the API) are
+    /// logged and skipped.
+    pub fn new(rows: Vec<Blackout>) -> Self {
+        let entries = rows
+            .into_iter()
+            .filter_map(|b| match Span::parse(&b.start, &b.end) {
+                Ok(span) => Some((b, span)),
+                Err(e) => {
+                    tracing::warn!(id = b.id, "ignoring blackout: {e}");
+                    None
+                }
+            })
+            .collect();
+        Self { entries }
+    }
+
+    /// The first blackout covering `date`.
+    pub fn active(&self, date: Date) -> Option<&Blackout> {
+        self.entries
+            .iter()
+            .find(|(_, span)| span.contains(date))
+            .map(|(b, _)| b)
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    #[test]
+    fn parse_spans() {
+        assert_eq!(
+            Span::parse("2026-07-10", "2026-07-24"),
+            Ok(Span::Dates {
+                start: date!(2026 - 07 - 10),
+                end: date!(2026 - 07 - 24)
+            })
+        );
+        assert_eq!(
+            Span::parse("11-01", "03-15"),
+            Ok(Span::Yearly {
+                start: (11, 1),
+                end: (3, 15)
+            })
+        );
+        assert!(Span::parse("02-29", "02-29").is_ok());
+        assert!(Span::parse("2026-07-24", "2026-07-10")
+            .unwrap_err()
+            .contains("before start"));
+        for (start, end) in [
+            ("2026-07-10", "07-24"),
+            ("02-30", "03-01"),
+            ("2026-13-01", "2026-13-02"),
+            ("26-07-10", "26-07-11"),
+            ("", ""),
+        ] {
+            assert!(Span::parse(start, end).is_err(), "{start}..{end}");
+        }
+    }
+
+    #[test]
+    fn yearly_ranges_wrap_over_new_year() {
+        let winter = Span::parse("11-01", "03-15").unwrap();
+        assert!(winter.contains(date!(2026 - 11 - 01)));
+        assert!(winter.contains(date!(2027 - 01 - 20)));
+        assert!(winter.contains(date!(2027 - 03 - 15)));
+        assert!(!winter.contains(date!(2027 - 03 - 16)));
+        assert!(!winter.contains(date!(2026 - 10 - 31)));
+
+        let summer = Span::parse("06-01", "06-30").unwrap();
+        assert!(summer.contains(date!(2030 - 06 - 15)));
+        assert!(!summer.contains(date!(2030 - 07 - 01)));
+    }
+
+    #[test]
+    fn active_blackout() {
+        let row = |id, start: &str, end: &str| Blackout {
+            id,
+            start: start.into(),
+            end: end.into(),
+            reason: format!("b{id}"),
+        };
+        let blackouts = Blackouts::new(vec![
+            row(1, "bogus", "2026-01-01"),
+            row(2, "2026-07-10", "2026-07-24"),
+            row(3, "11-01", "03-15"),
+        ]);
+        assert_eq!(blackouts.active(date!(2026 - 07 - 24)).unwrap().id, 2);
+        assert_eq!(blackouts.active(date!(2026 - 12 - 25)).unwrap().id, 3);
+        assert!(blackouts.active(date!(2026 - 07 - 25)).is_none());
+        assert!(Blackouts::default().active(date!(2026 - 12 - 25)).is_none());
+    }
+}
@@ -0,0 +1,287 @@
+//! Daily water budgets shared between zones, on top of each zone's own
+//! daily limits.
+//!
+//! A budget scope is either global (every zone) or a named group from
+//! `[budget.groups]`, limited in open seconds and/or litres per calendar
+//! day.  Before an idle zone starts a pulse, the scheduler checks every
+//! scope the zone belongs to: the pulse must fit in what is left *after*
+//! keeping one pulse in reserve for each higher-priority zone in the scope
+//! that hasn't reached its own daily cap.  Lower-priority zones are thus
+//! deferred first as a budget runs low, and everything stops once it is
+//! exhausted.  Manual valve commands are not blocked, but their open time
+//! counts towards the budgets.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::config::BudgetConfig;
+use crate::db::ZoneConfig;
+
+/// Usage of one budget scope today, reported in `/api/status`.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct BudgetUsage {
+    /// `"global"` or the group name.
+    pub scope: String,
+    pub limit_open_sec: Option<i64>,
+    pub used_open_sec: i64,
+    pub limit_litres: Option<f64>,
+    /// Only zones with a `flow_lpm` contribute litres.
+    pub used_litres: f64,
+}
+
+#[derive(Debug, Clone)]
+struct Scope {
+    name: String,
+    /// `None` = every zone.
+    zones: Option<HashSet<String>>,
+    daily_open_sec: Option<i64>,
+    daily_litres: Option<f64>,
+}
+
+impl Scope {
+    fn includes(&self, zone_id: &str) -> bool {
+        self.zones.as_ref().is_none_or(|z| z.contains(zone_id))
+    }
+}
+
+/// The configured budgets.  Empty = no budget beyond per-zone limits.
+#[derive(Debug, Clone, Default)]
+pub struct Budget {
+    scopes: Vec<Scope>,
+}
+
+impl Budget {
+    pub fn new(cfg: &BudgetConfig) -> Self {
+        let mut scopes = Vec::new();
+        if cfg.daily_open_sec.is_some() || cfg.daily_litres.is_some() {
+            scopes.push(Scope {
+                name: "global".to_string(),
+                zones: None,
+                daily_open_sec: cfg.daily_open_sec,
+                daily_litres: cfg.daily_litres,
+            });
+        }
+        for (name, g) in &cfg.groups {
+            scopes.push(Scope {
+                name: name.clone(),
+                zones: Some(g.zones.iter().cloned().collect()),
+                daily_open_sec: g.daily_open_sec,
+                daily_litres: g.daily_litres,
+            });
+        }
+        Self { scopes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scopes.is_empty()
+    }
+
+    /// Usage of every scope given each zone's open seconds today.
+    pub fn usage(
+        &self,
+        zones: &HashMap<String, ZoneConfig>,
+        open_sec: &HashMap<String, i64>,
+    ) -> Vec<BudgetUsage> {
+        self.scopes
+            .iter()
+            .map(|scope| {
+                let (used_open_sec, used_litres) = scope_used(scope, zones, open_sec);
+                BudgetUsage {
+                    scope: scope.name.clone(),
+                    limit_open_sec: scope.daily_open_sec,
+                    used_open_sec,
+                    limit_litres: scope.daily_litres,
+                    used_litres,
+                }
+            })
+            .collect()
+    }
+
+    /// Whether `zone` may start a pulse.  `Err` explains which scope ran
+    /// out or is holding the rest back for higher-priority zones.
+    pub fn check(
+        &self,
+        zone: &ZoneConfig,
+        zones: &HashMap<String, ZoneConfig>,
+        open_sec: &HashMap<String, i64>,
+    ) -> Result<(), String> {
+        for scope in self.scopes.iter().filter(|s| s.includes(&zone.zone_id)) {
+            let (used_sec, used_l) = scope_used(scope, zones, open_sec);
+
+            // One pulse held back for each higher-priority zone in the
+            // scope that can still water today.
+            let (reserve_sec, reserve_l) = zones
+                .values()
+                .filter(|z| scope.includes(&z.zone_id) && z.priority > zone.priority)
+                .filter(|z| open_sec.get(&z.zone_id).copied().unwrap_or(0) < z.max_open_sec_per_day)
+                .fold((0, 0.0), |(s, l), z| {
+                    (s + z.pulse_sec, l + litres(z, z.pulse_sec))
+                });
+
+            let pulse_sec = zone.pulse_sec;
+            let pulse_l = litres(zone, pulse_sec);
+            if let Some(limit) = scope.daily_open_sec {
+                check_limit(
+                    &scope.name,
+                    "s",
+                    used_sec as f64,
+                    pulse_sec as f64,
+                    reserve_sec as f64,
+                    limit as f64,
+                )?;
+            }
+            if let Some(limit) = scope.daily_litres {
+                check_limit(&scope.name, " L", used_l, pulse_l, reserve_l, limit)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_limit(
+    scope: &str,
+    unit: &str,
+    used: f64,
+    pulse: f64,
+    reserve: f64,
+    limit: f64,
+) -> Result<(), String> {
+    if used + pulse > limit {
+        Err(format!(
+            "{scope} budget exhausted ({used:.0}/{limit:.0}{unit} used)"
+        ))
+    } else if used + pulse + reserve > limit {
+        Err(format!(
+            "{scope} budget: {:.0}{unit} left, held for higher-priority zones",
+            limit - used
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn litres(zone: &ZoneConfig, open_sec: i64) -> f64 {
+    zone.flow_lpm
+        .map_or(0.0, |lpm| f64::from(lpm) * open_sec as f64 / 60.0)
+}
+
+fn scope_used(
+    scope: &Scope,
+    zones: &HashMap<String, ZoneConfig>,
+    open_sec: &HashMap<String, i64>,
+) -> (i64, f64) {
+    open_sec
+        .iter()
+        .filter(|(zone_id, _)| scope.includes(zone_id))
+        .fold((0, 0.0), |(s, l), (zone_id, &sec)| {
+            let l_zone = zones.get(zone_id).map_or(0.0, |z| litres(z, sec));
+            (s + sec, l + l_zone)
+        })
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::BudgetGroupConfig;
+
+    fn zone(zone_id: &str, priority: i64, flow_lpm: Option<f32>) -> ZoneConfig {
+        ZoneConfig {
+            name: zone_id.into(),
+            pulse_sec: 60,
+            max_open_sec_per_day: 300,
+            flow_lpm,
+            priority,
+            ..ZoneConfig::test(zone_id)
+        }
+    }
+
+    fn zones(list: Vec<ZoneConfig>) -> HashMap<String, ZoneConfig> {
+        list.into_iter().map(|z| (z.zone_id.clone(), z)).collect()
+    }
+
+    fn open_sec(list: &[(&str, i64)]) -> HashMap<String, i64> {
+        list.iter().map(|(z, s)| (z.to_string(), *s)).collect()
+    }
+
+    #[test]
+    fn no_budget_allows_everything() {
+        let budget = Budget::new(&BudgetConfig::default());
+        assert!(budget.is_empty());
+        let zs = zones(vec![zone("a", 0, None)]);
+        assert!(budget
+            .check(&zs["a"], &zs, &open_sec(&[("a", 10_000)]))
+            .is_ok());
+    }
+
+    #[test]
+    fn global_budget_exhausted() {
+        let budget = Budget::new(&BudgetConfig {
+            daily_open_sec: Some(200),
+            ..BudgetConfig::default()
+        });
+        let zs = zones(vec![zone("a", 0, None), zone("b", 0, None)]);
+        let used = open_sec(&[("a", 100), ("b", 60)]);
+        let err = budget.check(&zs["a"], &zs, &used).unwrap_err();
+        assert!(err.contains("global budget exhausted (160/200s"), "{err}");
+
+        let usage = budget.usage(&zs, &used);
+        assert_eq!(usage[0].used_open_sec, 160);
+        assert_eq!(usage[0].limit_open_sec, Some(200));
+    }
+
+    #[test]
+    fn lower_priority_deferred_for_reserve() {
+        let budget = Budget::new(&BudgetConfig {
+            daily_open_sec: Some(200),
+            ..BudgetConfig::default()
+        });
+        let zs = zones(vec![zone("high", 10, None), zone("low", 0, None)]);
+        // 100s left: enough for one pulse, which is kept for "high".
+        let used = open_sec(&[("low", 100)]);
+        let err = budget.check(&zs["low"], &zs, &used).unwrap_err();
+        assert!(err.contains("held for higher-priority zones"), "{err}");
+        assert!(budget.check(&zs["high"], &zs, &used).is_ok());
+
+        // Once "high" has hit its own daily cap, nothing is reserved.
+        let used = open_sec(&[("low", 100), ("high", 300)]);
+        let budget = Budget::new(&BudgetConfig {
+            daily_open_sec: Some(500),
+            ..BudgetConfig::default()
+        });
+        assert!(budget.check(&zs["low"], &zs, &used).is_ok());
+    }
+
+    #[test]
+    fn group_litre_budget_only_applies_to_members() {
+        let mut groups = std::collections::BTreeMap::new();
+        groups.insert(
+            "lawns".to_string(),
+            BudgetGroupConfig {
+                zones: vec!["lawn".into()],
+                daily_open_sec: None,
+                daily_litres: Some(20.0),
+            },
+        );
+        let budget = Budget::new(&BudgetConfig {
+            groups,
+            ..BudgetConfig::default()
+        });
+        // 6 L/min: 120s used = 12 L, one more 60s pulse = 6 L → 18 L fits.
+        let zs = zones(vec![zone("lawn", 0, Some(6.0)), zone("bed", 0, Some(6.0))]);
+        let used = open_sec(&[("lawn", 120), ("bed", 600)]);
+        assert!(budget.check(&zs["lawn"], &zs, &used).is_ok());
+        assert!(budget.check(&zs["bed"], &zs, &used).is_ok());
+
+        let used = open_sec(&[("lawn", 180)]);
+        let err = budget.check(&zs["lawn"], &zs, &used).unwrap_err();
+        assert!(err.contains("lawns budget exhausted (18/20 L"), "{err}");
+        let usage = budget.usage(&zs, &used);
+        assert_eq!(usage[0].scope, "lawns");
+        assert!((usage[0].used_litres - 18.0).abs() < 1e-9);
+    }
+}
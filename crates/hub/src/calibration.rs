@@ -0,0 +1,198 @@
+//! Per-sensor calibration curves.  By default moisture is linear between a
+//! sensor's `raw_dry` and `raw_wet` counts, but capacitive probes aren't
+//! linear: most of their range sits in the wet half.  A sensor may carry a
+//! curve that maps its raw counts to moisture instead.
+//!
+//! - `table`: `(raw, moisture)` points, e.g. from weighing soil samples at
+//!   known water contents.  Moisture is interpolated linearly between the
+//!   two nearest points and holds the end value outside the table.
+//! - `polynomial`: coefficients `c0, c1, ...` of a polynomial in the linear
+//!   fraction `x` (0 at `raw_dry`, 1 at `raw_wet`): moisture =
+//!   `c0 + c1·x + c2·x² + ...`.  Working on `x` keeps the coefficients
+//!   small and lets the same curve follow a recalibrated dry/wet range.
+//!
+//! Either way the result is clamped to 0–1.  The curve is stored as JSON in
+//! `sensors.curve` (NULL = linear).
+//!
+//! ```toml
+//! curve = { kind = "table", points = [[26000, 0.0], [21000, 0.2], [15000, 0.6], [12000, 1.0]] }
+//! curve = { kind = "polynomial", coefficients = [0.0, 0.4, 0.6] }
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::compute_moisture;
+
+/// Most polynomial coefficients (a 5th-degree polynomial).
+const MAX_COEFFICIENTS: usize = 6;
+
+/// Highest raw count a table point may use (ADS1115 full scale).
+const MAX_RAW: f64 = 32767.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Curve {
+    /// `[raw, moisture]` points, in any order.
+    Table { points: Vec<[f64; 2]> },
+    /// Coefficients in ascending powers of the linear fraction.
+    Polynomial { coefficients: Vec<f64> },
+}
+
+impl Curve {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        match self {
+            Self::Table { points } => {
+                if points.len() < 2 {
+                    errors.push("curve: a table needs at least 2 points".to_string());
+                }
+                for [raw, moisture] in points {
+                    if !(0.0..=MAX_RAW).contains(raw) {
+                        errors.push(format!("curve: raw {raw} out of range (0–32767)"));
+                    }
+                    if !(0.0..=1.0).contains(moisture) {
+                        errors.push(format!("curve: moisture {moisture} out of range (0–1)"));
+                    }
+                }
+                let mut raws: Vec<f64> = points.iter().map(|p| p[0]).collect();
+                raws.sort_by(f64::total_cmp);
+                if let Some(w) = raws.windows(2).find(|w| w[0] == w[1]) {
+                    errors.push(format!("curve: raw {} appears twice", w[0]));
+                }
+            }
+            Self::Polynomial { coefficients } => {
+                if !(1..=MAX_COEFFICIENTS).contains(&coefficients.len()) {
+                    errors.push(format!(
+                        "curve: a polynomial needs 1–{MAX_COEFFICIENTS} coefficients, got {}",
+                        coefficients.len()
+                    ));
+                }
+                if coefficients.iter().any(|c| !c.is_finite()) {
+                    errors.push("curve: coefficients must be finite".to_string());
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Moisture (0–1) for `raw` on a sensor calibrated `raw_dry`..`raw_wet`.
+    pub fn moisture(&self, raw: i64, raw_dry: i64, raw_wet: i64) -> f32 {
+        let m = match self {
+            Self::Table { points } => {
+                let mut points = points.clone();
+                points.sort_by(|a, b| a[0].total_cmp(&b[0]));
+                interpolate(&points, raw as f64)
+            }
+            Self::Polynomial { coefficients } => {
+                let x = f64::from(compute_moisture(raw, raw_dry, raw_wet));
+                coefficients.iter().rev().fold(0.0, |acc, c| acc * x + c)
+            }
+        };
+        if m.is_finite() {
+            m.clamp(0.0, 1.0) as f32
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Piecewise-linear interpolation through `points`, sorted by raw.
+fn interpolate(points: &[[f64; 2]], raw: f64) -> f64 {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return 0.0;
+    };
+    if raw <= first[0] {
+        return first[1];
+    }
+    if raw >= last[0] {
+        return last[1];
+    }
+    points
+        .windows(2)
+        .find(|w| raw <= w[1][0])
+        .map_or(last[1], |w| {
+            let [(x0, y0), (x1, y1)] = [(w[0][0], w[0][1]), (w[1][0], w[1][1])];
+            y0 + (y1 - y0) * (raw - x0) / (x1 - x0)
+        })
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_interpolates_between_points() {
+        let curve: Curve = serde_json::from_str(
+            r#"{"kind": "table", "points": [[12000, 1.0], [26000, 0.0], [21000, 0.2]]}"#,
+        )
+        .unwrap();
+        assert!(curve.validate().is_ok());
+        assert_eq!(curve.moisture(26000, 26000, 12000), 0.0);
+        assert_eq!(curve.moisture(21000, 26000, 12000), 0.2);
+        assert!((curve.moisture(23500, 26000, 12000) - 0.1).abs() < 1e-6);
+        assert!((curve.moisture(16500, 26000, 12000) - 0.6).abs() < 1e-6);
+        // Outside the table: the end values.
+        assert_eq!(curve.moisture(30000, 26000, 12000), 0.0);
+        assert_eq!(curve.moisture(9000, 26000, 12000), 1.0);
+    }
+
+    #[test]
+    fn polynomial_works_on_the_linear_fraction() {
+        let curve = Curve::Polynomial {
+            coefficients: vec![0.0, 0.4, 0.6],
+        };
+        // x = 0.5 → 0.2 + 0.15
+        assert!((curve.moisture(19000, 26000, 12000) - 0.35).abs() < 1e-6);
+        assert_eq!(curve.moisture(26000, 26000, 12000), 0.0);
+        assert_eq!(curve.moisture(12000, 26000, 12000), 1.0);
+        // Clamped to 0–1.
+        let steep = Curve::Polynomial {
+            coefficients: vec![-0.5, 3.0],
+        };
+        assert_eq!(steep.moisture(26000, 26000, 12000), 0.0);
+        assert_eq!(steep.moisture(12000, 26000, 12000), 1.0);
+    }
+
+    #[test]
+    fn toml_form_and_validation() {
+        #[derive(Deserialize)]
+        struct Sensor {
+            curve: Curve,
+        }
+        let s: Sensor =
+            toml::from_str("curve = { kind = \"table\", points = [[26000, 0], [12000, 1.0]] }")
+                .unwrap();
+        assert!(s.curve.validate().is_ok());
+
+        let errs = Curve::Table {
+            points: vec![[40000.0, 1.5]],
+        }
+        .validate()
+        .unwrap_err();
+        assert_eq!(errs.len(), 3, "{errs:?}");
+        let errs = Curve::Table {
+            points: vec![[20000.0, 0.1], [20000.0, 0.2]],
+        }
+        .validate()
+        .unwrap_err();
+        assert_eq!(errs, vec!["curve: raw 20000 appears twice"]);
+        assert!(Curve::Polynomial {
+            coefficients: vec![]
+        }
+        .validate()
+        .is_err());
+        assert!(Curve::Polynomial {
+            coefficients: vec![f64::NAN]
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,216 @@
+//! The hub's time source: the scheduler, the valve watchdog, the daily
+//! counters and pruning read the time from here rather than from the
+//! system clock directly.
+//!
+//! Normally it is the system clock.  For development, `--sim-speed 60x`
+//! makes it run 60 times faster from the moment the hub starts: a 30 s
+//! pulse lasts half a real second, a 20 min soak 20 s, and a day of
+//! pulse/soak cycles and the midnight counter reset pass in 24 minutes.
+//! Sleeps and intervals taken through [`sleep`] and [`interval`] shrink by
+//! the same factor.  At normal speed every function here is exactly the
+//! tokio / system clock call it replaces.
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use time::OffsetDateTime;
+use tokio::time::Instant;
+
+/// Fastest accepted `--sim-speed`.
+pub const MAX_SPEED: u32 = 3600;
+
+#[derive(Debug, Clone, Copy)]
+pub struct TimeSource {
+    speed: u32,
+    /// Wall time and monotonic time when the source was created.
+    wall_start: OffsetDateTime,
+    start: Instant,
+}
+
+impl TimeSource {
+    /// The system clock.
+    pub fn real() -> Self {
+        Self::accelerated(1)
+    }
+
+    /// A clock that starts at the current time and runs `speed` times
+    /// faster.
+    pub fn accelerated(speed: u32) -> Self {
+        Self {
+            speed: speed.max(1),
+            wall_start: OffsetDateTime::now_utc(),
+            start: Instant::now(),
+        }
+    }
+
+    pub fn is_real(&self) -> bool {
+        self.speed == 1
+    }
+
+    /// Simulated time passed since the start, at real instant `at`.
+    fn elapsed_at(&self, at: Instant) -> Duration {
+        at.saturating_duration_since(self.start) * self.speed
+    }
+
+    fn now_at(&self, at: Instant) -> Instant {
+        self.start + self.elapsed_at(at)
+    }
+
+    fn utc_at(&self, at: Instant) -> OffsetDateTime {
+        self.wall_start + self.elapsed_at(at)
+    }
+
+    /// Real time a simulated `d` takes.
+    pub fn real_duration(&self, d: Duration) -> Duration {
+        d / self.speed
+    }
+}
+
+static CLOCK: OnceLock<TimeSource> = OnceLock::new();
+
+/// Install the time source.  Call once at startup, before any task reads
+/// the time.
+pub fn set(source: TimeSource) {
+    if CLOCK.set(source).is_err() {
+        tracing::warn!("time source already set — ignoring");
+    }
+}
+
+fn clock() -> &'static TimeSource {
+    CLOCK.get_or_init(TimeSource::real)
+}
+
+/// Parse a `--sim-speed` value: a factor like `60x` or `60`.
+pub fn parse_speed(s: &str) -> Result<u32, String> {
+    let digits = s.trim().trim_end_matches(['x', 'X']);
+    match digits.parse::<u32>() {
+        Ok(speed) if (1..=MAX_SPEED).contains(&speed) => Ok(speed),
+        _ => Err(format!(
+            "--sim-speed must be a factor from 1x to {MAX_SPEED}x, got '{s}'"
+        )),
+    }
+}
+
+/// The speed from the hub's command line: nothing, or
+/// `--sim-speed <factor>`.
+pub fn speed_from_args(args: &[String]) -> Result<u32, String> {
+    match args {
+        [] => Ok(1),
+        [flag, speed] if flag == "--sim-speed" => parse_speed(speed),
+        _ => Err("usage: irrigation-hub [--sim-speed FACTORx]".into()),
+    }
+}
+
+pub fn is_real() -> bool {
+    clock().is_real()
+}
+
+/// The monotonic clock.
+pub fn now() -> Instant {
+    let c = clock();
+    if c.is_real() {
+        Instant::now()
+    } else {
+        c.now_at(Instant::now())
+    }
+}
+
+/// Time since `earlier` (an instant from [`now`]).
+pub fn since(earlier: Instant) -> Duration {
+    now().saturating_duration_since(earlier)
+}
+
+/// The wall clock.
+pub fn now_utc() -> OffsetDateTime {
+    let c = clock();
+    if c.is_real() {
+        OffsetDateTime::now_utc()
+    } else {
+        c.utc_at(Instant::now())
+    }
+}
+
+/// The wall clock in unix seconds.
+pub fn now_unix() -> i64 {
+    now_utc().unix_timestamp()
+}
+
+/// The system clock in unix seconds, whatever the time source: for
+/// checking timestamps from nodes, which run on real time.
+pub fn real_now_unix() -> i64 {
+    OffsetDateTime::now_utc().unix_timestamp()
+}
+
+/// Today's date (UTC) as `YYYY-MM-DD`, the daily counters' key.
+pub fn today() -> String {
+    let now = now_utc();
+    format!(
+        "{:04}-{:02}-{:02}",
+        now.year(),
+        now.month() as u8,
+        now.day()
+    )
+}
+
+/// Sleep for `d` of clock time.
+pub async fn sleep(d: Duration) {
+    tokio::time::sleep(clock().real_duration(d)).await;
+}
+
+/// An interval ticking every `period` of clock time.
+pub fn interval(period: Duration) -> tokio::time::Interval {
+    tokio::time::interval(clock().real_duration(period))
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_speeds() {
+        assert_eq!(parse_speed("60x"), Ok(60));
+        assert_eq!(parse_speed(" 1 "), Ok(1));
+        assert_eq!(parse_speed("3600X"), Ok(3600));
+        for bad in ["0x", "3601x", "fast", "", "-2x", "1.5x"] {
+            assert!(parse_speed(bad).is_err(), "{bad}");
+        }
+
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(speed_from_args(&args(&[])), Ok(1));
+        assert_eq!(speed_from_args(&args(&["--sim-speed", "60x"])), Ok(60));
+        assert!(speed_from_args(&args(&["--sim-speed"])).is_err());
+        assert!(speed_from_args(&args(&["--fast"])).is_err());
+    }
+
+    #[test]
+    fn accelerated_clock_runs_faster() {
+        let clock = TimeSource::accelerated(60);
+        let later = clock.start + Duration::from_secs(30);
+        assert_eq!(
+            clock.utc_at(later),
+            clock.wall_start + Duration::from_secs(30 * 60)
+        );
+        assert_eq!(
+            clock.now_at(later) - clock.start,
+            Duration::from_secs(30 * 60)
+        );
+        // No time has passed at the start.
+        assert_eq!(clock.utc_at(clock.start), clock.wall_start);
+        assert_eq!(
+            clock.real_duration(Duration::from_secs(20 * 60)),
+            Duration::from_secs(20)
+        );
+
+        let real = TimeSource::real();
+        assert!(real.is_real());
+        assert_eq!(
+            real.real_duration(Duration::from_secs(5)),
+            Duration::from_secs(5)
+        );
+        assert_eq!(TimeSource::accelerated(0).speed, 1);
+    }
+}
@@ -3,9 +3,27 @@
 
 use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
-
-use crate::db::{Db, SensorConfig, ZoneConfig};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::adapters::{self, MqttAdapter};
+use crate::aggregation::Aggregation;
+use crate::alerts::MoistureAlertConfig;
+use crate::calibration::Curve;
+use crate::db::{
+    default_sensor_enabled, default_sensor_weight, Db, SensorConfig, ZoneConfig, ZoneOdometer,
+    ADS1115_MAX_CHANNEL,
+};
+use crate::et::EtConfig;
+use crate::federation::FederationConfig;
+use crate::gpio::GpioConfig;
+use crate::maintenance::MaintenanceWindows;
+use crate::ota::OtaConfig;
+use crate::pressure::PressureConfig;
+use crate::retention::RetentionPolicy;
+use crate::selftest::ValveTestConfig;
+use crate::strategy::StrategyConfig;
+use crate::sunlight::SunlightConfig;
+use crate::valve::ValveConfig;
 
 // ---------------------------------------------------------------------------
 // Operation mode
@@ -43,13 +61,313 @@ pub struct Config {
     pub zones: Vec<ZoneEntry>,
     #[serde(default)]
     pub sensors: Vec<SensorEntry>,
+    /// Adaptive soak behaviour (early exit / extension).  Defaults to the
+    /// purely timer-based soak.
+    #[serde(default)]
+    pub soak: SoakPolicy,
+    /// Relay board wiring (preset and/or overrides).  Optional — without it
+    /// zones name their GPIO pins directly.
+    #[serde(default)]
+    pub relay_board: Option<RelayBoardConfig>,
+    /// The board the hub runs on and how it drives GPIO (see `gpio`).
+    /// Defaults to a Raspberry Pi.
+    #[serde(default)]
+    pub gpio: GpioConfig,
+    /// When heavy background jobs may run.  Defaults to any time.
+    #[serde(default)]
+    pub maintenance: MaintenanceConfig,
+    /// Daily water budgets shared between zones.  Defaults to none.
+    #[serde(default)]
+    pub budget: BudgetConfig,
+    /// Low-temperature watering lockout.  Off unless `lockout_below_c` is
+    /// set.
+    #[serde(default)]
+    pub frost: FrostConfig,
+    /// Line-pressure lockout and cut-off (see `pressure`).  Off unless a
+    /// threshold is set.
+    #[serde(default)]
+    pub pressure: PressureConfig,
+    /// Periodic emitter flushes for drip zones.  Off unless `zones` is set.
+    #[serde(default)]
+    pub flush: FlushConfig,
+    /// Valve service thresholds.  Off unless a threshold is set.
+    #[serde(default)]
+    pub valve_service: ValveServiceConfig,
+    /// Blip length and pass thresholds of the valve self-test (see
+    /// `selftest`).
+    #[serde(default)]
+    pub valve_test: ValveTestConfig,
+    /// Physical emergency-stop button.  Optional.
+    #[serde(default)]
+    pub emergency_stop: Option<EmergencyStopConfig>,
+    /// How long history is kept.  A policy set over the API takes
+    /// precedence.
+    #[serde(default)]
+    pub retention: RetentionPolicy,
+    /// Valve interlock groups: name -> zones of which at most one valve is
+    /// open at a time (see `interlock`).  Defaults to none.
+    #[serde(default)]
+    pub interlocks: BTreeMap<String, Vec<String>>,
+    /// Other hubs to mirror read-only (see `federation`).
+    #[serde(default)]
+    pub federation: FederationConfig,
+    /// Site details for reference evapotranspiration (see `et`).
+    #[serde(default)]
+    pub et: EtConfig,
+    /// Low-moisture alert thresholds (see `alerts`).
+    #[serde(default)]
+    pub moisture_alerts: MoistureAlertConfig,
+    /// Peak-sun deferral of pulses (see `sunlight`).  Off unless
+    /// `avoid_above_lux` is set.
+    #[serde(default)]
+    pub sunlight: SunlightConfig,
+    /// Signed node images to offer over the air (see `ota`).
+    #[serde(default)]
+    pub ota: OtaConfig,
+    /// Tasmota / ESPHome topics translated into node telemetry (see
+    /// `adapters`).  Defaults to none.
+    #[serde(default)]
+    pub mqtt_adapters: Vec<MqttAdapter>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            mode: OperationMode::default(),
+            max_concurrent_valves: default_max_concurrent_valves(),
+            zones: Vec::new(),
+            sensors: Vec::new(),
+            soak: SoakPolicy::default(),
+            relay_board: None,
+            gpio: GpioConfig::default(),
+            maintenance: MaintenanceConfig::default(),
+            budget: BudgetConfig::default(),
+            frost: FrostConfig::default(),
+            pressure: PressureConfig::default(),
+            flush: FlushConfig::default(),
+            valve_service: ValveServiceConfig::default(),
+            valve_test: ValveTestConfig::default(),
+            emergency_stop: None,
+            retention: RetentionPolicy::default(),
+            interlocks: BTreeMap::new(),
+            federation: FederationConfig::default(),
+            et: EtConfig::default(),
+            moisture_alerts: MoistureAlertConfig::default(),
+            sunlight: SunlightConfig::default(),
+            ota: OtaConfig::default(),
+            mqtt_adapters: Vec::new(),
+        }
+    }
 }
 
 fn default_max_concurrent_valves() -> usize {
     2
 }
 
-#[derive(Debug, Deserialize)]
+/// Moisture-driven adjustments to the pulse/soak cycle's soak phase.
+///
+/// ```toml
+/// [soak]
+/// early_exit = true
+/// early_exit_after_min = 5
+/// extend_on_rise = true
+/// rise_threshold_per_min = 0.002
+/// rise_window_min = 10
+/// max_extend_min = 10
+/// ```
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct SoakPolicy {
+    /// Leave the soak early once the averaged moisture reaches the zone's
+    /// `target_moisture`.
+    pub early_exit: bool,
+    /// Minimum time spent soaking before an early exit is considered, so a
+    /// probe sitting next to an emitter can't short-circuit the soak.
+    pub early_exit_after_min: i64,
+    /// Keep soaking past the timer while moisture is still climbing faster
+    /// than `rise_threshold_per_min` — water is still moving down to the probe.
+    pub extend_on_rise: bool,
+    /// Moisture rise (fraction per minute) considered "still rising sharply".
+    pub rise_threshold_per_min: f32,
+    /// Window of recent readings used to estimate the rise rate.
+    pub rise_window_min: i64,
+    /// Upper bound on the total extension added to a single soak.
+    pub max_extend_min: i64,
+}
+
+impl Default for SoakPolicy {
+    fn default() -> Self {
+        Self {
+            early_exit: false,
+            early_exit_after_min: 5,
+            extend_on_rise: false,
+            rise_threshold_per_min: 0.002,
+            rise_window_min: 10,
+            max_extend_min: 10,
+        }
+    }
+}
+
+/// Time windows for heavy maintenance (pruning/vacuum, backups).
+///
+/// ```toml
+/// [maintenance]
+/// windows = ["02:00-04:00"]
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct MaintenanceConfig {
+    /// `"HH:MM-HH:MM"` ranges (UTC).  Empty = no restriction.
+    pub windows: Vec<String>,
+}
+
+/// Daily water budgets on top of the per-zone limits: a global one and
+/// optional named groups of zones.  Open seconds and/or litres (litres need
+/// the zones' `flow_lpm`).
+///
+/// ```toml
+/// [budget]
+/// daily_open_sec = 600
+///
+/// [budget.groups.lawns]
+/// zones = ["front-lawn", "back-lawn"]
+/// daily_litres = 150
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct BudgetConfig {
+    pub daily_open_sec: Option<i64>,
+    pub daily_litres: Option<f64>,
+    pub groups: BTreeMap<String, BudgetGroupConfig>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct BudgetGroupConfig {
+    pub zones: Vec<String>,
+    pub daily_open_sec: Option<i64>,
+    pub daily_litres: Option<f64>,
+}
+
+/// Block every valve ON command while the outdoor temperature (published
+/// to `temp/<source_id>/reading`) is below `lockout_below_c`, until it
+/// climbs back above `release_above_c`.
+///
+/// ```toml
+/// [frost]
+/// lockout_below_c = 2.0
+/// release_above_c = 3.0
+/// ```
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct FrostConfig {
+    /// Lock out at or below this temperature (°C).  Unset = no lockout.
+    pub lockout_below_c: Option<f64>,
+    /// Release once a reading is above this (°C).  Defaults to one degree
+    /// above `lockout_below_c`, so readings hovering at the threshold don't
+    /// toggle the lockout.
+    pub release_above_c: Option<f64>,
+}
+
+/// Brief full-pressure openings that clear drip emitters.  Each listed
+/// zone is flushed for `duration_sec` once every `interval_days`, at the
+/// first scheduler tick inside one of `windows` (UTC; empty = any time).
+/// Flushes count against the zone's daily limits.
+///
+/// ```toml
+/// [flush]
+/// zones = ["veg-beds"]
+/// interval_days = 7
+/// duration_sec = 20
+/// windows = ["05:00-06:00"]
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct FlushConfig {
+    pub zones: Vec<String>,
+    pub interval_days: i64,
+    pub duration_sec: i64,
+    pub windows: Vec<String>,
+}
+
+impl Default for FlushConfig {
+    fn default() -> Self {
+        Self {
+            zones: Vec::new(),
+            interval_days: 7,
+            duration_sec: 20,
+            windows: Vec::new(),
+        }
+    }
+}
+
+/// When a zone's valve is due for service.  Solenoids and relays have
+/// finite cycle lives: once actuations or open hours since the last
+/// recorded service reach either threshold, a maintenance event is raised.
+///
+/// ```toml
+/// [valve_service]
+/// actuations = 100000
+/// open_hours = 2000
+/// ```
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct ValveServiceConfig {
+    pub actuations: Option<i64>,
+    pub open_hours: Option<f64>,
+}
+
+impl ValveServiceConfig {
+    /// Whether usage since the last service has reached a threshold.
+    pub fn is_due(&self, odometer: &ZoneOdometer) -> bool {
+        self.actuations
+            .is_some_and(|max| odometer.actuations_since_service >= max)
+            || self
+                .open_hours
+                .is_some_and(|max| odometer.open_sec_since_service as f64 >= max * 3600.0)
+    }
+}
+
+/// Emergency-stop button on a GPIO input.  A press held for `debounce_ms`
+/// turns every valve off and latches a lockout until it is cleared over
+/// the API.  With `active_low` (the default) the button connects the pin to
+/// ground and the internal pull-up holds it high otherwise.
+///
+/// ```toml
+/// [emergency_stop]
+/// gpio_pin = 26
+/// active_low = true
+/// debounce_ms = 50
+/// ```
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+pub struct EmergencyStopConfig {
+    pub gpio_pin: i64,
+    #[serde(default = "default_estop_active_low")]
+    pub active_low: bool,
+    #[serde(default = "default_estop_debounce_ms")]
+    pub debounce_ms: u64,
+}
+
+fn default_estop_active_low() -> bool {
+    true
+}
+
+fn default_estop_debounce_ms() -> u64 {
+    50
+}
+
+/// Longest accepted emergency-stop debounce.
+pub const MAX_ESTOP_DEBOUNCE_MS: u64 = 1000;
+
+impl FrostConfig {
+    /// `(lockout_below_c, release_above_c)` when the lockout is enabled.
+    pub fn thresholds(&self) -> Option<(f64, f64)> {
+        let below = self.lockout_below_c?;
+        Some((below, self.release_above_c.unwrap_or(below + 1.0)))
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ZoneEntry {
     pub zone_id: String,
     pub name: String,
@@ -66,8 +384,48 @@ pub struct ZoneEntry {
     pub stale_timeout_min: i64,
     #[serde(default)]
     pub valve_gpio_pin: i64,
+    /// Measured valve flow (litres/minute) for water-usage reports.
+    #[serde(default)]
+    pub flow_lpm: Option<f32>,
+    /// 1-based channel on the configured `[relay_board]`; resolves to that
+    /// channel's GPIO pin.  Mutually exclusive with `valve_gpio_pin`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relay_channel: Option<usize>,
+    /// Watering strategy; defaults to the moisture threshold.
+    #[serde(default)]
+    pub strategy: StrategyConfig,
+    /// Higher is served first when a `[budget]` runs low or valve slots are
+    /// scarce (see `arbitration`).
+    #[serde(default)]
+    pub priority: i64,
+    /// Valve hardware; defaults to a solenoid on `valve_gpio_pin`.
+    #[serde(default)]
+    pub valve: ValveConfig,
+    /// Zones that must finish their cycle for the day before this one is
+    /// considered for watering (e.g. a downstream bed on a shared drip line).
+    #[serde(default)]
+    pub after: Vec<String>,
+    /// How the zone's sensors are combined: `mean` (default, weighted),
+    /// `median` or `min`.
+    #[serde(default)]
+    pub aggregation: Aggregation,
+    /// Free-form details, shown with the zone (at most
+    /// [`MAX_ZONE_TEXT_CHARS`] each).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plant_type: Option<String>,
+    /// Nominal emitter flow (litres/hour) for usage estimates when
+    /// `flow_lpm` isn't measured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emitter_flow_lph: Option<f32>,
 }
 
+/// Longest `notes`, `location` or `plant_type` on a zone.
+pub const MAX_ZONE_TEXT_CHARS: usize = 2000;
+
 fn default_pulse_sec() -> i64 {
     30
 }
@@ -80,35 +438,219 @@ fn default_max_open_sec_per_day() -> i64 {
 fn default_max_pulses_per_day() -> i64 {
     6
 }
+fn is_true(b: &bool) -> bool {
+    *b
+}
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct SensorEntry {
     pub sensor_id: String,
     pub node_id: String,
     pub zone_id: String,
     pub raw_dry: i64,
     pub raw_wet: i64,
+    /// ADS1115 input on the node (default: derived from the local id).
+    #[serde(default)]
+    pub channel: Option<i64>,
+    /// Share of the zone's moisture value (default 1; 0 = ignored for
+    /// watering, e.g. a probe in a shaded corner).
+    #[serde(default = "default_sensor_weight")]
+    pub weight: f64,
+    /// Raw counts a reading may fall outside `raw_dry`..`raw_wet` before
+    /// it counts as a sensor failure (default 3000).
+    #[serde(default)]
+    pub failure_margin: Option<i64>,
+    /// The failure margin as a percentage of the calibration range instead.
+    #[serde(default)]
+    pub failure_margin_pct: Option<f64>,
+    /// Non-linear calibration (default: linear from `raw_dry` to `raw_wet`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub curve: Option<Curve>,
+    /// `false` takes the sensor out of service: the node stops sampling its
+    /// channel and it doesn't count toward zone moisture (default true).
+    #[serde(default = "default_sensor_enabled", skip_serializing_if = "is_true")]
+    pub enabled: bool,
+}
+
+/// A stored zone as a config entry, on its resolved GPIO pin.
+impl From<&ZoneConfig> for ZoneEntry {
+    fn from(z: &ZoneConfig) -> Self {
+        Self {
+            zone_id: z.zone_id.clone(),
+            name: z.name.clone(),
+            min_moisture: z.min_moisture,
+            target_moisture: z.target_moisture,
+            pulse_sec: z.pulse_sec,
+            soak_min: z.soak_min,
+            max_open_sec_per_day: z.max_open_sec_per_day,
+            max_pulses_per_day: z.max_pulses_per_day,
+            stale_timeout_min: z.stale_timeout_min,
+            valve_gpio_pin: z.valve_gpio_pin,
+            flow_lpm: z.flow_lpm,
+            relay_channel: None,
+            strategy: z.strategy.clone(),
+            priority: z.priority,
+            valve: z.valve.clone(),
+            after: z.after.clone(),
+            aggregation: z.aggregation,
+            notes: z.notes.clone(),
+            location: z.location.clone(),
+            plant_type: z.plant_type.clone(),
+            emitter_flow_lph: z.emitter_flow_lph,
+        }
+    }
+}
+
+impl From<&SensorConfig> for SensorEntry {
+    fn from(s: &SensorConfig) -> Self {
+        Self {
+            sensor_id: s.sensor_id.clone(),
+            node_id: s.node_id.clone(),
+            zone_id: s.zone_id.clone(),
+            raw_dry: s.raw_dry,
+            raw_wet: s.raw_wet,
+            channel: s.channel,
+            weight: s.weight,
+            failure_margin: s.failure_margin,
+            failure_margin_pct: s.failure_margin_pct,
+            curve: s.curve.clone(),
+            enabled: s.enabled,
+        }
+    }
+}
+
+/// The `zones` and `sensors` sections of a config file on their own, as
+/// served by `GET /api/config/export` and taken by `PUT /api/config/import`
+/// (TOML or JSON).  An export pastes straight into `config.toml`.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ZoneExport {
+    #[serde(default)]
+    pub zones: Vec<ZoneEntry>,
+    #[serde(default)]
+    pub sensors: Vec<SensorEntry>,
 }
 
 // ---------------------------------------------------------------------------
-// GPIO whitelist
+// Relay board presets
 // ---------------------------------------------------------------------------
 
-/// BCM GPIO pins safe for general-purpose relay driving on the Raspberry Pi
-/// 40-pin header. Excludes:
-///   - GPIO 0-1: ID EEPROM (must never be used)
-///   - GPIO 2-3: I2C1 (SDA/SCL) with hard-wired 1.8k pull-ups — cannot
-///     reliably drive active-low relay optocouplers
-///   - GPIO 7-11: SPI0 (CE1, CE0, MISO, MOSI, SCLK)
-///   - GPIO 14-15: UART0 (TX/RX) — used for serial console
-///   - GPIO 28+: not exposed on the standard header
-const VALID_GPIO_PINS: &[i64] = &[
-    4, 5, 6, 12, 13, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27,
+/// `[relay_board]` section.  Either pick a `preset` (and optionally override
+/// parts of it) or describe a custom board with `active_low` + `pins`.
+///
+/// ```toml
+/// [relay_board]
+/// preset = "sainsmart-8ch"
+/// # stagger_ms = 500      # override the preset's value
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RelayBoardConfig {
+    pub preset: Option<String>,
+    pub active_low: Option<bool>,
+    /// BCM pin for each channel, channel 1 first.
+    pub pins: Option<Vec<i64>>,
+    /// Minimum delay between switching two relays, to spread the load of
+    /// several valves opening or closing back-to-back.
+    pub stagger_ms: Option<u64>,
+}
+
+/// A known relay board and its conventional wiring to the Pi header.
+pub struct RelayBoardPreset {
+    pub name: &'static str,
+    pub active_low: bool,
+    pub pins: &'static [i64],
+    pub stagger_ms: u64,
+}
+
+/// Built-in presets.  Pin lists follow the wiring used in WALKTHROUGH.md
+/// (channel 1 → GPIO 17, channel 2 → GPIO 27, …) except for HATs, whose
+/// pins are fixed by the PCB.
+pub const RELAY_BOARD_PRESETS: &[RelayBoardPreset] = &[
+    RelayBoardPreset {
+        name: "sainsmart-2ch",
+        active_low: true,
+        pins: &[17, 27],
+        stagger_ms: 250,
+    },
+    RelayBoardPreset {
+        name: "sainsmart-4ch",
+        active_low: true,
+        pins: &[17, 27, 22, 23],
+        stagger_ms: 250,
+    },
+    RelayBoardPreset {
+        name: "sainsmart-8ch",
+        active_low: true,
+        pins: &[17, 27, 22, 23, 24, 25, 5, 6],
+        stagger_ms: 250,
+    },
+    RelayBoardPreset {
+        name: "generic-4ch-active-high",
+        active_low: false,
+        pins: &[17, 27, 22, 23],
+        stagger_ms: 250,
+    },
+    // Waveshare "RPi Relay Board" HAT: 3 channels hard-wired to GPIO 26/20/21.
+    RelayBoardPreset {
+        name: "waveshare-rpi-relay",
+        active_low: true,
+        pins: &[26, 20, 21],
+        stagger_ms: 250,
+    },
 ];
 
+/// Fully resolved relay board description.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelayBoard {
+    pub active_low: bool,
+    pub pins: Vec<i64>,
+    pub stagger_ms: u64,
+}
+
+impl RelayBoardConfig {
+    /// Merge the preset (if any) with explicit overrides.
+    pub fn resolve(&self) -> Result<RelayBoard, String> {
+        let preset = match &self.preset {
+            Some(name) => Some(
+                RELAY_BOARD_PRESETS
+                    .iter()
+                    .find(|p| p.name.eq_ignore_ascii_case(name))
+                    .ok_or_else(|| {
+                        let known: Vec<&str> = RELAY_BOARD_PRESETS.iter().map(|p| p.name).collect();
+                        format!("relay_board: unknown preset '{name}' (known: {known:?})")
+                    })?,
+            ),
+            None => None,
+        };
+
+        let pins = match (&self.pins, preset) {
+            (Some(pins), _) => pins.clone(),
+            (None, Some(p)) => p.pins.to_vec(),
+            (None, None) => {
+                return Err("relay_board: either preset or pins must be set".to_string())
+            }
+        };
+
+        Ok(RelayBoard {
+            active_low: self
+                .active_low
+                .or(preset.map(|p| p.active_low))
+                .unwrap_or(true),
+            pins,
+            stagger_ms: self
+                .stagger_ms
+                .or(preset.map(|p| p.stagger_ms))
+                .unwrap_or(0),
+        })
+    }
+}
+
 /// Maximum single-ended reading from the ADS1115 (15-bit unsigned).
 const ADS1115_MAX: i64 = 32767;
 
+/// Longest allowed emitter flush: it's a surge, not a watering.
+const MAX_FLUSH_SEC: i64 = 120;
+
 // ---------------------------------------------------------------------------
 // Validation
 // ---------------------------------------------------------------------------
@@ -117,6 +659,21 @@ impl Config {
     /// Validate all config entries. Returns `Ok(())` or an error describing
     /// every violation found (not just the first one).
     pub fn validate(&self) -> Result<()> {
+        let errors = self.errors();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            bail!(
+                "config validation failed ({} error{}):\n  - {}",
+                errors.len(),
+                if errors.len() == 1 { "" } else { "s" },
+                errors.join("\n  - ")
+            );
+        }
+    }
+
+    /// Every violation [`validate`](Self::validate) reports, one per entry.
+    pub fn errors(&self) -> Vec<String> {
         let mut errors: Vec<String> = Vec::new();
 
         // max_concurrent_valves is only relevant in auto mode.
@@ -124,18 +681,95 @@ impl Config {
             errors.push("max_concurrent_valves must be at least 1".to_string());
         }
 
+        if let Err(errs) = self.gpio.validate() {
+            errors.extend(errs);
+        }
+        self.validate_relay_board(&mut errors);
         self.validate_zones(&mut errors);
         self.validate_sensors(&mut errors);
+        self.validate_soak(&mut errors);
+        self.validate_budget(&mut errors);
+        self.validate_dependencies(&mut errors);
+        self.validate_frost(&mut errors);
+        if let Err(errs) = self.pressure.validate() {
+            errors.extend(errs);
+        }
+        self.validate_flush(&mut errors);
+        self.validate_valve_service(&mut errors);
+        if let Err(errs) = self.valve_test.validate() {
+            errors.extend(errs);
+        }
+        self.validate_emergency_stop(&mut errors);
+        self.validate_interlocks(&mut errors);
+        if let Err(errs) = MaintenanceWindows::parse(&self.maintenance.windows) {
+            errors.extend(errs);
+        }
+        if let Err(errs) = self.retention.validate() {
+            errors.extend(errs);
+        }
+        if let Err(errs) = self.federation.validate() {
+            errors.extend(errs);
+        }
+        if let Err(errs) = self.et.validate() {
+            errors.extend(errs);
+        }
+        if let Err(errs) = self.moisture_alerts.validate() {
+            errors.extend(errs);
+        }
+        if let Err(errs) = self.sunlight.validate() {
+            errors.extend(errs);
+        }
+        if let Err(errs) = self.ota.validate() {
+            errors.extend(errs);
+        }
+        if let Err(errs) = adapters::validate(&self.mqtt_adapters) {
+            errors.extend(errs);
+        }
+        errors
+    }
 
-        if errors.is_empty() {
-            Ok(())
-        } else {
-            bail!(
-                "config validation failed ({} error{}):\n  - {}",
-                errors.len(),
-                if errors.len() == 1 { "" } else { "s" },
-                errors.join("\n  - ")
-            );
+    /// The parsed maintenance windows (empty if unset or invalid).
+    pub fn maintenance_windows(&self) -> MaintenanceWindows {
+        MaintenanceWindows::parse(&self.maintenance.windows).unwrap_or_default()
+    }
+
+    /// The resolved relay board, if one is configured and valid.
+    pub fn relay_board(&self) -> Option<RelayBoard> {
+        self.relay_board.as_ref().and_then(|b| b.resolve().ok())
+    }
+
+    /// GPIO pin a zone drives: its `relay_channel` on the relay board when
+    /// set, otherwise `valve_gpio_pin`.  Unresolvable channels map to 0,
+    /// which `validate` rejects in auto mode.
+    pub fn zone_gpio_pin(&self, z: &ZoneEntry) -> i64 {
+        match z.relay_channel {
+            Some(ch) => self
+                .relay_board()
+                .and_then(|b| ch.checked_sub(1).and_then(|i| b.pins.get(i).copied()))
+                .unwrap_or(0),
+            None => z.valve_gpio_pin,
+        }
+    }
+
+    fn validate_relay_board(&self, errors: &mut Vec<String>) {
+        let Some(cfg) = &self.relay_board else {
+            return;
+        };
+        let allowed = self.gpio.allowed_pins();
+        match cfg.resolve() {
+            Ok(board) => {
+                let mut seen: HashSet<i64> = HashSet::new();
+                for pin in &board.pins {
+                    if !allowed.contains(pin) {
+                        errors.push(format!(
+                            "relay_board: pin {pin} is not a safe GPIO pin (allowed: {allowed:?})"
+                        ));
+                    } else if !seen.insert(*pin) {
+                        errors.push(format!("relay_board: pin {pin} listed more than once"));
+                    }
+                }
+            }
+            Err(e) => errors.push(e),
         }
     }
 
@@ -143,6 +777,7 @@ impl Config {
         let mut seen_ids: HashSet<&str> = HashSet::new();
         let mut seen_pins: HashSet<i64> = HashSet::new();
         let is_auto = self.mode == OperationMode::Auto;
+        let allowed = self.gpio.allowed_pins();
 
         for (i, z) in self.zones.iter().enumerate() {
             let ctx = || {
@@ -229,6 +864,41 @@ impl Config {
                 ));
             }
 
+            if let Some(flow) = z.flow_lpm {
+                if flow.is_nan() || flow <= 0.0 {
+                    errors.push(format!(
+                        "{}: flow_lpm must be positive, got {}",
+                        ctx(),
+                        flow
+                    ));
+                }
+            }
+            if let Some(flow) = z.emitter_flow_lph.filter(|f| f.is_nan() || *f <= 0.0) {
+                errors.push(format!(
+                    "{}: emitter_flow_lph must be positive, got {flow}",
+                    ctx()
+                ));
+            }
+            for (field, text) in [
+                ("notes", &z.notes),
+                ("location", &z.location),
+                ("plant_type", &z.plant_type),
+            ] {
+                if text
+                    .as_deref()
+                    .is_some_and(|t| t.chars().count() > MAX_ZONE_TEXT_CHARS)
+                {
+                    errors.push(format!(
+                        "{}: {field} must be at most {MAX_ZONE_TEXT_CHARS} characters",
+                        ctx()
+                    ));
+                }
+            }
+
+            for e in z.strategy.validate() {
+                errors.push(format!("{}: {e}", ctx()));
+            }
+
             // pulse_sec cannot exceed the daily maximum (auto mode only).
             if is_auto
                 && z.pulse_sec > 0
@@ -243,23 +913,72 @@ impl Config {
                 ));
             }
 
+            // ── Relay channel → pin (auto mode only) ─────────────
+            let pin = self.zone_gpio_pin(z);
+            let channel_resolved = z.relay_channel.is_none() || pin != 0;
+            if let (true, Some(ch)) = (is_auto, z.relay_channel) {
+                if z.valve_gpio_pin != 0 {
+                    errors.push(format!(
+                        "{}: set either relay_channel or valve_gpio_pin, not both",
+                        ctx()
+                    ));
+                }
+                match self.relay_board() {
+                    None if self.relay_board.is_none() => errors.push(format!(
+                        "{}: relay_channel {ch} requires a [relay_board] section",
+                        ctx()
+                    )),
+                    None => {} // board error already reported
+                    Some(b) if ch == 0 || ch > b.pins.len() => errors.push(format!(
+                        "{}: relay_channel {ch} out of range (board has {} channels)",
+                        ctx(),
+                        b.pins.len()
+                    )),
+                    Some(_) => {}
+                }
+            }
+
             // ── GPIO pin whitelist (auto mode only) ──────────────
-            if is_auto {
-                if !VALID_GPIO_PINS.contains(&z.valve_gpio_pin) {
+            if is_auto && channel_resolved {
+                if !allowed.contains(&pin) {
                     errors.push(format!(
                         "{}: valve_gpio_pin {} is not a safe GPIO pin (allowed: {:?})",
                         ctx(),
-                        z.valve_gpio_pin,
-                        VALID_GPIO_PINS,
+                        pin,
+                        allowed,
                     ));
-                } else if !seen_pins.insert(z.valve_gpio_pin) {
+                } else if !seen_pins.insert(pin) {
                     errors.push(format!(
                         "{}: valve_gpio_pin {} is already used by another zone",
                         ctx(),
-                        z.valve_gpio_pin
+                        pin
                     ));
                 }
             }
+
+            // ── Motorized valve (auto mode only) ─────────────────
+            if is_auto {
+                for e in z.valve.validate(pin) {
+                    errors.push(format!("{}: {e}", ctx()));
+                }
+                if let ValveConfig::Motorized { close_gpio_pin, .. } = z.valve {
+                    if close_gpio_pin < 0 || close_gpio_pin == pin {
+                        // Already reported above.
+                    } else if !allowed.contains(&close_gpio_pin) {
+                        errors.push(format!(
+                            "{}: valve close_gpio_pin {} is not a safe GPIO pin",
+                            ctx(),
+                            close_gpio_pin
+                        ));
+                    } else if !seen_pins.insert(close_gpio_pin) {
+                        errors.push(format!(
+                            "{}: valve close_gpio_pin {} is already used by another zone",
+                            ctx(),
+                            close_gpio_pin
+                        ));
+                    }
+                }
+            }
         }
     }
 
@@ -319,10 +1038,292 @@ impl Config {
                     s.raw_dry
                 ));
             }
+            if let Some(ch) = s.channel {
+                if !(0..=ADS1115_MAX_CHANNEL).contains(&ch) {
+                    errors.push(format!(
+                        "{}: channel {ch} out of ADS1115 range [0, {ADS1115_MAX_CHANNEL}]",
+                        ctx()
+                    ));
+                }
+            }
+            if !s.weight.is_finite() || s.weight < 0.0 {
+                errors.push(format!(
+                    "{}: weight must be zero or positive, got {}",
+                    ctx(),
+                    s.weight
+                ));
+            }
+            if s.failure_margin.is_some() && s.failure_margin_pct.is_some() {
+                errors.push(format!(
+                    "{}: set failure_margin or failure_margin_pct, not both",
+                    ctx()
+                ));
+            }
+            if let Some(m) = s.failure_margin.filter(|m| *m < 0) {
+                errors.push(format!(
+                    "{}: failure_margin must be zero or positive, got {m}",
+                    ctx()
+                ));
+            }
+            if let Some(p) = s.failure_margin_pct.filter(|p| !(0.0..=100.0).contains(p)) {
+                errors.push(format!(
+                    "{}: failure_margin_pct must be 0–100, got {p}",
+                    ctx()
+                ));
+            }
+            if let Some(Err(errs)) = s.curve.as_ref().map(Curve::validate) {
+                errors.extend(errs.into_iter().map(|e| format!("{}: {e}", ctx())));
+            }
+        }
+    }
+
+    fn validate_budget(&self, errors: &mut Vec<String>) {
+        let b = &self.budget;
+        let mut check_limits = |scope: &str, open_sec: Option<i64>, litres: Option<f64>| {
+            if open_sec.is_some_and(|v| v <= 0) {
+                errors.push(format!("{scope}: daily_open_sec must be positive"));
+            }
+            if litres.is_some_and(|v| v <= 0.0 || !v.is_finite()) {
+                errors.push(format!("{scope}: daily_litres must be positive"));
+            }
+        };
+        check_limits("budget", b.daily_open_sec, b.daily_litres);
+        for (name, g) in &b.groups {
+            check_limits(
+                &format!("budget group '{name}'"),
+                g.daily_open_sec,
+                g.daily_litres,
+            );
+        }
+
+        let known: HashSet<&str> = self.zones.iter().map(|z| z.zone_id.as_str()).collect();
+        for (name, g) in &b.groups {
+            if g.zones.is_empty() {
+                errors.push(format!("budget group '{name}': zones must not be empty"));
+            }
+            if g.daily_open_sec.is_none() && g.daily_litres.is_none() {
+                errors.push(format!(
+                    "budget group '{name}': set daily_open_sec and/or daily_litres"
+                ));
+            }
+            for z in &g.zones {
+                if !known.contains(z.as_str()) {
+                    errors.push(format!("budget group '{name}': unknown zone '{z}'"));
+                }
+            }
+        }
+
+        // Litre budgets can only count zones with a measured flow.
+        for z in self.zones.iter().filter(|z| z.flow_lpm.is_none()) {
+            let in_litre_group = b
+                .groups
+                .values()
+                .any(|g| g.daily_litres.is_some() && g.zones.contains(&z.zone_id));
+            if b.daily_litres.is_some() || in_litre_group {
+                errors.push(format!(
+                    "zone '{}': flow_lpm is required under a daily_litres budget",
+                    z.zone_id
+                ));
+            }
+        }
+    }
+
+    fn validate_dependencies(&self, errors: &mut Vec<String>) {
+        let known: HashSet<&str> = self.zones.iter().map(|z| z.zone_id.as_str()).collect();
+        for z in &self.zones {
+            for up in &z.after {
+                if !known.contains(up.as_str()) {
+                    errors.push(format!("zone '{}': after: unknown zone '{up}'", z.zone_id));
+                }
+            }
+        }
+        let deps: BTreeMap<&str, &[String]> = self
+            .zones
+            .iter()
+            .map(|z| (z.zone_id.as_str(), z.after.as_slice()))
+            .collect();
+        if let Some(cycle) = dependency_cycle(&deps) {
+            errors.push(format!("zone dependency cycle: {}", cycle.join(" -> ")));
+        }
+    }
+
+    fn validate_frost(&self, errors: &mut Vec<String>) {
+        let f = &self.frost;
+        for (name, v) in [
+            ("lockout_below_c", f.lockout_below_c),
+            ("release_above_c", f.release_above_c),
+        ] {
+            if v.is_some_and(|v| !v.is_finite()) {
+                errors.push(format!("frost: {name} must be a number"));
+            }
+        }
+        match (f.lockout_below_c, f.release_above_c) {
+            (None, Some(_)) => {
+                errors.push("frost: release_above_c requires lockout_below_c".to_string())
+            }
+            (Some(below), Some(above)) if above < below => errors.push(format!(
+                "frost: release_above_c ({above}) must not be below lockout_below_c ({below})"
+            )),
+            _ => {}
+        }
+    }
+
+    fn validate_flush(&self, errors: &mut Vec<String>) {
+        let f = &self.flush;
+        if f.interval_days <= 0 {
+            errors.push(format!(
+                "flush: interval_days must be positive, got {}",
+                f.interval_days
+            ));
+        }
+        if !(1..=MAX_FLUSH_SEC).contains(&f.duration_sec) {
+            errors.push(format!(
+                "flush: duration_sec must be 1..={MAX_FLUSH_SEC}, got {}",
+                f.duration_sec
+            ));
+        }
+        if let Err(errs) = MaintenanceWindows::parse(&f.windows) {
+            errors.extend(errs.into_iter().map(|e| format!("flush: {e}")));
+        }
+        let known: HashSet<&str> = self.zones.iter().map(|z| z.zone_id.as_str()).collect();
+        for z in &f.zones {
+            if !known.contains(z.as_str()) {
+                errors.push(format!("flush: unknown zone '{z}'"));
+            }
+        }
+    }
+
+    fn validate_valve_service(&self, errors: &mut Vec<String>) {
+        let v = &self.valve_service;
+        if v.actuations.is_some_and(|n| n <= 0) {
+            errors.push("valve_service: actuations must be positive".to_string());
+        }
+        if v.open_hours.is_some_and(|h| h <= 0.0 || !h.is_finite()) {
+            errors.push("valve_service: open_hours must be positive".to_string());
+        }
+    }
+
+    fn validate_interlocks(&self, errors: &mut Vec<String>) {
+        let known: HashSet<&str> = self.zones.iter().map(|z| z.zone_id.as_str()).collect();
+        for (name, zones) in &self.interlocks {
+            let distinct: HashSet<&str> = zones.iter().map(String::as_str).collect();
+            if distinct.len() < 2 {
+                errors.push(format!(
+                    "interlock group '{name}': needs at least two zones"
+                ));
+            }
+            for z in zones {
+                if !known.contains(z.as_str()) {
+                    errors.push(format!("interlock group '{name}': unknown zone '{z}'"));
+                }
+            }
+        }
+    }
+
+    fn validate_emergency_stop(&self, errors: &mut Vec<String>) {
+        let Some(e) = &self.emergency_stop else {
+            return;
+        };
+        let allowed = self.gpio.allowed_pins();
+        if !allowed.contains(&e.gpio_pin) {
+            errors.push(format!(
+                "emergency_stop: gpio_pin {} is not a safe GPIO pin (allowed: {allowed:?})",
+                e.gpio_pin
+            ));
+        }
+        let valve_pins = self.zones.iter().flat_map(|z| {
+            let close = match z.valve {
+                ValveConfig::Motorized { close_gpio_pin, .. } => Some(close_gpio_pin),
+                ValveConfig::Solenoid => None,
+            };
+            std::iter::once(self.zone_gpio_pin(z)).chain(close)
+        });
+        let board_pins = self.relay_board().map(|b| b.pins).unwrap_or_default();
+        if valve_pins.chain(board_pins).any(|p| p == e.gpio_pin) {
+            errors.push(format!(
+                "emergency_stop: gpio_pin {} is already used by a valve or relay",
+                e.gpio_pin
+            ));
+        }
+        if !(1..=MAX_ESTOP_DEBOUNCE_MS).contains(&e.debounce_ms) {
+            errors.push(format!(
+                "emergency_stop: debounce_ms must be 1..={MAX_ESTOP_DEBOUNCE_MS}, got {}",
+                e.debounce_ms
+            ));
+        }
+    }
+
+    fn validate_soak(&self, errors: &mut Vec<String>) {
+        let s = &self.soak;
+        if s.early_exit_after_min < 0 {
+            errors.push(format!(
+                "soak: early_exit_after_min must not be negative, got {}",
+                s.early_exit_after_min
+            ));
+        }
+        if s.extend_on_rise {
+            if s.rise_threshold_per_min <= 0.0 {
+                errors.push(format!(
+                    "soak: rise_threshold_per_min must be positive, got {}",
+                    s.rise_threshold_per_min
+                ));
+            }
+            if s.rise_window_min <= 0 {
+                errors.push(format!(
+                    "soak: rise_window_min must be positive, got {}",
+                    s.rise_window_min
+                ));
+            }
+            if s.max_extend_min <= 0 {
+                errors.push(format!(
+                    "soak: max_extend_min must be positive, got {}",
+                    s.max_extend_min
+                ));
+            }
         }
     }
 }
 
+/// Find a cycle in zone `after` dependencies (zone → upstream zones).
+/// Returns the zones along the cycle, starting and ending with the same
+/// one.  Unknown upstream zones are ignored.
+pub fn dependency_cycle<'a>(deps: &BTreeMap<&'a str, &'a [String]>) -> Option<Vec<&'a str>> {
+    fn visit<'a>(
+        zone: &'a str,
+        deps: &BTreeMap<&'a str, &'a [String]>,
+        done: &mut HashSet<&'a str>,
+        path: &mut Vec<&'a str>,
+    ) -> Option<Vec<&'a str>> {
+        path.push(zone);
+        for up in deps.get(zone).copied().unwrap_or_default() {
+            let up = up.as_str();
+            if let Some(start) = path.iter().position(|z| *z == up) {
+                let mut cycle = path[start..].to_vec();
+                cycle.push(up);
+                return Some(cycle);
+            }
+            if deps.contains_key(up) && !done.contains(up) {
+                if let Some(cycle) = visit(up, deps, done, path) {
+                    return Some(cycle);
+                }
+            }
+        }
+        path.pop();
+        done.insert(zone);
+        None
+    }
+
+    let mut done = HashSet::new();
+    for zone in deps.keys() {
+        if !done.contains(zone) {
+            if let Some(cycle) = visit(zone, deps, &mut done, &mut Vec::new()) {
+                return Some(cycle);
+            }
+        }
+    }
+    None
+}
+
 // ---------------------------------------------------------------------------
 // Load + apply
 // ---------------------------------------------------------------------------
@@ -352,7 +1353,18 @@ pub async fn apply(config: &Config, db: &Db) -> Result<()> {
             max_open_sec_per_day: z.max_open_sec_per_day,
             max_pulses_per_day: z.max_pulses_per_day,
             stale_timeout_min: z.stale_timeout_min,
-            valve_gpio_pin: z.valve_gpio_pin,
+            valve_gpio_pin: config.zone_gpio_pin(z),
+            flow_lpm: z.flow_lpm,
+            strategy: z.strategy.clone(),
+            priority: z.priority,
+            valve: z.valve.clone(),
+            after: z.after.clone(),
+            aggregation: z.aggregation,
+            archived_at: None,
+            notes: z.notes.clone(),
+            location: z.location.clone(),
+            plant_type: z.plant_type.clone(),
+            emitter_flow_lph: z.emitter_flow_lph,
         })
         .await
         .with_context(|| format!("failed to upsert zone '{}'", z.zone_id))?;
@@ -365,6 +1377,13 @@ pub async fn apply(config: &Config, db: &Db) -> Result<()> {
             zone_id: s.zone_id.clone(),
             raw_dry: s.raw_dry,
             raw_wet: s.raw_wet,
+            channel: s.channel,
+            archived_at: None,
+            weight: s.weight,
+            failure_margin: s.failure_margin,
+            failure_margin_pct: s.failure_margin_pct,
+            curve: s.curve.clone(),
+            enabled: s.enabled,
         })
         .await
         .with_context(|| format!("failed to upsert sensor '{}'", s.sensor_id))?;
@@ -401,6 +1420,17 @@ mod tests {
             max_pulses_per_day: 6,
             stale_timeout_min: 30,
             valve_gpio_pin: 17,
+            flow_lpm: None,
+            relay_channel: None,
+            strategy: StrategyConfig::default(),
+            priority: 0,
+            valve: ValveConfig::default(),
+            after: Vec::new(),
+            aggregation: Aggregation::Mean,
+            notes: None,
+            location: None,
+            plant_type: None,
+            emitter_flow_lph: None,
         }
     }
 
@@ -411,6 +1441,12 @@ mod tests {
             zone_id: "z1".into(),
             raw_dry: 26000,
             raw_wet: 12000,
+            channel: None,
+            weight: 1.0,
+            failure_margin: None,
+            failure_margin_pct: None,
+            curve: None,
+            enabled: true,
         }
     }
 
@@ -420,6 +1456,7 @@ mod tests {
             max_concurrent_valves: 2,
             zones: vec![valid_zone()],
             sensors: vec![valid_sensor()],
+            ..Config::default()
         }
     }
 
@@ -438,8 +1475,20 @@ mod tests {
                 max_pulses_per_day: 0,   // irrelevant in monitor mode
                 stale_timeout_min: 30,
                 valve_gpio_pin: 0, // irrelevant in monitor mode
+                flow_lpm: None,
+                relay_channel: None,
+                strategy: StrategyConfig::default(),
+                priority: 0,
+                valve: ValveConfig::default(),
+                after: Vec::new(),
+                aggregation: Aggregation::Mean,
+                notes: None,
+                location: None,
+                plant_type: None,
+                emitter_flow_lph: None,
             }],
             sensors: vec![valid_sensor()],
+            ..Config::default()
         }
     }
 
@@ -505,6 +1554,7 @@ raw_wet = 12000
             max_concurrent_valves: 2,
             zones: vec![],
             sensors: vec![],
+            ..Config::default()
         };
         cfg.validate().unwrap();
     }
@@ -539,6 +1589,7 @@ raw_wet = 12000
                     ..valid_sensor()
                 },
             ],
+            ..Config::default()
         };
         cfg.validate().unwrap();
     }
@@ -569,6 +1620,40 @@ raw_wet = 12000
         assert_validation_err(&cfg, "name is empty");
     }
 
+    #[test]
+    fn zone_metadata_parsed_and_validated() {
+        let cfg: Config = toml::from_str(
+            r#"
+            [[zones]]
+            zone_id = "z1"
+            name = "Beds"
+            min_moisture = 0.3
+            target_moisture = 0.5
+            pulse_sec = 30
+            soak_min = 20
+            max_open_sec_per_day = 180
+            max_pulses_per_day = 6
+            stale_timeout_min = 30
+            valve_gpio_pin = 17
+            notes = "Mulched in April"
+            location = "Back yard"
+            plant_type = "peppers"
+            emitter_flow_lph = 4.0
+            "#,
+        )
+        .unwrap();
+        cfg.validate().unwrap();
+        assert_eq!(cfg.zones[0].plant_type.as_deref(), Some("peppers"));
+        assert_eq!(cfg.zones[0].emitter_flow_lph, Some(4.0));
+
+        let mut cfg = valid_config();
+        cfg.zones[0].emitter_flow_lph = Some(0.0);
+        assert_validation_err(&cfg, "emitter_flow_lph must be positive");
+        let mut cfg = valid_config();
+        cfg.zones[0].location = Some("x".repeat(MAX_ZONE_TEXT_CHARS + 1));
+        assert_validation_err(&cfg, "location must be at most 2000 characters");
+    }
+
     // -- Zone: moisture bounds --------------------------------------------
 
     #[test]
@@ -672,6 +1757,48 @@ raw_wet = 12000
 
     // -- Zone: GPIO whitelist ---------------------------------------------
 
+    #[test]
+    fn motorized_valve_parsed_and_validated() {
+        let toml_str = r#"
+[[zones]]
+zone_id = "z1"
+name = "Ball valve"
+min_moisture = 0.3
+target_moisture = 0.5
+stale_timeout_min = 30
+valve_gpio_pin = 17
+
+[zones.valve]
+type = "motorized"
+close_gpio_pin = 22
+travel_sec = 8
+"#;
+        let mut cfg: Config = toml::from_str(toml_str).unwrap();
+        cfg.validate().unwrap();
+        assert_eq!(
+            cfg.zones[0].valve,
+            ValveConfig::Motorized {
+                close_gpio_pin: 22,
+                travel_sec: 8,
+            }
+        );
+
+        // The close pin counts towards the duplicate-pin check.
+        cfg.zones.push(ZoneEntry {
+            zone_id: "z2".into(),
+            valve_gpio_pin: 22,
+            ..valid_zone()
+        });
+        assert_validation_err(&cfg, "valve_gpio_pin 22 is already used by another zone");
+
+        cfg.zones[0].valve = ValveConfig::Motorized {
+            close_gpio_pin: 1,
+            travel_sec: 0,
+        };
+        assert_validation_err(&cfg, "valve close_gpio_pin 1 is not a safe GPIO pin");
+        assert_validation_err(&cfg, "valve travel_sec must be 1–60, got 0");
+    }
+
     #[test]
     fn zone_gpio_pin_0_rejected() {
         let mut cfg = valid_config();
@@ -761,6 +1888,7 @@ raw_wet = 12000
                 },
             ],
             sensors: vec![],
+            ..Config::default()
         };
         assert_validation_err(&cfg, "already used by another zone");
     }
@@ -774,6 +1902,32 @@ raw_wet = 12000
         assert_validation_err(&cfg, "sensor_id is empty");
     }
 
+    #[test]
+    fn sensor_failure_margin_validated() {
+        let mut cfg = valid_config();
+        cfg.sensors[0].failure_margin_pct = Some(20.0);
+        cfg.validate().unwrap();
+        cfg.sensors[0].failure_margin = Some(-1);
+        assert_validation_err(&cfg, "failure_margin must be zero or positive, got -1");
+        assert_validation_err(&cfg, "set failure_margin or failure_margin_pct, not both");
+        cfg.sensors[0].failure_margin = None;
+        cfg.sensors[0].failure_margin_pct = Some(150.0);
+        assert_validation_err(&cfg, "failure_margin_pct must be 0–100, got 150");
+    }
+
+    #[test]
+    fn sensor_curve_validated() {
+        let mut cfg = valid_config();
+        cfg.sensors[0].curve = Some(Curve::Polynomial {
+            coefficients: vec![0.0, 0.4, 0.6],
+        });
+        cfg.validate().unwrap();
+        cfg.sensors[0].curve = Some(Curve::Polynomial {
+            coefficients: vec![],
+        });
+        assert_validation_err(&cfg, "a polynomial needs 1–6 coefficients, got 0");
+    }
+
     #[test]
     fn sensor_duplicate_id_rejected() {
         let mut cfg = valid_config();
@@ -832,6 +1986,13 @@ raw_wet = 12000
         assert_validation_err(&cfg, "raw_wet 32768 out of ADS1115 range");
     }
 
+    #[test]
+    fn sensor_channel_out_of_range() {
+        let mut cfg = valid_config();
+        cfg.sensors[0].channel = Some(4);
+        assert_validation_err(&cfg, "channel 4 out of ADS1115 range");
+    }
+
     #[test]
     fn sensor_raw_dry_equals_wet() {
         let mut cfg = valid_config();
@@ -858,8 +2019,20 @@ raw_wet = 12000
                 max_pulses_per_day: 0,
                 stale_timeout_min: 0,
                 valve_gpio_pin: 0,
+                flow_lpm: None,
+                relay_channel: None,
+                strategy: StrategyConfig::default(),
+                priority: 0,
+                valve: ValveConfig::default(),
+                after: Vec::new(),
+                aggregation: Aggregation::Mean,
+                notes: None,
+                location: None,
+                plant_type: None,
+                emitter_flow_lph: None,
             }],
             sensors: vec![],
+            ..Config::default()
         };
         let err = cfg.validate().unwrap_err();
         let msg = format!("{err:#}");
@@ -921,6 +2094,21 @@ raw_wet = 12000
         assert_eq!(config.max_concurrent_valves, 4);
     }
 
+    #[test]
+    fn mqtt_adapters_parsed_and_validated() {
+        let toml_str = r#"
+            [[mqtt_adapters]]
+            topic = "tele/tasmota_bed/SENSOR"
+            node_id = "tasmota-bed"
+            readings = [{ sensor_id = "s1", field = "ANALOG.A0", scale = 32 }]
+        "#;
+        let mut config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.mqtt_adapters[0].readings[0].scale, 32.0);
+        config.validate().unwrap();
+        config.mqtt_adapters[0].topic = "tele/+/SENSOR".into();
+        assert_validation_err(&config, "must be an exact topic without wildcards");
+    }
+
     // -- Operation mode ---------------------------------------------------
 
     #[test]
@@ -1032,6 +2220,507 @@ raw_wet = 12000
         config.validate().unwrap();
     }
 
+    // -- relay board presets ----------------------------------------------
+
+    fn board(preset: &str) -> Option<RelayBoardConfig> {
+        Some(RelayBoardConfig {
+            preset: Some(preset.into()),
+            ..RelayBoardConfig::default()
+        })
+    }
+
+    #[test]
+    fn relay_preset_resolves() {
+        let b = board("sainsmart-8ch").unwrap().resolve().unwrap();
+        assert!(b.active_low);
+        assert_eq!(b.pins.len(), 8);
+        assert_eq!(b.pins[0], 17);
+        assert_eq!(b.stagger_ms, 250);
+    }
+
+    #[test]
+    fn relay_preset_overrides_apply() {
+        let cfg = RelayBoardConfig {
+            preset: Some("sainsmart-4ch".into()),
+            active_low: Some(false),
+            stagger_ms: Some(0),
+            ..RelayBoardConfig::default()
+        };
+        let b = cfg.resolve().unwrap();
+        assert!(!b.active_low);
+        assert_eq!(b.stagger_ms, 0);
+        assert_eq!(b.pins, vec![17, 27, 22, 23]);
+    }
+
+    #[test]
+    fn relay_unknown_preset_rejected() {
+        let cfg = Config {
+            relay_board: board("acme-99ch"),
+            ..valid_config()
+        };
+        assert_validation_err(&cfg, "unknown preset 'acme-99ch'");
+    }
+
+    #[test]
+    fn relay_custom_board_unsafe_pin_rejected() {
+        let cfg = Config {
+            relay_board: Some(RelayBoardConfig {
+                pins: Some(vec![17, 2]),
+                ..RelayBoardConfig::default()
+            }),
+            ..valid_config()
+        };
+        assert_validation_err(&cfg, "relay_board: pin 2 is not a safe GPIO pin");
+    }
+
+    #[test]
+    fn board_profile_sets_the_pin_whitelist() {
+        let mut cfg = Config {
+            gpio: toml::from_str("board = \"orange-pi-zero2\"").unwrap(),
+            ..valid_config()
+        };
+        assert_validation_err(&cfg, "valve_gpio_pin 17 is not a safe GPIO pin");
+        cfg.zones[0].valve_gpio_pin = 73;
+        cfg.validate().unwrap();
+
+        cfg.gpio = toml::from_str("board = \"custom\"\npins = [60]").unwrap();
+        assert_validation_err(
+            &cfg,
+            "valve_gpio_pin 73 is not a safe GPIO pin (allowed: [60])",
+        );
+    }
+
+    #[test]
+    fn relay_channel_maps_to_preset_pin() {
+        let cfg = Config {
+            relay_board: board("waveshare-rpi-relay"),
+            zones: vec![ZoneEntry {
+                valve_gpio_pin: 0,
+                relay_channel: Some(2),
+                ..valid_zone()
+            }],
+            ..valid_config()
+        };
+        cfg.validate().unwrap();
+        assert_eq!(cfg.zone_gpio_pin(&cfg.zones[0]), 20);
+    }
+
+    #[test]
+    fn relay_channel_out_of_range_rejected() {
+        let cfg = Config {
+            relay_board: board("sainsmart-2ch"),
+            zones: vec![ZoneEntry {
+                valve_gpio_pin: 0,
+                relay_channel: Some(3),
+                ..valid_zone()
+            }],
+            ..valid_config()
+        };
+        assert_validation_err(&cfg, "relay_channel 3 out of range (board has 2 channels)");
+    }
+
+    #[test]
+    fn relay_channel_without_board_rejected() {
+        let cfg = Config {
+            zones: vec![ZoneEntry {
+                valve_gpio_pin: 0,
+                relay_channel: Some(1),
+                ..valid_zone()
+            }],
+            ..valid_config()
+        };
+        assert_validation_err(&cfg, "requires a [relay_board] section");
+    }
+
+    #[test]
+    fn relay_channel_and_pin_both_set_rejected() {
+        let cfg = Config {
+            relay_board: board("sainsmart-2ch"),
+            zones: vec![ZoneEntry {
+                relay_channel: Some(1),
+                ..valid_zone()
+            }],
+            ..valid_config()
+        };
+        assert_validation_err(&cfg, "set either relay_channel or valve_gpio_pin");
+    }
+
+    #[test]
+    fn relay_board_parsed_from_toml() {
+        let toml_str = r#"
+[relay_board]
+preset = "sainsmart-4ch"
+
+[[zones]]
+zone_id = "z1"
+name = "Zone 1"
+min_moisture = 0.3
+target_moisture = 0.5
+stale_timeout_min = 30
+relay_channel = 4
+"#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.zone_gpio_pin(&config.zones[0]), 23);
+    }
+
+    // -- frost lockout -----------------------------------------------------
+
+    #[test]
+    fn frost_release_defaults_one_degree_above_lockout() {
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.frost.thresholds(), None);
+        let config: Config = toml::from_str("[frost]\nlockout_below_c = 2.0").unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.frost.thresholds(), Some((2.0, 3.0)));
+    }
+
+    #[test]
+    fn frost_release_below_lockout_rejected() {
+        let config: Config =
+            toml::from_str("[frost]\nlockout_below_c = 2.0\nrelease_above_c = 1.0").unwrap();
+        assert_validation_err(
+            &config,
+            "release_above_c (1) must not be below lockout_below_c (2)",
+        );
+        let config: Config = toml::from_str("[frost]\nrelease_above_c = 1.0").unwrap();
+        assert_validation_err(&config, "release_above_c requires lockout_below_c");
+    }
+
+    // -- flush ----------------------------------------------------------------
+
+    #[test]
+    fn flush_defaults_and_validation() {
+        let config: Config = toml::from_str("").unwrap();
+        assert!(config.flush.zones.is_empty());
+        assert_eq!(config.flush.interval_days, 7);
+
+        let mut config = valid_config();
+        config.flush = FlushConfig {
+            zones: vec!["z1".into()],
+            ..FlushConfig::default()
+        };
+        config.validate().unwrap();
+
+        config.flush.zones.push("nope".into());
+        config.flush.duration_sec = 600;
+        config.flush.windows = vec!["5-6".into()];
+        assert_validation_err(&config, "flush: unknown zone 'nope'");
+        assert_validation_err(&config, "flush: duration_sec must be 1..=120, got 600");
+        assert_validation_err(&config, "flush: maintenance window '5-6'");
+    }
+
+    // -- emergency stop -------------------------------------------------------
+
+    #[test]
+    fn emergency_stop_defaults_and_pin_checks() {
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.emergency_stop, None);
+
+        let mut config = valid_config();
+        config.emergency_stop = toml::from_str("gpio_pin = 26").ok();
+        let e = config.emergency_stop.unwrap();
+        assert!(e.active_low);
+        assert_eq!(e.debounce_ms, 50);
+        config.validate().unwrap();
+
+        let pin = config.zone_gpio_pin(&config.zones[0]);
+        config.emergency_stop = Some(EmergencyStopConfig {
+            gpio_pin: pin,
+            debounce_ms: 0,
+            ..e
+        });
+        assert_validation_err(
+            &config,
+            &format!("emergency_stop: gpio_pin {pin} is already used by a valve or relay"),
+        );
+        assert_validation_err(
+            &config,
+            "emergency_stop: debounce_ms must be 1..=1000, got 0",
+        );
+
+        config.emergency_stop = Some(EmergencyStopConfig { gpio_pin: 2, ..e });
+        assert_validation_err(&config, "emergency_stop: gpio_pin 2 is not a safe GPIO pin");
+    }
+
+    // -- valve service --------------------------------------------------------
+
+    #[test]
+    fn valve_service_thresholds() {
+        let config: Config = toml::from_str("").unwrap();
+        assert!(!config.valve_service.is_due(&ZoneOdometer {
+            actuations_since_service: i64::MAX,
+            ..ZoneOdometer::default()
+        }));
+
+        let config: Config =
+            toml::from_str("[valve_service]\nactuations = 100\nopen_hours = 1.5").unwrap();
+        config.validate().unwrap();
+        let due = |actuations, open_sec| {
+            config.valve_service.is_due(&ZoneOdometer {
+                actuations_since_service: actuations,
+                open_sec_since_service: open_sec,
+                ..ZoneOdometer::default()
+            })
+        };
+        assert!(!due(99, 5399));
+        assert!(due(100, 0));
+        assert!(due(0, 5400));
+
+        let config: Config = toml::from_str("[valve_service]\nactuations = 0").unwrap();
+        assert_validation_err(&config, "valve_service: actuations must be positive");
+    }
+
+    // -- soak policy --------------------------------------------------------
+
+    #[test]
+    fn soak_policy_defaults_to_timer_only() {
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.soak, SoakPolicy::default());
+        assert!(!config.soak.early_exit);
+        assert!(!config.soak.extend_on_rise);
+    }
+
+    #[test]
+    fn soak_policy_parsed_from_toml() {
+        let toml_str = r#"
+[soak]
+early_exit = true
+early_exit_after_min = 3
+extend_on_rise = true
+max_extend_min = 15
+"#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.soak.early_exit);
+        assert_eq!(config.soak.early_exit_after_min, 3);
+        assert!(config.soak.extend_on_rise);
+        assert_eq!(config.soak.max_extend_min, 15);
+        assert_eq!(config.soak.rise_window_min, 10); // serde default
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn soak_extension_requires_positive_threshold() {
+        let cfg = Config {
+            soak: SoakPolicy {
+                extend_on_rise: true,
+                rise_threshold_per_min: 0.0,
+                ..SoakPolicy::default()
+            },
+            ..valid_config()
+        };
+        assert_validation_err(&cfg, "rise_threshold_per_min must be positive");
+    }
+
+    #[test]
+    fn soak_negative_early_exit_delay_rejected() {
+        let cfg = Config {
+            soak: SoakPolicy {
+                early_exit: true,
+                early_exit_after_min: -1,
+                ..SoakPolicy::default()
+            },
+            ..valid_config()
+        };
+        assert_validation_err(&cfg, "early_exit_after_min must not be negative");
+    }
+
+    // -- budget -------------------------------------------------------------
+
+    #[test]
+    fn budget_parsed_from_toml() {
+        let config: Config = toml::from_str(
+            r#"
+[budget]
+daily_open_sec = 600
+
+[budget.groups.lawns]
+zones = ["z1"]
+daily_open_sec = 300
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.budget.daily_open_sec, Some(600));
+        assert_eq!(config.budget.groups["lawns"].zones, vec!["z1".to_string()]);
+    }
+
+    #[test]
+    fn budget_validation() {
+        let mut groups = BTreeMap::new();
+        groups.insert(
+            "beds".to_string(),
+            BudgetGroupConfig {
+                zones: vec!["nope".into()],
+                daily_open_sec: Some(0),
+                daily_litres: None,
+            },
+        );
+        let cfg = Config {
+            budget: BudgetConfig {
+                daily_open_sec: None,
+                daily_litres: Some(100.0),
+                groups,
+            },
+            ..valid_config()
+        };
+        assert_validation_err(&cfg, "budget group 'beds': daily_open_sec must be positive");
+        assert_validation_err(&cfg, "budget group 'beds': unknown zone 'nope'");
+        assert_validation_err(&cfg, "flow_lpm is required under a daily_litres budget");
+    }
+
+    // -- interlocks ---------------------------------------------------------
+
+    #[test]
+    fn interlocks_parsed_and_validated() {
+        let config: Config = toml::from_str(
+            r#"
+[interlocks]
+north-line = ["z1", "z2"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.interlocks["north-line"], ["z1", "z2"]);
+
+        let cfg = Config {
+            interlocks: BTreeMap::from([
+                ("solo".to_string(), vec!["z1".to_string(), "z1".to_string()]),
+                (
+                    "far".to_string(),
+                    vec!["z1".to_string(), "nope".to_string()],
+                ),
+            ]),
+            ..valid_config()
+        };
+        assert_validation_err(&cfg, "interlock group 'solo': needs at least two zones");
+        assert_validation_err(&cfg, "interlock group 'far': unknown zone 'nope'");
+    }
+
+    // -- zone dependencies ---------------------------------------------------
+
+    fn zone_after(id: &str, pin: i64, after: &[&str]) -> ZoneEntry {
+        ZoneEntry {
+            zone_id: id.into(),
+            valve_gpio_pin: pin,
+            after: after.iter().map(|s| s.to_string()).collect(),
+            ..valid_zone()
+        }
+    }
+
+    #[test]
+    fn zone_dependencies_validated() {
+        let mut cfg = valid_config();
+        cfg.zones = vec![
+            zone_after("z1", 17, &[]),
+            zone_after("z2", 27, &["z1"]),
+            zone_after("z3", 22, &["z1", "z2"]),
+        ];
+        cfg.validate().unwrap();
+
+        cfg.zones[0].after = vec!["nope".into()];
+        assert_validation_err(&cfg, "zone 'z1': after: unknown zone 'nope'");
+
+        cfg.zones[0].after = vec!["z3".into()];
+        assert_validation_err(&cfg, "zone dependency cycle: z1 -> z3 -> z1");
+
+        cfg.zones[0].after = vec!["z1".into()];
+        assert_validation_err(&cfg, "zone dependency cycle: z1 -> z1");
+    }
+
+    #[test]
+    fn dependency_cycle_ignores_unknown_zones() {
+        let a = vec!["b".to_string(), "x".to_string()];
+        let b = vec!["x".to_string()];
+        let mut deps: BTreeMap<&str, &[String]> = BTreeMap::new();
+        deps.insert("a", &a);
+        deps.insert("b", &b);
+        assert_eq!(dependency_cycle(&deps), None);
+
+        let c = vec!["a".to_string()];
+        let b = vec!["c".to_string()];
+        deps.insert("b", &b);
+        deps.insert("c", &c);
+        assert_eq!(dependency_cycle(&deps), Some(vec!["a", "b", "c", "a"]));
+    }
+
+    // -- maintenance windows ------------------------------------------------
+
+    #[test]
+    fn maintenance_windows_parsed_and_validated() {
+        let config: Config = toml::from_str(
+            r#"
+[maintenance]
+windows = ["02:00-04:00", "23:30-00:30"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.maintenance.windows.len(), 2);
+        assert_ne!(config.maintenance_windows(), MaintenanceWindows::default());
+
+        let cfg = Config {
+            maintenance: MaintenanceConfig {
+                windows: vec!["2-4".into()],
+            },
+            ..valid_config()
+        };
+        assert_validation_err(&cfg, "maintenance window '2-4' must be HH:MM-HH:MM");
+    }
+
+    #[test]
+    fn retention_parsed_and_validated() {
+        let config: Config = toml::from_str(
+            r#"
+[retention]
+readings_days = 30
+watering_events_days = 730
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.retention.readings_days, 30);
+        assert_eq!(config.retention.watering_events_days, Some(730));
+        assert_eq!(config.retention.decisions_days, 30);
+
+        let cfg = Config {
+            retention: RetentionPolicy {
+                logs_days: 0,
+                ..RetentionPolicy::default()
+            },
+            ..valid_config()
+        };
+        assert_validation_err(&cfg, "retention: logs_days must be 1..=3650, got 0");
+    }
+
+    // -- Export ------------------------------------------------------------
+
+    #[test]
+    fn zone_export_round_trips_through_toml() {
+        let export = ZoneExport {
+            zones: vec![ZoneEntry {
+                strategy: StrategyConfig::Schedule {
+                    times: vec!["06:00".into()],
+                    pulses: 2,
+                },
+                valve: ValveConfig::Motorized {
+                    close_gpio_pin: 22,
+                    travel_sec: 5,
+                },
+                flow_lpm: Some(4.5),
+                after: vec!["z0".into()],
+                ..valid_zone()
+            }],
+            sensors: vec![valid_sensor()],
+        };
+        let text = toml::to_string(&export).unwrap();
+        assert!(text.contains("[[zones]]"), "{text}");
+        assert!(!text.contains("relay_channel"), "{text}");
+
+        let parsed: ZoneExport = toml::from_str(&text).unwrap();
+        assert_eq!(toml::to_string(&parsed).unwrap(), text);
+        assert_eq!(parsed.zones[0].strategy, export.zones[0].strategy);
+        assert_eq!(parsed.sensors[0].sensor_id, "node-a/s1");
+
+        assert!(toml::from_str::<ZoneExport>("mode = \"auto\"").is_err());
+    }
+
     // -- DB integration ---------------------------------------------------
 
     #[tokio::test]
@@ -0,0 +1,132 @@
+//! Two-step confirmation of destructive API calls.  The first call returns
+//! a short-lived token instead of acting; repeating the call with that
+//! token within [`CONFIRM_TTL`] carries it out.  This guards against a
+//! script firing the wrong request or a stray tap on a shared tablet — it
+//! is not authentication.
+//!
+//! Restoring a backup always needs a token.  With `API_CONFIRM_DELETES=true`
+//! so does `DELETE` of a zone or sensor (passed as `?confirm=<token>`).
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use tokio::sync::Mutex;
+
+/// How long a confirmation token stays valid.
+pub const CONFIRM_TTL: Duration = Duration::from_secs(120);
+
+struct Pending {
+    token: String,
+    expires_at: Instant,
+}
+
+/// Outstanding tokens, one per action (e.g. `"DELETE zone front-lawn"`).
+/// Asking again for the same action replaces its token.
+#[derive(Default)]
+pub struct Confirmations(HashMap<String, Pending>);
+
+impl Confirmations {
+    /// Issue a token confirming `action`.
+    pub fn issue(&mut self, action: &str, now: Instant) -> String {
+        self.0.retain(|_, p| now < p.expires_at);
+        let token = new_token();
+        self.0.insert(
+            action.to_string(),
+            Pending {
+                token: token.clone(),
+                expires_at: now + CONFIRM_TTL,
+            },
+        );
+        token
+    }
+
+    /// Consume the token if it was issued for `action` and hasn't expired.
+    pub fn take(&mut self, action: &str, token: &str, now: Instant) -> bool {
+        let valid = self
+            .0
+            .get(action)
+            .is_some_and(|p| p.token == token && now < p.expires_at);
+        if valid {
+            self.0.remove(action);
+        }
+        valid
+    }
+}
+
+/// Unpredictable enough to prove the caller saw the first response; this
+/// is a confirmation step, not authentication.
+fn new_token() -> String {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        UNIX_EPOCH
+            .elapsed()
+            .map(|d| d.as_nanos())
+            .unwrap_or_default(),
+    );
+    format!("{:016x}", hasher.finish())
+}
+
+/// Confirmation settings and tokens shared with the web handlers.
+#[derive(Clone, Default)]
+pub struct ConfirmApi {
+    /// `API_CONFIRM_DELETES`: zone and sensor deletes need a token too.
+    pub deletes: bool,
+    pub pending: Arc<Mutex<Confirmations>>,
+}
+
+impl ConfirmApi {
+    pub fn new(deletes: bool) -> Self {
+        Self {
+            deletes,
+            pending: Arc::default(),
+        }
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_must_match_action_and_be_fresh() {
+        let now = Instant::now();
+        let mut c = Confirmations::default();
+        let token = c.issue("restore a.db", now);
+        assert!(!c.take("restore b.db", &token, now));
+        assert!(!c.take("restore a.db", "nope", now));
+        assert!(!c.take("restore a.db", &token, now + CONFIRM_TTL));
+        assert!(c.take("restore a.db", &token, now));
+        // Single use.
+        assert!(!c.take("restore a.db", &token, now));
+    }
+
+    #[test]
+    fn new_token_replaces_pending() {
+        let now = Instant::now();
+        let mut c = Confirmations::default();
+        let first = c.issue("restore a.db", now);
+        let second = c.issue("restore a.db", now);
+        assert_ne!(first, second);
+        assert!(!c.take("restore a.db", &first, now));
+        assert!(c.take("restore a.db", &second, now));
+    }
+
+    #[test]
+    fn actions_have_their_own_tokens() {
+        let now = Instant::now();
+        let mut c = Confirmations::default();
+        let zone = c.issue("DELETE zone z1", now);
+        let sensor = c.issue("DELETE sensor n1/s1", now);
+        assert!(!c.take("DELETE zone z1", &sensor, now));
+        assert!(c.take("DELETE sensor n1/s1", &sensor, now));
+        assert!(c.take("DELETE zone z1", &zone, now));
+    }
+}
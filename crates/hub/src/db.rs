@@ -4,13 +4,122 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous};
-use sqlx::{Pool, QueryBuilder, Row, Sqlite};
+use sqlx::{Connection, Pool, QueryBuilder, Row, Sqlite};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::future::Future;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use time::OffsetDateTime;
+use tokio::sync::Mutex;
+
+use crate::aggregation::{Aggregation, SensorMoisture};
+use crate::audit::{self, AuditEntry, AuditSource};
+use crate::blackout::Blackout;
+use crate::calibration::Curve;
+use crate::clock;
+use crate::efficiency::{self, PulseOutcome};
+use crate::et::{EtDay, EtMethod};
+use crate::flow::DailyFlow;
+use crate::history::{DailyMoisture, UsageTotals};
+use crate::logs::LogRecord;
+use crate::retention::RetentionPolicy;
+use crate::review::Finding;
+use crate::strategy::StrategyConfig;
+use crate::valve::ValveConfig;
 
 #[derive(Clone)]
 pub struct Db {
     pool: Pool<Sqlite>,
+    /// Readings waiting for the next batched write (see `queue_reading`).
+    readings: Arc<Mutex<ReadingBuffer>>,
+    /// Buffered rows that trigger a write; 0 = no buffering.
+    batch_rows: usize,
+    /// Statements retried after SQLite reported the database busy.
+    retried: Arc<AtomicU64>,
+    /// Readings dropped as duplicates of one queued or already stored.
+    duplicates: Arc<AtomicU64>,
+}
+
+/// How long a connection waits on another's lock before SQLite gives up
+/// with `database is locked` (`DB_BUSY_TIMEOUT_MS`).
+pub const DEFAULT_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Pauses before each retry of a statement that still failed busy or
+/// locked.  SQLite returns some of those at once, without waiting out the
+/// busy timeout (a read transaction that can't be upgraded to a write).
+const RETRY_BACKOFF: [Duration; 4] = [
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+];
+
+/// Whether `e` is SQLite's `SQLITE_BUSY` or `SQLITE_LOCKED`, in any of
+/// their extended forms: another connection holds the lock, and trying
+/// again shortly is expected to work.
+fn is_transient(e: &sqlx::Error) -> bool {
+    const SQLITE_BUSY: i32 = 5;
+    const SQLITE_LOCKED: i32 = 6;
+    let sqlx::Error::Database(e) = e else {
+        return false;
+    };
+    e.code()
+        .and_then(|c| c.parse::<i32>().ok())
+        .is_some_and(|c| matches!(c & 0xff, SQLITE_BUSY | SQLITE_LOCKED))
+}
+
+/// Most readings kept queued while writes keep failing; the oldest are
+/// dropped beyond this.
+pub const READING_BUFFER_CAP: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq)]
+struct PendingReading {
+    ts: i64,
+    sensor_id: String,
+    raw: i64,
+    moisture: f64,
+}
+
+#[derive(Default)]
+struct ReadingBuffer {
+    rows: Vec<PendingReading>,
+    /// `(ts, sensor_id)` of the last [`READING_BUFFER_CAP`] readings
+    /// claimed with `claim_reading`, oldest first.  Kept after they are
+    /// written, to spot redeliveries and replays.
+    recent: VecDeque<(i64, String)>,
+    seen: HashSet<(i64, String)>,
+}
+
+impl ReadingBuffer {
+    /// Remember `key`; `false` if it already was.
+    fn claim(&mut self, key: (i64, String)) -> bool {
+        if !self.seen.insert(key.clone()) {
+            return false;
+        }
+        self.recent.push_back(key);
+        if self.recent.len() > READING_BUFFER_CAP {
+            if let Some(old) = self.recent.pop_front() {
+                self.seen.remove(&old);
+            }
+        }
+        true
+    }
+
+    /// Put rows that failed to write back in front of anything queued since.
+    /// Rows dropped for space are forgotten, so a replay can bring them back.
+    fn requeue(&mut self, mut failed: Vec<PendingReading>) {
+        failed.append(&mut self.rows);
+        if failed.len() > READING_BUFFER_CAP {
+            let dropped = failed.len() - READING_BUFFER_CAP;
+            tracing::warn!(dropped, "reading buffer full — dropping oldest readings");
+            for r in failed.drain(..dropped) {
+                self.seen.remove(&(r.ts, r.sensor_id));
+            }
+        }
+        self.rows = failed;
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,6 +138,84 @@ pub struct ZoneConfig {
     pub stale_timeout_min: i64,
 
     pub valve_gpio_pin: i64,
+
+    /// Measured flow through this zone's valve (litres/minute).  When set,
+    /// usage reports convert open time into litres.
+    #[serde(default)]
+    pub flow_lpm: Option<f32>,
+
+    /// Decision strategy (stored as JSON; NULL = moisture threshold).
+    #[serde(default)]
+    pub strategy: StrategyConfig,
+
+    /// Higher is served first when a water budget runs low (default 0).
+    #[serde(default)]
+    pub priority: i64,
+
+    /// Valve hardware (stored as JSON; NULL = solenoid).
+    #[serde(default)]
+    pub valve: ValveConfig,
+
+    /// Zones that must finish their cycle for the day before this one is
+    /// considered for watering (stored as JSON; NULL = none).
+    #[serde(default)]
+    pub after: Vec<String>,
+
+    /// How the zone's sensors are combined (stored as text; NULL = mean).
+    #[serde(default)]
+    pub aggregation: Aggregation,
+
+    /// Free-form details for people; the hub doesn't act on them.
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub location: Option<String>,
+    #[serde(default)]
+    pub plant_type: Option<String>,
+
+    /// Nominal flow of the zone's emitters (litres/hour, e.g. 20 drippers
+    /// × 2 L/h = 40).  Usage reports fall back to it without `flow_lpm`.
+    #[serde(default)]
+    pub emitter_flow_lph: Option<f32>,
+
+    /// Set while the zone is archived.  Archived zones keep their readings
+    /// and watering history but are excluded from `load_zones` (and thus
+    /// scheduling and the valve board).  Owned by `set_zone_archived`;
+    /// upserts leave it untouched.
+    #[serde(default)]
+    pub archived_at: Option<i64>,
+}
+
+#[cfg(test)]
+impl ZoneConfig {
+    /// A zone for tests: 30 s pulses with a 20 min soak between 0.3 and
+    /// 0.5, capped at 6 pulses / 180 s a day, on GPIO 17.  Override fields
+    /// with struct update syntax.
+    pub fn test(zone_id: &str) -> Self {
+        Self {
+            zone_id: zone_id.into(),
+            name: "Test".into(),
+            min_moisture: 0.3,
+            target_moisture: 0.5,
+            pulse_sec: 30,
+            soak_min: 20,
+            max_open_sec_per_day: 180,
+            max_pulses_per_day: 6,
+            stale_timeout_min: 30,
+            valve_gpio_pin: 17,
+            flow_lpm: None,
+            strategy: StrategyConfig::default(),
+            priority: 0,
+            valve: ValveConfig::default(),
+            after: Vec::new(),
+            aggregation: Aggregation::default(),
+            notes: None,
+            location: None,
+            plant_type: None,
+            emitter_flow_lph: None,
+            archived_at: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +225,165 @@ pub struct SensorConfig {
     pub zone_id: String,
     pub raw_dry: i64,
     pub raw_wet: i64,
+    /// ADS1115 input on the node (`None` = derived from the local id, see
+    /// `SensorConfig::channel_or_default`).
+    #[serde(default)]
+    pub channel: Option<i64>,
+    /// Set when the sensor's node was decommissioned.  Archived sensors keep
+    /// their readings but are excluded from `load_sensors` (and thus ingest).
+    #[serde(default)]
+    pub archived_at: Option<i64>,
+    /// Share of the zone's moisture value (0 = ignored for watering).
+    #[serde(default = "default_sensor_weight")]
+    pub weight: f64,
+    /// Raw counts a reading may fall outside the calibration range before it
+    /// counts as a sensor failure (default [`SENSOR_FAILURE_MARGIN`]).
+    #[serde(default)]
+    pub failure_margin: Option<i64>,
+    /// The same margin as a percentage of the calibration range.  At most
+    /// one of the two is set.
+    #[serde(default)]
+    pub failure_margin_pct: Option<f64>,
+    /// Calibration curve replacing the linear dry/wet mapping.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub curve: Option<Curve>,
+    /// Cleared to take the sensor out of service (e.g. a corroded probe
+    /// awaiting replacement): its node stops sampling the channel and it
+    /// no longer counts toward zone moisture.
+    #[serde(default = "default_sensor_enabled")]
+    pub enabled: bool,
+}
+
+pub fn default_sensor_weight() -> f64 {
+    1.0
+}
+
+pub fn default_sensor_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeConfig {
+    pub node_id: String,
+    pub name: String,
+    /// Expected telemetry interval, as configured on the node.
+    pub sample_interval_sec: Option<i64>,
+    /// Overrides the hub-wide NODE_STALE_TIMEOUT_MIN for this node.
+    pub stale_timeout_min: Option<i64>,
+    /// Deep-sleep node: silent between wakes, so staleness is judged against
+    /// `sample_interval_sec` instead of the timeout.  See `StalePolicy`.
+    #[serde(default)]
+    pub battery_powered: bool,
+    /// Multiple of `sample_interval_sec` a battery node may stay silent
+    /// before its wake counts as missed (`None` = `DEFAULT_WAKE_GRACE_FACTOR`).
+    #[serde(default)]
+    pub wake_grace_factor: Option<f64>,
+    pub decommissioned_at: Option<i64>,
+}
+
+/// Grace factor applied to a battery node's wake interval when the node
+/// doesn't set its own.
+pub const DEFAULT_WAKE_GRACE_FACTOR: f64 = 1.5;
+
+/// When a node counts as stale.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StalePolicy {
+    /// Always-on node: stale once silent for longer than the timeout.
+    Timeout { minutes: i64 },
+    /// Battery node: expected to be silent for `interval_sec` at a time and
+    /// only flagged once a scheduled wake is missed by the grace factor.
+    Wake {
+        interval_sec: i64,
+        grace_factor: f64,
+    },
+}
+
+impl StalePolicy {
+    /// Pick the policy for a node.  Battery nodes without a known wake
+    /// interval fall back to the timeout.
+    pub fn for_node(cfg: Option<&NodeConfig>, default_timeout_min: i64) -> Self {
+        match cfg {
+            Some(NodeConfig {
+                battery_powered: true,
+                sample_interval_sec: Some(interval_sec),
+                wake_grace_factor,
+                ..
+            }) => Self::Wake {
+                interval_sec: *interval_sec,
+                grace_factor: wake_grace_factor.unwrap_or(DEFAULT_WAKE_GRACE_FACTOR),
+            },
+            _ => Self::Timeout {
+                minutes: cfg
+                    .and_then(|c| c.stale_timeout_min)
+                    .unwrap_or(default_timeout_min),
+            },
+        }
+    }
+
+    /// Silence (seconds) tolerated before the node is flagged.
+    pub fn threshold_sec(&self) -> i64 {
+        match *self {
+            Self::Timeout { minutes } => minutes * 60,
+            Self::Wake {
+                interval_sec,
+                grace_factor,
+            } => (interval_sec as f64 * grace_factor).ceil() as i64,
+        }
+    }
+}
+
+/// A time range during which a zone's readings are not trusted.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Disturbance {
+    pub id: i64,
+    pub zone_id: String,
+    pub start_ts: i64,
+    /// `None` while the disturbance is ongoing.
+    pub end_ts: Option<i64>,
+    pub reason: String,
+}
+
+/// The effective zone / sensor / node configuration at one point in time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigSnapshot {
+    /// All zones, archived ones included.
+    pub zones: Vec<ZoneConfig>,
+    /// All sensors, archived ones included.
+    pub sensors: Vec<SensorConfig>,
+    pub nodes: Vec<NodeConfig>,
+}
+
+/// Metadata of a stored configuration snapshot.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ConfigVersion {
+    pub version: i64,
+    pub created_ts: i64,
+    pub reason: String,
+}
+
+/// Outcome of restoring a configuration snapshot.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RestoreSummary {
+    /// Sensors not in the snapshot, archived rather than deleted so their
+    /// readings are kept.
+    pub archived_sensors: Vec<String>,
+    /// Zones not in the snapshot that couldn't be deleted because history
+    /// (readings, watering events, counters) still references them.
+    pub kept_zones: Vec<String>,
+}
+
+/// Length of the rolling window the daily limits are also checked over.
+pub const ROLLING_WINDOW_SEC: i64 = 86400;
+
+/// Pulses and open seconds of a zone's watering events in the
+/// [`ROLLING_WINDOW_SEC`] before now.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RollingCounters {
+    /// Events that started in the window.
+    pub pulses: i64,
+    /// Open time inside the window; an event straddling its start counts
+    /// only the part after it.
+    pub open_sec: i64,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -48,6 +394,39 @@ pub struct DailyCounters {
     pub pulses: i64,
 }
 
+/// One bucket of a water-usage report.
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageRow {
+    pub zone_id: String,
+    /// Bucket label: `YYYY-MM-DD`, `YYYY-Www` (Monday-based), or `YYYY-MM`.
+    pub period: String,
+    pub open_sec: i64,
+    pub pulses: i64,
+    /// Only present when the zone has a measured `flow_lpm` or an
+    /// `emitter_flow_lph` estimate.
+    pub litres: Option<f64>,
+}
+
+/// Granularity of a usage report.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum UsageBucket {
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+impl UsageBucket {
+    fn strftime_format(self) -> &'static str {
+        match self {
+            Self::Day => "%Y-%m-%d",
+            Self::Week => "%Y-W%W",
+            Self::Month => "%Y-%m",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, sqlx::FromRow)]
 pub struct ReadingRow {
     pub ts: i64,
@@ -56,6 +435,61 @@ pub struct ReadingRow {
     pub moisture: f64,
 }
 
+/// A node's air temperature, humidity and light sample (see
+/// `climate_readings`).
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct ClimateReading {
+    pub ts: i64,
+    pub air_temp_c: Option<f64>,
+    pub humidity_pct: Option<f64>,
+    pub lux: Option<f64>,
+}
+
+/// A sensor's moisture over one hour or day (see `sensor_moisture_trend`).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MoistureBucket {
+    /// Start of the bucket, unix seconds.
+    pub ts: i64,
+    pub samples: i64,
+    pub moisture_min: f64,
+    pub moisture_avg: f64,
+    pub moisture_max: f64,
+}
+
+/// Implausible-reading history for one sensor.
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct SensorHealth {
+    pub sensor_id: String,
+    pub consecutive_failures: i64,
+    pub total_failures: i64,
+    pub last_failure_ts: Option<i64>,
+    /// Set while the sensor is quarantined (left out of zone moisture).
+    pub quarantined_ts: Option<i64>,
+}
+
+/// Lifetime valve usage for one zone.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, sqlx::FromRow)]
+pub struct ZoneOdometer {
+    pub actuations: i64,
+    pub open_sec: i64,
+    pub actuations_since_service: i64,
+    pub open_sec_since_service: i64,
+    pub serviced_ts: Option<i64>,
+    /// Set once usage since the last service crossed the configured
+    /// threshold.
+    pub service_due_ts: Option<i64>,
+}
+
+/// A zone's persisted scheduler phase (see `scheduler_zone_state`).
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct SchedulerZoneState {
+    pub zone_id: String,
+    pub phase: String,
+    pub started_ts: i64,
+    pub until_ts: i64,
+    pub extended_sec: i64,
+}
+
 #[derive(Debug, Clone, Serialize, sqlx::FromRow)]
 pub struct WateringEventRow {
     pub ts_start: i64,
@@ -63,6 +497,84 @@ pub struct WateringEventRow {
     pub zone_id: String,
     pub reason: String,
     pub result: String,
+    /// How long the valve was meant to stay open.
+    pub planned_sec: Option<i64>,
+    /// How long it was open (`ts_end - ts_start`).
+    pub duration_sec: i64,
+    /// `scheduler`, `manual` or `mqtt`.
+    pub triggered_by: Option<String>,
+    /// `off_command`, `watchdog`, `emergency`, `hub_restart` or `restore`.
+    pub ended_by: Option<String>,
+    /// `planned_sec` at the zone's configured `flow_lpm`.
+    pub expected_litres: Option<f64>,
+    /// From the zone's flow-meter readings while the valve was open.
+    pub litres: Option<f64>,
+    #[serde(skip)]
+    pub flow_lpm: Option<f64>,
+    /// See [`WateringEventRow::find_anomalies`].
+    #[sqlx(skip)]
+    pub anomalies: Vec<&'static str>,
+}
+
+/// Slack between planned and actual open time before an event is flagged
+/// (command latency, whole-second timestamps).
+const DURATION_TOLERANCE_SEC: i64 = 5;
+
+/// Measured volume this far off the configured flow is flagged.
+const VOLUME_TOLERANCE: f64 = 0.25;
+
+impl WateringEventRow {
+    /// What stands out about the event: `short` or `overran` against its
+    /// planned duration, `forced` when something other than an `OFF`
+    /// command closed it, and `low_flow` or `high_flow` when the metered
+    /// volume is off the zone's `flow_lpm` for the time it was open.
+    pub fn find_anomalies(&self) -> Vec<&'static str> {
+        let mut found = Vec::new();
+        if let Some(planned) = self.planned_sec {
+            if self.duration_sec + DURATION_TOLERANCE_SEC < planned {
+                found.push("short");
+            } else if self.duration_sec > planned + DURATION_TOLERANCE_SEC {
+                found.push("overran");
+            }
+        }
+        if self.ended_by.as_deref().is_some_and(|e| e != "off_command") {
+            found.push("forced");
+        }
+        if let (Some(litres), Some(lpm)) = (self.litres, self.flow_lpm) {
+            let expected = lpm * self.duration_sec as f64 / 60.0;
+            if expected > 0.0 {
+                if litres < expected * (1.0 - VOLUME_TOLERANCE) {
+                    found.push("low_flow");
+                } else if litres > expected * (1.0 + VOLUME_TOLERANCE) {
+                    found.push("high_flow");
+                }
+            }
+        }
+        found
+    }
+}
+
+/// What a watering event was meant to do and what ended it, beyond its
+/// reason and result.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EventDetails<'a> {
+    pub planned_sec: Option<i64>,
+    pub triggered_by: Option<&'a str>,
+    pub ended_by: Option<&'a str>,
+}
+
+/// One scheduler evaluation from the `scheduler_decisions` audit log.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct SchedulerDecision {
+    pub ts: i64,
+    pub zone_id: String,
+    /// Zone state when evaluated: `idle`, `watering` or `soaking`.
+    pub phase: String,
+    pub avg_moisture: Option<f64>,
+    /// Guard that stopped the evaluation, if any.
+    pub blocked_by: Option<String>,
+    pub action: String,
+    pub detail: String,
 }
 
 /// Convert a raw ADC reading to a 0.0..=1.0 moisture fraction using
@@ -77,20 +589,334 @@ pub fn compute_moisture(raw: i64, raw_dry: i64, raw_wet: i64) -> f32 {
     m.clamp(0.0, 1.0) as f32
 }
 
-/// Margin beyond calibration endpoints that indicates a likely sensor failure.
-/// A disconnected ADS1115 input reads ~32767; a shorted input reads ~0.
-const SENSOR_FAILURE_MARGIN: i64 = 3000;
+/// Highest ADS1115 single-ended input (AIN0–AIN3).
+pub const ADS1115_MAX_CHANNEL: i64 = 3;
+
+impl SensorConfig {
+    /// Plausibility margin in raw counts (see [`is_reading_plausible`]).
+    pub fn failure_margin_counts(&self) -> i64 {
+        match (self.failure_margin, self.failure_margin_pct) {
+            (Some(counts), _) => counts,
+            (None, Some(pct)) => {
+                ((self.raw_dry - self.raw_wet).abs() as f64 * pct / 100.0).round() as i64
+            }
+            (None, None) => SENSOR_FAILURE_MARGIN,
+        }
+    }
+
+    /// Moisture fraction for `raw`, on the sensor's curve if it has one.
+    pub fn moisture(&self, raw: i64) -> f32 {
+        match &self.curve {
+            Some(curve) => curve.moisture(raw, self.raw_dry, self.raw_wet),
+            None => compute_moisture(raw, self.raw_dry, self.raw_wet),
+        }
+    }
+
+    /// Whether `raw` is plausible for this sensor's calibration and margin.
+    pub fn is_plausible(&self, raw: i64) -> bool {
+        is_reading_plausible(
+            raw,
+            self.raw_dry,
+            self.raw_wet,
+            self.failure_margin_counts(),
+        )
+    }
+
+    /// Sensor id as the node reports it (`"node-a/s1"` → `"s1"`).
+    pub fn local_id(&self) -> &str {
+        self.sensor_id
+            .strip_prefix(self.node_id.as_str())
+            .and_then(|rest| rest.strip_prefix('/'))
+            .unwrap_or(&self.sensor_id)
+    }
+
+    /// The configured channel, or the node's default mapping for the local
+    /// id (`"s1"` → 0, `"s2"` → 1, …).  `None` if neither applies.
+    pub fn channel_or_default(&self) -> Option<i64> {
+        self.channel.or_else(|| {
+            self.local_id()
+                .strip_prefix('s')
+                .and_then(|n| n.parse::<i64>().ok())
+                .map(|n| n - 1)
+                .filter(|ch| (0..=ADS1115_MAX_CHANNEL).contains(ch))
+        })
+    }
+}
+
+/// Readings rewritten per transaction by `Db::recompute_moisture`.
+const RECOMPUTE_BATCH: i64 = 500;
+
+/// Default margin beyond calibration endpoints that indicates a likely sensor
+/// failure.  A disconnected ADS1115 input reads ~32767; a shorted input reads
+/// ~0.
+pub const SENSOR_FAILURE_MARGIN: i64 = 3000;
 
 /// Returns `true` if the raw ADC value is plausibly within calibration range.
-/// Values far outside the dry/wet endpoints suggest a disconnected, shorted,
-/// or otherwise failed sensor.
-pub fn is_reading_plausible(raw: i64, raw_dry: i64, raw_wet: i64) -> bool {
+/// Values more than `margin` counts outside the dry/wet endpoints suggest a
+/// disconnected, shorted, or otherwise failed sensor.
+pub fn is_reading_plausible(raw: i64, raw_dry: i64, raw_wet: i64, margin: i64) -> bool {
     let (lo, hi) = if raw_wet < raw_dry {
         (raw_wet, raw_dry)
     } else {
         (raw_dry, raw_wet)
     };
-    raw >= lo - SENSOR_FAILURE_MARGIN && raw <= hi + SENSOR_FAILURE_MARGIN
+    raw >= lo - margin && raw <= hi + margin
+}
+
+/// Serialize a zone strategy for the `zones.strategy` column.  The default
+/// threshold strategy is stored as NULL so existing rows stay untouched.
+fn strategy_to_db(s: &StrategyConfig) -> Option<String> {
+    match s {
+        StrategyConfig::Threshold => None,
+        other => serde_json::to_string(other).ok(),
+    }
+}
+
+/// Parse `zones.strategy`, falling back to the threshold strategy (with a
+/// warning) if the stored JSON is unreadable.
+fn strategy_from_db(zone_id: &str, raw: Option<&str>) -> StrategyConfig {
+    let Some(raw) = raw else {
+        return StrategyConfig::default();
+    };
+    serde_json::from_str(raw).unwrap_or_else(|e| {
+        tracing::warn!(zone_id, error = %e, "invalid stored strategy; using threshold");
+        StrategyConfig::default()
+    })
+}
+
+/// Serialize a sensor's calibration curve for the `sensors.curve` column.
+fn curve_to_db(c: Option<&Curve>) -> Option<String> {
+    c.and_then(|c| serde_json::to_string(c).ok())
+}
+
+/// Parse `sensors.curve`, falling back to the linear mapping (with a
+/// warning) if the stored JSON is unreadable.
+fn curve_from_db(sensor_id: &str, raw: Option<&str>) -> Option<Curve> {
+    serde_json::from_str(raw?)
+        .inspect_err(
+            |e| tracing::warn!(sensor_id, error = %e, "invalid stored curve; using linear"),
+        )
+        .ok()
+}
+
+/// Serialize a zone's valve type for the `zones.valve` column (solenoid = NULL).
+fn valve_to_db(v: &ValveConfig) -> Option<String> {
+    match v {
+        ValveConfig::Solenoid => None,
+        other => serde_json::to_string(other).ok(),
+    }
+}
+
+/// Parse `zones.valve`, falling back to a solenoid (with a warning) if the
+/// stored JSON is unreadable.
+fn valve_from_db(zone_id: &str, raw: Option<&str>) -> ValveConfig {
+    let Some(raw) = raw else {
+        return ValveConfig::default();
+    };
+    serde_json::from_str(raw).unwrap_or_else(|e| {
+        tracing::warn!(zone_id, error = %e, "invalid stored valve type; using solenoid");
+        ValveConfig::default()
+    })
+}
+
+/// Serialize a zone's dependencies for the `zones.after` column (none = NULL).
+fn after_to_db(after: &[String]) -> Option<String> {
+    if after.is_empty() {
+        None
+    } else {
+        serde_json::to_string(after).ok()
+    }
+}
+
+/// Parse `zones.after`, falling back to no dependencies (with a warning) if
+/// the stored JSON is unreadable.
+fn after_from_db(zone_id: &str, raw: Option<&str>) -> Vec<String> {
+    let Some(raw) = raw else {
+        return Vec::new();
+    };
+    serde_json::from_str(raw).unwrap_or_else(|e| {
+        tracing::warn!(zone_id, error = %e, "invalid stored zone dependencies; ignoring");
+        Vec::new()
+    })
+}
+
+/// Parse `zones.aggregation`, falling back to the mean (with a warning) if
+/// the stored name is unknown.
+fn aggregation_from_db(zone_id: &str, raw: Option<&str>) -> Aggregation {
+    let Some(raw) = raw else {
+        return Aggregation::default();
+    };
+    Aggregation::parse(raw).unwrap_or_else(|| {
+        tracing::warn!(
+            zone_id,
+            aggregation = raw,
+            "invalid stored aggregation; using mean"
+        );
+        Aggregation::default()
+    })
+}
+
+async fn insert_reading_with<'e, E>(exec: E, r: &PendingReading) -> Result<bool, sqlx::Error>
+where
+    E: sqlx::Executor<'e, Database = Sqlite>,
+{
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO readings (ts, sensor_id, raw, moisture)
+        VALUES (?, ?, ?, ?)
+        ON CONFLICT(ts, sensor_id) DO NOTHING
+        "#,
+        r.ts,
+        r.sensor_id,
+        r.raw,
+        r.moisture
+    )
+    .execute(exec)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// ---------------------------------------------------------------------------
+// Config upserts shared by the API paths and config rollback
+// ---------------------------------------------------------------------------
+
+async fn upsert_zone_with<'e, E>(exec: E, z: &ZoneConfig) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = Sqlite>,
+{
+    let min_m = z.min_moisture as f64;
+    let target_m = z.target_moisture as f64;
+    let flow_lpm = z.flow_lpm.map(|v| v as f64);
+    let strategy = strategy_to_db(&z.strategy);
+    let valve = valve_to_db(&z.valve);
+    let after = after_to_db(&z.after);
+    let aggregation = (z.aggregation != Aggregation::Mean).then(|| z.aggregation.as_str());
+    let emitter_flow_lph = z.emitter_flow_lph.map(|v| v as f64);
+    sqlx::query!(
+        r#"
+        INSERT INTO zones (
+          zone_id, name,
+          min_moisture, target_moisture,
+          pulse_sec, soak_min,
+          max_open_sec_per_day, max_pulses_per_day, stale_timeout_min,
+          valve_gpio_pin, flow_lpm, strategy, priority, valve, after, aggregation,
+          notes, location, plant_type, emitter_flow_lph
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(zone_id) DO UPDATE SET
+          name=excluded.name,
+          min_moisture=excluded.min_moisture,
+          target_moisture=excluded.target_moisture,
+          pulse_sec=excluded.pulse_sec,
+          soak_min=excluded.soak_min,
+          max_open_sec_per_day=excluded.max_open_sec_per_day,
+          max_pulses_per_day=excluded.max_pulses_per_day,
+          stale_timeout_min=excluded.stale_timeout_min,
+          valve_gpio_pin=excluded.valve_gpio_pin,
+          flow_lpm=excluded.flow_lpm,
+          strategy=excluded.strategy,
+          priority=excluded.priority,
+          valve=excluded.valve,
+          after=excluded.after,
+          aggregation=excluded.aggregation,
+          notes=excluded.notes,
+          location=excluded.location,
+          plant_type=excluded.plant_type,
+          emitter_flow_lph=excluded.emitter_flow_lph
+        "#,
+        z.zone_id,
+        z.name,
+        min_m,
+        target_m,
+        z.pulse_sec,
+        z.soak_min,
+        z.max_open_sec_per_day,
+        z.max_pulses_per_day,
+        z.stale_timeout_min,
+        z.valve_gpio_pin,
+        flow_lpm,
+        strategy,
+        z.priority,
+        valve,
+        after,
+        aggregation,
+        z.notes,
+        z.location,
+        z.plant_type,
+        emitter_flow_lph
+    )
+    .execute(exec)
+    .await
+    .context("upsert_zone failed")?;
+    Ok(())
+}
+
+async fn upsert_sensor_with<'e, E>(exec: E, s: &SensorConfig) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = Sqlite>,
+{
+    let curve = curve_to_db(s.curve.as_ref());
+    sqlx::query!(
+        r#"
+        INSERT INTO sensors (sensor_id, node_id, zone_id, raw_dry, raw_wet, channel, weight,
+                             failure_margin, failure_margin_pct, curve, enabled)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(sensor_id) DO UPDATE SET
+          node_id=excluded.node_id,
+          zone_id=excluded.zone_id,
+          raw_dry=excluded.raw_dry,
+          raw_wet=excluded.raw_wet,
+          channel=excluded.channel,
+          weight=excluded.weight,
+          failure_margin=excluded.failure_margin,
+          failure_margin_pct=excluded.failure_margin_pct,
+          curve=excluded.curve,
+          enabled=excluded.enabled
+        "#,
+        s.sensor_id,
+        s.node_id,
+        s.zone_id,
+        s.raw_dry,
+        s.raw_wet,
+        s.channel,
+        s.weight,
+        s.failure_margin,
+        s.failure_margin_pct,
+        curve,
+        s.enabled
+    )
+    .execute(exec)
+    .await
+    .context("upsert_sensor failed")?;
+    Ok(())
+}
+
+async fn upsert_node_with<'e, E>(exec: E, n: &NodeConfig) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = Sqlite>,
+{
+    sqlx::query!(
+        r#"
+        INSERT INTO nodes (node_id, name, sample_interval_sec, stale_timeout_min,
+                           battery_powered, wake_grace_factor)
+        VALUES (?, ?, ?, ?, ?, ?)
+        ON CONFLICT(node_id) DO UPDATE SET
+          name=excluded.name,
+          sample_interval_sec=excluded.sample_interval_sec,
+          stale_timeout_min=excluded.stale_timeout_min,
+          battery_powered=excluded.battery_powered,
+          wake_grace_factor=excluded.wake_grace_factor
+        "#,
+        n.node_id,
+        n.name,
+        n.sample_interval_sec,
+        n.stale_timeout_min,
+        n.battery_powered,
+        n.wake_grace_factor
+    )
+    .execute(exec)
+    .await
+    .context("upsert_node failed")?;
+    Ok(())
 }
 
 impl Db {
@@ -98,10 +924,16 @@ impl Db {
     /// - "sqlite:/home/pi/irrigation/irrigation.db"
     /// - "sqlite::memory:" (tests)
     pub async fn connect(db_url: &str) -> Result<Self> {
+        Self::connect_with(db_url, DEFAULT_BUSY_TIMEOUT).await
+    }
+
+    /// `connect` with a busy timeout other than [`DEFAULT_BUSY_TIMEOUT`].
+    pub async fn connect_with(db_url: &str, busy_timeout: Duration) -> Result<Self> {
         let options = SqliteConnectOptions::from_str(db_url)
             .with_context(|| format!("invalid sqlite connection string: {db_url}"))?
             .journal_mode(SqliteJournalMode::Wal)
             .synchronous(SqliteSynchronous::Normal)
+            .busy_timeout(busy_timeout)
             .foreign_keys(true);
 
         let pool = SqlitePoolOptions::new()
@@ -110,7 +942,60 @@ impl Db {
             .await
             .with_context(|| format!("failed to connect to sqlite db: {db_url}"))?;
 
-        Ok(Self { pool })
+        Ok(Self {
+            pool,
+            readings: Arc::new(Mutex::new(ReadingBuffer::default())),
+            batch_rows: 0,
+            retried: Arc::default(),
+            duplicates: Arc::default(),
+        })
+    }
+
+    /// Run `op`, and run it again after a short pause (see
+    /// [`RETRY_BACKOFF`]) while it fails with the database busy or locked.
+    async fn retry<T, F, Fut>(&self, what: &str, mut op: F) -> Result<T, sqlx::Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, sqlx::Error>>,
+    {
+        let mut backoff = RETRY_BACKOFF.iter();
+        loop {
+            match op().await {
+                Err(e) if is_transient(&e) => {
+                    let Some(pause) = backoff.next() else {
+                        return Err(e);
+                    };
+                    self.retried.fetch_add(1, Ordering::Relaxed);
+                    tracing::debug!("{what}: {e}; retrying in {pause:?}");
+                    tokio::time::sleep(*pause).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Statements retried since startup after the database was busy,
+    /// exported at `GET /metrics`.
+    pub fn retried_statements(&self) -> u64 {
+        self.retried.load(Ordering::Relaxed)
+    }
+
+    /// Readings dropped since startup as duplicates of a `(ts, sensor_id)`
+    /// already queued or stored, exported at `GET /metrics`.
+    pub fn duplicate_readings(&self) -> u64 {
+        self.duplicates.load(Ordering::Relaxed)
+    }
+
+    fn count_duplicates(&self, n: u64) {
+        self.duplicates.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Buffer readings passed to `queue_reading` and write them in batches
+    /// of up to `max_rows` (0 = write each one straight away).  The caller
+    /// also flushes on a timer with `flush_readings`.
+    pub fn with_reading_batch(mut self, max_rows: usize) -> Self {
+        self.batch_rows = max_rows;
+        self
     }
 
     /// Ensures the database uses `auto_vacuum = INCREMENTAL`, which is
@@ -169,56 +1054,33 @@ impl Db {
     // ----------------------------
 
     pub async fn upsert_zone(&self, z: &ZoneConfig) -> Result<()> {
-        let min_m = z.min_moisture as f64;
-        let target_m = z.target_moisture as f64;
-        sqlx::query!(
-            r#"
-            INSERT INTO zones (
-              zone_id, name,
-              min_moisture, target_moisture,
-              pulse_sec, soak_min,
-              max_open_sec_per_day, max_pulses_per_day, stale_timeout_min,
-              valve_gpio_pin
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-            ON CONFLICT(zone_id) DO UPDATE SET
-              name=excluded.name,
-              min_moisture=excluded.min_moisture,
-              target_moisture=excluded.target_moisture,
-              pulse_sec=excluded.pulse_sec,
-              soak_min=excluded.soak_min,
-              max_open_sec_per_day=excluded.max_open_sec_per_day,
-              max_pulses_per_day=excluded.max_pulses_per_day,
-              stale_timeout_min=excluded.stale_timeout_min,
-              valve_gpio_pin=excluded.valve_gpio_pin
-            "#,
-            z.zone_id,
-            z.name,
-            min_m,
-            target_m,
-            z.pulse_sec,
-            z.soak_min,
-            z.max_open_sec_per_day,
-            z.max_pulses_per_day,
-            z.stale_timeout_min,
-            z.valve_gpio_pin
-        )
-        .execute(&self.pool)
-        .await
-        .context("upsert_zone failed")?;
-        Ok(())
+        upsert_zone_with(&self.pool, z).await
     }
 
+    /// Active zones (archived ones are left out).
     pub async fn load_zones(&self) -> Result<Vec<ZoneConfig>> {
+        self.query_zones(false).await
+    }
+
+    /// All zones, archived ones included.
+    pub async fn load_all_zones(&self) -> Result<Vec<ZoneConfig>> {
+        self.query_zones(true).await
+    }
+
+    async fn query_zones(&self, include_archived: bool) -> Result<Vec<ZoneConfig>> {
         let rows = sqlx::query!(
             r#"
             SELECT zone_id as "zone_id!", name,
                    min_moisture, target_moisture,
                    pulse_sec, soak_min,
                    max_open_sec_per_day, max_pulses_per_day, stale_timeout_min,
-                   valve_gpio_pin
+                   valve_gpio_pin, flow_lpm, strategy, priority, valve, after, aggregation,
+                   notes, location, plant_type, emitter_flow_lph, archived_at
             FROM zones
+            WHERE ? OR archived_at IS NULL
             ORDER BY zone_id
-            "#
+            "#,
+            include_archived
         )
         .fetch_all(&self.pool)
         .await
@@ -226,21 +1088,39 @@ impl Db {
 
         Ok(rows
             .into_iter()
-            .map(|r| ZoneConfig {
-                zone_id: r.zone_id,
-                name: r.name,
-                min_moisture: r.min_moisture as f32,
-                target_moisture: r.target_moisture as f32,
-                pulse_sec: r.pulse_sec,
-                soak_min: r.soak_min,
-                max_open_sec_per_day: r.max_open_sec_per_day,
-                max_pulses_per_day: r.max_pulses_per_day,
-                stale_timeout_min: r.stale_timeout_min,
-                valve_gpio_pin: r.valve_gpio_pin,
+            .map(|r| {
+                let strategy = strategy_from_db(&r.zone_id, r.strategy.as_deref());
+                let valve = valve_from_db(&r.zone_id, r.valve.as_deref());
+                let after = after_from_db(&r.zone_id, r.after.as_deref());
+                let aggregation = aggregation_from_db(&r.zone_id, r.aggregation.as_deref());
+                ZoneConfig {
+                    zone_id: r.zone_id,
+                    name: r.name,
+                    min_moisture: r.min_moisture as f32,
+                    target_moisture: r.target_moisture as f32,
+                    pulse_sec: r.pulse_sec,
+                    soak_min: r.soak_min,
+                    max_open_sec_per_day: r.max_open_sec_per_day,
+                    max_pulses_per_day: r.max_pulses_per_day,
+                    stale_timeout_min: r.stale_timeout_min,
+                    valve_gpio_pin: r.valve_gpio_pin,
+                    flow_lpm: r.flow_lpm.map(|v| v as f32),
+                    strategy,
+                    priority: r.priority,
+                    valve,
+                    after,
+                    aggregation,
+                    notes: r.notes,
+                    location: r.location,
+                    plant_type: r.plant_type,
+                    emitter_flow_lph: r.emitter_flow_lph.map(|v| v as f32),
+                    archived_at: r.archived_at,
+                }
             })
             .collect())
     }
 
+    /// A zone by id, archived or not.
     pub async fn get_zone(&self, zone_id: &str) -> Result<Option<ZoneConfig>> {
         let r = sqlx::query!(
             r#"
@@ -248,7 +1128,8 @@ impl Db {
                    min_moisture, target_moisture,
                    pulse_sec, soak_min,
                    max_open_sec_per_day, max_pulses_per_day, stale_timeout_min,
-                   valve_gpio_pin
+                   valve_gpio_pin, flow_lpm, strategy, priority, valve, after, aggregation,
+                   notes, location, plant_type, emitter_flow_lph, archived_at
             FROM zones
             WHERE zone_id = ?
             "#,
@@ -258,20 +1139,51 @@ impl Db {
         .await
         .context("get_zone failed")?;
 
-        Ok(r.map(|r| ZoneConfig {
-            zone_id: r.zone_id,
-            name: r.name,
-            min_moisture: r.min_moisture as f32,
-            target_moisture: r.target_moisture as f32,
-            pulse_sec: r.pulse_sec,
-            soak_min: r.soak_min,
-            max_open_sec_per_day: r.max_open_sec_per_day,
-            max_pulses_per_day: r.max_pulses_per_day,
-            stale_timeout_min: r.stale_timeout_min,
-            valve_gpio_pin: r.valve_gpio_pin,
+        Ok(r.map(|r| {
+            let strategy = strategy_from_db(&r.zone_id, r.strategy.as_deref());
+            let valve = valve_from_db(&r.zone_id, r.valve.as_deref());
+            let after = after_from_db(&r.zone_id, r.after.as_deref());
+            let aggregation = aggregation_from_db(&r.zone_id, r.aggregation.as_deref());
+            ZoneConfig {
+                zone_id: r.zone_id,
+                name: r.name,
+                min_moisture: r.min_moisture as f32,
+                target_moisture: r.target_moisture as f32,
+                pulse_sec: r.pulse_sec,
+                soak_min: r.soak_min,
+                max_open_sec_per_day: r.max_open_sec_per_day,
+                max_pulses_per_day: r.max_pulses_per_day,
+                stale_timeout_min: r.stale_timeout_min,
+                valve_gpio_pin: r.valve_gpio_pin,
+                flow_lpm: r.flow_lpm.map(|v| v as f32),
+                strategy,
+                priority: r.priority,
+                valve,
+                after,
+                aggregation,
+                notes: r.notes,
+                location: r.location,
+                plant_type: r.plant_type,
+                emitter_flow_lph: r.emitter_flow_lph.map(|v| v as f32),
+                archived_at: r.archived_at,
+            }
         }))
     }
 
+    /// Archive a zone (`Some(ts)`) or bring it back (`None`).  Returns
+    /// whether the zone exists.
+    pub async fn set_zone_archived(&self, zone_id: &str, archived_at: Option<i64>) -> Result<bool> {
+        let result = sqlx::query!(
+            "UPDATE zones SET archived_at = ? WHERE zone_id = ?",
+            archived_at,
+            zone_id
+        )
+        .execute(&self.pool)
+        .await
+        .context("set_zone_archived failed")?;
+        Ok(result.rows_affected() > 0)
+    }
+
     pub async fn delete_zone(&self, zone_id: &str) -> Result<bool> {
         let result = sqlx::query!("DELETE FROM zones WHERE zone_id = ?", zone_id)
             .execute(&self.pool)
@@ -285,33 +1197,17 @@ impl Db {
     // ----------------------------
 
     pub async fn upsert_sensor(&self, s: &SensorConfig) -> Result<()> {
-        sqlx::query!(
-            r#"
-            INSERT INTO sensors (sensor_id, node_id, zone_id, raw_dry, raw_wet)
-            VALUES (?, ?, ?, ?, ?)
-            ON CONFLICT(sensor_id) DO UPDATE SET
-              node_id=excluded.node_id,
-              zone_id=excluded.zone_id,
-              raw_dry=excluded.raw_dry,
-              raw_wet=excluded.raw_wet
-            "#,
-            s.sensor_id,
-            s.node_id,
-            s.zone_id,
-            s.raw_dry,
-            s.raw_wet
-        )
-        .execute(&self.pool)
-        .await
-        .context("upsert_sensor failed")?;
-        Ok(())
+        upsert_sensor_with(&self.pool, s).await
     }
 
     pub async fn load_sensors(&self) -> Result<Vec<SensorConfig>> {
         let rows = sqlx::query!(
             r#"
-            SELECT sensor_id as "sensor_id!", node_id, zone_id, raw_dry, raw_wet
+            SELECT sensor_id as "sensor_id!", node_id, zone_id, raw_dry, raw_wet, channel,
+                   archived_at, weight, failure_margin, failure_margin_pct, curve,
+                   enabled as "enabled: bool"
             FROM sensors
+            WHERE archived_at IS NULL
             ORDER BY sensor_id
             "#
         )
@@ -322,23 +1218,29 @@ impl Db {
         Ok(rows
             .into_iter()
             .map(|r| SensorConfig {
+                curve: curve_from_db(&r.sensor_id, r.curve.as_deref()),
                 sensor_id: r.sensor_id,
                 node_id: r.node_id,
                 zone_id: r.zone_id,
                 raw_dry: r.raw_dry,
                 raw_wet: r.raw_wet,
+                channel: r.channel,
+                archived_at: r.archived_at,
+                weight: r.weight,
+                failure_margin: r.failure_margin,
+                failure_margin_pct: r.failure_margin_pct,
+                enabled: r.enabled,
             })
             .collect())
     }
 
-    /// List sensors assigned to a specific node.  Not yet called from
-    /// production code — kept for the planned per-node diagnostics API
-    /// endpoint and used in tests.
-    #[allow(dead_code)]
+    /// List sensors assigned to a specific node, including archived ones.
     pub async fn sensors_for_node(&self, node_id: &str) -> Result<Vec<SensorConfig>> {
         let rows = sqlx::query!(
             r#"
-            SELECT sensor_id as "sensor_id!", node_id, zone_id, raw_dry, raw_wet
+            SELECT sensor_id as "sensor_id!", node_id, zone_id, raw_dry, raw_wet, channel,
+                   archived_at, weight, failure_margin, failure_margin_pct, curve,
+                   enabled as "enabled: bool"
             FROM sensors
             WHERE node_id = ?
             ORDER BY sensor_id
@@ -352,11 +1254,18 @@ impl Db {
         Ok(rows
             .into_iter()
             .map(|r| SensorConfig {
+                curve: curve_from_db(&r.sensor_id, r.curve.as_deref()),
                 sensor_id: r.sensor_id,
                 node_id: r.node_id,
                 zone_id: r.zone_id,
                 raw_dry: r.raw_dry,
                 raw_wet: r.raw_wet,
+                channel: r.channel,
+                archived_at: r.archived_at,
+                weight: r.weight,
+                failure_margin: r.failure_margin,
+                failure_margin_pct: r.failure_margin_pct,
+                enabled: r.enabled,
             })
             .collect())
     }
@@ -364,7 +1273,9 @@ impl Db {
     pub async fn get_sensor(&self, sensor_id: &str) -> Result<Option<SensorConfig>> {
         let r = sqlx::query!(
             r#"
-            SELECT sensor_id as "sensor_id!", node_id, zone_id, raw_dry, raw_wet
+            SELECT sensor_id as "sensor_id!", node_id, zone_id, raw_dry, raw_wet, channel,
+                   archived_at, weight, failure_margin, failure_margin_pct, curve,
+                   enabled as "enabled: bool"
             FROM sensors
             WHERE sensor_id = ?
             "#,
@@ -375,11 +1286,18 @@ impl Db {
         .context("get_sensor failed")?;
 
         Ok(r.map(|r| SensorConfig {
+            curve: curve_from_db(&r.sensor_id, r.curve.as_deref()),
             sensor_id: r.sensor_id,
             node_id: r.node_id,
             zone_id: r.zone_id,
             raw_dry: r.raw_dry,
             raw_wet: r.raw_wet,
+            channel: r.channel,
+            archived_at: r.archived_at,
+            weight: r.weight,
+            failure_margin: r.failure_margin,
+            failure_margin_pct: r.failure_margin_pct,
+            enabled: r.enabled,
         }))
     }
 
@@ -392,519 +1310,4197 @@ impl Db {
     }
 
     // ----------------------------
-    // Readings + aggregation helpers
+    // Node administration
     // ----------------------------
 
-    pub async fn insert_reading(
-        &self,
-        ts: i64,
-        sensor_id: &str,
-        raw: i64,
-        moisture: f32,
-    ) -> Result<()> {
-        let moisture_f64 = moisture as f64;
-        sqlx::query!(
+    /// Insert or update a node's metadata.  `decommissioned_at` is owned by
+    /// `decommission_node` and is left untouched here.
+    pub async fn upsert_node(&self, n: &NodeConfig) -> Result<()> {
+        upsert_node_with(&self.pool, n).await
+    }
+
+    pub async fn load_nodes(&self) -> Result<Vec<NodeConfig>> {
+        let rows = sqlx::query_as!(
+            NodeConfig,
             r#"
-            INSERT INTO readings (ts, sensor_id, raw, moisture)
-            VALUES (?, ?, ?, ?)
-            "#,
-            ts,
-            sensor_id,
-            raw,
-            moisture_f64
+            SELECT node_id as "node_id!", name, sample_interval_sec,
+                   stale_timeout_min, battery_powered as "battery_powered: bool",
+                   wake_grace_factor, decommissioned_at
+            FROM nodes
+            ORDER BY node_id
+            "#
         )
-        .execute(&self.pool)
+        .fetch_all(&self.pool)
         .await
-        .context("insert_reading failed")?;
-        Ok(())
+        .context("load_nodes failed")?;
+        Ok(rows)
     }
 
-    /// Returns the newest moisture reading for a given zone across its sensors.
-    /// (V1 simple approach: max(ts) across zone’s sensors)
-    pub async fn latest_zone_moisture(&self, zone_id: &str) -> Result<Option<(i64, f32)>> {
-        let row = sqlx::query!(
+    pub async fn get_node(&self, node_id: &str) -> Result<Option<NodeConfig>> {
+        let row = sqlx::query_as!(
+            NodeConfig,
             r#"
-            SELECT r.ts as ts, r.moisture as moisture
-            FROM readings r
-            JOIN sensors s ON s.sensor_id = r.sensor_id
-            WHERE s.zone_id = ?
-            ORDER BY r.ts DESC
-            LIMIT 1
+            SELECT node_id as "node_id!", name, sample_interval_sec,
+                   stale_timeout_min, battery_powered as "battery_powered: bool",
+                   wake_grace_factor, decommissioned_at
+            FROM nodes
+            WHERE node_id = ?
             "#,
-            zone_id
+            node_id
         )
         .fetch_optional(&self.pool)
         .await
-        .context("latest_zone_moisture failed")?;
-
-        Ok(row.map(|r| (r.ts, r.moisture as f32)))
+        .context("get_node failed")?;
+        Ok(row)
     }
 
-    /// Returns a (simple) average moisture over the last N readings for a zone.
-    pub async fn avg_zone_moisture_last_n(&self, zone_id: &str, n: i64) -> Result<Option<f32>> {
-        let row = sqlx::query!(
+    /// Mark a node decommissioned and archive its active sensors in one
+    /// transaction.  Creates the node row (named after its id) if the node
+    /// was only known through its sensors.  Returns the number of sensors
+    /// archived.
+    pub async fn decommission_node(&self, node_id: &str, ts: i64) -> Result<u64> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("decommission_node: begin failed")?;
+
+        sqlx::query!(
             r#"
-            SELECT AVG(r.moisture) as avg_m
-            FROM (
-              SELECT r.moisture
-              FROM readings r
-              JOIN sensors s ON s.sensor_id = r.sensor_id
-              WHERE s.zone_id = ?
-              ORDER BY r.ts DESC
-              LIMIT ?
-            ) r
+            INSERT INTO nodes (node_id, name, decommissioned_at)
+            VALUES (?, ?, ?)
+            ON CONFLICT(node_id) DO UPDATE SET
+              decommissioned_at=excluded.decommissioned_at
             "#,
-            zone_id,
-            n
+            node_id,
+            node_id,
+            ts
         )
-        .fetch_one(&self.pool)
+        .execute(&mut *tx)
+        .await
+        .context("decommission_node: node update failed")?;
+
+        let archived = sqlx::query!(
+            "UPDATE sensors SET archived_at = ? WHERE node_id = ? AND archived_at IS NULL",
+            ts,
+            node_id
+        )
+        .execute(&mut *tx)
         .await
-        .context("avg_zone_moisture_last_n failed")?;
+        .context("decommission_node: sensor archive failed")?;
 
-        Ok(row.avg_m.map(|v| v as f32))
+        tx.commit()
+            .await
+            .context("decommission_node: commit failed")?;
+        Ok(archived.rows_affected())
     }
 
-    pub async fn list_readings(
+    // ----------------------------
+    // Config versions
+    // ----------------------------
+
+    /// Current zones and sensors (archived included) and nodes.
+    pub async fn config_snapshot(&self) -> Result<ConfigSnapshot> {
+        let zones = self.load_all_zones().await?;
+        let sensors = sqlx::query!(
+            r#"
+            SELECT sensor_id as "sensor_id!", node_id, zone_id, raw_dry, raw_wet, channel,
+                   archived_at, weight, failure_margin, failure_margin_pct, curve,
+                   enabled as "enabled: bool"
+            FROM sensors
+            ORDER BY sensor_id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("config_snapshot: sensors failed")?
+        .into_iter()
+        .map(|r| SensorConfig {
+            curve: curve_from_db(&r.sensor_id, r.curve.as_deref()),
+            sensor_id: r.sensor_id,
+            node_id: r.node_id,
+            zone_id: r.zone_id,
+            raw_dry: r.raw_dry,
+            raw_wet: r.raw_wet,
+            channel: r.channel,
+            archived_at: r.archived_at,
+            weight: r.weight,
+            failure_margin: r.failure_margin,
+            failure_margin_pct: r.failure_margin_pct,
+            enabled: r.enabled,
+        })
+        .collect();
+        let nodes = self.load_nodes().await?;
+        Ok(ConfigSnapshot {
+            zones,
+            sensors,
+            nodes,
+        })
+    }
+
+    /// Store the current configuration as a new version, unless it is
+    /// identical to the latest one.  Returns the new version number.
+    ///
+    /// The zones and sensors that differ from the latest version are written
+    /// to the audit log in the same transaction, attributed to `source` and
+    /// `actor` (an API token name).
+    pub async fn record_config_version(
         &self,
-        sensor_id: Option<&str>,
-        zone_id: Option<&str>,
+        ts: i64,
+        reason: &str,
+        source: AuditSource,
+        actor: Option<&str>,
+    ) -> Result<Option<i64>> {
+        let current = self.config_snapshot().await?;
+        let snapshot =
+            serde_json::to_string(&current).context("config snapshot serialization failed")?;
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("record_config_version: begin failed")?;
+        let latest = sqlx::query_scalar!(
+            "SELECT snapshot FROM config_versions ORDER BY version DESC LIMIT 1"
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .context("record_config_version: latest failed")?;
+        if latest.as_deref() == Some(snapshot.as_str()) {
+            return Ok(None);
+        }
+
+        let version = sqlx::query!(
+            "INSERT INTO config_versions (created_ts, reason, snapshot) VALUES (?, ?, ?)",
+            ts,
+            reason,
+            snapshot
+        )
+        .execute(&mut *tx)
+        .await
+        .context("record_config_version: insert failed")?
+        .last_insert_rowid();
+
+        // A previous version that no longer parses can't be compared; skip
+        // the audit rather than report every zone and sensor as new.
+        let previous = match latest
+            .as_deref()
+            .map(serde_json::from_str::<ConfigSnapshot>)
+        {
+            Some(Ok(previous)) => Some(Some(previous)),
+            Some(Err(e)) => {
+                tracing::warn!("audit: latest config version is unreadable: {e}");
+                None
+            }
+            None => Some(None),
+        };
+        if let Some(previous) = previous {
+            let source = source.as_str();
+            for change in audit::diff(previous.as_ref(), &current) {
+                let action = change.action();
+                let old_value = change.old.as_ref().map(|v| v.to_string());
+                let new_value = change.new.as_ref().map(|v| v.to_string());
+                sqlx::query!(
+                    r#"
+                    INSERT INTO audit_log
+                        (ts, entity, entity_id, action, source, actor, old_value, new_value)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                    "#,
+                    ts,
+                    change.entity,
+                    change.entity_id,
+                    action,
+                    source,
+                    actor,
+                    old_value,
+                    new_value
+                )
+                .execute(&mut *tx)
+                .await
+                .context("record_config_version: audit insert failed")?;
+            }
+        }
+
+        tx.commit()
+            .await
+            .context("record_config_version: commit failed")?;
+        Ok(Some(version))
+    }
+
+    /// Audit log rows, newest first.  `entity` is `zone` or `sensor`;
+    /// the time range is inclusive.
+    pub async fn list_audit(
+        &self,
+        entity: Option<&str>,
+        entity_id: Option<&str>,
+        from_ts: Option<i64>,
+        to_ts: Option<i64>,
         limit: i64,
         offset: i64,
-    ) -> Result<Vec<ReadingRow>> {
+    ) -> Result<Vec<AuditEntry>> {
         let mut qb = QueryBuilder::<Sqlite>::new(
-            "SELECT r.ts AS ts, r.sensor_id AS sensor_id, r.raw AS raw, r.moisture AS moisture FROM readings r",
+            "SELECT id, ts, entity, entity_id, action, source, actor, old_value, new_value \
+             FROM audit_log WHERE 1=1",
         );
-
-        if zone_id.is_some() {
-            qb.push(" JOIN sensors s ON s.sensor_id = r.sensor_id");
+        if let Some(entity) = entity {
+            qb.push(" AND entity = ");
+            qb.push_bind(entity);
         }
-
-        let mut has_where = false;
-        if let Some(sid) = sensor_id {
-            qb.push(" WHERE r.sensor_id = ");
-            qb.push_bind(sid.to_string());
-            has_where = true;
+        if let Some(entity_id) = entity_id {
+            qb.push(" AND entity_id = ");
+            qb.push_bind(entity_id);
         }
-        if let Some(zid) = zone_id {
-            qb.push(if has_where { " AND " } else { " WHERE " });
-            qb.push("s.zone_id = ");
-            qb.push_bind(zid.to_string());
+        if let Some(from) = from_ts {
+            qb.push(" AND ts >= ");
+            qb.push_bind(from);
         }
-
-        qb.push(" ORDER BY r.ts DESC LIMIT ");
+        if let Some(to) = to_ts {
+            qb.push(" AND ts <= ");
+            qb.push_bind(to);
+        }
+        qb.push(" ORDER BY ts DESC, id DESC LIMIT ");
         qb.push_bind(limit);
         qb.push(" OFFSET ");
         qb.push_bind(offset);
 
         let rows = qb
-            .build_query_as::<ReadingRow>()
+            .build()
             .fetch_all(&self.pool)
             .await
-            .context("list_readings failed")?;
-
-        Ok(rows)
+            .context("list_audit failed")?;
+        let json = |v: Option<String>| v.and_then(|s| serde_json::from_str(&s).ok());
+        Ok(rows
+            .into_iter()
+            .map(|r| AuditEntry {
+                id: r.get("id"),
+                ts: r.get("ts"),
+                entity: r.get("entity"),
+                entity_id: r.get("entity_id"),
+                action: r.get("action"),
+                source: r.get("source"),
+                actor: r.get("actor"),
+                old_value: json(r.get("old_value")),
+                new_value: json(r.get("new_value")),
+            })
+            .collect())
     }
 
-    /// Delete readings older than the given number of days and reclaim disk space.
-    pub async fn prune_old_readings(&self, retention_days: i64) -> Result<u64> {
-        let cutoff = OffsetDateTime::now_utc().unix_timestamp() - (retention_days * 86400);
-        let result = sqlx::query!("DELETE FROM readings WHERE ts < ?", cutoff)
-            .execute(&self.pool)
-            .await
-            .context("prune_old_readings failed")?;
-
-        // Reclaim freed pages without locking the entire DB
-        sqlx::query("PRAGMA incremental_vacuum(100)")
-            .execute(&self.pool)
-            .await
-            .context("incremental_vacuum failed")?;
-
-        Ok(result.rows_affected())
+    /// All stored versions, newest first.
+    pub async fn list_config_versions(&self) -> Result<Vec<ConfigVersion>> {
+        let rows = sqlx::query_as!(
+            ConfigVersion,
+            r#"
+            SELECT version as "version!", created_ts, reason
+            FROM config_versions
+            ORDER BY version DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("list_config_versions failed")?;
+        Ok(rows)
     }
 
-    // ----------------------------
-    // Watering events
-    // ----------------------------
-
-    pub async fn insert_watering_event(
+    pub async fn get_config_version(
         &self,
-        ts_start: i64,
-        ts_end: i64,
-        zone_id: &str,
-        reason: &str,
-        result: &str,
-    ) -> Result<()> {
-        sqlx::query!(
+        version: i64,
+    ) -> Result<Option<(ConfigVersion, ConfigSnapshot)>> {
+        let row = sqlx::query!(
             r#"
-            INSERT INTO watering_events (ts_start, ts_end, zone_id, reason, result)
-            VALUES (?, ?, ?, ?, ?)
+            SELECT version as "version!", created_ts, reason, snapshot
+            FROM config_versions
+            WHERE version = ?
             "#,
-            ts_start,
-            ts_end,
-            zone_id,
-            reason,
-            result
+            version
         )
-        .execute(&self.pool)
+        .fetch_optional(&self.pool)
         .await
-        .context("insert_watering_event failed")?;
-        Ok(())
+        .context("get_config_version failed")?;
+
+        row.map(|r| {
+            let snapshot = serde_json::from_str(&r.snapshot)
+                .with_context(|| format!("config version {version} is unreadable"))?;
+            Ok((
+                ConfigVersion {
+                    version: r.version,
+                    created_ts: r.created_ts,
+                    reason: r.reason,
+                },
+                snapshot,
+            ))
+        })
+        .transpose()
     }
 
-    pub async fn list_watering_events(
+    /// Make `snapshot` the current configuration in one transaction.
+    ///
+    /// Zones, sensors and nodes in the snapshot are upserted (archive and
+    /// decommission stamps included).  Sensors added since are archived;
+    /// zones added since are deleted when nothing references them and kept
+    /// otherwise.  Nodes are metadata only and are never removed.
+    pub async fn restore_config(
         &self,
-        zone_id: Option<&str>,
-        limit: i64,
-        offset: i64,
-    ) -> Result<Vec<WateringEventRow>> {
-        let mut qb = QueryBuilder::<Sqlite>::new(
-            "SELECT ts_start, ts_end, zone_id, reason, result FROM watering_events",
-        );
+        snapshot: &ConfigSnapshot,
+        ts: i64,
+    ) -> Result<RestoreSummary> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("restore_config: begin failed")?;
 
-        if let Some(zid) = zone_id {
-            qb.push(" WHERE zone_id = ");
-            qb.push_bind(zid.to_string());
+        for z in &snapshot.zones {
+            upsert_zone_with(&mut *tx, z).await?;
+            sqlx::query!(
+                "UPDATE zones SET archived_at = ? WHERE zone_id = ?",
+                z.archived_at,
+                z.zone_id
+            )
+            .execute(&mut *tx)
+            .await
+            .context("restore_config: zone archive failed")?;
+        }
+        for n in &snapshot.nodes {
+            upsert_node_with(&mut *tx, n).await?;
+            sqlx::query!(
+                "UPDATE nodes SET decommissioned_at = ? WHERE node_id = ?",
+                n.decommissioned_at,
+                n.node_id
+            )
+            .execute(&mut *tx)
+            .await
+            .context("restore_config: node decommission failed")?;
+        }
+        for s in &snapshot.sensors {
+            upsert_sensor_with(&mut *tx, s).await?;
+            sqlx::query!(
+                "UPDATE sensors SET archived_at = ? WHERE sensor_id = ?",
+                s.archived_at,
+                s.sensor_id
+            )
+            .execute(&mut *tx)
+            .await
+            .context("restore_config: sensor archive failed")?;
         }
 
-        qb.push(" ORDER BY ts_start DESC LIMIT ");
-        qb.push_bind(limit);
-        qb.push(" OFFSET ");
-        qb.push_bind(offset);
+        let keep_sensors: Vec<&str> = snapshot
+            .sensors
+            .iter()
+            .map(|s| s.sensor_id.as_str())
+            .collect();
+        let active: Vec<String> = sqlx::query_scalar!(
+            r#"SELECT sensor_id as "sensor_id!" FROM sensors WHERE archived_at IS NULL"#
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .context("restore_config: sensor list failed")?;
+        let mut archived_sensors = Vec::new();
+        for sensor_id in active {
+            if keep_sensors.contains(&sensor_id.as_str()) {
+                continue;
+            }
+            sqlx::query!(
+                "UPDATE sensors SET archived_at = ? WHERE sensor_id = ?",
+                ts,
+                sensor_id
+            )
+            .execute(&mut *tx)
+            .await
+            .context("restore_config: sensor archive failed")?;
+            archived_sensors.push(sensor_id);
+        }
 
-        let rows = qb
-            .build_query_as::<WateringEventRow>()
-            .fetch_all(&self.pool)
+        let keep_zones: Vec<&str> = snapshot.zones.iter().map(|z| z.zone_id.as_str()).collect();
+        let zones: Vec<String> = sqlx::query_scalar!(r#"SELECT zone_id as "zone_id!" FROM zones"#)
+            .fetch_all(&mut *tx)
             .await
-            .context("list_watering_events failed")?;
+            .context("restore_config: zone list failed")?;
+        let mut kept_zones = Vec::new();
+        for zone_id in zones {
+            if keep_zones.contains(&zone_id.as_str()) {
+                continue;
+            }
+            // Savepoint, so a foreign-key failure only undoes this delete.
+            let mut sp = tx
+                .begin()
+                .await
+                .context("restore_config: savepoint failed")?;
+            let deleted = sqlx::query!("DELETE FROM zones WHERE zone_id = ?", zone_id)
+                .execute(&mut *sp)
+                .await;
+            if deleted.is_ok() {
+                sp.commit()
+                    .await
+                    .context("restore_config: savepoint commit failed")?;
+            } else {
+                sp.rollback()
+                    .await
+                    .context("restore_config: savepoint rollback failed")?;
+                kept_zones.push(zone_id);
+            }
+        }
 
-        Ok(rows)
+        tx.commit().await.context("restore_config: commit failed")?;
+        Ok(RestoreSummary {
+            archived_sensors,
+            kept_zones,
+        })
     }
 
     // ----------------------------
-    // Daily counters (safety limits)
+    // Zone disturbances
     // ----------------------------
 
-    pub fn today_yyyy_mm_dd() -> String {
-        let now = OffsetDateTime::now_utc();
-        format!(
-            "{:04}-{:02}-{:02}",
-            now.year(),
-            now.month() as u8,
-            now.day()
+    /// Record a disturbance and return its id.
+    pub async fn insert_disturbance(
+        &self,
+        zone_id: &str,
+        start_ts: i64,
+        end_ts: Option<i64>,
+        reason: &str,
+    ) -> Result<i64> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO zone_disturbances (zone_id, start_ts, end_ts, reason)
+            VALUES (?, ?, ?, ?)
+            "#,
+            zone_id,
+            start_ts,
+            end_ts,
+            reason
         )
+        .execute(&self.pool)
+        .await
+        .context("insert_disturbance failed")?;
+        Ok(result.last_insert_rowid())
     }
 
-    pub async fn get_daily_counters(&self, day: &str, zone_id: &str) -> Result<DailyCounters> {
-        let row = sqlx::query!(
+    /// Disturbances for a zone, newest first.
+    pub async fn list_disturbances(&self, zone_id: &str) -> Result<Vec<Disturbance>> {
+        let rows = sqlx::query_as!(
+            Disturbance,
             r#"
-            SELECT day, zone_id, open_sec, pulses
-            FROM zone_daily_counters
-            WHERE day = ? AND zone_id = ?
+            SELECT id as "id!", zone_id, start_ts, end_ts, reason
+            FROM zone_disturbances
+            WHERE zone_id = ?
+            ORDER BY start_ts DESC, id DESC
             "#,
-            day,
             zone_id
         )
-        .fetch_optional(&self.pool)
+        .fetch_all(&self.pool)
         .await
-        .context("get_daily_counters failed")?;
-
-        Ok(match row {
-            Some(r) => DailyCounters {
-                day: r.day,
-                zone_id: r.zone_id,
-                open_sec: r.open_sec,
-                pulses: r.pulses,
-            },
-            None => DailyCounters {
-                day: day.to_string(),
-                zone_id: zone_id.to_string(),
-                open_sec: 0,
-                pulses: 0,
-            },
-        })
+        .context("list_disturbances failed")?;
+        Ok(rows)
     }
 
-    pub async fn ensure_daily_row(&self, day: &str, zone_id: &str) -> Result<()> {
-        sqlx::query!(
+    /// Every zone's disturbances, for the scheduler's moisture window.
+    pub async fn all_disturbances(&self) -> Result<Vec<Disturbance>> {
+        let rows = sqlx::query_as!(
+            Disturbance,
             r#"
-            INSERT INTO zone_daily_counters (day, zone_id, open_sec, pulses)
-            VALUES (?, ?, 0, 0)
-            ON CONFLICT(day, zone_id) DO NOTHING
-            "#,
-            day,
-            zone_id
+            SELECT id as "id!", zone_id, start_ts, end_ts, reason
+            FROM zone_disturbances
+            ORDER BY zone_id, start_ts
+            "#
         )
-        .execute(&self.pool)
+        .fetch_all(&self.pool)
         .await
-        .context("ensure_daily_row failed")?;
-        Ok(())
+        .context("all_disturbances failed")?;
+        Ok(rows)
     }
 
-    pub async fn add_open_seconds(&self, day: &str, zone_id: &str, delta: i64) -> Result<()> {
-        self.ensure_daily_row(day, zone_id).await?;
-        sqlx::query!(
+    pub async fn get_disturbance(&self, zone_id: &str, id: i64) -> Result<Option<Disturbance>> {
+        let row = sqlx::query_as!(
+            Disturbance,
             r#"
-            UPDATE zone_daily_counters
-            SET open_sec = open_sec + ?
-            WHERE day = ? AND zone_id = ?
+            SELECT id as "id!", zone_id, start_ts, end_ts, reason
+            FROM zone_disturbances
+            WHERE zone_id = ? AND id = ?
             "#,
-            delta,
-            day,
-            zone_id
+            zone_id,
+            id
         )
-        .execute(&self.pool)
+        .fetch_optional(&self.pool)
         .await
-        .context("add_open_seconds failed")?;
-        Ok(())
+        .context("get_disturbance failed")?;
+        Ok(row)
     }
 
-    pub async fn add_pulse(&self, day: &str, zone_id: &str, delta: i64) -> Result<()> {
-        self.ensure_daily_row(day, zone_id).await?;
-        sqlx::query!(
+    /// Update a disturbance's range and reason (e.g. to close an ongoing
+    /// one).  Returns false if no such disturbance exists for the zone.
+    pub async fn update_disturbance(&self, d: &Disturbance) -> Result<bool> {
+        let result = sqlx::query!(
             r#"
-            UPDATE zone_daily_counters
-            SET pulses = pulses + ?
-            WHERE day = ? AND zone_id = ?
+            UPDATE zone_disturbances
+            SET start_ts = ?, end_ts = ?, reason = ?
+            WHERE zone_id = ? AND id = ?
             "#,
-            delta,
-            day,
-            zone_id
+            d.start_ts,
+            d.end_ts,
+            d.reason,
+            d.zone_id,
+            d.id
         )
         .execute(&self.pool)
         .await
-        .context("add_pulse failed")?;
-        Ok(())
+        .context("update_disturbance failed")?;
+        Ok(result.rows_affected() > 0)
     }
 
-    /// Quick connectivity check — runs a trivial query.
-    pub async fn health_check(&self) -> Result<()> {
-        sqlx::query("SELECT 1")
+    pub async fn delete_disturbance(&self, zone_id: &str, id: i64) -> Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM zone_disturbances WHERE zone_id = ? AND id = ?",
+            zone_id,
+            id
+        )
+        .execute(&self.pool)
+        .await
+        .context("delete_disturbance failed")?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    // ----------------------------
+    // Blackout calendar
+    // ----------------------------
+
+    /// Store a blackout and return its id.
+    pub async fn insert_blackout(&self, start: &str, end: &str, reason: &str) -> Result<i64> {
+        let result = sqlx::query!(
+            "INSERT INTO blackouts (start, end, reason) VALUES (?, ?, ?)",
+            start,
+            end,
+            reason
+        )
+        .execute(&self.pool)
+        .await
+        .context("insert_blackout failed")?;
+        Ok(result.last_insert_rowid())
+    }
+
+    /// All blackouts, by id.
+    pub async fn list_blackouts(&self) -> Result<Vec<Blackout>> {
+        let rows = sqlx::query_as!(
+            Blackout,
+            r#"SELECT id as "id!", start, end, reason FROM blackouts ORDER BY id"#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("list_blackouts failed")?;
+        Ok(rows)
+    }
+
+    /// Returns false if no such blackout exists.
+    pub async fn update_blackout(&self, b: &Blackout) -> Result<bool> {
+        let result = sqlx::query!(
+            "UPDATE blackouts SET start = ?, end = ?, reason = ? WHERE id = ?",
+            b.start,
+            b.end,
+            b.reason,
+            b.id
+        )
+        .execute(&self.pool)
+        .await
+        .context("update_blackout failed")?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete_blackout(&self, id: i64) -> Result<bool> {
+        let result = sqlx::query!("DELETE FROM blackouts WHERE id = ?", id)
             .execute(&self.pool)
             .await
-            .context("db health check failed")?;
-        Ok(())
+            .context("delete_blackout failed")?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    // ----------------------------
+    // Evapotranspiration
+    // ----------------------------
+
+    /// Store a closed day, replacing an earlier row for the same date.
+    pub async fn upsert_et_day(&self, day: &EtDay) -> Result<()> {
+        let method = day.method.as_str();
+        sqlx::query!(
+            r#"
+            INSERT INTO et_daily (date, et0_mm, rain_mm, method) VALUES (?, ?, ?, ?)
+            ON CONFLICT(date) DO UPDATE SET
+              et0_mm=excluded.et0_mm, rain_mm=excluded.rain_mm, method=excluded.method
+            "#,
+            day.date,
+            day.et0_mm,
+            day.rain_mm,
+            method
+        )
+        .execute(&self.pool)
+        .await
+        .context("upsert_et_day failed")?;
+        Ok(())
+    }
+
+    /// The latest `limit` closed days, newest first.
+    pub async fn list_et_days(&self, limit: i64) -> Result<Vec<EtDay>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT date as "date!", et0_mm, rain_mm, method
+            FROM et_daily ORDER BY date DESC LIMIT ?
+            "#,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("list_et_days failed")?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|r| {
+                Some(EtDay {
+                    method: EtMethod::parse(&r.method)?,
+                    date: r.date,
+                    et0_mm: r.et0_mm,
+                    rain_mm: r.rain_mm,
+                })
+            })
+            .collect())
+    }
+
+    /// Every stored zone deficit (mm).
+    pub async fn load_et_deficits(&self) -> Result<BTreeMap<String, f64>> {
+        let rows = sqlx::query!(r#"SELECT zone_id as "zone_id!", deficit_mm FROM zone_et_deficit"#)
+            .fetch_all(&self.pool)
+            .await
+            .context("load_et_deficits failed")?;
+        Ok(rows
+            .into_iter()
+            .map(|r| (r.zone_id, r.deficit_mm))
+            .collect())
+    }
+
+    pub async fn set_et_deficit(&self, zone_id: &str, deficit_mm: f64, ts: i64) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO zone_et_deficit (zone_id, deficit_mm, updated_at) VALUES (?, ?, ?)
+            ON CONFLICT(zone_id) DO UPDATE SET
+              deficit_mm=excluded.deficit_mm, updated_at=excluded.updated_at
+            "#,
+            zone_id,
+            deficit_mm,
+            ts
+        )
+        .execute(&self.pool)
+        .await
+        .context("set_et_deficit failed")?;
+        Ok(())
+    }
+
+    // ----------------------------
+    // Readings + aggregation helpers
+    // ----------------------------
+
+    /// Store a reading.  Returns `false` if a reading with the same
+    /// `(ts, sensor_id)` already exists (e.g. a node replaying its offline
+    /// buffer after a lost acknowledgement); the existing row is kept.
+    #[tracing::instrument(name = "db.insert_reading", skip_all, fields(sensor = %sensor_id))]
+    pub async fn insert_reading(
+        &self,
+        ts: i64,
+        sensor_id: &str,
+        raw: i64,
+        moisture: f32,
+    ) -> Result<bool> {
+        let row = PendingReading {
+            ts,
+            sensor_id: sensor_id.to_string(),
+            raw,
+            moisture: moisture as f64,
+        };
+        insert_reading_with(&self.pool, &row)
+            .await
+            .context("insert_reading failed")
+    }
+
+    /// Whether `(ts, sensor_id)` is new: `false` for a redelivery or replay
+    /// of a reading claimed before, whether it was queued, written or
+    /// dropped as implausible.  Call it before acting on a reading at all;
+    /// duplicates are counted (see `duplicate_readings`).  Only the last
+    /// [`READING_BUFFER_CAP`] readings are remembered; older duplicates are
+    /// caught by the primary key when written.
+    pub async fn claim_reading(&self, ts: i64, sensor_id: &str) -> bool {
+        let new = self
+            .readings
+            .lock()
+            .await
+            .claim((ts, sensor_id.to_string()));
+        self.count_duplicates(u64::from(!new));
+        new
+    }
+
+    /// Store a reading claimed with `claim_reading` with the next batched
+    /// write, or straight away when batching is off (see
+    /// `with_reading_batch`).  Returns `false` when batching is off and the
+    /// row is already stored; with batching, such duplicates are dropped
+    /// when the batch is written.  Either way the row is left as first
+    /// stored and the duplicate counted.  An error means a write failed:
+    /// with batching the readings stay queued, without it the reading is
+    /// forgotten so a redelivery can store it.
+    #[tracing::instrument(name = "db.queue_reading", skip_all, fields(sensor = %sensor_id))]
+    pub async fn queue_reading(
+        &self,
+        ts: i64,
+        sensor_id: &str,
+        raw: i64,
+        moisture: f32,
+    ) -> Result<bool> {
+        if self.batch_rows == 0 {
+            return match self.insert_reading(ts, sensor_id, raw, moisture).await {
+                Ok(new) => {
+                    self.count_duplicates(u64::from(!new));
+                    Ok(new)
+                }
+                Err(e) => {
+                    let key = (ts, sensor_id.to_string());
+                    self.readings.lock().await.seen.remove(&key);
+                    Err(e)
+                }
+            };
+        }
+        let full = {
+            let mut buf = self.readings.lock().await;
+            buf.rows.push(PendingReading {
+                ts,
+                sensor_id: sensor_id.to_string(),
+                raw,
+                moisture: moisture as f64,
+            });
+            buf.rows.len() >= self.batch_rows
+        };
+        if full {
+            self.flush_readings().await?;
+        }
+        Ok(true)
+    }
+
+    /// Write every queued reading in one transaction, returning how many
+    /// were new; the rest were already stored and count as duplicates.
+    /// If the transaction fails the rows are retried one by one:
+    /// a row rejected by a constraint (say its sensor was deleted while it
+    /// waited) is dropped with a warning, and on any other error the rest
+    /// stay queued for the next flush.
+    #[tracing::instrument(name = "db.flush_readings", skip_all)]
+    pub async fn flush_readings(&self) -> Result<u64> {
+        let rows = { std::mem::take(&mut self.readings.lock().await.rows) };
+        if rows.is_empty() {
+            return Ok(0);
+        }
+        match self.write_readings(&rows).await {
+            Ok(written) => {
+                self.count_duplicates(rows.len() as u64 - written);
+                return Ok(written);
+            }
+            Err(e) => tracing::warn!(rows = rows.len(), "batched reading write failed: {e:#}"),
+        }
+
+        let mut written = 0;
+        let mut rows = rows.into_iter();
+        while let Some(row) = rows.next() {
+            match insert_reading_with(&self.pool, &row).await {
+                Ok(new) => {
+                    written += u64::from(new);
+                    self.count_duplicates(u64::from(!new));
+                }
+                Err(sqlx::Error::Database(e)) if e.kind() != sqlx::error::ErrorKind::Other => {
+                    tracing::warn!(
+                        sensor = %row.sensor_id,
+                        ts = row.ts,
+                        "dropping reading rejected by the database: {e}"
+                    );
+                }
+                Err(e) => {
+                    let failed = std::iter::once(row).chain(rows).collect();
+                    self.readings.lock().await.requeue(failed);
+                    return Err(e).context("flush_readings failed");
+                }
+            }
+        }
+        Ok(written)
+    }
+
+    async fn write_readings(&self, rows: &[PendingReading]) -> Result<u64> {
+        self.retry("write_readings", || async {
+            let mut tx = self.pool.begin().await?;
+            let mut written = 0;
+            for row in rows {
+                written += u64::from(insert_reading_with(&mut *tx, row).await?);
+            }
+            tx.commit().await?;
+            Ok(written)
+        })
+        .await
+        .context("write_readings failed")
+    }
+
+    /// Returns the newest moisture reading for a given zone across its sensors.
+    /// (V1 simple approach: max(ts) across zone’s sensors)
+    ///
+    /// This and the other zone-level moisture queries below skip readings
+    /// that fall inside a recorded disturbance.
+    pub async fn latest_zone_moisture(&self, zone_id: &str) -> Result<Option<(i64, f32)>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT r.ts as ts, r.moisture as moisture
+            FROM readings r
+            JOIN sensors s ON s.sensor_id = r.sensor_id
+            WHERE s.zone_id = ?
+              AND NOT EXISTS (
+                SELECT 1 FROM zone_disturbances d
+                WHERE d.zone_id = s.zone_id
+                  AND r.ts >= d.start_ts
+                  AND (d.end_ts IS NULL OR r.ts < d.end_ts)
+              )
+            ORDER BY r.ts DESC
+            LIMIT 1
+            "#,
+            zone_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("latest_zone_moisture failed")?;
+
+        Ok(row.map(|r| (r.ts, r.moisture as f32)))
+    }
+
+    /// Newest stored reading for a single sensor.
+    pub async fn latest_sensor_reading(&self, sensor_id: &str) -> Result<Option<ReadingRow>> {
+        let row = sqlx::query_as!(
+            ReadingRow,
+            r#"
+            SELECT ts, sensor_id, raw, moisture
+            FROM readings
+            WHERE sensor_id = ?
+            ORDER BY ts DESC
+            LIMIT 1
+            "#,
+            sensor_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("latest_sensor_reading failed")?;
+        Ok(row)
+    }
+
+    /// Each active sensor's last `n` readings, oldest first.
+    pub async fn recent_readings(&self, n: i64) -> Result<Vec<ReadingRow>> {
+        let rows = sqlx::query_as!(
+            ReadingRow,
+            r#"
+            SELECT ts as "ts!", sensor_id as "sensor_id!", raw as "raw!", moisture as "moisture!"
+            FROM (
+              SELECT r.ts, r.sensor_id, r.raw, r.moisture,
+                     ROW_NUMBER() OVER (PARTITION BY r.sensor_id ORDER BY r.ts DESC) AS rn
+              FROM readings r
+              JOIN sensors s ON s.sensor_id = r.sensor_id
+              WHERE s.archived_at IS NULL
+            )
+            WHERE rn <= ?
+            ORDER BY sensor_id, ts
+            "#,
+            n
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("recent_readings failed")?;
+        Ok(rows)
+    }
+
+    /// Each active, enabled, unquarantined sensor's mean moisture over its last `n`
+    /// readings, for sensors that reported since `since_ts` (disturbed
+    /// readings excluded).
+    pub async fn zone_sensor_moisture(
+        &self,
+        zone_id: &str,
+        n: i64,
+        since_ts: i64,
+    ) -> Result<Vec<SensorMoisture>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT sensor_id as "sensor_id!", weight as "weight!: f64",
+                   AVG(moisture) as "moisture!: f64"
+            FROM (
+              SELECT r.sensor_id, s.weight, r.moisture,
+                     ROW_NUMBER() OVER (PARTITION BY r.sensor_id ORDER BY r.ts DESC) AS rn
+              FROM readings r
+              JOIN sensors s ON s.sensor_id = r.sensor_id
+              WHERE s.zone_id = ? AND s.archived_at IS NULL AND s.enabled AND r.ts >= ?
+                AND NOT EXISTS (
+                  SELECT 1 FROM sensor_health h
+                  WHERE h.sensor_id = s.sensor_id AND h.quarantined_ts IS NOT NULL
+                )
+                AND NOT EXISTS (
+                  SELECT 1 FROM zone_disturbances d
+                  WHERE d.zone_id = s.zone_id
+                    AND r.ts >= d.start_ts
+                    AND (d.end_ts IS NULL OR r.ts < d.end_ts)
+                )
+            )
+            WHERE rn <= ?
+            GROUP BY sensor_id
+            ORDER BY sensor_id
+            "#,
+            zone_id,
+            since_ts,
+            n
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("zone_sensor_moisture failed")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| SensorMoisture {
+                sensor_id: r.sensor_id,
+                weight: r.weight,
+                moisture: r.moisture as f32,
+            })
+            .collect())
+    }
+
+    /// Zone moisture series since `since_ts`, oldest first.  Readings from
+    /// several sensors sharing a timestamp are averaged into one point.
+    pub async fn zone_moisture_since(
+        &self,
+        zone_id: &str,
+        since_ts: i64,
+    ) -> Result<Vec<(i64, f32)>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT r.ts as "ts!: i64", AVG(r.moisture) as "moisture!: f64"
+            FROM readings r
+            JOIN sensors s ON s.sensor_id = r.sensor_id
+            WHERE s.zone_id = ? AND r.ts >= ?
+              AND NOT EXISTS (
+                SELECT 1 FROM zone_disturbances d
+                WHERE d.zone_id = s.zone_id
+                  AND r.ts >= d.start_ts
+                  AND (d.end_ts IS NULL OR r.ts < d.end_ts)
+              )
+            GROUP BY r.ts
+            ORDER BY r.ts ASC
+            "#,
+            zone_id,
+            since_ts
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("zone_moisture_since failed")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| (r.ts, r.moisture as f32))
+            .collect())
+    }
+
+    pub async fn list_readings(
+        &self,
+        sensor_id: Option<&str>,
+        zone_id: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<ReadingRow>> {
+        let mut qb = QueryBuilder::<Sqlite>::new(
+            "SELECT r.ts AS ts, r.sensor_id AS sensor_id, r.raw AS raw, r.moisture AS moisture FROM readings r",
+        );
+
+        if zone_id.is_some() {
+            qb.push(" JOIN sensors s ON s.sensor_id = r.sensor_id");
+        }
+
+        let mut has_where = false;
+        if let Some(sid) = sensor_id {
+            qb.push(" WHERE r.sensor_id = ");
+            qb.push_bind(sid.to_string());
+            has_where = true;
+        }
+        if let Some(zid) = zone_id {
+            qb.push(if has_where { " AND " } else { " WHERE " });
+            qb.push("s.zone_id = ");
+            qb.push_bind(zid.to_string());
+        }
+
+        qb.push(" ORDER BY r.ts DESC LIMIT ");
+        qb.push_bind(limit);
+        qb.push(" OFFSET ");
+        qb.push_bind(offset);
+
+        let rows = qb
+            .build_query_as::<ReadingRow>()
+            .fetch_all(&self.pool)
+            .await
+            .context("list_readings failed")?;
+
+        Ok(rows)
+    }
+
+    /// Per-day moisture for a zone between `from` and `to` (inclusive,
+    /// `YYYY-MM-DD`), oldest first.  Days already rolled up (see
+    /// `rollup_daily_moisture`) come from `zone_daily_moisture`, the rest
+    /// from the raw readings.
+    pub async fn daily_moisture(
+        &self,
+        zone_id: &str,
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<DailyMoisture>> {
+        let rows = sqlx::query_as!(
+            DailyMoisture,
+            r#"
+            SELECT day as "day!: String",
+                   avg_moisture as "avg_moisture!: f64",
+                   min_moisture as "min_moisture!: f64",
+                   max_moisture as "max_moisture!: f64",
+                   samples as "samples!: i64"
+            FROM zone_daily_moisture
+            WHERE zone_id = ? AND day >= ? AND day <= ?
+            UNION ALL
+            SELECT date(r.ts, 'unixepoch'),
+                   AVG(r.moisture), MIN(r.moisture), MAX(r.moisture), COUNT(*)
+            FROM readings r
+            JOIN sensors s ON s.sensor_id = r.sensor_id
+            WHERE s.zone_id = ?
+              AND r.ts >= unixepoch(?) AND r.ts < unixepoch(?, '+1 day')
+              AND date(r.ts, 'unixepoch') NOT IN (
+                SELECT day FROM zone_daily_moisture WHERE zone_id = s.zone_id
+              )
+              AND NOT EXISTS (
+                SELECT 1 FROM zone_disturbances d
+                WHERE d.zone_id = s.zone_id
+                  AND r.ts >= d.start_ts
+                  AND (d.end_ts IS NULL OR r.ts < d.end_ts)
+              )
+            GROUP BY 1
+            ORDER BY 1
+            "#,
+            zone_id,
+            from,
+            to,
+            zone_id,
+            from,
+            to
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("daily_moisture failed")?;
+        Ok(rows)
+    }
+
+    /// Roll every whole UTC day of readings up to the day containing
+    /// `before_ts` into `zone_daily_moisture`.  Days already rolled up are
+    /// left alone: by the time the pruning cutoff first reaches a day, none
+    /// of its readings have been deleted yet, so the first rollup is the
+    /// complete one.
+    pub async fn rollup_daily_moisture(&self, before_ts: i64) -> Result<u64> {
+        let day_end = (before_ts.div_euclid(86400) + 1) * 86400;
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO zone_daily_moisture (
+              day, zone_id, avg_moisture, min_moisture, max_moisture, samples
+            )
+            SELECT date(r.ts, 'unixepoch'), s.zone_id,
+                   AVG(r.moisture), MIN(r.moisture), MAX(r.moisture), COUNT(*)
+            FROM readings r
+            JOIN sensors s ON s.sensor_id = r.sensor_id
+            WHERE r.ts < ?
+              AND NOT EXISTS (
+                SELECT 1 FROM zone_disturbances d
+                WHERE d.zone_id = s.zone_id
+                  AND r.ts >= d.start_ts
+                  AND (d.end_ts IS NULL OR r.ts < d.end_ts)
+              )
+            GROUP BY 1, 2
+            ON CONFLICT(day, zone_id) DO NOTHING
+            "#,
+            day_end
+        )
+        .execute(&self.pool)
+        .await
+        .context("rollup_daily_moisture failed")?;
+        Ok(result.rows_affected())
+    }
+
+    /// Delete readings (sensor, flow and climate) older than the given number of days.
+    /// Moisture is rolled up per zone and day, and per sensor and hour into
+    /// `readings_hourly`, first.
+    pub async fn prune_old_readings(&self, retention_days: i64) -> Result<u64> {
+        let cutoff = clock::now_unix() - (retention_days * 86400);
+        self.rollup_daily_moisture(cutoff).await?;
+        // Rollup and delete commit together, so a reading is summarised
+        // exactly once.  An hour split by the cutoff is merged into the
+        // existing row on the next run.
+        let mut tx = self.pool.begin().await.context("begin failed")?;
+        sqlx::query!(
+            r#"
+            INSERT INTO readings_hourly (
+              ts, sensor_id, samples, moisture_min, moisture_avg, moisture_max
+            )
+            SELECT (ts / 3600) * 3600, sensor_id,
+                   COUNT(*), MIN(moisture), AVG(moisture), MAX(moisture)
+            FROM readings
+            WHERE ts < ?
+            GROUP BY 1, 2
+            ON CONFLICT(sensor_id, ts) DO UPDATE SET
+              samples = samples + excluded.samples,
+              moisture_min = MIN(moisture_min, excluded.moisture_min),
+              moisture_avg = (moisture_avg * samples
+                              + excluded.moisture_avg * excluded.samples)
+                             / (samples + excluded.samples),
+              moisture_max = MAX(moisture_max, excluded.moisture_max)
+            "#,
+            cutoff
+        )
+        .execute(&mut *tx)
+        .await
+        .context("prune_old_readings: hourly rollup failed")?;
+        let result = sqlx::query!("DELETE FROM readings WHERE ts < ?", cutoff)
+            .execute(&mut *tx)
+            .await
+            .context("prune_old_readings failed")?;
+        tx.commit().await.context("commit failed")?;
+        let flow = sqlx::query!("DELETE FROM flow_readings WHERE ts < ?", cutoff)
+            .execute(&self.pool)
+            .await
+            .context("prune_old_readings: flow_readings failed")?;
+        let climate = sqlx::query!("DELETE FROM climate_readings WHERE ts < ?", cutoff)
+            .execute(&self.pool)
+            .await
+            .context("prune_old_readings: climate_readings failed")?;
+        Ok(result.rows_affected() + flow.rows_affected() + climate.rows_affected())
+    }
+
+    /// Roll hourly summaries older than the given number of days into
+    /// `readings_daily`.  Returns the hourly rows compacted.
+    pub async fn compact_hourly_readings(&self, retention_days: i64) -> Result<u64> {
+        let cutoff = clock::now_unix() - (retention_days * 86400);
+        let mut tx = self.pool.begin().await.context("begin failed")?;
+        sqlx::query!(
+            r#"
+            INSERT INTO readings_daily (
+              ts, sensor_id, samples, moisture_min, moisture_avg, moisture_max
+            )
+            SELECT (ts / 86400) * 86400, sensor_id, SUM(samples), MIN(moisture_min),
+                   SUM(moisture_avg * samples) / SUM(samples), MAX(moisture_max)
+            FROM readings_hourly
+            WHERE ts < ?
+            GROUP BY 1, 2
+            ON CONFLICT(sensor_id, ts) DO UPDATE SET
+              samples = samples + excluded.samples,
+              moisture_min = MIN(moisture_min, excluded.moisture_min),
+              moisture_avg = (moisture_avg * samples
+                              + excluded.moisture_avg * excluded.samples)
+                             / (samples + excluded.samples),
+              moisture_max = MAX(moisture_max, excluded.moisture_max)
+            "#,
+            cutoff
+        )
+        .execute(&mut *tx)
+        .await
+        .context("compact_hourly_readings: daily rollup failed")?;
+        let result = sqlx::query!("DELETE FROM readings_hourly WHERE ts < ?", cutoff)
+            .execute(&mut *tx)
+            .await
+            .context("compact_hourly_readings failed")?;
+        tx.commit().await.context("commit failed")?;
+        Ok(result.rows_affected())
+    }
+
+    /// Rewrite the moisture of `sensor`'s readings from `from_ts` on with
+    /// its current calibration, [`RECOMPUTE_BATCH`] rows per transaction,
+    /// yielding between batches so ingest and the scheduler keep running.
+    /// Hourly and daily rollups keep their old values: they have no raw
+    /// counts to recompute from.  Returns the readings rewritten.
+    pub async fn recompute_moisture(&self, sensor: &SensorConfig, from_ts: i64) -> Result<u64> {
+        // Queued readings carry moisture from the old calibration too.
+        self.flush_readings().await?;
+        let mut cursor = from_ts;
+        let mut updated = 0;
+        loop {
+            let rows = sqlx::query!(
+                r#"
+                SELECT ts, raw FROM readings
+                WHERE sensor_id = ? AND ts >= ?
+                ORDER BY ts
+                LIMIT ?
+                "#,
+                sensor.sensor_id,
+                cursor,
+                RECOMPUTE_BATCH
+            )
+            .fetch_all(&self.pool)
+            .await
+            .context("recompute_moisture: select failed")?;
+            let Some(last) = rows.last() else {
+                break;
+            };
+            cursor = last.ts + 1;
+
+            let mut tx = self.pool.begin().await.context("begin failed")?;
+            for r in &rows {
+                let moisture = f64::from(sensor.moisture(r.raw));
+                sqlx::query!(
+                    "UPDATE readings SET moisture = ? WHERE ts = ? AND sensor_id = ?",
+                    moisture,
+                    r.ts,
+                    sensor.sensor_id
+                )
+                .execute(&mut *tx)
+                .await
+                .context("recompute_moisture: update failed")?;
+            }
+            tx.commit().await.context("commit failed")?;
+            updated += rows.len() as u64;
+            tokio::task::yield_now().await;
+        }
+        Ok(updated)
+    }
+
+    /// A sensor's moisture in `[from, to)` per hour or day (`bucket_sec`),
+    /// oldest first, from raw readings and their hourly and daily rollups.
+    /// Rolled-up periods are only as fine as they were stored: asking for
+    /// hours where only daily rows are left gives one bucket per day.
+    pub async fn sensor_moisture_trend(
+        &self,
+        sensor_id: &str,
+        bucket_sec: i64,
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<MoistureBucket>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT (ts / ?1) * ?1 AS "ts!: i64",
+                   SUM(samples) AS "samples!: i64",
+                   MIN(lo) AS "moisture_min!: f64",
+                   SUM(total) / SUM(samples) AS "moisture_avg!: f64",
+                   MAX(hi) AS "moisture_max!: f64"
+            FROM (
+              SELECT ts, 1 AS samples, moisture AS total, moisture AS lo, moisture AS hi
+              FROM readings WHERE sensor_id = ?2 AND ts >= ?3 AND ts < ?4
+              UNION ALL
+              SELECT ts, samples, moisture_avg * samples, moisture_min, moisture_max
+              FROM readings_hourly WHERE sensor_id = ?2 AND ts >= ?3 AND ts < ?4
+              UNION ALL
+              SELECT ts, samples, moisture_avg * samples, moisture_min, moisture_max
+              FROM readings_daily WHERE sensor_id = ?2 AND ts >= ?3 AND ts < ?4
+            )
+            GROUP BY 1
+            ORDER BY 1
+            "#,
+            bucket_sec,
+            sensor_id,
+            from,
+            to
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("sensor_moisture_trend failed")?;
+        Ok(rows
+            .into_iter()
+            .map(|r| MoistureBucket {
+                ts: r.ts,
+                samples: r.samples,
+                moisture_min: r.moisture_min,
+                moisture_avg: r.moisture_avg,
+                moisture_max: r.moisture_max,
+            })
+            .collect())
+    }
+
+    pub async fn prune_watering_events(&self, retention_days: i64) -> Result<u64> {
+        let cutoff = clock::now_unix() - (retention_days * 86400);
+        let result = sqlx::query!("DELETE FROM watering_events WHERE ts_start < ?", cutoff)
+            .execute(&self.pool)
+            .await
+            .context("prune_watering_events failed")?;
+        Ok(result.rows_affected())
+    }
+
+    /// Return up to `max_pages` free pages to the filesystem (all of them if
+    /// unset).  Needs `auto_vacuum = INCREMENTAL`.
+    pub async fn incremental_vacuum(&self, max_pages: Option<u32>) -> Result<()> {
+        let sql = match max_pages {
+            Some(n) => format!("PRAGMA incremental_vacuum({n})"),
+            None => "PRAGMA incremental_vacuum".to_string(),
+        };
+        sqlx::query(&sql)
+            .execute(&self.pool)
+            .await
+            .context("incremental_vacuum failed")?;
+        Ok(())
+    }
+
+    /// Size of the database in bytes (pages in use and free).
+    pub async fn size_bytes(&self) -> Result<u64> {
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .context("size_bytes: acquire failed")?;
+        let pages: i64 = sqlx::query_scalar("PRAGMA page_count")
+            .fetch_one(&mut *conn)
+            .await
+            .context("page_count failed")?;
+        let page_size: i64 = sqlx::query_scalar("PRAGMA page_size")
+            .fetch_one(&mut *conn)
+            .await
+            .context("page_size failed")?;
+        Ok(u64::try_from(pages * page_size).unwrap_or(0))
+    }
+
+    // ----------------------------
+    // Sensor health
+    // ----------------------------
+
+    /// Count an implausible reading from `sensor_id` at `ts`, quarantining
+    /// the sensor once `quarantine_after` failures come in a row.  Returns
+    /// `true` if this failure quarantined it.
+    pub async fn record_sensor_failure(
+        &self,
+        sensor_id: &str,
+        ts: i64,
+        quarantine_after: i64,
+    ) -> Result<bool> {
+        let mut tx = self.pool.begin().await.context("begin failed")?;
+        sqlx::query!(
+            r#"
+            INSERT INTO sensor_health
+              (sensor_id, consecutive_failures, total_failures, last_failure_ts)
+            VALUES (?, 1, 1, ?)
+            ON CONFLICT(sensor_id) DO UPDATE SET
+              consecutive_failures = consecutive_failures + 1,
+              total_failures = total_failures + 1,
+              last_failure_ts = excluded.last_failure_ts
+            "#,
+            sensor_id,
+            ts
+        )
+        .execute(&mut *tx)
+        .await
+        .context("record_sensor_failure: count failed")?;
+        let quarantined = sqlx::query!(
+            r#"
+            UPDATE sensor_health SET quarantined_ts = ?
+            WHERE sensor_id = ? AND quarantined_ts IS NULL AND consecutive_failures >= ?
+            "#,
+            ts,
+            sensor_id,
+            quarantine_after
+        )
+        .execute(&mut *tx)
+        .await
+        .context("record_sensor_failure: quarantine failed")?
+        .rows_affected()
+            > 0;
+        tx.commit().await.context("commit failed")?;
+        Ok(quarantined)
+    }
+
+    /// Reset a sensor's run of failures after a plausible reading.  A
+    /// quarantined sensor stays quarantined until released.
+    pub async fn record_sensor_ok(&self, sensor_id: &str) -> Result<()> {
+        sqlx::query!(
+            "UPDATE sensor_health SET consecutive_failures = 0 WHERE sensor_id = ?",
+            sensor_id
+        )
+        .execute(&self.pool)
+        .await
+        .context("record_sensor_ok failed")?;
+        Ok(())
+    }
+
+    /// Put a quarantined sensor back in service.  Returns `false` if it
+    /// wasn't quarantined.
+    pub async fn release_sensor(&self, sensor_id: &str) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE sensor_health SET consecutive_failures = 0, quarantined_ts = NULL
+            WHERE sensor_id = ? AND quarantined_ts IS NOT NULL
+            "#,
+            sensor_id
+        )
+        .execute(&self.pool)
+        .await
+        .context("release_sensor failed")?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Every sensor that has ever reported an implausible reading.
+    pub async fn list_sensor_health(&self) -> Result<Vec<SensorHealth>> {
+        let rows = sqlx::query_as!(
+            SensorHealth,
+            r#"
+            SELECT sensor_id as "sensor_id!", consecutive_failures, total_failures,
+                   last_failure_ts, quarantined_ts
+            FROM sensor_health
+            ORDER BY sensor_id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("list_sensor_health failed")?;
+        Ok(rows)
+    }
+
+    // ----------------------------
+    // Safety review
+    // ----------------------------
+
+    /// Mark `findings` acknowledged as of `ts`.
+    pub async fn acknowledge_findings(&self, findings: &[Finding], ts: i64) -> Result<()> {
+        let mut tx = self.pool.begin().await.context("begin failed")?;
+        for f in findings {
+            sqlx::query!(
+                r#"
+                INSERT INTO safety_acks (zone_id, code, detail, acked_ts) VALUES (?, ?, ?, ?)
+                ON CONFLICT(zone_id, code, detail) DO NOTHING
+                "#,
+                f.zone_id,
+                f.code,
+                f.detail,
+                ts
+            )
+            .execute(&mut *tx)
+            .await
+            .context("acknowledge_findings failed")?;
+        }
+        tx.commit().await.context("commit failed")?;
+        Ok(())
+    }
+
+    /// Set `acknowledged` on each finding with a stored acknowledgement.
+    pub async fn mark_acknowledged(&self, findings: &mut [Finding]) -> Result<()> {
+        let acks: HashSet<(String, String, String)> = sqlx::query!(
+            r#"SELECT zone_id as "zone_id!", code as "code!", detail as "detail!" FROM safety_acks"#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("mark_acknowledged failed")?
+        .into_iter()
+        .map(|r| (r.zone_id, r.code, r.detail))
+        .collect();
+        for f in findings {
+            f.acknowledged =
+                acks.contains(&(f.zone_id.clone(), f.code.to_string(), f.detail.clone()));
+        }
+        Ok(())
+    }
+
+    // ----------------------------
+    // Flow readings
+    // ----------------------------
+
+    /// Store a flow-meter sample.  Returns `false` for a duplicate
+    /// `(ts, zone_id)`.
+    pub async fn insert_flow_reading(
+        &self,
+        ts: i64,
+        zone_id: &str,
+        lpm: f64,
+        pressure_kpa: Option<f64>,
+    ) -> Result<bool> {
+        let result = self
+            .retry("insert_flow_reading", || {
+                sqlx::query!(
+                    r#"
+                    INSERT INTO flow_readings (ts, zone_id, lpm, pressure_kpa)
+                    VALUES (?, ?, ?, ?)
+                    ON CONFLICT(ts, zone_id) DO NOTHING
+                    "#,
+                    ts,
+                    zone_id,
+                    lpm,
+                    pressure_kpa
+                )
+                .execute(&self.pool)
+            })
+            .await
+            .context("insert_flow_reading failed")?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Store a node's air temperature, humidity and light.  Returns false if the
+    /// node already sent a sample for `ts` (a replay from its buffer).
+    pub async fn insert_climate_reading(
+        &self,
+        ts: i64,
+        node_id: &str,
+        air_temp_c: Option<f64>,
+        humidity_pct: Option<f64>,
+        lux: Option<f64>,
+    ) -> Result<bool> {
+        let result = self
+            .retry("insert_climate_reading", || {
+                sqlx::query!(
+                    r#"
+                    INSERT INTO climate_readings (ts, node_id, air_temp_c, humidity_pct, lux)
+                    VALUES (?, ?, ?, ?, ?)
+                    ON CONFLICT(ts, node_id) DO NOTHING
+                    "#,
+                    ts,
+                    node_id,
+                    air_temp_c,
+                    humidity_pct,
+                    lux
+                )
+                .execute(&self.pool)
+            })
+            .await
+            .context("insert_climate_reading failed")?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// A node's climate samples since `since_ts`, oldest first.
+    pub async fn climate_readings_since(
+        &self,
+        node_id: &str,
+        since_ts: i64,
+    ) -> Result<Vec<ClimateReading>> {
+        let rows = sqlx::query_as!(
+            ClimateReading,
+            r#"
+            SELECT ts as "ts!", air_temp_c, humidity_pct, lux
+            FROM climate_readings
+            WHERE node_id = ? AND ts >= ?
+            ORDER BY ts
+            "#,
+            node_id,
+            since_ts
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("climate_readings_since failed")?;
+        Ok(rows)
+    }
+
+    /// Number of flow samples for a zone in `[from_ts, to_ts]`, and the
+    /// largest flow among them.
+    pub async fn flow_between(
+        &self,
+        zone_id: &str,
+        from_ts: i64,
+        to_ts: i64,
+    ) -> Result<(i64, Option<f64>)> {
+        let row = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as "samples!: i64", MAX(lpm) as "max_lpm: f64"
+            FROM flow_readings
+            WHERE zone_id = ? AND ts >= ? AND ts <= ?
+            "#,
+            zone_id,
+            from_ts,
+            to_ts
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("flow_between failed")?;
+        Ok((row.samples, row.max_lpm))
+    }
+
+    /// Per-day flow averages for a zone since `since_ts`, oldest first.
+    /// Only samples with water flowing (`lpm > 0`) count.
+    pub async fn daily_flow(&self, zone_id: &str, since_ts: i64) -> Result<Vec<DailyFlow>> {
+        let rows = sqlx::query_as!(
+            DailyFlow,
+            r#"
+            SELECT date(ts, 'unixepoch') as "day!: String",
+                   AVG(lpm) as "avg_lpm!: f64",
+                   AVG(pressure_kpa) as "avg_pressure_kpa: f64",
+                   COUNT(*) as "samples!: i64"
+            FROM flow_readings
+            WHERE zone_id = ? AND ts >= ? AND lpm > 0
+            GROUP BY 1
+            ORDER BY 1
+            "#,
+            zone_id,
+            since_ts
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("daily_flow failed")?;
+        Ok(rows)
+    }
+
+    // ----------------------------
+    // Watering events
+    // ----------------------------
+
+    #[tracing::instrument(name = "db.insert_watering_event", skip_all, fields(zone = %zone_id))]
+    pub async fn insert_watering_event(
+        &self,
+        ts_start: i64,
+        ts_end: i64,
+        zone_id: &str,
+        reason: &str,
+        result: &str,
+        details: EventDetails<'_>,
+    ) -> Result<()> {
+        self.retry("insert_watering_event", || {
+            sqlx::query!(
+                r#"
+                INSERT INTO watering_events
+                  (ts_start, ts_end, zone_id, reason, result, planned_sec, triggered_by, ended_by)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+                ts_start,
+                ts_end,
+                zone_id,
+                reason,
+                result,
+                details.planned_sec,
+                details.triggered_by,
+                details.ended_by
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .context("insert_watering_event failed")?;
+        Ok(())
+    }
+
+    /// Pulses and open seconds from the zone's watering events in the 24
+    /// hours before `now_ts`.
+    pub async fn rolling_counters(&self, zone_id: &str, now_ts: i64) -> Result<RollingCounters> {
+        let since = now_ts - ROLLING_WINDOW_SEC;
+        let row = sqlx::query!(
+            r#"
+            SELECT
+              COALESCE(SUM(CASE WHEN ts_start >= ?1 THEN 1 ELSE 0 END), 0) as "pulses!: i64",
+              COALESCE(SUM(MAX(0, MIN(ts_end, ?2) - MAX(ts_start, ?1))), 0) as "open_sec!: i64"
+            FROM watering_events
+            WHERE zone_id = ?3 AND ts_end > ?1
+            "#,
+            since,
+            now_ts,
+            zone_id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("rolling_counters failed")?;
+        Ok(RollingCounters {
+            pulses: row.pulses,
+            open_sec: row.open_sec,
+        })
+    }
+
+    /// Start of the zone's newest watering event with `reason`.
+    pub async fn last_watering_event_ts(&self, zone_id: &str, reason: &str) -> Result<Option<i64>> {
+        let ts = sqlx::query_scalar!(
+            r#"
+            SELECT MAX(ts_start) as "ts: i64"
+            FROM watering_events
+            WHERE zone_id = ? AND reason = ?
+            "#,
+            zone_id,
+            reason
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("last_watering_event_ts failed")?;
+        Ok(ts)
+    }
+
+    pub async fn list_watering_events(
+        &self,
+        zone_id: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<WateringEventRow>> {
+        let mut qb = QueryBuilder::<Sqlite>::new(
+            r#"
+            SELECT e.ts_start, e.ts_end, e.zone_id, e.reason, e.result, e.planned_sec,
+              e.ts_end - e.ts_start AS duration_sec, e.triggered_by, e.ended_by,
+              e.planned_sec * z.flow_lpm / 60.0 AS expected_litres,
+              (SELECT AVG(f.lpm) FROM flow_readings f
+               WHERE f.zone_id = e.zone_id AND f.ts BETWEEN e.ts_start AND e.ts_end)
+                * (e.ts_end - e.ts_start) / 60.0 AS litres,
+              z.flow_lpm
+            FROM watering_events e
+            LEFT JOIN zones z ON z.zone_id = e.zone_id
+            "#,
+        );
+
+        if let Some(zid) = zone_id {
+            qb.push(" WHERE e.zone_id = ");
+            qb.push_bind(zid.to_string());
+        }
+
+        qb.push(" ORDER BY e.ts_start DESC LIMIT ");
+        qb.push_bind(limit);
+        qb.push(" OFFSET ");
+        qb.push_bind(offset);
+
+        let mut rows = qb
+            .build_query_as::<WateringEventRow>()
+            .fetch_all(&self.pool)
+            .await
+            .context("list_watering_events failed")?;
+        for row in &mut rows {
+            row.anomalies = row.find_anomalies();
+        }
+
+        Ok(rows)
+    }
+
+    /// Watering events (flushes excluded) starting between `from` and `to`
+    /// (inclusive, `YYYY-MM-DD`), each with the zone's mean moisture just
+    /// before it opened and over its soak window after it closed (see
+    /// `efficiency`).  Ordered by zone, then start.
+    pub async fn pulse_outcomes(
+        &self,
+        zone_id: Option<&str>,
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<PulseOutcome>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT e.zone_id as "zone_id!",
+                   date(e.ts_start, 'unixepoch') as "day!: String",
+                   e.ts_end - e.ts_start as "open_sec!: i64",
+                   (SELECT AVG(r.moisture)
+                    FROM readings r
+                    JOIN sensors s ON s.sensor_id = r.sensor_id
+                    WHERE s.zone_id = e.zone_id
+                      AND r.ts >= e.ts_start - ? AND r.ts < e.ts_start) as "before: f64",
+                   (SELECT AVG(r.moisture)
+                    FROM readings r
+                    JOIN sensors s ON s.sensor_id = r.sensor_id
+                    WHERE s.zone_id = e.zone_id
+                      AND r.ts >= e.ts_end
+                      AND r.ts <= e.ts_end + MAX(z.soak_min * 60, ?)) as "after: f64"
+            FROM watering_events e
+            JOIN zones z ON z.zone_id = e.zone_id
+            WHERE e.reason != 'flush'
+              AND e.ts_start >= unixepoch(?) AND e.ts_start < unixepoch(?, '+1 day')
+              AND (? IS NULL OR e.zone_id = ?)
+            ORDER BY e.zone_id, e.ts_start
+            "#,
+            efficiency::BEFORE_WINDOW_SEC,
+            efficiency::MIN_AFTER_WINDOW_SEC,
+            from,
+            to,
+            zone_id,
+            zone_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("pulse_outcomes failed")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| PulseOutcome {
+                zone_id: r.zone_id,
+                day: r.day,
+                open_sec: r.open_sec,
+                before: r.before,
+                after: r.after,
+            })
+            .collect())
+    }
+
+    /// A zone's last `limit` watering events (flushes excluded) with the
+    /// same moisture windows as `pulse_outcomes`, oldest first.
+    pub async fn recent_pulse_outcomes(
+        &self,
+        zone_id: &str,
+        limit: i64,
+    ) -> Result<Vec<PulseOutcome>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT e.zone_id as "zone_id!",
+                   date(e.ts_start, 'unixepoch') as "day!: String",
+                   e.ts_end - e.ts_start as "open_sec!: i64",
+                   (SELECT AVG(r.moisture)
+                    FROM readings r
+                    JOIN sensors s ON s.sensor_id = r.sensor_id
+                    WHERE s.zone_id = e.zone_id
+                      AND r.ts >= e.ts_start - ? AND r.ts < e.ts_start) as "before: f64",
+                   (SELECT AVG(r.moisture)
+                    FROM readings r
+                    JOIN sensors s ON s.sensor_id = r.sensor_id
+                    WHERE s.zone_id = e.zone_id
+                      AND r.ts >= e.ts_end
+                      AND r.ts <= e.ts_end + MAX(z.soak_min * 60, ?)) as "after: f64"
+            FROM watering_events e
+            JOIN zones z ON z.zone_id = e.zone_id
+            WHERE e.reason != 'flush' AND e.zone_id = ?
+            ORDER BY e.ts_start DESC, e.rowid DESC
+            LIMIT ?
+            "#,
+            efficiency::BEFORE_WINDOW_SEC,
+            efficiency::MIN_AFTER_WINDOW_SEC,
+            zone_id,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("recent_pulse_outcomes failed")?;
+
+        Ok(rows
+            .into_iter()
+            .rev()
+            .map(|r| PulseOutcome {
+                zone_id: r.zone_id,
+                day: r.day,
+                open_sec: r.open_sec,
+                before: r.before,
+                after: r.after,
+            })
+            .collect())
+    }
+
+    // ----------------------------
+    // Scheduler decisions (audit log)
+    // ----------------------------
+
+    /// Store one scheduler tick's decisions in a single transaction.
+    #[tracing::instrument(name = "db.insert_scheduler_decisions", skip_all)]
+    pub async fn insert_scheduler_decisions(&self, decisions: &[SchedulerDecision]) -> Result<()> {
+        self.retry("insert_scheduler_decisions", || async {
+            let mut tx = self.pool.begin().await?;
+            for d in decisions {
+                sqlx::query!(
+                    r#"
+                    INSERT INTO scheduler_decisions
+                      (ts, zone_id, phase, avg_moisture, blocked_by, action, detail)
+                    VALUES (?, ?, ?, ?, ?, ?, ?)
+                    "#,
+                    d.ts,
+                    d.zone_id,
+                    d.phase,
+                    d.avg_moisture,
+                    d.blocked_by,
+                    d.action,
+                    d.detail
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+            tx.commit().await
+        })
+        .await
+        .context("insert_scheduler_decisions failed")?;
+        Ok(())
+    }
+
+    /// Scheduler decisions newest first, optionally filtered by zone and an
+    /// inclusive `[from_ts, to_ts]` range.
+    pub async fn list_scheduler_decisions(
+        &self,
+        zone_id: Option<&str>,
+        from_ts: Option<i64>,
+        to_ts: Option<i64>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<SchedulerDecision>> {
+        let mut qb = QueryBuilder::<Sqlite>::new(
+            "SELECT ts, zone_id, phase, avg_moisture, blocked_by, action, detail \
+             FROM scheduler_decisions WHERE 1=1",
+        );
+        if let Some(zid) = zone_id {
+            qb.push(" AND zone_id = ");
+            qb.push_bind(zid.to_string());
+        }
+        if let Some(from) = from_ts {
+            qb.push(" AND ts >= ");
+            qb.push_bind(from);
+        }
+        if let Some(to) = to_ts {
+            qb.push(" AND ts <= ");
+            qb.push_bind(to);
+        }
+        qb.push(" ORDER BY ts DESC, id DESC LIMIT ");
+        qb.push_bind(limit);
+        qb.push(" OFFSET ");
+        qb.push_bind(offset);
+
+        let rows = qb
+            .build_query_as::<SchedulerDecision>()
+            .fetch_all(&self.pool)
+            .await
+            .context("list_scheduler_decisions failed")?;
+        Ok(rows)
+    }
+
+    /// Delete scheduler decisions older than the given number of days.
+    pub async fn prune_scheduler_decisions(&self, retention_days: i64) -> Result<u64> {
+        let cutoff = clock::now_unix() - (retention_days * 86400);
+        let result = sqlx::query!("DELETE FROM scheduler_decisions WHERE ts < ?", cutoff)
+            .execute(&self.pool)
+            .await
+            .context("prune_scheduler_decisions failed")?;
+        Ok(result.rows_affected())
+    }
+
+    // ----------------------------
+    // Stored log records (see `logs`)
+    // ----------------------------
+
+    pub async fn insert_logs(&self, records: &[LogRecord]) -> Result<()> {
+        self.retry("insert_logs", || async {
+            let mut tx = self.pool.begin().await?;
+            for r in records {
+                let fields = serde_json::Value::Object(r.fields.clone()).to_string();
+                sqlx::query!(
+                    "INSERT INTO logs (ts, level, target, message, fields) VALUES (?, ?, ?, ?, ?)",
+                    r.ts,
+                    r.level,
+                    r.target,
+                    r.message,
+                    fields
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+            tx.commit().await
+        })
+        .await
+        .context("insert_logs failed")?;
+        Ok(())
+    }
+
+    /// Stored log records, newest first, optionally of one `level` and
+    /// within an inclusive unix-seconds range.
+    pub async fn list_logs(
+        &self,
+        level: Option<&str>,
+        from_ts: Option<i64>,
+        to_ts: Option<i64>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<LogRecord>> {
+        let mut qb = QueryBuilder::<Sqlite>::new(
+            "SELECT ts, level, target, message, fields FROM logs WHERE 1=1",
+        );
+        if let Some(level) = level {
+            qb.push(" AND level = ");
+            qb.push_bind(level.to_ascii_uppercase());
+        }
+        if let Some(from) = from_ts {
+            qb.push(" AND ts >= ");
+            qb.push_bind(from);
+        }
+        if let Some(to) = to_ts {
+            qb.push(" AND ts <= ");
+            qb.push_bind(to);
+        }
+        qb.push(" ORDER BY ts DESC, id DESC LIMIT ");
+        qb.push_bind(limit);
+        qb.push(" OFFSET ");
+        qb.push_bind(offset);
+
+        let rows: Vec<(i64, String, String, String, String)> = qb
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .context("list_logs failed")?;
+        Ok(rows
+            .into_iter()
+            .map(|(ts, level, target, message, fields)| LogRecord {
+                ts,
+                level,
+                target,
+                message,
+                fields: serde_json::from_str(&fields).unwrap_or_default(),
+            })
+            .collect())
+    }
+
+    /// Delete log records older than the given number of days.
+    pub async fn prune_logs(&self, retention_days: i64) -> Result<u64> {
+        let cutoff = OffsetDateTime::now_utc().unix_timestamp() - (retention_days * 86400);
+        let result = sqlx::query!("DELETE FROM logs WHERE ts < ?", cutoff)
+            .execute(&self.pool)
+            .await
+            .context("prune_logs failed")?;
+        Ok(result.rows_affected())
+    }
+
+    // ----------------------------
+    // Open valves (crash recovery)
+    // ----------------------------
+
+    /// Record an open valve with who opened it and for how long, for
+    /// [`Db::close_open_valves`].
+    pub async fn mark_valve_open(
+        &self,
+        zone_id: &str,
+        opened_ts: i64,
+        triggered_by: &str,
+        planned_sec: Option<i64>,
+    ) -> Result<()> {
+        self.retry("mark_valve_open", || {
+            sqlx::query!(
+                r#"
+                INSERT INTO open_valves (zone_id, opened_ts, triggered_by, planned_sec)
+                VALUES (?, ?, ?, ?)
+                ON CONFLICT(zone_id) DO NOTHING
+                "#,
+                zone_id,
+                opened_ts,
+                triggered_by,
+                planned_sec
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .context("mark_valve_open failed")?;
+        Ok(())
+    }
+
+    pub async fn mark_valve_closed(&self, zone_id: &str) -> Result<()> {
+        self.retry("mark_valve_closed", || {
+            sqlx::query!("DELETE FROM open_valves WHERE zone_id = ?", zone_id).execute(&self.pool)
+        })
+        .await
+        .context("mark_valve_closed failed")?;
+        Ok(())
+    }
+
+    /// Close out every valve still recorded as open: count its open time
+    /// (up to `now_ts`) towards today's counters, record a watering event
+    /// ended by `ended_by`, and clear the row.  Returns
+    /// `(zone_id, duration_secs)` per valve.
+    pub async fn close_open_valves(
+        &self,
+        now_ts: i64,
+        reason: &str,
+        result: &str,
+        ended_by: &str,
+    ) -> Result<Vec<(String, i64)>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT zone_id as "zone_id!", opened_ts, triggered_by, planned_sec
+            FROM open_valves ORDER BY zone_id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("close_open_valves: load failed")?;
+
+        let today = Self::today_yyyy_mm_dd();
+        let mut closed = Vec::with_capacity(rows.len());
+        for r in rows {
+            let duration = (now_ts - r.opened_ts).max(0);
+            self.add_open_seconds(&today, &r.zone_id, duration).await?;
+            let details = EventDetails {
+                planned_sec: r.planned_sec,
+                triggered_by: r.triggered_by.as_deref(),
+                ended_by: Some(ended_by),
+            };
+            self.insert_watering_event(r.opened_ts, now_ts, &r.zone_id, reason, result, details)
+                .await?;
+            self.mark_valve_closed(&r.zone_id).await?;
+            closed.push((r.zone_id, duration));
+        }
+        Ok(closed)
+    }
+
+    // ----------------------------
+    // Daily counters (safety limits)
+    // ----------------------------
+
+    pub fn today_yyyy_mm_dd() -> String {
+        clock::today()
+    }
+
+    pub async fn get_daily_counters(&self, day: &str, zone_id: &str) -> Result<DailyCounters> {
+        let row = sqlx::query!(
+            r#"
+            SELECT day, zone_id, open_sec, pulses
+            FROM zone_daily_counters
+            WHERE day = ? AND zone_id = ?
+            "#,
+            day,
+            zone_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("get_daily_counters failed")?;
+
+        Ok(match row {
+            Some(r) => DailyCounters {
+                day: r.day,
+                zone_id: r.zone_id,
+                open_sec: r.open_sec,
+                pulses: r.pulses,
+            },
+            None => DailyCounters {
+                day: day.to_string(),
+                zone_id: zone_id.to_string(),
+                open_sec: 0,
+                pulses: 0,
+            },
+        })
+    }
+
+    /// Aggregate daily counters between `from` and `to` (inclusive,
+    /// `YYYY-MM-DD`) into day/week/month buckets, optionally for one zone.
+    pub async fn usage_report(
+        &self,
+        zone_id: Option<&str>,
+        from: &str,
+        to: &str,
+        bucket: UsageBucket,
+    ) -> Result<Vec<UsageRow>> {
+        let fmt = bucket.strftime_format();
+        let rows = sqlx::query!(
+            r#"
+            SELECT c.zone_id as "zone_id!",
+                   strftime(?, c.day) as "period!: String",
+                   SUM(c.open_sec) as "open_sec!: i64",
+                   SUM(c.pulses) as "pulses!: i64",
+                   SUM(c.open_sec) * COALESCE(z.flow_lpm, z.emitter_flow_lph / 60.0) / 60.0
+                     as "litres: f64"
+            FROM zone_daily_counters c
+            LEFT JOIN zones z ON z.zone_id = c.zone_id
+            WHERE c.day >= ? AND c.day <= ?
+              AND (? IS NULL OR c.zone_id = ?)
+            GROUP BY c.zone_id, 2
+            ORDER BY c.zone_id, 2
+            "#,
+            fmt,
+            from,
+            to,
+            zone_id,
+            zone_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("usage_report failed")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| UsageRow {
+                zone_id: r.zone_id,
+                period: r.period,
+                open_sec: r.open_sec,
+                pulses: r.pulses,
+                litres: r.litres,
+            })
+            .collect())
+    }
+
+    /// Watering totals for one zone between `from` and `to` (inclusive,
+    /// `YYYY-MM-DD`).
+    pub async fn usage_totals(&self, zone_id: &str, from: &str, to: &str) -> Result<UsageTotals> {
+        let r = sqlx::query!(
+            r#"
+            SELECT COALESCE(SUM(c.open_sec), 0) as "open_sec!: i64",
+                   COALESCE(SUM(c.pulses), 0) as "pulses!: i64",
+                   COALESCE(SUM(c.open_sec), 0) * COALESCE(z.flow_lpm, z.emitter_flow_lph / 60.0)
+                     / 60.0 as "litres: f64",
+                   COUNT(CASE WHEN c.pulses > 0 THEN 1 END) as "watering_days!: i64"
+            FROM zones z
+            LEFT JOIN zone_daily_counters c
+              ON c.zone_id = z.zone_id AND c.day >= ? AND c.day <= ?
+            WHERE z.zone_id = ?
+            "#,
+            from,
+            to,
+            zone_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("usage_totals failed")?;
+
+        Ok(r.map(|r| UsageTotals {
+            open_sec: r.open_sec,
+            pulses: r.pulses,
+            litres: r.litres,
+            watering_days: r.watering_days,
+        })
+        .unwrap_or_default())
+    }
+
+    pub async fn ensure_daily_row(&self, day: &str, zone_id: &str) -> Result<()> {
+        self.retry("ensure_daily_row", || {
+            sqlx::query!(
+                r#"
+                INSERT INTO zone_daily_counters (day, zone_id, open_sec, pulses)
+                VALUES (?, ?, 0, 0)
+                ON CONFLICT(day, zone_id) DO NOTHING
+                "#,
+                day,
+                zone_id
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .context("ensure_daily_row failed")?;
+        Ok(())
+    }
+
+    /// Open seconds per zone for one day (zones with no row are absent).
+    pub async fn open_sec_by_zone(&self, day: &str) -> Result<HashMap<String, i64>> {
+        let rows = sqlx::query!(
+            "SELECT zone_id, open_sec FROM zone_daily_counters WHERE day = ?",
+            day
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("open_sec_by_zone failed")?;
+        Ok(rows.into_iter().map(|r| (r.zone_id, r.open_sec)).collect())
+    }
+
+    #[tracing::instrument(name = "db.add_open_seconds", skip_all, fields(zone = %zone_id))]
+    pub async fn add_open_seconds(&self, day: &str, zone_id: &str, delta: i64) -> Result<()> {
+        self.ensure_daily_row(day, zone_id).await?;
+        self.retry("add_open_seconds", || {
+            sqlx::query!(
+                r#"
+                UPDATE zone_daily_counters
+                SET open_sec = open_sec + ?
+                WHERE day = ? AND zone_id = ?
+                "#,
+                delta,
+                day,
+                zone_id
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .context("add_open_seconds failed")?;
+        self.retry("add_open_seconds", || {
+            sqlx::query!(
+                r#"
+                INSERT INTO zone_odometer (zone_id, open_sec) VALUES (?, ?)
+                ON CONFLICT(zone_id) DO UPDATE SET open_sec = open_sec + excluded.open_sec
+                "#,
+                zone_id,
+                delta
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .context("add_open_seconds: odometer update failed")?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "db.add_pulse", skip_all, fields(zone = %zone_id))]
+    pub async fn add_pulse(&self, day: &str, zone_id: &str, delta: i64) -> Result<()> {
+        self.ensure_daily_row(day, zone_id).await?;
+        self.retry("add_pulse", || {
+            sqlx::query!(
+                r#"
+                UPDATE zone_daily_counters
+                SET pulses = pulses + ?
+                WHERE day = ? AND zone_id = ?
+                "#,
+                delta,
+                day,
+                zone_id
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .context("add_pulse failed")?;
+        self.retry("add_pulse", || {
+            sqlx::query!(
+                r#"
+                INSERT INTO zone_odometer (zone_id, actuations) VALUES (?, ?)
+                ON CONFLICT(zone_id) DO UPDATE SET actuations = actuations + excluded.actuations
+                "#,
+                zone_id,
+                delta
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .context("add_pulse: odometer update failed")?;
+        Ok(())
+    }
+
+    /// Every zone's odometer (zones never actuated are absent).
+    pub async fn list_odometers(&self) -> Result<HashMap<String, ZoneOdometer>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT zone_id as "zone_id!", actuations, open_sec,
+                   actuations - serviced_actuations as "actuations_since_service!: i64",
+                   open_sec - serviced_open_sec as "open_sec_since_service!: i64",
+                   serviced_ts, service_due_ts
+            FROM zone_odometer
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("list_odometers failed")?;
+        Ok(rows
+            .into_iter()
+            .map(|r| {
+                (
+                    r.zone_id,
+                    ZoneOdometer {
+                        actuations: r.actuations,
+                        open_sec: r.open_sec,
+                        actuations_since_service: r.actuations_since_service,
+                        open_sec_since_service: r.open_sec_since_service,
+                        serviced_ts: r.serviced_ts,
+                        service_due_ts: r.service_due_ts,
+                    },
+                )
+            })
+            .collect())
+    }
+
+    pub async fn get_odometer(&self, zone_id: &str) -> Result<ZoneOdometer> {
+        let row = sqlx::query_as!(
+            ZoneOdometer,
+            r#"
+            SELECT actuations, open_sec,
+                   actuations - serviced_actuations as "actuations_since_service!: i64",
+                   open_sec - serviced_open_sec as "open_sec_since_service!: i64",
+                   serviced_ts, service_due_ts
+            FROM zone_odometer
+            WHERE zone_id = ?
+            "#,
+            zone_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("get_odometer failed")?;
+        Ok(row.unwrap_or_default())
+    }
+
+    /// Flag the zone's valve as due for service.  Returns false if it
+    /// already was.
+    pub async fn mark_service_due(&self, zone_id: &str, ts: i64) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE zone_odometer SET service_due_ts = ?
+            WHERE zone_id = ? AND service_due_ts IS NULL
+            "#,
+            ts,
+            zone_id
+        )
+        .execute(&self.pool)
+        .await
+        .context("mark_service_due failed")?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Record that the zone's valve was serviced: usage since service
+    /// starts again from zero.
+    pub async fn record_valve_service(&self, zone_id: &str, ts: i64) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO zone_odometer (zone_id, serviced_ts) VALUES (?, ?)
+            ON CONFLICT(zone_id) DO UPDATE SET
+              serviced_actuations = actuations,
+              serviced_open_sec = open_sec,
+              serviced_ts = excluded.serviced_ts,
+              service_due_ts = NULL
+            "#,
+            zone_id,
+            ts
+        )
+        .execute(&self.pool)
+        .await
+        .context("record_valve_service failed")?;
+        Ok(())
+    }
+
+    /// Quick connectivity check — runs a trivial query.
+    pub async fn health_check(&self) -> Result<()> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .context("db health check failed")?;
+        Ok(())
+    }
+
+    /// Verify the database accepts writes.  `health_check` only reads, which
+    /// keeps succeeding on a full disk or a read-only remount.
+    pub async fn write_probe(&self) -> Result<()> {
+        let now = OffsetDateTime::now_utc().unix_timestamp().to_string();
+        sqlx::query!(
+            r#"
+            INSERT INTO hub_meta (key, value) VALUES ('write_probe', ?)
+            ON CONFLICT(key) DO UPDATE SET value=excluded.value
+            "#,
+            now
+        )
+        .execute(&self.pool)
+        .await
+        .context("db write probe failed")?;
+        Ok(())
+    }
+
+    /// Every zone's persisted scheduler state.
+    pub async fn load_scheduler_states(&self) -> Result<Vec<SchedulerZoneState>> {
+        sqlx::query_as!(
+            SchedulerZoneState,
+            r#"
+            SELECT zone_id as "zone_id!", phase, started_ts, until_ts, extended_sec
+            FROM scheduler_zone_state
+            ORDER BY zone_id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("load_scheduler_states failed")
+    }
+
+    pub async fn save_scheduler_state(&self, state: &SchedulerZoneState) -> Result<()> {
+        self.retry("save_scheduler_state", || {
+            sqlx::query!(
+                r#"
+                INSERT INTO scheduler_zone_state (zone_id, phase, started_ts, until_ts, extended_sec)
+                VALUES (?, ?, ?, ?, ?)
+                ON CONFLICT(zone_id) DO UPDATE SET
+                  phase=excluded.phase,
+                  started_ts=excluded.started_ts,
+                  until_ts=excluded.until_ts,
+                  extended_sec=excluded.extended_sec
+                "#,
+                state.zone_id,
+                state.phase,
+                state.started_ts,
+                state.until_ts,
+                state.extended_sec
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .context("save_scheduler_state failed")?;
+        Ok(())
+    }
+
+    /// Forget a zone's scheduler state (it went idle).
+    pub async fn clear_scheduler_state(&self, zone_id: &str) -> Result<()> {
+        self.retry("clear_scheduler_state", || {
+            sqlx::query!(
+                "DELETE FROM scheduler_zone_state WHERE zone_id = ?",
+                zone_id
+            )
+            .execute(&self.pool)
+        })
+        .await
+        .context("clear_scheduler_state failed")?;
+        Ok(())
+    }
+
+    /// When the emergency stop was latched (unix seconds), if it still is.
+    pub async fn load_estop_latch(&self) -> Result<Option<i64>> {
+        let value = sqlx::query_scalar!("SELECT value FROM hub_meta WHERE key = 'estop_latched'")
+            .fetch_optional(&self.pool)
+            .await
+            .context("load_estop_latch failed")?;
+        Ok(value.and_then(|v| v.parse().ok()))
+    }
+
+    /// Persist the emergency-stop latch (`None` clears it).
+    pub async fn save_estop_latch(&self, since: Option<i64>) -> Result<()> {
+        match since {
+            Some(ts) => {
+                let value = ts.to_string();
+                sqlx::query!(
+                    r#"
+                    INSERT INTO hub_meta (key, value) VALUES ('estop_latched', ?)
+                    ON CONFLICT(key) DO UPDATE SET value=excluded.value
+                    "#,
+                    value
+                )
+                .execute(&self.pool)
+                .await
+            }
+            None => {
+                sqlx::query!("DELETE FROM hub_meta WHERE key = 'estop_latched'")
+                    .execute(&self.pool)
+                    .await
+            }
+        }
+        .context("save_estop_latch failed")?;
+        Ok(())
+    }
+
+    /// The retention policy set over the API, if any.
+    pub async fn load_retention_policy(&self) -> Result<Option<RetentionPolicy>> {
+        let value = sqlx::query_scalar!("SELECT value FROM hub_meta WHERE key = 'retention'")
+            .fetch_optional(&self.pool)
+            .await
+            .context("load_retention_policy failed")?;
+        value
+            .map(|v| serde_json::from_str(&v).context("stored retention policy is invalid"))
+            .transpose()
+    }
+
+    pub async fn save_retention_policy(&self, policy: &RetentionPolicy) -> Result<()> {
+        let value = serde_json::to_string(policy)?;
+        sqlx::query!(
+            r#"
+            INSERT INTO hub_meta (key, value) VALUES ('retention', ?)
+            ON CONFLICT(key) DO UPDATE SET value=excluded.value
+            "#,
+            value
+        )
+        .execute(&self.pool)
+        .await
+        .context("save_retention_policy failed")?;
+        Ok(())
+    }
+
+    /// Create a consistent backup of the database at `dest_path`.
+    ///
+    /// Uses SQLite `VACUUM INTO` to produce an atomic, defragmented copy
+    /// safe to call while the database is in active use.  The backup is
+    /// written to a temporary file and atomically renamed so a crash
+    /// mid-write cannot corrupt the previous good backup.
+    pub async fn backup(&self, dest_path: &str) -> Result<()> {
+        // Include readings still waiting for a batched write.
+        if let Err(e) = self.flush_readings().await {
+            tracing::warn!("reading flush before backup failed: {e:#}");
+        }
+
+        // Ensure the destination directory exists.
+        if let Some(parent) = std::path::Path::new(dest_path).parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .with_context(|| format!("create backup dir {}", parent.display()))?;
+        }
+
+        let tmp_path = format!("{dest_path}.tmp");
+
+        // Remove any leftover temp file from a previous failed backup.
+        let _ = tokio::fs::remove_file(&tmp_path).await;
+
+        // VACUUM INTO produces a defragmented, consistent snapshot.
+        // The path must be a SQL string literal (not a bind parameter).
+        let escaped = tmp_path.replace('\'', "''");
+        sqlx::query(&format!("VACUUM INTO '{escaped}'"))
+            .execute(&self.pool)
+            .await
+            .with_context(|| format!("VACUUM INTO '{tmp_path}' failed"))?;
+
+        // Atomically replace the previous backup.
+        tokio::fs::rename(&tmp_path, dest_path)
+            .await
+            .with_context(|| format!("rename '{tmp_path}' -> '{dest_path}' failed"))?;
+
+        Ok(())
+    }
+
+    /// Bring the existing backup at `dest_path` up to date in place,
+    /// writing only the rows that changed since it was taken.  Unlike
+    /// [`Db::backup`] this doesn't rewrite the whole file, so it spares the
+    /// SD card on large databases; the backup stays a plain SQLite file and
+    /// is updated in one transaction, so a crash leaves the previous state.
+    ///
+    /// Returns `false` without touching anything when there is no backup
+    /// yet or its schema differs (migrations ran since): take a full one.
+    /// The live database must be file-backed, as for [`Db::restore_from`].
+    pub async fn backup_incremental(&self, dest_path: &str) -> Result<bool> {
+        if let Err(e) = self.flush_readings().await {
+            tracing::warn!("reading flush before backup failed: {e:#}");
+        }
+        if !tokio::fs::try_exists(dest_path).await.unwrap_or(false) {
+            return Ok(false);
+        }
+
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .context("backup: acquire connection failed")?;
+        sqlx::query("ATTACH DATABASE ? AS backup")
+            .bind(dest_path)
+            .execute(&mut *conn)
+            .await
+            .with_context(|| format!("backup: attach '{dest_path}' failed"))?;
+
+        let synced = sync_attached_backup(&mut conn).await;
+
+        // Always detach: the connection goes back to the pool.  If that
+        // fails, close it instead so the next backup can attach again.
+        if let Err(e) = sqlx::query("DETACH DATABASE backup")
+            .execute(&mut *conn)
+            .await
+        {
+            drop(conn.detach());
+            return Err(e).context("backup: detach failed");
+        }
+        synced
+    }
+
+    /// Replace the contents of this database with the backup at
+    /// `backup_path`, returning the number of rows restored.
+    ///
+    /// The backup is copied to a staging file and migrated there first, so
+    /// backups taken before later schema changes restore cleanly and a
+    /// corrupt or foreign file fails before anything is touched.  The rows
+    /// are then swapped in table by table inside one transaction on the
+    /// live database, which keeps every `Db` clone (and its pool) valid.
+    /// The live database must be file-backed: an in-memory connection
+    /// attaches files as empty in-memory databases.
+    pub async fn restore_from(&self, backup_path: &str) -> Result<i64> {
+        let staging = format!("{backup_path}.restore");
+        let _ = tokio::fs::remove_file(&staging).await;
+        tokio::fs::copy(backup_path, &staging)
+            .await
+            .with_context(|| format!("copy backup '{backup_path}' -> '{staging}'"))?;
+
+        let result = self.restore_from_staged(&staging).await;
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = tokio::fs::remove_file(format!("{staging}{suffix}")).await;
+        }
+        result
+    }
+
+    async fn restore_from_staged(&self, staging: &str) -> Result<i64> {
+        let staged = Db::connect(&format!("sqlite:{staging}"))
+            .await
+            .context("open backup")?;
+        staged.migrate().await.context("migrate backup")?;
+        staged.pool.close().await;
+
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .context("restore: acquire connection failed")?;
+        sqlx::query("ATTACH DATABASE ? AS restore")
+            .bind(staging)
+            .execute(&mut *conn)
+            .await
+            .context("restore: attach failed")?;
+
+        let copied = copy_attached_tables(&mut conn).await;
+
+        // Always detach: the connection goes back to the pool.
+        sqlx::query("DETACH DATABASE restore")
+            .execute(&mut *conn)
+            .await
+            .context("restore: detach failed")?;
+        copied
+    }
+}
+
+/// Replace every table in `main` with its counterpart in the attached
+/// `restore` database (same schema, both fully migrated).
+async fn copy_attached_tables(conn: &mut sqlx::SqliteConnection) -> Result<i64> {
+    let tables: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM main.sqlite_master \
+         WHERE type = 'table' AND name NOT LIKE 'sqlite_%' AND name <> '_sqlx_migrations' \
+         ORDER BY name",
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .context("restore: list tables failed")?;
+
+    let mut tx = conn.begin().await.context("restore: begin failed")?;
+    // Parents and children are replaced in arbitrary order; the constraints
+    // are checked once, at commit.
+    sqlx::query("PRAGMA defer_foreign_keys = ON")
+        .execute(&mut *tx)
+        .await
+        .context("restore: defer_foreign_keys failed")?;
+
+    let mut rows = 0;
+    for table in &tables {
+        let columns: Vec<String> =
+            sqlx::query_scalar("SELECT name FROM pragma_table_info(?, 'main') ORDER BY cid")
+                .bind(table)
+                .fetch_all(&mut *tx)
+                .await
+                .with_context(|| format!("restore: columns of {table} failed"))?;
+        let columns = columns
+            .iter()
+            .map(|c| format!("\"{c}\""))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        sqlx::query(&format!("DELETE FROM main.\"{table}\""))
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("restore: clear {table} failed"))?;
+        rows += sqlx::query(&format!(
+            "INSERT INTO main.\"{table}\" ({columns}) SELECT {columns} FROM restore.\"{table}\""
+        ))
+        .execute(&mut *tx)
+        .await
+        .with_context(|| format!("restore: copy {table} failed"))?
+        .rows_affected() as i64;
+    }
+
+    tx.commit().await.context("restore: commit failed")?;
+    Ok(rows)
+}
+
+/// Make the attached `backup` database match `main`, table by table and
+/// row by row (matched on rowid).  Returns `false` if the schemas differ.
+async fn sync_attached_backup(conn: &mut sqlx::SqliteConnection) -> Result<bool> {
+    // Keep every change in the backup file itself, durably: it is copied
+    // as a single file on restore.
+    for pragma in [
+        "PRAGMA backup.journal_mode = DELETE",
+        "PRAGMA backup.synchronous = FULL",
+    ] {
+        sqlx::query(pragma)
+            .execute(&mut *conn)
+            .await
+            .with_context(|| format!("backup: {pragma} failed"))?;
+    }
+
+    let schema = |db: &str| {
+        format!("SELECT type, name, tbl_name, sql FROM {db}.sqlite_master ORDER BY type, name")
+    };
+    let live: Vec<(String, String, String, Option<String>)> = sqlx::query_as(&schema("main"))
+        .fetch_all(&mut *conn)
+        .await
+        .context("backup: read schema failed")?;
+    let backed_up: Vec<(String, String, String, Option<String>)> =
+        sqlx::query_as(&schema("backup"))
+            .fetch_all(&mut *conn)
+            .await
+            .context("backup: read backup schema failed")?;
+    if live != backed_up {
+        return Ok(false);
+    }
+
+    let mut tx = conn.begin().await.context("backup: begin failed")?;
+    sqlx::query("PRAGMA defer_foreign_keys = ON")
+        .execute(&mut *tx)
+        .await
+        .context("backup: defer_foreign_keys failed")?;
+
+    for (_, table, _, sql) in live.iter().filter(|(ty, ..)| ty == "table") {
+        let columns: Vec<String> =
+            sqlx::query_scalar("SELECT name FROM pragma_table_info(?, 'main') ORDER BY cid")
+                .bind(table)
+                .fetch_all(&mut *tx)
+                .await
+                .with_context(|| format!("backup: columns of {table} failed"))?;
+        let list = |prefix: &str| {
+            columns
+                .iter()
+                .map(|c| format!("{prefix}\"{c}\""))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let statements = if sql
+            .as_deref()
+            .is_some_and(|s| s.to_uppercase().contains("WITHOUT ROWID"))
+        {
+            // No rowid to match rows on: copy the table whole.
+            vec![
+                format!("DELETE FROM backup.\"{table}\""),
+                format!(
+                    "INSERT INTO backup.\"{table}\" ({}) SELECT {} FROM main.\"{table}\"",
+                    list(""),
+                    list("")
+                ),
+            ]
+        } else {
+            // Correlated lookups by rowid rather than NOT IN / EXCEPT, which
+            // would build temporary indexes over the whole table.
+            let unchanged = columns
+                .iter()
+                .map(|c| format!("b.\"{c}\" IS m.\"{c}\""))
+                .collect::<Vec<_>>()
+                .join(" AND ");
+            vec![
+                format!(
+                    "DELETE FROM backup.\"{table}\" WHERE NOT EXISTS \
+                     (SELECT 1 FROM main.\"{table}\" AS m WHERE m.rowid = backup.\"{table}\".rowid)"
+                ),
+                format!(
+                    "INSERT OR REPLACE INTO backup.\"{table}\" (rowid, {}) \
+                     SELECT m.rowid, {} FROM main.\"{table}\" AS m WHERE NOT EXISTS \
+                     (SELECT 1 FROM backup.\"{table}\" AS b WHERE b.rowid = m.rowid AND {unchanged})",
+                    list(""),
+                    list("m.")
+                ),
+            ]
+        };
+        for statement in statements {
+            sqlx::query(&statement)
+                .execute(&mut *tx)
+                .await
+                .with_context(|| format!("backup: sync {table} failed"))?;
+        }
+    }
+
+    tx.commit().await.context("backup: commit failed")?;
+    Ok(true)
+}
+
+// ---------------------------------------------------------------------------
+// Backup / restore helpers (SD card wear mitigation)
+// ---------------------------------------------------------------------------
+
+/// Extract the filesystem path from a SQLite connection URL.
+///
+/// Returns `None` for in-memory databases or non-sqlite URLs.
+pub fn db_file_path(db_url: &str) -> Option<String> {
+    let stripped = db_url.strip_prefix("sqlite:")?;
+    if stripped.starts_with(":memory:") || stripped.is_empty() {
+        return None;
+    }
+    let path = stripped.split('?').next().unwrap_or(stripped);
+    if path.is_empty() {
+        return None;
+    }
+    Some(path.to_string())
+}
+
+/// Restore a database backup to the working path if the working DB does
+/// not exist or is empty (e.g. after a tmpfs reboot).
+///
+/// Call **before** [`Db::connect`] when using a RAM-backed working directory.
+/// Returns `true` if a restore was performed.
+pub fn restore_from_backup(working_path: &str, backup_path: &str) -> Result<bool> {
+    let backup = std::path::Path::new(backup_path);
+    if !backup.exists() {
+        tracing::info!(
+            backup_path,
+            "no backup file found — starting with fresh database"
+        );
+        return Ok(false);
+    }
+
+    let working = std::path::Path::new(working_path);
+    let needs_restore =
+        !working.exists() || working.metadata().map(|m| m.len() == 0).unwrap_or(true);
+
+    if needs_restore {
+        if let Some(parent) = working.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("create dir {}", parent.display()))?;
+        }
+        std::fs::copy(backup, working)
+            .with_context(|| format!("restore backup '{backup_path}' -> '{working_path}'"))?;
+        tracing::info!(backup_path, working_path, "database restored from backup");
+        Ok(true)
+    } else {
+        tracing::debug!(working_path, "working database exists — skipping restore");
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // -- compute_moisture -----------------------------------------------
+
+    #[test]
+    fn compute_moisture_mid_range() {
+        let m = compute_moisture(19000, 26000, 12000);
+        assert!((m - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn compute_moisture_bone_dry() {
+        let m = compute_moisture(26000, 26000, 12000);
+        assert!((m - 0.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn compute_moisture_saturated() {
+        let m = compute_moisture(12000, 26000, 12000);
+        assert!((m - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn compute_moisture_clamped_below() {
+        let m = compute_moisture(30000, 26000, 12000);
+        assert_eq!(m, 0.0);
+    }
+
+    #[test]
+    fn compute_moisture_clamped_above() {
+        let m = compute_moisture(5000, 26000, 12000);
+        assert_eq!(m, 1.0);
+    }
+
+    #[test]
+    fn compute_moisture_degenerate_calibration() {
+        let m = compute_moisture(20000, 15000, 15000);
+        assert_eq!(m, 0.0);
+    }
+
+    // -- is_reading_plausible -------------------------------------------
+
+    #[test]
+    fn plausible_reading_in_range() {
+        assert!(is_reading_plausible(
+            20000,
+            26000,
+            12000,
+            SENSOR_FAILURE_MARGIN
+        ));
+    }
+
+    #[test]
+    fn plausible_reading_at_dry() {
+        assert!(is_reading_plausible(
+            26000,
+            26000,
+            12000,
+            SENSOR_FAILURE_MARGIN
+        ));
+    }
+
+    #[test]
+    fn plausible_reading_at_wet() {
+        assert!(is_reading_plausible(
+            12000,
+            26000,
+            12000,
+            SENSOR_FAILURE_MARGIN
+        ));
+    }
+
+    #[test]
+    fn plausible_reading_slightly_beyond_range() {
+        // Within the margin — still plausible
+        assert!(is_reading_plausible(
+            28000,
+            26000,
+            12000,
+            SENSOR_FAILURE_MARGIN
+        ));
+        assert!(is_reading_plausible(
+            10000,
+            26000,
+            12000,
+            SENSOR_FAILURE_MARGIN
+        ));
+    }
+
+    #[test]
+    fn implausible_reading_disconnected_sensor() {
+        // ADS1115 open input reads ~32767
+        assert!(!is_reading_plausible(
+            32767,
+            26000,
+            12000,
+            SENSOR_FAILURE_MARGIN
+        ));
+    }
+
+    #[test]
+    fn implausible_reading_shorted_sensor() {
+        // Shorted to ground reads ~0
+        assert!(!is_reading_plausible(
+            0,
+            26000,
+            12000,
+            SENSOR_FAILURE_MARGIN
+        ));
+    }
+
+    #[test]
+    fn plausible_with_inverted_calibration() {
+        // Some sensors have raw_wet > raw_dry
+        assert!(is_reading_plausible(
+            20000,
+            12000,
+            26000,
+            SENSOR_FAILURE_MARGIN
+        ));
+        assert!(!is_reading_plausible(
+            32767,
+            12000,
+            26000,
+            SENSOR_FAILURE_MARGIN
+        ));
+    }
+
+    #[test]
+    fn per_sensor_failure_margin() {
+        let mut s = SensorConfig {
+            sensor_id: "node-a/s1".into(),
+            node_id: "node-a".into(),
+            zone_id: "zone1".into(),
+            raw_dry: 16000,
+            raw_wet: 14000,
+            channel: None,
+            archived_at: None,
+            weight: 1.0,
+            failure_margin: None,
+            failure_margin_pct: None,
+            curve: None,
+            enabled: true,
+        };
+        assert_eq!(s.failure_margin_counts(), SENSOR_FAILURE_MARGIN);
+        assert!(s.is_plausible(18500));
+
+        // A narrow range gets a proportionally narrow margin.
+        s.failure_margin_pct = Some(25.0);
+        assert_eq!(s.failure_margin_counts(), 500);
+        assert!(s.is_plausible(16500));
+        assert!(!s.is_plausible(16501));
+        assert!(!s.is_plausible(13499));
+
+        // Absolute counts win over the percentage.
+        s.failure_margin = Some(0);
+        assert!(s.is_plausible(14000));
+        assert!(!s.is_plausible(13999));
+    }
+
+    // -- prune_old_readings ---------------------------------------------
+
+    #[tokio::test]
+    async fn insert_reading_ignores_duplicates() {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        db.upsert_zone(&ZoneConfig::test("z1")).await.unwrap();
+        db.upsert_sensor(&SensorConfig {
+            sensor_id: "s1".into(),
+            node_id: "n1".into(),
+            zone_id: "z1".into(),
+            raw_dry: 26000,
+            raw_wet: 12000,
+            archived_at: None,
+            channel: None,
+            weight: 1.0,
+            failure_margin: None,
+            failure_margin_pct: None,
+            curve: None,
+            enabled: true,
+        })
+        .await
+        .unwrap();
+
+        assert!(db.insert_reading(1000, "s1", 20000, 0.4).await.unwrap());
+        // Replayed with the same (ts, sensor_id): kept as-is, no error.
+        assert!(!db.insert_reading(1000, "s1", 21000, 0.3).await.unwrap());
+        let latest = db.latest_sensor_reading("s1").await.unwrap().unwrap();
+        assert_eq!(latest.raw, 20000);
+    }
+
+    #[tokio::test]
+    async fn scheduler_decisions_filter_and_prune() {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let decision = |ts: i64, zone_id: &str, action: &str| SchedulerDecision {
+            ts,
+            zone_id: zone_id.into(),
+            phase: "idle".into(),
+            avg_moisture: Some(0.25),
+            blocked_by: (action == "skip").then(|| "daily_limit".into()),
+            action: action.into(),
+            detail: String::new(),
+        };
+        db.insert_scheduler_decisions(&[
+            decision(now - 40 * 86400, "z1", "wait"),
+            decision(now - 60, "z1", "skip"),
+            decision(now - 60, "z2", "pulse"),
+            decision(now, "z1", "wait"),
+        ])
+        .await
+        .unwrap();
+
+        let z1 = db
+            .list_scheduler_decisions(Some("z1"), None, None, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(z1.len(), 3);
+        assert_eq!(z1[0].ts, now);
+        assert_eq!(z1[1].blocked_by.as_deref(), Some("daily_limit"));
+
+        let window = db
+            .list_scheduler_decisions(None, Some(now - 120), Some(now - 1), 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(window.len(), 2);
+
+        assert_eq!(db.prune_scheduler_decisions(30).await.unwrap(), 1);
+        let all = db
+            .list_scheduler_decisions(None, None, None, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn daily_flow_groups_by_day() {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        db.upsert_zone(&ZoneConfig::test("z1")).await.unwrap();
+
+        // 1970-01-02: two samples plus an idle one; 1970-01-03: one sample.
+        assert!(db
+            .insert_flow_reading(86_400, "z1", 6.0, Some(300.0))
+            .await
+            .unwrap());
+        assert!(!db
+            .insert_flow_reading(86_400, "z1", 1.0, None)
+            .await
+            .unwrap());
+        db.insert_flow_reading(86_460, "z1", 4.0, Some(310.0))
+            .await
+            .unwrap();
+        db.insert_flow_reading(86_520, "z1", 0.0, None)
+            .await
+            .unwrap();
+        db.insert_flow_reading(2 * 86_400, "z1", 5.0, None)
+            .await
+            .unwrap();
+
+        let days = db.daily_flow("z1", 0).await.unwrap();
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].day, "1970-01-02");
+        assert!((days[0].avg_lpm - 5.0).abs() < 1e-9);
+        assert_eq!(days[0].avg_pressure_kpa, Some(305.0));
+        assert_eq!(days[0].samples, 2);
+        assert_eq!(days[1].avg_pressure_kpa, None);
+
+        assert_eq!(db.daily_flow("z1", 2 * 86_400).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn prune_old_readings_removes_old_data() {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+
+        // Insert a zone and sensor for FK constraints
+        db.upsert_zone(&ZoneConfig::test("z1")).await.unwrap();
+        db.upsert_sensor(&SensorConfig {
+            sensor_id: "s1".into(),
+            node_id: "n1".into(),
+            zone_id: "z1".into(),
+            raw_dry: 26000,
+            raw_wet: 12000,
+            archived_at: None,
+            channel: None,
+            weight: 1.0,
+            failure_margin: None,
+            failure_margin_pct: None,
+            curve: None,
+            enabled: true,
+        })
+        .await
+        .unwrap();
+
+        // Insert an old reading (200 days ago) and a recent one
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let old_ts = now - (200 * 86400);
+        db.insert_reading(old_ts, "s1", 20000, 0.5).await.unwrap();
+        db.insert_reading(now, "s1", 20000, 0.5).await.unwrap();
+
+        // Prune readings older than 90 days
+        let deleted = db.prune_old_readings(90).await.unwrap();
+        assert_eq!(deleted, 1);
+
+        // Only the recent reading should remain
+        let remaining = db.list_readings(None, None, 100, 0).await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].ts, now);
+    }
+
+    #[tokio::test]
+    async fn recompute_moisture_rewrites_from_raw() {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        db.upsert_zone(&ZoneConfig::test("z1")).await.unwrap();
+        let mut sensor = SensorConfig {
+            sensor_id: "s1".into(),
+            node_id: "n1".into(),
+            zone_id: "z1".into(),
+            raw_dry: 26000,
+            raw_wet: 12000,
+            archived_at: None,
+            channel: None,
+            weight: 1.0,
+            failure_margin: None,
+            failure_margin_pct: None,
+            curve: None,
+            enabled: true,
+        };
+        db.upsert_sensor(&sensor).await.unwrap();
+        // More than two batches, all at raw 19000 (0.5 on the old range).
+        for ts in 0..1200 {
+            db.insert_reading(ts, "s1", 19000, 0.5).await.unwrap();
+        }
+
+        // Recalibrated: 19000 is now 0.75.
+        sensor.raw_dry = 33000;
+        sensor.raw_wet = 14333;
+        let moisture = sensor.moisture(19000);
+        assert!((moisture - 0.75).abs() < 1e-3);
+        let updated = db.recompute_moisture(&sensor, 100).await.unwrap();
+        assert_eq!(updated, 1100);
+
+        let rows = db.list_readings(Some("s1"), None, 2000, 0).await.unwrap();
+        assert_eq!(rows.len(), 1200);
+        for r in rows {
+            let expected = if r.ts >= 100 {
+                f64::from(moisture)
+            } else {
+                0.5
+            };
+            assert_eq!(r.moisture, expected, "ts {}", r.ts);
+        }
+        assert_eq!(db.recompute_moisture(&sensor, 5000).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn old_readings_downsampled_hourly_then_daily() {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        db.upsert_zone(&ZoneConfig::test("z1")).await.unwrap();
+        db.upsert_sensor(&SensorConfig {
+            sensor_id: "s1".into(),
+            node_id: "n1".into(),
+            zone_id: "z1".into(),
+            raw_dry: 26000,
+            raw_wet: 12000,
+            archived_at: None,
+            channel: None,
+            weight: 1.0,
+            failure_margin: None,
+            failure_margin_pct: None,
+            curve: None,
+            enabled: true,
+        })
+        .await
+        .unwrap();
+
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let day = (now - 200 * 86400) / 86400 * 86400;
+        let hour = day + 5 * 3600;
+        db.insert_reading(hour, "s1", 20000, 0.25).await.unwrap();
+        db.insert_reading(hour + 60, "s1", 20000, 0.75)
+            .await
+            .unwrap();
+        db.insert_reading(hour + 3600, "s1", 20000, 0.125)
+            .await
+            .unwrap();
+        assert_eq!(db.prune_old_readings(90).await.unwrap(), 3);
+
+        // A late reading for an hour already rolled up is merged into it.
+        db.insert_reading(hour + 120, "s1", 20000, 0.5)
+            .await
+            .unwrap();
+        assert_eq!(db.prune_old_readings(90).await.unwrap(), 1);
+
+        let hourly = db
+            .sensor_moisture_trend("s1", 3600, day, day + 86400)
+            .await
+            .unwrap();
+        assert_eq!(hourly.len(), 2);
+        assert_eq!(hourly[0].ts, hour);
+        assert_eq!(hourly[0].samples, 3);
+        assert_eq!(hourly[0].moisture_avg, 0.5);
+        assert_eq!(hourly[0].moisture_min, 0.25);
+        assert_eq!(hourly[0].moisture_max, 0.75);
+
+        // Past the hourly retention the hours become one day.
+        assert_eq!(db.compact_hourly_readings(365).await.unwrap(), 0);
+        assert_eq!(db.compact_hourly_readings(100).await.unwrap(), 2);
+        let daily = db
+            .sensor_moisture_trend("s1", 86400, day, day + 86400)
+            .await
+            .unwrap();
+        assert_eq!(daily.len(), 1);
+        assert_eq!(daily[0].ts, day);
+        assert_eq!(daily[0].samples, 4);
+        assert_eq!(daily[0].moisture_avg, 0.40625);
+        assert_eq!(daily[0].moisture_min, 0.125);
+        // Hourly trends show the day as a single bucket.
+        let hourly = db
+            .sensor_moisture_trend("s1", 3600, day, day + 86400)
+            .await
+            .unwrap();
+        assert_eq!(hourly, daily);
     }
 
-    /// Create a consistent backup of the database at `dest_path`.
-    ///
-    /// Uses SQLite `VACUUM INTO` to produce an atomic, defragmented copy
-    /// safe to call while the database is in active use.  The backup is
-    /// written to a temporary file and atomically renamed so a crash
-    /// mid-write cannot corrupt the previous good backup.
-    pub async fn backup(&self, dest_path: &str) -> Result<()> {
-        // Ensure the destination directory exists.
-        if let Some(parent) = std::path::Path::new(dest_path).parent() {
-            tokio::fs::create_dir_all(parent)
+    // -- batched reading writes ------------------------------------------
+
+    #[tokio::test]
+    async fn queued_readings_written_in_batches() {
+        let db = Db::connect("sqlite::memory:")
+            .await
+            .unwrap()
+            .with_reading_batch(3);
+        db.migrate().await.unwrap();
+        db.upsert_zone(&ZoneConfig::test("z1")).await.unwrap();
+        db.upsert_sensor(&SensorConfig {
+            sensor_id: "s1".into(),
+            node_id: "n1".into(),
+            zone_id: "z1".into(),
+            raw_dry: 26000,
+            raw_wet: 12000,
+            archived_at: None,
+            channel: None,
+            weight: 1.0,
+            failure_margin: None,
+            failure_margin_pct: None,
+            curve: None,
+            enabled: true,
+        })
+        .await
+        .unwrap();
+        let count = || async {
+            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM readings")
+                .fetch_one(&db.pool)
                 .await
-                .with_context(|| format!("create backup dir {}", parent.display()))?;
+                .unwrap()
+        };
+
+        assert!(db.claim_reading(100, "s1").await);
+        assert!(db.queue_reading(100, "s1", 20000, 0.4).await.unwrap());
+        // A replay of a queued reading is spotted before it is written.
+        assert!(!db.claim_reading(100, "s1").await);
+        assert_eq!(db.duplicate_readings(), 1);
+        assert!(db.queue_reading(200, "s1", 20000, 0.4).await.unwrap());
+        assert_eq!(count().await, 0);
+        // The third row fills the batch.
+        assert!(db.queue_reading(300, "s1", 20000, 0.4).await.unwrap());
+        assert_eq!(count().await, 3);
+
+        // An unknown sensor fails its foreign key and is dropped on its
+        // own, without holding back the rest of the batch.
+        db.queue_reading(400, "s1", 20000, 0.4).await.unwrap();
+        db.queue_reading(400, "ghost", 20000, 0.4).await.unwrap();
+        assert_eq!(db.flush_readings().await.unwrap(), 1);
+        assert_eq!(count().await, 4);
+        assert_eq!(db.flush_readings().await.unwrap(), 0);
+
+        // A redelivery of a reading already written is still spotted.
+        assert!(!db.claim_reading(100, "s1").await);
+        assert_eq!(db.duplicate_readings(), 2);
+        // One the hub has forgotten is dropped and counted by the batched
+        // write; the stored row is kept.
+        assert!(db.queue_reading(100, "s1", 21000, 0.3).await.unwrap());
+        assert_eq!(db.flush_readings().await.unwrap(), 0);
+        assert_eq!(db.duplicate_readings(), 3);
+        assert_eq!(count().await, 4);
+        let first = sqlx::query_scalar::<_, i64>("SELECT raw FROM readings WHERE ts = 100")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(first, 20000);
+    }
+
+    #[test]
+    fn requeue_keeps_newest_within_cap() {
+        let row = |ts| PendingReading {
+            ts,
+            sensor_id: "s1".into(),
+            raw: 0,
+            moisture: 0.0,
+        };
+        let mut buf = ReadingBuffer::default();
+        for ts in 0..READING_BUFFER_CAP as i64 {
+            buf.claim((ts, "s1".to_string()));
         }
+        buf.rows.push(row(READING_BUFFER_CAP as i64 + 10));
+        buf.requeue((0..READING_BUFFER_CAP as i64).map(row).collect());
+        assert_eq!(buf.rows.len(), READING_BUFFER_CAP);
+        assert_eq!(buf.rows[0].ts, 1);
+        assert_eq!(buf.rows.last().unwrap().ts, READING_BUFFER_CAP as i64 + 10);
+        // Dropped rows are forgotten; queued ones are still known.
+        assert!(!buf.claim((1, "s1".to_string())));
+        assert!(buf.claim((0, "s1".to_string())));
+    }
 
-        let tmp_path = format!("{dest_path}.tmp");
+    // -- daily moisture rollup -------------------------------------------
 
-        // Remove any leftover temp file from a previous failed backup.
-        let _ = tokio::fs::remove_file(&tmp_path).await;
+    #[tokio::test]
+    async fn daily_moisture_survives_pruning() {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        db.upsert_zone(&ZoneConfig::test("z1")).await.unwrap();
+        db.upsert_sensor(&SensorConfig {
+            sensor_id: "s1".into(),
+            node_id: "n1".into(),
+            zone_id: "z1".into(),
+            raw_dry: 26000,
+            raw_wet: 12000,
+            archived_at: None,
+            channel: None,
+            weight: 1.0,
+            failure_margin: None,
+            failure_margin_pct: None,
+            curve: None,
+            enabled: true,
+        })
+        .await
+        .unwrap();
+        let old = OffsetDateTime::now_utc().unix_timestamp() - 200 * 86400;
+        let old_day = old - old.rem_euclid(86400);
+        db.insert_reading(old_day + 3600, "s1", 20000, 0.2)
+            .await
+            .unwrap();
+        db.insert_reading(old_day + 7200, "s1", 20000, 0.4)
+            .await
+            .unwrap();
+        let day = OffsetDateTime::from_unix_timestamp(old_day)
+            .unwrap()
+            .date()
+            .to_string();
 
-        // VACUUM INTO produces a defragmented, consistent snapshot.
-        // The path must be a SQL string literal (not a bind parameter).
-        let escaped = tmp_path.replace('\'', "''");
-        sqlx::query(&format!("VACUUM INTO '{escaped}'"))
-            .execute(&self.pool)
+        let before = db.daily_moisture("z1", &day, &day).await.unwrap();
+        assert_eq!(before.len(), 1);
+        assert_eq!(before[0].samples, 2);
+        assert!((before[0].avg_moisture - 0.3).abs() < 1e-6);
+
+        db.prune_old_readings(90).await.unwrap();
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM readings")
+            .fetch_one(&db.pool)
             .await
-            .with_context(|| format!("VACUUM INTO '{tmp_path}' failed"))?;
+            .unwrap();
+        assert_eq!(count, 0);
+        assert_eq!(db.daily_moisture("z1", &day, &day).await.unwrap(), before);
 
-        // Atomically replace the previous backup.
-        tokio::fs::rename(&tmp_path, dest_path)
+        // A second rollup leaves the stored day untouched.
+        assert_eq!(db.rollup_daily_moisture(old).await.unwrap(), 0);
+    }
+
+    // -- zone strategy ----------------------------------------------------
+
+    #[tokio::test]
+    async fn zone_strategy_round_trip() {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        let mut z = ZoneConfig {
+            strategy: StrategyConfig::Schedule {
+                times: vec!["06:00".into()],
+                pulses: 2,
+            },
+            ..ZoneConfig::test("z1")
+        };
+        db.upsert_zone(&z).await.unwrap();
+        assert_eq!(
+            db.get_zone("z1").await.unwrap().unwrap().strategy,
+            z.strategy
+        );
+
+        z.strategy = StrategyConfig::Threshold;
+        db.upsert_zone(&z).await.unwrap();
+        assert_eq!(
+            db.load_zones().await.unwrap()[0].strategy,
+            StrategyConfig::Threshold
+        );
+
+        // Unreadable JSON falls back to the threshold strategy.
+        sqlx::query("UPDATE zones SET strategy = 'nonsense'")
+            .execute(&db.pool)
             .await
-            .with_context(|| format!("rename '{tmp_path}' -> '{dest_path}' failed"))?;
+            .unwrap();
+        assert_eq!(
+            db.get_zone("z1").await.unwrap().unwrap().strategy,
+            StrategyConfig::Threshold
+        );
+    }
 
-        Ok(())
+    #[tokio::test]
+    async fn zone_valve_round_trip() {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        let z = ZoneConfig {
+            valve: ValveConfig::Motorized {
+                close_gpio_pin: 22,
+                travel_sec: 8,
+            },
+            ..ZoneConfig::test("z1")
+        };
+        db.upsert_zone(&z).await.unwrap();
+        assert_eq!(db.get_zone("z1").await.unwrap().unwrap().valve, z.valve);
+
+        let raw: Option<String> = sqlx::query_scalar("SELECT valve FROM zones")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert!(raw.unwrap().contains("motorized"));
+
+        db.upsert_zone(&ZoneConfig {
+            valve: ValveConfig::Solenoid,
+            ..z
+        })
+        .await
+        .unwrap();
+        let raw: Option<String> = sqlx::query_scalar("SELECT valve FROM zones")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(raw, None);
+        assert_eq!(
+            db.load_zones().await.unwrap()[0].valve,
+            ValveConfig::Solenoid
+        );
     }
-}
 
-// ---------------------------------------------------------------------------
-// Backup / restore helpers (SD card wear mitigation)
-// ---------------------------------------------------------------------------
+    // -- config versions ------------------------------------------------
 
-/// Extract the filesystem path from a SQLite connection URL.
-///
-/// Returns `None` for in-memory databases or non-sqlite URLs.
-pub fn db_file_path(db_url: &str) -> Option<String> {
-    let stripped = db_url.strip_prefix("sqlite:")?;
-    if stripped.starts_with(":memory:") || stripped.is_empty() {
-        return None;
+    #[tokio::test]
+    async fn restore_config_archives_new_sensors_and_keeps_referenced_zones() {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        db.upsert_zone(&ZoneConfig::test("z1")).await.unwrap();
+        let v1 = db
+            .record_config_version(100, "initial", AuditSource::Api, None)
+            .await
+            .unwrap();
+        assert_eq!(v1, Some(1));
+        // Unchanged config is not re-recorded.
+        assert_eq!(
+            db.record_config_version(101, "again", AuditSource::Api, None)
+                .await
+                .unwrap(),
+            None
+        );
+
+        // Later: a new zone with a sensor and readings, plus an empty zone.
+        db.upsert_zone(&ZoneConfig::test("z2")).await.unwrap();
+        db.upsert_zone(&ZoneConfig::test("z3")).await.unwrap();
+        db.upsert_sensor(&SensorConfig {
+            sensor_id: "s2".into(),
+            node_id: "n1".into(),
+            zone_id: "z2".into(),
+            raw_dry: 26000,
+            raw_wet: 12000,
+            archived_at: None,
+            channel: None,
+            weight: 1.0,
+            failure_margin: None,
+            failure_margin_pct: None,
+            curve: None,
+            enabled: true,
+        })
+        .await
+        .unwrap();
+        db.insert_reading(150, "s2", 20000, 0.4).await.unwrap();
+        assert_eq!(
+            db.record_config_version(200, "added", AuditSource::Api, None)
+                .await
+                .unwrap(),
+            Some(2)
+        );
+
+        let (meta, snapshot) = db.get_config_version(1).await.unwrap().unwrap();
+        assert_eq!(meta.reason, "initial");
+        let summary = db.restore_config(&snapshot, 300).await.unwrap();
+        assert_eq!(summary.archived_sensors, vec!["s2".to_string()]);
+        assert_eq!(summary.kept_zones, vec!["z2".to_string()]);
+
+        let zones: Vec<String> = db
+            .load_zones()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|z| z.zone_id)
+            .collect();
+        assert_eq!(zones, ["z1", "z2"]);
+        assert!(db.load_sensors().await.unwrap().is_empty());
+        assert_eq!(
+            db.get_sensor("s2").await.unwrap().unwrap().archived_at,
+            Some(300)
+        );
+
+        let versions = db.list_config_versions().await.unwrap();
+        assert_eq!(
+            versions.iter().map(|v| v.version).collect::<Vec<_>>(),
+            [2, 1]
+        );
+        assert!(db.get_config_version(9).await.unwrap().is_none());
     }
-    let path = stripped.split('?').next().unwrap_or(stripped);
-    if path.is_empty() {
-        return None;
+
+    #[tokio::test]
+    async fn config_versions_write_the_audit_log() {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        let mut zone = ZoneConfig {
+            name: "Lawn".into(),
+            ..ZoneConfig::test("z1")
+        };
+        db.upsert_zone(&zone).await.unwrap();
+        db.record_config_version(100, "startup", AuditSource::ConfigFile, None)
+            .await
+            .unwrap();
+        zone.max_open_sec_per_day = 3600;
+        db.upsert_zone(&zone).await.unwrap();
+        db.record_config_version(
+            200,
+            "zone 'z1' updated",
+            AuditSource::Api,
+            Some("greenhouse"),
+        )
+        .await
+        .unwrap();
+        // Nothing changed: no version, no audit row.
+        db.record_config_version(300, "again", AuditSource::Api, Some("greenhouse"))
+            .await
+            .unwrap();
+        db.delete_zone("z1").await.unwrap();
+        db.record_config_version(400, "zone 'z1' deleted", AuditSource::Api, None)
+            .await
+            .unwrap();
+
+        let audit = db
+            .list_audit(Some("zone"), Some("z1"), None, None, 10, 0)
+            .await
+            .unwrap();
+        let summary: Vec<(i64, &str, &str, Option<&str>)> = audit
+            .iter()
+            .map(|a| {
+                (
+                    a.ts,
+                    a.action.as_str(),
+                    a.source.as_str(),
+                    a.actor.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (400, "delete", "api", None),
+                (200, "update", "api", Some("greenhouse")),
+                (100, "create", "config_file", None),
+            ]
+        );
+        let update = &audit[1];
+        assert_eq!(
+            update.old_value.as_ref().unwrap()["max_open_sec_per_day"],
+            180
+        );
+        assert_eq!(
+            update.new_value.as_ref().unwrap()["max_open_sec_per_day"],
+            3600
+        );
+        assert!(audit[0].new_value.is_none());
+
+        let since = db
+            .list_audit(None, None, Some(200), Some(300), 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(since.len(), 1);
+        assert!(db
+            .list_audit(Some("sensor"), None, None, None, 10, 0)
+            .await
+            .unwrap()
+            .is_empty());
     }
-    Some(path.to_string())
-}
 
-/// Restore a database backup to the working path if the working DB does
-/// not exist or is empty (e.g. after a tmpfs reboot).
-///
-/// Call **before** [`Db::connect`] when using a RAM-backed working directory.
-/// Returns `true` if a restore was performed.
-pub fn restore_from_backup(working_path: &str, backup_path: &str) -> Result<bool> {
-    let backup = std::path::Path::new(backup_path);
-    if !backup.exists() {
-        tracing::info!(
-            backup_path,
-            "no backup file found — starting with fresh database"
+    // -- decommission_node ----------------------------------------------
+
+    #[tokio::test]
+    async fn decommission_node_archives_its_sensors() {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        db.upsert_zone(&ZoneConfig::test("z1")).await.unwrap();
+        for (sensor_id, node_id) in [("n1/s1", "n1"), ("n1/s2", "n1"), ("n2/s1", "n2")] {
+            db.upsert_sensor(&SensorConfig {
+                sensor_id: sensor_id.into(),
+                node_id: node_id.into(),
+                zone_id: "z1".into(),
+                raw_dry: 26000,
+                raw_wet: 12000,
+                archived_at: None,
+                channel: None,
+                weight: 1.0,
+                failure_margin: None,
+                failure_margin_pct: None,
+                curve: None,
+                enabled: true,
+            })
+            .await
+            .unwrap();
+        }
+
+        let archived = db.decommission_node("n1", 1_700_000_000).await.unwrap();
+        assert_eq!(archived, 2);
+
+        // Node row created on the fly, named after its id.
+        let node = db.get_node("n1").await.unwrap().unwrap();
+        assert_eq!(node.name, "n1");
+        assert_eq!(node.decommissioned_at, Some(1_700_000_000));
+
+        // Archived sensors drop out of the active set but stay listed per node.
+        let active = db.load_sensors().await.unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].sensor_id, "n2/s1");
+        let n1 = db.sensors_for_node("n1").await.unwrap();
+        assert_eq!(n1.len(), 2);
+        assert!(n1.iter().all(|s| s.archived_at == Some(1_700_000_000)));
+
+        // Re-upserting metadata keeps the decommission stamp.
+        db.upsert_node(&NodeConfig {
+            node_id: "n1".into(),
+            name: "Old shed".into(),
+            sample_interval_sec: Some(300),
+            stale_timeout_min: None,
+            battery_powered: false,
+            wake_grace_factor: None,
+            decommissioned_at: None,
+        })
+        .await
+        .unwrap();
+        let node = db.get_node("n1").await.unwrap().unwrap();
+        assert_eq!(node.name, "Old shed");
+        assert_eq!(node.decommissioned_at, Some(1_700_000_000));
+    }
+
+    #[tokio::test]
+    async fn battery_node_round_trip() {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        db.upsert_node(&NodeConfig {
+            node_id: "n1".into(),
+            name: "Far bed".into(),
+            sample_interval_sec: Some(3600),
+            stale_timeout_min: None,
+            battery_powered: true,
+            wake_grace_factor: Some(2.0),
+            decommissioned_at: None,
+        })
+        .await
+        .unwrap();
+        let node = db.get_node("n1").await.unwrap().unwrap();
+        assert!(node.battery_powered);
+        assert_eq!(node.wake_grace_factor, Some(2.0));
+        assert_eq!(
+            StalePolicy::for_node(Some(&node), 15),
+            StalePolicy::Wake {
+                interval_sec: 3600,
+                grace_factor: 2.0
+            }
+        );
+    }
+
+    #[test]
+    fn stale_policy_thresholds() {
+        let mut node = NodeConfig {
+            node_id: "n1".into(),
+            name: "n1".into(),
+            sample_interval_sec: Some(1800),
+            stale_timeout_min: Some(20),
+            battery_powered: false,
+            wake_grace_factor: None,
+            decommissioned_at: None,
+        };
+        assert_eq!(StalePolicy::for_node(None, 15).threshold_sec(), 15 * 60);
+        assert_eq!(
+            StalePolicy::for_node(Some(&node), 15).threshold_sec(),
+            20 * 60
+        );
+
+        // Battery nodes are judged against their wake interval, not the timeout.
+        node.battery_powered = true;
+        assert_eq!(StalePolicy::for_node(Some(&node), 15).threshold_sec(), 2700);
+
+        // Without a known interval there's nothing to expect a wake against.
+        node.sample_interval_sec = None;
+        assert_eq!(
+            StalePolicy::for_node(Some(&node), 15).threshold_sec(),
+            20 * 60
+        );
+    }
+
+    // -- disturbances ----------------------------------------------------
+
+    #[tokio::test]
+    async fn disturbed_readings_excluded_from_zone_moisture() {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        db.upsert_zone(&ZoneConfig::test("z1")).await.unwrap();
+        db.upsert_sensor(&SensorConfig {
+            sensor_id: "s1".into(),
+            node_id: "n1".into(),
+            zone_id: "z1".into(),
+            raw_dry: 26000,
+            raw_wet: 12000,
+            archived_at: None,
+            channel: None,
+            weight: 1.0,
+            failure_margin: None,
+            failure_margin_pct: None,
+            curve: None,
+            enabled: true,
+        })
+        .await
+        .unwrap();
+
+        db.insert_reading(1000, "s1", 20000, 0.4).await.unwrap();
+        db.insert_reading(1100, "s1", 26000, 0.0).await.unwrap(); // probe pulled
+        db.insert_reading(1200, "s1", 26000, 0.0).await.unwrap(); // probe pulled
+
+        let id = db
+            .insert_disturbance("z1", 1050, None, "probe cleaning")
+            .await
+            .unwrap();
+
+        // Ongoing disturbance hides everything from 1050 on.
+        assert_eq!(
+            db.latest_zone_moisture("z1").await.unwrap(),
+            Some((1000, 0.4))
+        );
+        assert_eq!(
+            db.zone_sensor_moisture("z1", 5, 0)
+                .await
+                .unwrap()
+                .iter()
+                .map(|s| s.moisture)
+                .collect::<Vec<_>>(),
+            vec![0.4]
+        );
+        assert_eq!(
+            db.zone_moisture_since("z1", 0).await.unwrap(),
+            vec![(1000, 0.4)]
+        );
+
+        // Closing it (end exclusive) lets later readings back in.
+        let mut d = db.get_disturbance("z1", id).await.unwrap().unwrap();
+        d.end_ts = Some(1200);
+        assert!(db.update_disturbance(&d).await.unwrap());
+        assert_eq!(
+            db.latest_zone_moisture("z1").await.unwrap(),
+            Some((1200, 0.0))
+        );
+        assert_eq!(db.zone_moisture_since("z1", 0).await.unwrap().len(), 2);
+
+        // Raw readings are kept regardless.
+        assert_eq!(
+            db.list_readings(None, Some("z1"), 10, 0)
+                .await
+                .unwrap()
+                .len(),
+            3
+        );
+
+        assert_eq!(db.list_disturbances("z1").await.unwrap(), vec![d]);
+        assert!(db.delete_disturbance("z1", id).await.unwrap());
+        assert!(!db.delete_disturbance("z1", id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn zone_sensor_moisture_smooths_each_active_sensor() {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        db.upsert_zone(&ZoneConfig {
+            aggregation: Aggregation::Median,
+            ..ZoneConfig::test("z1")
+        })
+        .await
+        .unwrap();
+        for (id, weight) in [("s1", 1.0), ("s2", 0.5), ("s3", 1.0)] {
+            db.upsert_sensor(&SensorConfig {
+                sensor_id: id.into(),
+                node_id: "n1".into(),
+                zone_id: "z1".into(),
+                raw_dry: 26000,
+                raw_wet: 12000,
+                channel: None,
+                archived_at: None,
+                weight,
+                failure_margin: None,
+                failure_margin_pct: None,
+                curve: None,
+                enabled: true,
+            })
+            .await
+            .unwrap();
+        }
+        assert_eq!(
+            db.get_zone("z1").await.unwrap().unwrap().aggregation,
+            Aggregation::Median
+        );
+
+        // s1: only its newest two readings count.
+        for (ts, m) in [(1000, 0.9), (1100, 0.3), (1200, 0.5)] {
+            db.insert_reading(ts, "s1", 20000, m).await.unwrap();
+        }
+        db.insert_reading(1200, "s2", 20000, 0.2).await.unwrap();
+        // s3 went quiet before the window.
+        db.insert_reading(500, "s3", 20000, 0.8).await.unwrap();
+
+        let sensors = db.zone_sensor_moisture("z1", 2, 900).await.unwrap();
+        assert_eq!(sensors.len(), 2);
+        assert_eq!(sensors[0].sensor_id, "s1");
+        assert!((sensors[0].moisture - 0.4).abs() < 1e-6);
+        assert_eq!(sensors[1].weight, 0.5);
+
+        // Quarantined sensors drop out until released.
+        assert!(!db.record_sensor_failure("s2", 1300, 2).await.unwrap());
+        assert!(db.record_sensor_failure("s2", 1400, 2).await.unwrap());
+        let sensors = db.zone_sensor_moisture("z1", 2, 900).await.unwrap();
+        assert_eq!(sensors.len(), 1);
+        assert!(db.release_sensor("s2").await.unwrap());
+        assert_eq!(
+            db.zone_sensor_moisture("z1", 2, 900).await.unwrap().len(),
+            2
         );
-        return Ok(false);
+
+        // So do disabled ones, and they're still listed as disabled.
+        let mut s2 = db.get_sensor("s2").await.unwrap().unwrap();
+        s2.enabled = false;
+        db.upsert_sensor(&s2).await.unwrap();
+        assert_eq!(
+            db.zone_sensor_moisture("z1", 2, 900).await.unwrap().len(),
+            1
+        );
+        assert!(!db.get_sensor("s2").await.unwrap().unwrap().enabled);
+
+        // Archived sensors drop out.
+        db.decommission_node("n1", 2000).await.unwrap();
+        assert!(db
+            .zone_sensor_moisture("z1", 2, 900)
+            .await
+            .unwrap()
+            .is_empty());
     }
 
-    let working = std::path::Path::new(working_path);
-    let needs_restore =
-        !working.exists() || working.metadata().map(|m| m.len() == 0).unwrap_or(true);
+    // -- open valves ----------------------------------------------------
 
-    if needs_restore {
-        if let Some(parent) = working.parent() {
-            std::fs::create_dir_all(parent)
-                .with_context(|| format!("create dir {}", parent.display()))?;
+    #[tokio::test]
+    async fn close_open_valves_records_interrupted_sessions() {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        for zone_id in ["z1", "z2"] {
+            db.upsert_zone(&ZoneConfig::test(zone_id)).await.unwrap();
         }
-        std::fs::copy(backup, working)
-            .with_context(|| format!("restore backup '{backup_path}' -> '{working_path}'"))?;
-        tracing::info!(backup_path, working_path, "database restored from backup");
-        Ok(true)
-    } else {
-        tracing::debug!(working_path, "working database exists — skipping restore");
-        Ok(false)
-    }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        db.mark_valve_open("z1", 1000, "scheduler", Some(30))
+            .await
+            .unwrap();
+        // Re-marking keeps the original open time.
+        db.mark_valve_open("z1", 1010, "mqtt", None).await.unwrap();
+        db.mark_valve_open("z2", 1000, "mqtt", None).await.unwrap();
+        db.mark_valve_closed("z2").await.unwrap();
 
-    // -- compute_moisture -----------------------------------------------
+        let closed = db
+            .close_open_valves(1045, "hub_restart", "recovered", "hub_restart")
+            .await
+            .unwrap();
+        assert_eq!(closed, vec![("z1".to_string(), 45)]);
 
-    #[test]
-    fn compute_moisture_mid_range() {
-        let m = compute_moisture(19000, 26000, 12000);
-        assert!((m - 0.5).abs() < 0.01);
-    }
+        let today = Db::today_yyyy_mm_dd();
+        assert_eq!(
+            db.get_daily_counters(&today, "z1").await.unwrap().open_sec,
+            45
+        );
+        let events = db.list_watering_events(Some("z1"), 10, 0).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].ts_start, 1000);
+        assert_eq!(events[0].reason, "hub_restart");
+        assert_eq!(events[0].triggered_by.as_deref(), Some("scheduler"));
+        assert_eq!(events[0].planned_sec, Some(30));
+        assert_eq!(events[0].ended_by.as_deref(), Some("hub_restart"));
+        assert_eq!(events[0].anomalies, ["overran", "forced"]);
 
-    #[test]
-    fn compute_moisture_bone_dry() {
-        let m = compute_moisture(26000, 26000, 12000);
-        assert!((m - 0.0).abs() < 0.01);
+        // Nothing left to close.
+        assert!(db
+            .close_open_valves(2000, "hub_restart", "recovered", "hub_restart")
+            .await
+            .unwrap()
+            .is_empty());
     }
 
-    #[test]
-    fn compute_moisture_saturated() {
-        let m = compute_moisture(12000, 26000, 12000);
-        assert!((m - 1.0).abs() < 0.01);
-    }
+    // -- scheduler state --------------------------------------------------
 
-    #[test]
-    fn compute_moisture_clamped_below() {
-        let m = compute_moisture(30000, 26000, 12000);
-        assert_eq!(m, 0.0);
-    }
+    #[tokio::test]
+    async fn scheduler_state_round_trips() {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        let mut soak = SchedulerZoneState {
+            zone_id: "z1".into(),
+            phase: "soaking".into(),
+            started_ts: 1_000,
+            until_ts: 2_200,
+            extended_sec: 0,
+        };
+        db.save_scheduler_state(&soak).await.unwrap();
+        soak.until_ts = 2_320;
+        soak.extended_sec = 120;
+        db.save_scheduler_state(&soak).await.unwrap();
+        assert_eq!(db.load_scheduler_states().await.unwrap(), vec![soak]);
 
-    #[test]
-    fn compute_moisture_clamped_above() {
-        let m = compute_moisture(5000, 26000, 12000);
-        assert_eq!(m, 1.0);
+        db.clear_scheduler_state("z1").await.unwrap();
+        assert!(db.load_scheduler_states().await.unwrap().is_empty());
     }
 
-    #[test]
-    fn compute_moisture_degenerate_calibration() {
-        let m = compute_moisture(20000, 15000, 15000);
-        assert_eq!(m, 0.0);
-    }
+    // -- odometer ---------------------------------------------------------
 
-    // -- is_reading_plausible -------------------------------------------
+    #[tokio::test]
+    async fn odometer_accumulates_across_days_and_resets_on_service() {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        db.upsert_zone(&ZoneConfig::test("z1")).await.unwrap();
+        assert_eq!(
+            db.get_odometer("z1").await.unwrap(),
+            ZoneOdometer::default()
+        );
 
-    #[test]
-    fn plausible_reading_in_range() {
-        assert!(is_reading_plausible(20000, 26000, 12000));
-    }
+        for day in ["2025-06-01", "2025-06-02"] {
+            db.add_pulse(day, "z1", 3).await.unwrap();
+            db.add_open_seconds(day, "z1", 90).await.unwrap();
+        }
+        let odo = db.get_odometer("z1").await.unwrap();
+        assert_eq!((odo.actuations, odo.open_sec), (6, 180));
+        assert_eq!(odo.actuations_since_service, 6);
 
-    #[test]
-    fn plausible_reading_at_dry() {
-        assert!(is_reading_plausible(26000, 26000, 12000));
-    }
+        assert!(db.mark_service_due("z1", 1000).await.unwrap());
+        assert!(!db.mark_service_due("z1", 2000).await.unwrap());
+        assert_eq!(
+            db.list_odometers().await.unwrap()["z1"].service_due_ts,
+            Some(1000)
+        );
 
-    #[test]
-    fn plausible_reading_at_wet() {
-        assert!(is_reading_plausible(12000, 26000, 12000));
+        db.record_valve_service("z1", 3000).await.unwrap();
+        db.add_pulse("2025-06-03", "z1", 1).await.unwrap();
+        let odo = db.get_odometer("z1").await.unwrap();
+        assert_eq!(odo.actuations, 7);
+        assert_eq!(odo.actuations_since_service, 1);
+        assert_eq!(odo.open_sec_since_service, 0);
+        assert_eq!(odo.serviced_ts, Some(3000));
+        assert_eq!(odo.service_due_ts, None);
     }
 
-    #[test]
-    fn plausible_reading_slightly_beyond_range() {
-        // Within the margin — still plausible
-        assert!(is_reading_plausible(28000, 26000, 12000));
-        assert!(is_reading_plausible(10000, 26000, 12000));
-    }
+    // -- usage_report -----------------------------------------------------
 
-    #[test]
-    fn implausible_reading_disconnected_sensor() {
-        // ADS1115 open input reads ~32767
-        assert!(!is_reading_plausible(32767, 26000, 12000));
-    }
+    #[tokio::test]
+    async fn usage_report_buckets_and_litres() {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        for (zone_id, flow_lpm, emitter_flow_lph) in [
+            ("z1", Some(6.0), None),
+            ("z2", None, None),
+            ("z3", None, Some(240.0)),
+        ] {
+            db.upsert_zone(&ZoneConfig {
+                flow_lpm,
+                emitter_flow_lph,
+                ..ZoneConfig::test(zone_id)
+            })
+            .await
+            .unwrap();
+        }
+        // 2025-06-02 is a Monday, so 06-01 and 06-02 fall in different weeks.
+        for day in ["2025-06-01", "2025-06-02", "2025-06-03", "2025-07-01"] {
+            db.add_open_seconds(day, "z1", 60).await.unwrap();
+            db.add_pulse(day, "z1", 2).await.unwrap();
+        }
+        db.add_open_seconds("2025-06-01", "z2", 30).await.unwrap();
+        db.add_open_seconds("2025-06-01", "z3", 90).await.unwrap();
 
-    #[test]
-    fn implausible_reading_shorted_sensor() {
-        // Shorted to ground reads ~0
-        assert!(!is_reading_plausible(0, 26000, 12000));
-    }
+        let daily = db
+            .usage_report(Some("z1"), "2025-06-01", "2025-06-30", UsageBucket::Day)
+            .await
+            .unwrap();
+        assert_eq!(daily.len(), 3);
+        assert_eq!(daily[0].period, "2025-06-01");
+        assert_eq!(daily[0].open_sec, 60);
+        assert_eq!(daily[0].litres, Some(6.0));
 
-    #[test]
-    fn plausible_with_inverted_calibration() {
-        // Some sensors have raw_wet > raw_dry
-        assert!(is_reading_plausible(20000, 12000, 26000));
-        assert!(!is_reading_plausible(32767, 12000, 26000));
-    }
+        let weekly = db
+            .usage_report(Some("z1"), "2025-06-01", "2025-06-30", UsageBucket::Week)
+            .await
+            .unwrap();
+        assert_eq!(weekly.len(), 2);
+        assert_eq!(weekly[1].open_sec, 120);
+        assert_eq!(weekly[1].pulses, 4);
 
-    // -- prune_old_readings ---------------------------------------------
+        let monthly = db
+            .usage_report(None, "2025-01-01", "2025-12-31", UsageBucket::Month)
+            .await
+            .unwrap();
+        assert_eq!(monthly.len(), 4); // z1 June, z1 July, z2 June, z3 June
+        assert_eq!(monthly[0].period, "2025-06");
+        assert_eq!(monthly[0].open_sec, 180);
+        assert_eq!(monthly[0].litres, Some(18.0));
+        assert_eq!(monthly[2].zone_id, "z2");
+        assert_eq!(monthly[2].litres, None); // no flow measurement
+        assert_eq!(monthly[3].litres, Some(6.0)); // 240 L/h emitters for 90 s
+    }
 
     #[tokio::test]
-    async fn prune_old_readings_removes_old_data() {
+    async fn rolling_counters_span_midnight() {
         let db = Db::connect("sqlite::memory:").await.unwrap();
         db.migrate().await.unwrap();
+        db.upsert_zone(&ZoneConfig::test("z1")).await.unwrap();
 
-        // Insert a zone and sensor for FK constraints
+        let now = 1_750_000_000;
+        let since = now - ROLLING_WINDOW_SEC;
+        for (start, end) in [
+            (since - 100, since - 40), // before the window
+            (since - 20, since + 10),  // straddles its start: 10 s
+            (now - 600, now - 570),    // 30 s
+            (now - 60, now - 30),      // 30 s
+        ] {
+            db.insert_watering_event(start, end, "z1", "scheduler", "ok", EventDetails::default())
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            db.rolling_counters("z1", now).await.unwrap(),
+            RollingCounters {
+                pulses: 2,
+                open_sec: 70
+            }
+        );
+        assert_eq!(
+            db.rolling_counters("z2", now).await.unwrap(),
+            RollingCounters::default()
+        );
+    }
+
+    #[tokio::test]
+    async fn watering_events_compare_planned_and_measured() {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
         db.upsert_zone(&ZoneConfig {
-            zone_id: "z1".into(),
-            name: "Test".into(),
-            min_moisture: 0.3,
-            target_moisture: 0.5,
-            pulse_sec: 30,
-            soak_min: 20,
-            max_open_sec_per_day: 180,
-            max_pulses_per_day: 6,
-            stale_timeout_min: 30,
-            valve_gpio_pin: 17,
+            pulse_sec: 60,
+            flow_lpm: Some(6.0),
+            ..ZoneConfig::test("z1")
         })
         .await
         .unwrap();
-        db.upsert_sensor(&SensorConfig {
-            sensor_id: "s1".into(),
-            node_id: "n1".into(),
-            zone_id: "z1".into(),
-            raw_dry: 26000,
-            raw_wet: 12000,
-        })
+
+        let details = |ended_by| EventDetails {
+            planned_sec: Some(60),
+            triggered_by: Some("scheduler"),
+            ended_by: Some(ended_by),
+        };
+        // As planned, at the configured flow.
+        db.insert_watering_event(1000, 1060, "z1", "scheduler", "ok", details("off_command"))
+            .await
+            .unwrap();
+        for ts in [1010, 1030, 1050] {
+            db.insert_flow_reading(ts, "z1", 6.0, None).await.unwrap();
+        }
+        // Cut short by the watchdog, with a third of the flow.
+        db.insert_watering_event(
+            2000,
+            2020,
+            "z1",
+            "scheduler",
+            "watchdog",
+            details("watchdog"),
+        )
         .await
         .unwrap();
+        db.insert_flow_reading(2010, "z1", 2.0, None).await.unwrap();
+        // Recorded before the details existed.
+        db.insert_watering_event(3000, 3030, "z1", "scheduler", "ok", EventDetails::default())
+            .await
+            .unwrap();
 
-        // Insert an old reading (200 days ago) and a recent one
-        let now = OffsetDateTime::now_utc().unix_timestamp();
-        let old_ts = now - (200 * 86400);
-        db.insert_reading(old_ts, "s1", 20000, 0.5).await.unwrap();
-        db.insert_reading(now, "s1", 20000, 0.5).await.unwrap();
+        let events = db.list_watering_events(Some("z1"), 10, 0).await.unwrap();
+        let [old, cut, normal] = &events[..] else {
+            panic!("{events:?}");
+        };
+        assert_eq!(normal.duration_sec, 60);
+        assert_eq!(normal.expected_litres, Some(6.0));
+        assert_eq!(normal.litres, Some(6.0));
+        assert!(normal.anomalies.is_empty());
 
-        // Prune readings older than 90 days
-        let deleted = db.prune_old_readings(90).await.unwrap();
-        assert_eq!(deleted, 1);
+        assert_eq!(cut.duration_sec, 20);
+        assert_eq!(cut.ended_by.as_deref(), Some("watchdog"));
+        assert!((cut.litres.unwrap() - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(cut.anomalies, ["short", "forced", "low_flow"]);
 
-        // Only the recent reading should remain
-        let remaining = db.list_readings(None, None, 100, 0).await.unwrap();
-        assert_eq!(remaining.len(), 1);
-        assert_eq!(remaining[0].ts, now);
+        assert_eq!(old.planned_sec, None);
+        assert_eq!(old.triggered_by, None);
+        assert_eq!(old.litres, None);
+        assert!(old.anomalies.is_empty());
     }
 
     // -- health_check ---------------------------------------------------
@@ -915,6 +5511,49 @@ mod tests {
         db.health_check().await.unwrap();
     }
 
+    #[tokio::test]
+    async fn writes_retry_while_the_database_is_locked() {
+        let dir = std::env::temp_dir().join(format!("irrigation_busy_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_url = format!("sqlite:{}?mode=rwc", dir.join("test.db").display());
+
+        // No busy timeout: a locked database fails at once.
+        let db = Db::connect_with(&db_url, Duration::ZERO).await.unwrap();
+        db.migrate().await.unwrap();
+        let mut other = sqlx::SqliteConnection::connect(&db_url).await.unwrap();
+        sqlx::query("BEGIN EXCLUSIVE")
+            .execute(&mut other)
+            .await
+            .unwrap();
+
+        let release = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(120)).await;
+            sqlx::query("COMMIT").execute(&mut other).await.unwrap();
+        });
+        db.clear_scheduler_state("z1").await.unwrap();
+        release.await.unwrap();
+        assert!(db.retried_statements() >= 1);
+
+        // A lock held past the last retry still fails.
+        let mut other = sqlx::SqliteConnection::connect(&db_url).await.unwrap();
+        sqlx::query("BEGIN EXCLUSIVE")
+            .execute(&mut other)
+            .await
+            .unwrap();
+        let err = db.mark_valve_closed("z1").await.unwrap_err();
+        assert!(format!("{err:#}").contains("locked"), "{err:#}");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn write_probe_succeeds_repeatedly() {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        db.write_probe().await.unwrap();
+        db.write_probe().await.unwrap();
+    }
+
     // -- db_file_path -------------------------------------------------------
 
     #[test]
@@ -995,20 +5634,7 @@ mod tests {
         // Create and populate
         let db = Db::connect(&db_url).await.unwrap();
         db.migrate().await.unwrap();
-        db.upsert_zone(&ZoneConfig {
-            zone_id: "z1".into(),
-            name: "Test".into(),
-            min_moisture: 0.3,
-            target_moisture: 0.5,
-            pulse_sec: 30,
-            soak_min: 20,
-            max_open_sec_per_day: 180,
-            max_pulses_per_day: 6,
-            stale_timeout_min: 30,
-            valve_gpio_pin: 17,
-        })
-        .await
-        .unwrap();
+        db.upsert_zone(&ZoneConfig::test("z1")).await.unwrap();
 
         // Backup
         let backup_str = backup_path.to_str().unwrap();
@@ -1034,4 +5660,169 @@ mod tests {
         drop(db);
         let _ = std::fs::remove_dir_all(&dir);
     }
+
+    #[tokio::test]
+    async fn incremental_backup_mirrors_changes() {
+        let dir = std::env::temp_dir().join(format!(
+            "irrigation_incremental_backup_{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let backup_path = dir.join("backup.db");
+        let backup_str = backup_path.to_str().unwrap();
+
+        let zone = |zone_id: &str, name: &str| ZoneConfig {
+            name: name.into(),
+            ..ZoneConfig::test(zone_id)
+        };
+
+        let db_url = format!("sqlite:{}?mode=rwc", dir.join("live.db").display());
+        let db = Db::connect(&db_url).await.unwrap();
+        db.migrate().await.unwrap();
+        // No backup to update yet.
+        assert!(!db.backup_incremental(backup_str).await.unwrap());
+
+        db.upsert_zone(&zone("z1", "Front")).await.unwrap();
+        db.upsert_zone(&zone("z2", "Back")).await.unwrap();
+        db.insert_disturbance("z2", 1000, None, "probe cleaning")
+            .await
+            .unwrap();
+        db.backup(backup_str).await.unwrap();
+
+        // Update, insert (into an AUTOINCREMENT table) and delete.
+        db.upsert_zone(&zone("z1", "Front Lawn")).await.unwrap();
+        db.upsert_zone(&zone("z3", "Side")).await.unwrap();
+        db.insert_disturbance("z1", 2000, Some(2600), "mowing")
+            .await
+            .unwrap();
+        let old = db.list_disturbances("z2").await.unwrap();
+        db.delete_disturbance("z2", old[0].id).await.unwrap();
+        db.delete_zone("z2").await.unwrap();
+        assert!(db.backup_incremental(backup_str).await.unwrap());
+
+        let backup = Db::connect(&format!("sqlite:{backup_str}")).await.unwrap();
+        let names: Vec<(String, String)> = backup
+            .load_zones()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|z| (z.zone_id, z.name))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("z1".to_string(), "Front Lawn".to_string()),
+                ("z3".to_string(), "Side".to_string())
+            ]
+        );
+        assert_eq!(backup.list_disturbances("z1").await.unwrap().len(), 1);
+        assert!(backup.list_disturbances("z2").await.unwrap().is_empty());
+        backup.pool.close().await;
+
+        // A later schema change needs a full backup.
+        sqlx::query("CREATE TABLE extra (x INTEGER)")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        assert!(!db.backup_incremental(backup_str).await.unwrap());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn restore_from_replaces_live_contents() {
+        let dir =
+            std::env::temp_dir().join(format!("irrigation_restore_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let backup_path = dir.join("backup.db");
+        let backup_str = backup_path.to_str().unwrap();
+
+        let db_url = format!("sqlite:{}?mode=rwc", dir.join("live.db").display());
+        let db = Db::connect(&db_url).await.unwrap();
+        db.migrate().await.unwrap();
+        db.upsert_zone(&ZoneConfig::test("z1")).await.unwrap();
+        db.upsert_sensor(&SensorConfig {
+            sensor_id: "n1/s1".into(),
+            node_id: "n1".into(),
+            zone_id: "z1".into(),
+            raw_dry: 26000,
+            raw_wet: 12000,
+            archived_at: None,
+            channel: None,
+            weight: 1.0,
+            failure_margin: None,
+            failure_margin_pct: None,
+            curve: None,
+            enabled: true,
+        })
+        .await
+        .unwrap();
+        db.backup(backup_str).await.unwrap();
+
+        // Diverge after the backup: the sensor goes, a zone is added.
+        db.delete_sensor("n1/s1").await.unwrap();
+        db.upsert_zone(&ZoneConfig::test("z2")).await.unwrap();
+
+        let rows = db.restore_from(backup_str).await.unwrap();
+        assert!(rows >= 2);
+        let zones: Vec<String> = db
+            .load_zones()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|z| z.zone_id)
+            .collect();
+        assert_eq!(zones, vec!["z1"]);
+        assert_eq!(db.load_sensors().await.unwrap().len(), 1);
+
+        // A file that isn't a database fails without touching anything.
+        let junk = dir.join("junk.db");
+        std::fs::write(&junk, b"definitely not sqlite").unwrap();
+        assert!(db.restore_from(junk.to_str().unwrap()).await.is_err());
+        assert_eq!(db.load_zones().await.unwrap().len(), 1);
+        assert!(!dir.join("junk.db.restore").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn sensor_health_quarantines_after_consecutive_failures() {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+
+        assert!(!db.record_sensor_failure("n1/s1", 100, 3).await.unwrap());
+        assert!(!db.record_sensor_failure("n1/s1", 200, 3).await.unwrap());
+        // A plausible reading breaks the run.
+        db.record_sensor_ok("n1/s1").await.unwrap();
+        assert!(!db.record_sensor_failure("n1/s1", 300, 3).await.unwrap());
+        assert!(!db.record_sensor_failure("n1/s1", 400, 3).await.unwrap());
+        assert!(db.record_sensor_failure("n1/s1", 500, 3).await.unwrap());
+        // Only the failure that crossed the threshold reports it.
+        assert!(!db.record_sensor_failure("n1/s1", 600, 3).await.unwrap());
+
+        let health = db.list_sensor_health().await.unwrap();
+        assert_eq!(
+            health,
+            vec![SensorHealth {
+                sensor_id: "n1/s1".into(),
+                consecutive_failures: 4,
+                total_failures: 6,
+                last_failure_ts: Some(600),
+                quarantined_ts: Some(500),
+            }]
+        );
+
+        // Plausible readings don't lift a quarantine; a release does.
+        db.record_sensor_ok("n1/s1").await.unwrap();
+        assert!(db.list_sensor_health().await.unwrap()[0]
+            .quarantined_ts
+            .is_some());
+        assert!(db.release_sensor("n1/s1").await.unwrap());
+        assert!(!db.release_sensor("n1/s1").await.unwrap());
+        let health = &db.list_sensor_health().await.unwrap()[0];
+        assert_eq!(health.quarantined_ts, None);
+        assert_eq!(health.total_failures, 6);
+    }
 }
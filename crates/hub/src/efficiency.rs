@@ -0,0 +1,339 @@
+//! Watering efficiency: how much moisture a zone's water actually buys,
+//! scored per day so a trend shows which zones need hardware attention and
+//! which need tuning.
+//!
+//! Each watering event (flushes excluded) is compared against the zone's
+//! mean moisture in the half hour before it opened and in its soak window
+//! after it closed.  Two parts make up the score:
+//!
+//! - `delivery`: moisture gained per minute of water, against what the
+//!   zone's own config expects — a full refill from `min_moisture` to
+//!   `target_moisture` within `max_open_sec_per_day`.  Capped at 1.  Low
+//!   delivery means water isn't reaching the soil around the sensors: a
+//!   clog, a leak or a misplaced sensor.
+//! - `pulse_use`: the share of pulses that raised moisture measurably.  Low
+//!   pulse use with good delivery points at tuning (pulses too short, soak
+//!   too short, or watering a zone that is already wet).
+//!
+//! `score` is `100 × delivery × pulse_use`.  Events without readings on
+//! both sides (pruned, or the sensors were offline) are counted as water
+//! but left out of the score.
+//!
+//! `soak_effectiveness` looks at a zone's last N events instead of a date
+//! range: moisture rise per second of open valve, and whether the newer
+//! half of those events does markedly worse than the older half.
+
+use serde::Serialize;
+
+use crate::db::ZoneConfig;
+
+/// A pulse raising zone moisture by less than this counts as wasted.
+pub const MIN_EFFECTIVE_RISE: f64 = 0.01;
+
+/// Readings this long before a pulse opens give its starting moisture.
+pub const BEFORE_WINDOW_SEC: i64 = 1800;
+
+/// Shortest after-pulse window, for zones with a very short soak.
+pub const MIN_AFTER_WINDOW_SEC: i64 = 600;
+
+/// Soak effectiveness falling by this share or more between the older and
+/// newer half of the events is flagged as declining.
+pub const DECLINE_FRACTION: f64 = 0.25;
+
+/// Measured events needed before a decline is flagged.
+pub const MIN_DECLINE_EVENTS: usize = 4;
+
+/// One watering event and the zone's moisture around it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PulseOutcome {
+    pub zone_id: String,
+    /// "YYYY-MM-DD" (UTC) the event started.
+    pub day: String,
+    pub open_sec: i64,
+    pub before: Option<f64>,
+    pub after: Option<f64>,
+}
+
+impl PulseOutcome {
+    fn rise(&self) -> Option<f64> {
+        Some(self.after? - self.before?)
+    }
+}
+
+/// Efficiency over a set of pulses (a day, or a whole period).
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Efficiency {
+    pub open_sec: i64,
+    pub pulses: i64,
+    /// Only present when the zone has a measured `flow_lpm`.
+    pub litres: Option<f64>,
+    /// Pulses with moisture readings on both sides.
+    pub measured_pulses: i64,
+    /// Measured pulses that raised moisture by `MIN_EFFECTIVE_RISE` or more.
+    pub effective_pulses: i64,
+    /// Sum of the measured pulses' moisture rises (falls count as 0).
+    pub moisture_gain: Option<f64>,
+    pub delivery: Option<f64>,
+    pub pulse_use: Option<f64>,
+    /// 0–100; `None` without measured pulses.
+    pub score: Option<f64>,
+}
+
+impl Efficiency {
+    fn new(zone: &ZoneConfig, pulses: &[&PulseOutcome]) -> Self {
+        let open_sec: i64 = pulses.iter().map(|p| p.open_sec).sum();
+        let measured: Vec<(i64, f64)> = pulses
+            .iter()
+            .filter_map(|p| Some((p.open_sec, p.rise()?)))
+            .collect();
+        let mut e = Self {
+            open_sec,
+            pulses: pulses.len() as i64,
+            litres: zone
+                .flow_lpm
+                .map(|lpm| open_sec as f64 * f64::from(lpm) / 60.0),
+            measured_pulses: measured.len() as i64,
+            effective_pulses: measured
+                .iter()
+                .filter(|(_, rise)| *rise >= MIN_EFFECTIVE_RISE)
+                .count() as i64,
+            ..Self::default()
+        };
+        if measured.is_empty() {
+            return e;
+        }
+
+        let gain: f64 = measured.iter().map(|(_, rise)| rise.max(0.0)).sum();
+        let minutes = measured.iter().map(|(sec, _)| *sec).sum::<i64>() as f64 / 60.0;
+        let expected_per_min = f64::from(zone.target_moisture - zone.min_moisture)
+            / (zone.max_open_sec_per_day as f64 / 60.0);
+        let delivery = if minutes > 0.0 && expected_per_min > 0.0 {
+            (gain / minutes / expected_per_min).min(1.0)
+        } else {
+            0.0
+        };
+        let pulse_use = e.effective_pulses as f64 / e.measured_pulses as f64;
+
+        e.moisture_gain = Some(gain);
+        e.delivery = Some(delivery);
+        e.pulse_use = Some(pulse_use);
+        e.score = Some((100.0 * delivery * pulse_use * 10.0).round() / 10.0);
+        e
+    }
+}
+
+/// One day of a zone's efficiency.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DailyEfficiency {
+    pub day: String,
+    #[serde(flatten)]
+    pub efficiency: Efficiency,
+}
+
+/// A zone's efficiency over a period, with its daily trend.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ZoneEfficiency {
+    pub zone_id: String,
+    #[serde(flatten)]
+    pub period: Efficiency,
+    /// Mean daily score over the later half of the scored days minus the
+    /// earlier half; negative when the zone is getting worse.  `None` with
+    /// fewer than two scored days.
+    pub trend: Option<f64>,
+    /// Days with watering, oldest first.
+    pub days: Vec<DailyEfficiency>,
+}
+
+/// Summarise `outcomes` (one zone's, oldest first) for `zone`.
+pub fn summarize(zone: &ZoneConfig, outcomes: &[PulseOutcome]) -> ZoneEfficiency {
+    let mut days: Vec<DailyEfficiency> = Vec::new();
+    let mut start = 0;
+    while start < outcomes.len() {
+        let day = &outcomes[start].day;
+        let end = start
+            + outcomes[start..]
+                .iter()
+                .take_while(|o| &o.day == day)
+                .count();
+        let pulses: Vec<&PulseOutcome> = outcomes[start..end].iter().collect();
+        days.push(DailyEfficiency {
+            day: day.clone(),
+            efficiency: Efficiency::new(zone, &pulses),
+        });
+        start = end;
+    }
+
+    let scores: Vec<f64> = days.iter().filter_map(|d| d.efficiency.score).collect();
+    let trend = (scores.len() >= 2).then(|| {
+        let (earlier, later) = scores.split_at(scores.len() / 2);
+        let mean = |s: &[f64]| s.iter().sum::<f64>() / s.len() as f64;
+        ((mean(later) - mean(earlier)) * 10.0).round() / 10.0
+    });
+
+    let all: Vec<&PulseOutcome> = outcomes.iter().collect();
+    ZoneEfficiency {
+        zone_id: zone.zone_id.clone(),
+        period: Efficiency::new(zone, &all),
+        trend,
+        days,
+    }
+}
+
+/// How well a zone's recent events wetted the soil.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SoakEffectiveness {
+    pub zone_id: String,
+    pub events: i64,
+    /// Events with moisture readings on both sides.
+    pub measured_events: i64,
+    /// Moisture rise per second of open valve over the measured events
+    /// (falls count as 0).  `None` without measured events.
+    pub rise_per_sec: Option<f64>,
+    /// The same over the older and newer half of the measured events.
+    pub earlier_rise_per_sec: Option<f64>,
+    pub later_rise_per_sec: Option<f64>,
+    /// `later / earlier - 1`; -0.3 is a 30% drop.
+    pub change: Option<f64>,
+    /// Dropped by `DECLINE_FRACTION` or more: check for clogged emitters
+    /// or a coverage problem.
+    pub declining: bool,
+}
+
+/// Rise per open second of `outcomes` (one zone's last events, oldest
+/// first).
+pub fn soak_effectiveness(zone_id: &str, outcomes: &[PulseOutcome]) -> SoakEffectiveness {
+    let measured: Vec<(i64, f64)> = outcomes
+        .iter()
+        .filter_map(|p| Some((p.open_sec, p.rise()?.max(0.0))))
+        .collect();
+    let rate = |m: &[(i64, f64)]| {
+        let sec: i64 = m.iter().map(|(sec, _)| *sec).sum();
+        (sec > 0).then(|| m.iter().map(|(_, rise)| rise).sum::<f64>() / sec as f64)
+    };
+
+    let (earlier, later) = if measured.len() >= 2 {
+        let (e, l) = measured.split_at(measured.len() / 2);
+        (rate(e), rate(l))
+    } else {
+        (None, None)
+    };
+    let change = match (earlier, later) {
+        (Some(e), Some(l)) if e > 0.0 => Some(l / e - 1.0),
+        _ => None,
+    };
+    SoakEffectiveness {
+        zone_id: zone_id.to_string(),
+        events: outcomes.len() as i64,
+        measured_events: measured.len() as i64,
+        rise_per_sec: rate(&measured),
+        earlier_rise_per_sec: earlier,
+        later_rise_per_sec: later,
+        change,
+        declining: measured.len() >= MIN_DECLINE_EVENTS
+            && change.is_some_and(|c| c <= -DECLINE_FRACTION),
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Expects 0.2 moisture from 180 s of water: 1/15 per minute.
+    fn zone() -> ZoneConfig {
+        ZoneConfig {
+            flow_lpm: Some(6.0),
+            ..ZoneConfig::test("z1")
+        }
+    }
+
+    fn pulse(day: &str, before: Option<f64>, after: Option<f64>) -> PulseOutcome {
+        PulseOutcome {
+            zone_id: "z1".into(),
+            day: day.into(),
+            open_sec: 30,
+            before,
+            after,
+        }
+    }
+
+    #[test]
+    fn score_combines_delivery_and_pulse_use() {
+        let outcomes = [
+            // Two pulses, each 0.04 for half a minute: 0.08/min, above the
+            // expected 1/15 — delivery capped at 1.
+            pulse("2025-06-01", Some(0.30), Some(0.34)),
+            pulse("2025-06-01", Some(0.34), Some(0.38)),
+            // One good pulse, one that did nothing, one unmeasured.
+            pulse("2025-06-02", Some(0.30), Some(0.32)),
+            pulse("2025-06-02", Some(0.32), Some(0.32)),
+            pulse("2025-06-02", None, Some(0.33)),
+        ];
+        let z = summarize(&zone(), &outcomes);
+
+        assert_eq!(z.days.len(), 2);
+        let d1 = &z.days[0].efficiency;
+        assert_eq!(d1.pulses, 2);
+        assert_eq!(d1.litres, Some(6.0));
+        assert_eq!(d1.delivery, Some(1.0));
+        assert_eq!(d1.score, Some(100.0));
+
+        let d2 = &z.days[1].efficiency;
+        assert_eq!(
+            (d2.pulses, d2.measured_pulses, d2.effective_pulses),
+            (3, 2, 1)
+        );
+        // 0.02 over one minute against 1/15: 0.3.
+        assert!((d2.delivery.unwrap() - 0.3).abs() < 1e-6);
+        assert_eq!(d2.pulse_use, Some(0.5));
+        assert_eq!(d2.score, Some(15.0));
+        assert_eq!(z.trend, Some(-85.0));
+        assert_eq!(z.period.pulses, 5);
+    }
+
+    #[test]
+    fn unmeasured_days_have_no_score() {
+        let z = summarize(&zone(), &[pulse("2025-06-01", Some(0.3), None)]);
+        assert_eq!(z.days[0].efficiency.open_sec, 30);
+        assert_eq!(z.days[0].efficiency.score, None);
+        assert_eq!(z.trend, None);
+
+        let z = summarize(&zone(), &[]);
+        assert!(z.days.is_empty());
+        assert_eq!(z.period.score, None);
+    }
+
+    #[test]
+    fn soak_effectiveness_flags_a_decline() {
+        let rise = |r: f64| pulse("2025-06-01", Some(0.30), Some(0.30 + r));
+        let outcomes = [
+            rise(0.06),
+            rise(0.06),
+            pulse("2025-06-02", None, Some(0.33)),
+            rise(0.03),
+            // A fall counts as no rise.
+            rise(-0.01),
+        ];
+        let s = soak_effectiveness("z1", &outcomes);
+        assert_eq!((s.events, s.measured_events), (5, 4));
+        assert!((s.rise_per_sec.unwrap() - 0.15 / 120.0).abs() < 1e-9);
+        assert!((s.earlier_rise_per_sec.unwrap() - 0.002).abs() < 1e-9);
+        assert!((s.later_rise_per_sec.unwrap() - 0.0005).abs() < 1e-9);
+        assert!((s.change.unwrap() + 0.75).abs() < 1e-9);
+        assert!(s.declining);
+
+        // Steady zones and too few events aren't flagged.
+        let s = soak_effectiveness("z1", &[rise(0.04), rise(0.05), rise(0.04), rise(0.04)]);
+        assert!(!s.declining, "{s:?}");
+        let s = soak_effectiveness("z1", &[rise(0.06), rise(0.01)]);
+        assert!(s.change.unwrap() < -0.8);
+        assert!(!s.declining);
+
+        let s = soak_effectiveness("z1", &[]);
+        assert_eq!(s.rise_per_sec, None);
+        assert_eq!(s.change, None);
+    }
+}
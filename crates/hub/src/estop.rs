@@ -0,0 +1,190 @@
+//! Emergency stop: a physical button wired to a GPIO input, for when a pipe
+//! bursts and the person next to the box has no laptop.
+//!
+//! A debounced press turns every valve off and latches a lockout that
+//! refuses all valve ON commands, scheduler or MQTT.  The latch is stored in
+//! `hub_meta` so a restart doesn't quietly resume watering; it is cleared
+//! only through `POST /api/emergency-stop/clear`, and not while the button
+//! is still held down.  The `gpio` and `gpiod` features gate the real
+//! input (see `gpio`); without either, a mock input that is never pressed
+//! stands in.
+
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::gpio::GpioConfig;
+#[cfg(any(feature = "gpio", feature = "gpiod"))]
+use crate::gpio::InputLine;
+
+/// How often the input is sampled.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Lockout state, shown under `emergency_stop` in `/api/status`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EmergencyStop {
+    /// Whether a button is configured.
+    pub configured: bool,
+    /// Whether the button is held down right now (debounced).
+    pub pressed: bool,
+    /// Unix seconds of the press that latched the lockout; unset when
+    /// valves may open.
+    pub latched_since: Option<i64>,
+}
+
+impl EmergencyStop {
+    pub fn is_latched(&self) -> bool {
+        self.latched_since.is_some()
+    }
+
+    /// Latch the lockout.  Returns false if it already was.
+    pub fn latch(&mut self, ts: i64) -> bool {
+        if self.is_latched() {
+            return false;
+        }
+        self.latched_since = Some(ts);
+        true
+    }
+
+    /// Clear the lockout, unless the button is still held down.
+    pub fn clear(&mut self) -> Result<(), &'static str> {
+        if !self.is_latched() {
+            return Err("emergency stop is not latched");
+        }
+        if self.pressed {
+            return Err("emergency stop button is still pressed");
+        }
+        self.latched_since = None;
+        Ok(())
+    }
+}
+
+/// Turns raw input samples into press / release edges: a level must hold
+/// for `debounce` before it counts, so contact bounce and wiring noise
+/// don't trip the stop.
+#[derive(Debug, Clone)]
+pub struct Debouncer {
+    debounce: Duration,
+    stable: bool,
+    /// A level differing from `stable`, and when it was first seen.
+    candidate: Option<(bool, Instant)>,
+}
+
+impl Debouncer {
+    pub fn new(debounce: Duration, initial: bool) -> Self {
+        Self {
+            debounce,
+            stable: initial,
+            candidate: None,
+        }
+    }
+
+    /// Feed a sample; returns the new level when it has settled.
+    pub fn update(&mut self, level: bool, now: Instant) -> Option<bool> {
+        if level == self.stable {
+            self.candidate = None;
+            return None;
+        }
+        match self.candidate {
+            Some((_, since)) if now.duration_since(since) >= self.debounce => {
+                self.stable = level;
+                self.candidate = None;
+                Some(level)
+            }
+            Some(_) => None,
+            None => {
+                self.candidate = Some((level, now));
+                None
+            }
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Real GPIO input (production — requires rppal or a GPIO chardev)
+// ---------------------------------------------------------------------------
+#[cfg(any(feature = "gpio", feature = "gpiod"))]
+pub struct StopInput {
+    pin: InputLine,
+    active_low: bool,
+}
+
+#[cfg(any(feature = "gpio", feature = "gpiod"))]
+impl StopInput {
+    /// Claim `pin_num` as an input, pulled to its released level: up when
+    /// the button shorts to ground, down when it shorts to 3.3 V.
+    pub fn new(gpio: &GpioConfig, pin_num: u8, active_low: bool) -> Result<Self> {
+        let pin = InputLine::claim(gpio, pin_num, active_low)?;
+        Ok(Self { pin, active_low })
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        self.pin.is_high() != self.active_low
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Mock input (development — no hardware, never pressed)
+// ---------------------------------------------------------------------------
+#[cfg(not(any(feature = "gpio", feature = "gpiod")))]
+pub struct StopInput;
+
+#[cfg(not(any(feature = "gpio", feature = "gpiod")))]
+impl StopInput {
+    pub fn new(_gpio: &GpioConfig, pin_num: u8, _active_low: bool) -> Result<Self> {
+        tracing::info!(gpio = pin_num, "[mock] emergency stop input (not wired)");
+        Ok(Self)
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        false
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debounce_ignores_bounce() {
+        let t0 = Instant::now();
+        let ms = |n| t0 + Duration::from_millis(n);
+        let mut d = Debouncer::new(Duration::from_millis(50), false);
+
+        // Bouncing contact: never holds for 50 ms.
+        assert_eq!(d.update(true, ms(0)), None);
+        assert_eq!(d.update(false, ms(10)), None);
+        assert_eq!(d.update(true, ms(20)), None);
+        assert_eq!(d.update(true, ms(60)), None);
+        // Held from 20 ms: settles at 70 ms.
+        assert_eq!(d.update(true, ms(70)), Some(true));
+        assert_eq!(d.update(true, ms(80)), None);
+
+        assert_eq!(d.update(false, ms(100)), None);
+        assert_eq!(d.update(false, ms(150)), Some(false));
+    }
+
+    #[test]
+    fn latch_clears_only_once_released() {
+        let mut stop = EmergencyStop {
+            configured: true,
+            ..EmergencyStop::default()
+        };
+        assert_eq!(stop.clear(), Err("emergency stop is not latched"));
+
+        stop.pressed = true;
+        assert!(stop.latch(1000));
+        assert!(!stop.latch(1010));
+        assert_eq!(stop.latched_since, Some(1000));
+        assert_eq!(stop.clear(), Err("emergency stop button is still pressed"));
+
+        stop.pressed = false;
+        assert_eq!(stop.clear(), Ok(()));
+        assert!(!stop.is_latched());
+    }
+}
@@ -0,0 +1,483 @@
+//! Reference evapotranspiration (ET0) and the per-zone water balance behind
+//! the `et` watering strategy.
+//!
+//! Weather arrives on `weather/<source_id>/reading` (a weather API bridge
+//! or a local station) and outdoor temperatures on `temp/<source_id>/reading`
+//! count too.  Samples are gathered per UTC day; the first sample of the
+//! next day closes it and the day's ET0 is worked out:
+//!
+//! - `reported`: a source sent `et0_mm` for the day (e.g. from a weather
+//!   API); the last value wins.
+//! - `penman_monteith`: FAO-56 Penman-Monteith, when the day has min/max
+//!   temperature, humidity and solar radiation.  Wind defaults to
+//!   `[et] wind_m_s` if no sample carried one.
+//! - `hargreaves`: Hargreaves-Samani from min/max temperature alone.
+//!
+//! The computed methods need `[et] latitude`.  Each `et` zone keeps a
+//! deficit in mm: every closed day adds `ET0 × crop_coefficient` and
+//! subtracts the rain, and every watering subtracts the depth applied
+//! (`application_rate_mm_hr` × open time).  The deficit never goes below
+//! zero; excess water drains away.
+
+use serde::{Deserialize, Serialize};
+use time::{Date, OffsetDateTime};
+
+/// Stefan-Boltzmann constant, MJ K⁻⁴ m⁻² day⁻¹.
+const STEFAN_BOLTZMANN: f64 = 4.903e-9;
+
+/// Solar constant, MJ m⁻² min⁻¹.
+const SOLAR_CONSTANT: f64 = 0.0820;
+
+fn default_wind_m_s() -> f64 {
+    2.0
+}
+
+/// `[et]` in `config.toml`.
+///
+/// ```toml
+/// [et]
+/// latitude = 45.5
+/// elevation_m = 50
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct EtConfig {
+    /// Degrees, north positive.  Needed to compute ET0 from weather.
+    #[serde(default)]
+    pub latitude: Option<f64>,
+    #[serde(default)]
+    pub elevation_m: f64,
+    /// Wind speed at 2 m used when no sample reports one.
+    #[serde(default = "default_wind_m_s")]
+    pub wind_m_s: f64,
+}
+
+impl Default for EtConfig {
+    fn default() -> Self {
+        Self {
+            latitude: None,
+            elevation_m: 0.0,
+            wind_m_s: default_wind_m_s(),
+        }
+    }
+}
+
+impl EtConfig {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errs = Vec::new();
+        if let Some(lat) = self.latitude {
+            if !(-90.0..=90.0).contains(&lat) {
+                errs.push(format!("et: latitude must be within -90..=90, got {lat}"));
+            }
+        }
+        if !(-500.0..=9000.0).contains(&self.elevation_m) {
+            errs.push(format!(
+                "et: elevation_m must be within -500..=9000, got {}",
+                self.elevation_m
+            ));
+        }
+        if !self.wind_m_s.is_finite() || self.wind_m_s < 0.0 {
+            errs.push(format!(
+                "et: wind_m_s must not be negative, got {}",
+                self.wind_m_s
+            ));
+        }
+        if errs.is_empty() {
+            Ok(())
+        } else {
+            Err(errs)
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Daily weather
+// ---------------------------------------------------------------------------
+
+/// One weather sample.  Every field but `ts` is optional.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Observation {
+    pub ts: i64,
+    pub temp_c: Option<f64>,
+    pub humidity_pct: Option<f64>,
+    pub solar_w_m2: Option<f64>,
+    pub wind_m_s: Option<f64>,
+    /// Rain since the source's previous sample.
+    pub rain_mm: Option<f64>,
+    /// The day's reference ET, from a source that already knows it.
+    pub et0_mm: Option<f64>,
+}
+
+/// Running totals for one UTC day.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeatherDay {
+    pub date: Date,
+    pub temp_min_c: Option<f64>,
+    pub temp_max_c: Option<f64>,
+    humidity: Mean,
+    solar: Mean,
+    wind: Mean,
+    pub rain_mm: f64,
+    pub reported_et0_mm: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Mean {
+    sum: f64,
+    n: u32,
+}
+
+impl Mean {
+    fn add(&mut self, v: Option<f64>) {
+        if let Some(v) = v {
+            self.sum += v;
+            self.n += 1;
+        }
+    }
+
+    fn get(&self) -> Option<f64> {
+        (self.n > 0).then(|| self.sum / f64::from(self.n))
+    }
+}
+
+impl WeatherDay {
+    fn new(date: Date) -> Self {
+        Self {
+            date,
+            temp_min_c: None,
+            temp_max_c: None,
+            humidity: Mean::default(),
+            solar: Mean::default(),
+            wind: Mean::default(),
+            rain_mm: 0.0,
+            reported_et0_mm: None,
+        }
+    }
+
+    fn add(&mut self, obs: &Observation) {
+        if let Some(t) = obs.temp_c {
+            self.temp_min_c = Some(self.temp_min_c.map_or(t, |m| m.min(t)));
+            self.temp_max_c = Some(self.temp_max_c.map_or(t, |m| m.max(t)));
+        }
+        self.humidity.add(obs.humidity_pct);
+        self.solar.add(obs.solar_w_m2);
+        self.wind.add(obs.wind_m_s);
+        self.rain_mm += obs.rain_mm.unwrap_or(0.0);
+        if obs.et0_mm.is_some() {
+            self.reported_et0_mm = obs.et0_mm;
+        }
+    }
+
+    /// The day's ET0, or `None` if the samples (or the config) aren't
+    /// enough to work it out.
+    pub fn et0(&self, cfg: &EtConfig) -> Option<EtDay> {
+        let day = |et0_mm: f64, method| EtDay {
+            date: self.date.to_string(),
+            et0_mm: et0_mm.max(0.0),
+            rain_mm: self.rain_mm,
+            method,
+        };
+        if let Some(et0) = self.reported_et0_mm {
+            return Some(day(et0, EtMethod::Reported));
+        }
+        let (tmin, tmax) = (self.temp_min_c?, self.temp_max_c?);
+        let ra = extraterrestrial_radiation(cfg.latitude?, self.date.ordinal());
+        match (self.humidity.get(), self.solar.get()) {
+            (Some(rh), Some(solar)) => {
+                let wind = self.wind.get().unwrap_or(cfg.wind_m_s);
+                // Mean W/m² over the whole day → MJ/m²/day.
+                let rs = solar * 0.0864;
+                Some(day(
+                    penman_monteith(tmin, tmax, rh, rs, wind, ra, cfg.elevation_m),
+                    EtMethod::PenmanMonteith,
+                ))
+            }
+            _ => Some(day(hargreaves(tmin, tmax, ra), EtMethod::Hargreaves)),
+        }
+    }
+}
+
+/// Gathers samples into the current UTC day.
+#[derive(Debug, Clone, Default)]
+pub struct Weather {
+    today: Option<WeatherDay>,
+}
+
+impl Weather {
+    /// Add a sample.  Returns the previous day once a sample from a later
+    /// day arrives.  Samples from a day already closed are dropped.
+    pub fn record(&mut self, obs: &Observation) -> Option<WeatherDay> {
+        let date = OffsetDateTime::from_unix_timestamp(obs.ts).ok()?.date();
+        let mut closed = None;
+        match &self.today {
+            Some(day) if date < day.date => return None,
+            Some(day) if date > day.date => closed = self.today.take(),
+            _ => {}
+        }
+        self.today
+            .get_or_insert_with(|| WeatherDay::new(date))
+            .add(obs);
+        closed
+    }
+
+    /// The day being gathered.
+    pub fn today(&self) -> Option<&WeatherDay> {
+        self.today.as_ref()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// ET0
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EtMethod {
+    Reported,
+    PenmanMonteith,
+    Hargreaves,
+}
+
+impl EtMethod {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "reported" => Some(Self::Reported),
+            "penman_monteith" => Some(Self::PenmanMonteith),
+            "hargreaves" => Some(Self::Hargreaves),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Reported => "reported",
+            Self::PenmanMonteith => "penman_monteith",
+            Self::Hargreaves => "hargreaves",
+        }
+    }
+}
+
+/// A closed day, as stored in `et_daily`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EtDay {
+    /// `YYYY-MM-DD`, UTC.
+    pub date: String,
+    pub et0_mm: f64,
+    pub rain_mm: f64,
+    pub method: EtMethod,
+}
+
+/// Extraterrestrial radiation Ra (MJ/m²/day) for a latitude and day of
+/// the year, FAO-56 eq. 21.
+pub fn extraterrestrial_radiation(latitude: f64, day_of_year: u16) -> f64 {
+    let j = f64::from(day_of_year);
+    let phi = latitude.to_radians();
+    let dr = 1.0 + 0.033 * (2.0 * std::f64::consts::PI * j / 365.0).cos();
+    let delta = 0.409 * (2.0 * std::f64::consts::PI * j / 365.0 - 1.39).sin();
+    // Clamped for polar day and night.
+    let ws = (-phi.tan() * delta.tan()).clamp(-1.0, 1.0).acos();
+    24.0 * 60.0 / std::f64::consts::PI
+        * SOLAR_CONSTANT
+        * dr
+        * (ws * phi.sin() * delta.sin() + phi.cos() * delta.cos() * ws.sin())
+}
+
+/// Hargreaves-Samani ET0 (mm/day) from the day's temperature range.
+pub fn hargreaves(tmin: f64, tmax: f64, ra: f64) -> f64 {
+    let tmean = (tmin + tmax) / 2.0;
+    0.0023 * (tmean + 17.8) * (tmax - tmin).max(0.0).sqrt() * 0.408 * ra
+}
+
+/// Saturation vapour pressure (kPa) at `t` °C.
+fn saturation_vp(t: f64) -> f64 {
+    0.6108 * (17.27 * t / (t + 237.3)).exp()
+}
+
+/// FAO-56 Penman-Monteith ET0 (mm/day) for a grass reference, with the
+/// soil heat flux taken as zero over a day.  `rs` is the measured solar
+/// radiation in MJ/m²/day.
+pub fn penman_monteith(
+    tmin: f64,
+    tmax: f64,
+    rh_mean: f64,
+    rs: f64,
+    wind_m_s: f64,
+    ra: f64,
+    elevation_m: f64,
+) -> f64 {
+    let tmean = (tmin + tmax) / 2.0;
+    let delta = 4098.0 * saturation_vp(tmean) / (tmean + 237.3).powi(2);
+    let pressure = 101.3 * ((293.0 - 0.0065 * elevation_m) / 293.0).powf(5.26);
+    let gamma = 0.000665 * pressure;
+    let es = (saturation_vp(tmax) + saturation_vp(tmin)) / 2.0;
+    let ea = rh_mean.clamp(0.0, 100.0) / 100.0 * es;
+
+    let rso = (0.75 + 2e-5 * elevation_m) * ra;
+    let cloudiness = if rso > 0.0 { (rs / rso).min(1.0) } else { 0.0 };
+    let rns = 0.77 * rs;
+    let rnl = STEFAN_BOLTZMANN * ((tmax + 273.16).powi(4) + (tmin + 273.16).powi(4)) / 2.0
+        * (0.34 - 0.14 * ea.sqrt())
+        * (1.35 * cloudiness - 0.35);
+    let rn = rns - rnl;
+
+    (0.408 * delta * rn + gamma * 900.0 / (tmean + 273.0) * wind_m_s * (es - ea))
+        / (delta + gamma * (1.0 + 0.34 * wind_m_s))
+}
+
+// ---------------------------------------------------------------------------
+// Water balance
+// ---------------------------------------------------------------------------
+
+/// A zone's deficit after a closed day.
+pub fn add_day(deficit_mm: f64, day: &EtDay, crop_coefficient: f64) -> f64 {
+    (deficit_mm + day.et0_mm * crop_coefficient - day.rain_mm).max(0.0)
+}
+
+/// Depth of water (mm) applied by `secs` of watering.
+pub fn applied_mm(application_rate_mm_hr: f64, secs: i64) -> f64 {
+    application_rate_mm_hr * secs.max(0) as f64 / 3600.0
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::{date, datetime};
+
+    fn obs(ts: OffsetDateTime, temp_c: f64) -> Observation {
+        Observation {
+            ts: ts.unix_timestamp(),
+            temp_c: Some(temp_c),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn extraterrestrial_radiation_matches_fao56() {
+        // FAO-56 example 8: 20°S on 3 September.
+        let ra = extraterrestrial_radiation(-20.0, 246);
+        assert!((ra - 32.2).abs() < 0.1, "{ra}");
+        // Polar night.
+        assert!(extraterrestrial_radiation(80.0, 355).abs() < 1e-9);
+    }
+
+    #[test]
+    fn penman_monteith_matches_fao56() {
+        // FAO-56 example 18: Brussels, 6 July, measured Rs 22.07 MJ/m².
+        let ra = extraterrestrial_radiation(50.8, 187);
+        assert!((ra - 41.09).abs() < 0.1, "{ra}");
+        let et0 = penman_monteith(12.3, 21.5, 73.5, 22.07, 2.078, ra, 100.0);
+        assert!((et0 - 3.9).abs() < 0.2, "{et0}");
+
+        let et0 = hargreaves(12.3, 21.5, ra);
+        assert!((et0 - 4.06).abs() < 0.05, "{et0}");
+    }
+
+    #[test]
+    fn day_closes_on_the_first_sample_of_the_next() {
+        let cfg = EtConfig {
+            latitude: Some(50.8),
+            ..Default::default()
+        };
+        let mut weather = Weather::default();
+        assert_eq!(
+            weather.record(&obs(datetime!(2026-07-06 05:00 UTC), 12.3)),
+            None
+        );
+        assert_eq!(
+            weather.record(&obs(datetime!(2026-07-06 14:00 UTC), 21.5)),
+            None
+        );
+        weather.record(&Observation {
+            ts: datetime!(2026-07-06 18:00 UTC).unix_timestamp(),
+            rain_mm: Some(1.5),
+            ..Default::default()
+        });
+
+        let closed = weather
+            .record(&obs(datetime!(2026-07-07 00:10 UTC), 11.0))
+            .unwrap();
+        assert_eq!(closed.date, date!(2026 - 07 - 06));
+        assert_eq!(
+            (closed.temp_min_c, closed.temp_max_c),
+            (Some(12.3), Some(21.5))
+        );
+        let day = closed.et0(&cfg).unwrap();
+        assert_eq!(day.date, "2026-07-06");
+        assert_eq!(day.method, EtMethod::Hargreaves);
+        assert_eq!(day.rain_mm, 1.5);
+
+        // A late sample for the closed day is dropped.
+        assert_eq!(
+            weather.record(&obs(datetime!(2026-07-06 23:00 UTC), 30.0)),
+            None
+        );
+        assert_eq!(weather.today().unwrap().temp_max_c, Some(11.0));
+    }
+
+    #[test]
+    fn et0_method_depends_on_the_samples() {
+        let cfg = EtConfig {
+            latitude: Some(50.8),
+            ..Default::default()
+        };
+        let mut day = WeatherDay::new(date!(2026 - 07 - 06));
+        assert_eq!(day.et0(&cfg), None);
+
+        day.add(&obs(datetime!(2026-07-06 05:00 UTC), 12.3));
+        day.add(&obs(datetime!(2026-07-06 14:00 UTC), 21.5));
+        assert_eq!(day.et0(&cfg).unwrap().method, EtMethod::Hargreaves);
+        assert_eq!(day.et0(&EtConfig::default()), None);
+
+        day.add(&Observation {
+            humidity_pct: Some(73.5),
+            solar_w_m2: Some(255.4),
+            ..Default::default()
+        });
+        let pm = day.et0(&cfg).unwrap();
+        assert_eq!(pm.method, EtMethod::PenmanMonteith);
+        assert!((pm.et0_mm - 3.9).abs() < 0.2, "{}", pm.et0_mm);
+
+        day.add(&Observation {
+            et0_mm: Some(4.4),
+            ..Default::default()
+        });
+        let reported = day.et0(&EtConfig::default()).unwrap();
+        assert_eq!(
+            (reported.method, reported.et0_mm),
+            (EtMethod::Reported, 4.4)
+        );
+    }
+
+    #[test]
+    fn water_balance() {
+        let day = EtDay {
+            date: "2026-07-06".into(),
+            et0_mm: 5.0,
+            rain_mm: 1.0,
+            method: EtMethod::Reported,
+        };
+        assert_eq!(add_day(2.0, &day, 0.8), 5.0);
+        let soaked = EtDay {
+            rain_mm: 20.0,
+            ..day
+        };
+        assert_eq!(add_day(2.0, &soaked, 0.8), 0.0);
+        assert_eq!(applied_mm(12.0, 900), 3.0);
+        assert_eq!(applied_mm(12.0, -5), 0.0);
+    }
+
+    #[test]
+    fn config_validation() {
+        assert!(EtConfig::default().validate().is_ok());
+        let bad = EtConfig {
+            latitude: Some(91.0),
+            elevation_m: 10_000.0,
+            wind_m_s: -1.0,
+        };
+        assert_eq!(bad.validate().unwrap_err().len(), 3);
+    }
+}
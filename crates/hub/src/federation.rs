@@ -0,0 +1,508 @@
+//! Federation: mirror other hubs, read-only.
+//!
+//! A hub with `[[federation.sites]]` in config.toml connects to each
+//! site's MQTT broker and subscribes to the site hub's retained
+//! `status/hub` (online/offline) and its `status/hub/heartbeat` (mode,
+//! zone moisture, open valves, nodes and the newest events).  It never
+//! publishes there, so a mirror can't water a remote garden.
+//! `GET /api/federation` combines every site's zones, nodes and events,
+//! each tagged with the site name.
+//!
+//! ```toml
+//! [federation]
+//! stale_after_sec = 180
+//!
+//! [[federation.sites]]
+//! name = "cabin"
+//! host = "cabin.example.net"
+//! port = 1883
+//! topic_prefix = "cabin"
+//! user = "mirror"
+//! password_env = "CABIN_MQTT_PASS"
+//! ```
+
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::time::Duration;
+
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::mqtt;
+use crate::state::{HubHeartbeat, SharedState, SystemEvent};
+
+/// Seconds without a heartbeat after which a site is shown as stale
+/// (three of the default heartbeat intervals).
+pub const DEFAULT_STALE_AFTER_SEC: u64 = 180;
+
+/// Pause before reconnecting to a site's broker.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct FederationConfig {
+    pub sites: Vec<SiteConfig>,
+    pub stale_after_sec: u64,
+}
+
+impl Default for FederationConfig {
+    fn default() -> Self {
+        Self {
+            sites: Vec::new(),
+            stale_after_sec: DEFAULT_STALE_AFTER_SEC,
+        }
+    }
+}
+
+/// A remote hub, reached through its MQTT broker.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SiteConfig {
+    /// Shown with everything from this site; letters, digits, `-` and `_`.
+    pub name: String,
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// The site hub's `MQTT_TOPIC_PREFIX`.
+    #[serde(default)]
+    pub topic_prefix: String,
+    #[serde(default)]
+    pub user: Option<String>,
+    /// Environment variable holding the password, so it stays out of the
+    /// config file.
+    #[serde(default)]
+    pub password_env: Option<String>,
+}
+
+fn default_port() -> u16 {
+    1883
+}
+
+impl FederationConfig {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        let mut names = HashSet::new();
+        for site in &self.sites {
+            let name = &site.name;
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                errors.push(format!(
+                    "federation: site name '{name}' must be letters, digits, '-' or '_'"
+                ));
+            }
+            if !names.insert(name.as_str()) {
+                errors.push(format!("federation: site '{name}' is listed twice"));
+            }
+            if site.host.trim().is_empty() {
+                errors.push(format!("federation: site '{name}' needs a host"));
+            }
+            if site.port == 0 {
+                errors.push(format!("federation: site '{name}' port must not be 0"));
+            }
+            if let Err(e) = mqtt::normalize_topic_prefix(&site.topic_prefix) {
+                errors.push(format!("federation: site '{name}': {e}"));
+            }
+            if site.password_env.is_some() && site.user.is_none() {
+                errors.push(format!(
+                    "federation: site '{name}' sets password_env without a user"
+                ));
+            }
+        }
+        if self.stale_after_sec == 0 {
+            errors.push("federation: stale_after_sec must be at least 1".to_string());
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// What the mirror knows about one site.
+#[derive(Debug, Clone, Default)]
+struct SiteState {
+    /// Connected to the site's broker.
+    connected: bool,
+    /// The site hub's retained `status/hub`; unset until it arrives.
+    hub_online: Option<bool>,
+    heartbeat: Option<HubHeartbeat>,
+    /// When the heartbeat arrived (unix seconds).
+    received_at: Option<i64>,
+}
+
+/// Mirrored state of every configured site.
+#[derive(Debug, Default)]
+pub struct Federation {
+    sites: BTreeMap<String, SiteState>,
+    stale_after_sec: u64,
+}
+
+impl Federation {
+    pub fn new(cfg: &FederationConfig) -> Self {
+        Self {
+            sites: cfg
+                .sites
+                .iter()
+                .map(|s| (s.name.clone(), SiteState::default()))
+                .collect(),
+            stale_after_sec: cfg.stale_after_sec,
+        }
+    }
+
+    pub fn set_connected(&mut self, site: &str, connected: bool) {
+        if let Some(s) = self.sites.get_mut(site) {
+            s.connected = connected;
+        }
+    }
+
+    /// A message from `site` on `path` (its topic without the site's
+    /// prefix).
+    pub fn handle(
+        &mut self,
+        site: &str,
+        path: &str,
+        payload: &[u8],
+        now: i64,
+    ) -> Result<(), String> {
+        let Some(s) = self.sites.get_mut(site) else {
+            return Ok(());
+        };
+        match path {
+            "status/hub" => {
+                s.hub_online =
+                    Some(mqtt::parse_node_status(payload).is_ok_and(|status| status.online));
+            }
+            "status/hub/heartbeat" => {
+                let heartbeat: HubHeartbeat = serde_json::from_slice(payload)
+                    .map_err(|e| format!("bad heartbeat from site '{site}': {e}"))?;
+                s.heartbeat = Some(heartbeat);
+                s.received_at = Some(now);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Combined view for `GET /api/federation`.
+    pub fn view(&self, now: i64) -> FederationView {
+        let mut view = FederationView::default();
+        for (name, s) in &self.sites {
+            let hb = s.heartbeat.as_ref();
+            view.sites.push(SiteView {
+                name: name.clone(),
+                connected: s.connected,
+                hub_online: s.hub_online,
+                last_heartbeat: s.received_at,
+                stale: s
+                    .received_at
+                    .is_none_or(|at| now - at > self.stale_after_sec as i64),
+                mode: hb.map(|h| h.mode.clone()),
+                uptime_secs: hb.map(|h| h.uptime_secs),
+                emergency_stop_latched: hb.is_some_and(|h| h.emergency_stop_latched),
+                frost_locked: hb.is_some_and(|h| h.frost_locked),
+                db_degraded: hb.is_some_and(|h| h.db_degraded),
+            });
+            let Some(hb) = hb else {
+                continue;
+            };
+            view.zones
+                .extend(hb.zones.iter().map(|(zone_id, z)| SiteZone {
+                    site: name.clone(),
+                    zone_id: zone_id.clone(),
+                    watering: hb.open_valves.contains(zone_id),
+                    moisture: z.moisture,
+                    moisture_ts: z.moisture_ts,
+                }));
+            view.nodes
+                .extend(hb.nodes.iter().map(|(node_id, n)| SiteNode {
+                    site: name.clone(),
+                    node_id: node_id.clone(),
+                    online: n.online,
+                    last_seen: n.last_seen,
+                }));
+            view.events.extend(hb.events.iter().map(|e| SiteEvent {
+                site: name.clone(),
+                event: e.clone(),
+            }));
+        }
+        view.events.sort_by_key(|e| std::cmp::Reverse(e.event.ts));
+        view
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct FederationView {
+    pub sites: Vec<SiteView>,
+    pub zones: Vec<SiteZone>,
+    pub nodes: Vec<SiteNode>,
+    /// Newest first.
+    pub events: Vec<SiteEvent>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SiteView {
+    pub name: String,
+    pub connected: bool,
+    pub hub_online: Option<bool>,
+    /// When the latest heartbeat arrived (unix seconds).
+    pub last_heartbeat: Option<i64>,
+    /// No heartbeat within `stale_after_sec`; the site's zones, nodes and
+    /// events are as of `last_heartbeat`.
+    pub stale: bool,
+    pub mode: Option<String>,
+    pub uptime_secs: Option<u64>,
+    pub emergency_stop_latched: bool,
+    pub frost_locked: bool,
+    pub db_degraded: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SiteZone {
+    pub site: String,
+    pub zone_id: String,
+    pub watering: bool,
+    pub moisture: Option<f32>,
+    pub moisture_ts: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SiteNode {
+    pub site: String,
+    pub node_id: String,
+    pub online: bool,
+    pub last_seen: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SiteEvent {
+    pub site: String,
+    #[serde(flatten)]
+    pub event: SystemEvent,
+}
+
+/// Mirror one site until the hub stops.  Reconnects on its own.
+pub async fn run(site: SiteConfig, shared: SharedState) {
+    let prefix = mqtt::normalize_topic_prefix(&site.topic_prefix).unwrap_or_default();
+    let topic = |path: &str| {
+        if prefix.is_empty() {
+            path.to_string()
+        } else {
+            format!("{prefix}/{path}")
+        }
+    };
+    let topics = [topic("status/hub"), topic("status/hub/heartbeat")];
+
+    let mut options = MqttOptions::new(
+        format!("irrigation-hub-federation-{}", site.name),
+        &site.host,
+        site.port,
+    );
+    options.set_keep_alive(Duration::from_secs(30));
+    if let Some(user) = &site.user {
+        let password = site
+            .password_env
+            .as_deref()
+            .and_then(|var| env::var(var).ok())
+            .unwrap_or_default();
+        options.set_credentials(user, password);
+    }
+    let (client, mut eventloop) = AsyncClient::new(options, 10);
+
+    let mut connected = false;
+    loop {
+        match eventloop.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                connected = true;
+                tracing::info!(site = %site.name, host = %site.host, "federation: connected");
+                shared
+                    .write()
+                    .await
+                    .federation
+                    .set_connected(&site.name, true);
+                // The event loop is polled in this task, so don't wait on
+                // the request queue.
+                for t in &topics {
+                    if let Err(e) = client.try_subscribe(t, QoS::AtLeastOnce) {
+                        tracing::error!(site = %site.name, "federation: subscribe failed: {e}");
+                    }
+                }
+            }
+            Ok(Event::Incoming(Packet::Publish(msg))) => {
+                let path = if prefix.is_empty() {
+                    Some(msg.topic.as_str())
+                } else {
+                    msg.topic
+                        .strip_prefix(prefix.as_str())
+                        .and_then(|t| t.strip_prefix('/'))
+                };
+                let Some(path) = path else {
+                    continue;
+                };
+                let now = OffsetDateTime::now_utc().unix_timestamp();
+                let result =
+                    shared
+                        .write()
+                        .await
+                        .federation
+                        .handle(&site.name, path, &msg.payload, now);
+                if let Err(e) = result {
+                    tracing::warn!("federation: {e}");
+                }
+            }
+            Ok(_) => {}
+            Err(e) => {
+                if connected {
+                    tracing::warn!(site = %site.name, "federation: connection lost: {e}");
+                    shared
+                        .write()
+                        .await
+                        .federation
+                        .set_connected(&site.name, false);
+                }
+                connected = false;
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        }
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn site(name: &str) -> SiteConfig {
+        SiteConfig {
+            name: name.into(),
+            host: "broker".into(),
+            port: 1883,
+            topic_prefix: String::new(),
+            user: None,
+            password_env: None,
+        }
+    }
+
+    #[test]
+    fn config_validation() {
+        let cfg: FederationConfig = toml::from_str(
+            r#"
+            [[sites]]
+            name = "cabin"
+            host = "cabin.example.net"
+            topic_prefix = "cabin"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(cfg.sites[0].port, 1883);
+        assert_eq!(cfg.stale_after_sec, DEFAULT_STALE_AFTER_SEC);
+        assert!(cfg.validate().is_ok());
+
+        let bad = FederationConfig {
+            sites: vec![
+                site("farm house"),
+                SiteConfig {
+                    topic_prefix: "a/+".into(),
+                    password_env: Some("PASS".into()),
+                    ..site("cabin")
+                },
+                SiteConfig {
+                    host: " ".into(),
+                    ..site("cabin")
+                },
+            ],
+            stale_after_sec: 0,
+        };
+        let errs = bad.validate().unwrap_err();
+        assert_eq!(errs.len(), 6, "{errs:?}");
+        assert!(errs[0].contains("'farm house'"));
+        assert!(errs[1].contains("wildcards"));
+        assert!(errs[2].contains("password_env without a user"));
+        assert!(errs[3].contains("listed twice"));
+        assert!(errs[4].contains("needs a host"));
+        assert!(errs[5].contains("stale_after_sec"));
+    }
+
+    #[test]
+    fn view_combines_sites() {
+        let cfg = FederationConfig {
+            sites: vec![site("cabin"), site("home")],
+            ..FederationConfig::default()
+        };
+        let mut fed = Federation::new(&cfg);
+        fed.set_connected("cabin", true);
+        // Hubs announce their build with the status; older ones a bare
+        // `online`.
+        fed.handle(
+            "cabin",
+            "status/hub",
+            br#"{"status":"online","version":"0.2.0","git":"1a2b3c4d"}"#,
+            100,
+        )
+        .unwrap();
+        let heartbeat = serde_json::json!({
+            "ts": 95,
+            "uptime_secs": 600,
+            "mode": "auto",
+            "open_valves": ["lawn"],
+            "zones": {
+                "lawn": {"moisture": 0.25, "moisture_ts": 90},
+                "beds": {"moisture": null, "moisture_ts": null}
+            },
+            "nodes": {"node-a": {"online": true, "last_seen": 90}},
+            "events": [
+                {"ts": "1970-01-01T00:01:30Z", "kind": "valve", "detail": "lawn ON"},
+                {"ts": "1970-01-01T00:01:00Z", "kind": "system", "detail": "started"}
+            ],
+            "emergency_stop_latched": false,
+            "frost_locked": true,
+            "db_degraded": false
+        });
+        fed.handle(
+            "cabin",
+            "status/hub/heartbeat",
+            heartbeat.to_string().as_bytes(),
+            100,
+        )
+        .unwrap();
+        assert!(fed
+            .handle("home", "status/hub/heartbeat", b"{", 100)
+            .unwrap_err()
+            .contains("site 'home'"));
+        // Unknown sites and topics are ignored.
+        fed.handle("elsewhere", "status/hub", b"online", 100)
+            .unwrap();
+        fed.handle("cabin", "status/node/x", b"offline", 100)
+            .unwrap();
+
+        let view = fed.view(150);
+        let cabin = &view.sites[0];
+        assert!(cabin.connected && !cabin.stale && cabin.frost_locked);
+        assert_eq!(cabin.hub_online, Some(true));
+        assert_eq!(cabin.mode.as_deref(), Some("auto"));
+        let home = &view.sites[1];
+        assert!(!home.connected && home.stale);
+        assert_eq!(home.hub_online, None);
+
+        let zones: Vec<(&str, &str, bool)> = view
+            .zones
+            .iter()
+            .map(|z| (z.site.as_str(), z.zone_id.as_str(), z.watering))
+            .collect();
+        assert_eq!(zones, [("cabin", "beds", false), ("cabin", "lawn", true)]);
+        assert_eq!(view.nodes[0].node_id, "node-a");
+        assert_eq!(view.events[0].event.detail, "lawn ON");
+        let json = serde_json::to_value(&view.events[0]).unwrap();
+        assert_eq!(json["site"], "cabin");
+        assert_eq!(json["kind"], "valve");
+
+        assert!(fed.view(100 + DEFAULT_STALE_AFTER_SEC as i64 + 1).sites[0].stale);
+    }
+}
@@ -0,0 +1,183 @@
+//! Clogged-emitter heuristic: compares a zone's recent flow-meter readings
+//! with its own historical baseline.
+//!
+//! Emitters and filters clog gradually, so the signal is a sustained decline
+//! in flow at the same supply pressure — not a single low day.  Days whose
+//! mean pressure (when the meter reports it) differs from the baseline are
+//! left out, since lower pressure alone also means lower flow.
+
+use serde::Serialize;
+use time::macros::format_description;
+use time::Date;
+
+/// History considered by the heuristic.
+pub const LOOKBACK_DAYS: i64 = 28;
+
+/// Days with flow data needed before the trend is trusted.
+const MIN_DAYS: usize = 7;
+
+/// Trailing days averaged into the "recent" flow.
+const RECENT_DAYS: usize = 3;
+
+/// Recent flow this far below the baseline raises the alert.
+pub const DECLINE_ALERT_PCT: f64 = 15.0;
+
+/// Days whose mean pressure is further than this from the median pressure
+/// are excluded from the comparison.
+const PRESSURE_TOLERANCE: f64 = 0.10;
+
+/// One day of flow readings for a zone (only samples with water flowing).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DailyFlow {
+    /// "YYYY-MM-DD" (UTC).
+    pub day: String,
+    pub avg_lpm: f64,
+    pub avg_pressure_kpa: Option<f64>,
+    pub samples: i64,
+}
+
+/// Flow trend for a zone, returned by the API and attached to alerts.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FlowTrend {
+    /// Median daily flow before the recent window.
+    pub baseline_lpm: f64,
+    /// Mean daily flow over the last `RECENT_DAYS` days.
+    pub recent_lpm: f64,
+    /// How far `recent_lpm` is below `baseline_lpm` (negative = above).
+    pub decline_pct: f64,
+    /// Least-squares slope of daily flow.
+    pub slope_lpm_per_day: f64,
+    pub clog_suspected: bool,
+    /// Days used for the comparison, oldest first.
+    pub days: Vec<DailyFlow>,
+}
+
+/// Analyse daily flow (oldest first).  `None` if there isn't enough
+/// comparable history yet.
+pub fn analyze(days: Vec<DailyFlow>) -> Option<FlowTrend> {
+    let days = comparable_days(days);
+    if days.len() < MIN_DAYS {
+        return None;
+    }
+
+    let (history, recent) = days.split_at(days.len() - RECENT_DAYS);
+    let baseline_lpm = median(history.iter().map(|d| d.avg_lpm).collect())?;
+    if baseline_lpm <= 0.0 {
+        return None;
+    }
+    let recent_lpm = recent.iter().map(|d| d.avg_lpm).sum::<f64>() / recent.len() as f64;
+    let decline_pct = (baseline_lpm - recent_lpm) / baseline_lpm * 100.0;
+    let slope_lpm_per_day = slope(&days)?;
+
+    Some(FlowTrend {
+        baseline_lpm,
+        recent_lpm,
+        decline_pct,
+        slope_lpm_per_day,
+        clog_suspected: decline_pct >= DECLINE_ALERT_PCT && slope_lpm_per_day < 0.0,
+        days,
+    })
+}
+
+/// Drop days measured at a noticeably different pressure than usual.
+fn comparable_days(days: Vec<DailyFlow>) -> Vec<DailyFlow> {
+    let Some(typical) = median(days.iter().filter_map(|d| d.avg_pressure_kpa).collect()) else {
+        return days;
+    };
+    days.into_iter()
+        .filter(|d| {
+            d.avg_pressure_kpa
+                .is_none_or(|p| ((p - typical) / typical).abs() <= PRESSURE_TOLERANCE)
+        })
+        .collect()
+}
+
+fn median(mut values: Vec<f64>) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    Some(if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    })
+}
+
+/// Least-squares slope of flow against calendar day (gaps respected).
+fn slope(days: &[DailyFlow]) -> Option<f64> {
+    let fmt = format_description!("[year]-[month]-[day]");
+    let points: Vec<(f64, f64)> = days
+        .iter()
+        .filter_map(|d| {
+            let date = Date::parse(&d.day, fmt).ok()?;
+            Some((date.to_julian_day() as f64, d.avg_lpm))
+        })
+        .collect();
+    let n = points.len() as f64;
+    if n < 2.0 {
+        return None;
+    }
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let sxx: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+    let sxy: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+    (sxx > 0.0).then(|| sxy / sxx)
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(n: u32, lpm: f64, pressure: Option<f64>) -> DailyFlow {
+        DailyFlow {
+            day: format!("2025-06-{n:02}"),
+            avg_lpm: lpm,
+            avg_pressure_kpa: pressure,
+            samples: 10,
+        }
+    }
+
+    #[test]
+    fn steady_flow_is_healthy() {
+        let days = (1..=14).map(|n| day(n, 6.0, Some(300.0))).collect();
+        let t = analyze(days).unwrap();
+        assert!(!t.clog_suspected);
+        assert!(t.decline_pct.abs() < 0.01);
+    }
+
+    #[test]
+    fn gradual_decline_is_flagged() {
+        // 6.0 L/min falling 0.15 per day over two weeks.
+        let days = (1..=14)
+            .map(|n| day(n, 6.0 - 0.15 * n as f64, Some(300.0)))
+            .collect();
+        let t = analyze(days).unwrap();
+        assert!(t.clog_suspected, "{t:?}");
+        assert!((t.slope_lpm_per_day + 0.15).abs() < 0.001);
+        assert_eq!(t.days.len(), 14);
+    }
+
+    #[test]
+    fn low_pressure_days_are_ignored() {
+        // Flow is low only on the days the supply pressure dropped.
+        let mut days: Vec<DailyFlow> = (1..=11).map(|n| day(n, 6.0, Some(300.0))).collect();
+        days.extend((12..=14).map(|n| day(n, 4.0, Some(200.0))));
+        let t = analyze(days).unwrap();
+        assert!(!t.clog_suspected);
+        assert_eq!(t.days.len(), 11);
+    }
+
+    #[test]
+    fn too_little_history() {
+        let days = (1..=MIN_DAYS as u32 - 1)
+            .map(|n| day(n, 6.0, None))
+            .collect();
+        assert!(analyze(days).is_none());
+    }
+}
@@ -0,0 +1,112 @@
+//! Emitter flushes: a short full-pressure opening of a drip zone every few
+//! days, to clear silt and mineral build-up from the emitters.
+//!
+//! The scheduler starts a flush at the first tick inside a flush window
+//! once `interval_days` have passed since the zone's last one.  Flushes go
+//! through the same valve path as pulses, so daily limits and the frost
+//! lockout apply, and are recorded as watering events with reason
+//! `flush`.  The last flush is read back from those events at startup.
+
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use time::OffsetDateTime;
+
+use crate::config::FlushConfig;
+use crate::maintenance::MaintenanceWindows;
+
+/// Watering event reason for flushes.
+pub const FLUSH_REASON: &str = "flush";
+
+/// Which zones are flushed, how often, and when.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FlushPlan {
+    zones: BTreeSet<String>,
+    interval_sec: i64,
+    duration: Duration,
+    windows: MaintenanceWindows,
+}
+
+impl FlushPlan {
+    pub fn new(cfg: &FlushConfig) -> Self {
+        Self {
+            zones: cfg.zones.iter().cloned().collect(),
+            interval_sec: cfg.interval_days * 86_400,
+            duration: Duration::from_secs(cfg.duration_sec.max(0) as u64),
+            windows: MaintenanceWindows::parse(&cfg.windows).unwrap_or_default(),
+        }
+    }
+
+    pub fn zones(&self) -> impl Iterator<Item = &str> {
+        self.zones.iter().map(String::as_str)
+    }
+
+    pub fn includes(&self, zone_id: &str) -> bool {
+        self.zones.contains(zone_id)
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Whether `zone_id` should be flushed now, given when it last was
+    /// (unix seconds; `None` if never).
+    pub fn is_due(&self, zone_id: &str, last: Option<i64>, now: OffsetDateTime) -> bool {
+        self.includes(zone_id)
+            && last.is_none_or(|last| now.unix_timestamp() - last >= self.interval_sec)
+            && self.windows.time_until_open(now).is_zero()
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    fn plan() -> FlushPlan {
+        FlushPlan::new(&FlushConfig {
+            zones: vec!["drip".into()],
+            interval_days: 7,
+            duration_sec: 20,
+            windows: vec!["05:00-06:00".into()],
+        })
+    }
+
+    #[test]
+    fn due_after_interval_inside_window() {
+        let p = plan();
+        let now = datetime!(2025-06-10 05:30 UTC);
+        let ts = now.unix_timestamp();
+        assert!(p.is_due("drip", None, now));
+        assert!(p.is_due("drip", Some(ts - 7 * 86_400), now));
+        assert!(!p.is_due("drip", Some(ts - 6 * 86_400), now));
+        // Only listed zones.
+        assert!(!p.is_due("lawn", None, now));
+        assert_eq!(p.duration(), Duration::from_secs(20));
+    }
+
+    #[test]
+    fn waits_for_window() {
+        let p = plan();
+        assert!(!p.is_due("drip", None, datetime!(2025-06-10 06:00 UTC)));
+        assert!(!p.is_due("drip", None, datetime!(2025-06-10 04:59 UTC)));
+
+        // No windows: any time.
+        let any = FlushPlan::new(&FlushConfig {
+            zones: vec!["drip".into()],
+            ..FlushConfig::default()
+        });
+        assert!(any.is_due("drip", None, datetime!(2025-06-10 13:00 UTC)));
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let p = FlushPlan::new(&FlushConfig::default());
+        assert_eq!(p.zones().count(), 0);
+        assert!(!p.is_due("drip", None, datetime!(2025-06-10 05:30 UTC)));
+    }
+}
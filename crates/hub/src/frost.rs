@@ -0,0 +1,141 @@
+//! Frost lockout: while the outdoor temperature is at or below the
+//! configured threshold, every valve ON command — scheduler or MQTT — is
+//! refused, so watering can't ice paths and walkways.
+//!
+//! Temperatures arrive on `temp/<source_id>/reading` from any source (a
+//! node's DS18B20, a weather API bridge).  The newest reading decides.
+//! The lockout only changes on a reading: if the source goes quiet the last
+//! state holds, which keeps a lockout engaged rather than guessing it has
+//! thawed.
+
+use serde::Serialize;
+
+use crate::config::FrostConfig;
+
+/// The newest outdoor temperature.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TempReading {
+    pub source: String,
+    pub temp_c: f64,
+    /// Unix seconds.
+    pub ts: i64,
+}
+
+/// A lockout state change caused by a reading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Engaged,
+    Released,
+}
+
+/// Lockout state, shown under `frost` in `/api/status`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FrostLockout {
+    /// Unset when the lockout is disabled.
+    lockout_below_c: Option<f64>,
+    release_above_c: Option<f64>,
+    locked: bool,
+    latest: Option<TempReading>,
+}
+
+impl FrostLockout {
+    pub fn new(cfg: &FrostConfig) -> Self {
+        let thresholds = cfg.thresholds();
+        Self {
+            lockout_below_c: thresholds.map(|(below, _)| below),
+            release_above_c: thresholds.map(|(_, above)| above),
+            locked: false,
+            latest: None,
+        }
+    }
+
+    /// Take a new reading.  Readings older than the latest are ignored.
+    pub fn record(&mut self, reading: TempReading) -> Option<Change> {
+        if self.latest.as_ref().is_some_and(|l| reading.ts < l.ts) {
+            return None;
+        }
+        let temp = reading.temp_c;
+        self.latest = Some(reading);
+        let (below, above) = self.lockout_below_c.zip(self.release_above_c)?;
+        if !self.locked && temp <= below {
+            self.locked = true;
+            Some(Change::Engaged)
+        } else if self.locked && temp > above {
+            self.locked = false;
+            Some(Change::Released)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    #[cfg(test)]
+    pub fn latest(&self) -> Option<&TempReading> {
+        self.latest.as_ref()
+    }
+
+    /// Why valves are locked out, e.g. "frost lockout: 1.5 °C (weather)".
+    pub fn reason(&self) -> String {
+        match &self.latest {
+            Some(r) => format!("frost lockout: {} °C ({})", r.temp_c, r.source),
+            None => "frost lockout".to_string(),
+        }
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(temp_c: f64, ts: i64) -> TempReading {
+        TempReading {
+            source: "weather".into(),
+            temp_c,
+            ts,
+        }
+    }
+
+    fn lockout() -> FrostLockout {
+        FrostLockout::new(&FrostConfig {
+            lockout_below_c: Some(2.0),
+            release_above_c: None,
+        })
+    }
+
+    #[test]
+    fn engages_at_threshold_and_releases_above_hysteresis() {
+        let mut f = lockout();
+        assert_eq!(f.record(reading(5.0, 1)), None);
+        assert_eq!(f.record(reading(2.0, 2)), Some(Change::Engaged));
+        assert!(f.is_locked());
+        // Between the thresholds: stays locked.
+        assert_eq!(f.record(reading(2.8, 3)), None);
+        assert!(f.is_locked());
+        assert_eq!(f.record(reading(3.1, 4)), Some(Change::Released));
+        assert!(!f.is_locked());
+    }
+
+    #[test]
+    fn out_of_order_readings_ignored() {
+        let mut f = lockout();
+        f.record(reading(1.0, 10));
+        assert_eq!(f.record(reading(8.0, 5)), None);
+        assert!(f.is_locked());
+        assert_eq!(f.reason(), "frost lockout: 1 °C (weather)");
+    }
+
+    #[test]
+    fn disabled_lockout_only_tracks_temperature() {
+        let mut f = FrostLockout::new(&FrostConfig::default());
+        assert_eq!(f.record(reading(-10.0, 1)), None);
+        assert!(!f.is_locked());
+        assert_eq!(f.latest().map(|r| r.temp_c), Some(-10.0));
+    }
+}
@@ -0,0 +1,293 @@
+//! GPIO access for the valve board and the emergency-stop input, on either
+//! of two backends:
+//!
+//! - `rppal` (the `gpio` feature): Raspberry Pi only, with internal pull
+//!   resistors for the stop button.
+//! - `cdev` (the `gpiod` feature): the Linux GPIO character device
+//!   (`/dev/gpiochipN`), for Orange Pi, BeagleBone and industrial SBCs, and
+//!   for Raspberry Pis whose kernels no longer expose `/dev/gpiomem`.  Pin
+//!   numbers are line offsets on `chip`.  It can't set pull resistors, so a
+//!   stop button needs an external one.
+//!
+//! The `board` profile decides which pins the config may use.
+//!
+//! ```toml
+//! [gpio]
+//! board = "orange-pi-zero2"
+//! backend = "cdev"
+//! chip = "/dev/gpiochip0"
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(any(feature = "gpio", feature = "gpiod"))]
+use anyhow::Result;
+
+/// BCM GPIO pins safe for general-purpose relay driving on the Raspberry Pi
+/// 40-pin header. Excludes:
+///   - GPIO 0-1: ID EEPROM (must never be used)
+///   - GPIO 2-3: I2C1 (SDA/SCL) with hard-wired 1.8k pull-ups — cannot
+///     reliably drive active-low relay optocouplers
+///   - GPIO 7-11: SPI0 (CE1, CE0, MISO, MOSI, SCLK)
+///   - GPIO 14-15: UART0 (TX/RX) — used for serial console
+///   - GPIO 28+: not exposed on the standard header
+const RASPBERRY_PI_PINS: &[i64] = &[
+    4, 5, 6, 12, 13, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27,
+];
+
+/// `gpiochip0` lines on the Orange Pi Zero2 26-pin header that aren't
+/// I2C, UART or SPI: PC5–PC11, PC14 and PC15 (header pins 13, 11, 22, 15,
+/// 7, 26, 12, 18 and 16).
+const ORANGE_PI_ZERO2_PINS: &[i64] = &[69, 70, 71, 72, 73, 74, 75, 78, 79];
+
+/// The board the hub runs on, which fixes the safe pins.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum BoardProfile {
+    #[default]
+    RaspberryPi,
+    OrangePiZero2,
+    /// Any other board: `pins` lists the usable lines.
+    Custom,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    Rppal,
+    Cdev,
+}
+
+/// `[gpio]` section.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct GpioConfig {
+    pub board: BoardProfile,
+    /// Defaults to `rppal` on Raspberry Pi builds with the `gpio` feature,
+    /// otherwise `cdev`.
+    pub backend: Option<Backend>,
+    /// Character device for the `cdev` backend.
+    pub chip: String,
+    /// Usable pins for the `custom` board.
+    pub pins: Vec<i64>,
+}
+
+impl Default for GpioConfig {
+    fn default() -> Self {
+        Self {
+            board: BoardProfile::default(),
+            backend: None,
+            chip: "/dev/gpiochip0".to_string(),
+            pins: Vec::new(),
+        }
+    }
+}
+
+impl GpioConfig {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        if self.backend == Some(Backend::Rppal) && self.board != BoardProfile::RaspberryPi {
+            errors.push("gpio: the rppal backend only drives Raspberry Pi boards".to_string());
+        }
+        if self.chip.trim().is_empty() {
+            errors.push("gpio: chip must not be empty".to_string());
+        }
+        match self.board {
+            BoardProfile::Custom if self.pins.is_empty() => {
+                errors.push("gpio: a custom board must list its pins".to_string())
+            }
+            BoardProfile::Custom => {
+                if let Some(p) = self.pins.iter().find(|p| !(0..=255).contains(*p)) {
+                    errors.push(format!("gpio: pin {p} out of range (0–255)"));
+                }
+            }
+            _ if !self.pins.is_empty() => {
+                errors.push("gpio: pins only apply to the custom board".to_string())
+            }
+            _ => {}
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Pins valves, relays and the stop button may use.
+    pub fn allowed_pins(&self) -> &[i64] {
+        match self.board {
+            BoardProfile::RaspberryPi => RASPBERRY_PI_PINS,
+            BoardProfile::OrangePiZero2 => ORANGE_PI_ZERO2_PINS,
+            BoardProfile::Custom => &self.pins,
+        }
+    }
+
+    /// The backend in use.
+    pub fn backend(&self) -> Backend {
+        self.backend.unwrap_or(
+            if cfg!(feature = "gpio") && self.board == BoardProfile::RaspberryPi {
+                Backend::Rppal
+            } else {
+                Backend::Cdev
+            },
+        )
+    }
+}
+
+/// Label the `cdev` backend claims lines under (shown by `gpioinfo`).
+#[cfg(feature = "gpiod")]
+const CONSUMER: &str = "irrigation-hub";
+
+/// A pin driven as an output.
+#[cfg(any(feature = "gpio", feature = "gpiod"))]
+pub enum OutputLine {
+    #[cfg(feature = "gpio")]
+    Rppal(rppal::gpio::OutputPin),
+    #[cfg(feature = "gpiod")]
+    Cdev(gpio_cdev::LineHandle),
+}
+
+#[cfg(any(feature = "gpio", feature = "gpiod"))]
+impl OutputLine {
+    /// Claim `pin` as an output that starts at `high`, so the relay never
+    /// sees a glitch while the pin switches mode.
+    pub fn claim(cfg: &GpioConfig, pin: u8, high: bool) -> Result<Self> {
+        match cfg.backend() {
+            #[cfg(feature = "gpio")]
+            Backend::Rppal => {
+                let pin = rppal::gpio::Gpio::new()?.get(pin)?;
+                let mut pin = if high {
+                    pin.into_output_high()
+                } else {
+                    pin.into_output_low()
+                };
+                // Prevent rppal from resetting the pin to input mode
+                // (floating) when dropped.  The valve board drives pins to
+                // the safe OFF level before that — floating after it would
+                // risk relay chatter on boards with weak pull-ups.
+                pin.set_reset_on_drop(false);
+                Ok(Self::Rppal(pin))
+            }
+            #[cfg(feature = "gpiod")]
+            Backend::Cdev => {
+                let line = gpio_cdev::Chip::new(&cfg.chip)?.get_line(u32::from(pin))?;
+                let handle = line.request(
+                    gpio_cdev::LineRequestFlags::OUTPUT,
+                    u8::from(high),
+                    CONSUMER,
+                )?;
+                Ok(Self::Cdev(handle))
+            }
+            #[allow(unreachable_patterns)]
+            backend => anyhow::bail!("GPIO backend {backend:?} not built into this hub"),
+        }
+    }
+
+    pub fn write(&mut self, high: bool) {
+        match self {
+            #[cfg(feature = "gpio")]
+            Self::Rppal(pin) => pin.write(high.into()),
+            #[cfg(feature = "gpiod")]
+            Self::Cdev(handle) => {
+                if let Err(e) = handle.set_value(u8::from(high)) {
+                    tracing::error!(line = handle.line().offset(), "GPIO write failed: {e}");
+                }
+            }
+        }
+    }
+}
+
+/// A pin read as an input.
+#[cfg(any(feature = "gpio", feature = "gpiod"))]
+pub enum InputLine {
+    #[cfg(feature = "gpio")]
+    Rppal(rppal::gpio::InputPin),
+    #[cfg(feature = "gpiod")]
+    Cdev(gpio_cdev::LineHandle),
+}
+
+#[cfg(any(feature = "gpio", feature = "gpiod"))]
+impl InputLine {
+    /// Claim `pin` as an input, pulled up or down where the backend can.
+    pub fn claim(cfg: &GpioConfig, pin: u8, pull_up: bool) -> Result<Self> {
+        match cfg.backend() {
+            #[cfg(feature = "gpio")]
+            Backend::Rppal => {
+                let pin = rppal::gpio::Gpio::new()?.get(pin)?;
+                Ok(Self::Rppal(if pull_up {
+                    pin.into_input_pullup()
+                } else {
+                    pin.into_input_pulldown()
+                }))
+            }
+            #[cfg(feature = "gpiod")]
+            Backend::Cdev => {
+                let _ = pull_up; // needs an external resistor
+                let line = gpio_cdev::Chip::new(&cfg.chip)?.get_line(u32::from(pin))?;
+                let handle = line.request(gpio_cdev::LineRequestFlags::INPUT, 0, CONSUMER)?;
+                Ok(Self::Cdev(handle))
+            }
+            #[allow(unreachable_patterns)]
+            backend => anyhow::bail!("GPIO backend {backend:?} not built into this hub"),
+        }
+    }
+
+    pub fn is_high(&self) -> bool {
+        match self {
+            #[cfg(feature = "gpio")]
+            Self::Rppal(pin) => pin.is_high(),
+            // A failed read counts as released; the debouncer only acts on
+            // a level that holds.
+            #[cfg(feature = "gpiod")]
+            Self::Cdev(handle) => handle.get_value().is_ok_and(|v| v != 0),
+        }
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_set_the_allowed_pins() {
+        let cfg: GpioConfig = toml::from_str("").unwrap();
+        assert_eq!(cfg, GpioConfig::default());
+        assert!(cfg.allowed_pins().contains(&17));
+        assert!(!cfg.allowed_pins().contains(&2));
+
+        let cfg: GpioConfig = toml::from_str("board = \"orange-pi-zero2\"").unwrap();
+        assert!(cfg.allowed_pins().contains(&73));
+        assert!(!cfg.allowed_pins().contains(&17));
+        assert_eq!(cfg.backend(), Backend::Cdev);
+
+        let cfg: GpioConfig = toml::from_str("board = \"custom\"\npins = [60, 48]").unwrap();
+        assert_eq!(cfg.allowed_pins(), &[60, 48]);
+        assert!(cfg.validate().is_ok());
+    }
+
+    #[test]
+    fn validation() {
+        assert!(GpioConfig::default().validate().is_ok());
+        let errs = GpioConfig {
+            board: BoardProfile::OrangePiZero2,
+            backend: Some(Backend::Rppal),
+            chip: " ".into(),
+            pins: vec![3],
+        }
+        .validate()
+        .unwrap_err();
+        assert_eq!(errs.len(), 3, "{errs:?}");
+        let errs = GpioConfig {
+            board: BoardProfile::Custom,
+            ..Default::default()
+        }
+        .validate()
+        .unwrap_err();
+        assert_eq!(errs, vec!["gpio: a custom board must list its pins"]);
+        assert!(toml::from_str::<GpioConfig>("board = \"pi\"").is_err());
+    }
+}
@@ -0,0 +1,225 @@
+//! Historical comparison: a zone's watering totals and moisture profile over
+//! one period against a baseline period of the same length — by default
+//! the same dates last year — for tuning zones season to season.
+//!
+//! Watering totals come from the daily counters.  Moisture comes from the
+//! `zone_daily_moisture` rollup for days whose readings have been pruned,
+//! and from the raw readings otherwise.
+
+use serde::Serialize;
+use time::{Date, Month};
+
+/// Longest period a comparison accepts.
+pub const MAX_PERIOD_DAYS: i64 = 366;
+
+/// Default period length when only `to` is given (one week).
+pub const DEFAULT_PERIOD_DAYS: i64 = 7;
+
+/// One day of a zone's moisture (disturbed readings excluded).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DailyMoisture {
+    /// "YYYY-MM-DD" (UTC).
+    pub day: String,
+    pub avg_moisture: f64,
+    pub min_moisture: f64,
+    pub max_moisture: f64,
+    pub samples: i64,
+}
+
+/// Watering totals for a zone over a date range.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UsageTotals {
+    pub open_sec: i64,
+    pub pulses: i64,
+    /// Only present when the zone has a measured `flow_lpm` or an
+    /// `emitter_flow_lph` estimate.
+    pub litres: Option<f64>,
+    /// Days with at least one pulse.
+    pub watering_days: i64,
+}
+
+/// One side of a comparison.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PeriodSummary {
+    pub from: String,
+    pub to: String,
+    #[serde(flatten)]
+    pub usage: UsageTotals,
+    /// Sample-weighted mean over the period; `None` without readings.
+    pub avg_moisture: Option<f64>,
+    pub min_moisture: Option<f64>,
+    pub max_moisture: Option<f64>,
+    /// Daily moisture profile, oldest first.
+    pub moisture: Vec<DailyMoisture>,
+}
+
+impl PeriodSummary {
+    pub fn new(from: String, to: String, usage: UsageTotals, moisture: Vec<DailyMoisture>) -> Self {
+        let samples: i64 = moisture.iter().map(|d| d.samples).sum();
+        let avg_moisture = (samples > 0).then(|| {
+            moisture
+                .iter()
+                .map(|d| d.avg_moisture * d.samples as f64)
+                .sum::<f64>()
+                / samples as f64
+        });
+        let min_moisture = moisture.iter().map(|d| d.min_moisture).reduce(f64::min);
+        let max_moisture = moisture.iter().map(|d| d.max_moisture).reduce(f64::max);
+        Self {
+            from,
+            to,
+            usage,
+            avg_moisture,
+            min_moisture,
+            max_moisture,
+            moisture,
+        }
+    }
+}
+
+/// Period minus baseline.  `None` where either side has no data.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Delta {
+    pub open_sec: i64,
+    pub pulses: i64,
+    pub litres: Option<f64>,
+    pub avg_moisture: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Comparison {
+    pub zone_id: String,
+    pub period: PeriodSummary,
+    pub baseline: PeriodSummary,
+    pub delta: Delta,
+}
+
+impl Comparison {
+    pub fn new(zone_id: String, period: PeriodSummary, baseline: PeriodSummary) -> Self {
+        let delta = Delta {
+            open_sec: period.usage.open_sec - baseline.usage.open_sec,
+            pulses: period.usage.pulses - baseline.usage.pulses,
+            litres: period
+                .usage
+                .litres
+                .zip(baseline.usage.litres)
+                .map(|(p, b)| p - b),
+            avg_moisture: period
+                .avg_moisture
+                .zip(baseline.avg_moisture)
+                .map(|(p, b)| p - b),
+        };
+        Self {
+            zone_id,
+            period,
+            baseline,
+            delta,
+        }
+    }
+}
+
+/// The same date one year earlier (29 February becomes the 28th).
+pub fn year_before(date: Date) -> Date {
+    let year = date.year() - 1;
+    Date::from_calendar_date(year, date.month(), date.day())
+        .or_else(|_| Date::from_calendar_date(year, Month::February, 28))
+        .unwrap_or(date)
+}
+
+/// Baseline range for the period `from..=to`: starting at `baseline_from`
+/// if given, otherwise a year before `from`, and always the same length.
+pub fn baseline_range(from: Date, to: Date, baseline_from: Option<Date>) -> (Date, Date) {
+    let start = baseline_from.unwrap_or_else(|| year_before(from));
+    (start, start + (to - from))
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    fn day(d: &str, avg: f64, min: f64, max: f64, samples: i64) -> DailyMoisture {
+        DailyMoisture {
+            day: d.into(),
+            avg_moisture: avg,
+            min_moisture: min,
+            max_moisture: max,
+            samples,
+        }
+    }
+
+    #[test]
+    fn baseline_defaults_to_last_year() {
+        assert_eq!(
+            baseline_range(date!(2026 - 06 - 01), date!(2026 - 06 - 07), None),
+            (date!(2025 - 06 - 01), date!(2025 - 06 - 07))
+        );
+        // Leap day: same length, starting on the 28th.
+        assert_eq!(
+            baseline_range(date!(2028 - 02 - 29), date!(2028 - 03 - 06), None),
+            (date!(2027 - 02 - 28), date!(2027 - 03 - 06))
+        );
+        assert_eq!(
+            baseline_range(
+                date!(2026 - 06 - 01),
+                date!(2026 - 06 - 07),
+                Some(date!(2026 - 05 - 01))
+            ),
+            (date!(2026 - 05 - 01), date!(2026 - 05 - 07))
+        );
+    }
+
+    #[test]
+    fn summary_weights_moisture_by_samples() {
+        let s = PeriodSummary::new(
+            "2026-06-01".into(),
+            "2026-06-02".into(),
+            UsageTotals::default(),
+            vec![
+                day("2026-06-01", 0.2, 0.1, 0.3, 1),
+                day("2026-06-02", 0.5, 0.4, 0.6, 3),
+            ],
+        );
+        assert!((s.avg_moisture.unwrap() - 0.425).abs() < 1e-9);
+        assert_eq!(s.min_moisture, Some(0.1));
+        assert_eq!(s.max_moisture, Some(0.6));
+
+        let empty = PeriodSummary::new("a".into(), "b".into(), UsageTotals::default(), vec![]);
+        assert_eq!(empty.avg_moisture, None);
+    }
+
+    #[test]
+    fn delta_needs_both_sides() {
+        let period = PeriodSummary::new(
+            "2026-06-01".into(),
+            "2026-06-07".into(),
+            UsageTotals {
+                open_sec: 300,
+                pulses: 10,
+                litres: Some(30.0),
+                watering_days: 5,
+            },
+            vec![day("2026-06-01", 0.4, 0.3, 0.5, 2)],
+        );
+        let baseline = PeriodSummary::new(
+            "2025-06-01".into(),
+            "2025-06-07".into(),
+            UsageTotals {
+                open_sec: 420,
+                pulses: 14,
+                litres: None,
+                watering_days: 7,
+            },
+            vec![],
+        );
+        let c = Comparison::new("z1".into(), period, baseline);
+        assert_eq!(c.delta.open_sec, -120);
+        assert_eq!(c.delta.pulses, -4);
+        assert_eq!(c.delta.litres, None);
+        assert_eq!(c.delta.avg_moisture, None);
+    }
+}
@@ -0,0 +1,90 @@
+//! Valve interlock groups: zones sharing a supply line that can't feed two
+//! of them at once.  At most one valve per group is open, whatever
+//! `max_concurrent_valves` would allow; an ON for a zone whose group
+//! partner is open is refused, from the scheduler or MQTT alike.
+//!
+//! ```toml
+//! [interlocks]
+//! north-line = ["front-lawn", "back-lawn"]
+//! ```
+
+use std::collections::BTreeMap;
+
+/// Interlock groups by name.  A zone may be in several groups.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Interlocks {
+    groups: BTreeMap<String, Vec<String>>,
+}
+
+/// An open zone that keeps another one shut.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub group: String,
+    pub open_zone: String,
+}
+
+impl std::fmt::Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "interlocked with '{}' (group '{}')",
+            self.open_zone, self.group
+        )
+    }
+}
+
+impl Interlocks {
+    pub fn new(groups: &BTreeMap<String, Vec<String>>) -> Self {
+        Self {
+            groups: groups.clone(),
+        }
+    }
+
+    /// The first other zone sharing a group with `zone_id` that `is_open`
+    /// reports open.
+    pub fn conflict(&self, zone_id: &str, is_open: impl Fn(&str) -> bool) -> Option<Conflict> {
+        self.groups
+            .iter()
+            .filter(|(_, zones)| zones.iter().any(|z| z == zone_id))
+            .find_map(|(group, zones)| {
+                zones
+                    .iter()
+                    .find(|z| *z != zone_id && is_open(z))
+                    .map(|z| Conflict {
+                        group: group.clone(),
+                        open_zone: z.clone(),
+                    })
+            })
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_partner_blocks_the_group() {
+        let groups = BTreeMap::from([
+            ("north".to_string(), vec!["a".to_string(), "b".to_string()]),
+            ("south".to_string(), vec!["b".to_string(), "c".to_string()]),
+        ]);
+        let locks = Interlocks::new(&groups);
+
+        assert_eq!(locks.conflict("a", |_| false), None);
+        // A zone's own valve doesn't block it.
+        assert_eq!(locks.conflict("a", |z| z == "a"), None);
+        // Zones outside every group are never blocked.
+        assert_eq!(locks.conflict("d", |_| true), None);
+
+        let conflict = locks.conflict("b", |z| z == "c").unwrap();
+        assert_eq!(conflict.group, "south");
+        assert_eq!(conflict.open_zone, "c");
+        assert_eq!(conflict.to_string(), "interlocked with 'c' (group 'south')");
+        assert_eq!(locks.conflict("a", |z| z == "c"), None);
+        assert_eq!(locks.conflict("a", |z| z == "b").unwrap().group, "north");
+    }
+}
@@ -0,0 +1,238 @@
+//! Read-only kiosk view at `/kiosk`: each zone's moisture and valve state
+//! as a plain HTML page for a wall-mounted tablet or e-ink display.
+//!
+//! The page is rendered on the hub and reloads itself every
+//! [`REFRESH_SEC`]; it has no scripts, no controls and no calls back to
+//! the API, so a display with a minimal browser can show it.  `KIOSK`
+//! decides who may load it:
+//!
+//! - `public` (default): anyone who can reach the hub.
+//! - `token`: `/kiosk?token=<token>` with any API token (a `viewer` token
+//!   is enough).  Open like the API when no tokens are configured.
+//! - `off`: not served.
+
+use std::fmt::Write;
+
+use crate::db::ZoneConfig;
+use crate::state::HubHeartbeat;
+
+/// How often the page reloads itself.
+pub const REFRESH_SEC: u64 = 60;
+
+/// Who may load `/kiosk` (`KIOSK`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KioskAccess {
+    #[default]
+    Public,
+    Token,
+    Off,
+}
+
+impl KioskAccess {
+    /// Parse `KIOSK`; unset or empty is [`KioskAccess::Public`].
+    pub fn parse(s: Option<&str>) -> Result<Self, String> {
+        match s.map(str::trim).unwrap_or_default() {
+            "" | "public" => Ok(Self::Public),
+            "token" => Ok(Self::Token),
+            "off" => Ok(Self::Off),
+            other => Err(format!("KIOSK: '{other}' is not one of public, token, off")),
+        }
+    }
+}
+
+/// The kiosk page for `zones` (active ones, in order) at `hb.ts`.
+pub fn render(zones: &[ZoneConfig], hb: &HubHeartbeat) -> String {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!doctype html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <meta http-equiv=\"refresh\" content=\"{REFRESH_SEC}\">\n\
+         <title>Irrigation</title>\n<style>{STYLE}</style>\n</head>\n<body>\n"
+    );
+
+    let mut banners = Vec::new();
+    if hb.emergency_stop_latched {
+        banners.push("Emergency stop latched: valves stay off");
+    }
+    if hb.frost_locked {
+        banners.push("Frost lockout: watering paused");
+    }
+    if hb.db_degraded {
+        banners.push("Database degraded");
+    }
+    for b in banners {
+        let _ = writeln!(html, "<p class=\"banner\">{b}</p>");
+    }
+    let _ = writeln!(
+        html,
+        "<header><h1>Irrigation</h1><span>mode: {}</span></header>",
+        escape(&hb.mode)
+    );
+
+    if zones.is_empty() {
+        html.push_str("<p>No zones configured.</p>\n");
+    }
+    for z in zones {
+        let latest = hb.zones.get(&z.zone_id);
+        let moisture = latest.and_then(|l| l.moisture);
+        let watering = hb.open_valves.contains(&z.zone_id);
+        let _ = write!(
+            html,
+            "<section class=\"zone{}\">\n<h2>{}</h2>\n",
+            if watering { " on" } else { "" },
+            escape(&z.name)
+        );
+        match moisture {
+            Some(m) => {
+                let pct = (m * 100.0).round();
+                let _ = write!(
+                    html,
+                    "<p class=\"moisture\">{pct:.0}%</p>\n\
+                     <div class=\"bar\"><div style=\"width:{pct:.0}%\"></div>\
+                     <i style=\"left:{:.0}%\"></i><i style=\"left:{:.0}%\"></i></div>\n",
+                    z.min_moisture * 100.0,
+                    z.target_moisture * 100.0
+                );
+            }
+            None => html.push_str("<p class=\"moisture\">–</p>\n"),
+        }
+        let mut details = vec![if watering { "Watering" } else { "Valve closed" }.to_string()];
+        details.push(format!(
+            "min {:.0}% · target {:.0}%",
+            z.min_moisture * 100.0,
+            z.target_moisture * 100.0
+        ));
+        match latest.and_then(|l| l.moisture_ts) {
+            Some(ts) => {
+                let age = (hb.ts - ts).max(0);
+                let stale = age > z.stale_timeout_min * 60;
+                details.push(format!(
+                    "{}{}",
+                    if stale { "STALE · " } else { "" },
+                    ago(age)
+                ));
+            }
+            None => details.push("no readings".to_string()),
+        }
+        for d in details {
+            let _ = writeln!(html, "<p>{d}</p>");
+        }
+        html.push_str("</section>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// `age` seconds as "just now", "5 min ago", "3 h ago" or "2 d ago".
+fn ago(age: i64) -> String {
+    match age {
+        ..60 => "just now".to_string(),
+        60..3600 => format!("{} min ago", age / 60),
+        3600..86_400 => format!("{} h ago", age / 3600),
+        _ => format!("{} d ago", age / 86_400),
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Black on white, large type, no animation: readable on e-ink.
+const STYLE: &str = "body{font-family:sans-serif;margin:1rem;background:#fff;color:#000}\
+header{display:flex;justify-content:space-between;align-items:baseline}\
+h1{margin:0 0 1rem}\
+.banner{border:3px solid #000;padding:.5rem;font-weight:bold;font-size:1.4rem}\
+.zone{border:2px solid #000;padding:.75rem;margin-bottom:1rem}\
+.zone.on{border-width:6px}\
+h2{margin:0}\
+.zone p{margin:.25rem 0;font-size:1.2rem}\
+.moisture{font-size:3rem!important;font-weight:bold}\
+.bar{position:relative;height:1rem;border:2px solid #000}\
+.bar div{height:100%;background:#000}\
+.bar i{position:absolute;top:-4px;bottom:-4px;border-left:2px dashed #000}";
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::state::ZoneHeartbeat;
+
+    fn heartbeat(zones: &[(&str, Option<f32>, Option<i64>)]) -> HubHeartbeat {
+        HubHeartbeat {
+            ts: 10_000,
+            uptime_secs: 0,
+            mode: "auto".into(),
+            open_valves: vec!["z1".into()],
+            zones: zones
+                .iter()
+                .map(|(id, moisture, moisture_ts)| {
+                    (
+                        id.to_string(),
+                        ZoneHeartbeat {
+                            moisture: *moisture,
+                            moisture_ts: *moisture_ts,
+                        },
+                    )
+                })
+                .collect::<BTreeMap<_, _>>(),
+            nodes: BTreeMap::new(),
+            events: Vec::new(),
+            emergency_stop_latched: true,
+            frost_locked: false,
+            db_degraded: false,
+        }
+    }
+
+    #[test]
+    fn access_parsed() {
+        assert_eq!(KioskAccess::parse(None), Ok(KioskAccess::Public));
+        assert_eq!(KioskAccess::parse(Some(" token ")), Ok(KioskAccess::Token));
+        assert_eq!(KioskAccess::parse(Some("off")), Ok(KioskAccess::Off));
+        assert!(KioskAccess::parse(Some("yes")).is_err());
+    }
+
+    #[test]
+    fn renders_zones_without_controls() {
+        let zone = |id: &str, name: &str| ZoneConfig {
+            name: name.into(),
+            ..ZoneConfig::test(id)
+        };
+        let html = render(
+            &[zone("z1", "Beds <north>"), zone("z2", "Pots")],
+            &heartbeat(&[("z1", Some(0.423), Some(9_900)), ("z2", None, None)]),
+        );
+        assert!(html.contains(&format!("content=\"{REFRESH_SEC}\"")));
+        assert!(html.contains("Emergency stop latched"));
+        assert!(html.contains("Beds &lt;north&gt;"));
+        assert!(html.contains("42%"));
+        assert!(html.contains("Watering"));
+        assert!(html.contains("1 min ago"));
+        assert!(html.contains("no readings"));
+        assert!(!html.contains("<script") && !html.contains("<form") && !html.contains("<button"));
+    }
+
+    #[test]
+    fn ages() {
+        assert_eq!(ago(5), "just now");
+        assert_eq!(ago(600), "10 min ago");
+        assert_eq!(ago(7200), "2 h ago");
+        assert_eq!(ago(200_000), "2 d ago");
+    }
+}
@@ -0,0 +1,144 @@
+//! The running safety envelope, served by `GET /api/limits`: every setting
+//! that bounds how long and how often valves open, and how much inbound
+//! data the hub accepts, as resolved from constants, environment variables
+//! and `config.toml` at startup.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use crate::config::{BudgetConfig, FrostConfig, OperationMode, ValveServiceConfig};
+use crate::db::ZoneConfig;
+use crate::flush::FlushPlan;
+use crate::gpio::GpioConfig;
+use crate::pressure::PressureConfig;
+use crate::state::degraded_limit;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SafetyLimits {
+    pub mode: OperationMode,
+    pub max_concurrent_valves: usize,
+    pub watchdog: WatchdogLimits,
+    /// MQTT errors tolerated this long before all valves are closed.
+    pub mqtt_grace_period_sec: u64,
+    /// A critical task silent for this long is reported unhealthy.
+    pub task_heartbeat_timeout_sec: i64,
+    pub scheduler_tick_sec: u64,
+    /// Daily caps are divided by this while the database is degraded.
+    pub degraded_limit_divisor: i64,
+    /// Minimum gap between relay switches.
+    pub valve_stagger_ms: u64,
+    /// Board profile and GPIO backend; the profile fixes the usable pins.
+    pub gpio: GpioConfig,
+    /// Zones allowing more watering than this a day are held for review.
+    pub review_long_runtime_sec: i64,
+    pub frost: FrostConfig,
+    pub pressure: PressureConfig,
+    pub budget: BudgetConfig,
+    /// Valve interlock groups: at most one valve per group open.
+    pub interlocks: BTreeMap<String, Vec<String>>,
+    pub valve_service: ValveServiceConfig,
+    pub ingest: IngestLimits,
+    /// Per zone, ordered by zone_id.
+    pub zones: Vec<ZoneLimits>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct WatchdogLimits {
+    pub interval_sec: u64,
+    /// Added to a zone's pulse (or flush) before the watchdog closes it.
+    pub margin_sec: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct IngestLimits {
+    pub max_json_payload_bytes: usize,
+    pub max_readings_per_message: usize,
+    /// How far behind the hub's clock telemetry may be stamped.
+    pub max_backfill_sec: i64,
+    /// Readings queued in memory while the database is unwritable.
+    pub reading_buffer_cap: usize,
+    /// 0 = readings are written one by one.
+    pub readings_flush_interval_sec: u64,
+    pub readings_flush_max_rows: usize,
+    pub sensor_quarantine_after: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ZoneLimits {
+    pub zone_id: String,
+    pub pulse_sec: i64,
+    pub max_pulses_per_day: i64,
+    pub max_open_sec_per_day: i64,
+    pub degraded_max_pulses_per_day: i64,
+    pub degraded_max_open_sec_per_day: i64,
+    /// Open time after which the watchdog force-closes the valve (a running
+    /// flush is allowed `flush_sec` plus the margin instead, if longer).
+    pub watchdog_max_open_sec: u64,
+    pub stale_timeout_min: i64,
+    /// Emitter flush length, for flushed zones.
+    pub flush_sec: Option<u64>,
+}
+
+/// Per-zone limits.  `watchdog_sec` gives the watchdog's limit for a zone.
+pub fn zone_limits(
+    zone_configs: &HashMap<String, ZoneConfig>,
+    flush: &FlushPlan,
+    watchdog_sec: impl Fn(&str) -> u64,
+) -> Vec<ZoneLimits> {
+    let mut zones: Vec<ZoneLimits> = zone_configs
+        .values()
+        .map(|z| ZoneLimits {
+            zone_id: z.zone_id.clone(),
+            pulse_sec: z.pulse_sec,
+            max_pulses_per_day: z.max_pulses_per_day,
+            max_open_sec_per_day: z.max_open_sec_per_day,
+            degraded_max_pulses_per_day: degraded_limit(z.max_pulses_per_day),
+            degraded_max_open_sec_per_day: degraded_limit(z.max_open_sec_per_day),
+            watchdog_max_open_sec: watchdog_sec(&z.zone_id),
+            stale_timeout_min: z.stale_timeout_min,
+            flush_sec: flush
+                .includes(&z.zone_id)
+                .then(|| flush.duration().as_secs()),
+        })
+        .collect();
+    zones.sort_by(|a, b| a.zone_id.cmp(&b.zone_id));
+    zones
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FlushConfig;
+
+    fn zone(zone_id: &str) -> ZoneConfig {
+        ZoneConfig {
+            name: zone_id.into(),
+            max_pulses_per_day: 5,
+            ..ZoneConfig::test(zone_id)
+        }
+    }
+
+    #[test]
+    fn zone_limits_include_degraded_caps_and_flushes() {
+        let zones = HashMap::from([("b".to_string(), zone("b")), ("a".to_string(), zone("a"))]);
+        let flush = FlushPlan::new(&FlushConfig {
+            zones: vec!["b".into()],
+            duration_sec: 45,
+            ..FlushConfig::default()
+        });
+        let limits = zone_limits(&zones, &flush, |z| if z == "b" { 75 } else { 60 });
+
+        assert_eq!(limits.len(), 2);
+        assert_eq!(limits[0].zone_id, "a");
+        assert_eq!(limits[0].degraded_max_pulses_per_day, 2);
+        assert_eq!(limits[0].degraded_max_open_sec_per_day, 90);
+        assert_eq!(limits[0].flush_sec, None);
+        assert_eq!(limits[1].watchdog_max_open_sec, 75);
+        assert_eq!(limits[1].flush_sec, Some(45));
+    }
+}
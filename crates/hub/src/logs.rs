@@ -0,0 +1,184 @@
+//! Log output options.
+//!
+//! `LOG_FORMAT=json` prints one JSON object per record instead of the
+//! human-readable lines, for log shippers.  With `LOG_TO_DB` set, WARN and
+//! ERROR records that pass `RUST_LOG` are also kept in the `logs` table for
+//! `LOG_RETENTION_DAYS` and served by `GET /api/logs`: journald on the Pi
+//! rotates them away too quickly for post-mortems.
+//!
+//! [`DbLogLayer`] only queues records; [`run_writer`] stores them in
+//! batches.  Records are dropped rather than block the logging thread when
+//! the queue is full, and records logged by this module (the writer's own
+//! failures) are never queued, so a failing database can't feed itself.
+
+use std::fmt;
+
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+use crate::db::Db;
+
+/// Default `LOG_RETENTION_DAYS`.
+pub const DEFAULT_RETENTION_DAYS: i64 = 30;
+
+/// Records queued for the writer before new ones are dropped.
+pub const QUEUE_CAPACITY: usize = 1024;
+
+/// Most records stored per transaction.
+const WRITE_BATCH: usize = 100;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl LogFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "text" => Some(Self::Text),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// One stored record, as returned by `GET /api/logs`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LogRecord {
+    pub ts: i64,
+    /// `WARN` or `ERROR`.
+    pub level: String,
+    /// Module that logged it.
+    pub target: String,
+    pub message: String,
+    /// The record's other structured fields.
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Tracing layer queueing WARN and ERROR records for [`run_writer`].
+pub struct DbLogLayer {
+    tx: mpsc::Sender<LogRecord>,
+}
+
+impl DbLogLayer {
+    pub fn new() -> (Self, mpsc::Receiver<LogRecord>) {
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        (Self { tx }, rx)
+    }
+}
+
+impl<S: Subscriber> Layer<S> for DbLogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let meta = event.metadata();
+        // More verbose levels compare greater.
+        if *meta.level() > Level::WARN || meta.target().starts_with(module_path!()) {
+            return;
+        }
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let _ = self.tx.try_send(LogRecord {
+            ts: time::OffsetDateTime::now_utc().unix_timestamp(),
+            level: meta.level().to_string(),
+            target: meta.target().to_string(),
+            message: visitor.message,
+            fields: visitor.fields,
+        });
+    }
+}
+
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl FieldVisitor {
+    fn insert(&mut self, field: &Field, value: serde_json::Value) {
+        if field.name() == "message" {
+            self.message = match value {
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            };
+        } else {
+            self.fields.insert(field.name().to_string(), value);
+        }
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, format!("{value:?}").into());
+    }
+}
+
+/// Store queued records until every [`DbLogLayer`] is gone.
+pub async fn run_writer(db: Db, mut rx: mpsc::Receiver<LogRecord>) {
+    let mut batch = Vec::with_capacity(WRITE_BATCH);
+    while rx.recv_many(&mut batch, WRITE_BATCH).await > 0 {
+        if let Err(e) = db.insert_logs(&batch).await {
+            tracing::warn!(records = batch.len(), "dropping log records: {e:#}");
+        }
+        batch.clear();
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn parse_formats() {
+        assert_eq!(LogFormat::parse("JSON"), Some(LogFormat::Json));
+        assert_eq!(LogFormat::parse(" text"), Some(LogFormat::Text));
+        assert_eq!(LogFormat::parse("logfmt"), None);
+    }
+
+    #[test]
+    fn layer_queues_warnings_and_errors() {
+        let (layer, mut rx) = DbLogLayer::new();
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("routine");
+            tracing::warn!(target: "irrigation_hub::mqtt", zone = "z1", pulses = 3, "valve {} stuck", "z1");
+            tracing::error!(target: "irrigation_hub::logs", "own failure");
+        });
+
+        let r = rx.try_recv().unwrap();
+        assert_eq!(r.level, "WARN");
+        assert_eq!(r.target, "irrigation_hub::mqtt");
+        assert_eq!(r.message, "valve z1 stuck");
+        assert_eq!(r.fields["zone"], "z1");
+        assert_eq!(r.fields["pulses"], 3);
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -8,35 +8,94 @@
 //! - Valve watchdog: force-close valves open longer than pulse_sec + margin
 //! - Sensor failure detection: skip implausible raw ADC readings
 //! - Data retention: periodic pruning of old readings
-
+//! - Flow health: alert on gradually declining zone flow (clogged emitters)
+//! - Task supervisor: restart a failed watchdog/scheduler (valves off first)
+//!   with backoff; exit only after repeated failures
+
+mod adapters;
+mod aggregation;
+mod alerts;
+mod arbitration;
+mod audit;
+mod auth;
+mod backup;
+mod blackout;
+mod budget;
+mod calibration;
+mod clock;
 mod config;
+mod confirm;
 mod db;
+mod efficiency;
+mod estop;
+mod et;
+mod federation;
+mod flow;
+mod flush;
+mod frost;
+mod gpio;
+mod history;
+mod interlock;
+mod kiosk;
+mod limits;
+mod logs;
+mod maintenance;
+mod metrics;
+mod moisture;
 mod mqtt;
+mod ota;
+mod otel;
+mod pressure;
+mod restore;
+mod retention;
+mod review;
 mod scheduler;
+mod selftest;
+mod sessions;
 mod state;
+mod strategy;
+mod sunlight;
+mod supervisor;
 mod valve;
 mod web;
 
 use anyhow::{Context, Result};
 use rumqttc::{AsyncClient, Event, LastWill, MqttOptions, Packet, QoS};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
     env,
     sync::Arc,
     time::Duration,
 };
 use time::OffsetDateTime;
-use tokio::sync::{Mutex, RwLock};
+use tokio::sync::{Mutex, Notify, RwLock};
 use tokio::time::Instant;
-use tracing::{error, info, warn};
-
-use config::OperationMode;
-use db::{compute_moisture, is_reading_plausible, Db, SensorConfig, ZoneConfig};
+use tracing::{debug, error, info, instrument, warn};
+use tracing_subscriber::prelude::*;
+
+use audit::AuditSource;
+use blackout::Blackouts;
+use config::{OperationMode, ValveServiceConfig};
+use db::{Db, EventDetails, NodeConfig, SensorConfig, StalePolicy, ZoneConfig};
+use interlock::Interlocks;
+use metrics::{CommandSource, LatencyStage};
 use mqtt::{
-    extract_node_id, extract_node_status_id, extract_zone_id, parse_valve_command, ReadingMsg,
+    extract_advice_zone_id, extract_cbor_node_id, extract_flow_zone_id, extract_node_id,
+    extract_node_logs_id, extract_node_pong_id, extract_node_status_id, extract_ota_report_node_id,
+    extract_pressure_source_id, extract_temp_source_id, extract_weather_source_id, extract_zone_id,
+    node_command_topic, node_ota_topic, node_settings_topic, parse_advice, parse_flow,
+    parse_node_logs, parse_node_pong, parse_node_status, parse_ota_report, parse_pressure,
+    parse_telemetry_as, parse_temperature, parse_valve_command, parse_weather, sim_valve_topic,
+    valve_set_topic, NodeSettingsMsg, PayloadEncoding, ReadingMsg, ValveCommandDedup,
 };
-use state::{SensorReading, SystemState};
-use valve::ValveBoard;
+use sessions::{Planned, SessionResult};
+use state::{
+    degraded_limit, NodeLogs, NodePong, SensorReading, SystemState, DEFAULT_NODE_STALE_TIMEOUT_MIN,
+    DEFAULT_SENSOR_QUARANTINE_AFTER,
+};
+use strategy::{Advice, StrategyConfig};
+use supervisor::{Decision, Supervisor};
+use valve::{MotorSpec, ValveBoard, ValveConfig};
 
 /// Margin (in seconds) added to a zone's `pulse_sec` for the watchdog timer.
 const WATCHDOG_MARGIN_SEC: u64 = 30;
@@ -44,11 +103,14 @@ const WATCHDOG_MARGIN_SEC: u64 = 30;
 /// How often the watchdog checks for stuck-open valves.
 const WATCHDOG_INTERVAL_SEC: u64 = 5;
 
-/// Data retention pruning interval (6 hours).
-const PRUNE_INTERVAL_SEC: u64 = 6 * 3600;
+/// Default seconds between batched reading writes.
+const DEFAULT_READINGS_FLUSH_INTERVAL_SEC: u64 = 30;
+
+/// Default queued readings that trigger a write before the interval is up.
+const DEFAULT_READINGS_FLUSH_MAX_ROWS: usize = 50;
 
-/// Default data retention period in days.
-const RETENTION_DAYS: i64 = 90;
+/// Default seconds between `status/hub/heartbeat` messages.
+const DEFAULT_HUB_HEARTBEAT_INTERVAL_SEC: u64 = 60;
 
 /// Grace period (seconds) for MQTT errors before triggering emergency valve
 /// shutdown.  During this window the hub logs warnings but does not interrupt
@@ -59,19 +121,70 @@ const MQTT_GRACE_PERIOD_SEC: u64 = 60;
 /// How often the heartbeat monitor checks for stale nodes (seconds).
 const HEARTBEAT_CHECK_INTERVAL_SEC: u64 = 60;
 
-/// Default threshold (in minutes) after which a node is considered stale if no
-/// telemetry has been received.  Override with `NODE_STALE_TIMEOUT_MIN` env var.
-/// Should be roughly 2× the node sampling interval (default 300s = 5 min).
-const DEFAULT_NODE_STALE_TIMEOUT_MIN: i64 = 10;
+/// How often the database is probed for writability (degraded mode).
+const DB_PROBE_INTERVAL_SEC: u64 = 30;
+
+/// How often zone flow trends are checked for clogging (6 hours).
+const FLOW_CHECK_INTERVAL_SEC: u64 = 6 * 3600;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // ── Structured logging ──────────────────────────────────────────
-    tracing_subscriber::fmt()
-        .with_env_filter(
+    // LOG_FORMAT=json prints JSON lines; LOG_TO_DB also keeps WARN/ERROR
+    // records in the `logs` table (see `logs`).
+    let log_format = env::var("LOG_FORMAT").ok().filter(|s| !s.is_empty());
+    let log_to_db = env::var("LOG_TO_DB").is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+    let (db_log_layer, db_log_rx) = if log_to_db {
+        let (layer, rx) = logs::DbLogLayer::new();
+        (Some(layer), Some(rx))
+    } else {
+        (None, None)
+    };
+    // Trace export to an OTLP collector (see `otel`).
+    let (otel_layer, otel_guard) = otel::init()?;
+    let json_logs =
+        log_format.as_deref().and_then(logs::LogFormat::parse) == Some(logs::LogFormat::Json);
+    tracing_subscriber::registry()
+        .with(
             tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
         )
+        .with((!json_logs).then(tracing_subscriber::fmt::layer))
+        .with(json_logs.then(|| tracing_subscriber::fmt::layer().json()))
+        .with(db_log_layer)
+        .with(otel_layer)
         .init();
+    if let Some(s) = log_format.filter(|s| logs::LogFormat::parse(s).is_none()) {
+        warn!(value = %s, "invalid LOG_FORMAT — using text");
+    }
+    if otel::endpoint_configured() {
+        if cfg!(feature = "otlp") {
+            info!("exporting traces over OTLP");
+        } else {
+            warn!(
+                "OTEL_EXPORTER_OTLP_ENDPOINT is set but the hub was built without the otlp feature"
+            );
+        }
+    }
+    let log_retention_days: Option<i64> = env::var("LOG_RETENTION_DAYS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|&d| d > 0);
+
+    // ── Simulated clock ─────────────────────────────────────────────
+    // `--sim-speed 60x` runs the hub's clock 60 times faster (development
+    // only; see `clock`).
+    let sim_speed = clock::speed_from_args(&env::args().skip(1).collect::<Vec<_>>())
+        .map_err(anyhow::Error::msg)?;
+    if sim_speed > 1 {
+        if cfg!(any(feature = "gpio", feature = "gpiod")) {
+            anyhow::bail!("--sim-speed is for the mock valve board; this hub was built with GPIO");
+        }
+        warn!(
+            speed = sim_speed,
+            "simulated clock: time runs {sim_speed}x faster — development only"
+        );
+        clock::set(clock::TimeSource::accelerated(sim_speed));
+    }
 
     // ── Env config ──────────────────────────────────────────────────
     let broker = env::var("MQTT_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
@@ -80,11 +193,57 @@ async fn main() -> Result<()> {
         .and_then(|s| s.parse().ok())
         .unwrap_or(1883);
     let db_url = env::var("DB_URL").unwrap_or_else(|_| "sqlite:irrigation.db?mode=rwc".to_string());
+    let db_busy_timeout = env::var("DB_BUSY_TIMEOUT_MS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .map_or(db::DEFAULT_BUSY_TIMEOUT, Duration::from_millis);
     let db_backup_path = env::var("DB_BACKUP_PATH").ok().filter(|s| !s.is_empty());
     let db_backup_interval: u64 = env::var("DB_BACKUP_INTERVAL_SEC")
         .ok()
         .and_then(|s| s.parse().ok())
         .unwrap_or(1800);
+    // Incremental backups update the backup in place and take a full
+    // snapshot every DB_BACKUP_FULL_INTERVAL_SEC (see `backup`).
+    let db_backup_mode = match env::var("DB_BACKUP_MODE") {
+        Ok(s) if !s.is_empty() => backup::BackupMode::parse(&s).unwrap_or_else(|| {
+            warn!(value = %s, "invalid DB_BACKUP_MODE — using full backups");
+            backup::BackupMode::Full
+        }),
+        _ => backup::BackupMode::Full,
+    };
+    let db_backup_full_interval: u64 = env::var("DB_BACKUP_FULL_INTERVAL_SEC")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(backup::DEFAULT_FULL_INTERVAL_SEC);
+    let backup_schedule = Arc::new(Mutex::new(backup::BackupSchedule::new(
+        db_backup_mode,
+        Duration::from_secs(db_backup_full_interval),
+    )));
+    // Readings are written in batches to spare the SD card: every
+    // READINGS_FLUSH_INTERVAL_SEC, or sooner once READINGS_FLUSH_MAX_ROWS
+    // are queued.  An interval of 0 writes each reading immediately.
+    let readings_flush_interval: u64 = env::var("READINGS_FLUSH_INTERVAL_SEC")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_READINGS_FLUSH_INTERVAL_SEC);
+    let readings_flush_max_rows: usize = env::var("READINGS_FLUSH_MAX_ROWS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(DEFAULT_READINGS_FLUSH_MAX_ROWS);
+    // Periodic health message on status/hub/heartbeat; 0 disables it.
+    let hub_heartbeat_interval: u64 = env::var("HUB_HEARTBEAT_INTERVAL_SEC")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_HUB_HEARTBEAT_INTERVAL_SEC);
+
+    // Recent dashboard events survive restarts in a sidecar file, by
+    // default next to the database file.  Point EVENTS_PATH at persistent
+    // storage when the database lives on tmpfs.
+    let events_path = env::var("EVENTS_PATH")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .or_else(|| db::db_file_path(&db_url).map(|p| format!("{p}.events.json")));
 
     // ── Database ────────────────────────────────────────────────────
     // When using tmpfs the database file is lost on reboot.  Restore
@@ -97,16 +256,40 @@ async fn main() -> Result<()> {
         }
     }
 
-    let db = Db::connect(&db_url).await?;
+    let mut db = Db::connect_with(&db_url, db_busy_timeout).await?;
     db.migrate().await?;
+    if let Some(rx) = db_log_rx {
+        tokio::spawn(logs::run_writer(db.clone(), rx));
+        info!("storing warnings and errors in the database");
+    }
+    if readings_flush_interval > 0 {
+        db = db.with_reading_batch(readings_flush_max_rows);
+    }
 
     // ── Config file (seed zones + sensors) ───────────────────────────
     let config_path = env::var("CONFIG_PATH").unwrap_or_else(|_| "config.toml".to_string());
     let cfg = config::load(&config_path)?;
     config::apply(&cfg, &db).await?;
+    // Snapshot the seeded configuration (no-op if unchanged since last run).
+    if let Err(e) = db
+        .record_config_version(now_unix(), "startup", AuditSource::ConfigFile, None)
+        .await
+    {
+        warn!("config snapshot failed: {e:#}");
+    }
     let max_concurrent_valves = cfg.max_concurrent_valves;
     let mode = cfg.mode;
+    let soak_policy = cfg.soak;
+    let maintenance = cfg.maintenance_windows();
+    let budget = budget::Budget::new(&cfg.budget);
+    let flush_plan = flush::FlushPlan::new(&cfg.flush);
+    let interlocks = Interlocks::new(&cfg.interlocks);
+    let mqtt_adapters = cfg.mqtt_adapters.clone();
+    let relay_board = cfg.relay_board();
     info!(?mode, "operation mode");
+    if !cfg.maintenance.windows.is_empty() {
+        info!(windows = ?cfg.maintenance.windows, "maintenance restricted to windows (UTC)");
+    }
 
     // Load zone config from DB — this is the source of truth.
     let zones = db.load_zones().await?;
@@ -132,14 +315,19 @@ async fn main() -> Result<()> {
             })
             .collect::<Result<Vec<_>>>()?
     };
+    let motorized = if mode == OperationMode::Monitor {
+        Vec::new()
+    } else {
+        motorized_valves(&zones)?
+    };
 
     // Build zone config lookup for safety limit enforcement + watchdog.
-    let zone_configs: HashMap<String, ZoneConfig> =
+    let mut zone_configs: HashMap<String, ZoneConfig> =
         zones.into_iter().map(|z| (z.zone_id.clone(), z)).collect();
 
     // Build sensor lookup table for calibration during MQTT readings.
     let sensors = db.load_sensors().await?;
-    let sensor_map: HashMap<String, SensorConfig> = sensors
+    let mut sensor_map: HashMap<String, SensorConfig> = sensors
         .into_iter()
         .map(|s| (s.sensor_id.clone(), s))
         .collect();
@@ -150,13 +338,60 @@ async fn main() -> Result<()> {
         "database ready"
     );
 
+    // ── Crash recovery ──────────────────────────────────────────────
+    // Valves still marked open were interrupted by a crash: all valves are
+    // forced off below, so close the sessions out and count their time.
+    let recovered_valves = match db
+        .close_open_valves(now_unix(), "hub_restart", "recovered", "hub_restart")
+        .await
+    {
+        Ok(closed) => closed,
+        Err(e) => {
+            error!("open-valve recovery failed: {e:#}");
+            Vec::new()
+        }
+    };
+    for (zone_id, duration_secs) in &recovered_valves {
+        warn!(
+            zone = %zone_id,
+            duration_secs,
+            "valve was open when the hub stopped — session closed out"
+        );
+    }
+
     // ── Valve board ─────────────────────────────────────────────────
+    // RELAY_ACTIVE_LOW (if set) wins over the [relay_board] config.
     let active_low = env::var("RELAY_ACTIVE_LOW")
         .ok()
         .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .or(relay_board.as_ref().map(|b| b.active_low))
         .unwrap_or(true);
+    // VALVE_STAGGER_MS (if set) wins over the board's stagger_ms.
+    let stagger = Duration::from_millis(
+        env::var("VALVE_STAGGER_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .or(relay_board.as_ref().map(|b| b.stagger_ms))
+            .unwrap_or(0),
+    );
 
-    let valves = Arc::new(Mutex::new(ValveBoard::new(&zone_to_gpio, active_low)?));
+    // SIM_HIL: mirror the mock board's writes to the node simulator so
+    // simulated sensors respond to watering (demos, acceptance tests).
+    let sim_hil = env::var("SIM_HIL").is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+    info!(
+        board = ?cfg.gpio.board,
+        backend = ?cfg.gpio.backend(),
+        "GPIO"
+    );
+    let mut valve_board = ValveBoard::new(&cfg.gpio, &zone_to_gpio, active_low)?
+        .with_motorized(&motorized)?
+        .with_stagger(stagger);
+    let gpio_intents = if sim_hil {
+        valve_board.mirror_intents()
+    } else {
+        None
+    };
+    let valves = Arc::new(Mutex::new(valve_board));
     valves.lock().await.all_off();
 
     // Track when each valve was opened (for watchdog + duration accounting).
@@ -168,47 +403,250 @@ async fn main() -> Result<()> {
         OperationMode::Auto => "auto",
         OperationMode::Monitor => "monitor",
     };
+    let node_stale_timeout_min: i64 = env::var("NODE_STALE_TIMEOUT_MIN")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_NODE_STALE_TIMEOUT_MIN);
+    let sensor_quarantine_after: i64 = env::var("SENSOR_QUARANTINE_AFTER")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_SENSOR_QUARANTINE_AFTER);
+    let sensor_health = match db.list_sensor_health().await {
+        Ok(health) => health,
+        Err(e) => {
+            warn!("sensor health not loaded: {e:#}");
+            Vec::new()
+        }
+    };
+
+    // A retention policy set over the API wins over `[retention]`, which
+    // LOG_RETENTION_DAYS overrides for log records.
+    let retention = match db.load_retention_policy().await {
+        Ok(Some(policy)) => {
+            info!(?policy, "using retention policy set over the API");
+            policy
+        }
+        Ok(None) => retention::RetentionPolicy {
+            logs_days: log_retention_days.unwrap_or(cfg.retention.logs_days),
+            ..cfg.retention
+        },
+        Err(e) => {
+            warn!("stored retention policy not loaded: {e:#}");
+            cfg.retention
+        }
+    };
+
+    // A latched emergency stop survives restarts.  If its state can't be
+    // read, err on the side of keeping valves shut.
+    let estop_latch = match db.load_estop_latch().await {
+        Ok(latch) => latch,
+        Err(e) if cfg.emergency_stop.is_some() => {
+            error!("emergency stop state not loaded — latching: {e:#}");
+            Some(now_unix())
+        }
+        Err(e) => {
+            warn!("emergency stop state not loaded: {e:#}");
+            None
+        }
+    };
+
+    let blackouts = match db.list_blackouts().await {
+        Ok(rows) => Blackouts::new(rows),
+        Err(e) => {
+            warn!("blackout calendar not loaded: {e:#}");
+            Blackouts::default()
+        }
+    };
+
+    let et_deficits = db.load_et_deficits().await.unwrap_or_else(|e| {
+        warn!("water-balance deficits not loaded: {e:#}");
+        Default::default()
+    });
+
     let shared = Arc::new(RwLock::new(SystemState::new(&zone_to_gpio, mode_str)));
     {
         let mut st = shared.write().await;
+        st.node_stale_timeout_min = node_stale_timeout_min;
+        st.sensor_quarantine_after = sensor_quarantine_after;
+        st.frost = frost::FrostLockout::new(&cfg.frost);
+        st.sunlight = sunlight::Sunlight::new(&cfg.sunlight);
+        st.pressure = pressure::PressureMonitor::new(&cfg.pressure);
+        st.valve_test = selftest::ValveTest::new(&cfg.valve_test);
+        st.retention = retention;
+        st.blackouts = blackouts;
+        st.et_deficits = et_deficits;
+        st.federation = federation::Federation::new(&cfg.federation);
+        st.moisture_alerts = alerts::MoistureAlerts::new(&cfg.moisture_alerts);
+        st.estop.configured = cfg.emergency_stop.is_some();
+        st.estop.latched_since = estop_latch;
+        st.limits = limits::SafetyLimits {
+            mode,
+            max_concurrent_valves,
+            watchdog: limits::WatchdogLimits {
+                interval_sec: WATCHDOG_INTERVAL_SEC,
+                margin_sec: WATCHDOG_MARGIN_SEC,
+            },
+            mqtt_grace_period_sec: MQTT_GRACE_PERIOD_SEC,
+            task_heartbeat_timeout_sec: state::TASK_HEARTBEAT_TIMEOUT_SEC,
+            scheduler_tick_sec: scheduler::TICK_INTERVAL_SEC,
+            degraded_limit_divisor: state::DEGRADED_LIMIT_DIVISOR,
+            valve_stagger_ms: stagger.as_millis() as u64,
+            gpio: cfg.gpio.clone(),
+            review_long_runtime_sec: review::LONG_RUNTIME_SEC,
+            frost: cfg.frost,
+            pressure: cfg.pressure,
+            budget: cfg.budget.clone(),
+            interlocks: cfg.interlocks.clone(),
+            valve_service: cfg.valve_service,
+            ingest: limits::IngestLimits {
+                max_json_payload_bytes: mqtt::MAX_JSON_PAYLOAD_BYTES,
+                max_readings_per_message: mqtt::MAX_READINGS_PER_MESSAGE,
+                max_backfill_sec: mqtt::MAX_BACKFILL_SEC,
+                reading_buffer_cap: db::READING_BUFFER_CAP,
+                readings_flush_interval_sec: readings_flush_interval,
+                readings_flush_max_rows,
+                sensor_quarantine_after,
+            },
+            zones: limits::zone_limits(&zone_configs, &flush_plan, |z| {
+                watchdog_limit_sec(z, &zone_configs, &flush_plan, false)
+            }),
+        };
+        // Sensors failing when the hub stopped stay out of zone moisture.
+        for h in &sensor_health {
+            if h.consecutive_failures > 0 {
+                st.set_sensor_faulted(&h.sensor_id, true);
+            }
+            if h.quarantined_ts.is_some() {
+                st.set_sensor_quarantined(&h.sensor_id, true);
+            }
+        }
+        if let Some(path) = &events_path {
+            match state::load_events(path) {
+                Ok(saved) if !saved.is_empty() => {
+                    info!(path = %path, events = saved.len(), "restored recent events");
+                    st.restore_events(saved);
+                }
+                Ok(_) => {}
+                Err(e) => warn!("event history not restored: {e:#}"),
+            }
+        }
         st.record_system("hub started".to_string());
+        if estop_latch.is_some() {
+            st.record_error(
+                "emergency stop still latched — valves stay off until it is cleared".to_string(),
+            );
+        }
+        for (zone_id, duration_secs) in &recovered_valves {
+            st.record_error(format!(
+                "recovered valve {zone_id} left open by previous run — {duration_secs}s recorded"
+            ));
+        }
+    }
+    {
+        let zones: Vec<ZoneConfig> = zone_configs.values().cloned().collect();
+        let sensors: Vec<SensorConfig> = sensor_map.values().cloned().collect();
+        run_safety_review(&zones, &sensors, &db, &shared).await;
+    }
+
+    // ── Federation ──────────────────────────────────────────────────
+    // Read-only mirrors of other hubs; losing one never affects this one.
+    for site in &cfg.federation.sites {
+        info!(site = %site.name, host = %site.host, "federation: mirroring site");
+        tokio::spawn(federation::run(site.clone(), Arc::clone(&shared)));
     }
 
     // ── Web server ──────────────────────────────────────────────────
+    // Signalled by the API on sensor / node changes and on every MQTT
+    // (re)connect; the publisher task below pushes node settings.
+    let node_settings = Arc::new(Notify::new());
+
+    // Confirmed restores from POST /api/backups/restore, carried out by the
+    // main loop (it owns the valves and the critical tasks).
+    let (restore_tx, mut restore_rx) = tokio::sync::mpsc::channel(1);
+    let restore_api = restore::RestoreApi::new(
+        db_backup_path.as_deref().map(restore::backup_dir),
+        restore_tx,
+    );
+
+    // Diagnostics commands (restart, send-logs) from the node API,
+    // published by the node command task below.
+    let (node_command_tx, mut node_command_rx) = tokio::sync::mpsc::channel(16);
+    // Update announcements (or `None` to withdraw one) for
+    // `cfg/<node_id>/ota`, published by the same task.
+    let (ota_tx, mut ota_rx) = tokio::sync::mpsc::channel::<ota::AnnounceRequest>(16);
+    let ota_api = ota::OtaApi {
+        config: cfg.ota.clone(),
+        announcements: ota_tx.clone(),
+    };
+
+    // Zones of sessions cancelled through the API, sent OFF by the session
+    // cancel task below.
+    let (session_cancel_tx, mut session_cancel_rx) = tokio::sync::mpsc::channel::<String>(16);
+    // Valves opened with a `duration_sec` are closed the same way.
+    let timed_close_tx = session_cancel_tx.clone();
+    // Manual pulses from `POST /api/zones/{id}/water-now`: (zone, seconds),
+    // sent ON by the same task.
+    let (water_now_tx, mut water_now_rx) = tokio::sync::mpsc::channel::<(String, i64)>(16);
+    // Telemetry posted to `POST /api/telemetry`: (node, JSON payload),
+    // ingested by the main loop like MQTT telemetry.
+    let (http_telemetry_tx, mut http_telemetry_rx) =
+        tokio::sync::mpsc::channel::<(String, Vec<u8>)>(64);
+
     let web_state = Arc::clone(&shared);
     let web_db = db.clone();
+    let web_node_settings = Arc::clone(&node_settings);
     let mut web_handle = tokio::spawn(async move {
-        web::serve(web_state, web_db).await;
+        web::serve(
+            web_state,
+            web_db,
+            web_node_settings,
+            node_command_tx,
+            session_cancel_tx,
+            water_now_tx,
+            restore_api,
+            ota_api,
+            http_telemetry_tx,
+        )
+        .await;
     });
 
     // ── Valve watchdog ──────────────────────────────────────────────
-    let mut watchdog_handle = if mode == OperationMode::Monitor {
-        tokio::spawn(async { std::future::pending::<()>().await })
-    } else {
+    // Critical tasks are built by spawn functions so the supervisor can
+    // restart them (after `delay`) if they panic or exit, and a backup
+    // restore can restart them with the reloaded zones.
+    let spawn_watchdog = |delay: Duration, zone_configs: &HashMap<String, ZoneConfig>| {
         let wd_valves = Arc::clone(&valves);
         let wd_opened = Arc::clone(&valve_opened_at);
         let wd_shared = Arc::clone(&shared);
         let wd_zone_configs = zone_configs.clone();
+        let wd_flush = flush_plan.clone();
         let wd_db = db.clone();
         tokio::spawn(async move {
-            let mut ticker = tokio::time::interval(Duration::from_secs(WATCHDOG_INTERVAL_SEC));
+            tokio::time::sleep(delay).await;
+            if mode == OperationMode::Monitor {
+                std::future::pending::<()>().await;
+            }
+            let mut ticker = clock::interval(Duration::from_secs(WATCHDOG_INTERVAL_SEC));
             loop {
                 ticker.tick().await;
+                wd_shared.write().await.watchdog_heartbeat = Some(OffsetDateTime::now_utc());
 
                 let mut opened = wd_opened.lock().await;
                 let mut to_close: Vec<(String, u64)> = Vec::new();
 
+                let st = wd_shared.read().await;
                 for (zone_id, opened_time) in opened.iter() {
-                    let elapsed_secs = opened_time.elapsed().as_secs();
-                    let max_secs = wd_zone_configs
-                        .get(zone_id)
-                        .map(|z| z.pulse_sec as u64 + WATCHDOG_MARGIN_SEC)
-                        .unwrap_or(60 + WATCHDOG_MARGIN_SEC);
+                    let elapsed_secs = clock::since(*opened_time).as_secs();
+                    let flushing = st.sessions.active_reason(zone_id) == Some(flush::FLUSH_REASON);
+                    let max_secs =
+                        watchdog_limit_sec(zone_id, &wd_zone_configs, &wd_flush, flushing);
 
                     if elapsed_secs > max_secs {
                         to_close.push((zone_id.clone(), elapsed_secs));
                     }
                 }
+                drop(st);
 
                 if to_close.is_empty() {
                     continue;
@@ -225,40 +663,80 @@ async fn main() -> Result<()> {
                     );
                     board.set(zone_id, false);
                     opened.remove(zone_id.as_str());
+                    if let Err(e) = wd_db.mark_valve_closed(zone_id).await {
+                        error!(zone = %zone_id, "watchdog: mark_valve_closed failed: {e}");
+                    }
                     st.record_valve(zone_id, false);
+                    let session = st.sessions.finish(
+                        zone_id,
+                        Some(SessionResult::Watchdog),
+                        OffsetDateTime::now_utc(),
+                    );
                     st.record_error(format!(
                         "watchdog force-closed valve {zone_id} after {elapsed_secs}s"
                     ));
 
-                    // Record the open duration in daily counters
+                    // Record the open duration in daily counters (in memory
+                    // while the DB is degraded).
                     let today = Db::today_yyyy_mm_dd();
-                    if let Err(e) = wd_db
-                        .add_open_seconds(&today, zone_id, *elapsed_secs as i64)
-                        .await
-                    {
+                    let secs = *elapsed_secs as i64;
+                    if st.is_db_degraded() {
+                        st.add_pending_open_sec(&today, zone_id, secs);
+                    } else if let Err(e) = wd_db.add_open_seconds(&today, zone_id, secs).await {
                         error!(zone = %zone_id, "watchdog: add_open_seconds failed: {e}");
+                        st.mark_db_degraded("add_open_seconds failed");
+                        st.add_pending_open_sec(&today, zone_id, secs);
+                    }
+
+                    if !st.is_db_degraded() {
+                        let reason = session
+                            .as_ref()
+                            .map_or(sessions::UNPLANNED_REASON, |s| s.reason);
+                        let details = EventDetails {
+                            planned_sec: session.as_ref().and_then(|s| s.planned_sec),
+                            triggered_by: Some(sessions::triggered_by(reason)),
+                            ended_by: Some(SessionResult::Watchdog.ended_by()),
+                        };
+                        let now_ts = now_unix();
+                        if let Err(e) = wd_db
+                            .insert_watering_event(
+                                now_ts - secs,
+                                now_ts,
+                                zone_id,
+                                reason,
+                                SessionResult::Watchdog.event_result(),
+                                details,
+                            )
+                            .await
+                        {
+                            error!(zone = %zone_id, "watchdog: insert_watering_event failed: {e}");
+                        }
                     }
                 }
             }
         })
     };
+    let mut watchdog_handle = spawn_watchdog(Duration::ZERO, &zone_configs);
 
     // ── Data retention pruning ──────────────────────────────────────
     let mut prune_handle = {
         let prune_db = db.clone();
         let prune_shared = Arc::clone(&shared);
+        let prune_windows = maintenance.clone();
         tokio::spawn(async move {
             // Don't prune immediately on startup — wait a bit first.
             tokio::time::sleep(Duration::from_secs(60)).await;
 
-            let mut ticker = tokio::time::interval(Duration::from_secs(PRUNE_INTERVAL_SEC));
             loop {
-                ticker.tick().await;
-                match prune_db.prune_old_readings(RETENTION_DAYS).await {
-                    Ok(n) if n > 0 => {
-                        info!(deleted = n, "pruned old readings");
+                prune_windows.wait("prune").await;
+                let policy = prune_shared.read().await.retention;
+                match retention::prune(&prune_db, &policy, Some(retention::PERIODIC_VACUUM_PAGES))
+                    .await
+                {
+                    Ok(report) if report.total_rows() > 0 => {
+                        info!(?report, "pruned old data");
                         let mut st = prune_shared.write().await;
-                        st.record_system(format!("pruned {n} old readings (>{RETENTION_DAYS}d)"));
+                        st.record_system(report.summary());
                     }
                     Ok(_) => {}
                     Err(e) => {
@@ -267,6 +745,136 @@ async fn main() -> Result<()> {
                         st.record_error(format!("data retention prune failed: {e:#}"));
                     }
                 }
+                // Re-read so an interval changed over the API applies from
+                // the next run.
+                let hours = prune_shared.read().await.retention.interval_hours;
+                clock::sleep(Duration::from_secs(hours * 3600)).await;
+            }
+        })
+    };
+
+    // ── Emergency-stop button ───────────────────────────────────────
+    // A configured button that can't be read is fatal: the hub must not
+    // water without a working stop.
+    let estop_input = match cfg.emergency_stop {
+        Some(c) => Some((
+            c,
+            estop::StopInput::new(&cfg.gpio, c.gpio_pin as u8, c.active_low).with_context(
+                || format!("emergency stop input on GPIO {} unavailable", c.gpio_pin),
+            )?,
+        )),
+        None => None,
+    };
+    let mut estop_handle = {
+        let estop_valves = Arc::clone(&valves);
+        let estop_opened = Arc::clone(&valve_opened_at);
+        let estop_db = db.clone();
+        let estop_shared = Arc::clone(&shared);
+        tokio::spawn(async move {
+            let Some((cfg, input)) = estop_input else {
+                // No button configured — park this task forever.
+                std::future::pending::<()>().await;
+                return;
+            };
+            info!(gpio = cfg.gpio_pin, "emergency stop input ready");
+            let initial = input.is_pressed();
+            let mut debouncer =
+                estop::Debouncer::new(Duration::from_millis(cfg.debounce_ms), initial);
+            if initial {
+                // Held down at startup: treat as a press.
+                estop_shared.write().await.estop.pressed = true;
+                emergency_stop(&estop_valves, &estop_opened, &estop_db, &estop_shared).await;
+            }
+
+            let mut ticker = tokio::time::interval(estop::POLL_INTERVAL);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                ticker.tick().await;
+                let Some(pressed) = debouncer.update(input.is_pressed(), std::time::Instant::now())
+                else {
+                    continue;
+                };
+                estop_shared.write().await.estop.pressed = pressed;
+                if pressed {
+                    emergency_stop(&estop_valves, &estop_opened, &estop_db, &estop_shared).await;
+                } else {
+                    info!("emergency stop button released");
+                }
+            }
+        })
+    };
+
+    // ── Motorized valve drives ──────────────────────────────────────
+    // Powers each ball valve motor down once it has run its travel time.
+    let mut motor_handle = {
+        let motor_valves = Arc::clone(&valves);
+        let has_motors = !motorized.is_empty();
+        tokio::spawn(async move {
+            if !has_motors {
+                // Solenoids only — park this task forever.
+                std::future::pending::<()>().await;
+            }
+            let mut ticker = tokio::time::interval(valve::MOTOR_SERVICE_INTERVAL);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                motor_valves.lock().await.service_motors();
+            }
+        })
+    };
+
+    // ── Batched reading writes (SD card wear mitigation) ────────────
+    let mut flush_handle = {
+        let flush_db = db.clone();
+        let flush_shared = Arc::clone(&shared);
+        tokio::spawn(async move {
+            if readings_flush_interval == 0 {
+                // Readings are written as they arrive — park this task forever.
+                std::future::pending::<()>().await;
+            }
+            let mut ticker = tokio::time::interval(Duration::from_secs(readings_flush_interval));
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                match flush_db.flush_readings().await {
+                    Ok(n) if n > 0 => debug!(rows = n, "readings flushed"),
+                    Ok(_) => {}
+                    Err(e) => {
+                        error!("reading flush failed: {e:#}");
+                        flush_shared
+                            .write()
+                            .await
+                            .mark_db_degraded("reading flush failed");
+                    }
+                }
+            }
+        })
+    };
+
+    // ── Event ring sidecar file ─────────────────────────────────────
+    let mut events_handle = {
+        let events_shared = Arc::clone(&shared);
+        let events_path = events_path.clone();
+        tokio::spawn(async move {
+            let Some(path) = events_path else {
+                // Nowhere to save events — park this task forever.
+                std::future::pending::<()>().await;
+                return;
+            };
+            let mut saved_seq = 0;
+            let mut ticker =
+                tokio::time::interval(Duration::from_secs(state::EVENTS_SAVE_INTERVAL_SEC));
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                let seq = events_shared.read().await.events_seq;
+                if seq == saved_seq {
+                    continue;
+                }
+                match save_events(&events_shared, &path).await {
+                    Ok(()) => saved_seq = seq,
+                    Err(e) => warn!("saving events failed: {e:#}"),
+                }
             }
         })
     };
@@ -276,6 +884,7 @@ async fn main() -> Result<()> {
         let backup_db = db.clone();
         let backup_dest = db_backup_path.clone();
         let backup_shared = Arc::clone(&shared);
+        let schedule = Arc::clone(&backup_schedule);
         tokio::spawn(async move {
             let Some(dest) = backup_dest else {
                 // No backup path configured — park this task forever.
@@ -285,14 +894,16 @@ async fn main() -> Result<()> {
             info!(
                 path = %dest,
                 interval_sec = db_backup_interval,
+                mode = db_backup_mode.as_str(),
                 "database backup task started"
             );
 
             {
                 let mut st = backup_shared.write().await;
                 st.record_system(format!(
-                    "database backup task started (interval: {}s)",
-                    db_backup_interval
+                    "database backup task started (interval: {}s, mode: {})",
+                    db_backup_interval,
+                    db_backup_mode.as_str()
                 ));
             }
 
@@ -300,13 +911,14 @@ async fn main() -> Result<()> {
             tokio::time::sleep(Duration::from_secs(120)).await;
 
             let mut ticker = tokio::time::interval(Duration::from_secs(db_backup_interval));
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
             loop {
                 ticker.tick().await;
-                match backup_db.backup(&dest).await {
-                    Ok(()) => {
-                        info!(path = %dest, "database backup complete");
-                        let mut st = backup_shared.write().await;
-                        st.record_system("database backup complete".to_string());
+                maintenance.wait("backup").await;
+                match schedule.lock().await.run(&backup_db, &dest).await {
+                    Ok(kind) => {
+                        info!(path = %dest, kind = kind.as_str(), "database backup complete");
+                        backup_shared.write().await.record_backup();
                     }
                     Err(e) => {
                         error!("database backup failed: {e:#}");
@@ -319,12 +931,22 @@ async fn main() -> Result<()> {
     };
 
     // ── MQTT ────────────────────────────────────────────────────────
+    // Optional namespace for every topic, e.g. MQTT_TOPIC_PREFIX=garden →
+    // garden/tele/+/reading.  Nodes must use the same prefix.
+    let topic_prefix =
+        mqtt::normalize_topic_prefix(&env::var("MQTT_TOPIC_PREFIX").unwrap_or_default())
+            .map_err(anyhow::Error::msg)?;
+    if !topic_prefix.is_empty() {
+        info!(prefix = %topic_prefix, "using MQTT topic prefix");
+    }
+    mqtt::set_topic_prefix(topic_prefix);
+
     let client_id = "irrigation-hub";
     let mut mqttoptions = MqttOptions::new(client_id, &broker, port);
     mqttoptions.set_keep_alive(Duration::from_secs(30));
     mqttoptions.set_clean_session(false);
     mqttoptions.set_last_will(LastWill::new(
-        "status/hub",
+        mqtt::topic("status/hub"),
         b"offline".to_vec(),
         QoS::AtLeastOnce,
         true,
@@ -341,18 +963,47 @@ async fn main() -> Result<()> {
     let (client, mut eventloop) = AsyncClient::new(mqttoptions, 20);
 
     // Initial subscriptions (re-issued on every reconnect in ConnAck handler).
-    client.subscribe("tele/+/reading", QoS::AtLeastOnce).await?;
-    client.subscribe("valve/+/set", QoS::AtLeastOnce).await?;
-    client.subscribe("status/node/+", QoS::AtLeastOnce).await?;
-    info!("subscribed to tele/+/reading, valve/+/set, status/node/+");
+    for filter in mqtt::SUBSCRIPTIONS {
+        client
+            .subscribe(mqtt::topic(filter), QoS::AtLeastOnce)
+            .await?;
+    }
+    info!(topics = ?mqtt::SUBSCRIPTIONS, "subscribed");
+    // Device topics for the adapters, as written (no namespace prefix).
+    for adapter in &mqtt_adapters {
+        client.subscribe(&adapter.topic, QoS::AtLeastOnce).await?;
+        info!(topic = %adapter.topic, node = %adapter.node_id, "subscribed adapter");
+    }
+
+    // ── Hardware-in-the-loop simulation ─────────────────────────────
+    // Not supervised: if it dies the simulator just stops seeing valves.
+    if let Some(mut intents) = gpio_intents {
+        let hil_mqtt = client.clone();
+        tokio::spawn(async move {
+            info!("hardware-in-the-loop: publishing valve writes to sim/valve/+");
+            while let Some((zone_id, on)) = intents.recv().await {
+                let payload: &[u8] = if on { b"open" } else { b"close" };
+                if let Err(e) = hil_mqtt
+                    .publish(sim_valve_topic(&zone_id), QoS::AtLeastOnce, true, payload)
+                    .await
+                {
+                    warn!(zone = %zone_id, "sim valve publish failed: {e}");
+                }
+            }
+        });
+    }
 
     // ── Auto-watering scheduler ─────────────────────────────────────
-    let mut scheduler_handle = {
+    let spawn_scheduler = |delay: Duration, zone_configs: &HashMap<String, ZoneConfig>| {
         let sched_db = db.clone();
         let sched_configs = zone_configs.clone();
         let sched_mqtt = client.clone();
         let sched_shared = Arc::clone(&shared);
+        let sched_budget = budget.clone();
+        let sched_flush = flush_plan.clone();
+        let sched_interlocks = interlocks.clone();
         tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
             scheduler::run(
                 sched_db,
                 sched_configs,
@@ -360,21 +1011,23 @@ async fn main() -> Result<()> {
                 sched_shared,
                 max_concurrent_valves,
                 mode,
+                soak_policy,
+                sched_budget,
+                sched_flush,
+                sched_interlocks,
             )
             .await;
         })
     };
+    let mut scheduler_handle = spawn_scheduler(Duration::ZERO, &zone_configs);
+    let mut supervisor = Supervisor::new();
 
     // ── Node heartbeat monitor ─────────────────────────────────────
     let mut heartbeat_handle = {
         let hb_shared = Arc::clone(&shared);
+        let hb_db = db.clone();
         tokio::spawn(async move {
-            let stale_timeout_min: i64 = env::var("NODE_STALE_TIMEOUT_MIN")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(DEFAULT_NODE_STALE_TIMEOUT_MIN);
-
-            let stale_threshold = time::Duration::minutes(stale_timeout_min);
+            let stale_timeout_min = node_stale_timeout_min;
 
             info!(stale_timeout_min, "node heartbeat monitor started");
 
@@ -388,18 +1041,35 @@ async fn main() -> Result<()> {
             loop {
                 ticker.tick().await;
 
+                // Per-node overrides from /api/nodes.  Decommissioned nodes
+                // are never reported stale.
+                let node_cfgs: HashMap<String, NodeConfig> = match hb_db.load_nodes().await {
+                    Ok(nodes) => nodes.into_iter().map(|n| (n.node_id.clone(), n)).collect(),
+                    Err(e) => {
+                        warn!("heartbeat: load_nodes failed, using defaults: {e:#}");
+                        HashMap::new()
+                    }
+                };
+
                 let st = hb_shared.read().await;
                 let now = OffsetDateTime::now_utc();
 
-                let mut newly_stale: Vec<(String, i64)> = Vec::new();
+                let mut newly_stale: Vec<(String, i64, StalePolicy)> = Vec::new();
                 let mut recovered: Vec<String> = Vec::new();
 
                 for (node_id, node) in &st.nodes {
+                    let node_cfg = node_cfgs.get(node_id);
+                    if node_cfg.is_some_and(|n| n.decommissioned_at.is_some()) {
+                        continue;
+                    }
+                    // Battery nodes sleep between wakes; only a missed wake
+                    // counts, not the normal timeout.
+                    let policy = StalePolicy::for_node(node_cfg, stale_timeout_min);
                     let elapsed = now - node.last_seen;
-                    let is_stale = elapsed > stale_threshold;
+                    let is_stale = elapsed.whole_seconds() > policy.threshold_sec();
 
                     if is_stale && !warned_stale.contains(node_id) {
-                        newly_stale.push((node_id.clone(), elapsed.whole_minutes()));
+                        newly_stale.push((node_id.clone(), elapsed.whole_minutes(), policy));
                     } else if !is_stale && warned_stale.contains(node_id) {
                         recovered.push(node_id.clone());
                     }
@@ -413,13 +1083,31 @@ async fn main() -> Result<()> {
 
                 let mut st = hb_shared.write().await;
 
-                for (node_id, mins) in &newly_stale {
-                    warn!(
-                        node = %node_id,
-                        last_seen_min_ago = mins,
-                        "node is stale — no data received"
-                    );
-                    st.record_error(format!("node {node_id} stale — last seen {mins} min ago"));
+                for (node_id, mins, policy) in &newly_stale {
+                    match policy {
+                        StalePolicy::Wake { interval_sec, .. } => {
+                            warn!(
+                                node = %node_id,
+                                last_seen_min_ago = mins,
+                                wake_interval_sec = interval_sec,
+                                "battery node missed its scheduled wake"
+                            );
+                            st.record_error(format!(
+                                "node {node_id} missed scheduled wake — last seen {mins} min ago (wakes every {} min)",
+                                interval_sec / 60
+                            ));
+                        }
+                        StalePolicy::Timeout { .. } => {
+                            warn!(
+                                node = %node_id,
+                                last_seen_min_ago = mins,
+                                "node is stale — no data received"
+                            );
+                            st.record_error(format!(
+                                "node {node_id} stale — last seen {mins} min ago"
+                            ));
+                        }
+                    }
                     warned_stale.insert(node_id.clone());
                 }
 
@@ -432,6 +1120,19 @@ async fn main() -> Result<()> {
         })
     };
 
+    // ── DB writability monitor (degraded mode entry / recovery) ────
+    let mut db_monitor_handle = {
+        let mon_db = db.clone();
+        let mon_shared = Arc::clone(&shared);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(DB_PROBE_INTERVAL_SEC));
+            loop {
+                ticker.tick().await;
+                check_db_writable(&mon_db, &mon_shared).await;
+            }
+        })
+    };
+
     // ── System metrics collector ────────────────────────────────────
     let mut metrics_handle = {
         let metrics_shared = Arc::clone(&shared);
@@ -474,6 +1175,135 @@ async fn main() -> Result<()> {
         })
     };
 
+    // ── Flow health monitor (clogged emitters / filter) ─────────────
+    let mut flow_handle = {
+        let flow_db = db.clone();
+        let flow_shared = Arc::clone(&shared);
+        tokio::spawn(async move {
+            let mut alerted: HashSet<String> = HashSet::new();
+            let mut ticker = tokio::time::interval(Duration::from_secs(FLOW_CHECK_INTERVAL_SEC));
+            loop {
+                ticker.tick().await;
+                check_flow_health(&flow_db, &flow_shared, &mut alerted).await;
+            }
+        })
+    };
+
+    // ── Node settings publisher ─────────────────────────────────────
+    let mut node_settings_handle = {
+        let ns_db = db.clone();
+        let ns_mqtt = client.clone();
+        let ns_notify = Arc::clone(&node_settings);
+        tokio::spawn(async move {
+            loop {
+                ns_notify.notified().await;
+                publish_node_settings(&ns_db, &ns_mqtt).await;
+            }
+        })
+    };
+
+    // ── Hub heartbeat ───────────────────────────────────────────────
+    let mut hub_heartbeat_handle = {
+        let hb_shared = Arc::clone(&shared);
+        let hb_mqtt = client.clone();
+        tokio::spawn(async move {
+            if hub_heartbeat_interval == 0 {
+                // Heartbeat disabled — park this task forever.
+                std::future::pending::<()>().await;
+                return;
+            }
+            let topic = mqtt::topic("status/hub/heartbeat");
+            let mut ticker = tokio::time::interval(Duration::from_secs(hub_heartbeat_interval));
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                let payload = {
+                    let st = hb_shared.read().await;
+                    if !st.mqtt_connected {
+                        continue;
+                    }
+                    serde_json::to_vec(&st.to_heartbeat()).expect("heartbeat serialization failed")
+                };
+                // Not retained: a stale heartbeat must not look alive.
+                if let Err(e) = hb_mqtt
+                    .publish(&topic, QoS::AtMostOnce, false, payload)
+                    .await
+                {
+                    warn!(topic = %topic, "hub heartbeat publish failed: {e}");
+                }
+            }
+        })
+    };
+
+    // ── Node command publisher ──────────────────────────────────────
+    let mut node_command_handle = {
+        let nc_mqtt = client.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    Some((node_id, command)) = node_command_rx.recv() => {
+                        let topic = node_command_topic(&node_id, command);
+                        // Not retained: a restart replayed on every reconnect
+                        // would loop the node.
+                        match nc_mqtt
+                            .publish(&topic, QoS::AtLeastOnce, false, command.payload())
+                            .await
+                        {
+                            Ok(()) => {
+                                info!(node = %node_id, command = command.as_str(), "node command sent")
+                            }
+                            Err(e) => warn!(topic = %topic, "node command publish failed: {e}"),
+                        }
+                    }
+                    Some((node_id, announcement)) = ota_rx.recv() => {
+                        let topic = node_ota_topic(&node_id);
+                        // Retained, so a node that is offline picks it up when
+                        // it reconnects; an empty payload clears it.
+                        let payload = announcement
+                            .as_ref()
+                            .map(|a| serde_json::to_vec(a).expect("ota announcement serialization failed"))
+                            .unwrap_or_default();
+                        match nc_mqtt.publish(&topic, QoS::AtLeastOnce, true, payload).await {
+                            Ok(()) => match announcement {
+                                Some(a) => info!(node = %node_id, version = %a.version, "update announced"),
+                                None => info!(node = %node_id, "update announcement cleared"),
+                            },
+                            Err(e) => warn!(topic = %topic, "update announcement publish failed: {e}"),
+                        }
+                    }
+                    else => break,
+                }
+            }
+        })
+    };
+
+    // ── Session cancel / water-now publisher ────────────────────────
+    // OFF and ON go through the valve topic like any other command, so the
+    // hub's own handler enforces its limits, opens or closes the valve and
+    // starts or finishes the session.  A manual pulse is a timed ON.
+    let mut session_cancel_handle = {
+        let sc_mqtt = client.clone();
+        tokio::spawn(async move {
+            loop {
+                let (zone_id, payload) = tokio::select! {
+                    Some(zone_id) = session_cancel_rx.recv() => (zone_id, b"OFF".to_vec()),
+                    Some((zone_id, secs)) = water_now_rx.recv() => {
+                        let payload = serde_json::json!({ "state": "ON", "duration_sec": secs });
+                        (zone_id, payload.to_string().into_bytes())
+                    }
+                    else => break,
+                };
+                let topic = valve_set_topic(&zone_id);
+                if let Err(e) = sc_mqtt
+                    .publish(&topic, QoS::AtLeastOnce, false, payload)
+                    .await
+                {
+                    warn!(topic = %topic, "manual valve command publish failed: {e}");
+                }
+            }
+        })
+    };
+
     // ── Signal handling ─────────────────────────────────────────────
     let ctrl_c = tokio::signal::ctrl_c();
     tokio::pin!(ctrl_c);
@@ -486,6 +1316,7 @@ async fn main() -> Result<()> {
     // MQTT error grace period tracking (audit item #15).  Transient network
     // hiccups should not kill active watering sessions.
     let mut mqtt_first_error_at: Option<Instant> = None;
+    let mut valve_dedup = ValveCommandDedup::default();
     let mut mqtt_error_count: u32 = 0;
 
     loop {
@@ -515,30 +1346,90 @@ async fn main() -> Result<()> {
                                 let topic = p.topic.clone();
                                 let payload = p.payload.to_vec();
 
-                                if let Some(node_id) = extract_node_id(&topic) {
+                                if let Some(adapter) =
+                                    mqtt_adapters.iter().find(|a| a.topic == topic)
+                                {
+                                    match adapter.translate(&payload, now_unix()) {
+                                        Ok(reading) => {
+                                            handle_telemetry(
+                                                &adapter.node_id,
+                                                &reading,
+                                                PayloadEncoding::Json,
+                                                &sensor_map,
+                                                &zone_configs,
+                                                &cfg.et,
+                                                &db,
+                                                &shared,
+                                            )
+                                            .await;
+                                        }
+                                        Err(reject) => {
+                                            warn!(topic = %topic, "adapted payload rejected: {reject}");
+                                            shared
+                                                .write()
+                                                .await
+                                                .record_reject(&adapter.node_id, &reject);
+                                        }
+                                    }
+                                } else if let Some(node_id) = extract_node_id(&topic) {
                                     handle_telemetry(
                                         node_id,
                                         &payload,
+                                        PayloadEncoding::Json,
                                         &sensor_map,
+                                        &zone_configs,
+                                        &cfg.et,
                                         &db,
                                         &shared,
                                     )
                                     .await;
-                                } else if let Some(zone_id) =
-                                    extract_zone_id(&topic)
+                                } else if let Some(node_id) =
+                                    extract_cbor_node_id(&topic)
                                 {
-                                    handle_valve_command(
-                                        zone_id,
+                                    handle_telemetry(
+                                        node_id,
                                         &payload,
+                                        PayloadEncoding::Cbor,
+                                        &sensor_map,
                                         &zone_configs,
-                                        &valves,
-                                        &valve_opened_at,
+                                        &cfg.et,
                                         &db,
                                         &shared,
-                                        max_concurrent_valves,
-                                        mode,
                                     )
                                     .await;
+                                } else if let Some(zone_id) =
+                                    extract_zone_id(&topic)
+                                {
+                                    // A retained command would replay on
+                                    // every (re)subscribe, and a redelivered
+                                    // one would open the valve twice.
+                                    if p.retain {
+                                        warn!(zone = %zone_id, "retained valve command ignored");
+                                        shared.write().await.record_system(format!(
+                                            "retained valve command for {zone_id} ignored"
+                                        ));
+                                    } else if valve_dedup.is_duplicate(
+                                        zone_id,
+                                        &payload,
+                                        p.pkid,
+                                        p.dup,
+                                        std::time::Instant::now(),
+                                    ) {
+                                        info!(zone = %zone_id, "redelivered valve ON ignored");
+                                    } else {
+                                        handle_valve_command(
+                                            zone_id,
+                                            &payload,
+                                            &zone_configs,
+                                            &valves,
+                                            &valve_opened_at,
+                                            &timed_close_tx,
+                                            &db,
+                                            &shared,
+                                            mode,
+                                        )
+                                        .await;
+                                    }
                                 } else if let Some(node_id) =
                                     extract_node_status_id(&topic)
                                 {
@@ -546,6 +1437,76 @@ async fn main() -> Result<()> {
                                         node_id, &payload, &shared,
                                     )
                                     .await;
+                                } else if let Some(zone_id) =
+                                    extract_advice_zone_id(&topic)
+                                {
+                                    handle_advice(
+                                        zone_id,
+                                        &payload,
+                                        &zone_configs,
+                                        &shared,
+                                    )
+                                    .await;
+                                } else if let Some(zone_id) =
+                                    extract_flow_zone_id(&topic)
+                                {
+                                    handle_flow(
+                                        zone_id,
+                                        &payload,
+                                        &zone_configs,
+                                        &db,
+                                        &shared,
+                                    )
+                                    .await;
+                                } else if let Some(source_id) =
+                                    extract_temp_source_id(&topic)
+                                {
+                                    handle_temperature(
+                                        source_id,
+                                        &payload,
+                                        &zone_configs,
+                                        &cfg.et,
+                                        &db,
+                                        &shared,
+                                    )
+                                    .await;
+                                } else if let Some(source_id) =
+                                    extract_pressure_source_id(&topic)
+                                {
+                                    handle_pressure(
+                                        source_id,
+                                        &payload,
+                                        &valves,
+                                        &valve_opened_at,
+                                        &db,
+                                        &shared,
+                                    )
+                                    .await;
+                                } else if let Some(source_id) =
+                                    extract_weather_source_id(&topic)
+                                {
+                                    handle_weather(
+                                        source_id,
+                                        &payload,
+                                        &zone_configs,
+                                        &cfg.et,
+                                        &db,
+                                        &shared,
+                                    )
+                                    .await;
+                                } else if let Some(node_id) =
+                                    extract_node_logs_id(&topic)
+                                {
+                                    handle_node_logs(node_id, &payload, &shared).await;
+                                } else if let Some(node_id) =
+                                    extract_node_pong_id(&topic)
+                                {
+                                    handle_node_pong(node_id, &payload, &shared).await;
+                                } else if let Some(node_id) =
+                                    extract_ota_report_node_id(&topic)
+                                {
+                                    handle_ota_report(node_id, &payload, &shared, &ota_tx)
+                                        .await;
                                 } else {
                                     warn!(topic = %topic, "unhandled topic");
                                 }
@@ -557,50 +1518,40 @@ async fn main() -> Result<()> {
                                 // Re-subscribe on every (re)connect — broker
                                 // may have lost our session even with
                                 // clean_session(false).
-                                if let Err(e) = client
-                                    .subscribe(
-                                        "tele/+/reading",
-                                        QoS::AtLeastOnce,
-                                    )
-                                    .await
-                                {
-                                    error!(
-                                        "re-subscribe tele/+/reading failed: {e}"
-                                    );
+                                for filter in mqtt::SUBSCRIPTIONS {
+                                    if let Err(e) = client
+                                        .subscribe(
+                                            mqtt::topic(filter),
+                                            QoS::AtLeastOnce,
+                                        )
+                                        .await
+                                    {
+                                        error!("re-subscribe {filter} failed: {e}");
+                                    }
                                 }
-                                if let Err(e) = client
-                                    .subscribe(
-                                        "valve/+/set",
-                                        QoS::AtLeastOnce,
-                                    )
-                                    .await
-                                {
-                                    error!(
-                                        "re-subscribe valve/+/set failed: {e}"
-                                    );
-                                }
-                                if let Err(e) = client
-                                    .subscribe(
-                                        "status/node/+",
-                                        QoS::AtLeastOnce,
-                                    )
-                                    .await
-                                {
-                                    error!(
-                                        "re-subscribe status/node/+ failed: {e}"
-                                    );
+                                for adapter in &mqtt_adapters {
+                                    if let Err(e) = client
+                                        .subscribe(&adapter.topic, QoS::AtLeastOnce)
+                                        .await
+                                    {
+                                        error!("re-subscribe {} failed: {e}", adapter.topic);
+                                    }
                                 }
 
-                                // Announce online status (retained)
+                                // Announce online status and build (retained)
                                 let _ = client
                                     .publish(
-                                        "status/hub",
+                                        mqtt::topic("status/hub"),
                                         QoS::AtLeastOnce,
                                         true,
-                                        b"online".to_vec(),
+                                        mqtt::online_payload(&state::Firmware::hub()),
                                     )
                                     .await;
 
+                                // Retained, but the broker may have restarted
+                                // without persistence.
+                                node_settings.notify_one();
+
                                 let mut st = shared.write().await;
                                 st.mqtt_connected = true;
                                 st.record_system("mqtt connected".to_string());
@@ -655,6 +1606,7 @@ async fn main() -> Result<()> {
                             emergency_all_off(
                                 &valves,
                                 &valve_opened_at,
+                                &db,
                                 &shared,
                                 &format!(
                                     "mqtt error: {e} ({MQTT_GRACE_PERIOD_SEC}s grace period expired, \
@@ -691,14 +1643,93 @@ async fn main() -> Result<()> {
             // ── Critical task monitoring ──────────────────────────
             result = &mut watchdog_handle => {
                 error!("CRITICAL: valve watchdog task exited unexpectedly: {result:?}");
-                exit_reason = "watchdog task died";
-                break;
+                match supervisor.on_failure("watchdog", std::time::Instant::now()) {
+                    Decision::Restart { attempt, backoff } => {
+                        restart_critical_task(
+                            "watchdog", attempt, backoff, &valves, &valve_opened_at, &db, &shared,
+                        )
+                        .await;
+                        watchdog_handle = spawn_watchdog(backoff, &zone_configs);
+                    }
+                    Decision::GiveUp { failures } => {
+                        error!(failures, "valve watchdog keeps failing — giving up");
+                        exit_reason = "watchdog task died";
+                        break;
+                    }
+                }
             }
 
             result = &mut scheduler_handle => {
                 error!("CRITICAL: scheduler task exited unexpectedly: {result:?}");
-                exit_reason = "scheduler task died";
-                break;
+                match supervisor.on_failure("scheduler", std::time::Instant::now()) {
+                    Decision::Restart { attempt, backoff } => {
+                        restart_critical_task(
+                            "scheduler", attempt, backoff, &valves, &valve_opened_at, &db, &shared,
+                        )
+                        .await;
+                        scheduler_handle = spawn_scheduler(backoff, &zone_configs);
+                    }
+                    Decision::GiveUp { failures } => {
+                        error!(failures, "scheduler keeps failing — giving up");
+                        exit_reason = "scheduler task died";
+                        break;
+                    }
+                }
+            }
+
+            Some((node_id, payload)) = http_telemetry_rx.recv() => {
+                handle_telemetry(
+                    &node_id,
+                    &payload,
+                    PayloadEncoding::Json,
+                    &sensor_map,
+                    &zone_configs,
+                    &cfg.et,
+                    &db,
+                    &shared,
+                )
+                .await;
+            }
+            Some(req) = restore_rx.recv() => {
+                // Runs inline, so MQTT traffic (and with it telemetry
+                // writes) waits until the restore is done.
+                scheduler_handle.abort();
+                watchdog_handle.abort();
+                let result = restore_database(
+                    &req.path,
+                    db::db_file_path(&db_url).as_deref(),
+                    &cfg,
+                    &zone_to_gpio,
+                    &motorized,
+                    &valves,
+                    &valve_opened_at,
+                    &db,
+                    &shared,
+                )
+                .await;
+                let reply = match result {
+                    Ok((zones, sensors, outcome)) => {
+                        zone_configs = zones;
+                        sensor_map = sensors;
+                        shared.write().await.limits.zones =
+                            limits::zone_limits(&zone_configs, &flush_plan, |z| {
+                                watchdog_limit_sec(z, &zone_configs, &flush_plan, false)
+                            });
+                        Ok(outcome)
+                    }
+                    Err(e) => {
+                        error!("database restore failed: {e:#}");
+                        shared.write().await.record_error(format!(
+                            "database restore from {} failed: {e:#}",
+                            req.path.display()
+                        ));
+                        Err(format!("{e:#}"))
+                    }
+                };
+                watchdog_handle = spawn_watchdog(Duration::ZERO, &zone_configs);
+                scheduler_handle = spawn_scheduler(Duration::ZERO, &zone_configs);
+                node_settings.notify_one();
+                let _ = req.reply.send(reply);
             }
 
             result = &mut web_handle => {
@@ -712,6 +1743,30 @@ async fn main() -> Result<()> {
                 // Not safety-critical; log and continue.
             }
 
+            result = &mut estop_handle => {
+                // Without it a press would go unnoticed.
+                error!("CRITICAL: emergency stop task exited unexpectedly: {result:?}");
+                exit_reason = "emergency stop task died";
+                break;
+            }
+
+            result = &mut motor_handle => {
+                // Motors would keep running against their end stops.
+                error!("CRITICAL: motorized valve task exited unexpectedly: {result:?}");
+                exit_reason = "motor task died";
+                break;
+            }
+
+            result = &mut flush_handle => {
+                error!("reading flush task exited unexpectedly: {result:?}");
+                // Not safety-critical; a full buffer still triggers a write.
+            }
+
+            result = &mut events_handle => {
+                error!("event saver task exited unexpectedly: {result:?}");
+                // Not safety-critical; log and continue.
+            }
+
             result = &mut backup_handle => {
                 error!("database backup task exited unexpectedly: {result:?}");
                 // Not safety-critical; log and continue.
@@ -722,11 +1777,41 @@ async fn main() -> Result<()> {
                 // Not safety-critical; log and continue.
             }
 
+            result = &mut db_monitor_handle => {
+                error!("database monitor exited unexpectedly: {result:?}");
+                // Not safety-critical; degraded mode simply won't auto-recover.
+            }
+
             result = &mut metrics_handle => {
                 error!("system metrics collector exited unexpectedly: {result:?}");
                 // Not safety-critical; log and continue.
             }
 
+            result = &mut flow_handle => {
+                error!("flow health monitor exited unexpectedly: {result:?}");
+                // Not safety-critical; log and continue.
+            }
+
+            result = &mut node_settings_handle => {
+                error!("node settings publisher exited unexpectedly: {result:?}");
+                // Not safety-critical; nodes keep their last settings.
+            }
+
+            result = &mut hub_heartbeat_handle => {
+                error!("hub heartbeat publisher exited unexpectedly: {result:?}");
+                // Not safety-critical; the retained status/hub still works.
+            }
+
+            result = &mut node_command_handle => {
+                error!("node command publisher exited unexpectedly: {result:?}");
+                // Not safety-critical; only remote diagnostics are lost.
+            }
+
+            result = &mut session_cancel_handle => {
+                error!("session cancel publisher exited unexpectedly: {result:?}");
+                // Not safety-critical; sessions still end on their own.
+            }
+
             _ = &mut ctrl_c => {
                 exit_reason = "SIGINT";
                 break;
@@ -747,26 +1832,58 @@ async fn main() -> Result<()> {
     emergency_all_off(
         &valves,
         &valve_opened_at,
+        &db,
         &shared,
         &format!("shutdown: {exit_reason}"),
     )
     .await;
 
+    // Give motorized valves their travel time to close.
+    let travel = valves.lock().await.motor_travel_remaining();
+    if !travel.is_zero() {
+        info!(
+            wait_ms = travel.as_millis() as u64,
+            "waiting for motorized valves to close"
+        );
+        tokio::time::sleep(travel).await;
+        valves.lock().await.service_motors();
+    }
+
+    // Write any readings still queued.
+    match db.flush_readings().await {
+        Ok(n) if n > 0 => info!(rows = n, "queued readings written"),
+        Ok(_) => {}
+        Err(e) => error!("final reading flush failed: {e:#}"),
+    }
+
+    if let Some(path) = &events_path {
+        if let Err(e) = save_events(&shared, path).await {
+            warn!("saving events failed: {e:#}");
+        }
+    }
+
     // Final database backup before exit.
     if let Some(ref dest) = db_backup_path {
         info!("performing final database backup");
-        match db.backup(dest).await {
-            Ok(()) => info!(path = %dest, "final database backup complete"),
+        match backup_schedule.lock().await.run(&db, dest).await {
+            Ok(kind) => info!(path = %dest, kind = kind.as_str(), "final database backup complete"),
             Err(e) => error!("final database backup failed: {e:#}"),
         }
     }
 
     // Best-effort offline announcement before exit.
     let _ = client
-        .publish("status/hub", QoS::AtLeastOnce, true, b"offline".to_vec())
+        .publish(
+            mqtt::topic("status/hub"),
+            QoS::AtLeastOnce,
+            true,
+            b"offline".to_vec(),
+        )
         .await;
 
     info!("shutdown complete");
+    // Flush buffered trace spans; the exporter blocks on HTTP.
+    let _ = tokio::task::spawn_blocking(move || otel_guard.shutdown()).await;
     Ok(())
 }
 
@@ -774,64 +1891,62 @@ async fn main() -> Result<()> {
 // Telemetry handling (with sensor failure detection)
 // ---------------------------------------------------------------------------
 
-/// Maximum MQTT telemetry payload size (4 KiB). Anything larger is likely
-/// malicious or a bug — a normal reading message is a few hundred bytes.
-const MAX_TELEMETRY_PAYLOAD_BYTES: usize = 4096;
-
-/// Maximum number of sensor readings in a single telemetry message.
-const MAX_READINGS_PER_MESSAGE: usize = 32;
-
+#[allow(clippy::too_many_arguments)]
+#[instrument(name = "mqtt.telemetry", skip_all, fields(node = %node_id))]
 async fn handle_telemetry(
     node_id: &str,
     payload: &[u8],
+    encoding: PayloadEncoding,
     sensor_map: &HashMap<String, SensorConfig>,
+    zone_configs: &HashMap<String, ZoneConfig>,
+    et_cfg: &et::EtConfig,
     db: &Db,
     shared: &RwLock<SystemState>,
 ) {
-    if payload.len() > MAX_TELEMETRY_PAYLOAD_BYTES {
-        warn!(
-            node = %node_id,
-            bytes = payload.len(),
-            "telemetry payload too large — dropping"
-        );
-        let mut st = shared.write().await;
-        st.record_error(format!(
-            "telemetry from {node_id} dropped: {} bytes exceeds {} limit",
-            payload.len(),
-            MAX_TELEMETRY_PAYLOAD_BYTES
-        ));
-        return;
-    }
-
-    let msg: ReadingMsg = match serde_json::from_slice(payload) {
+    let mut msg = match parse_telemetry_as(encoding, payload) {
         Ok(m) => m,
-        Err(e) => {
-            warn!(node = %node_id, "bad telemetry json: {e}");
-            let mut st = shared.write().await;
-            st.record_error(format!("bad telemetry json from {node_id}: {e}"));
+        Err(reject) => {
+            warn!(node = %node_id, "telemetry rejected: {reject}");
+            shared.write().await.record_reject(node_id, &reject);
             return;
         }
     };
-
-    if msg.readings.len() > MAX_READINGS_PER_MESSAGE {
-        warn!(
-            node = %node_id,
-            count = msg.readings.len(),
-            "too many readings in message — dropping"
-        );
-        let mut st = shared.write().await;
-        st.record_error(format!(
-            "telemetry from {node_id} dropped: {} readings exceeds {} limit",
-            msg.readings.len(),
-            MAX_READINGS_PER_MESSAGE
-        ));
-        return;
+    match mqtt::check_telemetry_clock(&mut msg, clock::real_now_unix()) {
+        Ok(0) => {}
+        Ok(dropped) => {
+            warn!(node = %node_id, dropped, "telemetry readings outside the clock window dropped");
+            shared.write().await.record_error(format!(
+                "{node_id}: {dropped} reading(s) stamped outside the hub's clock window dropped"
+            ));
+        }
+        Err(reject) => {
+            warn!(node = %node_id, "telemetry rejected: {reject}");
+            shared.write().await.record_reject(node_id, &reject);
+            return;
+        }
+    }
+    // Nodes stamp readings with real time; under a simulated clock they
+    // are stamped on arrival so they stay fresh (batched readings keep
+    // their offset from the message).
+    if !clock::is_real() {
+        let shift = now_unix() - msg.ts;
+        msg.ts += shift;
+        for r in &mut msg.readings {
+            if let Some(ts) = r.ts.as_mut() {
+                *ts += shift;
+            }
+        }
+    }
+    if msg.air_temp_c.is_some() || msg.humidity_pct.is_some() || msg.lux.is_some() {
+        record_climate(node_id, &msg, zone_configs, et_cfg, db, shared).await;
     }
 
-    let mut valid_readings: Vec<SensorReading> = Vec::new();
+    let mut valid_readings: Vec<(i64, SensorReading)> = Vec::new();
+    let mut stored: Vec<(String, i64, f32)> = Vec::new();
 
     for r in &msg.readings {
         let qualified_id = format!("{node_id}/{}", r.sensor_id);
+        let ts = r.ts_or(msg.ts);
 
         let Some(sc) = sensor_map.get(&qualified_id) else {
             warn!(sensor = %qualified_id, "unknown sensor — skipping DB write");
@@ -840,47 +1955,170 @@ async fn handle_telemetry(
             continue;
         };
 
+        // A QoS 1 redelivery or a replay from the node's buffer.  Spotted
+        // before anything else, so it can't count as a second failure.
+        if !db.claim_reading(ts, &qualified_id).await {
+            debug!(sensor = %qualified_id, ts, "duplicate reading ignored");
+            continue;
+        }
+
         // ── Sensor failure detection ────────────────────────────
-        if !is_reading_plausible(r.raw, sc.raw_dry, sc.raw_wet) {
-            warn!(
-                sensor = %qualified_id,
-                raw = r.raw,
-                raw_dry = sc.raw_dry,
-                raw_wet = sc.raw_wet,
-                "implausible reading — possible sensor failure, skipping"
-            );
+        if !sc.is_plausible(r.raw) {
+            let quarantine_after = shared.read().await.sensor_quarantine_after;
+            let newly_quarantined = match db
+                .record_sensor_failure(&qualified_id, ts, quarantine_after)
+                .await
+            {
+                Ok(q) => q,
+                Err(e) => {
+                    warn!(sensor = %qualified_id, "sensor health write failed: {e:#}");
+                    false
+                }
+            };
             let mut st = shared.write().await;
-            st.record_error(format!(
-                "sensor {qualified_id} implausible raw={} (dry={}, wet={})",
-                r.raw, sc.raw_dry, sc.raw_wet
-            ));
+            st.set_sensor_faulted(&qualified_id, true);
+            if newly_quarantined {
+                warn!(sensor = %qualified_id, "sensor quarantined");
+                st.set_sensor_quarantined(&qualified_id, true);
+                st.record_error(format!(
+                    "sensor {qualified_id} quarantined after {quarantine_after} consecutive \
+                     implausible readings — excluded from zone moisture until released"
+                ));
+            } else if st.is_sensor_quarantined(&qualified_id) {
+                // Already reported; don't flood the event buffer.
+                debug!(sensor = %qualified_id, raw = r.raw, "quarantined sensor still implausible");
+            } else {
+                warn!(
+                    sensor = %qualified_id,
+                    raw = r.raw,
+                    raw_dry = sc.raw_dry,
+                    raw_wet = sc.raw_wet,
+                    margin = sc.failure_margin_counts(),
+                    "implausible reading — possible sensor failure, skipping"
+                );
+                st.record_error(format!(
+                    "sensor {qualified_id} implausible raw={} (dry={}, wet={})",
+                    r.raw, sc.raw_dry, sc.raw_wet
+                ));
+            }
             continue;
         }
+        if shared.read().await.is_sensor_faulted(&qualified_id) {
+            if let Err(e) = db.record_sensor_ok(&qualified_id).await {
+                warn!(sensor = %qualified_id, "sensor health write failed: {e:#}");
+            }
+            let mut st = shared.write().await;
+            if st.set_sensor_faulted(&qualified_id, false) {
+                let note = if st.is_sensor_quarantined(&qualified_id) {
+                    " (still quarantined until released)"
+                } else {
+                    ""
+                };
+                st.record_system(format!("sensor {qualified_id} plausible again{note}"));
+            }
+        }
 
-        let moisture = compute_moisture(r.raw, sc.raw_dry, sc.raw_wet);
-        if let Err(e) = db
-            .insert_reading(msg.ts, &qualified_id, r.raw, moisture)
-            .await
-        {
-            error!(sensor = %qualified_id, "insert_reading failed: {e}");
+        let moisture = sc.moisture(r.raw);
+        match db.queue_reading(ts, &qualified_id, r.raw, moisture).await {
+            Ok(true) => {}
+            Ok(false) => {
+                // Already stored, though too long ago to be remembered.
+                debug!(sensor = %qualified_id, ts, "duplicate reading ignored");
+                continue;
+            }
+            Err(e) => {
+                error!(sensor = %qualified_id, "reading write failed: {e:#}");
+                shared
+                    .write()
+                    .await
+                    .mark_db_degraded("reading write failed");
+            }
         }
 
-        valid_readings.push(SensorReading {
+        stored.push((qualified_id, ts, moisture));
+        let reading = SensorReading {
             sensor_id: r.sensor_id.clone(),
             raw: r.raw,
-        });
+            mv: r.mv,
+        };
+        // A batch can carry several readings per sensor; the node's
+        // status shows the newest.
+        match valid_readings
+            .iter_mut()
+            .find(|(_, v)| v.sensor_id == r.sensor_id)
+        {
+            Some(slot) if slot.0 > ts => {}
+            Some(slot) => *slot = (ts, reading),
+            None => valid_readings.push((ts, reading)),
+        }
     }
 
     if !valid_readings.is_empty() {
         info!(
             node = %node_id,
             ts = msg.ts,
-            count = valid_readings.len(),
+            count = stored.len(),
             "telemetry received"
         );
         let mut st = shared.write().await;
-        st.record_reading(node_id, valid_readings);
+        st.record_reading(
+            node_id,
+            valid_readings.into_iter().map(|(_, r)| r).collect(),
+        );
+        for (sensor_id, ts, moisture) in &stored {
+            st.moisture.push(sensor_id, *ts, *moisture);
+        }
+    }
+}
+
+/// Store a node's air temperature, humidity and light, count the first two
+/// towards the day's ET0 and hand the light to the peak-sun deferral.  A
+/// sample replayed from the node's buffer is only counted once.
+async fn record_climate(
+    node_id: &str,
+    msg: &ReadingMsg,
+    zone_configs: &HashMap<String, ZoneConfig>,
+    et_cfg: &et::EtConfig,
+    db: &Db,
+    shared: &RwLock<SystemState>,
+) {
+    match db
+        .insert_climate_reading(msg.ts, node_id, msg.air_temp_c, msg.humidity_pct, msg.lux)
+        .await
+    {
+        Ok(true) => {}
+        Ok(false) => {
+            debug!(node = %node_id, ts = msg.ts, "duplicate climate reading ignored");
+            return;
+        }
+        Err(e) => {
+            error!(node = %node_id, "climate write failed: {e:#}");
+            shared
+                .write()
+                .await
+                .mark_db_degraded("climate write failed");
+        }
+    }
+    debug!(
+        node = %node_id,
+        air_temp_c = ?msg.air_temp_c,
+        humidity_pct = ?msg.humidity_pct,
+        lux = ?msg.lux,
+        "climate sample"
+    );
+    if let Some(lux) = msg.lux {
+        shared.write().await.sunlight.record(node_id, lux, msg.ts);
+    }
+    if msg.air_temp_c.is_none() && msg.humidity_pct.is_none() {
+        return;
     }
+    let obs = et::Observation {
+        ts: msg.ts,
+        temp_c: msg.air_temp_c,
+        humidity_pct: msg.humidity_pct,
+        ..Default::default()
+    };
+    record_weather(&obs, zone_configs, et_cfg, db, shared).await;
 }
 
 // ---------------------------------------------------------------------------
@@ -888,17 +2126,20 @@ async fn handle_telemetry(
 // ---------------------------------------------------------------------------
 
 #[allow(clippy::too_many_arguments)]
+#[instrument(name = "mqtt.valve_command", skip_all, fields(zone = %zone_id))]
 async fn handle_valve_command(
     zone_id: &str,
     payload: &[u8],
     zone_configs: &HashMap<String, ZoneConfig>,
     valves: &Mutex<ValveBoard>,
-    valve_opened_at: &Mutex<HashMap<String, Instant>>,
+    valve_opened_at: &Arc<Mutex<HashMap<String, Instant>>>,
+    timed_close: &tokio::sync::mpsc::Sender<String>,
     db: &Db,
     shared: &RwLock<SystemState>,
-    max_concurrent_valves: usize,
     mode: OperationMode,
 ) {
+    let received = std::time::Instant::now();
+
     if mode == OperationMode::Monitor {
         warn!(zone = %zone_id, "valve command ignored — system is in monitor mode");
         let mut st = shared.write().await;
@@ -908,38 +2149,58 @@ async fn handle_valve_command(
         return;
     }
 
-    let on = match parse_valve_command(payload) {
-        Ok(v) => v,
-        Err(msg) => {
-            warn!(zone = %zone_id, "{msg} (expected ON/OFF)");
-            let mut st = shared.write().await;
-            st.record_error(msg);
-            return;
-        }
+    let command =
+        match parse_valve_command(payload).and_then(|c| c.check_fresh(now_unix()).map(|()| c)) {
+            Ok(c) => c,
+            Err(reject) => {
+                warn!(zone = %zone_id, "valve command rejected: {reject}");
+                shared.write().await.record_reject(zone_id, &reject);
+                return;
+            }
+        };
+    let on = command.on;
+
+    // Latency clock: from the scheduler's publish when it issued this
+    // command, otherwise from receipt here.
+    let (source, started, planned) = {
+        let mut st = shared.write().await;
+        let (source, started) = st.metrics.take_command_origin(zone_id, on, received);
+        let planned = if on {
+            let planned = st.sessions.unplan(zone_id);
+            match command.duration_sec {
+                Some(secs) => Some(Planned {
+                    planned_sec: Some(secs),
+                    ..planned.unwrap_or_else(Planned::unplanned)
+                }),
+                None => planned,
+            }
+        } else {
+            None
+        };
+        (source, started, planned)
     };
 
     if on {
-        // ── Concurrent valve limit ──────────────────────────────
-        {
-            let st = shared.read().await;
-            let zone_already_on = st.zones.get(zone_id).is_some_and(|z| z.on);
-            if !zone_already_on {
-                let active = st.zones.values().filter(|z| z.on).count();
-                if active >= max_concurrent_valves {
-                    drop(st);
-                    warn!(
-                        zone = %zone_id,
-                        active,
-                        limit = max_concurrent_valves,
-                        "concurrent valve limit reached — ignoring ON"
-                    );
-                    let mut st = shared.write().await;
-                    st.record_error(format!(
-                        "zone {zone_id}: ON blocked — {active}/{max_concurrent_valves} valves already open"
-                    ));
-                    return;
-                }
-            }
+        // ── Emergency stop, frost and low-pressure lockouts ─────
+        let lockout = shared.read().await.valve_lockout();
+        if let Some(reason) = lockout {
+            warn!(zone = %zone_id, "valve ON refused — {reason}");
+            shared
+                .write()
+                .await
+                .record_error(format!("valve ON refused for {zone_id} — {reason}"));
+            return;
+        }
+
+        // ── Concurrent valve limit and interlocks ───────────────
+        let conflict = shared.read().await.open_valve_conflict(zone_id);
+        if let Some(conflict) = conflict {
+            warn!(zone = %zone_id, "valve ON refused — {conflict}");
+            shared
+                .write()
+                .await
+                .record_error(format!("zone {zone_id}: ON blocked — {conflict}"));
+            return;
         }
 
         // ── Safety limit enforcement ────────────────────────────
@@ -947,89 +2208,250 @@ async fn handle_valve_command(
         let mut blocked = false;
 
         if let Some(zone_cfg) = zone_configs.get(zone_id) {
-            match db.get_daily_counters(&today, zone_id).await {
-                Ok(counters) => {
-                    if counters.pulses >= zone_cfg.max_pulses_per_day {
+            // Limits come from the DB counters normally.  In degraded mode
+            // (or if the read fails now) fall back to the last DB counters
+            // plus those kept in memory since, against reduced caps.
+            let mut limits = None;
+            if !shared.read().await.is_db_degraded() {
+                match db.get_daily_counters(&today, zone_id).await {
+                    Ok(c) => {
+                        shared
+                            .write()
+                            .await
+                            .cache_db_counters(&today, zone_id, c.pulses, c.open_sec);
+                        limits = Some((
+                            c.pulses,
+                            c.open_sec,
+                            zone_cfg.max_pulses_per_day,
+                            zone_cfg.max_open_sec_per_day,
+                        ));
+                    }
+                    Err(e) => {
+                        error!(
+                            zone = %zone_id,
+                            "failed to check daily counters: {e} — using degraded-mode limits"
+                        );
+                        shared
+                            .write()
+                            .await
+                            .mark_db_degraded("daily counter read failed");
+                    }
+                }
+            }
+            let counters = match limits {
+                Some(l) => Some(l),
+                None => shared
+                    .read()
+                    .await
+                    .degraded_counters(&today, zone_id)
+                    .map(|c| {
+                        (
+                            c.pulses,
+                            c.open_sec,
+                            degraded_limit(zone_cfg.max_pulses_per_day),
+                            degraded_limit(zone_cfg.max_open_sec_per_day),
+                        )
+                    }),
+            };
+            let Some((pulses, open_sec, max_pulses, max_open_sec)) = counters else {
+                // Today's usage was never read from the DB: refuse rather
+                // than guess.
+                warn!(zone = %zone_id, "degraded mode: usage today unknown — ignoring ON");
+                shared.write().await.record_error(format!(
+                    "zone {zone_id}: ON blocked — usage today unknown while the database is degraded"
+                ));
+                return;
+            };
+
+            if pulses >= max_pulses {
+                warn!(
+                    zone = %zone_id,
+                    pulses,
+                    limit = max_pulses,
+                    "safety limit: max pulses/day reached — ignoring ON"
+                );
+                let mut st = shared.write().await;
+                st.record_error(format!(
+                    "zone {zone_id}: ON blocked — {pulses}/{max_pulses} pulses today"
+                ));
+                blocked = true;
+            }
+            if !blocked && open_sec >= max_open_sec {
+                warn!(
+                    zone = %zone_id,
+                    open_sec,
+                    limit = max_open_sec,
+                    "safety limit: max open sec/day reached — ignoring ON"
+                );
+                let mut st = shared.write().await;
+                st.record_error(format!(
+                    "zone {zone_id}: ON blocked — {open_sec}s/{max_open_sec}s open today"
+                ));
+                blocked = true;
+            }
+            // The same caps over the last 24 hours, so pulses either side of
+            // midnight can't double the allowance.
+            if !blocked {
+                match scheduler::rolling_usage(db, shared, zone_id).await {
+                    Ok(r) if r.pulses >= max_pulses || r.open_sec >= max_open_sec => {
                         warn!(
                             zone = %zone_id,
-                            pulses = counters.pulses,
-                            limit = zone_cfg.max_pulses_per_day,
-                            "safety limit: max pulses/day reached — ignoring ON"
+                            pulses = r.pulses,
+                            open_sec = r.open_sec,
+                            "safety limit: 24h limit reached — ignoring ON"
                         );
                         let mut st = shared.write().await;
                         st.record_error(format!(
-                            "zone {zone_id}: ON blocked — {}/{} pulses today",
-                            counters.pulses, zone_cfg.max_pulses_per_day
+                            "zone {zone_id}: ON blocked — {}/{max_pulses} pulses, {}s/{max_open_sec}s open in the last 24h",
+                            r.pulses, r.open_sec
                         ));
                         blocked = true;
                     }
-                    if !blocked && counters.open_sec >= zone_cfg.max_open_sec_per_day {
-                        warn!(
+                    Ok(_) => {}
+                    Err(e) => {
+                        // Same fallback as an unreadable daily counter: the
+                        // degraded-mode caps on what is counted in memory.
+                        error!(
                             zone = %zone_id,
-                            open_sec = counters.open_sec,
-                            limit = zone_cfg.max_open_sec_per_day,
-                            "safety limit: max open sec/day reached — ignoring ON"
+                            "failed to check 24h counters: {e} — using degraded-mode limits"
                         );
                         let mut st = shared.write().await;
-                        st.record_error(format!(
-                            "zone {zone_id}: ON blocked — {}s/{}s open today",
-                            counters.open_sec, zone_cfg.max_open_sec_per_day
-                        ));
-                        blocked = true;
+                        st.mark_db_degraded("24h counter read failed");
+                        let max_pulses = degraded_limit(zone_cfg.max_pulses_per_day);
+                        let max_open_sec = degraded_limit(zone_cfg.max_open_sec_per_day);
+                        match st.degraded_counters(&today, zone_id) {
+                            None => {
+                                warn!(zone = %zone_id, "degraded mode: usage today unknown — ignoring ON");
+                                st.record_error(format!(
+                                    "zone {zone_id}: ON blocked — usage today unknown while the database is degraded"
+                                ));
+                                blocked = true;
+                            }
+                            Some(c) if c.pulses >= max_pulses || c.open_sec >= max_open_sec => {
+                                warn!(
+                                    zone = %zone_id,
+                                    pulses = c.pulses,
+                                    open_sec = c.open_sec,
+                                    "safety limit: degraded-mode limit reached — ignoring ON"
+                                );
+                                st.record_error(format!(
+                                    "zone {zone_id}: ON blocked — {}/{max_pulses} pulses, {}s/{max_open_sec}s open today",
+                                    c.pulses, c.open_sec
+                                ));
+                                blocked = true;
+                            }
+                            Some(_) => {}
+                        }
                     }
                 }
-                Err(e) => {
-                    // If we can't check limits, allow the valve ON but log loudly.
-                    error!(
-                        zone = %zone_id,
-                        "failed to check daily counters: {e} — allowing valve ON"
-                    );
-                }
             }
         }
 
         if !blocked {
             // Acquire both locks before opening to ensure the watchdog
             // sees the open timestamp atomically with the GPIO state change.
-            let mut board = valves.lock().await;
+            let mut board = lock_staggered(valves).await;
+            // The checks above awaited the DB: an emergency stop pressed or
+            // a lockout engaged meanwhile must still win.
+            let lockout = shared.read().await.valve_lockout();
+            if let Some(reason) = lockout {
+                drop(board);
+                warn!(zone = %zone_id, "valve ON refused — {reason}");
+                shared
+                    .write()
+                    .await
+                    .record_error(format!("valve ON refused for {zone_id} — {reason}"));
+                return;
+            }
             let mut opened = valve_opened_at.lock().await;
             board.set(zone_id, true);
-            opened.insert(zone_id.to_string(), Instant::now());
+            let actuated = started.elapsed();
+            let opened_at = clock::now();
+            opened.insert(zone_id.to_string(), opened_at);
             drop(opened);
             drop(board);
 
-            // Track daily pulse count.
-            let today = Db::today_yyyy_mm_dd();
-            if let Err(e) = db.add_pulse(&today, zone_id, 1).await {
-                error!(zone = %zone_id, "add_pulse failed: {e}");
+            if let Some(secs) = command.duration_sec {
+                schedule_timed_close(zone_id, secs, opened_at, valve_opened_at, timed_close);
             }
 
+            let plan = planned.unwrap_or_else(Planned::unplanned);
+            let triggered_by = sessions::triggered_by(plan.reason);
+            if let Err(e) = db
+                .mark_valve_open(zone_id, now_unix(), triggered_by, plan.planned_sec)
+                .await
+            {
+                error!(zone = %zone_id, "mark_valve_open failed: {e}");
+            }
+
+            // Track daily pulse count.
+            let today = Db::today_yyyy_mm_dd();
+            count_pulse(db, shared, &today, zone_id).await;
+            check_valve_service(db, shared, zone_id).await;
+
             let mut st = shared.write().await;
+            st.sessions
+                .start(zone_id, planned, OffsetDateTime::now_utc());
             st.record_valve(zone_id, true);
+            record_valve_latency(&mut st, source, started, actuated);
         }
     } else {
         // ── Valve OFF ───────────────────────────────────────────
-        valves.lock().await.set(zone_id, false);
+        lock_staggered(valves).await.set(zone_id, false);
+        let actuated = started.elapsed();
+        if let Err(e) = db.mark_valve_closed(zone_id).await {
+            error!(zone = %zone_id, "mark_valve_closed failed: {e}");
+        }
 
         // Record open duration if we were tracking this valve.
         let mut opened = valve_opened_at.lock().await;
         if let Some(opened_time) = opened.remove(zone_id) {
-            let duration_secs = opened_time.elapsed().as_secs() as i64;
+            let duration_secs = clock::since(opened_time).as_secs() as i64;
             drop(opened); // release lock before DB calls
 
             let today = Db::today_yyyy_mm_dd();
-            if let Err(e) = db.add_open_seconds(&today, zone_id, duration_secs).await {
-                error!(zone = %zone_id, "add_open_seconds failed: {e}");
+            count_open_seconds(db, shared, &today, zone_id, duration_secs).await;
+            check_valve_service(db, shared, zone_id).await;
+            if let Some(cfg) = zone_configs.get(zone_id) {
+                debit_et_deficit(db, shared, cfg, duration_secs).await;
             }
 
-            // Record watering event.
+            // Record watering event (dropped in degraded mode — only the
+            // safety counters are kept in memory).
             let now_ts = now_unix();
             let start_ts = now_ts - duration_secs;
-            if let Err(e) = db
-                .insert_watering_event(start_ts, now_ts, zone_id, "mqtt_command", "ok")
-                .await
-            {
-                error!(zone = %zone_id, "insert_watering_event failed: {e}");
+            let session =
+                shared
+                    .write()
+                    .await
+                    .sessions
+                    .finish(zone_id, None, OffsetDateTime::now_utc());
+            let (reason, result, details) = match &session {
+                Some(s) => {
+                    let ended = s.result.unwrap_or(SessionResult::Completed);
+                    (
+                        s.reason,
+                        ended.event_result(),
+                        EventDetails {
+                            planned_sec: s.planned_sec,
+                            triggered_by: Some(sessions::triggered_by(s.reason)),
+                            ended_by: Some(ended.ended_by()),
+                        },
+                    )
+                }
+                None => (sessions::UNPLANNED_REASON, "ok", EventDetails::default()),
+            };
+            if !shared.read().await.is_db_degraded() {
+                if let Err(e) = db
+                    .insert_watering_event(start_ts, now_ts, zone_id, reason, result, details)
+                    .await
+                {
+                    error!(zone = %zone_id, "insert_watering_event failed: {e}");
+                    shared
+                        .write()
+                        .await
+                        .mark_db_degraded("insert_watering_event failed");
+                }
             }
 
             info!(
@@ -1043,6 +2465,85 @@ async fn handle_valve_command(
 
         let mut st = shared.write().await;
         st.record_valve(zone_id, false);
+        record_valve_latency(&mut st, source, started, actuated);
+    }
+}
+
+/// Send `OFF` for `zone_id` after `duration_sec`, unless the valve has
+/// been closed (or re-opened) by then.  The watchdog limit still applies
+/// to a longer duration.
+fn schedule_timed_close(
+    zone_id: &str,
+    duration_sec: i64,
+    opened_at: Instant,
+    valve_opened_at: &Arc<Mutex<HashMap<String, Instant>>>,
+    timed_close: &tokio::sync::mpsc::Sender<String>,
+) {
+    let zone_id = zone_id.to_string();
+    let valve_opened_at = Arc::clone(valve_opened_at);
+    let timed_close = timed_close.clone();
+    tokio::spawn(async move {
+        clock::sleep(Duration::from_secs(duration_sec.unsigned_abs())).await;
+        if valve_opened_at.lock().await.get(&zone_id) != Some(&opened_at) {
+            return;
+        }
+        info!(zone = %zone_id, duration_sec, "closing valve after its command's duration");
+        if timed_close.send(zone_id).await.is_err() {
+            warn!("timed valve close dropped — close task is gone");
+        }
+    });
+}
+
+/// Longest a valve may stay open before the watchdog closes it: the zone's
+/// pulse plus a margin, or its emitter flush if longer while `flushing`.
+fn watchdog_limit_sec(
+    zone_id: &str,
+    zone_configs: &HashMap<String, ZoneConfig>,
+    flush: &flush::FlushPlan,
+    flushing: bool,
+) -> u64 {
+    let pulse_sec = zone_configs.get(zone_id).map_or(60, |z| z.pulse_sec as u64);
+    let flush_sec = if flushing && flush.includes(zone_id) {
+        flush.duration().as_secs()
+    } else {
+        0
+    };
+    pulse_sec.max(flush_sec) + WATCHDOG_MARGIN_SEC
+}
+
+/// Lock the valve board for a command, waiting out the relay stagger first.
+/// The lock is held while waiting, so queued commands switch one at a time
+/// in arrival order rather than being released together.
+async fn lock_staggered(valves: &Mutex<ValveBoard>) -> tokio::sync::MutexGuard<'_, ValveBoard> {
+    let board = valves.lock().await;
+    let wait = board.stagger_wait();
+    if !wait.is_zero() {
+        tokio::time::sleep(wait).await;
+    }
+    board
+}
+
+/// Record actuation and propagation latency for a valve command.  Called
+/// right after `record_valve`, so "propagation" covers everything up to the
+/// state change becoming visible to the API.
+fn record_valve_latency(
+    st: &mut SystemState,
+    source: CommandSource,
+    started: std::time::Instant,
+    actuated: Duration,
+) {
+    let propagated = started.elapsed();
+    st.metrics
+        .observe_valve_latency(source, LatencyStage::Actuation, actuated);
+    st.metrics
+        .observe_valve_latency(source, LatencyStage::Propagation, propagated);
+    if propagated > Duration::from_secs(1) {
+        warn!(
+            source = ?source,
+            actuation_ms = actuated.as_millis() as u64,
+            propagation_ms = propagated.as_millis() as u64,
+            "slow valve command"
+        );
     }
 }
 
@@ -1051,24 +2552,530 @@ async fn handle_valve_command(
 // ---------------------------------------------------------------------------
 
 async fn handle_node_status(node_id: &str, payload: &[u8], shared: &RwLock<SystemState>) {
-    let status = String::from_utf8_lossy(payload).trim().to_lowercase();
-    let online = match status.as_str() {
-        "online" => true,
-        "offline" => false,
-        _ => {
-            warn!(node = %node_id, status = %status, "unknown node status payload");
+    let status = match parse_node_status(payload) {
+        Ok(status) => status,
+        Err(reject) => {
+            warn!(node = %node_id, "node status rejected: {reject}");
+            shared.write().await.record_reject(node_id, &reject);
             return;
         }
     };
 
-    if online {
-        info!(node = %node_id, "node online");
+    if status.online {
+        match &status.firmware {
+            Some(fw) => info!(node = %node_id, version = %fw.version, git = %fw.git, "node online"),
+            None => info!(node = %node_id, "node online"),
+        }
     } else {
         warn!(node = %node_id, "node offline (LWT or graceful disconnect)");
     }
 
     let mut st = shared.write().await;
-    st.record_node_status(node_id, online);
+    st.record_node_status(node_id, status.online);
+    if status.online {
+        st.record_node_firmware(node_id, status.firmware);
+    }
+}
+
+/// Handle an external advisor's recommendation on
+/// `advice/<zone_id>/response`.  Only zones configured with the advisor
+/// strategy accept advice; the scheduler clamps and guards it like any
+/// other pulse decision.
+async fn handle_advice(
+    zone_id: &str,
+    payload: &[u8],
+    zone_configs: &HashMap<String, ZoneConfig>,
+    shared: &RwLock<SystemState>,
+) {
+    let Some(cfg) = zone_configs.get(zone_id) else {
+        warn!(zone = %zone_id, "advice for unknown zone — ignoring");
+        return;
+    };
+    if !matches!(cfg.strategy, StrategyConfig::Advisor { .. }) {
+        warn!(zone = %zone_id, "advice for zone not using the advisor strategy — ignoring");
+        return;
+    }
+    let msg = match parse_advice(payload) {
+        Ok(m) => m,
+        Err(reject) => {
+            warn!(zone = %zone_id, "advice rejected: {reject}");
+            shared.write().await.record_reject(zone_id, &reject);
+            return;
+        }
+    };
+
+    info!(zone = %zone_id, pulses = msg.pulses, reason = %msg.reason, "advice received");
+    shared.write().await.record_advice(
+        zone_id,
+        Advice {
+            pulses: msg.pulses,
+            reason: msg.reason,
+            received_at: OffsetDateTime::now_utc(),
+        },
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Outdoor temperature (frost lockout)
+// ---------------------------------------------------------------------------
+
+/// Take an outdoor temperature from `temp/<source_id>/reading` and engage
+/// or release the frost lockout.  It counts towards the day's ET0 too.
+async fn handle_temperature(
+    source_id: &str,
+    payload: &[u8],
+    zone_configs: &HashMap<String, ZoneConfig>,
+    et_cfg: &et::EtConfig,
+    db: &Db,
+    shared: &RwLock<SystemState>,
+) {
+    let msg = match parse_temperature(payload, clock::real_now_unix()) {
+        Ok(m) => m,
+        Err(reject) => {
+            warn!(source = %source_id, "temperature rejected: {reject}");
+            shared.write().await.record_reject(source_id, &reject);
+            return;
+        }
+    };
+    debug!(source = %source_id, temp_c = msg.temp_c, "outdoor temperature");
+    let obs = et::Observation {
+        ts: msg.ts,
+        temp_c: Some(msg.temp_c),
+        ..Default::default()
+    };
+    record_weather(&obs, zone_configs, et_cfg, db, shared).await;
+    let mut st = shared.write().await;
+    let change = st.frost.record(frost::TempReading {
+        source: source_id.to_string(),
+        temp_c: msg.temp_c,
+        ts: msg.ts,
+    });
+    match change {
+        Some(frost::Change::Engaged) => {
+            let reason = st.frost.reason();
+            warn!("{reason} — valve ON commands blocked");
+            st.record_error(format!("{reason} — valve ON commands blocked"));
+        }
+        Some(frost::Change::Released) => {
+            info!(source = %source_id, temp_c = msg.temp_c, "frost lockout released");
+            st.record_system(format!(
+                "frost lockout released: {} °C ({source_id})",
+                msg.temp_c
+            ));
+        }
+        None => {}
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Line pressure
+// ---------------------------------------------------------------------------
+
+/// Take a line pressure from `pressure/<source_id>/reading`: engage or
+/// release the low-pressure lockout, and turn every valve off when
+/// pressure has stayed too high with one open.
+async fn handle_pressure(
+    source_id: &str,
+    payload: &[u8],
+    valves: &Mutex<ValveBoard>,
+    valve_opened_at: &Mutex<HashMap<String, Instant>>,
+    db: &Db,
+    shared: &RwLock<SystemState>,
+) {
+    let msg = match parse_pressure(payload, clock::real_now_unix()) {
+        Ok(m) => m,
+        Err(reject) => {
+            warn!(source = %source_id, "pressure rejected: {reject}");
+            shared.write().await.record_reject(source_id, &reject);
+            return;
+        }
+    };
+    debug!(source = %source_id, kpa = msg.kpa, "line pressure");
+    let mut st = shared.write().await;
+    let valve_open = st.zones.values().any(|z| z.on);
+    let reading = pressure::PressureReading {
+        source: source_id.to_string(),
+        kpa: msg.kpa,
+        ts: msg.ts,
+    };
+    let mut cut_off = None;
+    for change in st.pressure.record(reading, valve_open) {
+        match change {
+            pressure::Change::Low => {
+                let reason = st.pressure.reason();
+                warn!("{reason} — valve ON commands blocked");
+                st.record_error(format!("{reason} — valve ON commands blocked"));
+            }
+            pressure::Change::Restored => {
+                info!(source = %source_id, kpa = msg.kpa, "line pressure restored");
+                st.record_system(format!(
+                    "line pressure restored: {} kPa ({source_id})",
+                    msg.kpa
+                ));
+            }
+            pressure::Change::High { since } => cut_off = Some(msg.ts - since),
+        }
+    }
+    drop(st);
+
+    let Some(secs) = cut_off else {
+        return;
+    };
+    error!(
+        source = %source_id,
+        kpa = msg.kpa,
+        secs,
+        "line pressure high with valves open — all valves off"
+    );
+    valves.lock().await.all_off();
+    valve_opened_at.lock().await.clear();
+    close_out_sessions(db).await;
+    let mut st = shared.write().await;
+    st.set_all_zones_off();
+    st.record_error(format!(
+        "all valves off: line pressure {} kPa ({source_id}) too high for {secs}s with valves open — check for a blocked line",
+        msg.kpa
+    ));
+}
+
+// ---------------------------------------------------------------------------
+// Weather (evapotranspiration)
+// ---------------------------------------------------------------------------
+
+/// Take a weather sample from `weather/<source_id>/reading`.
+async fn handle_weather(
+    source_id: &str,
+    payload: &[u8],
+    zone_configs: &HashMap<String, ZoneConfig>,
+    et_cfg: &et::EtConfig,
+    db: &Db,
+    shared: &RwLock<SystemState>,
+) {
+    let msg = match parse_weather(payload) {
+        Ok(m) => m,
+        Err(reject) => {
+            warn!(source = %source_id, "weather rejected: {reject}");
+            shared.write().await.record_reject(source_id, &reject);
+            return;
+        }
+    };
+    debug!(source = %source_id, ?msg, "weather sample");
+    let obs = et::Observation {
+        ts: msg.ts,
+        temp_c: msg.temp_c,
+        humidity_pct: msg.humidity_pct,
+        solar_w_m2: msg.solar_w_m2,
+        wind_m_s: msg.wind_m_s,
+        rain_mm: msg.rain_mm,
+        et0_mm: msg.et0_mm,
+    };
+    record_weather(&obs, zone_configs, et_cfg, db, shared).await;
+}
+
+/// Add a sample to the day's weather.  When it starts a new day, the
+/// previous day's ET0 is stored and added to every `et` zone's deficit.
+async fn record_weather(
+    obs: &et::Observation,
+    zone_configs: &HashMap<String, ZoneConfig>,
+    et_cfg: &et::EtConfig,
+    db: &Db,
+    shared: &RwLock<SystemState>,
+) {
+    let Some(closed) = shared.write().await.weather.record(obs) else {
+        return;
+    };
+    let Some(day) = closed.et0(et_cfg) else {
+        warn!(date = %closed.date, "no ET0: needs min/max temperature and [et] latitude, or et0_mm");
+        shared.write().await.record_error(format!(
+            "no ET0 for {}: needs min/max temperature and [et] latitude, or et0_mm",
+            closed.date
+        ));
+        return;
+    };
+    info!(
+        date = %day.date,
+        et0_mm = day.et0_mm,
+        rain_mm = day.rain_mm,
+        method = day.method.as_str(),
+        "day closed"
+    );
+    if let Err(e) = db.upsert_et_day(&day).await {
+        error!("upsert_et_day failed: {e:#}");
+    }
+
+    let deficits: Vec<(String, f64)> = {
+        let mut st = shared.write().await;
+        zone_configs
+            .iter()
+            .filter_map(|(zone_id, cfg)| {
+                let kc = cfg.strategy.crop_coefficient()?;
+                let deficit = et::add_day(
+                    st.et_deficits.get(zone_id).copied().unwrap_or(0.0),
+                    &day,
+                    kc,
+                );
+                st.et_deficits.insert(zone_id.clone(), deficit);
+                Some((zone_id.clone(), deficit))
+            })
+            .collect()
+    };
+    for (zone_id, deficit) in deficits {
+        if let Err(e) = db.set_et_deficit(&zone_id, deficit, now_unix()).await {
+            error!(zone = %zone_id, "set_et_deficit failed: {e:#}");
+        }
+    }
+    shared.write().await.record_system(format!(
+        "ET0 for {}: {:.1} mm ({}), rain {:.1} mm",
+        day.date,
+        day.et0_mm,
+        day.method.as_str(),
+        day.rain_mm
+    ));
+}
+
+/// Take the water a valve just applied off an `et` zone's deficit.
+async fn debit_et_deficit(db: &Db, shared: &RwLock<SystemState>, cfg: &ZoneConfig, secs: i64) {
+    let Some(rate) = cfg.strategy.application_rate_mm_hr() else {
+        return;
+    };
+    let deficit = {
+        let mut st = shared.write().await;
+        let deficit = st.et_deficits.entry(cfg.zone_id.clone()).or_insert(0.0);
+        *deficit = (*deficit - et::applied_mm(rate, secs)).max(0.0);
+        *deficit
+    };
+    if let Err(e) = db.set_et_deficit(&cfg.zone_id, deficit, now_unix()).await {
+        error!(zone = %cfg.zone_id, "set_et_deficit failed: {e:#}");
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Node diagnostics
+// ---------------------------------------------------------------------------
+
+/// Keep the log lines a node sent on `diag/<node_id>/logs` for
+/// `GET /api/nodes/{node_id}/logs`.
+async fn handle_node_logs(node_id: &str, payload: &[u8], shared: &RwLock<SystemState>) {
+    let msg = match parse_node_logs(payload) {
+        Ok(m) => m,
+        Err(reject) => {
+            warn!(node = %node_id, "node logs rejected: {reject}");
+            shared.write().await.record_reject(node_id, &reject);
+            return;
+        }
+    };
+    info!(node = %node_id, lines = msg.lines.len(), "node logs received");
+    shared.write().await.node_logs.insert(
+        node_id.to_string(),
+        NodeLogs {
+            received_at: OffsetDateTime::now_utc(),
+            ts: msg.ts,
+            lines: msg.lines,
+        },
+    );
+}
+
+/// Keep a node's answer to `ping` for the waiting `POST
+/// /api/nodes/{node_id}/ping`.
+async fn handle_node_pong(node_id: &str, payload: &[u8], shared: &RwLock<SystemState>) {
+    let msg = match parse_node_pong(payload) {
+        Ok(m) => m,
+        Err(reject) => {
+            warn!(node = %node_id, "node pong rejected: {reject}");
+            shared.write().await.record_reject(node_id, &reject);
+            return;
+        }
+    };
+    let received_at = OffsetDateTime::now_utc();
+    let received_ms = (received_at.unix_timestamp_nanos() / 1_000_000) as i64;
+    let round_trip_ms = msg.sent_ms.map(|sent| (received_ms - sent).max(0));
+    debug!(node = %node_id, ?round_trip_ms, "node pong received");
+    shared.write().await.node_pongs.insert(
+        node_id.to_string(),
+        NodePong {
+            received_at,
+            sent_ms: msg.sent_ms,
+            round_trip_ms,
+            ts: msg.ts,
+            uptime_sec: msg.uptime_sec,
+            sample_interval_sec: msg.sample_interval_sec,
+            last_sample_ts: msg.last_sample_ts,
+            buffered: msg.buffered,
+        },
+    );
+}
+
+/// Track a node's update progress from `ota/<node_id>/status`.  Once it
+/// runs the announced version the retained announcement is cleared, so a
+/// node later rolled back by hand isn't updated again on reconnect.
+async fn handle_ota_report(
+    node_id: &str,
+    payload: &[u8],
+    shared: &RwLock<SystemState>,
+    ota_tx: &tokio::sync::mpsc::Sender<ota::AnnounceRequest>,
+) {
+    let msg = match parse_ota_report(payload) {
+        Ok(m) => m,
+        Err(reject) => {
+            warn!(node = %node_id, "ota report rejected: {reject}");
+            shared.write().await.record_reject(node_id, &reject);
+            return;
+        }
+    };
+    info!(node = %node_id, state = %msg.state, version = %msg.version, "ota report");
+    let mut st = shared.write().await;
+    match msg.state.as_str() {
+        "installed" => st.record_system(format!(
+            "node {node_id} installed {}, restarting",
+            msg.version
+        )),
+        "failed" => st.record_error(format!(
+            "node {node_id} update to {} failed: {}",
+            msg.version,
+            msg.error.as_deref().unwrap_or("unknown error")
+        )),
+        _ => {}
+    }
+    let version = msg.version.clone();
+    let report = ota::OtaReport {
+        state: msg.state,
+        version: msg.version,
+        error: msg.error,
+        ts: msg.ts,
+        received_at: OffsetDateTime::now_utc(),
+    };
+    if ota::record_report(&mut st.node_ota, node_id, report) {
+        st.record_system(format!("node {node_id} is running {version}"));
+        // The event loop is the caller; never block it on the publisher.
+        if ota_tx.try_send((node_id.to_string(), None)).is_err() {
+            warn!(node = %node_id, "could not clear the update announcement");
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Flow meters
+// ---------------------------------------------------------------------------
+
+/// Store a flow-meter sample from `flow/<zone_id>/reading`.
+async fn handle_flow(
+    zone_id: &str,
+    payload: &[u8],
+    zone_configs: &HashMap<String, ZoneConfig>,
+    db: &Db,
+    shared: &RwLock<SystemState>,
+) {
+    if !zone_configs.contains_key(zone_id) {
+        warn!(zone = %zone_id, "flow reading for unknown zone — ignoring");
+        return;
+    }
+    let msg = match parse_flow(payload) {
+        Ok(m) => m,
+        Err(reject) => {
+            warn!(zone = %zone_id, "flow reading rejected: {reject}");
+            shared.write().await.record_reject(zone_id, &reject);
+            return;
+        }
+    };
+    let pressure_kpa = msg.pressure_kpa.filter(|p| p.is_finite() && *p > 0.0);
+    if let Err(e) = db
+        .insert_flow_reading(msg.ts, zone_id, msg.lpm, pressure_kpa)
+        .await
+    {
+        warn!(zone = %zone_id, "failed to store flow reading: {e:#}");
+    }
+}
+
+/// Compare each zone's recent flow with its baseline and raise a
+/// maintenance alert when it has declined at constant pressure.  `alerted`
+/// holds zones already reported, so each alert fires once until the zone
+/// recovers (e.g. after the filter is cleaned).
+async fn check_flow_health(db: &Db, shared: &RwLock<SystemState>, alerted: &mut HashSet<String>) {
+    let zones = match db.load_zones().await {
+        Ok(z) => z,
+        Err(e) => {
+            warn!("flow health: load_zones failed: {e:#}");
+            return;
+        }
+    };
+    let since = OffsetDateTime::now_utc().unix_timestamp() - flow::LOOKBACK_DAYS * 86400;
+
+    for zone in zones {
+        let days = match db.daily_flow(&zone.zone_id, since).await {
+            Ok(d) => d,
+            Err(e) => {
+                warn!(zone = %zone.zone_id, "flow health: query failed: {e:#}");
+                continue;
+            }
+        };
+        let Some(trend) = flow::analyze(days) else {
+            continue;
+        };
+
+        if trend.clog_suspected && !alerted.contains(&zone.zone_id) {
+            warn!(
+                zone = %zone.zone_id,
+                baseline_lpm = trend.baseline_lpm,
+                recent_lpm = trend.recent_lpm,
+                decline_pct = trend.decline_pct,
+                slope_lpm_per_day = trend.slope_lpm_per_day,
+                "flow declining — possible clogged emitters/filter"
+            );
+            shared.write().await.record_error(format!(
+                "zone {}: possible clogged emitters/filter — flow down {:.0}% ({:.2} → {:.2} L/min over {} days, {:+.3} L/min/day)",
+                zone.zone_id,
+                trend.decline_pct,
+                trend.baseline_lpm,
+                trend.recent_lpm,
+                trend.days.len(),
+                trend.slope_lpm_per_day
+            ));
+            alerted.insert(zone.zone_id);
+        } else if !trend.clog_suspected && alerted.remove(&zone.zone_id) {
+            info!(zone = %zone.zone_id, "zone flow back to baseline");
+            shared.write().await.record_system(format!(
+                "zone {}: flow back to baseline ({:.2} L/min)",
+                zone.zone_id, trend.recent_lpm
+            ));
+        }
+    }
+}
+
+/// Publish every node's settings (retained) to `cfg/<node_id>/set`.
+/// Decommissioned nodes get an empty retained message, which clears theirs.
+async fn publish_node_settings(db: &Db, client: &AsyncClient) {
+    let (nodes, sensors) = match tokio::try_join!(db.load_nodes(), db.load_sensors()) {
+        Ok(v) => v,
+        Err(e) => {
+            warn!("node settings: failed to load config: {e:#}");
+            return;
+        }
+    };
+    let nodes: HashMap<&str, &NodeConfig> = nodes.iter().map(|n| (n.node_id.as_str(), n)).collect();
+    let mut node_ids: BTreeSet<&str> = nodes.keys().copied().collect();
+    node_ids.extend(sensors.iter().map(|s| s.node_id.as_str()));
+
+    for node_id in node_ids {
+        let node = nodes.get(node_id).copied();
+        let payload = if node.is_some_and(|n| n.decommissioned_at.is_some()) {
+            Vec::new()
+        } else {
+            let node_sensors: Vec<SensorConfig> = sensors
+                .iter()
+                .filter(|s| s.node_id == node_id)
+                .cloned()
+                .collect();
+            let msg = NodeSettingsMsg::new(node, &node_sensors);
+            serde_json::to_vec(&msg).expect("node settings serialization failed")
+        };
+        let topic = node_settings_topic(node_id);
+        if let Err(e) = client
+            .publish(&topic, QoS::AtLeastOnce, true, payload)
+            .await
+        {
+            warn!(topic = %topic, "node settings publish failed: {e}");
+        }
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -1079,21 +3086,366 @@ async fn handle_node_status(node_id: &str, payload: &[u8], shared: &RwLock<Syste
 async fn emergency_all_off(
     valves: &Mutex<ValveBoard>,
     valve_opened_at: &Mutex<HashMap<String, Instant>>,
+    db: &Db,
     shared: &RwLock<SystemState>,
     reason: &str,
 ) {
     valves.lock().await.all_off();
     valve_opened_at.lock().await.clear();
+    close_out_sessions(db).await;
     let mut st = shared.write().await;
     st.mqtt_connected = false;
     st.set_all_zones_off();
     st.record_error(format!("all valves off: {reason}"));
 }
 
+/// Emergency-stop button pressed: turn every valve off and latch the
+/// lockout (persisted, so a restart keeps it).
+async fn emergency_stop(
+    valves: &Mutex<ValveBoard>,
+    valve_opened_at: &Mutex<HashMap<String, Instant>>,
+    db: &Db,
+    shared: &RwLock<SystemState>,
+) {
+    error!("emergency stop pressed — all valves off, lockout latched");
+    // Latch before turning the valves off: an ON in flight re-checks the
+    // latch once it holds the valve board, so it can't reopen a valve.
+    let since = {
+        let mut st = shared.write().await;
+        let latched = st.estop.latch(now_unix());
+        st.record_error(if latched {
+            "emergency stop pressed — all valves off, ON commands refused until cleared".to_string()
+        } else {
+            "emergency stop pressed again — all valves off".to_string()
+        });
+        st.estop.latched_since
+    };
+    valves.lock().await.all_off();
+    valve_opened_at.lock().await.clear();
+    close_out_sessions(db).await;
+    shared.write().await.set_all_zones_off();
+    if let Err(e) = db.save_estop_latch(since).await {
+        error!("emergency stop latch not saved: {e:#}");
+    }
+}
+
+/// Prepare to respawn a failed critical task: force every valve off (the
+/// dead task may have left one open with nothing watching it) and record
+/// the restart.  The caller respawns the task with `backoff` as its delay.
+async fn restart_critical_task(
+    task: &str,
+    attempt: u32,
+    backoff: Duration,
+    valves: &Mutex<ValveBoard>,
+    valve_opened_at: &Mutex<HashMap<String, Instant>>,
+    db: &Db,
+    shared: &RwLock<SystemState>,
+) {
+    warn!(
+        task,
+        attempt,
+        backoff_ms = backoff.as_millis() as u64,
+        "restarting critical task — turning all valves off"
+    );
+    valves.lock().await.all_off();
+    valve_opened_at.lock().await.clear();
+    close_out_sessions(db).await;
+    let mut st = shared.write().await;
+    st.set_all_zones_off();
+    st.record_task_restart(task, attempt, backoff);
+}
+
+/// Save the dashboard's event ring to `path` (see `state::save_events`).
+async fn save_events(shared: &RwLock<SystemState>, path: &str) -> Result<()> {
+    let events: Vec<state::SystemEvent> = shared.read().await.events.iter().cloned().collect();
+    let path = path.to_string();
+    tokio::task::spawn_blocking(move || state::save_events(&path, &events)).await?
+}
+
+/// Motorized ball valves among `zones`, as the valve board takes them.
+fn motorized_valves(zones: &[ZoneConfig]) -> Result<Vec<MotorSpec>> {
+    let mut motors = Vec::new();
+    for z in zones {
+        if let ValveConfig::Motorized {
+            close_gpio_pin,
+            travel_sec,
+        } = z.valve
+        {
+            let pin: u8 = close_gpio_pin.try_into().with_context(|| {
+                format!(
+                    "zone '{}': valve close_gpio_pin {} out of u8 range",
+                    z.zone_id, close_gpio_pin
+                )
+            })?;
+            motors.push((z.zone_id.clone(), pin, Duration::from_secs(travel_sec)));
+        }
+    }
+    Ok(motors)
+}
+
+/// Restore the database from a backup file (see `restore`) and reload the
+/// zone and sensor config.  The caller has stopped the scheduler and
+/// watchdog and restarts them afterwards.
+#[allow(clippy::too_many_arguments)]
+async fn restore_database(
+    path: &std::path::Path,
+    live_path: Option<&str>,
+    cfg: &config::Config,
+    zone_to_gpio: &[(String, u8)],
+    motorized: &[MotorSpec],
+    valves: &Mutex<ValveBoard>,
+    valve_opened_at: &Mutex<HashMap<String, Instant>>,
+    db: &Db,
+    shared: &RwLock<SystemState>,
+) -> Result<(
+    HashMap<String, ZoneConfig>,
+    HashMap<String, SensorConfig>,
+    restore::RestoreOutcome,
+)> {
+    let backup = path.display().to_string();
+    let same_file = |live: &str| {
+        let canonical = |p: &std::path::Path| std::fs::canonicalize(p).ok();
+        canonical(path).is_some_and(|p| Some(p) == canonical(std::path::Path::new(live)))
+    };
+    anyhow::ensure!(
+        !live_path.is_some_and(same_file),
+        "{backup} is the live database, not a backup"
+    );
+    warn!(backup = %backup, "restoring database from backup — turning all valves off");
+    valves.lock().await.all_off();
+    valve_opened_at.lock().await.clear();
+    close_out_sessions(db).await;
+    shared.write().await.set_all_zones_off();
+
+    let rows = db.restore_from(&backup).await?;
+    // The emergency stop stays as it is now, whatever the backup says.
+    let estop_latch = shared.read().await.estop.latched_since;
+    db.save_estop_latch(estop_latch).await?;
+    // Readings held in memory belong to the replaced database.
+    shared.write().await.moisture = moisture::MoistureWindow::default();
+    // The backup's blackout calendar applies from now on.
+    shared.write().await.blackouts = Blackouts::new(db.list_blackouts().await?);
+    shared.write().await.et_deficits = db.load_et_deficits().await?;
+    // Sessions open when the backup was taken never finished.
+    db.close_open_valves(now_unix(), "backup_restore", "recovered", "restore")
+        .await?;
+    // As at startup, zones and sensors from config.toml win.
+    config::apply(cfg, db).await?;
+    if let Err(e) = db
+        .record_config_version(
+            now_unix(),
+            "restore from backup",
+            AuditSource::Restore,
+            None,
+        )
+        .await
+    {
+        warn!("config snapshot failed: {e:#}");
+    }
+
+    let zones = db.load_zones().await?;
+    let sensors = db.load_sensors().await?;
+    // The valve board only claims pins at startup.
+    let board: HashSet<(&str, i64)> = zone_to_gpio
+        .iter()
+        .map(|(zone_id, pin)| (zone_id.as_str(), i64::from(*pin)))
+        .collect();
+    let restart_required = cfg.mode != OperationMode::Monitor
+        && (zones
+            .iter()
+            .map(|z| (z.zone_id.as_str(), z.valve_gpio_pin))
+            .collect::<HashSet<_>>()
+            != board
+            || motorized_valves(&zones).ok().as_deref() != Some(motorized));
+    let outcome = restore::RestoreOutcome {
+        rows,
+        zones: zones.len(),
+        sensors: sensors.len(),
+        restart_required,
+    };
+
+    run_safety_review(&zones, &sensors, db, shared).await;
+
+    info!(backup = %backup, rows, restart_required, "database restored from backup");
+    shared.write().await.record_system(format!(
+        "database restored from backup {backup} ({rows} rows){}",
+        if restart_required {
+            " — restart the hub to apply valve changes"
+        } else {
+            ""
+        }
+    ));
+    Ok((
+        zones.into_iter().map(|z| (z.zone_id.clone(), z)).collect(),
+        sensors
+            .into_iter()
+            .map(|s| (s.sensor_id.clone(), s))
+            .collect(),
+        outcome,
+    ))
+}
+
+/// Review the zone config for risky combinations (see `review`) and hand
+/// the findings, with their stored acknowledgements, to the shared state.
+async fn run_safety_review(
+    zones: &[ZoneConfig],
+    sensors: &[SensorConfig],
+    db: &Db,
+    shared: &RwLock<SystemState>,
+) {
+    let mut findings = review::review(zones, sensors);
+    if let Err(e) = db.mark_acknowledged(&mut findings).await {
+        // Unknown acknowledgements count as pending: fail closed.
+        warn!("safety review: loading acknowledgements failed: {e:#}");
+    }
+    let pending = findings.iter().filter(|f| !f.acknowledged).count();
+    if pending > 0 {
+        warn!(
+            pending,
+            "safety review: findings need acknowledging before auto mode waters those zones"
+        );
+    }
+    shared.write().await.set_safety_review(findings);
+}
+
+/// After forcing all valves off, close out the persisted open-valve rows so
+/// the interrupted sessions still count towards the daily limits.
+async fn close_out_sessions(db: &Db) {
+    if let Err(e) = db
+        .close_open_valves(now_unix(), "emergency_off", "forced_off", "emergency")
+        .await
+    {
+        error!("closing out open valve sessions failed: {e:#}");
+    }
+}
+
+/// Count a pulse towards today's limit: in the DB normally, in memory while
+/// degraded.  A failed write enters degraded mode.
+async fn count_pulse(db: &Db, shared: &RwLock<SystemState>, day: &str, zone_id: &str) {
+    if !shared.read().await.is_db_degraded() {
+        match db.add_pulse(day, zone_id, 1).await {
+            Ok(()) => return,
+            Err(e) => {
+                error!(zone = %zone_id, "add_pulse failed: {e}");
+                shared.write().await.mark_db_degraded("add_pulse failed");
+            }
+        }
+    }
+    shared.write().await.add_pending_pulse(day, zone_id);
+}
+
+/// Open-seconds counterpart of `count_pulse`.
+async fn count_open_seconds(
+    db: &Db,
+    shared: &RwLock<SystemState>,
+    day: &str,
+    zone_id: &str,
+    secs: i64,
+) {
+    if !shared.read().await.is_db_degraded() {
+        match db.add_open_seconds(day, zone_id, secs).await {
+            Ok(()) => return,
+            Err(e) => {
+                error!(zone = %zone_id, "add_open_seconds failed: {e}");
+                shared
+                    .write()
+                    .await
+                    .mark_db_degraded("add_open_seconds failed");
+            }
+        }
+    }
+    shared
+        .write()
+        .await
+        .add_pending_open_sec(day, zone_id, secs);
+}
+
+/// Raise a maintenance event the first time a zone's valve crosses its
+/// `[valve_service]` threshold.  Skipped while degraded: the odometer
+/// catches up when the pending counters are flushed.
+async fn check_valve_service(db: &Db, shared: &RwLock<SystemState>, zone_id: &str) {
+    let service = {
+        let st = shared.read().await;
+        if st.is_db_degraded() {
+            return;
+        }
+        st.limits.valve_service
+    };
+    if service == ValveServiceConfig::default() {
+        return;
+    }
+    let odometer = match db.get_odometer(zone_id).await {
+        Ok(o) => o,
+        Err(e) => {
+            error!(zone = %zone_id, "get_odometer failed: {e:#}");
+            return;
+        }
+    };
+    if odometer.service_due_ts.is_some() || !service.is_due(&odometer) {
+        return;
+    }
+    match db.mark_service_due(zone_id, now_unix()).await {
+        Ok(true) => {
+            warn!(zone = %zone_id, "valve due for service");
+            shared.write().await.record_maintenance(format!(
+                "zone {zone_id}: valve due for service ({} actuations, {:.1} h open since last service)",
+                odometer.actuations_since_service,
+                odometer.open_sec_since_service as f64 / 3600.0
+            ));
+        }
+        Ok(false) => {}
+        Err(e) => error!(zone = %zone_id, "mark_service_due failed: {e:#}"),
+    }
+}
+
+/// One DB monitor tick: probe writability, flush counters accrued while
+/// degraded, and leave degraded mode once both succeed.
+async fn check_db_writable(db: &Db, shared: &RwLock<SystemState>) {
+    if let Err(e) = db.write_probe().await {
+        let mut st = shared.write().await;
+        if !st.is_db_degraded() {
+            error!("{e:#}");
+        }
+        st.mark_db_degraded("write probe failed");
+        return;
+    }
+
+    let pending = shared.write().await.take_pending_counters();
+    for (i, (day, zone_id, c)) in pending.iter().enumerate() {
+        let flushed = async {
+            if c.pulses > 0 {
+                db.add_pulse(day, zone_id, c.pulses).await?;
+            }
+            if c.open_sec > 0 {
+                db.add_open_seconds(day, zone_id, c.open_sec).await?;
+            }
+            anyhow::Ok(())
+        }
+        .await;
+        if let Err(e) = flushed {
+            // Keep this and the remaining entries for the next tick.  A
+            // partially flushed entry may be counted twice — erring on the
+            // safe (more restrictive) side.
+            error!("flushing degraded-mode counters failed: {e:#}");
+            shared
+                .write()
+                .await
+                .restore_pending_counters(pending[i..].to_vec());
+            return;
+        }
+        shared
+            .write()
+            .await
+            .note_flushed_counters(day, zone_id, *c, now_unix());
+    }
+
+    shared.write().await.mark_db_recovered();
+}
+
 fn now_unix() -> i64 {
-    match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
-        Ok(d) => d.as_secs() as i64,
-        Err(_) => {
+    match clock::now_unix() {
+        ts if ts >= 0 => ts,
+        _ => {
             // System clock before UNIX epoch — can happen on Raspberry Pis
             // without an RTC before NTP syncs.  Return 0 so callers don't
             // panic, but log loudly so the operator notices.
@@ -1102,3 +3454,59 @@ fn now_unix() -> i64 {
         }
     }
 }
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(all(test, not(any(feature = "gpio", feature = "gpiod"))))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn emergency_stop_during_an_in_flight_on_keeps_the_valve_shut() {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        let zone = ZoneConfig::test("z1");
+        db.upsert_zone(&zone).await.unwrap();
+        let zone_configs = HashMap::from([("z1".to_string(), zone)]);
+        let zones = vec![("z1".to_string(), 17)];
+        let valves =
+            Mutex::new(ValveBoard::new(&gpio::GpioConfig::default(), &zones, true).unwrap());
+        let opened_at = Arc::new(Mutex::new(HashMap::new()));
+        let (timed_close, _rx) = tokio::sync::mpsc::channel(1);
+        let mut st = SystemState::new(&zones, "auto");
+        st.limits.max_concurrent_valves = 1;
+        let shared = RwLock::new(st);
+
+        // Hold the board so the command stops just before opening, past
+        // its early checks, then press the stop.
+        let board = valves.lock().await;
+        let command = handle_valve_command(
+            "z1",
+            b"ON",
+            &zone_configs,
+            &valves,
+            &opened_at,
+            &timed_close,
+            &db,
+            &shared,
+            OperationMode::Auto,
+        );
+        let press = async {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            shared.write().await.estop.latch(1);
+            drop(board);
+        };
+        tokio::join!(command, press);
+
+        assert!(!valves.lock().await.zones["z1"]);
+        assert!(opened_at.lock().await.is_empty());
+        let st = shared.read().await;
+        assert!(!st.zones["z1"].on);
+        assert!(st
+            .events
+            .iter()
+            .any(|e| e.detail == "valve ON refused for z1 — emergency stop latched"));
+    }
+}
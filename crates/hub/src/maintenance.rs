@@ -0,0 +1,166 @@
+//! Maintenance windows: times of day (UTC) when heavy background jobs —
+//! pruning with incremental vacuum, database backups — may start.
+//!
+//! Configured in `config.toml`:
+//!
+//! ```toml
+//! [maintenance]
+//! windows = ["02:00-04:00"]
+//! ```
+//!
+//! A window whose end is before its start wraps past midnight
+//! (`"23:00-01:00"`).  With no windows configured, jobs run whenever their
+//! timer fires.  Windows only gate when a job *starts*; a job that is
+//! already running is never interrupted.
+
+use std::time::Duration;
+
+use time::OffsetDateTime;
+use tracing::info;
+
+const DAY_SEC: i64 = 86_400;
+
+/// One daily time range, in seconds since midnight UTC.  `end` is
+/// exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaintenanceWindow {
+    start: i64,
+    end: i64,
+}
+
+impl MaintenanceWindow {
+    /// Parse `"HH:MM-HH:MM"`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let err = || format!("maintenance window '{s}' must be HH:MM-HH:MM (UTC)");
+        let (start, end) = s.split_once('-').ok_or_else(err)?;
+        let start = parse_hh_mm(start).ok_or_else(err)?;
+        let end = parse_hh_mm(end).ok_or_else(err)?;
+        if start == end {
+            return Err(format!("maintenance window '{s}' is empty"));
+        }
+        Ok(Self { start, end })
+    }
+
+    fn contains(&self, sec: i64) -> bool {
+        if self.start < self.end {
+            (self.start..self.end).contains(&sec)
+        } else {
+            sec >= self.start || sec < self.end
+        }
+    }
+
+    /// Seconds from `sec` until this window next opens.
+    fn until_open(&self, sec: i64) -> i64 {
+        if self.contains(sec) {
+            0
+        } else {
+            (self.start - sec).rem_euclid(DAY_SEC)
+        }
+    }
+}
+
+fn parse_hh_mm(s: &str) -> Option<i64> {
+    let (h, m) = s.trim().split_once(':')?;
+    let (h, m): (i64, i64) = (h.parse().ok()?, m.parse().ok()?);
+    ((0..24).contains(&h) && (0..60).contains(&m)).then_some(h * 3600 + m * 60)
+}
+
+/// The configured windows.  Empty = maintenance may run at any time.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MaintenanceWindows(Vec<MaintenanceWindow>);
+
+impl MaintenanceWindows {
+    /// Parse every window, collecting all errors.
+    pub fn parse(windows: &[String]) -> Result<Self, Vec<String>> {
+        let mut parsed = Vec::new();
+        let mut errs = Vec::new();
+        for w in windows {
+            match MaintenanceWindow::parse(w) {
+                Ok(w) => parsed.push(w),
+                Err(e) => errs.push(e),
+            }
+        }
+        if errs.is_empty() {
+            Ok(Self(parsed))
+        } else {
+            Err(errs)
+        }
+    }
+
+    /// How long until maintenance may start (zero if a window is open now).
+    pub fn time_until_open(&self, now: OffsetDateTime) -> Duration {
+        let sec =
+            i64::from(now.hour()) * 3600 + i64::from(now.minute()) * 60 + i64::from(now.second());
+        let wait = self.0.iter().map(|w| w.until_open(sec)).min().unwrap_or(0);
+        Duration::from_secs(wait as u64)
+    }
+
+    /// Sleep until a window is open.  `job` names the caller in the log.
+    pub async fn wait(&self, job: &str) {
+        let wait = self.time_until_open(crate::clock::now_utc());
+        if !wait.is_zero() {
+            info!(
+                job,
+                wait_min = wait.as_secs() / 60,
+                "deferring until the next maintenance window"
+            );
+            crate::clock::sleep(wait).await;
+        }
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    fn windows(list: &[&str]) -> MaintenanceWindows {
+        MaintenanceWindows::parse(&list.iter().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap()
+    }
+
+    #[test]
+    fn no_windows_always_open() {
+        let w = MaintenanceWindows::default();
+        assert!(w.time_until_open(datetime!(2025-06-01 12:00 UTC)).is_zero());
+    }
+
+    #[test]
+    fn waits_for_next_window() {
+        let w = windows(&["02:00-04:00"]);
+        assert!(w.time_until_open(datetime!(2025-06-01 03:59 UTC)).is_zero());
+        assert_eq!(
+            w.time_until_open(datetime!(2025-06-01 04:00 UTC)),
+            Duration::from_secs(22 * 3600)
+        );
+        assert_eq!(
+            w.time_until_open(datetime!(2025-06-01 01:30 UTC)),
+            Duration::from_secs(1800)
+        );
+    }
+
+    #[test]
+    fn window_wraps_midnight_and_nearest_wins() {
+        let w = windows(&["23:00-01:00", "12:00-12:30"]);
+        assert!(w.time_until_open(datetime!(2025-06-01 00:30 UTC)).is_zero());
+        assert!(w.time_until_open(datetime!(2025-06-01 23:30 UTC)).is_zero());
+        assert_eq!(
+            w.time_until_open(datetime!(2025-06-01 11:00 UTC)),
+            Duration::from_secs(3600)
+        );
+        assert_eq!(
+            w.time_until_open(datetime!(2025-06-01 13:00 UTC)),
+            Duration::from_secs(10 * 3600)
+        );
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        let bad = ["02:00", "2am-4am", "02:00-24:00", "03:00-03:00"];
+        let errs = MaintenanceWindows::parse(&bad.map(String::from)).unwrap_err();
+        assert_eq!(errs.len(), 4);
+    }
+}
@@ -0,0 +1,358 @@
+//! Prometheus-style metrics: fixed-bucket histograms rendered in the text
+//! exposition format at `GET /metrics`.
+//!
+//! Kept deliberately tiny (no registry, no atomics) — metrics live inside
+//! `SystemState` and are updated under its write lock, which every valve
+//! command already takes to record the state change.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use crate::mqtt::{PayloadKind, RejectReason};
+
+/// Bucket upper bounds (seconds) for valve command latency.  Spans sub-ms
+/// GPIO writes up to multi-second broker round-trips or relay staggering.
+const LATENCY_BUCKETS_SEC: &[f64] = &[
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// How long a scheduler-issued command stamp stays valid while waiting for
+/// its MQTT round-trip.  Older stamps are treated as unrelated.
+const SCHEDULER_STAMP_TTL: Duration = Duration::from_secs(60);
+
+// ---------------------------------------------------------------------------
+// Histogram
+// ---------------------------------------------------------------------------
+
+/// Cumulative histogram with fixed upper bounds.
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    bounds: &'static [f64],
+    /// Per-bucket (non-cumulative) counts; one extra slot for `+Inf`.
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    pub fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            counts: vec![0; bounds.len() + 1],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    pub fn observe(&mut self, value: f64) {
+        let idx = self
+            .bounds
+            .iter()
+            .position(|b| value <= *b)
+            .unwrap_or(self.bounds.len());
+        self.counts[idx] += 1;
+        self.sum += value;
+        self.count += 1;
+    }
+
+    #[cfg(test)]
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Append `_bucket`, `_sum` and `_count` lines for this series.
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bound, n) in self.bounds.iter().zip(&self.counts) {
+            cumulative += n;
+            let _ = writeln!(out, "{name}_bucket{{{labels},le=\"{bound}\"}} {cumulative}");
+        }
+        let _ = writeln!(out, "{name}_bucket{{{labels},le=\"+Inf\"}} {}", self.count);
+        let _ = writeln!(out, "{name}_sum{{{labels}}} {}", self.sum);
+        let _ = writeln!(out, "{name}_count{{{labels}}} {}", self.count);
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Valve command latency
+// ---------------------------------------------------------------------------
+
+/// Where a valve command originated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CommandSource {
+    /// Published to `valve/<zone>/set` by something other than the scheduler.
+    Mqtt,
+    /// Issued by the scheduler (measured from its publish, so the broker
+    /// round-trip is included).
+    Scheduler,
+}
+
+impl CommandSource {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Mqtt => "mqtt",
+            Self::Scheduler => "scheduler",
+        }
+    }
+}
+
+/// Which point of the command path a latency sample was taken at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LatencyStage {
+    /// GPIO pin written.
+    Actuation,
+    /// Shared state updated (visible to the API / dashboard).
+    Propagation,
+}
+
+impl LatencyStage {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Actuation => "actuation",
+            Self::Propagation => "propagation",
+        }
+    }
+}
+
+/// Rejected inbound MQTT payloads of one kind and reason (in `/api/status`).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RejectCount {
+    pub payload: &'static str,
+    pub reason: &'static str,
+    pub count: u64,
+}
+
+/// All hub metrics.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    valve_latency: BTreeMap<(CommandSource, LatencyStage), Histogram>,
+    /// zone_id -> (ON?, publish time) for scheduler commands in flight.
+    scheduler_stamps: BTreeMap<String, (bool, Instant)>,
+    mqtt_rejects: BTreeMap<(PayloadKind, RejectReason), u64>,
+}
+
+impl Metrics {
+    pub fn observe_valve_latency(
+        &mut self,
+        source: CommandSource,
+        stage: LatencyStage,
+        elapsed: Duration,
+    ) {
+        self.valve_latency
+            .entry((source, stage))
+            .or_insert_with(|| Histogram::new(LATENCY_BUCKETS_SEC))
+            .observe(elapsed.as_secs_f64());
+    }
+
+    #[cfg(test)]
+    pub fn valve_latency(&self, source: CommandSource, stage: LatencyStage) -> Option<&Histogram> {
+        self.valve_latency.get(&(source, stage))
+    }
+
+    /// Remember that the scheduler just published a command for `zone_id`.
+    pub fn stamp_scheduler_command(&mut self, zone_id: &str, on: bool) {
+        self.scheduler_stamps
+            .insert(zone_id.to_string(), (on, Instant::now()));
+    }
+
+    /// Attribute an incoming command: if the scheduler recently published the
+    /// same command for this zone, the latency clock starts at that publish;
+    /// otherwise at `received`.
+    pub fn take_command_origin(
+        &mut self,
+        zone_id: &str,
+        on: bool,
+        received: Instant,
+    ) -> (CommandSource, Instant) {
+        match self.scheduler_stamps.remove(zone_id) {
+            Some((stamp_on, at)) if stamp_on == on && at.elapsed() <= SCHEDULER_STAMP_TTL => {
+                (CommandSource::Scheduler, at)
+            }
+            _ => (CommandSource::Mqtt, received),
+        }
+    }
+
+    /// Count a rejected inbound MQTT payload.
+    pub fn count_mqtt_reject(&mut self, kind: PayloadKind, reason: RejectReason) {
+        *self.mqtt_rejects.entry((kind, reason)).or_default() += 1;
+    }
+
+    /// Reject counters, by payload kind then reason.
+    pub fn mqtt_rejects(&self) -> Vec<RejectCount> {
+        self.mqtt_rejects
+            .iter()
+            .map(|((kind, reason), count)| RejectCount {
+                payload: kind.as_str(),
+                reason: reason.as_str(),
+                count: *count,
+            })
+            .collect()
+    }
+
+    /// Render every metric in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let name = "irrigation_valve_command_latency_seconds";
+        let _ = writeln!(
+            out,
+            "# HELP {name} Time from valve command receipt to GPIO actuation and to state propagation."
+        );
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        for ((source, stage), h) in &self.valve_latency {
+            let labels = format!(
+                "source=\"{}\",stage=\"{}\"",
+                source.as_str(),
+                stage.as_str()
+            );
+            h.render(&mut out, name, &labels);
+        }
+
+        let name = "irrigation_mqtt_rejects_total";
+        let _ = writeln!(
+            out,
+            "# HELP {name} Inbound MQTT payloads rejected by schema validation."
+        );
+        let _ = writeln!(out, "# TYPE {name} counter");
+        for r in self.mqtt_rejects() {
+            let _ = writeln!(
+                out,
+                "{name}{{payload=\"{}\",reason=\"{}\"}} {}",
+                r.payload, r.reason, r.count
+            );
+        }
+        out
+    }
+}
+
+/// Append the count of database statements retried after a busy or locked
+/// error (see `Db::retried_statements`).
+pub fn render_db_retries(out: &mut String, retried: u64) {
+    let name = "irrigation_db_statements_retried_total";
+    let _ = writeln!(
+        out,
+        "# HELP {name} Database statements retried after SQLite reported the database busy or locked."
+    );
+    let _ = writeln!(out, "# TYPE {name} counter");
+    let _ = writeln!(out, "{name} {retried}");
+}
+
+/// Append the count of sensor readings dropped as already stored (see
+/// `Db::duplicate_readings`).
+pub fn render_duplicate_readings(out: &mut String, duplicates: u64) {
+    let name = "irrigation_duplicate_readings_total";
+    let _ = writeln!(
+        out,
+        "# HELP {name} Sensor readings dropped as already stored (redeliveries, node replays)."
+    );
+    let _ = writeln!(out, "# TYPE {name} counter");
+    let _ = writeln!(out, "{name} {duplicates}");
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let mut h = Histogram::new(&[0.1, 1.0]);
+        h.observe(0.05);
+        h.observe(0.5);
+        h.observe(5.0);
+        let mut out = String::new();
+        h.render(&mut out, "m", "a=\"b\"");
+        assert!(out.contains("m_bucket{a=\"b\",le=\"0.1\"} 1\n"));
+        assert!(out.contains("m_bucket{a=\"b\",le=\"1\"} 2\n"));
+        assert!(out.contains("m_bucket{a=\"b\",le=\"+Inf\"} 3\n"));
+        assert!(out.contains("m_sum{a=\"b\"} 5.55\n"));
+        assert!(out.contains("m_count{a=\"b\"} 3\n"));
+    }
+
+    #[test]
+    fn observe_records_per_source_and_stage() {
+        let mut m = Metrics::default();
+        m.observe_valve_latency(
+            CommandSource::Mqtt,
+            LatencyStage::Actuation,
+            Duration::from_millis(2),
+        );
+        m.observe_valve_latency(
+            CommandSource::Mqtt,
+            LatencyStage::Actuation,
+            Duration::from_millis(3),
+        );
+        let h = m
+            .valve_latency(CommandSource::Mqtt, LatencyStage::Actuation)
+            .unwrap();
+        assert_eq!(h.count(), 2);
+        assert!(m
+            .valve_latency(CommandSource::Scheduler, LatencyStage::Actuation)
+            .is_none());
+
+        let text = m.render();
+        assert!(text.contains("# TYPE irrigation_valve_command_latency_seconds histogram"));
+        assert!(text.contains("source=\"mqtt\",stage=\"actuation\",le=\"0.005\"} 2"));
+    }
+
+    #[test]
+    fn scheduler_stamp_attributes_matching_command() {
+        let mut m = Metrics::default();
+        m.stamp_scheduler_command("z1", true);
+        let received = Instant::now();
+        let (source, start) = m.take_command_origin("z1", true, received);
+        assert_eq!(source, CommandSource::Scheduler);
+        assert!(start <= received);
+        // Stamp is consumed.
+        let (source, _) = m.take_command_origin("z1", true, received);
+        assert_eq!(source, CommandSource::Mqtt);
+    }
+
+    #[test]
+    fn mqtt_rejects_counted_per_kind_and_reason() {
+        let mut m = Metrics::default();
+        m.count_mqtt_reject(PayloadKind::Telemetry, RejectReason::WrongType);
+        m.count_mqtt_reject(PayloadKind::Telemetry, RejectReason::WrongType);
+        m.count_mqtt_reject(PayloadKind::Flow, RejectReason::Malformed);
+        assert_eq!(
+            m.mqtt_rejects(),
+            vec![
+                RejectCount {
+                    payload: "telemetry",
+                    reason: "wrong_type",
+                    count: 2,
+                },
+                RejectCount {
+                    payload: "flow",
+                    reason: "malformed",
+                    count: 1,
+                },
+            ]
+        );
+        assert!(m.render().contains(
+            "irrigation_mqtt_rejects_total{payload=\"telemetry\",reason=\"wrong_type\"} 2\n"
+        ));
+    }
+
+    #[test]
+    fn duplicate_readings_rendered() {
+        let mut out = String::new();
+        render_duplicate_readings(&mut out, 2);
+        assert!(out.contains("# TYPE irrigation_duplicate_readings_total counter\n"));
+        assert!(out.contains("irrigation_duplicate_readings_total 2\n"));
+    }
+
+    #[test]
+    fn scheduler_stamp_ignored_for_other_command() {
+        let mut m = Metrics::default();
+        m.stamp_scheduler_command("z1", true);
+        let (source, _) = m.take_command_origin("z1", false, Instant::now());
+        assert_eq!(source, CommandSource::Mqtt);
+        let (source, _) = m.take_command_origin("z2", true, Instant::now());
+        assert_eq!(source, CommandSource::Mqtt);
+    }
+}
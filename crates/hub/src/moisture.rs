@@ -0,0 +1,306 @@
+//! Rolling per-sensor moisture window held in memory, so the scheduler
+//! doesn't query SQLite for every zone on every tick.
+//!
+//! `handle_telemetry` pushes each stored reading; the scheduler loads the
+//! window from the database on its first tick and again whenever it has
+//! been invalidated (sensor config changes, disturbance edits, a restore).
+//! Until a load succeeds the scheduler keeps reading from the database.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::aggregation::SensorMoisture;
+use crate::db::{Db, Disturbance};
+
+/// Readings kept per sensor.  Comfortably more than the scheduler's
+/// smoothing window, so a short disturbance doesn't leave it short.
+pub const WINDOW_LEN: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+struct SensorWindow {
+    zone_id: String,
+    weight: f64,
+    /// (ts, moisture), oldest first.
+    readings: VecDeque<(i64, f32)>,
+}
+
+impl SensorWindow {
+    fn push(&mut self, ts: i64, moisture: f32) {
+        // Replayed readings from a node's buffer can arrive out of order.
+        let at = self.readings.partition_point(|(t, _)| *t <= ts);
+        if at > 0 && self.readings[at - 1].0 == ts {
+            return;
+        }
+        self.readings.insert(at, (ts, moisture));
+        if self.readings.len() > WINDOW_LEN {
+            self.readings.pop_front();
+        }
+    }
+}
+
+/// Recent readings of every active sensor, plus the disturbed ranges that
+/// hide some of them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MoistureWindow {
+    loaded: bool,
+    sensors: HashMap<String, SensorWindow>,
+    /// zone_id -> (start_ts, end_ts) ranges, `None` end while ongoing.
+    disturbances: HashMap<String, Vec<(i64, Option<i64>)>>,
+    /// Readings pushed before the first load, replayed by it.
+    pending: Vec<(String, i64, f32)>,
+}
+
+impl MoistureWindow {
+    /// Build a loaded window from the database.
+    pub async fn load(db: &Db) -> anyhow::Result<Self> {
+        let mut window = Self {
+            loaded: true,
+            ..Self::default()
+        };
+        for s in db.load_sensors().await? {
+            if s.archived_at.is_none() && s.enabled {
+                window.sensors.insert(
+                    s.sensor_id,
+                    SensorWindow {
+                        zone_id: s.zone_id,
+                        weight: s.weight,
+                        readings: VecDeque::new(),
+                    },
+                );
+            }
+        }
+        for r in db.recent_readings(WINDOW_LEN as i64).await? {
+            window.push(&r.sensor_id, r.ts, r.moisture as f32);
+        }
+        for d in db.all_disturbances().await? {
+            window.add_disturbance(&d);
+        }
+        Ok(window)
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+
+    /// Mark the window out of date; the scheduler reads from the database
+    /// until it has reloaded.
+    pub fn invalidate(&mut self) {
+        self.loaded = false;
+    }
+
+    /// Replace this window with a freshly loaded one.  Readings already
+    /// held are kept: with batched writes they may not be stored yet.
+    pub fn replace(&mut self, loaded: MoistureWindow) {
+        let old = std::mem::replace(self, loaded);
+        for (sensor_id, s) in old.sensors {
+            for (ts, moisture) in s.readings {
+                self.push(&sensor_id, ts, moisture);
+            }
+        }
+        for (sensor_id, ts, moisture) in old.pending {
+            self.push(&sensor_id, ts, moisture);
+        }
+    }
+
+    /// Add a stored reading.  Readings from sensors the window doesn't know
+    /// (archived or unconfigured) are dropped.
+    pub fn push(&mut self, sensor_id: &str, ts: i64, moisture: f32) {
+        match self.sensors.get_mut(sensor_id) {
+            Some(s) => s.push(ts, moisture),
+            None if !self.loaded => {
+                self.pending.push((sensor_id.to_string(), ts, moisture));
+            }
+            None => {}
+        }
+    }
+
+    fn add_disturbance(&mut self, d: &Disturbance) {
+        self.disturbances
+            .entry(d.zone_id.clone())
+            .or_default()
+            .push((d.start_ts, d.end_ts));
+    }
+
+    fn disturbed(&self, zone_id: &str, ts: i64) -> bool {
+        self.disturbances.get(zone_id).is_some_and(|ranges| {
+            ranges
+                .iter()
+                .any(|&(start, end)| ts >= start && end.is_none_or(|end| ts < end))
+        })
+    }
+
+    /// Newest undisturbed reading from any of the zone's sensors.
+    pub fn latest(&self, zone_id: &str) -> Option<(i64, f32)> {
+        self.sensors
+            .values()
+            .filter(|s| s.zone_id == zone_id)
+            .filter_map(|s| {
+                s.readings
+                    .iter()
+                    .rev()
+                    .find(|(ts, _)| !self.disturbed(zone_id, *ts))
+            })
+            .max_by_key(|(ts, _)| *ts)
+            .copied()
+    }
+
+    /// Each sensor's mean over its last `n` undisturbed readings since
+    /// `since_ts`, for sensors that have any.  Mirrors
+    /// [`Db::zone_sensor_moisture`], without the quarantine filter.
+    pub fn sensor_moisture(&self, zone_id: &str, n: usize, since_ts: i64) -> Vec<SensorMoisture> {
+        let mut out: Vec<SensorMoisture> = self
+            .sensors
+            .iter()
+            .filter(|(_, s)| s.zone_id == zone_id)
+            .filter_map(|(sensor_id, s)| {
+                let recent: Vec<f32> = s
+                    .readings
+                    .iter()
+                    .rev()
+                    .filter(|(ts, _)| *ts >= since_ts && !self.disturbed(zone_id, *ts))
+                    .take(n)
+                    .map(|(_, m)| *m)
+                    .collect();
+                if recent.is_empty() {
+                    return None;
+                }
+                let mean = recent.iter().map(|m| f64::from(*m)).sum::<f64>() / recent.len() as f64;
+                Some(SensorMoisture {
+                    sensor_id: sensor_id.clone(),
+                    weight: s.weight,
+                    moisture: mean as f32,
+                })
+            })
+            .collect();
+        out.sort_by(|a, b| a.sensor_id.cmp(&b.sensor_id));
+        out
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{SensorConfig, ZoneConfig};
+
+    async fn seeded_db() -> Db {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        db.upsert_zone(&ZoneConfig::test("z1")).await.unwrap();
+        for (id, weight) in [("s1", 1.0), ("s2", 0.5)] {
+            db.upsert_sensor(&SensorConfig {
+                sensor_id: id.into(),
+                node_id: "n1".into(),
+                zone_id: "z1".into(),
+                raw_dry: 26000,
+                raw_wet: 12000,
+                channel: None,
+                archived_at: None,
+                weight,
+                failure_margin: None,
+                failure_margin_pct: None,
+                curve: None,
+                enabled: true,
+            })
+            .await
+            .unwrap();
+        }
+        db
+    }
+
+    fn moisture(window: &MoistureWindow, n: usize, since_ts: i64) -> Vec<(String, f32)> {
+        window
+            .sensor_moisture("z1", n, since_ts)
+            .into_iter()
+            .map(|s| (s.sensor_id, s.moisture))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn load_matches_database_queries() {
+        let db = seeded_db().await;
+        for (ts, s1, s2) in [(1000, 0.2, 0.5), (1100, 0.4, 0.5), (1200, 0.6, 0.7)] {
+            db.insert_reading(ts, "s1", 0, s1).await.unwrap();
+            db.insert_reading(ts + 10, "s2", 0, s2).await.unwrap();
+        }
+        db.insert_disturbance("z1", 1150, None, "probe cleaning")
+            .await
+            .unwrap();
+
+        let window = MoistureWindow::load(&db).await.unwrap();
+        assert!(window.is_loaded());
+        assert_eq!(
+            window.latest("z1"),
+            db.latest_zone_moisture("z1").await.unwrap()
+        );
+        assert_eq!(
+            window.sensor_moisture("z1", 2, 900),
+            db.zone_sensor_moisture("z1", 2, 900).await.unwrap()
+        );
+        assert_eq!(window.latest("z1"), Some((1110, 0.5)));
+    }
+
+    #[test]
+    fn window_is_capped_and_ordered() {
+        let mut window = MoistureWindow {
+            loaded: true,
+            ..MoistureWindow::default()
+        };
+        window.sensors.insert(
+            "s1".into(),
+            SensorWindow {
+                zone_id: "z1".into(),
+                weight: 1.0,
+                readings: VecDeque::new(),
+            },
+        );
+        for ts in 0..(WINDOW_LEN as i64 + 4) {
+            window.push("s1", 100 + ts, 0.5);
+        }
+        // A late replay lands in order; a duplicate is ignored.
+        window.push("s1", 110, 0.5);
+        window.push("s1", 1000, 0.1);
+        window.push("s1", 900, 0.3);
+        window.push("unknown", 1000, 0.9);
+
+        let readings = &window.sensors["s1"].readings;
+        assert_eq!(readings.len(), WINDOW_LEN);
+        assert!(readings
+            .iter()
+            .zip(readings.iter().skip(1))
+            .all(|(a, b)| a.0 < b.0));
+        assert_eq!(window.latest("z1"), Some((1000, 0.1)));
+        assert_eq!(moisture(&window, 2, 0), [("s1".to_string(), 0.2)]);
+        // Nothing since the staleness cutoff.
+        assert!(moisture(&window, 2, 2000).is_empty());
+    }
+
+    #[tokio::test]
+    async fn readings_pushed_while_unloaded_survive_reload() {
+        let db = seeded_db().await;
+        db.insert_reading(1000, "s1", 0, 0.2).await.unwrap();
+
+        let mut window = MoistureWindow::default();
+        window.push("s1", 1100, 0.4);
+        window.replace(MoistureWindow::load(&db).await.unwrap());
+        assert_eq!(moisture(&window, 5, 0), [("s1".to_string(), 0.3)]);
+
+        window.invalidate();
+        assert!(!window.is_loaded());
+        window.push("s2", 1200, 0.6);
+        db.insert_disturbance("z1", 1150, None, "probe cleaning")
+            .await
+            .unwrap();
+        window.replace(MoistureWindow::load(&db).await.unwrap());
+        // 1100 was never written to the database but is kept; the new
+        // disturbance hides 1200.
+        assert_eq!(window.latest("z1"), Some((1100, 0.4)));
+        window.push("s2", 1100, 0.5);
+        assert_eq!(
+            moisture(&window, 5, 0),
+            [("s1".to_string(), 0.3), ("s2".to_string(), 0.5)]
+        );
+    }
+}
@@ -1,21 +1,321 @@
 //! MQTT topic parsing, payload deserialization, and message types.
 
-use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use crate::config::OperationMode;
+use crate::db::{NodeConfig, SensorConfig};
+use crate::state::Firmware;
 
 // ---------------------------------------------------------------------------
 // MQTT message types
 // ---------------------------------------------------------------------------
 
 #[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub(crate) struct Reading {
     pub(crate) sensor_id: String,
     pub(crate) raw: i64,
+    /// Spread of the node's oversampled conversions (ADC backend only).
+    /// Validated but not stored.
+    #[serde(default)]
+    pub(crate) raw_stddev: Option<f32>,
+    /// Input voltage behind `raw` in millivolts, from nodes with
+    /// `ADC_REPORT_MV`.  Shown with the node's last readings, not stored.
+    #[serde(default)]
+    pub(crate) mv: Option<f32>,
+    /// When the reading was taken, for readings batched from a node's
+    /// offline buffer; the message's `ts` otherwise.
+    #[serde(default)]
+    pub(crate) ts: Option<i64>,
+}
+
+impl Reading {
+    /// The reading's own timestamp, or the message's.
+    pub(crate) fn ts_or(&self, msg_ts: i64) -> i64 {
+        self.ts.unwrap_or(msg_ts)
+    }
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub(crate) struct ReadingMsg {
     pub(crate) ts: i64,
     pub(crate) readings: Vec<Reading>,
+    /// Air temperature and relative humidity from the node's climate
+    /// sensor (DHT22 or SHT31), if it has one.
+    #[serde(default)]
+    pub(crate) air_temp_c: Option<f64>,
+    #[serde(default)]
+    pub(crate) humidity_pct: Option<f64>,
+    /// Light level from the node's BH1750, if it has one.
+    #[serde(default)]
+    pub(crate) lux: Option<f64>,
+}
+
+/// Recommendation from an external advisor on `advice/<zone_id>/response`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct AdviceMsg {
+    pub(crate) pulses: u32,
+    #[serde(default)]
+    pub(crate) reason: String,
+}
+
+/// Flow-meter sample on `flow/<zone_id>/reading`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct FlowMsg {
+    pub(crate) ts: i64,
+    pub(crate) lpm: f64,
+    #[serde(default)]
+    pub(crate) pressure_kpa: Option<f64>,
+}
+
+/// Outdoor temperature on `temp/<source_id>/reading` (frost lockout).
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct TempMsg {
+    pub(crate) ts: i64,
+    pub(crate) temp_c: f64,
+}
+
+/// Line pressure on `pressure/<source_id>/reading`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct PressureMsg {
+    pub(crate) ts: i64,
+    pub(crate) kpa: f64,
+}
+
+/// Weather sample on `weather/<source_id>/reading` (ET0).  `rain_mm` is
+/// the rain since the source's previous sample; `et0_mm` is the day's
+/// reference ET from a source that already knows it.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct WeatherMsg {
+    pub(crate) ts: i64,
+    #[serde(default)]
+    pub(crate) temp_c: Option<f64>,
+    #[serde(default)]
+    pub(crate) humidity_pct: Option<f64>,
+    #[serde(default)]
+    pub(crate) solar_w_m2: Option<f64>,
+    #[serde(default)]
+    pub(crate) wind_m_s: Option<f64>,
+    #[serde(default)]
+    pub(crate) rain_mm: Option<f64>,
+    #[serde(default)]
+    pub(crate) et0_mm: Option<f64>,
+}
+
+/// Log lines a node publishes to `diag/<node_id>/logs` in answer to
+/// `send-logs`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct NodeLogsMsg {
+    pub(crate) ts: i64,
+    pub(crate) lines: Vec<String>,
+}
+
+/// A node's answer to `ping` on `diag/<node_id>/pong`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct NodePongMsg {
+    pub(crate) ts: i64,
+    /// Echoed from the ping.
+    #[serde(default)]
+    pub(crate) sent_ms: Option<i64>,
+    pub(crate) uptime_sec: u64,
+    pub(crate) sample_interval_sec: u64,
+    /// When the node's sampling loop last ran; unset before the first pass.
+    #[serde(default)]
+    pub(crate) last_sample_ts: Option<i64>,
+    /// Readings waiting in the node's offline buffer.
+    pub(crate) buffered: u64,
+}
+
+/// A node's update progress on `ota/<node_id>/status`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct OtaReportMsg {
+    pub(crate) ts: i64,
+    /// `running`, `downloading`, `installed` or `failed`.
+    pub(crate) state: String,
+    pub(crate) version: String,
+    #[serde(default)]
+    pub(crate) error: Option<String>,
+}
+
+/// Diagnostics command for a node, published to `cmd/<node_id>/<command>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NodeCommand {
+    /// Exit and let systemd start the node again.
+    Restart,
+    /// Publish the last `lines` log lines to `diag/<node_id>/logs`.
+    SendLogs { lines: usize },
+    /// Answer on `diag/<node_id>/pong`, echoing `sent_ms`.
+    Ping { sent_ms: i64 },
+}
+
+impl NodeCommand {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Restart => "restart",
+            Self::SendLogs { .. } => "send-logs",
+            Self::Ping { .. } => "ping",
+        }
+    }
+
+    pub(crate) fn payload(self) -> Vec<u8> {
+        match self {
+            Self::Restart => Vec::new(),
+            Self::SendLogs { lines } => serde_json::json!({ "lines": lines })
+                .to_string()
+                .into_bytes(),
+            Self::Ping { sent_ms } => serde_json::json!({ "sent_ms": sent_ms })
+                .to_string()
+                .into_bytes(),
+        }
+    }
+}
+
+/// Zone context published to `advice/<zone_id>/request` for zones using the
+/// advisor strategy.
+#[derive(Debug, Serialize)]
+pub(crate) struct AdviceRequest<'a> {
+    pub(crate) zone_id: &'a str,
+    pub(crate) ts: i64,
+    pub(crate) mode: OperationMode,
+    pub(crate) avg_moisture: Option<f32>,
+    pub(crate) min_moisture: f32,
+    pub(crate) target_moisture: f32,
+    pub(crate) pulse_sec: i64,
+    pub(crate) soak_min: i64,
+    pub(crate) pulses_today: i64,
+    pub(crate) open_sec_today: i64,
+    pub(crate) max_pulses_per_day: i64,
+    pub(crate) max_open_sec_per_day: i64,
+}
+
+/// Settings published (retained) to `cfg/<node_id>/set`.  The node applies
+/// them at startup and whenever they change; unset fields leave the node's
+/// own env configuration in place.
+#[derive(Debug, Serialize, PartialEq)]
+pub(crate) struct NodeSettingsMsg {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) sample_interval_sec: Option<i64>,
+    pub(crate) channels: Vec<ChannelSettings>,
+}
+
+/// One ADS1115 input and the sensor wired to it.  `raw_dry` / `raw_wet`
+/// are the hub's calibration, sent as hints (the node still reports raw).
+#[derive(Debug, Serialize, PartialEq)]
+pub(crate) struct ChannelSettings {
+    pub(crate) channel: i64,
+    pub(crate) sensor_id: String,
+    pub(crate) raw_dry: i64,
+    pub(crate) raw_wet: i64,
+    /// `false` while the sensor is out of service: the node skips the
+    /// channel but keeps it in its map.
+    pub(crate) enabled: bool,
+}
+
+impl NodeSettingsMsg {
+    /// Build a node's settings from its admin record and active sensors.
+    /// Sensors with no usable channel are left out of the map.
+    pub(crate) fn new(node: Option<&NodeConfig>, sensors: &[SensorConfig]) -> Self {
+        let mut channels: Vec<ChannelSettings> = sensors
+            .iter()
+            .filter(|s| s.archived_at.is_none())
+            .filter_map(|s| {
+                Some(ChannelSettings {
+                    channel: s.channel_or_default()?,
+                    sensor_id: s.local_id().to_string(),
+                    raw_dry: s.raw_dry,
+                    raw_wet: s.raw_wet,
+                    enabled: s.enabled,
+                })
+            })
+            .collect();
+        channels.sort_by_key(|c| c.channel);
+        Self {
+            sample_interval_sec: node.and_then(|n| n.sample_interval_sec),
+            channels,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Topic namespace
+// ---------------------------------------------------------------------------
+
+/// Topic filters the hub subscribes to (before the namespace prefix).
+pub(crate) const SUBSCRIPTIONS: [&str; 12] = [
+    "tele/+/reading",
+    "tele/+/reading/cbor",
+    "valve/+/set",
+    "status/node/+",
+    "advice/+/response",
+    "flow/+/reading",
+    "temp/+/reading",
+    "pressure/+/reading",
+    "weather/+/reading",
+    "diag/+/logs",
+    "diag/+/pong",
+    "ota/+/status",
+];
+
+/// Namespace prepended to every topic (`MQTT_TOPIC_PREFIX`), so several
+/// systems can share one broker.  Unset = no prefix.
+static TOPIC_PREFIX: OnceLock<String> = OnceLock::new();
+
+/// Validate a raw `MQTT_TOPIC_PREFIX`: surrounding slashes are trimmed and
+/// wildcards rejected.  Empty means no prefix.
+pub(crate) fn normalize_topic_prefix(raw: &str) -> Result<String, String> {
+    let prefix = raw.trim().trim_matches('/');
+    if prefix.contains(['+', '#']) {
+        return Err(format!(
+            "MQTT_TOPIC_PREFIX must not contain wildcards, got '{raw}'"
+        ));
+    }
+    Ok(prefix.to_string())
+}
+
+/// Set the namespace.  Call once at startup, before any topic is built.
+pub(crate) fn set_topic_prefix(prefix: String) {
+    if TOPIC_PREFIX.set(prefix).is_err() {
+        tracing::warn!("MQTT topic prefix already set — ignoring");
+    }
+}
+
+fn topic_prefix() -> &'static str {
+    TOPIC_PREFIX.get().map_or("", String::as_str)
+}
+
+fn prefixed(prefix: &str, path: &str) -> String {
+    if prefix.is_empty() {
+        path.to_string()
+    } else {
+        format!("{prefix}/{path}")
+    }
+}
+
+fn unprefixed<'a>(prefix: &str, topic: &'a str) -> Option<&'a str> {
+    if prefix.is_empty() {
+        Some(topic)
+    } else {
+        topic.strip_prefix(prefix)?.strip_prefix('/')
+    }
+}
+
+/// Apply the namespace to a topic or subscription filter.
+pub(crate) fn topic(path: &str) -> String {
+    prefixed(topic_prefix(), path)
 }
 
 // ---------------------------------------------------------------------------
@@ -24,7 +324,7 @@ pub(crate) struct ReadingMsg {
 
 /// Extract node_id from "tele/<node_id>/reading".
 pub(crate) fn extract_node_id(topic: &str) -> Option<&str> {
-    let parts: Vec<&str> = topic.split('/').collect();
+    let parts: Vec<&str> = unprefixed(topic_prefix(), topic)?.split('/').collect();
     if parts.len() == 3 && parts[0] == "tele" && parts[2] == "reading" {
         Some(parts[1])
     } else {
@@ -32,9 +332,20 @@ pub(crate) fn extract_node_id(topic: &str) -> Option<&str> {
     }
 }
 
+/// Extract node_id from "tele/<node_id>/reading/cbor" (CBOR-encoded
+/// telemetry, for nodes on links where JSON overhead matters).
+pub(crate) fn extract_cbor_node_id(topic: &str) -> Option<&str> {
+    let parts: Vec<&str> = unprefixed(topic_prefix(), topic)?.split('/').collect();
+    if parts.len() == 4 && parts[0] == "tele" && parts[2] == "reading" && parts[3] == "cbor" {
+        Some(parts[1])
+    } else {
+        None
+    }
+}
+
 /// Extract zone_id from "valve/<zone_id>/set".
 pub(crate) fn extract_zone_id(topic: &str) -> Option<&str> {
-    let parts: Vec<&str> = topic.split('/').collect();
+    let parts: Vec<&str> = unprefixed(topic_prefix(), topic)?.split('/').collect();
     if parts.len() == 3 && parts[0] == "valve" && parts[2] == "set" {
         Some(parts[1])
     } else {
@@ -44,7 +355,7 @@ pub(crate) fn extract_zone_id(topic: &str) -> Option<&str> {
 
 /// Extract node_id from "status/node/<node_id>".
 pub(crate) fn extract_node_status_id(topic: &str) -> Option<&str> {
-    let parts: Vec<&str> = topic.split('/').collect();
+    let parts: Vec<&str> = unprefixed(topic_prefix(), topic)?.split('/').collect();
     if parts.len() == 3 && parts[0] == "status" && parts[1] == "node" {
         Some(parts[2])
     } else {
@@ -52,16 +363,869 @@ pub(crate) fn extract_node_status_id(topic: &str) -> Option<&str> {
     }
 }
 
-/// Parse an "ON"/"OFF" payload into a bool (case-insensitive, trims whitespace).
-pub(crate) fn parse_valve_command(payload: &[u8]) -> Result<bool, String> {
-    let s = String::from_utf8_lossy(payload).trim().to_uppercase();
+/// Extract zone_id from "advice/<zone_id>/response".
+pub(crate) fn extract_advice_zone_id(topic: &str) -> Option<&str> {
+    let parts: Vec<&str> = unprefixed(topic_prefix(), topic)?.split('/').collect();
+    if parts.len() == 3 && parts[0] == "advice" && parts[2] == "response" {
+        Some(parts[1])
+    } else {
+        None
+    }
+}
+
+/// Extract zone_id from "flow/<zone_id>/reading".
+pub(crate) fn extract_flow_zone_id(topic: &str) -> Option<&str> {
+    let parts: Vec<&str> = unprefixed(topic_prefix(), topic)?.split('/').collect();
+    if parts.len() == 3 && parts[0] == "flow" && parts[2] == "reading" {
+        Some(parts[1])
+    } else {
+        None
+    }
+}
+
+/// Extract source_id from "temp/<source_id>/reading".
+pub(crate) fn extract_temp_source_id(topic: &str) -> Option<&str> {
+    let parts: Vec<&str> = unprefixed(topic_prefix(), topic)?.split('/').collect();
+    if parts.len() == 3 && parts[0] == "temp" && parts[2] == "reading" {
+        Some(parts[1])
+    } else {
+        None
+    }
+}
+
+/// Extract source_id from "pressure/<source_id>/reading".
+pub(crate) fn extract_pressure_source_id(topic: &str) -> Option<&str> {
+    let parts: Vec<&str> = unprefixed(topic_prefix(), topic)?.split('/').collect();
+    if parts.len() == 3 && parts[0] == "pressure" && parts[2] == "reading" {
+        Some(parts[1])
+    } else {
+        None
+    }
+}
+
+/// Extract source_id from "weather/<source_id>/reading".
+pub(crate) fn extract_weather_source_id(topic: &str) -> Option<&str> {
+    let parts: Vec<&str> = unprefixed(topic_prefix(), topic)?.split('/').collect();
+    if parts.len() == 3 && parts[0] == "weather" && parts[2] == "reading" {
+        Some(parts[1])
+    } else {
+        None
+    }
+}
+
+/// Extract node_id from "diag/<node_id>/logs".
+pub(crate) fn extract_node_logs_id(topic: &str) -> Option<&str> {
+    let parts: Vec<&str> = unprefixed(topic_prefix(), topic)?.split('/').collect();
+    if parts.len() == 3 && parts[0] == "diag" && parts[2] == "logs" {
+        Some(parts[1])
+    } else {
+        None
+    }
+}
+
+/// Extract node_id from "diag/<node_id>/pong".
+pub(crate) fn extract_node_pong_id(topic: &str) -> Option<&str> {
+    let parts: Vec<&str> = unprefixed(topic_prefix(), topic)?.split('/').collect();
+    if parts.len() == 3 && parts[0] == "diag" && parts[2] == "pong" {
+        Some(parts[1])
+    } else {
+        None
+    }
+}
+
+/// Extract node_id from "ota/<node_id>/status".
+pub(crate) fn extract_ota_report_node_id(topic: &str) -> Option<&str> {
+    let parts: Vec<&str> = unprefixed(topic_prefix(), topic)?.split('/').collect();
+    if parts.len() == 3 && parts[0] == "ota" && parts[2] == "status" {
+        Some(parts[1])
+    } else {
+        None
+    }
+}
+
+/// Topic carrying the hub-pushed settings for `node_id`.
+pub(crate) fn node_settings_topic(node_id: &str) -> String {
+    topic(&format!("cfg/{node_id}/set"))
+}
+
+/// Topic an update for `node_id` is announced on (retained).
+pub(crate) fn node_ota_topic(node_id: &str) -> String {
+    topic(&format!("cfg/{node_id}/ota"))
+}
+
+/// Topic the mock valve board mirrors `zone_id`'s writes to in
+/// hardware-in-the-loop mode.  Payload is `open` / `close` (retained).
+pub(crate) fn sim_valve_topic(zone_id: &str) -> String {
+    topic(&format!("sim/valve/{zone_id}"))
+}
+
+/// Topic `command` is sent to `node_id` on.
+pub(crate) fn node_command_topic(node_id: &str, command: NodeCommand) -> String {
+    topic(&format!("cmd/{node_id}/{}", command.as_str()))
+}
+
+/// Command topic for `zone_id`'s valve.
+pub(crate) fn valve_set_topic(zone_id: &str) -> String {
+    topic(&format!("valve/{zone_id}/set"))
+}
+
+/// Topic the advisor strategy publishes zone context to.
+pub(crate) fn advice_request_topic(zone_id: &str) -> String {
+    topic(&format!("advice/{zone_id}/request"))
+}
+
+/// JSON form of a valve command on `valve/<zone_id>/set`:
+/// `{"state":"ON","ts":...,"ttl_sec":60,"duration_sec":120}`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ValveCommandMsg {
+    state: String,
+    /// When the command was issued (unix seconds).
+    #[serde(default)]
+    ts: Option<i64>,
+    /// Drop the command if it arrives more than this long after `ts`.
+    #[serde(default)]
+    ttl_sec: Option<i64>,
+    /// Close the valve again after this long (`ON` only).
+    #[serde(default)]
+    duration_sec: Option<i64>,
+}
+
+/// A parsed valve command.  The plain `ON`/`OFF` form has no timestamp,
+/// TTL or duration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ValveCommand {
+    pub(crate) on: bool,
+    pub(crate) ts: Option<i64>,
+    pub(crate) ttl_sec: Option<i64>,
+    pub(crate) duration_sec: Option<i64>,
+}
+
+/// How far ahead of the hub's clock a sender's timestamp may be.
+pub(crate) const MAX_CLOCK_SKEW_SEC: i64 = 5;
+
+impl ValveCommand {
+    /// Reject a command that arrived after its TTL ran out, or whose `ts`
+    /// is ahead of the hub's clock (it would never expire).
+    pub(crate) fn check_fresh(&self, now: i64) -> Result<(), Reject> {
+        if let Some(ts) = self.ts.filter(|&ts| ts > now + MAX_CLOCK_SKEW_SEC) {
+            return Err(Reject::invalid(
+                PayloadKind::ValveCommand,
+                "ts",
+                format!("command issued {}s in the future", ts - now),
+            ));
+        }
+        if let (Some(ts), Some(ttl)) = (self.ts, self.ttl_sec) {
+            let age = now - ts;
+            if age > ttl {
+                return Err(Reject::invalid(
+                    PayloadKind::ValveCommand,
+                    "ts",
+                    format!("command expired: issued {age}s ago, ttl {ttl}s"),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A redelivered `ON` is dropped if it arrives this long after the first
+/// copy at most.
+pub(crate) const VALVE_COMMAND_DEDUP_WINDOW: Duration = Duration::from_secs(30);
+
+/// Spots valve `ON` commands redelivered by the broker.  With QoS 1 and a
+/// persistent session, a command whose ack was lost is sent again after a
+/// reconnect with the DUP flag set and the same packet id; acting on it
+/// would re-open the valve and count a second pulse.  Only such a
+/// redelivery is dropped: a sender's retry is a new publish and is always
+/// acted on, even if it repeats an `ON` the hub refused or a valve the hub
+/// has since closed.  `OFF` is always acted on and ends the zone's window.
+#[derive(Default)]
+pub(crate) struct ValveCommandDedup {
+    last_on: HashMap<String, (u16, Vec<u8>, Instant)>,
+}
+
+impl ValveCommandDedup {
+    /// Whether `payload`, received with packet id `pkid` and the DUP flag
+    /// `dup`, redelivers the zone's last `ON` within
+    /// [`VALVE_COMMAND_DEDUP_WINDOW`].  Unparseable payloads are never
+    /// duplicates, so the handler still rejects them.
+    pub(crate) fn is_duplicate(
+        &mut self,
+        zone_id: &str,
+        payload: &[u8],
+        pkid: u16,
+        dup: bool,
+        now: Instant,
+    ) -> bool {
+        match parse_valve_command(payload) {
+            Ok(c) if c.on => {}
+            Ok(_) => {
+                self.last_on.remove(zone_id);
+                return false;
+            }
+            Err(_) => return false,
+        }
+        let payload = payload.trim_ascii();
+        let duplicate = dup
+            && self.last_on.get(zone_id).is_some_and(|(id, last, at)| {
+                *id == pkid
+                    && last == payload
+                    && now.duration_since(*at) < VALVE_COMMAND_DEDUP_WINDOW
+            });
+        if !duplicate {
+            self.last_on
+                .insert(zone_id.to_string(), (pkid, payload.to_vec(), now));
+        }
+        duplicate
+    }
+}
+
+fn parse_valve_state(kind: PayloadKind, s: &str) -> Result<bool, Reject> {
+    let s = s.trim().to_uppercase();
     match s.as_str() {
         "ON" => Ok(true),
         "OFF" => Ok(false),
-        _ => Err(format!("unknown valve command '{s}'")),
+        _ => Err(Reject::new(
+            kind,
+            RejectReason::InvalidValue,
+            None,
+            format!("unknown valve command '{s}' (expected ON/OFF)"),
+        )),
+    }
+}
+
+/// Parse a valve command: "ON"/"OFF" (case-insensitive, trims whitespace),
+/// or the JSON form with an optional timestamp, TTL and duration.
+pub(crate) fn parse_valve_command(payload: &[u8]) -> Result<ValveCommand, Reject> {
+    let kind = PayloadKind::ValveCommand;
+    let text = String::from_utf8_lossy(payload);
+    if !text.trim_start().starts_with('{') {
+        return parse_valve_state(kind, &text).map(|on| ValveCommand {
+            on,
+            ts: None,
+            ttl_sec: None,
+            duration_sec: None,
+        });
+    }
+    let msg: ValveCommandMsg = decode_json(kind, payload)?;
+    let on = parse_valve_state(kind, &msg.state).map_err(|mut r| {
+        r.field = Some("state".into());
+        r
+    })?;
+    for (field, value) in [
+        ("ts", msg.ts),
+        ("ttl_sec", msg.ttl_sec),
+        ("duration_sec", msg.duration_sec),
+    ] {
+        if let Some(v) = value.filter(|v| *v <= 0) {
+            return Err(Reject::invalid(
+                kind,
+                field,
+                format!("must be positive, got {v}"),
+            ));
+        }
+    }
+    if msg.ttl_sec.is_some() && msg.ts.is_none() {
+        return Err(Reject::new(
+            kind,
+            RejectReason::MissingField,
+            Some("ts".into()),
+            "ttl_sec needs the command's ts".into(),
+        ));
+    }
+    if !on && msg.duration_sec.is_some() {
+        return Err(Reject::invalid(
+            kind,
+            "duration_sec",
+            "only valid with ON".into(),
+        ));
+    }
+    Ok(ValveCommand {
+        on,
+        ts: msg.ts,
+        ttl_sec: msg.ttl_sec,
+        duration_sec: msg.duration_sec,
+    })
+}
+
+/// A hub or node status on `status/hub` / `status/node/<node_id>`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct NodeStatus {
+    pub(crate) online: bool,
+    /// Present when the announcement carried the build.
+    pub(crate) firmware: Option<Firmware>,
+}
+
+/// JSON form of a status: `{"status":"online","version":"0.2.0","git":"1a2b3c4d"}`.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct StatusJson {
+    status: String,
+    version: String,
+    git: String,
+}
+
+/// Retained payload announcing `firmware` online.  The last will stays a
+/// bare `offline`.
+pub(crate) fn online_payload(firmware: &Firmware) -> Vec<u8> {
+    serde_json::to_vec(&StatusJson {
+        status: "online".to_string(),
+        version: firmware.version.clone(),
+        git: firmware.git.clone(),
+    })
+    .expect("status serialization failed")
+}
+
+/// Parse a status: a bare "online"/"offline" (case-insensitive, trims
+/// whitespace), or the JSON form carrying the build.
+pub(crate) fn parse_node_status(payload: &[u8]) -> Result<NodeStatus, Reject> {
+    let kind = PayloadKind::NodeStatus;
+    let trimmed = String::from_utf8_lossy(payload).trim().to_string();
+    let (s, firmware) = if trimmed.starts_with('{') {
+        let msg: StatusJson = decode_json(kind, payload)?;
+        if msg.version.trim().is_empty() {
+            return Err(Reject::invalid(
+                kind,
+                "version",
+                "must not be empty".to_string(),
+            ));
+        }
+        let firmware = Firmware {
+            version: msg.version,
+            git: msg.git,
+        };
+        (msg.status.to_lowercase(), Some(firmware))
+    } else {
+        (trimmed.to_lowercase(), None)
+    };
+    match s.as_str() {
+        "online" => Ok(NodeStatus {
+            online: true,
+            firmware,
+        }),
+        "offline" => Ok(NodeStatus {
+            online: false,
+            firmware,
+        }),
+        _ => Err(Reject::new(
+            PayloadKind::NodeStatus,
+            RejectReason::InvalidValue,
+            None,
+            format!("unknown node status '{s}' (expected online/offline)"),
+        )),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Payload validation
+// ---------------------------------------------------------------------------
+
+/// Maximum JSON (or CBOR) payload size (4 KiB). Anything larger is likely
+/// malicious or a bug — a normal reading message is a few hundred bytes.
+pub(crate) const MAX_JSON_PAYLOAD_BYTES: usize = 4096;
+
+/// Maximum size of a node's `diag/<node_id>/logs` message (64 KiB): up to
+/// [`MAX_NODE_LOG_LINES`] formatted log lines.
+pub(crate) const MAX_NODE_LOGS_PAYLOAD_BYTES: usize = 64 * 1024;
+
+/// Most log lines accepted in one `diag/<node_id>/logs` message.
+pub(crate) const MAX_NODE_LOG_LINES: usize = 200;
+
+/// Maximum number of sensor readings in a single telemetry message.
+pub(crate) const MAX_READINGS_PER_MESSAGE: usize = 32;
+
+/// How far behind the hub's clock telemetry may be stamped (7 days, beyond
+/// any node's offline buffer).
+pub(crate) const MAX_BACKFILL_SEC: i64 = 7 * 86_400;
+
+/// Wire encoding of a telemetry payload, chosen by the node and signalled
+/// by the topic (`tele/<node_id>/reading[/cbor]`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PayloadEncoding {
+    Json,
+    Cbor,
+}
+
+/// Inbound payload types, for rejection reporting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum PayloadKind {
+    Telemetry,
+    ValveCommand,
+    NodeStatus,
+    Advice,
+    Flow,
+    Temperature,
+    Pressure,
+    Weather,
+    NodeLogs,
+    NodePong,
+    OtaReport,
+}
+
+impl PayloadKind {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Telemetry => "telemetry",
+            Self::ValveCommand => "valve_command",
+            Self::NodeStatus => "node_status",
+            Self::Advice => "advice",
+            Self::Flow => "flow",
+            Self::Temperature => "temperature",
+            Self::Pressure => "pressure",
+            Self::Weather => "weather",
+            Self::NodeLogs => "node_logs",
+            Self::NodePong => "node_pong",
+            Self::OtaReport => "ota_report",
+        }
+    }
+}
+
+/// Why an inbound payload was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum RejectReason {
+    /// Over [`MAX_JSON_PAYLOAD_BYTES`] (or the payload type's own limit).
+    TooLarge,
+    /// Not parseable JSON.
+    Malformed,
+    MissingField,
+    UnknownField,
+    /// A field of the wrong JSON type.
+    WrongType,
+    /// Well-typed, but outside the accepted range or set of values.
+    InvalidValue,
+}
+
+impl RejectReason {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::TooLarge => "too_large",
+            Self::Malformed => "malformed",
+            Self::MissingField => "missing_field",
+            Self::UnknownField => "unknown_field",
+            Self::WrongType => "wrong_type",
+            Self::InvalidValue => "invalid_value",
+        }
+    }
+}
+
+/// An inbound payload that failed validation.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Reject {
+    pub(crate) kind: PayloadKind,
+    pub(crate) reason: RejectReason,
+    /// Path of the offending field, e.g. `readings[2].raw`.
+    pub(crate) field: Option<String>,
+    pub(crate) detail: String,
+}
+
+impl Reject {
+    pub(crate) fn new(
+        kind: PayloadKind,
+        reason: RejectReason,
+        field: Option<String>,
+        detail: String,
+    ) -> Self {
+        Self {
+            kind,
+            reason,
+            field,
+            detail,
+        }
+    }
+
+    fn invalid(kind: PayloadKind, field: impl Into<String>, detail: String) -> Self {
+        Self::new(kind, RejectReason::InvalidValue, Some(field.into()), detail)
+    }
+}
+
+impl fmt::Display for Reject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.kind.as_str(), self.reason.as_str())?;
+        if let Some(field) = &self.field {
+            write!(f, " at {field}")?;
+        }
+        write!(f, ": {}", self.detail)
     }
 }
 
+/// Deserialize a JSON payload strictly: oversized payloads, unknown or
+/// missing fields and wrong types are all rejected, naming the field.
+fn decode_json<T: DeserializeOwned>(kind: PayloadKind, payload: &[u8]) -> Result<T, Reject> {
+    decode_json_limited(kind, payload, MAX_JSON_PAYLOAD_BYTES)
+}
+
+/// Deserialize a CBOR payload with the same rules (and field paths) as
+/// [`decode_json`]: it is transcoded to a JSON value first.
+fn decode_cbor<T: DeserializeOwned>(kind: PayloadKind, payload: &[u8]) -> Result<T, Reject> {
+    check_size(kind, payload, MAX_JSON_PAYLOAD_BYTES)?;
+    let value: serde_json::Value = ciborium::from_reader(payload).map_err(|e| {
+        Reject::new(
+            kind,
+            RejectReason::Malformed,
+            None,
+            format!("invalid CBOR: {e}"),
+        )
+    })?;
+    serde_path_to_error::deserialize(value).map_err(|e| {
+        let path = e.path().to_string();
+        json_reject(kind, path, e.into_inner())
+    })
+}
+
+fn check_size(kind: PayloadKind, payload: &[u8], limit: usize) -> Result<(), Reject> {
+    if payload.len() > limit {
+        return Err(Reject::new(
+            kind,
+            RejectReason::TooLarge,
+            None,
+            format!("{} bytes exceeds {limit} limit", payload.len()),
+        ));
+    }
+    Ok(())
+}
+
+/// [`decode_json`] with a size limit other than [`MAX_JSON_PAYLOAD_BYTES`].
+fn decode_json_limited<T: DeserializeOwned>(
+    kind: PayloadKind,
+    payload: &[u8],
+    limit: usize,
+) -> Result<T, Reject> {
+    check_size(kind, payload, limit)?;
+    let de = &mut serde_json::Deserializer::from_slice(payload);
+    serde_path_to_error::deserialize(de).map_err(|e| {
+        let path = e.path().to_string();
+        json_reject(kind, path, e.into_inner())
+    })
+}
+
+/// Classify a deserialization error at `path` (see [`decode_json`]).
+fn json_reject(kind: PayloadKind, path: String, inner: serde_json::Error) -> Reject {
+    // serde_json appends " at line L column C"; the field path is more
+    // useful for a single-line payload.
+    let full = inner.to_string();
+    let message = full
+        .rsplit_once(" at line ")
+        .map_or(full.as_str(), |(m, _)| m)
+        .to_string();
+    let reason = match inner.classify() {
+        serde_json::error::Category::Data => {
+            if message.starts_with("missing field") {
+                RejectReason::MissingField
+            } else if message.starts_with("unknown field") {
+                RejectReason::UnknownField
+            } else if message.starts_with("invalid type") {
+                RejectReason::WrongType
+            } else {
+                RejectReason::InvalidValue
+            }
+        }
+        _ => RejectReason::Malformed,
+    };
+    let mut field = (reason != RejectReason::Malformed && path != ".").then_some(path);
+    if reason == RejectReason::MissingField {
+        // The path points at the enclosing object; name the field too.
+        if let Some(name) = message.split('`').nth(1) {
+            field = Some(match field {
+                Some(parent) => format!("{parent}.{name}"),
+                None => name.to_string(),
+            });
+        }
+    }
+    Reject::new(kind, reason, field, message)
+}
+
+/// Decode and validate a telemetry message from `tele/<node_id>/reading`.
+pub(crate) fn parse_telemetry(payload: &[u8]) -> Result<ReadingMsg, Reject> {
+    validate_telemetry(decode_json(PayloadKind::Telemetry, payload)?)
+}
+
+/// [`parse_telemetry`] for either encoding.
+pub(crate) fn parse_telemetry_as(
+    encoding: PayloadEncoding,
+    payload: &[u8],
+) -> Result<ReadingMsg, Reject> {
+    match encoding {
+        PayloadEncoding::Json => parse_telemetry(payload),
+        PayloadEncoding::Cbor => validate_telemetry(decode_cbor(PayloadKind::Telemetry, payload)?),
+    }
+}
+
+fn validate_telemetry(msg: ReadingMsg) -> Result<ReadingMsg, Reject> {
+    let kind = PayloadKind::Telemetry;
+    if msg.ts <= 0 {
+        return Err(Reject::invalid(
+            kind,
+            "ts",
+            format!("must be positive, got {}", msg.ts),
+        ));
+    }
+    if msg.readings.len() > MAX_READINGS_PER_MESSAGE {
+        return Err(Reject::invalid(
+            kind,
+            "readings",
+            format!(
+                "{} readings exceeds {MAX_READINGS_PER_MESSAGE} limit",
+                msg.readings.len()
+            ),
+        ));
+    }
+    for (i, r) in msg.readings.iter().enumerate() {
+        if r.sensor_id.is_empty() || r.sensor_id.contains('/') {
+            return Err(Reject::invalid(
+                kind,
+                format!("readings[{i}].sensor_id"),
+                format!("must be non-empty without '/', got '{}'", r.sensor_id),
+            ));
+        }
+        if let Some(sd) = r.raw_stddev.filter(|sd| !sd.is_finite() || *sd < 0.0) {
+            return Err(Reject::invalid(
+                kind,
+                format!("readings[{i}].raw_stddev"),
+                format!("must be a non-negative number, got {sd}"),
+            ));
+        }
+        if let Some(mv) = r.mv.filter(|mv| !mv.is_finite() || *mv < 0.0) {
+            return Err(Reject::invalid(
+                kind,
+                format!("readings[{i}].mv"),
+                format!("must be a non-negative number, got {mv}"),
+            ));
+        }
+    }
+    let ranges = [
+        ("air_temp_c", msg.air_temp_c, TEMP_RANGE_C),
+        ("humidity_pct", msg.humidity_pct, 0.0..=100.0),
+        ("lux", msg.lux, LUX_RANGE),
+    ];
+    for (field, value, range) in ranges {
+        if let Some(v) = value.filter(|v| !range.contains(v)) {
+            return Err(Reject::invalid(
+                kind,
+                field,
+                format!(
+                    "must be within {}..={}, got {v}",
+                    range.start(),
+                    range.end()
+                ),
+            ));
+        }
+    }
+    Ok(msg)
+}
+
+/// Hold telemetry to the hub's clock (`now`, unix seconds): a message
+/// stamped more than [`MAX_CLOCK_SKEW_SEC`] ahead or [`MAX_BACKFILL_SEC`]
+/// behind is rejected, and batched readings outside that window are
+/// dropped rather than the whole message, since a replayed batch isn't
+/// sent again.  Returns how many readings were dropped.
+pub(crate) fn check_telemetry_clock(msg: &mut ReadingMsg, now: i64) -> Result<usize, Reject> {
+    check_clock_window(PayloadKind::Telemetry, msg.ts, now)?;
+    let window = now - MAX_BACKFILL_SEC..=now + MAX_CLOCK_SKEW_SEC;
+    let before = msg.readings.len();
+    msg.readings
+        .retain(|r| r.ts.is_none_or(|ts| window.contains(&ts)));
+    Ok(before - msg.readings.len())
+}
+
+/// Reject a `ts` more than [`MAX_CLOCK_SKEW_SEC`] ahead of the hub's clock
+/// (`now`, unix seconds) or more than [`MAX_BACKFILL_SEC`] behind it.
+fn check_clock_window(kind: PayloadKind, ts: i64, now: i64) -> Result<(), Reject> {
+    if (now - MAX_BACKFILL_SEC..=now + MAX_CLOCK_SKEW_SEC).contains(&ts) {
+        return Ok(());
+    }
+    Err(Reject::invalid(
+        kind,
+        "ts",
+        format!(
+            "must be within {MAX_BACKFILL_SEC}s before to {MAX_CLOCK_SKEW_SEC}s after the hub's clock ({now}), got {ts}"
+        ),
+    ))
+}
+
+/// Decode an advisor response from `advice/<zone_id>/response`.
+pub(crate) fn parse_advice(payload: &[u8]) -> Result<AdviceMsg, Reject> {
+    decode_json(PayloadKind::Advice, payload)
+}
+
+/// Light levels a BH1750 can report at its shortest measurement time.
+const LUX_RANGE: std::ops::RangeInclusive<f64> = 0.0..=125_000.0;
+
+/// Plausible outdoor temperatures (°C); anything outside is a sensor fault.
+const TEMP_RANGE_C: std::ops::RangeInclusive<f64> = -60.0..=70.0;
+
+/// Decode and validate an outdoor temperature from `temp/<source_id>/reading`.
+/// `ts` is held to the hub's clock (`now`): the frost lockout ignores
+/// readings older than its latest, so one stamped in the future would
+/// shut out every real one after it.
+pub(crate) fn parse_temperature(payload: &[u8], now: i64) -> Result<TempMsg, Reject> {
+    let kind = PayloadKind::Temperature;
+    let msg: TempMsg = decode_json(kind, payload)?;
+    check_clock_window(kind, msg.ts, now)?;
+    if !TEMP_RANGE_C.contains(&msg.temp_c) {
+        return Err(Reject::invalid(
+            kind,
+            "temp_c",
+            format!(
+                "must be within {}..={} °C, got {}",
+                TEMP_RANGE_C.start(),
+                TEMP_RANGE_C.end(),
+                msg.temp_c
+            ),
+        ));
+    }
+    Ok(msg)
+}
+
+/// Highest plausible line pressure (kPa); above is a sensor fault.
+const MAX_PRESSURE_KPA: f64 = 2500.0;
+
+/// Decode and validate a line pressure from `pressure/<source_id>/reading`.
+/// `ts` is held to the hub's clock (`now`) for the same reason as
+/// [`parse_temperature`]: the pressure monitor drops anything older than
+/// its latest reading.
+pub(crate) fn parse_pressure(payload: &[u8], now: i64) -> Result<PressureMsg, Reject> {
+    let kind = PayloadKind::Pressure;
+    let msg: PressureMsg = decode_json(kind, payload)?;
+    check_clock_window(kind, msg.ts, now)?;
+    if !(0.0..=MAX_PRESSURE_KPA).contains(&msg.kpa) {
+        return Err(Reject::invalid(
+            kind,
+            "kpa",
+            format!("must be within 0..={MAX_PRESSURE_KPA} kPa, got {}", msg.kpa),
+        ));
+    }
+    Ok(msg)
+}
+
+/// Decode and validate a weather sample from `weather/<source_id>/reading`.
+pub(crate) fn parse_weather(payload: &[u8]) -> Result<WeatherMsg, Reject> {
+    let kind = PayloadKind::Weather;
+    let msg: WeatherMsg = decode_json(kind, payload)?;
+    if msg.ts <= 0 {
+        return Err(Reject::invalid(
+            kind,
+            "ts",
+            format!("must be positive, got {}", msg.ts),
+        ));
+    }
+    let ranges: [(&str, Option<f64>, std::ops::RangeInclusive<f64>); 6] = [
+        ("temp_c", msg.temp_c, TEMP_RANGE_C),
+        ("humidity_pct", msg.humidity_pct, 0.0..=100.0),
+        ("solar_w_m2", msg.solar_w_m2, 0.0..=1500.0),
+        ("wind_m_s", msg.wind_m_s, 0.0..=100.0),
+        ("rain_mm", msg.rain_mm, 0.0..=500.0),
+        ("et0_mm", msg.et0_mm, 0.0..=30.0),
+    ];
+    for (field, value, range) in ranges {
+        if let Some(v) = value.filter(|v| !range.contains(v)) {
+            return Err(Reject::invalid(
+                kind,
+                field,
+                format!(
+                    "must be within {}..={}, got {v}",
+                    range.start(),
+                    range.end()
+                ),
+            ));
+        }
+    }
+    Ok(msg)
+}
+
+/// Decode and validate a flow-meter sample from `flow/<zone_id>/reading`.
+pub(crate) fn parse_flow(payload: &[u8]) -> Result<FlowMsg, Reject> {
+    let kind = PayloadKind::Flow;
+    let msg: FlowMsg = decode_json(kind, payload)?;
+    if msg.ts <= 0 {
+        return Err(Reject::invalid(
+            kind,
+            "ts",
+            format!("must be positive, got {}", msg.ts),
+        ));
+    }
+    if !msg.lpm.is_finite() || msg.lpm < 0.0 {
+        return Err(Reject::invalid(
+            kind,
+            "lpm",
+            format!("must be a non-negative number, got {}", msg.lpm),
+        ));
+    }
+    Ok(msg)
+}
+
+/// Decode and validate a node's update report from `ota/<node_id>/status`.
+pub(crate) fn parse_ota_report(payload: &[u8]) -> Result<OtaReportMsg, Reject> {
+    let kind = PayloadKind::OtaReport;
+    let msg: OtaReportMsg = decode_json(kind, payload)?;
+    if msg.ts <= 0 {
+        return Err(Reject::invalid(
+            kind,
+            "ts",
+            format!("must be positive, got {}", msg.ts),
+        ));
+    }
+    if !matches!(
+        msg.state.as_str(),
+        "running" | "downloading" | "installed" | "failed"
+    ) {
+        return Err(Reject::invalid(
+            kind,
+            "state",
+            format!(
+                "must be running, downloading, installed or failed, got '{}'",
+                msg.state
+            ),
+        ));
+    }
+    if msg.version.trim().is_empty() {
+        return Err(Reject::invalid(
+            kind,
+            "version",
+            "must not be empty".to_string(),
+        ));
+    }
+    Ok(msg)
+}
+
+/// Decode and validate a node's answer to `ping` from `diag/<node_id>/pong`.
+pub(crate) fn parse_node_pong(payload: &[u8]) -> Result<NodePongMsg, Reject> {
+    let kind = PayloadKind::NodePong;
+    let msg: NodePongMsg = decode_json(kind, payload)?;
+    if msg.ts <= 0 {
+        return Err(Reject::invalid(
+            kind,
+            "ts",
+            format!("must be positive, got {}", msg.ts),
+        ));
+    }
+    Ok(msg)
+}
+
+/// Decode and validate a node's log lines from `diag/<node_id>/logs`.
+pub(crate) fn parse_node_logs(payload: &[u8]) -> Result<NodeLogsMsg, Reject> {
+    let kind = PayloadKind::NodeLogs;
+    let msg: NodeLogsMsg = decode_json_limited(kind, payload, MAX_NODE_LOGS_PAYLOAD_BYTES)?;
+    if msg.ts <= 0 {
+        return Err(Reject::invalid(
+            kind,
+            "ts",
+            format!("must be positive, got {}", msg.ts),
+        ));
+    }
+    if msg.lines.len() > MAX_NODE_LOG_LINES {
+        return Err(Reject::invalid(
+            kind,
+            "lines",
+            format!(
+                "{} lines exceeds {MAX_NODE_LOG_LINES} limit",
+                msg.lines.len()
+            ),
+        ));
+    }
+    Ok(msg)
+}
+
 // ===========================================================================
 // Tests
 // ===========================================================================
@@ -183,32 +1347,272 @@ mod tests {
         assert_eq!(extract_node_status_id("status/hub"), None);
     }
 
+    // -- extract_advice_zone_id ---------------------------------------------
+
+    #[test]
+    fn extract_advice_zone_id_valid_topic() {
+        assert_eq!(
+            extract_advice_zone_id("advice/zone1/response"),
+            Some("zone1")
+        );
+    }
+
+    #[test]
+    fn extract_advice_zone_id_rejects_request_topic() {
+        // The hub's own requests must not loop back as advice.
+        assert_eq!(extract_advice_zone_id("advice/zone1/request"), None);
+        assert_eq!(extract_advice_zone_id("valve/zone1/response"), None);
+    }
+
+    // -- extract_flow_zone_id -----------------------------------------------
+
+    #[test]
+    fn extract_flow_zone_id_valid_topic() {
+        assert_eq!(extract_flow_zone_id("flow/zone1/reading"), Some("zone1"));
+        assert_eq!(extract_flow_zone_id("tele/zone1/reading"), None);
+        assert_eq!(extract_flow_zone_id("flow/zone1"), None);
+    }
+
+    // -- node diagnostics ----------------------------------------------------
+
+    #[test]
+    fn extract_node_logs_id_valid_topic() {
+        assert_eq!(extract_node_logs_id("diag/node-a/logs"), Some("node-a"));
+        assert_eq!(extract_node_logs_id("diag/node-a/status"), None);
+        assert_eq!(extract_node_logs_id("cmd/node-a/logs"), None);
+    }
+
+    #[test]
+    fn ota_report_topics_and_payloads() {
+        assert_eq!(
+            extract_ota_report_node_id("ota/node-a/status"),
+            Some("node-a")
+        );
+        assert_eq!(extract_ota_report_node_id("cfg/node-a/ota"), None);
+        assert_eq!(node_ota_topic("node-a"), "cfg/node-a/ota");
+
+        let msg = parse_ota_report(
+            br#"{"ts":1700000000,"state":"failed","version":"0.2.0","error":"sha256 mismatch"}"#,
+        )
+        .unwrap();
+        assert_eq!(msg.state, "failed");
+        assert_eq!(msg.error.as_deref(), Some("sha256 mismatch"));
+        let err = parse_ota_report(br#"{"ts":1700000000,"state":"flashing","version":"0.2.0"}"#)
+            .unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("state"));
+        assert_eq!(err.kind, PayloadKind::OtaReport);
+        assert!(parse_ota_report(br#"{"ts":1700000000,"state":"running","version":""}"#).is_err());
+    }
+
+    #[test]
+    fn node_command_topics_and_payloads() {
+        assert_eq!(
+            node_command_topic("node-a", NodeCommand::Restart),
+            "cmd/node-a/restart"
+        );
+        let logs = NodeCommand::SendLogs { lines: 20 };
+        assert_eq!(node_command_topic("node-a", logs), "cmd/node-a/send-logs");
+        assert_eq!(logs.payload(), br#"{"lines":20}"#);
+        assert!(NodeCommand::Restart.payload().is_empty());
+        let ping = NodeCommand::Ping { sent_ms: 17 };
+        assert_eq!(node_command_topic("node-a", ping), "cmd/node-a/ping");
+        assert_eq!(ping.payload(), br#"{"sent_ms":17}"#);
+    }
+
+    #[test]
+    fn node_pong_topic_and_payload() {
+        assert_eq!(extract_node_pong_id("diag/node-a/pong"), Some("node-a"));
+        assert_eq!(extract_node_pong_id("diag/node-a/logs"), None);
+        let msg = parse_node_pong(
+            br#"{"ts":1700000000,"sent_ms":17,"uptime_sec":3600,"sample_interval_sec":300,"last_sample_ts":1699999950,"buffered":0}"#,
+        )
+        .unwrap();
+        assert_eq!((msg.sent_ms, msg.uptime_sec), (Some(17), 3600));
+        assert_eq!(msg.last_sample_ts, Some(1_699_999_950));
+        let err =
+            parse_node_pong(br#"{"ts":0,"uptime_sec":1,"sample_interval_sec":300,"buffered":0}"#)
+                .unwrap_err();
+        assert_eq!(err.kind, PayloadKind::NodePong);
+        assert!(parse_node_pong(br#"{"ts":1}"#).is_err());
+    }
+
+    #[test]
+    fn parse_node_logs_limits() {
+        let msg = parse_node_logs(br#"{"ts":1700000000,"lines":["a","b"]}"#).unwrap();
+        assert_eq!(msg.lines, ["a", "b"]);
+
+        let too_many = serde_json::json!({ "ts": 1700000000, "lines": vec!["x"; 201] });
+        let err = parse_node_logs(too_many.to_string().as_bytes()).unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("lines"));
+
+        // Larger than the general JSON limit, within the logs limit.
+        let long = serde_json::json!({ "ts": 1700000000, "lines": ["y".repeat(8000)] });
+        assert!(parse_node_logs(long.to_string().as_bytes()).is_ok());
+
+        let huge = vec![b' '; MAX_NODE_LOGS_PAYLOAD_BYTES + 1];
+        let err = parse_node_logs(&huge).unwrap_err();
+        assert_eq!(err.reason, RejectReason::TooLarge);
+        assert_eq!(err.kind, PayloadKind::NodeLogs);
+    }
+
     // -- parse_valve_command ------------------------------------------------
 
+    fn valve_on(payload: &[u8]) -> Result<bool, Reject> {
+        parse_valve_command(payload).map(|c| c.on)
+    }
+
     #[test]
     fn parse_valve_command_on_uppercase() {
-        assert_eq!(parse_valve_command(b"ON"), Ok(true));
+        assert_eq!(valve_on(b"ON"), Ok(true));
     }
 
     #[test]
     fn parse_valve_command_off_uppercase() {
-        assert_eq!(parse_valve_command(b"OFF"), Ok(false));
+        assert_eq!(valve_on(b"OFF"), Ok(false));
     }
 
     #[test]
     fn parse_valve_command_on_lowercase() {
-        assert_eq!(parse_valve_command(b"on"), Ok(true));
+        assert_eq!(valve_on(b"on"), Ok(true));
     }
 
     #[test]
     fn parse_valve_command_off_mixed_case() {
-        assert_eq!(parse_valve_command(b"oFf"), Ok(false));
+        assert_eq!(valve_on(b"oFf"), Ok(false));
     }
 
     #[test]
     fn parse_valve_command_with_whitespace() {
-        assert_eq!(parse_valve_command(b"  ON  "), Ok(true));
-        assert_eq!(parse_valve_command(b"\tOFF\n"), Ok(false));
+        assert_eq!(valve_on(b"  ON  "), Ok(true));
+        assert_eq!(valve_on(b"\tOFF\n"), Ok(false));
+    }
+
+    #[test]
+    fn parse_valve_command_plain_has_no_extras() {
+        let cmd = parse_valve_command(b"ON").unwrap();
+        assert_eq!((cmd.ts, cmd.ttl_sec, cmd.duration_sec), (None, None, None));
+        assert!(cmd.check_fresh(i64::MAX).is_ok());
+    }
+
+    #[test]
+    fn parse_valve_command_json() {
+        let cmd = parse_valve_command(
+            br#" {"state":"on","ts":1700000000,"ttl_sec":60,"duration_sec":120}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            cmd,
+            ValveCommand {
+                on: true,
+                ts: Some(1_700_000_000),
+                ttl_sec: Some(60),
+                duration_sec: Some(120),
+            }
+        );
+        assert!(cmd.check_fresh(1_700_000_060).is_ok());
+        let expired = cmd.check_fresh(1_700_000_061).unwrap_err();
+        assert_eq!(expired.field.as_deref(), Some("ts"));
+        assert!(expired.detail.contains("expired"), "{expired}");
+
+        assert_eq!(valve_on(br#"{"state":"OFF"}"#), Ok(false));
+        // A timestamp without a TTL never expires.
+        let cmd = parse_valve_command(br#"{"state":"ON","ts":1}"#).unwrap();
+        assert!(cmd.check_fresh(1_700_000_000).is_ok());
+    }
+
+    #[test]
+    fn valve_command_from_the_future_is_rejected() {
+        let cmd = parse_valve_command(br#"{"state":"ON","ts":9999999999,"ttl_sec":1}"#).unwrap();
+        let rejected = cmd.check_fresh(1_700_000_000).unwrap_err();
+        assert_eq!(rejected.field.as_deref(), Some("ts"));
+        assert!(rejected.detail.contains("future"), "{rejected}");
+
+        // A few seconds of skew is tolerated.
+        let cmd = parse_valve_command(br#"{"state":"ON","ts":1700000005,"ttl_sec":1}"#).unwrap();
+        assert!(cmd.check_fresh(1_700_000_000).is_ok());
+        assert!(cmd.check_fresh(1_699_999_999).is_err());
+    }
+
+    #[test]
+    fn parse_valve_command_json_rejects() {
+        let cases: [(&[u8], RejectReason, &str); 6] = [
+            (
+                br#"{"state":"TOGGLE"}"#,
+                RejectReason::InvalidValue,
+                "state",
+            ),
+            (br#"{"ts":1}"#, RejectReason::MissingField, "state"),
+            (
+                br#"{"state":"ON","ttl_sec":60}"#,
+                RejectReason::MissingField,
+                "ts",
+            ),
+            (
+                br#"{"state":"ON","duration_sec":0}"#,
+                RejectReason::InvalidValue,
+                "duration_sec",
+            ),
+            (
+                br#"{"state":"OFF","duration_sec":30}"#,
+                RejectReason::InvalidValue,
+                "duration_sec",
+            ),
+            (
+                br#"{"state":"ON","pulse":1}"#,
+                RejectReason::UnknownField,
+                "pulse",
+            ),
+        ];
+        for (payload, reason, field) in cases {
+            let r = parse_valve_command(payload).unwrap_err();
+            let text = String::from_utf8_lossy(payload);
+            assert_eq!(r.reason, reason, "{text}");
+            assert_eq!(r.field.as_deref(), Some(field), "{text}");
+        }
+    }
+
+    #[test]
+    fn valve_command_dedup_drops_redelivered_on() {
+        let mut dedup = ValveCommandDedup::default();
+        let t0 = Instant::now();
+        assert!(!dedup.is_duplicate("z1", b"ON", 7, false, t0));
+        let t = t0 + Duration::from_secs(5);
+        assert!(dedup.is_duplicate("z1", b" ON\n", 7, true, t));
+        // Another zone has its own window.
+        assert!(!dedup.is_duplicate("z2", b"ON", 7, true, t0));
+        // The window runs from the first ON, not the repeats.
+        let late = t0 + VALVE_COMMAND_DEDUP_WINDOW;
+        assert!(!dedup.is_duplicate("z1", b"ON", 7, true, late));
+
+        // OFF is never dropped and ends the window.
+        let t1 = t0 + Duration::from_secs(100);
+        assert!(!dedup.is_duplicate("z1", b"ON", 8, false, t1));
+        assert!(!dedup.is_duplicate("z1", b"OFF", 9, false, t1));
+        assert!(!dedup.is_duplicate("z1", b"OFF", 9, true, t1));
+        assert!(!dedup.is_duplicate("z1", b"ON", 8, true, t1));
+
+        // A different command or packet id is not a redelivery.
+        let t2 = t1 + Duration::from_secs(100);
+        assert!(!dedup.is_duplicate("z1", br#"{"state":"ON","ts":1}"#, 10, false, t2));
+        assert!(dedup.is_duplicate("z1", br#"{"state":"ON","ts":1}"#, 10, true, t2));
+        assert!(!dedup.is_duplicate("z1", br#"{"state":"ON","ts":2}"#, 10, true, t2));
+        assert!(!dedup.is_duplicate("z1", br#"{"state":"ON","ts":2}"#, 11, true, t2));
+        assert!(!dedup.is_duplicate("z1", b"TOGGLE", 12, true, t2));
+    }
+
+    #[test]
+    fn valve_command_dedup_acts_on_retry_of_refused_on() {
+        // The hub refused the first ON (say an interlock was held); the
+        // sender's retry is a new publish and must reach the handler, as
+        // must a re-open after the watchdog closed the valve.
+        let mut dedup = ValveCommandDedup::default();
+        let t0 = Instant::now();
+        assert!(!dedup.is_duplicate("z1", b"ON", 1, false, t0));
+        let t = t0 + Duration::from_secs(2);
+        assert!(!dedup.is_duplicate("z1", b"ON", 2, false, t));
+        let water_now = br#"{"state":"ON","duration_sec":30}"#;
+        assert!(!dedup.is_duplicate("z1", water_now, 3, false, t));
+        assert!(!dedup.is_duplicate("z1", water_now, 4, false, t));
     }
 
     #[test]
@@ -248,10 +1652,448 @@ mod tests {
     }
 
     #[test]
-    fn reading_msg_deserialize_extra_fields_ignored() {
+    fn reading_msg_deserialize_extra_fields_rejected() {
         let json = r#"{"ts":1,"readings":[],"extra":"ignored"}"#;
-        let msg: ReadingMsg = serde_json::from_str(json).unwrap();
-        assert_eq!(msg.ts, 1);
-        assert!(msg.readings.is_empty());
+        assert!(serde_json::from_str::<ReadingMsg>(json).is_err());
+    }
+
+    #[test]
+    fn reading_msg_accepts_node_stddev() {
+        let json = r#"{"ts":1,"readings":[{"sensor_id":"s1","raw":1,"raw_stddev":2.5}]}"#;
+        let msg = parse_telemetry(json.as_bytes()).unwrap();
+        assert_eq!(msg.readings[0].raw_stddev, Some(2.5));
+    }
+
+    #[test]
+    fn reading_msg_accepts_millivolts() {
+        let json = r#"{"ts":1,"readings":[{"sensor_id":"s1","raw":16384,"mv":2048.0}]}"#;
+        let msg = parse_telemetry(json.as_bytes()).unwrap();
+        assert_eq!(msg.readings[0].mv, Some(2048.0));
+
+        let json = r#"{"ts":1,"readings":[{"sensor_id":"s1","raw":1,"mv":-5.0}]}"#;
+        assert_eq!(
+            reject(parse_telemetry(json.as_bytes())),
+            (RejectReason::InvalidValue, Some("readings[0].mv".into()))
+        );
+    }
+
+    #[test]
+    fn batched_readings_keep_their_own_ts() {
+        let json = r#"{"ts":1700003600,"readings":[
+            {"sensor_id":"s1","raw":20000,"ts":1700000000},
+            {"sensor_id":"s1","raw":20100,"ts":1700001800},
+            {"sensor_id":"s1","raw":20200}]}"#;
+        let msg = parse_telemetry(json.as_bytes()).unwrap();
+        let ts: Vec<i64> = msg.readings.iter().map(|r| r.ts_or(msg.ts)).collect();
+        assert_eq!(ts, vec![1_700_000_000, 1_700_001_800, 1_700_003_600]);
+    }
+
+    #[test]
+    fn telemetry_held_to_the_hub_clock() {
+        let now = 1_700_000_000;
+        let oldest = now - MAX_BACKFILL_SEC;
+        let json = format!(
+            r#"{{"ts":{now},"readings":[
+                {{"sensor_id":"s1","raw":1,"ts":{oldest}}},
+                {{"sensor_id":"s1","raw":2,"ts":{}}},
+                {{"sensor_id":"s1","raw":3,"ts":{}}},
+                {{"sensor_id":"s1","raw":4}}]}}"#,
+            oldest - 1,
+            now + MAX_CLOCK_SKEW_SEC + 1
+        );
+        let mut msg = parse_telemetry(json.as_bytes()).unwrap();
+        // Readings outside the window go; the rest of the batch stays.
+        assert_eq!(check_telemetry_clock(&mut msg, now), Ok(2));
+        let raw: Vec<i64> = msg.readings.iter().map(|r| r.raw).collect();
+        assert_eq!(raw, vec![1, 4]);
+
+        // A message stamped in the future or too long ago is rejected.
+        for ts in [now + MAX_CLOCK_SKEW_SEC + 1, oldest - 1, 1] {
+            let json = format!(r#"{{"ts":{ts},"readings":[]}}"#);
+            let mut msg = parse_telemetry(json.as_bytes()).unwrap();
+            let rejected = check_telemetry_clock(&mut msg, now).unwrap_err();
+            assert_eq!(rejected.field.as_deref(), Some("ts"));
+        }
+        let mut msg =
+            parse_telemetry(format!(r#"{{"ts":{},"readings":[]}}"#, now + 5).as_bytes()).unwrap();
+        assert_eq!(check_telemetry_clock(&mut msg, now), Ok(0));
+    }
+
+    #[test]
+    fn reading_msg_accepts_climate() {
+        let json = r#"{"ts":1,"readings":[],"air_temp_c":21.5,"humidity_pct":48.0}"#;
+        let msg = parse_telemetry(json.as_bytes()).unwrap();
+        assert_eq!(msg.air_temp_c, Some(21.5));
+        assert_eq!(msg.humidity_pct, Some(48.0));
+        let msg = parse_telemetry(br#"{"ts":1,"readings":[]}"#).unwrap();
+        assert_eq!((msg.air_temp_c, msg.humidity_pct), (None, None));
+
+        let json = r#"{"ts":1,"readings":[],"humidity_pct":104.0}"#;
+        assert_eq!(
+            reject(parse_telemetry(json.as_bytes())),
+            (RejectReason::InvalidValue, Some("humidity_pct".into()))
+        );
+        let json = r#"{"ts":1,"readings":[],"air_temp_c":-85.0}"#;
+        assert_eq!(
+            reject(parse_telemetry(json.as_bytes())),
+            (RejectReason::InvalidValue, Some("air_temp_c".into()))
+        );
+
+        let msg = parse_telemetry(br#"{"ts":1,"readings":[],"lux":41250.5}"#).unwrap();
+        assert_eq!(msg.lux, Some(41250.5));
+        assert_eq!(
+            reject(parse_telemetry(br#"{"ts":1,"readings":[],"lux":-3}"#)),
+            (RejectReason::InvalidValue, Some("lux".into()))
+        );
+    }
+
+    // -- payload validation ---------------------------------------------------
+
+    fn reject(r: Result<impl fmt::Debug, Reject>) -> (RejectReason, Option<String>) {
+        let e = r.unwrap_err();
+        (e.reason, e.field)
+    }
+
+    #[test]
+    fn telemetry_rejects_name_the_field() {
+        let cases: [(&str, RejectReason, Option<&str>); 7] = [
+            ("not json", RejectReason::Malformed, None),
+            (r#"{"ts":1}"#, RejectReason::MissingField, Some("readings")),
+            (
+                r#"{"ts":1,"readings":[{"sensor_id":"s1"}]}"#,
+                RejectReason::MissingField,
+                Some("readings[0].raw"),
+            ),
+            (
+                r#"{"ts":1,"readings":[{"sensor_id":"s1","raw":"12"}]}"#,
+                RejectReason::WrongType,
+                Some("readings[0].raw"),
+            ),
+            (
+                r#"{"ts":1,"readings":[{"sensor_id":"s1","raw":1,"volts":3.3}]}"#,
+                RejectReason::UnknownField,
+                Some("readings[0].volts"),
+            ),
+            (
+                r#"{"ts":0,"readings":[]}"#,
+                RejectReason::InvalidValue,
+                Some("ts"),
+            ),
+            (
+                r#"{"ts":1,"readings":[{"sensor_id":"a","raw":1},{"sensor_id":"a/b","raw":1}]}"#,
+                RejectReason::InvalidValue,
+                Some("readings[1].sensor_id"),
+            ),
+        ];
+        for (json, reason, field) in cases {
+            assert_eq!(
+                reject(parse_telemetry(json.as_bytes())),
+                (reason, field.map(String::from)),
+                "{json}"
+            );
+        }
+    }
+
+    fn cbor(value: serde_json::Value) -> Vec<u8> {
+        let mut buf = Vec::new();
+        ciborium::into_writer(&value, &mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn cbor_telemetry_decodes_like_json() {
+        assert_eq!(
+            extract_cbor_node_id("tele/node-a/reading/cbor"),
+            Some("node-a")
+        );
+        assert_eq!(extract_cbor_node_id("tele/node-a/reading"), None);
+        assert_eq!(extract_node_id("tele/node-a/reading/cbor"), None);
+
+        let payload = cbor(serde_json::json!({
+            "ts": 1700000000,
+            "readings": [{ "sensor_id": "s1", "raw": 23110, "raw_stddev": 4.5 }],
+        }));
+        let msg = parse_telemetry_as(PayloadEncoding::Cbor, &payload).unwrap();
+        assert_eq!(msg.ts, 1700000000);
+        assert_eq!(msg.readings[0].raw, 23110);
+        assert_eq!(msg.readings[0].raw_stddev, Some(4.5));
+
+        let unknown = cbor(serde_json::json!({
+            "ts": 1,
+            "readings": [{ "sensor_id": "s1", "raw": 1, "volts": 3.3 }],
+        }));
+        assert_eq!(
+            reject(parse_telemetry_as(PayloadEncoding::Cbor, &unknown)),
+            (RejectReason::UnknownField, Some("readings[0].volts".into()))
+        );
+        assert_eq!(
+            reject(parse_telemetry_as(PayloadEncoding::Cbor, &[0xff, 0x00])).0,
+            RejectReason::Malformed
+        );
+        // JSON sent to the CBOR topic is not CBOR.
+        assert_eq!(
+            reject(parse_telemetry_as(
+                PayloadEncoding::Cbor,
+                br#"{"ts":1,"readings":[]}"#
+            ))
+            .0,
+            RejectReason::Malformed
+        );
+    }
+
+    #[test]
+    fn telemetry_rejects_oversized_payloads() {
+        let big = vec![b' '; MAX_JSON_PAYLOAD_BYTES + 1];
+        assert_eq!(
+            reject(parse_telemetry(&big)),
+            (RejectReason::TooLarge, None)
+        );
+        let readings: Vec<String> = (0..=MAX_READINGS_PER_MESSAGE)
+            .map(|i| format!(r#"{{"sensor_id":"s{i}","raw":1}}"#))
+            .collect();
+        let json = format!(r#"{{"ts":1,"readings":[{}]}}"#, readings.join(","));
+        assert_eq!(
+            reject(parse_telemetry(json.as_bytes())),
+            (RejectReason::InvalidValue, Some("readings".into()))
+        );
+    }
+
+    #[test]
+    fn flow_and_advice_rejects() {
+        assert_eq!(
+            reject(parse_flow(br#"{"ts":1,"lpm":-2}"#)),
+            (RejectReason::InvalidValue, Some("lpm".into()))
+        );
+        assert_eq!(
+            reject(parse_advice(br#"{"pulses":-1}"#)),
+            (RejectReason::InvalidValue, Some("pulses".into()))
+        );
+        assert!(parse_flow(br#"{"ts":1,"lpm":5.5}"#).is_ok());
+    }
+
+    #[test]
+    fn temperature_payloads() {
+        let msg = parse_temperature(br#"{"ts":1,"temp_c":-3.5}"#, 1).unwrap();
+        assert_eq!(msg.temp_c, -3.5);
+        assert_eq!(
+            reject(parse_temperature(br#"{"ts":1,"temp_c":85}"#, 1)),
+            (RejectReason::InvalidValue, Some("temp_c".into()))
+        );
+        assert_eq!(extract_temp_source_id("temp/patio/reading"), Some("patio"));
+        assert_eq!(extract_temp_source_id("temp/patio/set"), None);
+    }
+
+    #[test]
+    fn temperature_ts_held_to_hub_clock() {
+        // A reading from a node with a bad RTC, an hour ahead, would
+        // otherwise make every real reading after it look stale.
+        let now = 1_700_000_000;
+        let ahead = format!(r#"{{"ts":{},"temp_c":-2}}"#, now + 3600);
+        assert_eq!(
+            reject(parse_temperature(ahead.as_bytes(), now)),
+            (RejectReason::InvalidValue, Some("ts".into()))
+        );
+        let behind = format!(r#"{{"ts":{},"temp_c":-2}}"#, now - MAX_BACKFILL_SEC - 1);
+        assert!(parse_temperature(behind.as_bytes(), now).is_err());
+        let skewed = format!(r#"{{"ts":{},"temp_c":-2}}"#, now + MAX_CLOCK_SKEW_SEC);
+        assert!(parse_temperature(skewed.as_bytes(), now).is_ok());
+        let real = format!(r#"{{"ts":{now},"temp_c":-2}}"#);
+        assert!(parse_temperature(real.as_bytes(), now).is_ok());
+    }
+
+    #[test]
+    fn pressure_payloads() {
+        let msg = parse_pressure(br#"{"ts":1,"kpa":320.5}"#, 1).unwrap();
+        assert_eq!(msg.kpa, 320.5);
+        assert_eq!(
+            reject(parse_pressure(br#"{"ts":1,"kpa":-4}"#, 1)),
+            (RejectReason::InvalidValue, Some("kpa".into()))
+        );
+        assert_eq!(
+            extract_pressure_source_id("pressure/pump/reading"),
+            Some("pump")
+        );
+        assert_eq!(extract_pressure_source_id("temp/pump/reading"), None);
+    }
+
+    #[test]
+    fn pressure_ts_held_to_hub_clock() {
+        let now = 1_700_000_000;
+        let ahead = format!(r#"{{"ts":{},"kpa":80}}"#, now + 3600);
+        assert_eq!(
+            reject(parse_pressure(ahead.as_bytes(), now)),
+            (RejectReason::InvalidValue, Some("ts".into()))
+        );
+        let behind = format!(r#"{{"ts":{},"kpa":80}}"#, now - MAX_BACKFILL_SEC - 1);
+        assert!(parse_pressure(behind.as_bytes(), now).is_err());
+        let real = format!(r#"{{"ts":{now},"kpa":80}}"#);
+        assert!(parse_pressure(real.as_bytes(), now).is_ok());
+    }
+
+    #[test]
+    fn weather_payloads() {
+        let msg =
+            parse_weather(br#"{"ts":1,"temp_c":21.5,"humidity_pct":60,"rain_mm":0.2}"#).unwrap();
+        assert_eq!(msg.humidity_pct, Some(60.0));
+        assert_eq!(msg.solar_w_m2, None);
+        assert_eq!(
+            parse_weather(br#"{"ts":1,"et0_mm":4.1}"#).unwrap().et0_mm,
+            Some(4.1)
+        );
+        assert_eq!(
+            reject(parse_weather(br#"{"ts":1,"humidity_pct":120}"#)),
+            (RejectReason::InvalidValue, Some("humidity_pct".into()))
+        );
+        assert_eq!(
+            reject(parse_weather(br#"{"ts":1,"rain_mm":-1}"#)),
+            (RejectReason::InvalidValue, Some("rain_mm".into()))
+        );
+        assert_eq!(
+            reject(parse_weather(br#"{"ts":1,"dew_c":3}"#)).0,
+            RejectReason::UnknownField
+        );
+        assert_eq!(
+            extract_weather_source_id("weather/station/reading"),
+            Some("station")
+        );
+        assert_eq!(extract_weather_source_id("temp/station/reading"), None);
+    }
+
+    #[test]
+    fn reject_display_includes_field() {
+        let e =
+            parse_telemetry(br#"{"ts":1,"readings":[{"sensor_id":"s1","raw":true}]}"#).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "telemetry (wrong_type) at readings[0].raw: invalid type: boolean `true`, expected i64"
+        );
+        assert!(parse_node_status(b" Online\n").unwrap().online);
+        assert_eq!(
+            parse_node_status(b"maybe").unwrap_err().reason,
+            RejectReason::InvalidValue
+        );
+    }
+
+    #[test]
+    fn node_status_with_build() {
+        let firmware = Firmware {
+            version: "0.2.0".to_string(),
+            git: "1a2b3c4d".to_string(),
+        };
+        let status = parse_node_status(&online_payload(&firmware)).unwrap();
+        assert_eq!(
+            status,
+            NodeStatus {
+                online: true,
+                firmware: Some(firmware),
+            }
+        );
+        assert_eq!(
+            parse_node_status(b"offline").unwrap(),
+            NodeStatus {
+                online: false,
+                firmware: None,
+            }
+        );
+        let err = parse_node_status(br#"{"status":"online","version":"0.2.0"}"#).unwrap_err();
+        assert_eq!(err.reason, RejectReason::MissingField);
+        let err =
+            parse_node_status(br#"{"status":"asleep","version":"0.2.0","git":"x"}"#).unwrap_err();
+        assert_eq!(err.reason, RejectReason::InvalidValue);
+    }
+
+    #[test]
+    fn advice_msg_reason_optional() {
+        let msg: AdviceMsg = serde_json::from_str(r#"{"pulses":2}"#).unwrap();
+        assert_eq!(msg.pulses, 2);
+        assert!(msg.reason.is_empty());
+        assert!(serde_json::from_str::<AdviceMsg>(r#"{"pulses":-1}"#).is_err());
+    }
+
+    #[test]
+    fn flow_msg_pressure_optional() {
+        let msg: FlowMsg = serde_json::from_str(r#"{"ts":1,"lpm":5.5}"#).unwrap();
+        assert_eq!(msg.lpm, 5.5);
+        assert_eq!(msg.pressure_kpa, None);
+        let msg: FlowMsg =
+            serde_json::from_str(r#"{"ts":1,"lpm":5.5,"pressure_kpa":280}"#).unwrap();
+        assert_eq!(msg.pressure_kpa, Some(280.0));
+    }
+
+    fn sensor(sensor_id: &str, channel: Option<i64>) -> SensorConfig {
+        SensorConfig {
+            sensor_id: sensor_id.into(),
+            node_id: "node-a".into(),
+            zone_id: "z1".into(),
+            raw_dry: 26000,
+            raw_wet: 12000,
+            channel,
+            archived_at: None,
+            weight: 1.0,
+            failure_margin: None,
+            failure_margin_pct: None,
+            curve: None,
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn node_settings_channel_map() {
+        let mut archived = sensor("node-a/s4", None);
+        archived.archived_at = Some(1);
+        let mut disabled = sensor("node-a/s2", None);
+        disabled.enabled = false;
+        let sensors = [
+            disabled,
+            sensor("node-a/s1", Some(3)),
+            sensor("node-a/probe", None),
+            archived,
+        ];
+        let msg = NodeSettingsMsg::new(None, &sensors);
+        assert_eq!(msg.sample_interval_sec, None);
+        // Explicit channel wins, "sN" falls back to N-1, others are skipped.
+        let map: Vec<(i64, &str)> = msg
+            .channels
+            .iter()
+            .map(|c| (c.channel, c.sensor_id.as_str()))
+            .collect();
+        assert_eq!(map, [(1, "s2"), (3, "s1")]);
+
+        let json = serde_json::to_value(&msg).unwrap();
+        assert!(json.get("sample_interval_sec").is_none());
+        assert_eq!(json["channels"][0]["raw_dry"], 26000);
+        // Disabled sensors stay in the map so the node doesn't fall back to
+        // its own channels.
+        assert_eq!(json["channels"][0]["enabled"], false);
+        assert_eq!(json["channels"][1]["enabled"], true);
+        assert_eq!(node_settings_topic("node-a"), "cfg/node-a/set");
+    }
+
+    #[test]
+    fn topic_prefix_normalized() {
+        assert_eq!(normalize_topic_prefix("").unwrap(), "");
+        assert_eq!(
+            normalize_topic_prefix(" /garden/irrigation/ ").unwrap(),
+            "garden/irrigation"
+        );
+        assert!(normalize_topic_prefix("garden/#").is_err());
+        assert!(normalize_topic_prefix("+/irrigation").is_err());
+    }
+
+    #[test]
+    fn topic_prefix_applied_and_stripped() {
+        assert_eq!(prefixed("", "tele/+/reading"), "tele/+/reading");
+        assert_eq!(
+            prefixed("garden", "tele/+/reading"),
+            "garden/tele/+/reading"
+        );
+        assert_eq!(
+            unprefixed("garden", "garden/tele/n1/reading"),
+            Some("tele/n1/reading")
+        );
+        assert_eq!(unprefixed("", "tele/n1/reading"), Some("tele/n1/reading"));
+        // Another tenant's topics (or a partial match) are not ours.
+        assert_eq!(unprefixed("garden", "tele/n1/reading"), None);
+        assert_eq!(unprefixed("garden", "gardens/tele/n1/reading"), None);
     }
 }
@@ -0,0 +1,360 @@
+//! Over-the-air node updates.
+//!
+//! Node binaries are dropped into the `[ota]` directory as
+//! `irrigation-node-<version>`, each with a detached Ed25519 signature in
+//! `irrigation-node-<version>.sig` (64 raw bytes or 128 hex digits) over
+//! `irrigation-node <version> <sha256 hex>`.  They are signed offline; the
+//! hub never holds the signing key, and nodes refuse anything that doesn't
+//! verify against their `OTA_PUBLIC_KEY` or isn't newer than what they
+//! run.
+//!
+//! ```toml
+//! [ota]
+//! dir = "/var/lib/irrigation/ota"
+//! base_url = "http://192.168.1.10:8080"   # how nodes reach this hub
+//! ```
+//!
+//! `POST /api/nodes/{node_id}/ota` publishes a retained announcement on
+//! `cfg/<node_id>/ota` with the image's URL (served unauthenticated under
+//! `/ota/`), size, SHA-256 and signature.  The node downloads and checks
+//! it, swaps its binary and restarts, reporting each step on
+//! `ota/<node_id>/status`.  Once it reports `running` the announced
+//! version, the hub clears the announcement.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+use tokio::sync::mpsc;
+
+/// File name prefix of node images.
+pub const IMAGE_PREFIX: &str = "irrigation-node-";
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct OtaConfig {
+    /// Directory holding the node images.  Unset: OTA is off.
+    pub dir: Option<String>,
+    /// This hub's address as the nodes reach it, e.g.
+    /// `http://192.168.1.10:8080`.
+    pub base_url: Option<String>,
+}
+
+impl OtaConfig {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        match (&self.dir, &self.base_url) {
+            (None, None) => {}
+            (Some(dir), Some(url)) => {
+                if dir.trim().is_empty() {
+                    errors.push("ota: dir must not be empty".to_string());
+                }
+                // Nodes download without TLS; the signature covers the
+                // binary.
+                if !url.starts_with("http://") || url.len() <= "http://".len() {
+                    errors.push(format!("ota: base_url must be an http:// URL, got '{url}'"));
+                }
+            }
+            _ => errors.push("ota: dir and base_url must be set together".to_string()),
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// `(dir, base_url)` when OTA is configured.
+    pub fn resolved(&self) -> Option<(&str, &str)> {
+        Some((self.dir.as_deref()?, self.base_url.as_deref()?))
+    }
+}
+
+/// Letters, digits and `.`, `-`, `+`, `_`: keeps versions usable as file
+/// names and out of other directories.
+pub fn valid_version(version: &str) -> bool {
+    !version.is_empty()
+        && version.len() <= 64
+        && !version.starts_with('.')
+        && version
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+' | '_'))
+}
+
+/// The version in an image file name, if it is one.
+pub fn image_version(file: &str) -> Option<&str> {
+    file.strip_prefix(IMAGE_PREFIX)
+        .filter(|v| !v.ends_with(".sig") && valid_version(v))
+}
+
+/// A signed node binary in the OTA directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OtaImage {
+    pub version: String,
+    pub file: String,
+    pub size: u64,
+    pub sha256: String,
+    /// Hex.
+    pub signature: String,
+}
+
+/// What a node is told on `cfg/<node_id>/ota`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Announcement {
+    pub version: String,
+    pub url: String,
+    pub size: u64,
+    pub sha256: String,
+    pub signature: String,
+}
+
+impl Announcement {
+    pub fn new(image: &OtaImage, base_url: &str) -> Self {
+        Self {
+            version: image.version.clone(),
+            url: format!("{}/ota/{}", base_url.trim_end_matches('/'), image.file),
+            size: image.size,
+            sha256: image.sha256.clone(),
+            signature: image.signature.clone(),
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// A `.sig` file as hex: 64 raw bytes (e.g. from `openssl pkeyutl`) or
+/// 128 hex digits.
+fn signature_hex(raw: &[u8]) -> Result<String> {
+    if raw.len() == 64 {
+        return Ok(hex(raw));
+    }
+    let text = std::str::from_utf8(raw).unwrap_or_default().trim();
+    if text.len() == 128 && text.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(text.to_ascii_lowercase())
+    } else {
+        bail!("signature must be 64 bytes or 128 hex digits")
+    }
+}
+
+/// Load the image for `version` from `dir`; `None` if there is no such
+/// binary.  Reads the whole file, so call it off the async runtime.
+pub fn load_image(dir: &Path, version: &str) -> Result<Option<OtaImage>> {
+    if !valid_version(version) {
+        return Ok(None);
+    }
+    let file = format!("{IMAGE_PREFIX}{version}");
+    let path = dir.join(&file);
+    let image = match std::fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+    };
+    let sig_path = dir.join(format!("{file}.sig"));
+    let signature = std::fs::read(&sig_path)
+        .with_context(|| format!("{file} has no signature ({})", sig_path.display()))?;
+    let signature = signature_hex(&signature).with_context(|| sig_path.display().to_string())?;
+    Ok(Some(OtaImage {
+        version: version.to_string(),
+        file,
+        size: image.len() as u64,
+        sha256: hex(&Sha256::digest(&image)),
+        signature,
+    }))
+}
+
+/// Every image in `dir`, by version.  Images that can't be loaded (no
+/// signature, unreadable) are logged and left out.
+pub fn list_images(dir: &Path) -> Result<Vec<OtaImage>> {
+    let mut images = Vec::new();
+    for entry in std::fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))? {
+        let name = entry?.file_name();
+        let Some(version) = name.to_str().and_then(image_version) else {
+            continue;
+        };
+        match load_image(dir, version) {
+            Ok(Some(image)) => images.push(image),
+            Ok(None) => {}
+            Err(e) => tracing::warn!(version, "skipping OTA image: {e:#}"),
+        }
+    }
+    images.sort_by(|a, b| a.version.cmp(&b.version));
+    Ok(images)
+}
+
+/// A node's last report on `ota/<node_id>/status`.
+#[derive(Debug, Clone, Serialize)]
+pub struct OtaReport {
+    /// `running`, `downloading`, `installed` or `failed`.
+    pub state: String,
+    pub version: String,
+    pub error: Option<String>,
+    /// The node's own timestamp (unix seconds).
+    pub ts: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    pub received_at: OffsetDateTime,
+}
+
+/// What the hub knows about a node's updates.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NodeOta {
+    /// Version announced and not yet reported running.
+    pub pending: Option<String>,
+    pub report: Option<OtaReport>,
+}
+
+/// An announcement to publish (retained) for a node, or `None` to clear
+/// it.
+pub type AnnounceRequest = (String, Option<Announcement>);
+
+/// OTA plumbing shared with the web handlers.
+#[derive(Clone)]
+pub struct OtaApi {
+    pub config: OtaConfig,
+    pub announcements: mpsc::Sender<AnnounceRequest>,
+}
+
+/// Record a node's report; returns `true` when it completes the pending
+/// update (the announcement can be cleared).
+pub fn record_report(
+    nodes: &mut HashMap<String, NodeOta>,
+    node_id: &str,
+    report: OtaReport,
+) -> bool {
+    let node = nodes.entry(node_id.to_string()).or_default();
+    let done =
+        report.state == "running" && node.pending.as_deref() == Some(report.version.as_str());
+    if done {
+        node.pending = None;
+    }
+    node.report = Some(report);
+    done
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_validation() {
+        assert!(OtaConfig::default().validate().is_ok());
+        let cfg = OtaConfig {
+            dir: Some("/var/lib/irrigation/ota".into()),
+            base_url: Some("http://hub:8080".into()),
+        };
+        assert!(cfg.validate().is_ok());
+        assert_eq!(
+            cfg.resolved(),
+            Some(("/var/lib/irrigation/ota", "http://hub:8080"))
+        );
+
+        let errs = OtaConfig {
+            base_url: Some("https://hub".into()),
+            ..cfg.clone()
+        }
+        .validate()
+        .unwrap_err();
+        assert!(errs[0].contains("http://"), "{errs:?}");
+        let errs = OtaConfig { dir: None, ..cfg }.validate().unwrap_err();
+        assert!(errs[0].contains("set together"), "{errs:?}");
+    }
+
+    #[test]
+    fn versions_and_file_names() {
+        assert!(valid_version("0.2.0"));
+        assert!(valid_version("0.2.0-rc1+g1a2b3c"));
+        for bad in ["", "../x", ".hidden", "a/b", "1 2", &"9".repeat(65)] {
+            assert!(!valid_version(bad), "{bad:?}");
+        }
+        assert_eq!(image_version("irrigation-node-0.2.0"), Some("0.2.0"));
+        assert_eq!(image_version("irrigation-node-0.2.0.sig"), None);
+        assert_eq!(image_version("irrigation-hub-0.2.0"), None);
+    }
+
+    #[test]
+    fn images_load_with_their_signatures() {
+        let dir = std::env::temp_dir().join(format!("irrigation-hub-ota-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("irrigation-node-0.2.0"), b"binary").unwrap();
+        std::fs::write(dir.join("irrigation-node-0.2.0.sig"), [0xab; 64]).unwrap();
+        std::fs::write(dir.join("irrigation-node-0.3.0"), b"newer").unwrap();
+        std::fs::write(
+            dir.join("irrigation-node-0.3.0.sig"),
+            format!("{}\n", "CD".repeat(64)),
+        )
+        .unwrap();
+        // No signature: listed nowhere.
+        std::fs::write(dir.join("irrigation-node-0.4.0"), b"unsigned").unwrap();
+        std::fs::write(dir.join("README"), b"notes").unwrap();
+
+        let images = list_images(&dir).unwrap();
+        let versions: Vec<&str> = images.iter().map(|i| i.version.as_str()).collect();
+        assert_eq!(versions, ["0.2.0", "0.3.0"]);
+        let image = &images[0];
+        assert_eq!(image.size, 6);
+        assert_eq!(
+            image.sha256,
+            "9a3a45d01531a20e89ac6ae10b0b0beb0492acd7216a368aa062d1a5fecaf9cd"
+        );
+        assert_eq!(image.signature, "ab".repeat(64));
+        assert_eq!(images[1].signature, "cd".repeat(64));
+
+        assert!(load_image(&dir, "0.4.0").is_err());
+        assert_eq!(load_image(&dir, "0.9.0").unwrap(), None);
+        assert_eq!(load_image(&dir, "../etc").unwrap(), None);
+
+        let ann = Announcement::new(image, "http://hub:8080/");
+        assert_eq!(ann.url, "http://hub:8080/ota/irrigation-node-0.2.0");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn running_the_pending_version_completes_the_update() {
+        let report = |state: &str, version: &str| OtaReport {
+            state: state.into(),
+            version: version.into(),
+            error: None,
+            ts: 1_700_000_000,
+            received_at: OffsetDateTime::now_utc(),
+        };
+        let mut nodes = HashMap::new();
+        nodes.insert(
+            "node-a".to_string(),
+            NodeOta {
+                pending: Some("0.2.0".into()),
+                report: None,
+            },
+        );
+        assert!(!record_report(
+            &mut nodes,
+            "node-a",
+            report("running", "0.1.0")
+        ));
+        assert!(!record_report(
+            &mut nodes,
+            "node-a",
+            report("installed", "0.2.0")
+        ));
+        assert!(record_report(
+            &mut nodes,
+            "node-a",
+            report("running", "0.2.0")
+        ));
+        assert_eq!(nodes["node-a"].pending, None);
+        assert_eq!(nodes["node-a"].report.as_ref().unwrap().state, "running");
+        // A node nobody updated just has its report kept.
+        assert!(!record_report(
+            &mut nodes,
+            "node-b",
+            report("running", "0.1.0")
+        ));
+    }
+}
@@ -0,0 +1,93 @@
+//! Optional OpenTelemetry trace export.
+//!
+//! MQTT handling (`mqtt.telemetry`, `mqtt.valve_command`), the scheduler
+//! (`scheduler.tick` with a span per zone evaluation) and the main database
+//! writes (`db.*`) run in tracing spans.  Built with the `otlp` feature and
+//! with `OTEL_EXPORTER_OTLP_ENDPOINT` (or `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`)
+//! set, those spans are exported over OTLP/HTTP (protobuf) to a collector
+//! such as Grafana Tempo.  The other standard `OTEL_*` variables (headers,
+//! timeout, resource attributes) are honoured by the exporter; the service
+//! name defaults to `irrigation-hub`.
+
+use std::env;
+
+/// Whether an OTLP endpoint is configured.
+pub fn endpoint_configured() -> bool {
+    [
+        "OTEL_EXPORTER_OTLP_ENDPOINT",
+        "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+    ]
+    .iter()
+    .any(|v| env::var(v).is_ok_and(|s| !s.is_empty()))
+}
+
+// ---------------------------------------------------------------------------
+// OTLP exporter (requires the `otlp` feature)
+// ---------------------------------------------------------------------------
+#[cfg(feature = "otlp")]
+mod export {
+    use anyhow::Result;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::SpanExporter;
+    use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+    use opentelemetry_sdk::Resource;
+    use tracing::Subscriber;
+    use tracing_opentelemetry::OpenTelemetryLayer;
+    use tracing_subscriber::registry::LookupSpan;
+
+    /// Flushes buffered spans when shut down.
+    pub struct Guard(Option<SdkTracerProvider>);
+
+    impl Guard {
+        pub fn shutdown(self) {
+            if let Some(provider) = self.0 {
+                if let Err(e) = provider.shutdown() {
+                    tracing::warn!("OTLP trace export shutdown failed: {e}");
+                }
+            }
+        }
+    }
+
+    type Layer<S> = OpenTelemetryLayer<S, SdkTracer>;
+
+    /// The export layer, when an endpoint is configured.
+    pub fn init<S>() -> Result<(Option<Layer<S>>, Guard)>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        if !super::endpoint_configured() {
+            return Ok((None, Guard(None)));
+        }
+        let exporter = SpanExporter::builder().with_http().build()?;
+        let mut resource = Resource::builder();
+        if std::env::var("OTEL_SERVICE_NAME").is_err() {
+            resource = resource.with_service_name("irrigation-hub");
+        }
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(resource.build())
+            .build();
+        let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("irrigation-hub"));
+        Ok((Some(layer), Guard(Some(provider))))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Without the `otlp` feature: no exporter
+// ---------------------------------------------------------------------------
+#[cfg(not(feature = "otlp"))]
+mod export {
+    use anyhow::Result;
+
+    pub struct Guard;
+
+    impl Guard {
+        pub fn shutdown(self) {}
+    }
+
+    pub fn init() -> Result<(Option<tracing_subscriber::layer::Identity>, Guard)> {
+        Ok((None, Guard))
+    }
+}
+
+pub use export::init;
@@ -0,0 +1,266 @@
+//! Line-pressure safety: a pressure transducer on the supply line guards
+//! against the two failures a moisture sensor can't see.
+//!
+//! - Low pressure (a failed pump, a closed main): while the newest reading
+//!   is below `min_kpa`, every valve ON command — scheduler or MQTT — is
+//!   refused.  It releases once a reading is `hysteresis_kpa` above it.
+//! - High pressure with a valve open (a blocked line or a valve that didn't
+//!   actually open): once readings have stayed above `max_kpa` for
+//!   `high_for_sec`, every valve is forced off.  Static pressure with all
+//!   valves closed is expected to be high, so it is ignored.
+//!
+//! Readings arrive on `pressure/<source_id>/reading` from any source (a
+//! node's ADC channel, a bridge from the pump controller).  As with the
+//! frost lockout, the newest reading decides and the state only changes on
+//! a reading.
+//!
+//! ```toml
+//! [pressure]
+//! min_kpa = 150
+//! max_kpa = 600
+//! high_for_sec = 30
+//! hysteresis_kpa = 10
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct PressureConfig {
+    /// Refuse valve ON below this (kPa).  Unset = no low-pressure lockout.
+    pub min_kpa: Option<f64>,
+    /// Force all valves off above this with a valve open (kPa).  Unset =
+    /// no high-pressure cut-off.
+    pub max_kpa: Option<f64>,
+    /// How long pressure must stay above `max_kpa` before the cut-off.
+    pub high_for_sec: i64,
+    /// The low-pressure lockout releases this far above `min_kpa`.
+    pub hysteresis_kpa: f64,
+}
+
+impl Default for PressureConfig {
+    fn default() -> Self {
+        Self {
+            min_kpa: None,
+            max_kpa: None,
+            high_for_sec: 30,
+            hysteresis_kpa: 10.0,
+        }
+    }
+}
+
+impl PressureConfig {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        for (key, v) in [("min_kpa", self.min_kpa), ("max_kpa", self.max_kpa)] {
+            if let Some(v) = v.filter(|v| !(v.is_finite() && *v > 0.0)) {
+                errors.push(format!("pressure: {key} must be positive, got {v}"));
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_kpa, self.max_kpa) {
+            if max <= min + self.hysteresis_kpa {
+                errors.push(format!(
+                    "pressure: max_kpa ({max}) must be above min_kpa + hysteresis_kpa ({})",
+                    min + self.hysteresis_kpa
+                ));
+            }
+        }
+        if self.high_for_sec < 0 {
+            errors.push(format!(
+                "pressure: high_for_sec must not be negative, got {}",
+                self.high_for_sec
+            ));
+        }
+        if !(self.hysteresis_kpa.is_finite() && self.hysteresis_kpa >= 0.0) {
+            errors.push(format!(
+                "pressure: hysteresis_kpa must not be negative, got {}",
+                self.hysteresis_kpa
+            ));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// The newest line pressure.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PressureReading {
+    pub source: String,
+    pub kpa: f64,
+    /// Unix seconds.
+    pub ts: i64,
+}
+
+/// A state change caused by a reading.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Change {
+    /// Dropped below `min_kpa`: valve ON is refused from now on.
+    Low,
+    /// Back above `min_kpa + hysteresis_kpa`.
+    Restored,
+    /// Above `max_kpa` with a valve open since `since`: turn everything off.
+    High { since: i64 },
+}
+
+/// Pressure state, shown under `pressure` in `/api/status`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PressureMonitor {
+    #[serde(skip)]
+    cfg: PressureConfig,
+    low: bool,
+    /// First reading of the current high spell with a valve open.
+    high_since: Option<i64>,
+    latest: Option<PressureReading>,
+}
+
+impl PressureMonitor {
+    pub fn new(cfg: &PressureConfig) -> Self {
+        Self {
+            cfg: *cfg,
+            ..Self::default()
+        }
+    }
+
+    /// Take a new reading; `valve_open` is whether any valve is open now.
+    /// Readings older than the latest are ignored.
+    pub fn record(&mut self, reading: PressureReading, valve_open: bool) -> Vec<Change> {
+        if self.latest.as_ref().is_some_and(|l| reading.ts < l.ts) {
+            return Vec::new();
+        }
+        let (kpa, ts) = (reading.kpa, reading.ts);
+        self.latest = Some(reading);
+        let mut changes = Vec::new();
+
+        if let Some(min) = self.cfg.min_kpa {
+            if !self.low && kpa < min {
+                self.low = true;
+                changes.push(Change::Low);
+            } else if self.low && kpa >= min + self.cfg.hysteresis_kpa {
+                self.low = false;
+                changes.push(Change::Restored);
+            }
+        }
+
+        match self.cfg.max_kpa {
+            Some(max) if valve_open && kpa > max => {
+                let since = *self.high_since.get_or_insert(ts);
+                if ts - since >= self.cfg.high_for_sec {
+                    // Valves are about to close; a new spell starts afresh.
+                    self.high_since = None;
+                    changes.push(Change::High { since });
+                }
+            }
+            _ => self.high_since = None,
+        }
+        changes
+    }
+
+    pub fn is_low(&self) -> bool {
+        self.low
+    }
+
+    /// The newest reading, if any.
+    pub fn latest(&self) -> Option<&PressureReading> {
+        self.latest.as_ref()
+    }
+
+    /// Why valves are locked out, e.g. "low line pressure: 80 kPa (pump)".
+    pub fn reason(&self) -> String {
+        match &self.latest {
+            Some(r) => format!("low line pressure: {} kPa ({})", r.kpa, r.source),
+            None => "low line pressure".to_string(),
+        }
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(kpa: f64, ts: i64) -> PressureReading {
+        PressureReading {
+            source: "pump".into(),
+            kpa,
+            ts,
+        }
+    }
+
+    fn monitor() -> PressureMonitor {
+        PressureMonitor::new(&PressureConfig {
+            min_kpa: Some(150.0),
+            max_kpa: Some(600.0),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn low_pressure_locks_out_until_above_hysteresis() {
+        let mut p = monitor();
+        assert!(p.record(reading(300.0, 1), false).is_empty());
+        assert_eq!(p.record(reading(80.0, 2), false), vec![Change::Low]);
+        assert!(p.is_low());
+        assert_eq!(p.reason(), "low line pressure: 80 kPa (pump)");
+        // Within the hysteresis band: still low.
+        assert!(p.record(reading(155.0, 3), false).is_empty());
+        assert!(p.is_low());
+        assert_eq!(p.record(reading(160.0, 4), false), vec![Change::Restored]);
+        assert!(!p.is_low());
+        // Out of order: ignored.
+        assert!(p.record(reading(10.0, 3), false).is_empty());
+        assert!(!p.is_low());
+    }
+
+    #[test]
+    fn sustained_high_pressure_with_a_valve_open_trips() {
+        let mut p = monitor();
+        // Closed valves: static pressure doesn't count.
+        assert!(p.record(reading(700.0, 0), false).is_empty());
+        assert!(p.record(reading(700.0, 10), true).is_empty());
+        // A dip resets the spell.
+        assert!(p.record(reading(400.0, 20), true).is_empty());
+        assert!(p.record(reading(700.0, 30), true).is_empty());
+        assert!(p.record(reading(700.0, 50), true).is_empty());
+        assert_eq!(
+            p.record(reading(700.0, 60), true),
+            vec![Change::High { since: 30 }]
+        );
+        assert!(p.record(reading(700.0, 70), true).is_empty());
+    }
+
+    #[test]
+    fn disabled_monitor_only_tracks_pressure() {
+        let mut p = PressureMonitor::new(&PressureConfig::default());
+        assert!(p.record(reading(0.0, 1), true).is_empty());
+        assert!(p.record(reading(9000.0, 100), true).is_empty());
+        assert!(!p.is_low());
+    }
+
+    #[test]
+    fn config_validation() {
+        assert!(PressureConfig::default().validate().is_ok());
+        let errs = PressureConfig {
+            min_kpa: Some(300.0),
+            max_kpa: Some(305.0),
+            high_for_sec: -1,
+            hysteresis_kpa: 10.0,
+        }
+        .validate()
+        .unwrap_err();
+        assert_eq!(errs.len(), 2, "{errs:?}");
+        assert!(errs[0].contains("max_kpa (305)"), "{errs:?}");
+        let errs = PressureConfig {
+            min_kpa: Some(-1.0),
+            ..Default::default()
+        }
+        .validate()
+        .unwrap_err();
+        assert!(errs[0].contains("min_kpa must be positive"), "{errs:?}");
+    }
+}
@@ -0,0 +1,167 @@
+//! Restoring the database from a backup over the API, so recovering from
+//! SD-card corruption doesn't need a shell on the Pi.
+//!
+//! Backups live in the directory of `DB_BACKUP_PATH`; any `*.db` file there
+//! can be restored (`GET /api/backups` lists them).  A restore takes two
+//! calls to `POST /api/backups/restore`: the first returns a confirmation
+//! token for the chosen file, the second repeats the request with that
+//! token (see `confirm`).
+//!
+//! The web handler then passes the request to the main loop, which owns the
+//! valves and the critical tasks.  It turns every valve off, stops the
+//! scheduler and watchdog, swaps the backup's contents in
+//! (`Db::restore_from`, which also migrates it), re-seeds `config.toml` as
+//! at startup, and restarts both tasks with the reloaded zones and sensors.
+//! Valve pin changes still need a hub restart.
+
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use tokio::sync::{mpsc, oneshot};
+
+/// A restorable backup file.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct BackupFile {
+    pub name: String,
+    pub size_bytes: u64,
+    /// Last modification (unix seconds).
+    pub modified: Option<i64>,
+}
+
+/// Directory searched for backups: the one holding `DB_BACKUP_PATH`.
+pub fn backup_dir(backup_path: &str) -> PathBuf {
+    match Path::new(backup_path).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Every `*.db` file in `dir`, newest first.  A missing directory has none.
+pub fn list_backups(dir: &Path) -> Result<Vec<BackupFile>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("read backup dir {}", dir.display())),
+    };
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry.with_context(|| format!("read backup dir {}", dir.display()))?;
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "db") {
+            continue;
+        }
+        let Ok(meta) = entry.metadata() else { continue };
+        if !meta.is_file() {
+            continue;
+        }
+        files.push(BackupFile {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            size_bytes: meta.len(),
+            modified: meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64),
+        });
+    }
+    files.sort_by(|a, b| {
+        b.modified
+            .cmp(&a.modified)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    Ok(files)
+}
+
+/// Path of backup `name` in `dir`.  Only plain `*.db` file names are
+/// accepted, so a request can't reach outside the backup directory.
+pub fn resolve(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let plain = !name.is_empty()
+        && !name.starts_with('.')
+        && !name.contains(['/', '\\'])
+        && name.ends_with(".db");
+    if plain {
+        Ok(dir.join(name))
+    } else {
+        Err(format!(
+            "file must be a backup file name (*.db), got '{name}'"
+        ))
+    }
+}
+
+/// Outcome of a restore, returned by the API.
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoreOutcome {
+    pub rows: i64,
+    pub zones: usize,
+    pub sensors: usize,
+    /// Zones or valve pins differ from the running valve board.
+    pub restart_required: bool,
+}
+
+/// A confirmed restore, handed from the web handler to the main loop.
+pub struct RestoreRequest {
+    pub path: PathBuf,
+    pub reply: oneshot::Sender<Result<RestoreOutcome, String>>,
+}
+
+/// Restore plumbing shared with the web handlers.
+#[derive(Clone)]
+pub struct RestoreApi {
+    /// `None` when `DB_BACKUP_PATH` is unset.
+    pub dir: Option<PathBuf>,
+    pub requests: mpsc::Sender<RestoreRequest>,
+}
+
+impl RestoreApi {
+    pub fn new(dir: Option<PathBuf>, requests: mpsc::Sender<RestoreRequest>) -> Self {
+        Self { dir, requests }
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_rejects_paths() {
+        let dir = Path::new("/var/backups");
+        assert_eq!(
+            resolve(dir, "irrigation.db").unwrap(),
+            PathBuf::from("/var/backups/irrigation.db")
+        );
+        for bad in ["", "../etc/passwd.db", "sub/x.db", ".hidden.db", "x.db.tmp"] {
+            assert!(resolve(dir, bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn backup_dir_of_relative_path() {
+        assert_eq!(backup_dir("backup.db"), PathBuf::from("."));
+        assert_eq!(backup_dir("/mnt/usb/b.db"), PathBuf::from("/mnt/usb"));
+    }
+
+    #[test]
+    fn lists_only_db_files() {
+        let dir = std::env::temp_dir().join(format!("irrigation_list_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.db"), b"x").unwrap();
+        std::fs::write(dir.join("a.db.tmp"), b"x").unwrap();
+        std::fs::write(dir.join("notes.txt"), b"x").unwrap();
+
+        let files = list_backups(&dir).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "a.db");
+        assert_eq!(files[0].size_bytes, 1);
+        assert!(list_backups(&dir.join("missing")).unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,227 @@
+//! Data retention: how long each history table is kept, and the pruner
+//! that enforces it.
+//!
+//! The policy comes from `[retention]` in config.toml (`LOG_RETENTION_DAYS`
+//! still sets `logs_days`).  `PUT /api/retention` replaces it at runtime
+//! and stores it in `hub_meta`, where it takes precedence over the file on
+//! later starts.  The pruner runs every `interval_hours` inside the
+//! maintenance windows; `POST /api/maintenance/prune` runs it right away.
+//! Readings aren't simply dropped: past `readings_days` they are rolled up
+//! into hourly min/avg/max rows per sensor, and past `hourly_days` those
+//! become daily rows, which are kept forever.  Freed pages are handed back
+//! to the filesystem with an incremental vacuum.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::db::Db;
+use crate::logs;
+
+/// Pages the periodic pruner vacuums per run, so a large backlog of free
+/// pages doesn't hold the write lock for long.
+pub const PERIODIC_VACUUM_PAGES: u32 = 100;
+
+/// Longest accepted retention or prune interval (10 years in days, a year
+/// in hours).
+pub const MAX_RETENTION_DAYS: i64 = 3650;
+pub const MAX_INTERVAL_HOURS: u64 = 24 * 366;
+
+/// ```toml
+/// [retention]
+/// readings_days = 90
+/// hourly_days = 365
+/// watering_events_days = 730
+/// decisions_days = 30
+/// logs_days = 30
+/// interval_hours = 6
+/// ```
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetentionPolicy {
+    /// Raw sensor and flow readings.
+    pub readings_days: i64,
+    /// Hourly moisture summaries, then compacted into daily ones.
+    pub hourly_days: i64,
+    /// Watering events.  Unset keeps them forever.
+    pub watering_events_days: Option<i64>,
+    /// Scheduler decision audit log (a row per zone per tick).
+    pub decisions_days: i64,
+    /// Stored log records (`LOG_TO_DB`).
+    pub logs_days: i64,
+    /// Hours between pruner runs.
+    pub interval_hours: u64,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            readings_days: 90,
+            hourly_days: 365,
+            watering_events_days: None,
+            decisions_days: 30,
+            logs_days: logs::DEFAULT_RETENTION_DAYS,
+            interval_hours: 6,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        for (name, days) in [
+            ("readings_days", Some(self.readings_days)),
+            ("hourly_days", Some(self.hourly_days)),
+            ("watering_events_days", self.watering_events_days),
+            ("decisions_days", Some(self.decisions_days)),
+            ("logs_days", Some(self.logs_days)),
+        ] {
+            if let Some(days) = days.filter(|d| !(1..=MAX_RETENTION_DAYS).contains(d)) {
+                errors.push(format!(
+                    "retention: {name} must be 1..={MAX_RETENTION_DAYS}, got {days}"
+                ));
+            }
+        }
+        if self.hourly_days < self.readings_days {
+            errors.push(format!(
+                "retention: hourly_days ({}) must not be below readings_days ({})",
+                self.hourly_days, self.readings_days
+            ));
+        }
+        if !(1..=MAX_INTERVAL_HOURS).contains(&self.interval_hours) {
+            errors.push(format!(
+                "retention: interval_hours must be 1..={MAX_INTERVAL_HOURS}, got {}",
+                self.interval_hours
+            ));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Rows deleted by one pruner run, per table, and the space given back.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PruneReport {
+    /// Sensor and flow readings (sensor readings rolled up hourly first).
+    pub readings: u64,
+    /// Hourly summaries compacted into daily ones.
+    pub hourly: u64,
+    pub watering_events: u64,
+    pub decisions: u64,
+    pub logs: u64,
+    /// Shrinkage of the database file.
+    pub bytes_reclaimed: u64,
+}
+
+impl PruneReport {
+    pub fn total_rows(&self) -> u64 {
+        self.readings + self.hourly + self.watering_events + self.decisions + self.logs
+    }
+
+    /// One-line description for the event log.
+    pub fn summary(&self) -> String {
+        format!(
+            "pruned {} old rows (readings {}, hourly {}, watering events {}, decisions {}, \
+             logs {}), reclaimed {} KiB",
+            self.total_rows(),
+            self.readings,
+            self.hourly,
+            self.watering_events,
+            self.decisions,
+            self.logs,
+            self.bytes_reclaimed / 1024
+        )
+    }
+}
+
+/// Delete everything older than `policy` allows, then vacuum up to
+/// `vacuum_pages` free pages (all of them if unset).
+pub async fn prune(
+    db: &Db,
+    policy: &RetentionPolicy,
+    vacuum_pages: Option<u32>,
+) -> Result<PruneReport> {
+    let size_before = db.size_bytes().await?;
+    let mut report = PruneReport {
+        readings: db.prune_old_readings(policy.readings_days).await?,
+        hourly: db.compact_hourly_readings(policy.hourly_days).await?,
+        ..PruneReport::default()
+    };
+    if let Some(days) = policy.watering_events_days {
+        report.watering_events = db.prune_watering_events(days).await?;
+    }
+    report.decisions = db.prune_scheduler_decisions(policy.decisions_days).await?;
+    report.logs = db.prune_logs(policy.logs_days).await?;
+    db.incremental_vacuum(vacuum_pages).await?;
+    report.bytes_reclaimed = size_before.saturating_sub(db.size_bytes().await?);
+    Ok(report)
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy_defaults_and_validation() {
+        let policy: RetentionPolicy = toml::from_str("watering_events_days = 365").unwrap();
+        assert_eq!(policy.readings_days, 90);
+        assert_eq!(policy.watering_events_days, Some(365));
+        assert_eq!(policy.interval_hours, 6);
+        assert!(policy.validate().is_ok());
+        assert!(toml::from_str::<RetentionPolicy>("reading_days = 10").is_err());
+
+        let below = RetentionPolicy {
+            hourly_days: 30,
+            ..RetentionPolicy::default()
+        };
+        assert!(below.validate().unwrap_err()[0].contains("hourly_days (30)"));
+
+        let bad = RetentionPolicy {
+            readings_days: 0,
+            watering_events_days: Some(-1),
+            interval_hours: 0,
+            ..RetentionPolicy::default()
+        };
+        let errs = bad.validate().unwrap_err();
+        assert_eq!(errs.len(), 3, "{errs:?}");
+        assert!(errs[0].contains("readings_days"));
+        assert!(errs[1].contains("watering_events_days"));
+        assert!(errs[2].contains("interval_hours"));
+    }
+
+    #[tokio::test]
+    async fn prune_reports_rows_per_table() {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+        let now = time::OffsetDateTime::now_utc().unix_timestamp();
+        let record = |ts| logs::LogRecord {
+            ts,
+            level: "WARN".into(),
+            target: "irrigation_hub::mqtt".into(),
+            message: "slow".into(),
+            fields: serde_json::Map::new(),
+        };
+        db.insert_logs(&[record(now - 40 * 86400), record(now - 2 * 86400)])
+            .await
+            .unwrap();
+
+        let mut policy = RetentionPolicy::default();
+        let report = prune(&db, &policy, None).await.unwrap();
+        assert_eq!(report.logs, 1);
+        assert_eq!(report.total_rows(), 1);
+        assert!(report.summary().starts_with("pruned 1 old rows"));
+
+        policy.logs_days = 1;
+        let report = prune(&db, &policy, Some(PERIODIC_VACUUM_PAGES))
+            .await
+            .unwrap();
+        assert_eq!(report.logs, 1);
+        assert_eq!(prune(&db, &policy, None).await.unwrap().total_rows(), 0);
+    }
+}
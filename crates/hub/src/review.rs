@@ -0,0 +1,182 @@
+//! Cold-start safety review: risky zone configurations flagged at startup
+//! (after migrations and the `config.toml` seed) and after a backup
+//! restore.
+//!
+//! In auto mode the scheduler leaves a flagged zone alone until its
+//! findings are acknowledged through the API.  An acknowledgement covers
+//! the finding's exact wording, so changing the numbers behind it raises a
+//! new finding that needs acknowledging again.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::db::{SensorConfig, ZoneConfig};
+use crate::valve::ValveConfig;
+
+/// Most watering a zone's limits may allow per day before it is flagged
+/// (2 hours).
+pub const LONG_RUNTIME_SEC: i64 = 2 * 3600;
+
+/// One risky configuration.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    pub zone_id: String,
+    /// `long_runtime`, `no_sensors` or `shared_gpio`.
+    pub code: &'static str,
+    pub detail: String,
+    pub acknowledged: bool,
+}
+
+impl Finding {
+    fn new(zone_id: &str, code: &'static str, detail: String) -> Self {
+        Self {
+            zone_id: zone_id.to_string(),
+            code,
+            detail,
+            acknowledged: false,
+        }
+    }
+}
+
+/// Review every zone.  Findings are ordered by zone, then by check.
+pub fn review(zones: &[ZoneConfig], sensors: &[SensorConfig]) -> Vec<Finding> {
+    // GPIO pin -> zones driving it (open and close pins alike).
+    let mut pins: BTreeMap<i64, Vec<&str>> = BTreeMap::new();
+    for z in zones {
+        pins.entry(z.valve_gpio_pin).or_default().push(&z.zone_id);
+        if let ValveConfig::Motorized { close_gpio_pin, .. } = z.valve {
+            pins.entry(close_gpio_pin).or_default().push(&z.zone_id);
+        }
+    }
+
+    let mut zones: Vec<&ZoneConfig> = zones.iter().collect();
+    zones.sort_by(|a, b| a.zone_id.cmp(&b.zone_id));
+
+    let mut findings = Vec::new();
+    for z in zones {
+        let daily_sec = (z.pulse_sec * z.max_pulses_per_day).min(z.max_open_sec_per_day);
+        if daily_sec > LONG_RUNTIME_SEC {
+            findings.push(Finding::new(
+                &z.zone_id,
+                "long_runtime",
+                format!(
+                    "pulse_sec {} x max_pulses_per_day {} (max_open_sec_per_day {}) allows \
+                     {} min of watering a day",
+                    z.pulse_sec,
+                    z.max_pulses_per_day,
+                    z.max_open_sec_per_day,
+                    daily_sec / 60
+                ),
+            ));
+        }
+
+        let has_sensor = sensors.iter().any(|s| {
+            s.zone_id == z.zone_id && s.archived_at.is_none() && s.enabled && s.weight > 0.0
+        });
+        if !has_sensor {
+            findings.push(Finding::new(
+                &z.zone_id,
+                "no_sensors",
+                "no active sensors: watering has no moisture feedback".to_string(),
+            ));
+        }
+
+        for (pin, users) in &pins {
+            if users.len() > 1 && users.contains(&z.zone_id.as_str()) {
+                findings.push(Finding::new(
+                    &z.zone_id,
+                    "shared_gpio",
+                    format!("GPIO {pin} is driven by zones {}", users.join(", ")),
+                ));
+            }
+        }
+    }
+    findings
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zone(zone_id: &str, pin: i64) -> ZoneConfig {
+        ZoneConfig {
+            name: zone_id.into(),
+            valve_gpio_pin: pin,
+            ..ZoneConfig::test(zone_id)
+        }
+    }
+
+    fn sensor(zone_id: &str, weight: f64) -> SensorConfig {
+        SensorConfig {
+            sensor_id: format!("n1/{zone_id}"),
+            node_id: "n1".into(),
+            zone_id: zone_id.into(),
+            raw_dry: 26000,
+            raw_wet: 12000,
+            channel: None,
+            archived_at: None,
+            weight,
+            failure_margin: None,
+            failure_margin_pct: None,
+            curve: None,
+            enabled: true,
+        }
+    }
+
+    fn codes(findings: &[Finding]) -> Vec<(&str, &str)> {
+        findings
+            .iter()
+            .map(|f| (f.zone_id.as_str(), f.code))
+            .collect()
+    }
+
+    #[test]
+    fn sane_config_has_no_findings() {
+        let zones = [zone("z1", 17), zone("z2", 27)];
+        let sensors = [sensor("z1", 1.0), sensor("z2", 1.0)];
+        assert!(review(&zones, &sensors).is_empty());
+    }
+
+    #[test]
+    fn long_runtime_is_capped_by_open_seconds() {
+        let mut z = zone("z1", 17);
+        z.pulse_sec = 900;
+        z.max_pulses_per_day = 12;
+        z.max_open_sec_per_day = 3600;
+        assert!(review(&[z.clone()], &[sensor("z1", 1.0)]).is_empty());
+
+        z.max_open_sec_per_day = 10_800;
+        let findings = review(&[z], &[sensor("z1", 1.0)]);
+        assert_eq!(codes(&findings), [("z1", "long_runtime")]);
+        assert!(findings[0].detail.contains("180 min"));
+    }
+
+    #[test]
+    fn zones_without_active_sensors_flagged() {
+        let zones = [zone("z1", 17), zone("z2", 27)];
+        // z2's only sensor has weight 0.
+        let findings = review(&zones, &[sensor("z1", 1.0), sensor("z2", 0.0)]);
+        assert_eq!(codes(&findings), [("z2", "no_sensors")]);
+    }
+
+    #[test]
+    fn shared_pins_flag_every_zone_involved() {
+        let mut z2 = zone("z2", 27);
+        z2.valve = ValveConfig::Motorized {
+            close_gpio_pin: 17,
+            travel_sec: 10,
+        };
+        let zones = [zone("z1", 17), z2, zone("z3", 22)];
+        let sensors = [sensor("z1", 1.0), sensor("z2", 1.0), sensor("z3", 1.0)];
+        let findings = review(&zones, &sensors);
+        assert_eq!(
+            codes(&findings),
+            [("z1", "shared_gpio"), ("z2", "shared_gpio")]
+        );
+        assert_eq!(findings[0].detail, "GPIO 17 is driven by zones z1, z2");
+    }
+}
@@ -7,7 +7,14 @@
 //! counters, watering-event logging, UI state updates) are handled by that
 //! existing path; nothing is duplicated here.
 //!
-//! ## Per-zone state machine
+//! Whether an idle zone should start a pulse is decided by its
+//! [`WateringStrategy`](crate::strategy::WateringStrategy) (moisture
+//! threshold by default, a fixed schedule, or an external advisor over
+//! MQTT).  The guards around that decision and the pulse/soak timing below
+//! are shared by all strategies; the moisture-driven soak exits only apply
+//! to strategies that use moisture.
+//!
+//! ## Per-zone state machine (threshold strategy)
 //!
 //! ```text
 //! Idle ──[moisture < min]──▶ Watering ──[pulse_sec elapsed]──▶ Soaking
@@ -16,35 +23,289 @@
 //!  ▲                                                              │
 //!  └────────────────────[moisture < target]── (another pulse) ────┘
 //! ```
+//!
+//! Zones mid-cycle are persisted to `scheduler_zone_state`, so a restart
+//! resumes the soak instead of dropping the zone back to Idle (and possibly
+//! straight into another pulse).
+//!
+//! Every idle evaluation, and every pulse/soak transition, is written to the
+//! `scheduler_decisions` table (one batch per tick) with the averaged
+//! moisture, the guard that blocked it, and the action taken, so "why didn't
+//! this zone water?" can be answered after the fact.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::time::Duration;
 
 use rumqttc::{AsyncClient, QoS};
+use serde::Serialize;
+use time::OffsetDateTime;
 use tokio::time::Instant;
-use tracing::{error, info, warn};
-
-use crate::config::OperationMode;
-use crate::db::{Db, ZoneConfig};
-use crate::state::SharedState;
+use tracing::{error, info, info_span, instrument, warn, Instrument};
+
+use crate::alerts::Change;
+use crate::arbitration::FairQueue;
+use crate::budget::Budget;
+use crate::clock;
+use crate::config::{OperationMode, SoakPolicy};
+use crate::db::{Db, RollingCounters, SchedulerDecision, SchedulerZoneState, ZoneConfig};
+use crate::flush::{FlushPlan, FLUSH_REASON};
+use crate::interlock::Interlocks;
+use crate::moisture::MoistureWindow;
+use crate::mqtt::{advice_request_topic, valve_set_topic, AdviceRequest};
+use crate::sessions::SCHEDULER_REASON;
+use crate::state::{SharedState, SystemState};
+use crate::strategy::{IdleDecision, WateringStrategy, ZoneContext};
 
 /// How often the scheduler evaluates each zone.
-const TICK_INTERVAL_SEC: u64 = 30;
+pub(crate) const TICK_INTERVAL_SEC: u64 = 30;
 
 /// Number of recent readings to average when deciding moisture level.
-const AVG_WINDOW: i64 = 5;
+const AVG_WINDOW: usize = 5;
+
+/// How much a single rise-based soak extension adds before re-checking.
+const SOAK_EXTEND_STEP_SEC: u64 = 120;
 
 // ---------------------------------------------------------------------------
 // Per-zone schedule state
 // ---------------------------------------------------------------------------
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum ZoneScheduleState {
     /// Waiting for moisture to drop below `min_moisture`.
     Idle,
     /// Valve ON; waiting for `pulse_sec` to elapse before sending OFF.
     Watering { since: Instant },
+    /// Valve ON for an emitter flush; back to Idle after `duration`.
+    Flushing { since: Instant, duration: Duration },
     /// Valve OFF; waiting for `soak_min` to elapse before re-evaluating.
-    Soaking { until: Instant },
+    /// `extended_sec` tracks how much the soak has been stretched by the
+    /// rise-based extension so it stays within `max_extend_min`.
+    Soaking {
+        started: Instant,
+        until: Instant,
+        extended_sec: u64,
+    },
+}
+
+impl ZoneScheduleState {
+    fn phase(&self) -> &'static str {
+        match self {
+            Self::Idle => "idle",
+            Self::Watering { .. } => "watering",
+            Self::Flushing { .. } => "flushing",
+            Self::Soaking { .. } => "soaking",
+        }
+    }
+
+    /// The persisted form of this state; `None` when idle.
+    fn to_row(self, zone_id: &str, now: Instant, now_ts: i64) -> Option<SchedulerZoneState> {
+        let (started, until, extended_sec) = match self {
+            Self::Idle => return None,
+            Self::Watering { since } => (since, None, 0),
+            Self::Flushing { since, duration } => (since, Some(since + duration), 0),
+            Self::Soaking {
+                started,
+                until,
+                extended_sec,
+            } => (started, Some(until), extended_sec),
+        };
+        let started_ts = instant_to_unix(started, now, now_ts);
+        Some(SchedulerZoneState {
+            zone_id: zone_id.to_string(),
+            phase: self.phase().to_string(),
+            started_ts,
+            // A pulse's end depends on the zone config; store it anyway so
+            // the row reads on its own.
+            until_ts: until.map_or(started_ts, |u| instant_to_unix(u, now, now_ts)),
+            extended_sec: extended_sec as i64,
+        })
+    }
+
+    /// Resume a persisted state after a restart.  Every valve is closed on
+    /// startup, so an interrupted pulse resumes as the soak that would have
+    /// followed it (from the pulse's planned end) and an interrupted flush
+    /// as Idle.
+    fn from_row(row: &SchedulerZoneState, cfg: &ZoneConfig, now: Instant, now_ts: i64) -> Self {
+        let at = |ts: i64| unix_to_instant(ts, now, now_ts);
+        match row.phase.as_str() {
+            "soaking" => Self::Soaking {
+                started: at(row.started_ts),
+                until: at(row.until_ts),
+                extended_sec: row.extended_sec.max(0) as u64,
+            },
+            "watering" => {
+                let pulse_end = row.started_ts + cfg.pulse_sec;
+                Self::Soaking {
+                    started: at(pulse_end),
+                    until: at(pulse_end + cfg.soak_min * 60),
+                    extended_sec: 0,
+                }
+            }
+            _ => Self::Idle,
+        }
+    }
+}
+
+fn instant_to_unix(at: Instant, now: Instant, now_ts: i64) -> i64 {
+    if at <= now {
+        now_ts - (now - at).as_secs() as i64
+    } else {
+        now_ts + (at - now).as_secs() as i64
+    }
+}
+
+/// Instants can't predate boot; anything older clamps to `now`.
+fn unix_to_instant(ts: i64, now: Instant, now_ts: i64) -> Instant {
+    if ts <= now_ts {
+        now.checked_sub(Duration::from_secs((now_ts - ts) as u64))
+            .unwrap_or(now)
+    } else {
+        now + Duration::from_secs((ts - now_ts) as u64)
+    }
+}
+
+/// Load the zones' persisted scheduler states into `states`.
+async fn restore_states(
+    db: &Db,
+    zone_configs: &HashMap<String, ZoneConfig>,
+    states: &mut HashMap<String, ZoneScheduleState>,
+    shared: &SharedState,
+) {
+    let rows = match db.load_scheduler_states().await {
+        Ok(rows) => rows,
+        Err(e) => {
+            error!("scheduler: loading persisted zone states failed: {e:#}");
+            return;
+        }
+    };
+    let (now, now_ts) = (clock::now(), now_unix());
+    for row in rows {
+        let Some(cfg) = zone_configs.get(&row.zone_id) else {
+            persist_state(db, &row.zone_id, &ZoneScheduleState::Idle).await;
+            continue;
+        };
+        let state = ZoneScheduleState::from_row(&row, cfg, now, now_ts);
+        if let ZoneScheduleState::Soaking { until, .. } = state {
+            let remaining = until.saturating_duration_since(now).as_secs();
+            info!(
+                zone = %row.zone_id,
+                was = %row.phase,
+                remaining_sec = remaining,
+                "scheduler: resuming soak after restart"
+            );
+            shared.write().await.record_scheduler(format!(
+                "{}: resumed soak after restart ({remaining}s left)",
+                row.zone_id
+            ));
+        }
+        persist_state(db, &row.zone_id, &state).await;
+        states.insert(row.zone_id, state);
+    }
+}
+
+/// Save a zone's state after it changed (idle clears it).  Failures are
+/// logged only: the worst case is a zone restarting its cycle after a
+/// restart.
+async fn persist_state(db: &Db, zone_id: &str, state: &ZoneScheduleState) {
+    let result = match state.to_row(zone_id, clock::now(), now_unix()) {
+        Some(row) => db.save_scheduler_state(&row).await,
+        None => db.clear_scheduler_state(zone_id).await,
+    };
+    if let Err(e) = result {
+        warn!(zone = %zone_id, "scheduler: persisting zone state failed: {e:#}");
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Decision audit
+// ---------------------------------------------------------------------------
+
+/// Outcome of evaluating one zone, persisted as a [`SchedulerDecision`].
+#[derive(Debug)]
+struct Evaluation {
+    avg_moisture: Option<f32>,
+    /// Guard that stopped the evaluation (`mqtt_disconnected`,
+    /// `db_degraded`, `emergency_stop`, `frost_lockout`, `low_pressure`,
+    /// `blackout`, `valve_on`, `max_concurrent_valves`, `no_readings`,
+    /// `stale_readings`, `daily_limit`, `budget`, `peak_sun`,
+    /// `dependency`, `safety_review`, `interlock`, `db_error`,
+    /// `publish_failed`).
+    blocked_by: Option<&'static str>,
+    /// `skip` when blocked; otherwise `wait`, `request_advice`, `pulse`,
+    /// `alert` (monitor mode), `pulse_end`, `soak_end_early`,
+    /// `soak_extend`, `target_reached`, `soak_done`, `flush` or
+    /// `flush_end`.
+    action: &'static str,
+    detail: String,
+}
+
+impl Evaluation {
+    fn blocked(guard: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            avg_moisture: None,
+            blocked_by: Some(guard),
+            action: "skip",
+            detail: detail.into(),
+        }
+    }
+
+    fn action(action: &'static str, avg_moisture: Option<f32>, detail: impl Into<String>) -> Self {
+        Self {
+            avg_moisture,
+            blocked_by: None,
+            action,
+            detail: detail.into(),
+        }
+    }
+
+    /// Whether an idle zone is done for the day as far as downstream zones
+    /// are concerned: it didn't need water, or can't water any more today.
+    fn settles(&self) -> bool {
+        self.action == "wait" || matches!(self.blocked_by, Some("daily_limit" | "budget"))
+    }
+
+    fn into_decision(self, ts: i64, zone_id: &str, phase: &str) -> SchedulerDecision {
+        SchedulerDecision {
+            ts,
+            zone_id: zone_id.to_string(),
+            phase: phase.to_string(),
+            avg_moisture: self.avg_moisture.map(f64::from),
+            blocked_by: self.blocked_by.map(str::to_string),
+            action: self.action.to_string(),
+            detail: self.detail,
+        }
+    }
+}
+
+/// Water budgets for one tick, with today's open seconds per zone.
+/// Pulses still running are counted up front, since their time only
+/// reaches the daily counters when the valve closes.
+struct TickBudget<'a> {
+    budget: &'a Budget,
+    zones: &'a HashMap<String, ZoneConfig>,
+    /// `None` if the counters couldn't be read (blocks budgeted pulses).
+    open_sec: Option<HashMap<String, i64>>,
+}
+
+impl TickBudget<'_> {
+    fn check(&self, cfg: &ZoneConfig) -> Result<(), Evaluation> {
+        let Some(open_sec) = &self.open_sec else {
+            return Err(Evaluation::blocked(
+                "db_error",
+                "budget counters unavailable",
+            ));
+        };
+        self.budget
+            .check(cfg, self.zones, open_sec)
+            .map_err(|why| Evaluation::blocked("budget", why))
+    }
+
+    /// Count a pulse started this tick before the next zone is checked.
+    fn add_pulse(&mut self, cfg: &ZoneConfig) {
+        if let Some(open_sec) = &mut self.open_sec {
+            *open_sec.entry(cfg.zone_id.clone()).or_default() += cfg.pulse_sec;
+        }
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -52,6 +313,7 @@ enum ZoneScheduleState {
 // ---------------------------------------------------------------------------
 
 /// Run the scheduler loop.  Intended to be `tokio::spawn`-ed from main.
+#[allow(clippy::too_many_arguments)]
 pub async fn run(
     db: Db,
     zone_configs: HashMap<String, ZoneConfig>,
@@ -59,17 +321,46 @@ pub async fn run(
     shared: SharedState,
     max_concurrent_valves: usize,
     mode: OperationMode,
+    soak_policy: SoakPolicy,
+    budget: Budget,
+    flush: FlushPlan,
+    interlocks: Interlocks,
 ) {
     let mut states: HashMap<String, ZoneScheduleState> = zone_configs
         .keys()
         .map(|z| (z.clone(), ZoneScheduleState::Idle))
         .collect();
+    restore_states(&db, &zone_configs, &mut states, &shared).await;
+    let mut strategies: HashMap<String, Box<dyn WateringStrategy>> = zone_configs
+        .iter()
+        .map(|(z, cfg)| (z.clone(), cfg.strategy.build()))
+        .collect();
+    // Day each zone last settled (finished a cycle, or was checked and
+    // didn't need water).  Zones with `after` wait on their upstream zones.
+    let mut settled: HashMap<String, String> = HashMap::new();
+    // Start of each flushed zone's last flush, from the watering events.
+    let mut last_flush: HashMap<String, i64> = HashMap::new();
+    // Zones waiting for a free valve slot; decides evaluation order.
+    let mut queue = FairQueue::default();
+    for zone_id in flush.zones() {
+        match db.last_watering_event_ts(zone_id, FLUSH_REASON).await {
+            Ok(Some(ts)) => {
+                last_flush.insert(zone_id.to_string(), ts);
+            }
+            Ok(None) => {}
+            Err(e) => error!(zone = %zone_id, "scheduler: last flush lookup failed: {e:#}"),
+        }
+    }
+
+    // First heartbeat now so /api/health doesn't report the scheduler dead
+    // during the startup delay.
+    shared.write().await.scheduler_heartbeat = Some(OffsetDateTime::now_utc());
 
     // Brief startup delay so the first telemetry readings can arrive before
     // the scheduler starts making decisions on empty data.
-    tokio::time::sleep(Duration::from_secs(TICK_INTERVAL_SEC)).await;
+    clock::sleep(Duration::from_secs(TICK_INTERVAL_SEC)).await;
 
-    let mut ticker = tokio::time::interval(Duration::from_secs(TICK_INTERVAL_SEC));
+    let mut ticker = clock::interval(Duration::from_secs(TICK_INTERVAL_SEC));
 
     info!(
         zones = zone_configs.len(),
@@ -87,95 +378,451 @@ pub async fn run(
 
     loop {
         ticker.tick().await;
-
-        // Snapshot how many valves are already open from SharedState, then
-        // track any additional ones started in *this* tick.  MQTT round-trips
-        // take ~ms to update SharedState, so without the local counter two
-        // Idle zones evaluated in the same tick could both publish ON.
-        let base_active = {
-            let st = shared.read().await;
-            st.zones.values().filter(|z| z.on).count()
-        };
-        let mut started_this_tick: usize = 0;
-
-        for (zone_id, zone_cfg) in &zone_configs {
-            let zone_state = states.get_mut(zone_id).expect("state map in sync");
-
-            match zone_state {
-                ZoneScheduleState::Idle => {
-                    if mode == OperationMode::Auto
-                        && base_active + started_this_tick >= max_concurrent_valves
-                    {
-                        continue;
-                    }
-                    handle_idle(
-                        zone_id,
-                        zone_cfg,
-                        zone_state,
-                        &db,
-                        &mqtt,
-                        &shared,
-                        max_concurrent_valves,
-                        mode,
+        async {
+            shared.write().await.scheduler_heartbeat = Some(OffsetDateTime::now_utc());
+            reload_moisture_window(&db, &shared).await;
+
+            // Snapshot how many valves are already open from SharedState, then
+            // track any additional ones started in *this* tick.  MQTT round-trips
+            // take ~ms to update SharedState, so without the local counter two
+            // Idle zones evaluated in the same tick could both publish ON.
+            let base_active = {
+                let st = shared.read().await;
+                st.zones.values().filter(|z| z.on).count()
+            };
+            let mut started_this_tick: usize = 0;
+            // Valves this scheduler holds open, including ones opened this
+            // tick, for the interlock check (same MQTT round-trip lag).
+            let mut scheduler_open: HashSet<String> = states
+                .iter()
+                .filter(|(_, s)| {
+                    matches!(
+                        s,
+                        ZoneScheduleState::Watering { .. } | ZoneScheduleState::Flushing { .. }
                     )
-                    .await;
-                    if mode == OperationMode::Auto
-                        && matches!(zone_state, ZoneScheduleState::Watering { .. })
+                })
+                .map(|(z, _)| z.clone())
+                .collect();
+            let tick_ts = now_unix();
+            let today = Db::today_yyyy_mm_dd();
+            let mut decisions: Vec<SchedulerDecision> = Vec::new();
+
+            let mut tick_budget = if budget.is_empty() {
+                None
+            } else {
+                let open_sec = match db.open_sec_by_zone(&today).await {
+                    Ok(mut open_sec) => {
+                        for (zone_id, st) in &states {
+                            if let (ZoneScheduleState::Watering { .. }, Some(cfg)) =
+                                (st, zone_configs.get(zone_id))
+                            {
+                                *open_sec.entry(zone_id.clone()).or_default() += cfg.pulse_sec;
+                            }
+                        }
+                        shared.write().await.budget = budget.usage(&zone_configs, &open_sec);
+                        Some(open_sec)
+                    }
+                    Err(e) => {
+                        error!("scheduler: open_sec_by_zone failed: {e:#}");
+                        None
+                    }
+                };
+                Some(TickBudget {
+                    budget: &budget,
+                    zones: &zone_configs,
+                    open_sec,
+                })
+            };
+
+            // Highest (aged) priority and driest zones first, so they get
+            // any free valve slots (see `arbitration`).
+            let now = clock::now();
+            let order = {
+                let st = shared.read().await;
+                queue.order(
+                    &zone_configs,
+                    |z| st.moisture.latest(z).map(|(_, m)| m),
+                    now,
+                )
+            };
+
+            for zone_cfg in order {
+                let zone_id = &zone_cfg.zone_id;
+                let zone_state = states.get_mut(zone_id).expect("state map in sync");
+                let strategy = strategies
+                    .get_mut(zone_id)
+                    .expect("strategy map in sync")
+                    .as_mut();
+                let phase = zone_state.phase();
+                let before = *zone_state;
+
+                let evaluation = match zone_state {
+                    ZoneScheduleState::Idle => {
+                        if mode == OperationMode::Auto
+                            && base_active + started_this_tick >= max_concurrent_valves
+                        {
+                            queue.mark_waiting(zone_id, now);
+                            decisions.push(
+                                Evaluation::blocked(
+                                    "max_concurrent_valves",
+                                    format!("{max_concurrent_valves} valve(s) already open"),
+                                )
+                                .into_decision(tick_ts, zone_id, phase),
+                            );
+                            continue;
+                        }
+                        let conflict = if mode == OperationMode::Auto {
+                            let st = shared.read().await;
+                            interlocks.conflict(zone_id, |z| {
+                                scheduler_open.contains(z) || st.zones.get(z).is_some_and(|s| s.on)
+                            })
+                        } else {
+                            None
+                        };
+                        if let Some(conflict) = conflict {
+                            queue.mark_waiting(zone_id, now);
+                            decisions.push(
+                                Evaluation::blocked("interlock", conflict.to_string())
+                                    .into_decision(tick_ts, zone_id, phase),
+                            );
+                            continue;
+                        }
+                        if let (OperationMode::Auto, Some(up)) = (
+                            mode,
+                            pending_upstream(zone_cfg, &zone_configs, &settled, &today),
+                        ) {
+                            decisions.push(
+                                Evaluation::blocked(
+                                    "dependency",
+                                    format!("waiting for zone '{up}' to finish today"),
+                                )
+                                .into_decision(tick_ts, zone_id, phase),
+                            );
+                            continue;
+                        }
+                        if mode == OperationMode::Auto
+                            && shared.read().await.needs_safety_review(zone_id)
+                        {
+                            decisions.push(
+                                Evaluation::blocked(
+                                    "safety_review",
+                                    "unacknowledged safety review findings",
+                                )
+                                .into_decision(tick_ts, zone_id, phase),
+                            );
+                            continue;
+                        }
+                        let flush_due = mode == OperationMode::Auto
+                            && flush.is_due(
+                                zone_id,
+                                last_flush.get(zone_id).copied(),
+                                clock::now_utc(),
+                            );
+                        let evaluation = if flush_due {
+                            start_flush(
+                                zone_id,
+                                zone_cfg,
+                                flush.duration(),
+                                zone_state,
+                                &db,
+                                &mqtt,
+                                &shared,
+                                max_concurrent_valves,
+                            )
+                            .await
+                        } else {
+                            handle_idle(
+                                zone_id,
+                                zone_cfg,
+                                zone_state,
+                                strategy,
+                                &db,
+                                &mqtt,
+                                &shared,
+                                max_concurrent_valves,
+                                mode,
+                                tick_budget.as_ref(),
+                            )
+                            .await
+                        };
+                        if evaluation.blocked_by == Some("max_concurrent_valves") {
+                            queue.mark_waiting(zone_id, now);
+                        } else {
+                            queue.clear(zone_id);
+                        }
+                        match zone_state {
+                            ZoneScheduleState::Watering { .. } if mode == OperationMode::Auto => {
+                                started_this_tick += 1;
+                                scheduler_open.insert(zone_id.clone());
+                                if let Some(b) = &mut tick_budget {
+                                    b.add_pulse(zone_cfg);
+                                }
+                            }
+                            ZoneScheduleState::Flushing { .. } => {
+                                started_this_tick += 1;
+                                scheduler_open.insert(zone_id.clone());
+                                last_flush.insert(zone_id.clone(), now_unix());
+                            }
+                            _ => {}
+                        }
+                        Some(evaluation)
+                    }
+                    ZoneScheduleState::Watering { since } => {
+                        handle_watering(zone_id, zone_cfg, *since, zone_state, &mqtt, &shared).await
+                    }
+                    ZoneScheduleState::Flushing { since, duration } => {
+                        handle_flushing(zone_id, *since, *duration, zone_state, &mqtt, &shared)
+                            .await
+                    }
+                    ZoneScheduleState::Soaking { until, .. } if !strategy.uses_moisture() => {
+                        // No moisture input: the soak is a plain timer.
+                        if clock::now() >= *until {
+                            *zone_state = ZoneScheduleState::Idle;
+                            Some(Evaluation::action("soak_done", None, "soak timer elapsed"))
+                        } else {
+                            None
+                        }
+                    }
+                    ZoneScheduleState::Soaking { .. } => {
+                        handle_soaking(zone_id, zone_cfg, &soak_policy, zone_state, &db, &shared)
+                            .await
+                    }
+                };
+                match zone_state {
+                    // A finished flush isn't a watering cycle.
+                    ZoneScheduleState::Idle if phase == "flushing" => {}
+                    ZoneScheduleState::Idle
+                        if phase != "idle"
+                            || evaluation.as_ref().is_some_and(Evaluation::settles) =>
                     {
-                        started_this_tick += 1;
+                        settled.insert(zone_id.clone(), today.clone());
+                    }
+                    ZoneScheduleState::Idle => {}
+                    _ => {
+                        settled.remove(zone_id);
                     }
                 }
-                ZoneScheduleState::Watering { since } => {
-                    handle_watering(zone_id, zone_cfg, *since, zone_state, &mqtt, &shared).await;
+                if *zone_state != before {
+                    persist_state(&db, zone_id, zone_state).await;
                 }
-                ZoneScheduleState::Soaking { until } => {
-                    handle_soaking(zone_id, zone_cfg, *until, zone_state, &db, &shared).await;
+                if let Some(evaluation) = evaluation {
+                    decisions.push(evaluation.into_decision(tick_ts, zone_id, phase));
                 }
             }
+
+            record_decisions(&db, &shared, &decisions).await;
+            check_moisture_alerts(&zone_configs, &db, &shared, tick_ts).await;
         }
+        .instrument(info_span!("scheduler.tick", zones = zone_configs.len()))
+        .await;
     }
 }
 
-// ---------------------------------------------------------------------------
-// State handlers
-// ---------------------------------------------------------------------------
+/// First upstream zone in `cfg.after` that hasn't settled today.  Zones no
+/// longer configured don't hold anything up.
+fn pending_upstream<'a>(
+    cfg: &'a ZoneConfig,
+    zones: &HashMap<String, ZoneConfig>,
+    settled: &HashMap<String, String>,
+    today: &str,
+) -> Option<&'a str> {
+    cfg.after
+        .iter()
+        .map(String::as_str)
+        .find(|up| zones.contains_key(*up) && settled.get(*up).map(String::as_str) != Some(today))
+}
 
-/// Idle: check moisture and decide whether to start a watering pulse.
-#[allow(clippy::too_many_arguments)]
-async fn handle_idle(
+/// Load the in-memory moisture window on the first tick, and again after
+/// it was invalidated by a config change.  On failure the scheduler keeps
+/// reading moisture from the database and retries next tick.
+async fn reload_moisture_window(db: &Db, shared: &SharedState) {
+    if shared.read().await.moisture.is_loaded() {
+        return;
+    }
+    match MoistureWindow::load(db).await {
+        Ok(window) => shared.write().await.moisture.replace(window),
+        Err(e) => warn!("scheduler: moisture window load failed: {e:#}"),
+    }
+}
+
+/// Newest undisturbed reading for the zone, from the moisture window once
+/// it is loaded.
+async fn latest_zone_moisture(
+    zone_id: &str,
+    db: &Db,
+    shared: &SharedState,
+) -> anyhow::Result<Option<(i64, f32)>> {
+    {
+        let st = shared.read().await;
+        if st.moisture.is_loaded() {
+            return Ok(st.moisture.latest(zone_id));
+        }
+    }
+    db.latest_zone_moisture(zone_id).await
+}
+
+/// The zone's moisture as the scheduler sees it: each sensor's mean over
+/// its last `AVG_WINDOW` readings within the staleness window, combined per
+/// the zone's aggregation.  Sensors reporting implausible values are left
+/// out until they recover.
+async fn zone_moisture(
     zone_id: &str,
     cfg: &ZoneConfig,
-    state: &mut ZoneScheduleState,
     db: &Db,
-    mqtt: &AsyncClient,
     shared: &SharedState,
-    max_concurrent_valves: usize,
-    mode: OperationMode,
+) -> anyhow::Result<Option<f32>> {
+    let since_ts = now_unix() - cfg.stale_timeout_min * 60;
+    let from_window = {
+        let st = shared.read().await;
+        st.moisture
+            .is_loaded()
+            .then(|| st.moisture.sensor_moisture(zone_id, AVG_WINDOW, since_ts))
+    };
+    let mut sensors = match from_window {
+        Some(sensors) => sensors,
+        None => {
+            db.zone_sensor_moisture(zone_id, AVG_WINDOW as i64, since_ts)
+                .await?
+        }
+    };
+    let st = shared.read().await;
+    sensors
+        .retain(|s| !st.is_sensor_faulted(&s.sensor_id) && !st.is_sensor_quarantined(&s.sensor_id));
+    Ok(cfg.aggregation.combine(&sensors))
+}
+
+/// Raise or clear low-moisture alerts (see `alerts`) from each zone's
+/// moisture as the watering decisions see it, whatever the mode.
+async fn check_moisture_alerts(
+    zone_configs: &HashMap<String, ZoneConfig>,
+    db: &Db,
+    shared: &SharedState,
+    now_ts: i64,
 ) {
-    // ── Guards (auto mode only) ──────────────────────────────────
-    if mode == OperationMode::Auto {
+    let zones: Vec<&ZoneConfig> = {
         let st = shared.read().await;
-        if !st.mqtt_connected {
+        if !st.moisture_alerts.is_enabled() {
             return;
         }
-        if let Some(z) = st.zones.get(zone_id) {
-            if z.on {
-                return;
+        let mut zones: Vec<&ZoneConfig> = zone_configs
+            .values()
+            .filter(|cfg| st.moisture_alerts.threshold(&cfg.zone_id).is_some())
+            .collect();
+        zones.sort_by(|a, b| a.zone_id.cmp(&b.zone_id));
+        zones
+    };
+    for cfg in zones {
+        let zone_id = &cfg.zone_id;
+        let moisture = match zone_moisture(zone_id, cfg, db, shared).await {
+            Ok(Some(m)) => m,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!(zone = %zone_id, "moisture alerts: zone moisture failed: {e:#}");
+                continue;
+            }
+        };
+        let mut st = shared.write().await;
+        match st.moisture_alerts.observe(zone_id, moisture, now_ts) {
+            Some(Change::Raised { threshold }) => {
+                warn!(zone = %zone_id, moisture, threshold, "low moisture alert");
+                st.record_alert(format!(
+                    "{zone_id}: moisture {moisture:.3} below alert threshold {threshold:.3}"
+                ));
+            }
+            Some(Change::Cleared) => {
+                info!(zone = %zone_id, moisture, "low moisture alert cleared");
+                st.record_alert(format!("{zone_id}: moisture back up to {moisture:.3}"));
             }
+            None => {}
         }
-        let active = st.zones.values().filter(|z| z.on).count();
-        if active >= max_concurrent_valves {
-            return;
+    }
+}
+
+/// Persist one tick's decisions.  Skipped while the database is degraded;
+/// losing audit rows is preferable to piling writes onto a failing disk.
+async fn record_decisions(db: &Db, shared: &SharedState, decisions: &[SchedulerDecision]) {
+    if decisions.is_empty() || shared.read().await.is_db_degraded() {
+        return;
+    }
+    if let Err(e) = db.insert_scheduler_decisions(decisions).await {
+        warn!("scheduler: failed to record decisions: {e:#}");
+    }
+}
+
+// ---------------------------------------------------------------------------
+// State handlers
+// ---------------------------------------------------------------------------
+
+/// Guards every auto-mode valve opening passes before its zone's own
+/// checks: broker connected, database writable, no emergency stop, frost
+/// or low-pressure lockout or blackout, valve not already open and a free
+/// concurrency slot.
+async fn check_auto_guards(
+    zone_id: &str,
+    shared: &SharedState,
+    max_concurrent_valves: usize,
+) -> Result<(), Evaluation> {
+    let st = shared.read().await;
+    if !st.mqtt_connected {
+        return Err(Evaluation::blocked("mqtt_disconnected", ""));
+    }
+    // Degraded DB mode: no new pulses until writes (and thus the daily
+    // safety counters) work again.  Running pulses still finish.
+    if st.is_db_degraded() {
+        return Err(Evaluation::blocked("db_degraded", ""));
+    }
+    if st.estop.is_latched() {
+        return Err(Evaluation::blocked(
+            "emergency_stop",
+            "cleared with POST /api/emergency-stop/clear",
+        ));
+    }
+    if st.frost.is_locked() {
+        return Err(Evaluation::blocked("frost_lockout", st.frost.reason()));
+    }
+    if st.pressure.is_low() {
+        return Err(Evaluation::blocked("low_pressure", st.pressure.reason()));
+    }
+    if let Some(b) = st.blackouts.active(clock::now_utc().date()) {
+        return Err(Evaluation::blocked(
+            "blackout",
+            format!("{} ({} to {})", b.reason, b.start, b.end),
+        ));
+    }
+    if let Some(z) = st.zones.get(zone_id) {
+        if z.on {
+            return Err(Evaluation::blocked(
+                "valve_on",
+                "valve opened outside the scheduler",
+            ));
         }
     }
+    let active = st.zones.values().filter(|z| z.on).count();
+    if active >= max_concurrent_valves {
+        return Err(Evaluation::blocked(
+            "max_concurrent_valves",
+            format!("{active} valve(s) already open"),
+        ));
+    }
+    Ok(())
+}
 
-    // ── Guard: fresh sensor data (both modes) ────────────────────
-    let latest = match db.latest_zone_moisture(zone_id).await {
+/// Whether the zone's newest reading is within its stale timeout.
+async fn check_fresh_readings(
+    zone_id: &str,
+    cfg: &ZoneConfig,
+    db: &Db,
+    shared: &SharedState,
+) -> Result<(), Evaluation> {
+    let latest = match latest_zone_moisture(zone_id, db, shared).await {
         Ok(Some(v)) => v,
-        Ok(None) => return,
+        Ok(None) => return Err(Evaluation::blocked("no_readings", "")),
         Err(e) => {
             error!(zone = %zone_id, "scheduler: latest_zone_moisture failed: {e}");
-            return;
+            return Err(Evaluation::blocked(
+                "db_error",
+                format!("latest_zone_moisture: {e}"),
+            ));
         }
     };
 
@@ -188,69 +835,375 @@ async fn handle_idle(
             stale_timeout_sec = stale_secs,
             "scheduler: stale sensor data — skipping"
         );
-        return;
+        return Err(Evaluation::blocked(
+            "stale_readings",
+            format!(
+                "last reading {}s ago (limit {stale_secs}s)",
+                now_ts - latest.0
+            ),
+        ));
     }
+    Ok(())
+}
 
-    // ── Guard: daily limits (auto mode only) ─────────────────────
-    if mode == OperationMode::Auto {
-        let today = Db::today_yyyy_mm_dd();
-        match db.get_daily_counters(&today, zone_id).await {
-            Ok(c) => {
-                if c.pulses >= cfg.max_pulses_per_day || c.open_sec >= cfg.max_open_sec_per_day {
-                    return;
-                }
+/// Whether the zone may water in the current light (see `sunlight`).
+/// Without its sensor list the zone goes by the brightest node.
+async fn check_sunlight(
+    zone_id: &str,
+    cfg: &ZoneConfig,
+    avg_moisture: Option<f32>,
+    db: &Db,
+    shared: &SharedState,
+) -> Result<(), Evaluation> {
+    if !shared.read().await.sunlight.is_enabled() {
+        return Ok(());
+    }
+    let nodes: Vec<String> = match db.load_sensors().await {
+        Ok(sensors) => sensors
+            .into_iter()
+            .filter(|s| s.zone_id == zone_id)
+            .map(|s| s.node_id)
+            .collect(),
+        Err(e) => {
+            warn!(zone = %zone_id, "scheduler: load_sensors failed: {e:#}");
+            Vec::new()
+        }
+    };
+    let since_ts = now_unix() - cfg.stale_timeout_min * 60;
+    let st = shared.read().await;
+    match st
+        .sunlight
+        .defer(&nodes, avg_moisture, cfg.min_moisture, since_ts)
+    {
+        Some(why) => Err(Evaluation::blocked("peak_sun", why)),
+        None => Ok(()),
+    }
+}
+
+/// The zone's usage over the last 24 hours: its watering events plus the
+/// pulses counted in memory during degraded mode, which have none.
+pub async fn rolling_usage(
+    db: &Db,
+    shared: &tokio::sync::RwLock<SystemState>,
+    zone_id: &str,
+) -> anyhow::Result<RollingCounters> {
+    let now = now_unix();
+    let mut r = db.rolling_counters(zone_id, now).await?;
+    let unlogged = shared.read().await.unlogged_counters(zone_id, now);
+    r.pulses += unlogged.pulses;
+    r.open_sec += unlogged.open_sec;
+    Ok(r)
+}
+
+/// Whether the zone has pulses and open seconds left, both today and in
+/// the last 24 hours (so watering either side of midnight can't double
+/// the allowance).
+async fn check_daily_limits(
+    zone_id: &str,
+    cfg: &ZoneConfig,
+    db: &Db,
+    shared: &SharedState,
+) -> Result<(), Evaluation> {
+    let today = Db::today_yyyy_mm_dd();
+    let counters = db.get_daily_counters(&today, zone_id).await;
+    if let Ok(c) = &counters {
+        // Kept for the manual-command caps should the DB degrade.
+        shared
+            .write()
+            .await
+            .cache_db_counters(&today, zone_id, c.pulses, c.open_sec);
+    }
+    let (pulses, open_sec, period) = match counters {
+        Ok(c) if c.pulses >= cfg.max_pulses_per_day || c.open_sec >= cfg.max_open_sec_per_day => {
+            (c.pulses, c.open_sec, "today")
+        }
+        Ok(_) => match rolling_usage(db, shared, zone_id).await {
+            Ok(r)
+                if r.pulses >= cfg.max_pulses_per_day || r.open_sec >= cfg.max_open_sec_per_day =>
+            {
+                (r.pulses, r.open_sec, "in the last 24h")
             }
+            Ok(_) => return Ok(()),
             Err(e) => {
-                error!(zone = %zone_id, "scheduler: get_daily_counters failed: {e}");
-                return;
+                error!(zone = %zone_id, "scheduler: rolling_counters failed: {e}");
+                return Err(Evaluation::blocked(
+                    "db_error",
+                    format!("rolling_counters: {e}"),
+                ));
             }
+        },
+        Err(e) => {
+            error!(zone = %zone_id, "scheduler: get_daily_counters failed: {e}");
+            return Err(Evaluation::blocked(
+                "db_error",
+                format!("get_daily_counters: {e}"),
+            ));
         }
+    };
+    Err(Evaluation::blocked(
+        "daily_limit",
+        format!(
+            "{pulses}/{} pulses, {open_sec}/{}s open {period}",
+            cfg.max_pulses_per_day, cfg.max_open_sec_per_day
+        ),
+    ))
+}
+
+/// Idle with an emitter flush due: open the valve for the flush duration.
+/// Auto mode only; the flush skips the strategy and sensor checks but not
+/// the daily limits.
+#[allow(clippy::too_many_arguments)]
+async fn start_flush(
+    zone_id: &str,
+    cfg: &ZoneConfig,
+    duration: Duration,
+    state: &mut ZoneScheduleState,
+    db: &Db,
+    mqtt: &AsyncClient,
+    shared: &SharedState,
+    max_concurrent_valves: usize,
+) -> Evaluation {
+    if let Err(blocked) = check_auto_guards(zone_id, shared, max_concurrent_valves).await {
+        return blocked;
+    }
+    if let Err(blocked) = check_daily_limits(zone_id, cfg, db, shared).await {
+        return blocked;
     }
 
-    // ── Moisture check (both modes) ──────────────────────────────
-    let avg_moisture = match db.avg_zone_moisture_last_n(zone_id, AVG_WINDOW).await {
-        Ok(Some(v)) => v,
-        Ok(None) => return,
-        Err(e) => {
-            error!(zone = %zone_id, "scheduler: avg_zone_moisture failed: {e}");
-            return;
+    info!(
+        zone = %zone_id,
+        duration_sec = duration.as_secs(),
+        "scheduler: starting emitter flush"
+    );
+    {
+        let mut st = shared.write().await;
+        st.metrics.stamp_scheduler_command(zone_id, true);
+        st.sessions
+            .plan(zone_id, FLUSH_REASON, Some(duration.as_secs() as i64));
+    }
+    if let Err(e) = mqtt
+        .publish(
+            valve_set_topic(zone_id),
+            QoS::AtLeastOnce,
+            false,
+            b"ON".to_vec(),
+        )
+        .await
+    {
+        error!(zone = %zone_id, "scheduler: failed to publish ON: {e}");
+        shared.write().await.sessions.unplan(zone_id);
+        return Evaluation::blocked("publish_failed", format!("ON: {e}"));
+    }
+    shared.write().await.record_scheduler(format!(
+        "{zone_id}: emitter flush started ({}s)",
+        duration.as_secs()
+    ));
+
+    *state = ZoneScheduleState::Flushing {
+        since: clock::now(),
+        duration,
+    };
+    Evaluation::action(
+        "flush",
+        None,
+        format!("{}s emitter flush", duration.as_secs()),
+    )
+}
+
+/// Flushing: send OFF once the flush duration has elapsed.  No soak
+/// follows; the zone goes straight back to idle.
+#[instrument(name = "scheduler.flushing", skip_all, fields(zone = %zone_id))]
+async fn handle_flushing(
+    zone_id: &str,
+    since: Instant,
+    duration: Duration,
+    state: &mut ZoneScheduleState,
+    mqtt: &AsyncClient,
+    shared: &SharedState,
+) -> Option<Evaluation> {
+    if clock::since(since) < duration {
+        return None;
+    }
+    shared
+        .write()
+        .await
+        .metrics
+        .stamp_scheduler_command(zone_id, false);
+    if let Err(e) = mqtt
+        .publish(
+            valve_set_topic(zone_id),
+            QoS::AtLeastOnce,
+            false,
+            b"OFF".to_vec(),
+        )
+        .await
+    {
+        error!(zone = %zone_id, "scheduler: failed to publish OFF: {e}");
+        // Don't transition — watchdog will catch it if OFF never arrives.
+        return Some(Evaluation::blocked("publish_failed", format!("OFF: {e}")));
+    }
+    info!(zone = %zone_id, "scheduler: emitter flush complete");
+    shared
+        .write()
+        .await
+        .record_scheduler(format!("{zone_id}: emitter flush done"));
+    *state = ZoneScheduleState::Idle;
+    Some(Evaluation::action("flush_end", None, "emitter flush done"))
+}
+
+/// Idle: check moisture and decide whether to start a watering pulse.
+#[allow(clippy::too_many_arguments)]
+#[instrument(name = "scheduler.idle", skip_all, fields(zone = %zone_id))]
+async fn handle_idle(
+    zone_id: &str,
+    cfg: &ZoneConfig,
+    state: &mut ZoneScheduleState,
+    strategy: &mut dyn WateringStrategy,
+    db: &Db,
+    mqtt: &AsyncClient,
+    shared: &SharedState,
+    max_concurrent_valves: usize,
+    mode: OperationMode,
+    budget: Option<&TickBudget<'_>>,
+) -> Evaluation {
+    // ── Guards (auto mode only) ──────────────────────────────────
+    if mode == OperationMode::Auto {
+        if let Err(blocked) = check_auto_guards(zone_id, shared, max_concurrent_valves).await {
+            return blocked;
+        }
+    }
+
+    // ── Guard: fresh sensor data (moisture strategies) ──────────
+    if strategy.uses_moisture() {
+        if let Err(blocked) = check_fresh_readings(zone_id, cfg, db, shared).await {
+            return blocked;
+        }
+    }
+
+    // ── Guard: daily limits (auto mode only) ─────────────────────
+    if mode == OperationMode::Auto {
+        if let Err(blocked) = check_daily_limits(zone_id, cfg, db, shared).await {
+            return blocked;
+        }
+    }
+
+    // ── Strategy decision (both modes) ───────────────────────────
+    let avg_moisture = if strategy.uses_moisture() {
+        match zone_moisture(zone_id, cfg, db, shared).await {
+            Ok(Some(v)) => Some(v),
+            Ok(None) => return Evaluation::blocked("no_readings", ""),
+            Err(e) => {
+                error!(zone = %zone_id, "scheduler: zone_moisture failed: {e}");
+                return Evaluation::blocked("db_error", format!("zone_moisture: {e}"));
+            }
         }
+    } else {
+        None
     };
 
-    if avg_moisture >= cfg.min_moisture {
-        return;
+    let (advice, mut deficit_mm) = {
+        let mut st = shared.write().await;
+        (
+            st.take_advice(zone_id),
+            st.et_deficits.get(zone_id).copied(),
+        )
+    };
+    // An `et` zone whose sensors read at target has a full root zone,
+    // whatever the water balance says.
+    if cfg.strategy.crop_coefficient().is_some()
+        && avg_moisture.is_some_and(|avg| avg >= cfg.target_moisture)
+        && deficit_mm.is_some_and(|d| d > 0.0)
+    {
+        reset_et_deficit(zone_id, deficit_mm.unwrap_or(0.0), db, shared).await;
+        deficit_mm = Some(0.0);
+    }
+    let ctx = ZoneContext {
+        cfg,
+        avg_moisture,
+        now: clock::now_utc(),
+        advice,
+        deficit_mm,
+    };
+    let reason = match strategy.on_idle(&ctx) {
+        IdleDecision::Wait => return Evaluation::action("wait", avg_moisture, strategy.name()),
+        IdleDecision::RequestAdvice => {
+            request_advice(zone_id, cfg, avg_moisture, db, mqtt, mode).await;
+            return Evaluation::action("request_advice", avg_moisture, strategy.name());
+        }
+        IdleDecision::Pulse { reason } => reason,
+    };
+
+    // ── Guard: peak sun (auto mode only) ─────────────────────────
+    if mode == OperationMode::Auto {
+        if let Err(blocked) = check_sunlight(zone_id, cfg, avg_moisture, db, shared).await {
+            return Evaluation {
+                avg_moisture,
+                ..blocked
+            };
+        }
+    }
+
+    // ── Guard: shared water budget (auto mode only) ─────────────
+    if mode == OperationMode::Auto {
+        if let Some(Err(blocked)) = budget.map(|b| b.check(cfg)) {
+            info!(
+                zone = %zone_id,
+                why = %blocked.detail,
+                "scheduler: pulse deferred by water budget"
+            );
+            return Evaluation {
+                avg_moisture,
+                ..blocked
+            };
+        }
     }
 
     // ── Monitor mode: record alert, stay idle ────────────────────
     if mode == OperationMode::Monitor {
         info!(
             zone = %zone_id,
-            avg_moisture = format!("{avg_moisture:.3}"),
-            min = format!("{:.3}", cfg.min_moisture),
-            "scheduler: low moisture alert (monitor mode)"
+            strategy = strategy.name(),
+            %reason,
+            "scheduler: would water (monitor mode)"
         );
         {
             let mut st = shared.write().await;
-            st.record_scheduler(format!(
-                "{zone_id}: low moisture alert ({avg_moisture:.3} < min {:.3})",
-                cfg.min_moisture
-            ));
+            let what = if strategy.uses_moisture() {
+                "low moisture alert"
+            } else {
+                "watering due"
+            };
+            st.record_scheduler(format!("{zone_id}: {what} ({reason})"));
         }
-        return; // stay Idle — no valve actuation
+        strategy.on_pulse();
+        // Stay Idle — no valve actuation.
+        return Evaluation::action(
+            "alert",
+            avg_moisture,
+            format!("{}: {reason}", strategy.name()),
+        );
     }
 
     // ── Auto mode: trigger watering pulse ────────────────────────
     info!(
         zone = %zone_id,
-        avg_moisture = format!("{avg_moisture:.3}"),
-        min = format!("{:.3}", cfg.min_moisture),
+        strategy = strategy.name(),
+        %reason,
         pulse_sec = cfg.pulse_sec,
-        "scheduler: moisture below min — starting pulse"
+        "scheduler: starting pulse"
     );
 
+    // Stamp before publishing so the round-trip is attributed to the
+    // scheduler in the valve latency histogram.
+    {
+        let mut st = shared.write().await;
+        st.metrics.stamp_scheduler_command(zone_id, true);
+        st.sessions
+            .plan(zone_id, SCHEDULER_REASON, Some(cfg.pulse_sec));
+    }
     if let Err(e) = mqtt
         .publish(
-            format!("valve/{zone_id}/set"),
+            valve_set_topic(zone_id),
             QoS::AtLeastOnce,
             false,
             b"ON".to_vec(),
@@ -258,23 +1211,31 @@ async fn handle_idle(
         .await
     {
         error!(zone = %zone_id, "scheduler: failed to publish ON: {e}");
-        return;
+        shared.write().await.sessions.unplan(zone_id);
+        return Evaluation::blocked("publish_failed", format!("ON: {e}"));
     }
+    strategy.on_pulse();
 
     {
         let mut st = shared.write().await;
         st.record_scheduler(format!(
-            "{zone_id}: pulse started (moisture {avg_moisture:.3} < min {:.3})",
-            cfg.min_moisture
+            "{zone_id}: pulse started ({}: {reason})",
+            strategy.name()
         ));
     }
 
     *state = ZoneScheduleState::Watering {
-        since: Instant::now(),
+        since: clock::now(),
     };
+    Evaluation::action(
+        "pulse",
+        avg_moisture,
+        format!("{}: {reason}", strategy.name()),
+    )
 }
 
 /// Watering: check if pulse duration has elapsed, then send OFF.
+#[instrument(name = "scheduler.watering", skip_all, fields(zone = %zone_id))]
 async fn handle_watering(
     zone_id: &str,
     cfg: &ZoneConfig,
@@ -282,15 +1243,22 @@ async fn handle_watering(
     state: &mut ZoneScheduleState,
     mqtt: &AsyncClient,
     shared: &SharedState,
-) {
-    if since.elapsed().as_secs() < cfg.pulse_sec as u64 {
-        return; // pulse still running
+) -> Option<Evaluation> {
+    if clock::since(since).as_secs() < cfg.pulse_sec as u64 {
+        return None; // pulse still running
     }
 
     // Pulse complete — turn valve off and enter soak.
+    // Stamp before publishing so the round-trip is attributed to the
+    // scheduler in the valve latency histogram.
+    shared
+        .write()
+        .await
+        .metrics
+        .stamp_scheduler_command(zone_id, false);
     if let Err(e) = mqtt
         .publish(
-            format!("valve/{zone_id}/set"),
+            valve_set_topic(zone_id),
             QoS::AtLeastOnce,
             false,
             b"OFF".to_vec(),
@@ -299,7 +1267,7 @@ async fn handle_watering(
     {
         error!(zone = %zone_id, "scheduler: failed to publish OFF: {e}");
         // Don't transition — watchdog will catch it if OFF never arrives.
-        return;
+        return Some(Evaluation::blocked("publish_failed", format!("OFF: {e}")));
     }
 
     let soak_duration = Duration::from_secs(cfg.soak_min as u64 * 60);
@@ -318,36 +1286,100 @@ async fn handle_watering(
         ));
     }
 
+    let now = clock::now();
     *state = ZoneScheduleState::Soaking {
-        until: Instant::now() + soak_duration,
+        started: now,
+        until: now + soak_duration,
+        extended_sec: 0,
     };
+    Some(Evaluation::action(
+        "pulse_end",
+        None,
+        format!("soaking {}min", cfg.soak_min),
+    ))
 }
 
 /// Soaking: wait for soak timer, then re-check moisture.
+///
+/// With `policy.early_exit`, the soak ends as soon as the averaged moisture
+/// reaches `target_moisture` (after a minimum soak time).  With
+/// `policy.extend_on_rise`, an expired soak is extended while moisture is
+/// still climbing sharply, up to `policy.max_extend_min` in total.
+#[instrument(name = "scheduler.soaking", skip_all, fields(zone = %zone_id))]
 async fn handle_soaking(
     zone_id: &str,
     cfg: &ZoneConfig,
-    until: Instant,
+    policy: &SoakPolicy,
     state: &mut ZoneScheduleState,
     db: &Db,
     shared: &SharedState,
-) {
-    if Instant::now() < until {
-        return; // still soaking
+) -> Option<Evaluation> {
+    let ZoneScheduleState::Soaking {
+        started,
+        until,
+        extended_sec,
+    } = *state
+    else {
+        return None;
+    };
+
+    let now = clock::now();
+    if now < until {
+        // Still soaking — optionally cut it short once the target is reached.
+        if !policy.early_exit
+            || clock::since(started).as_secs() < policy.early_exit_after_min as u64 * 60
+        {
+            return None;
+        }
+        let avg_moisture = match zone_moisture(zone_id, cfg, db, shared).await {
+            Ok(Some(v)) => v,
+            Ok(None) => return None,
+            Err(e) => {
+                error!(zone = %zone_id, "scheduler: zone_moisture failed: {e}");
+                return None;
+            }
+        };
+        if avg_moisture >= cfg.target_moisture {
+            let remaining = until.saturating_duration_since(now).as_secs();
+            info!(
+                zone = %zone_id,
+                avg_moisture = format!("{avg_moisture:.3}"),
+                target = format!("{:.3}", cfg.target_moisture),
+                remaining_sec = remaining,
+                "scheduler: target reached during soak — ending soak early"
+            );
+            {
+                let mut st = shared.write().await;
+                st.record_scheduler(format!(
+                    "{zone_id}: soak ended early, {remaining}s remaining (moisture {avg_moisture:.3} >= target {:.3})",
+                    cfg.target_moisture
+                ));
+            }
+            *state = ZoneScheduleState::Idle;
+            return Some(Evaluation::action(
+                "soak_end_early",
+                Some(avg_moisture),
+                format!("{remaining}s remaining"),
+            ));
+        }
+        return None;
     }
 
     // Soak complete — re-evaluate moisture.
-    let avg_moisture = match db.avg_zone_moisture_last_n(zone_id, AVG_WINDOW).await {
+    let avg_moisture = match zone_moisture(zone_id, cfg, db, shared).await {
         Ok(Some(v)) => v,
         Ok(None) => {
             // Lost all readings during soak — go idle to be safe.
             *state = ZoneScheduleState::Idle;
-            return;
+            return Some(Evaluation::blocked("no_readings", "soak done"));
         }
         Err(e) => {
-            error!(zone = %zone_id, "scheduler: avg_zone_moisture failed: {e}");
+            error!(zone = %zone_id, "scheduler: zone_moisture failed: {e}");
             *state = ZoneScheduleState::Idle;
-            return;
+            return Some(Evaluation::blocked(
+                "db_error",
+                format!("zone_moisture: {e}"),
+            ));
         }
     };
 
@@ -366,35 +1398,384 @@ async fn handle_soaking(
             ));
         }
         *state = ZoneScheduleState::Idle;
+        return Some(Evaluation::action(
+            "target_reached",
+            Some(avg_moisture),
+            format!("target {:.3}", cfg.target_moisture),
+        ));
+    }
+
+    // Still below target — if water is still moving down to the probe,
+    // give it more time rather than pulsing again on a stale picture.
+    let max_extend_sec = policy.max_extend_min.max(0) as u64 * 60;
+    if policy.extend_on_rise && extended_sec < max_extend_sec {
+        let since_ts = now_unix() - policy.rise_window_min * 60;
+        match db.zone_moisture_since(zone_id, since_ts).await {
+            Ok(points) => {
+                if let Some(slope) = moisture_slope_per_min(&points) {
+                    if slope > policy.rise_threshold_per_min {
+                        let step = SOAK_EXTEND_STEP_SEC.min(max_extend_sec - extended_sec);
+                        info!(
+                            zone = %zone_id,
+                            slope_per_min = format!("{slope:.4}"),
+                            extend_sec = step,
+                            "scheduler: moisture still rising — extending soak"
+                        );
+                        {
+                            let mut st = shared.write().await;
+                            st.record_scheduler(format!(
+                                "{zone_id}: soak extended {step}s (moisture rising {slope:.4}/min)"
+                            ));
+                        }
+                        *state = ZoneScheduleState::Soaking {
+                            started,
+                            until: now + Duration::from_secs(step),
+                            extended_sec: extended_sec + step,
+                        };
+                        return Some(Evaluation::action(
+                            "soak_extend",
+                            Some(avg_moisture),
+                            format!("+{step}s, rising {slope:.4}/min"),
+                        ));
+                    }
+                }
+            }
+            Err(e) => {
+                error!(zone = %zone_id, "scheduler: zone_moisture_since failed: {e}");
+            }
+        }
+    }
+
+    info!(
+        zone = %zone_id,
+        avg_moisture = format!("{avg_moisture:.3}"),
+        target = format!("{:.3}", cfg.target_moisture),
+        "scheduler: soak complete, moisture still below target — will re-evaluate"
+    );
+    {
+        let mut st = shared.write().await;
+        st.record_scheduler(format!(
+            "{zone_id}: soak done, moisture {avg_moisture:.3} < target {:.3}",
+            cfg.target_moisture
+        ));
+    }
+    // Return to Idle so the full guard-check sequence runs again
+    // (staleness, daily limits, MQTT connectivity) before the next pulse.
+    *state = ZoneScheduleState::Idle;
+    Some(Evaluation::action(
+        "soak_done",
+        Some(avg_moisture),
+        format!("below target {:.3}", cfg.target_moisture),
+    ))
+}
+
+// ---------------------------------------------------------------------------
+// Dry run
+// ---------------------------------------------------------------------------
+
+/// What the scheduler would do with a zone right now, for
+/// `GET /api/scheduler/preview`.
+#[derive(Debug, Serialize)]
+pub struct ZonePreview {
+    pub zone_id: String,
+    /// `idle`, or the phase of a cycle in progress.
+    pub phase: String,
+    /// `water`, `alert` (monitor mode), `wait`, `request_advice`,
+    /// `blocked` or `busy` (mid-cycle).
+    pub verdict: &'static str,
+    pub blocked_by: Option<&'static str>,
+    pub avg_moisture: Option<f32>,
+    /// E.g. "would water: threshold: moisture 0.210 < min 0.300".
+    pub summary: String,
+}
+
+/// Run the idle decision for every zone against current data, as the next
+/// tick would, without publishing or changing anything.  Each zone gets a
+/// fresh strategy, so a schedule slot or advice already acted on still
+/// shows as due.  `after` dependencies aren't checked: which zones have
+/// settled today is known only to the running scheduler.
+pub async fn preview(
+    db: &Db,
+    zones: &[ZoneConfig],
+    shared: &SharedState,
+) -> anyhow::Result<Vec<ZonePreview>> {
+    let phases: HashMap<String, String> = db
+        .load_scheduler_states()
+        .await?
+        .into_iter()
+        .map(|s| (s.zone_id, s.phase))
+        .collect();
+    let (mode, max_concurrent_valves, interlocks, budget) = {
+        let st = shared.read().await;
+        (
+            st.limits.mode,
+            st.limits.max_concurrent_valves,
+            Interlocks::new(&st.limits.interlocks),
+            Budget::new(&st.limits.budget),
+        )
+    };
+    let zone_map: HashMap<String, ZoneConfig> = zones
+        .iter()
+        .map(|z| (z.zone_id.clone(), z.clone()))
+        .collect();
+    let open_sec = if budget.is_empty() {
+        None
     } else {
-        info!(
-            zone = %zone_id,
-            avg_moisture = format!("{avg_moisture:.3}"),
-            target = format!("{:.3}", cfg.target_moisture),
-            "scheduler: soak complete, moisture still below target — will re-evaluate"
-        );
+        Some(db.open_sec_by_zone(&Db::today_yyyy_mm_dd()).await?)
+    };
+    let tick_budget = TickBudget {
+        budget: &budget,
+        zones: &zone_map,
+        open_sec,
+    };
+
+    let mut previews = Vec::with_capacity(zones.len());
+    for cfg in zones {
+        let phase = phases
+            .get(&cfg.zone_id)
+            .cloned()
+            .unwrap_or_else(|| "idle".to_string());
+        let evaluation = if phase != "idle" {
+            None
+        } else {
+            Some(
+                preview_idle(
+                    cfg,
+                    db,
+                    shared,
+                    mode,
+                    max_concurrent_valves,
+                    &interlocks,
+                    (!budget.is_empty()).then_some(&tick_budget),
+                )
+                .await,
+            )
+        };
+        previews.push(ZonePreview::new(&cfg.zone_id, phase, evaluation));
+    }
+    Ok(previews)
+}
+
+/// The checks of an idle tick, in `run` and `handle_idle` order.
+async fn preview_idle(
+    cfg: &ZoneConfig,
+    db: &Db,
+    shared: &SharedState,
+    mode: OperationMode,
+    max_concurrent_valves: usize,
+    interlocks: &Interlocks,
+    budget: Option<&TickBudget<'_>>,
+) -> Evaluation {
+    let zone_id = cfg.zone_id.as_str();
+    let mut strategy = cfg.strategy.build();
+    if mode == OperationMode::Auto {
+        let st = shared.read().await;
+        if let Some(conflict) =
+            interlocks.conflict(zone_id, |z| st.zones.get(z).is_some_and(|s| s.on))
         {
-            let mut st = shared.write().await;
-            st.record_scheduler(format!(
-                "{zone_id}: soak done, moisture {avg_moisture:.3} < target {:.3}",
-                cfg.target_moisture
-            ));
+            return Evaluation::blocked("interlock", conflict.to_string());
         }
-        // Return to Idle so the full guard-check sequence runs again
-        // (staleness, daily limits, MQTT connectivity) before the next pulse.
-        *state = ZoneScheduleState::Idle;
+        if st.needs_safety_review(zone_id) {
+            return Evaluation::blocked("safety_review", "unacknowledged safety review findings");
+        }
+        drop(st);
+        if let Err(blocked) = check_auto_guards(zone_id, shared, max_concurrent_valves).await {
+            return blocked;
+        }
+    }
+    if strategy.uses_moisture() {
+        if let Err(blocked) = check_fresh_readings(zone_id, cfg, db, shared).await {
+            return blocked;
+        }
+    }
+    if mode == OperationMode::Auto {
+        if let Err(blocked) = check_daily_limits(zone_id, cfg, db, shared).await {
+            return blocked;
+        }
+    }
+    let avg_moisture = if strategy.uses_moisture() {
+        match zone_moisture(zone_id, cfg, db, shared).await {
+            Ok(Some(v)) => Some(v),
+            Ok(None) => return Evaluation::blocked("no_readings", ""),
+            Err(e) => return Evaluation::blocked("db_error", format!("zone_moisture: {e}")),
+        }
+    } else {
+        None
+    };
+
+    let (advice, mut deficit_mm) = {
+        let st = shared.read().await;
+        (
+            st.peek_advice(zone_id).cloned(),
+            st.et_deficits.get(zone_id).copied(),
+        )
+    };
+    if cfg.strategy.crop_coefficient().is_some()
+        && avg_moisture.is_some_and(|avg| avg >= cfg.target_moisture)
+    {
+        deficit_mm = deficit_mm.map(|_| 0.0);
+    }
+    let ctx = ZoneContext {
+        cfg,
+        avg_moisture,
+        now: clock::now_utc(),
+        advice,
+        deficit_mm,
+    };
+    let reason = match strategy.on_idle(&ctx) {
+        IdleDecision::Wait => return Evaluation::action("wait", avg_moisture, strategy.name()),
+        IdleDecision::RequestAdvice => {
+            return Evaluation::action("request_advice", avg_moisture, strategy.name())
+        }
+        IdleDecision::Pulse { reason } => format!("{}: {reason}", strategy.name()),
+    };
+    if mode == OperationMode::Monitor {
+        return Evaluation::action("alert", avg_moisture, reason);
+    }
+    if let Err(blocked) = check_sunlight(zone_id, cfg, avg_moisture, db, shared).await {
+        return Evaluation {
+            avg_moisture,
+            ..blocked
+        };
+    }
+    if let Some(Err(blocked)) = budget.map(|b| b.check(cfg)) {
+        return Evaluation {
+            avg_moisture,
+            ..blocked
+        };
+    }
+    Evaluation::action("pulse", avg_moisture, reason)
+}
+
+impl ZonePreview {
+    fn new(zone_id: &str, phase: String, evaluation: Option<Evaluation>) -> Self {
+        let Some(e) = evaluation else {
+            return Self {
+                zone_id: zone_id.to_string(),
+                summary: format!("busy: {phase}"),
+                phase,
+                verdict: "busy",
+                blocked_by: None,
+                avg_moisture: None,
+            };
+        };
+        let (verdict, summary) = match (e.action, e.blocked_by) {
+            (_, Some(guard)) if e.detail.is_empty() => ("blocked", format!("blocked: {guard}")),
+            (_, Some(guard)) => ("blocked", format!("blocked: {guard}: {}", e.detail)),
+            ("pulse", _) => ("water", format!("would water: {}", e.detail)),
+            ("alert", _) => ("alert", format!("would alert: {}", e.detail)),
+            ("request_advice", _) => (
+                "request_advice",
+                format!("would request advice: {}", e.detail),
+            ),
+            _ => match e.avg_moisture {
+                Some(avg) => ("wait", format!("wait: {} (moisture {avg:.3})", e.detail)),
+                None => ("wait", format!("wait: {}", e.detail)),
+            },
+        };
+        Self {
+            zone_id: zone_id.to_string(),
+            phase,
+            verdict,
+            blocked_by: e.blocked_by,
+            avg_moisture: e.avg_moisture,
+            summary,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+/// Zero an `et` zone's deficit after its sensors showed it at target.
+async fn reset_et_deficit(zone_id: &str, was_mm: f64, db: &Db, shared: &SharedState) {
+    info!(zone = %zone_id, was_mm, "scheduler: moisture at target — deficit reset");
+    {
+        let mut st = shared.write().await;
+        st.et_deficits.insert(zone_id.to_string(), 0.0);
+        st.record_scheduler(format!(
+            "{zone_id}: moisture at target, water-balance deficit reset (was {was_mm:.1} mm)"
+        ));
+    }
+    if let Err(e) = db.set_et_deficit(zone_id, 0.0, now_unix()).await {
+        error!(zone = %zone_id, "scheduler: set_et_deficit failed: {e:#}");
+    }
+}
+
+/// Publish the zone's context to `advice/<zone_id>/request` for an external
+/// advisor.  The answer arrives asynchronously on `advice/<zone_id>/response`.
+async fn request_advice(
+    zone_id: &str,
+    cfg: &ZoneConfig,
+    avg_moisture: Option<f32>,
+    db: &Db,
+    mqtt: &AsyncClient,
+    mode: OperationMode,
+) {
+    let today = Db::today_yyyy_mm_dd();
+    let counters = match db.get_daily_counters(&today, zone_id).await {
+        Ok(c) => c,
+        Err(e) => {
+            error!(zone = %zone_id, "scheduler: get_daily_counters failed: {e}");
+            return;
+        }
+    };
+    let req = AdviceRequest {
+        zone_id,
+        ts: now_unix(),
+        mode,
+        avg_moisture,
+        min_moisture: cfg.min_moisture,
+        target_moisture: cfg.target_moisture,
+        pulse_sec: cfg.pulse_sec,
+        soak_min: cfg.soak_min,
+        pulses_today: counters.pulses,
+        open_sec_today: counters.open_sec,
+        max_pulses_per_day: cfg.max_pulses_per_day,
+        max_open_sec_per_day: cfg.max_open_sec_per_day,
+    };
+    let payload = serde_json::to_vec(&req).expect("advice request serialization failed");
+    if let Err(e) = mqtt
+        .publish(
+            advice_request_topic(zone_id),
+            QoS::AtLeastOnce,
+            false,
+            payload,
+        )
+        .await
+    {
+        error!(zone = %zone_id, "scheduler: failed to publish advice request: {e}");
+        return;
     }
+    info!(zone = %zone_id, "scheduler: advice requested");
 }
 
-// ---------------------------------------------------------------------------
-// Helpers
-// ---------------------------------------------------------------------------
+/// Least-squares slope of a moisture series, in moisture fraction per minute.
+/// Returns `None` with fewer than two points or no time spread.
+fn moisture_slope_per_min(points: &[(i64, f32)]) -> Option<f32> {
+    if points.len() < 2 {
+        return None;
+    }
+    let n = points.len() as f64;
+    let t0 = points[0].0;
+    let mean_t = points.iter().map(|(t, _)| (t - t0) as f64).sum::<f64>() / n;
+    let mean_m = points.iter().map(|(_, m)| *m as f64).sum::<f64>() / n;
+    let mut num = 0.0;
+    let mut den = 0.0;
+    for (t, m) in points {
+        let dt = (t - t0) as f64 - mean_t;
+        num += dt * (*m as f64 - mean_m);
+        den += dt * dt;
+    }
+    if den == 0.0 {
+        return None;
+    }
+    Some((num / den * 60.0) as f32)
+}
 
 fn now_unix() -> i64 {
-    std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs() as i64
+    clock::now_unix().max(0)
 }
 
 // ===========================================================================
@@ -404,28 +1785,14 @@ fn now_unix() -> i64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::aggregation::Aggregation;
     use crate::config::OperationMode;
-    use crate::db::{Db, SensorConfig, ZoneConfig};
+    use crate::db::{Db, EventDetails, SensorConfig, ZoneConfig};
     use crate::state::SystemState;
+    use crate::strategy::{StrategyConfig, ThresholdStrategy};
     use std::sync::Arc;
     use tokio::sync::RwLock;
 
-    /// Build a zone config with sensible defaults for testing.
-    fn test_zone_cfg() -> ZoneConfig {
-        ZoneConfig {
-            zone_id: "z1".into(),
-            name: "Test Zone".into(),
-            min_moisture: 0.3,
-            target_moisture: 0.5,
-            pulse_sec: 30,
-            soak_min: 20,
-            max_open_sec_per_day: 180,
-            max_pulses_per_day: 6,
-            stale_timeout_min: 30,
-            valve_gpio_pin: 17,
-        }
-    }
-
     /// Build a SharedState with one zone.
     fn test_shared() -> SharedState {
         Arc::new(RwLock::new(SystemState::new(
@@ -438,13 +1805,20 @@ mod tests {
     async fn seeded_db(moisture_values: &[f32]) -> Db {
         let db = Db::connect("sqlite::memory:").await.unwrap();
         db.migrate().await.unwrap();
-        db.upsert_zone(&test_zone_cfg()).await.unwrap();
+        db.upsert_zone(&ZoneConfig::test("z1")).await.unwrap();
         db.upsert_sensor(&SensorConfig {
             sensor_id: "s1".into(),
             node_id: "n1".into(),
             zone_id: "z1".into(),
             raw_dry: 26000,
             raw_wet: 12000,
+            archived_at: None,
+            channel: None,
+            weight: 1.0,
+            failure_margin: None,
+            failure_margin_pct: None,
+            curve: None,
+            enabled: true,
         })
         .await
         .unwrap();
@@ -462,6 +1836,26 @@ mod tests {
         db
     }
 
+    /// A soak that started `soak_min` ago-ish and ends at `until`.
+    fn soaking_until(until: Instant) -> ZoneScheduleState {
+        ZoneScheduleState::Soaking {
+            started: Instant::now() - Duration::from_secs(20 * 60),
+            until,
+            extended_sec: 0,
+        }
+    }
+
+    /// Seed readings at explicit ages (seconds ago) for rise-rate tests.
+    async fn seeded_db_at(points: &[(i64, f32)]) -> Db {
+        let db = seeded_db(&[]).await;
+        let now = now_unix();
+        for &(age, m) in points {
+            let raw = 26000 - (m * 14000.0) as i64;
+            db.insert_reading(now - age, "s1", raw, m).await.unwrap();
+        }
+        db
+    }
+
     /// Create a minimal MQTT AsyncClient.  We never poll its event loop, so
     /// publishes just accumulate in the internal buffer — sufficient for
     /// verifying that handler logic transitions state correctly.
@@ -473,13 +1867,84 @@ mod tests {
         AsyncClient::new(opts, 10)
     }
 
+    // -- Persisted state survives a restart -------------------------------
+
+    #[tokio::test]
+    async fn soak_state_is_restored_after_restart() {
+        let db = seeded_db(&[]).await;
+        let zones = HashMap::from([("z1".to_string(), ZoneConfig::test("z1"))]);
+        let shared = test_shared();
+        let now = Instant::now();
+        let soak = ZoneScheduleState::Soaking {
+            started: now,
+            until: now + Duration::from_secs(600),
+            extended_sec: 120,
+        };
+        persist_state(&db, "z1", &soak).await;
+        persist_state(&db, "gone", &soak).await;
+
+        let mut states = HashMap::from([("z1".to_string(), ZoneScheduleState::Idle)]);
+        restore_states(&db, &zones, &mut states, &shared).await;
+        let ZoneScheduleState::Soaking {
+            until,
+            extended_sec,
+            ..
+        } = states["z1"]
+        else {
+            panic!("expected soaking, got {:?}", states["z1"]);
+        };
+        assert_eq!(extended_sec, 120);
+        let left = until.saturating_duration_since(Instant::now()).as_secs();
+        assert!((598..=600).contains(&left), "{left}s left");
+        assert!(!states.contains_key("gone"));
+        assert_eq!(db.load_scheduler_states().await.unwrap().len(), 1);
+
+        persist_state(&db, "z1", &ZoneScheduleState::Idle).await;
+        assert!(db.load_scheduler_states().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn interrupted_pulse_resumes_as_soak() {
+        let cfg = ZoneConfig::test("z1");
+        let (now, now_ts) = (Instant::now(), 1_000_000);
+        let watering = ZoneScheduleState::Watering {
+            since: now - Duration::from_secs(10),
+        };
+        let row = watering.to_row("z1", now, now_ts).unwrap();
+        assert_eq!(
+            (row.phase.as_str(), row.started_ts),
+            ("watering", now_ts - 10)
+        );
+
+        // Pulse planned to end 20s from now, then a 20 min soak.
+        let ZoneScheduleState::Soaking { started, until, .. } =
+            ZoneScheduleState::from_row(&row, &cfg, now, now_ts)
+        else {
+            panic!("expected soaking");
+        };
+        assert_eq!(started, now + Duration::from_secs(20));
+        assert_eq!(until, started + Duration::from_secs(20 * 60));
+
+        let flushing = ZoneScheduleState::Flushing {
+            since: now,
+            duration: Duration::from_secs(60),
+        };
+        let row = flushing.to_row("z1", now, now_ts).unwrap();
+        assert_eq!(row.until_ts, now_ts + 60);
+        assert_eq!(
+            ZoneScheduleState::from_row(&row, &cfg, now, now_ts),
+            ZoneScheduleState::Idle
+        );
+        assert!(ZoneScheduleState::Idle.to_row("z1", now, now_ts).is_none());
+    }
+
     // -- Idle: no readings → stays idle ----------------------------------
 
     #[tokio::test]
     async fn idle_no_readings_stays_idle() {
         let db = Db::connect("sqlite::memory:").await.unwrap();
         db.migrate().await.unwrap();
-        db.upsert_zone(&test_zone_cfg()).await.unwrap();
+        db.upsert_zone(&ZoneConfig::test("z1")).await.unwrap();
 
         let (mqtt, _el) = test_mqtt();
         let shared = test_shared();
@@ -491,13 +1956,15 @@ mod tests {
         let mut state = ZoneScheduleState::Idle;
         handle_idle(
             "z1",
-            &test_zone_cfg(),
+            &ZoneConfig::test("z1"),
             &mut state,
+            &mut ThresholdStrategy,
             &db,
             &mqtt,
             &shared,
             2,
             OperationMode::Auto,
+            None,
         )
         .await;
 
@@ -519,13 +1986,15 @@ mod tests {
         let mut state = ZoneScheduleState::Idle;
         handle_idle(
             "z1",
-            &test_zone_cfg(),
+            &ZoneConfig::test("z1"),
             &mut state,
+            &mut ThresholdStrategy,
             &db,
             &mqtt,
             &shared,
             2,
             OperationMode::Auto,
+            None,
         )
         .await;
 
@@ -545,19 +2014,114 @@ mod tests {
         }
 
         let mut state = ZoneScheduleState::Idle;
-        handle_idle(
+        let eval = handle_idle(
             "z1",
-            &test_zone_cfg(),
+            &ZoneConfig::test("z1"),
             &mut state,
+            &mut ThresholdStrategy,
             &db,
             &mqtt,
             &shared,
             2,
             OperationMode::Auto,
+            None,
         )
         .await;
 
         assert!(matches!(state, ZoneScheduleState::Watering { .. }));
+        assert_eq!(eval.action, "pulse");
+        assert_eq!(eval.blocked_by, None);
+        assert!((eval.avg_moisture.unwrap() - 0.2).abs() < 0.001);
+    }
+
+    // -- Idle: peak sun → deferred unless critically dry -----------------
+
+    #[tokio::test]
+    async fn idle_peak_sun_defers_unless_critical() {
+        let (mqtt, _el) = test_mqtt();
+        let shared = test_shared();
+        {
+            let mut st = shared.write().await;
+            st.mqtt_connected = true;
+            st.sunlight = crate::sunlight::Sunlight::new(&crate::sunlight::SunlightConfig {
+                avoid_above_lux: Some(40_000.0),
+                critical_margin: 0.1,
+            });
+            st.sunlight.record("n1", 55_000.0, now_unix());
+        }
+        let idle = |db: Db| {
+            let (mqtt, shared) = (mqtt.clone(), shared.clone());
+            async move {
+                let mut state = ZoneScheduleState::Idle;
+                let eval = handle_idle(
+                    "z1",
+                    &ZoneConfig::test("z1"),
+                    &mut state,
+                    &mut ThresholdStrategy,
+                    &db,
+                    &mqtt,
+                    &shared,
+                    2,
+                    OperationMode::Auto,
+                    None,
+                )
+                .await;
+                (state, eval)
+            }
+        };
+
+        let (state, eval) = idle(seeded_db(&[0.25; 5]).await).await;
+        assert!(matches!(state, ZoneScheduleState::Idle));
+        assert_eq!(eval.blocked_by, Some("peak_sun"));
+        assert!(eval.detail.contains("55000 lux on n1"), "{}", eval.detail);
+        assert!(eval.avg_moisture.is_some());
+
+        // 0.15 is more than 0.1 below min_moisture: waters anyway.
+        let (state, eval) = idle(seeded_db(&[0.15; 5]).await).await;
+        assert!(matches!(state, ZoneScheduleState::Watering { .. }));
+        assert_eq!(eval.action, "pulse");
+    }
+
+    // -- Idle: water budget exhausted → deferred ------------------------
+
+    #[tokio::test]
+    async fn idle_budget_exhausted_stays_idle() {
+        let db = seeded_db(&[0.2, 0.2, 0.2, 0.2, 0.2]).await;
+        let (mqtt, _el) = test_mqtt();
+        let shared = test_shared();
+        shared.write().await.mqtt_connected = true;
+
+        let budget = Budget::new(&crate::config::BudgetConfig {
+            daily_open_sec: Some(60),
+            ..Default::default()
+        });
+        let zones: HashMap<String, ZoneConfig> =
+            [("z1".to_string(), ZoneConfig::test("z1"))].into();
+        let tick_budget = TickBudget {
+            budget: &budget,
+            zones: &zones,
+            open_sec: Some([("z1".to_string(), 45)].into()),
+        };
+
+        let mut state = ZoneScheduleState::Idle;
+        let eval = handle_idle(
+            "z1",
+            &ZoneConfig::test("z1"),
+            &mut state,
+            &mut ThresholdStrategy,
+            &db,
+            &mqtt,
+            &shared,
+            2,
+            OperationMode::Auto,
+            Some(&tick_budget),
+        )
+        .await;
+
+        assert!(matches!(state, ZoneScheduleState::Idle));
+        assert_eq!(eval.blocked_by, Some("budget"));
+        assert!(eval.detail.contains("global budget exhausted"));
+        assert!(eval.avg_moisture.is_some());
     }
 
     // -- Idle: MQTT disconnected → stays idle ----------------------------
@@ -572,17 +2136,165 @@ mod tests {
         let mut state = ZoneScheduleState::Idle;
         handle_idle(
             "z1",
-            &test_zone_cfg(),
+            &ZoneConfig::test("z1"),
+            &mut state,
+            &mut ThresholdStrategy,
+            &db,
+            &mqtt,
+            &shared,
+            2,
+            OperationMode::Auto,
+            None,
+        )
+        .await;
+
+        assert!(matches!(state, ZoneScheduleState::Idle));
+    }
+
+    // -- Idle: DB degraded → stays idle ----------------------------------
+
+    #[tokio::test]
+    async fn idle_db_degraded_stays_idle() {
+        let db = seeded_db(&[0.1, 0.1, 0.1, 0.1, 0.1]).await;
+        let (mqtt, _el) = test_mqtt();
+        let shared = test_shared();
+        {
+            let mut st = shared.write().await;
+            st.mqtt_connected = true;
+            st.mark_db_degraded("test");
+        }
+
+        let mut state = ZoneScheduleState::Idle;
+        handle_idle(
+            "z1",
+            &ZoneConfig::test("z1"),
+            &mut state,
+            &mut ThresholdStrategy,
+            &db,
+            &mqtt,
+            &shared,
+            2,
+            OperationMode::Auto,
+            None,
+        )
+        .await;
+
+        assert!(matches!(state, ZoneScheduleState::Idle));
+    }
+
+    // -- Idle: emergency stop latched → stays idle -----------------------
+
+    #[tokio::test]
+    async fn idle_emergency_stop_stays_idle() {
+        let db = seeded_db(&[0.1, 0.1, 0.1, 0.1, 0.1]).await;
+        let (mqtt, _el) = test_mqtt();
+        let shared = test_shared();
+        {
+            let mut st = shared.write().await;
+            st.mqtt_connected = true;
+            st.estop.latch(1000);
+        }
+
+        let mut state = ZoneScheduleState::Idle;
+        let eval = handle_idle(
+            "z1",
+            &ZoneConfig::test("z1"),
+            &mut state,
+            &mut ThresholdStrategy,
+            &db,
+            &mqtt,
+            &shared,
+            2,
+            OperationMode::Auto,
+            None,
+        )
+        .await;
+
+        assert!(matches!(state, ZoneScheduleState::Idle));
+        assert_eq!(eval.blocked_by, Some("emergency_stop"));
+    }
+
+    // -- Idle: et zone at target moisture → deficit reset ----------------
+
+    #[tokio::test]
+    async fn idle_et_zone_at_target_resets_deficit() {
+        let db = seeded_db(&[0.6, 0.6, 0.6, 0.6, 0.6]).await;
+        let (mqtt, _el) = test_mqtt();
+        let shared = test_shared();
+        {
+            let mut st = shared.write().await;
+            st.mqtt_connected = true;
+            st.et_deficits.insert("z1".into(), 12.0);
+        }
+        let cfg = ZoneConfig {
+            strategy: StrategyConfig::Et {
+                crop_coefficient: 1.0,
+                application_rate_mm_hr: 12.0,
+                allowed_depletion_mm: 5.0,
+                moisture_feedback: true,
+            },
+            ..ZoneConfig::test("z1")
+        };
+        let mut strategy = cfg.strategy.build();
+
+        let mut state = ZoneScheduleState::Idle;
+        let eval = handle_idle(
+            "z1",
+            &cfg,
+            &mut state,
+            strategy.as_mut(),
+            &db,
+            &mqtt,
+            &shared,
+            2,
+            OperationMode::Auto,
+            None,
+        )
+        .await;
+
+        assert!(matches!(state, ZoneScheduleState::Idle));
+        assert_eq!(eval.action, "wait");
+        assert_eq!(shared.read().await.et_deficits["z1"], 0.0);
+        assert_eq!(db.load_et_deficits().await.unwrap()["z1"], 0.0);
+    }
+
+    // -- Idle: blackout date → stays idle however dry --------------------
+
+    #[tokio::test]
+    async fn idle_blackout_stays_idle() {
+        let db = seeded_db(&[0.1, 0.1, 0.1, 0.1, 0.1]).await;
+        let (mqtt, _el) = test_mqtt();
+        let shared = test_shared();
+        {
+            let mut st = shared.write().await;
+            st.mqtt_connected = true;
+            st.blackouts = crate::blackout::Blackouts::new(vec![crate::blackout::Blackout {
+                id: 1,
+                start: "01-01".into(),
+                end: "12-31".into(),
+                reason: "winterized".into(),
+            }]);
+        }
+
+        let mut state = ZoneScheduleState::Idle;
+        let eval = handle_idle(
+            "z1",
+            &ZoneConfig::test("z1"),
             &mut state,
+            &mut ThresholdStrategy,
             &db,
             &mqtt,
             &shared,
             2,
             OperationMode::Auto,
+            None,
         )
         .await;
 
         assert!(matches!(state, ZoneScheduleState::Idle));
+        assert_eq!(eval.blocked_by, Some("blackout"));
+        assert_eq!(eval.action, "skip");
+        assert_eq!(eval.detail, "winterized (01-01 to 12-31)");
     }
 
     // -- Idle: zone already on → stays idle ------------------------------
@@ -601,13 +2313,15 @@ mod tests {
         let mut state = ZoneScheduleState::Idle;
         handle_idle(
             "z1",
-            &test_zone_cfg(),
+            &ZoneConfig::test("z1"),
             &mut state,
+            &mut ThresholdStrategy,
             &db,
             &mqtt,
             &shared,
             2,
             OperationMode::Auto,
+            None,
         )
         .await;
 
@@ -628,24 +2342,178 @@ mod tests {
 
         // Exhaust the pulse limit.
         let today = Db::today_yyyy_mm_dd();
-        for _ in 0..6 {
-            db.add_pulse(&today, "z1", 1).await.unwrap();
-        }
+        for _ in 0..6 {
+            db.add_pulse(&today, "z1", 1).await.unwrap();
+        }
+
+        let mut state = ZoneScheduleState::Idle;
+        let eval = handle_idle(
+            "z1",
+            &ZoneConfig::test("z1"),
+            &mut state,
+            &mut ThresholdStrategy,
+            &db,
+            &mqtt,
+            &shared,
+            2,
+            OperationMode::Auto,
+            None,
+        )
+        .await;
+
+        assert!(matches!(state, ZoneScheduleState::Idle));
+        assert_eq!(eval.action, "skip");
+        assert_eq!(eval.blocked_by, Some("daily_limit"));
+    }
+
+    #[tokio::test]
+    async fn idle_rolling_limit_exhausted_stays_idle() {
+        let db = seeded_db(&[0.1, 0.1, 0.1, 0.1, 0.1]).await;
+        let (mqtt, _el) = test_mqtt();
+        let shared = test_shared();
+        shared.write().await.mqtt_connected = true;
+
+        // Six pulses in the last 24h but none counted today, as after
+        // watering just before midnight.
+        let now = now_unix();
+        for i in 0..6 {
+            let start = now - 3600 + i * 60;
+            db.insert_watering_event(
+                start,
+                start + 10,
+                "z1",
+                "pulse",
+                "ok",
+                EventDetails::default(),
+            )
+            .await
+            .unwrap();
+        }
+
+        let mut state = ZoneScheduleState::Idle;
+        let eval = handle_idle(
+            "z1",
+            &ZoneConfig::test("z1"),
+            &mut state,
+            &mut ThresholdStrategy,
+            &db,
+            &mqtt,
+            &shared,
+            2,
+            OperationMode::Auto,
+            None,
+        )
+        .await;
+
+        assert!(matches!(state, ZoneScheduleState::Idle));
+        assert_eq!(eval.blocked_by, Some("daily_limit"));
+        assert!(eval.detail.contains("last 24h"), "{}", eval.detail);
+    }
+
+    // -- Moisture alerts ----------------------------------------------------
+
+    #[tokio::test]
+    async fn moisture_alert_raised_once_above_min_moisture() {
+        // 0.33: above min_moisture (0.3), so no watering, but below the
+        // 0.35 alert threshold.
+        let db = seeded_db(&[0.33, 0.33, 0.33, 0.33, 0.33]).await;
+        let shared = test_shared();
+        shared.write().await.moisture_alerts =
+            crate::alerts::MoistureAlerts::new(&crate::alerts::MoistureAlertConfig {
+                below: Some(0.35),
+                ..Default::default()
+            });
+        let zones = HashMap::from([("z1".to_string(), ZoneConfig::test("z1"))]);
+
+        let now = now_unix();
+        check_moisture_alerts(&zones, &db, &shared, now).await;
+        check_moisture_alerts(&zones, &db, &shared, now + 60).await;
+
+        let st = shared.read().await;
+        let alerts: Vec<&str> = st
+            .events
+            .iter()
+            .filter(|e| matches!(e.kind, crate::state::EventKind::Alert))
+            .map(|e| e.detail.as_str())
+            .collect();
+        assert_eq!(alerts, ["z1: moisture 0.330 below alert threshold 0.350"]);
+        assert_eq!(st.moisture_alerts.low_zones()[0].zone_id, "z1");
+    }
+
+    // -- Emitter flush ------------------------------------------------------
+
+    #[tokio::test]
+    async fn flush_opens_without_readings_and_returns_to_idle() {
+        // No readings at all: a pulse would be blocked, a flush isn't.
+        let db = seeded_db(&[]).await;
+        let (mqtt, _el) = test_mqtt();
+        let shared = test_shared();
+        shared.write().await.mqtt_connected = true;
+
+        let mut state = ZoneScheduleState::Idle;
+        let eval = start_flush(
+            "z1",
+            &ZoneConfig::test("z1"),
+            Duration::from_secs(20),
+            &mut state,
+            &db,
+            &mqtt,
+            &shared,
+            2,
+        )
+        .await;
+        assert_eq!(eval.action, "flush");
+        assert_eq!(
+            shared
+                .write()
+                .await
+                .sessions
+                .unplan("z1")
+                .map(|p| (p.reason, p.planned_sec)),
+            Some((FLUSH_REASON, Some(20)))
+        );
+        let ZoneScheduleState::Flushing { since, duration } = state else {
+            panic!("expected Flushing");
+        };
+
+        // Not elapsed yet.
+        assert!(
+            handle_flushing("z1", since, duration, &mut state, &mqtt, &shared)
+                .await
+                .is_none()
+        );
+        let since = Instant::now() - Duration::from_secs(21);
+        let eval = handle_flushing("z1", since, duration, &mut state, &mqtt, &shared)
+            .await
+            .unwrap();
+        assert_eq!(eval.action, "flush_end");
+        assert!(matches!(state, ZoneScheduleState::Idle));
+    }
+
+    #[tokio::test]
+    async fn flush_respects_daily_limits() {
+        let db = seeded_db(&[]).await;
+        let (mqtt, _el) = test_mqtt();
+        let shared = test_shared();
+        shared.write().await.mqtt_connected = true;
+        let today = Db::today_yyyy_mm_dd();
+        db.add_open_seconds(&today, "z1", 180).await.unwrap();
 
         let mut state = ZoneScheduleState::Idle;
-        handle_idle(
+        let eval = start_flush(
             "z1",
-            &test_zone_cfg(),
+            &ZoneConfig::test("z1"),
+            Duration::from_secs(20),
             &mut state,
             &db,
             &mqtt,
             &shared,
             2,
-            OperationMode::Auto,
         )
         .await;
-
+        assert_eq!(eval.blocked_by, Some("daily_limit"));
         assert!(matches!(state, ZoneScheduleState::Idle));
+        assert_eq!(shared.write().await.sessions.unplan("z1"), None);
     }
 
     // -- Watering: pulse not elapsed → stays Watering --------------------
@@ -657,7 +2525,15 @@ mod tests {
 
         let since = Instant::now(); // just started
         let mut state = ZoneScheduleState::Watering { since };
-        handle_watering("z1", &test_zone_cfg(), since, &mut state, &mqtt, &shared).await;
+        handle_watering(
+            "z1",
+            &ZoneConfig::test("z1"),
+            since,
+            &mut state,
+            &mqtt,
+            &shared,
+        )
+        .await;
 
         assert!(matches!(state, ZoneScheduleState::Watering { .. }));
     }
@@ -672,7 +2548,15 @@ mod tests {
         // Simulate pulse_sec already elapsed.
         let since = Instant::now() - Duration::from_secs(31);
         let mut state = ZoneScheduleState::Watering { since };
-        handle_watering("z1", &test_zone_cfg(), since, &mut state, &mqtt, &shared).await;
+        handle_watering(
+            "z1",
+            &ZoneConfig::test("z1"),
+            since,
+            &mut state,
+            &mqtt,
+            &shared,
+        )
+        .await;
 
         assert!(matches!(state, ZoneScheduleState::Soaking { .. }));
     }
@@ -685,8 +2569,16 @@ mod tests {
         let shared = test_shared();
 
         let until = Instant::now() + Duration::from_secs(600);
-        let mut state = ZoneScheduleState::Soaking { until };
-        handle_soaking("z1", &test_zone_cfg(), until, &mut state, &db, &shared).await;
+        let mut state = soaking_until(until);
+        handle_soaking(
+            "z1",
+            &ZoneConfig::test("z1"),
+            &SoakPolicy::default(),
+            &mut state,
+            &db,
+            &shared,
+        )
+        .await;
 
         assert!(matches!(state, ZoneScheduleState::Soaking { .. }));
     }
@@ -699,8 +2591,16 @@ mod tests {
         let shared = test_shared();
 
         let until = Instant::now() - Duration::from_secs(1); // already expired
-        let mut state = ZoneScheduleState::Soaking { until };
-        handle_soaking("z1", &test_zone_cfg(), until, &mut state, &db, &shared).await;
+        let mut state = soaking_until(until);
+        handle_soaking(
+            "z1",
+            &ZoneConfig::test("z1"),
+            &SoakPolicy::default(),
+            &mut state,
+            &db,
+            &shared,
+        )
+        .await;
 
         assert!(matches!(state, ZoneScheduleState::Idle));
     }
@@ -713,8 +2613,16 @@ mod tests {
         let shared = test_shared();
 
         let until = Instant::now() - Duration::from_secs(1);
-        let mut state = ZoneScheduleState::Soaking { until };
-        handle_soaking("z1", &test_zone_cfg(), until, &mut state, &db, &shared).await;
+        let mut state = soaking_until(until);
+        handle_soaking(
+            "z1",
+            &ZoneConfig::test("z1"),
+            &SoakPolicy::default(),
+            &mut state,
+            &db,
+            &shared,
+        )
+        .await;
 
         // Returns to Idle so full guard checks run before next pulse.
         assert!(matches!(state, ZoneScheduleState::Idle));
@@ -726,13 +2634,21 @@ mod tests {
     async fn soaking_expired_no_readings_goes_idle() {
         let db = Db::connect("sqlite::memory:").await.unwrap();
         db.migrate().await.unwrap();
-        db.upsert_zone(&test_zone_cfg()).await.unwrap();
+        db.upsert_zone(&ZoneConfig::test("z1")).await.unwrap();
 
         let shared = test_shared();
 
         let until = Instant::now() - Duration::from_secs(1);
-        let mut state = ZoneScheduleState::Soaking { until };
-        handle_soaking("z1", &test_zone_cfg(), until, &mut state, &db, &shared).await;
+        let mut state = soaking_until(until);
+        handle_soaking(
+            "z1",
+            &ZoneConfig::test("z1"),
+            &SoakPolicy::default(),
+            &mut state,
+            &db,
+            &shared,
+        )
+        .await;
 
         assert!(matches!(state, ZoneScheduleState::Idle));
     }
@@ -744,13 +2660,20 @@ mod tests {
         // Create a DB with readings that are old (>30 min stale_timeout_min).
         let db = Db::connect("sqlite::memory:").await.unwrap();
         db.migrate().await.unwrap();
-        db.upsert_zone(&test_zone_cfg()).await.unwrap();
+        db.upsert_zone(&ZoneConfig::test("z1")).await.unwrap();
         db.upsert_sensor(&SensorConfig {
             sensor_id: "s1".into(),
             node_id: "n1".into(),
             zone_id: "z1".into(),
             raw_dry: 26000,
             raw_wet: 12000,
+            archived_at: None,
+            channel: None,
+            weight: 1.0,
+            failure_margin: None,
+            failure_margin_pct: None,
+            curve: None,
+            enabled: true,
         })
         .await
         .unwrap();
@@ -767,19 +2690,22 @@ mod tests {
         }
 
         let mut state = ZoneScheduleState::Idle;
-        handle_idle(
+        let eval = handle_idle(
             "z1",
-            &test_zone_cfg(),
+            &ZoneConfig::test("z1"),
             &mut state,
+            &mut ThresholdStrategy,
             &db,
             &mqtt,
             &shared,
             2,
             OperationMode::Auto,
+            None,
         )
         .await;
 
         assert!(matches!(state, ZoneScheduleState::Idle));
+        assert_eq!(eval.blocked_by, Some("stale_readings"));
     }
 
     // -- Idle: concurrent valve limit reached → stays idle ----------------
@@ -804,13 +2730,15 @@ mod tests {
         // max_concurrent_valves = 1 → z2 fills the single slot.
         handle_idle(
             "z1",
-            &test_zone_cfg(),
+            &ZoneConfig::test("z1"),
             &mut state,
+            &mut ThresholdStrategy,
             &db,
             &mqtt,
             &shared,
             1,
             OperationMode::Auto,
+            None,
         )
         .await;
 
@@ -836,13 +2764,15 @@ mod tests {
         // max_concurrent_valves = 2 → one slot still available.
         handle_idle(
             "z1",
-            &test_zone_cfg(),
+            &ZoneConfig::test("z1"),
             &mut state,
+            &mut ThresholdStrategy,
             &db,
             &mqtt,
             &shared,
             2,
             OperationMode::Auto,
+            None,
         )
         .await;
 
@@ -860,13 +2790,15 @@ mod tests {
         let mut state = ZoneScheduleState::Idle;
         handle_idle(
             "z1",
-            &test_zone_cfg(),
+            &ZoneConfig::test("z1"),
             &mut state,
+            &mut ThresholdStrategy,
             &db,
             &mqtt,
             &shared,
             2,
             OperationMode::Monitor,
+            None,
         )
         .await;
 
@@ -895,6 +2827,117 @@ mod tests {
         );
     }
 
+    // -- Schedule strategy: due slot → pulses without sensor data -------
+
+    #[tokio::test]
+    async fn idle_schedule_strategy_pulses_without_readings() {
+        // No readings at all — a threshold zone would stay idle here.
+        let db = seeded_db(&[]).await;
+        let (mqtt, _el) = test_mqtt();
+        let shared = test_shared();
+        shared.write().await.mqtt_connected = true;
+
+        let now = OffsetDateTime::now_utc();
+        let mut strategy = StrategyConfig::Schedule {
+            times: vec![format!("{:02}:{:02}", now.hour(), now.minute())],
+            pulses: 1,
+        }
+        .build();
+
+        let mut state = ZoneScheduleState::Idle;
+        handle_idle(
+            "z1",
+            &ZoneConfig::test("z1"),
+            &mut state,
+            strategy.as_mut(),
+            &db,
+            &mqtt,
+            &shared,
+            2,
+            OperationMode::Auto,
+            None,
+        )
+        .await;
+        assert!(matches!(state, ZoneScheduleState::Watering { .. }));
+
+        // Cycle used up — the same slot doesn't fire again.
+        let mut state = ZoneScheduleState::Idle;
+        handle_idle(
+            "z1",
+            &ZoneConfig::test("z1"),
+            &mut state,
+            strategy.as_mut(),
+            &db,
+            &mqtt,
+            &shared,
+            2,
+            OperationMode::Auto,
+            None,
+        )
+        .await;
+        assert!(matches!(state, ZoneScheduleState::Idle));
+    }
+
+    // -- Advisor strategy: request, then act on clamped advice ------------
+
+    #[tokio::test]
+    async fn idle_advisor_requests_then_pulses_on_advice() {
+        let db = seeded_db(&[0.4, 0.4, 0.4, 0.4, 0.4]).await;
+        let (mqtt, _el) = test_mqtt();
+        let shared = test_shared();
+        shared.write().await.mqtt_connected = true;
+        let mut strategy = StrategyConfig::Advisor {
+            request_interval_min: 30,
+        }
+        .build();
+
+        // First idle tick only asks for advice.
+        let mut state = ZoneScheduleState::Idle;
+        handle_idle(
+            "z1",
+            &ZoneConfig::test("z1"),
+            &mut state,
+            strategy.as_mut(),
+            &db,
+            &mqtt,
+            &shared,
+            2,
+            OperationMode::Auto,
+            None,
+        )
+        .await;
+        assert!(matches!(state, ZoneScheduleState::Idle));
+
+        shared.write().await.record_advice(
+            "z1",
+            crate::strategy::Advice {
+                pulses: 1,
+                reason: "forecast heat".into(),
+                received_at: OffsetDateTime::now_utc(),
+            },
+        );
+        handle_idle(
+            "z1",
+            &ZoneConfig::test("z1"),
+            &mut state,
+            strategy.as_mut(),
+            &db,
+            &mqtt,
+            &shared,
+            2,
+            OperationMode::Auto,
+            None,
+        )
+        .await;
+        assert!(matches!(state, ZoneScheduleState::Watering { .. }));
+
+        let st = shared.read().await;
+        assert!(st
+            .events
+            .iter()
+            .any(|e| e.detail.contains("pulse started (advisor: forecast heat")));
+    }
+
     // -- Monitor mode: adequate moisture → stays idle, no alert --------
 
     #[tokio::test]
@@ -906,13 +2949,15 @@ mod tests {
         let mut state = ZoneScheduleState::Idle;
         handle_idle(
             "z1",
-            &test_zone_cfg(),
+            &ZoneConfig::test("z1"),
             &mut state,
+            &mut ThresholdStrategy,
             &db,
             &mqtt,
             &shared,
             2,
             OperationMode::Monitor,
+            None,
         )
         .await;
 
@@ -944,13 +2989,15 @@ mod tests {
         let mut state = ZoneScheduleState::Idle;
         handle_idle(
             "z1",
-            &test_zone_cfg(),
+            &ZoneConfig::test("z1"),
             &mut state,
+            &mut ThresholdStrategy,
             &db,
             &mqtt,
             &shared,
             2,
             OperationMode::Monitor,
+            None,
         )
         .await;
 
@@ -966,4 +3013,289 @@ mod tests {
             "expected alert even with MQTT disconnected in monitor mode"
         );
     }
+
+    // -- Soaking: adaptive early exit ------------------------------------
+
+    fn early_exit_policy() -> SoakPolicy {
+        SoakPolicy {
+            early_exit: true,
+            early_exit_after_min: 5,
+            ..SoakPolicy::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn soaking_early_exit_when_target_reached() {
+        let db = seeded_db(&[0.6, 0.6, 0.6, 0.6, 0.6]).await;
+        let shared = test_shared();
+
+        let mut state = soaking_until(Instant::now() + Duration::from_secs(600));
+        handle_soaking(
+            "z1",
+            &ZoneConfig::test("z1"),
+            &early_exit_policy(),
+            &mut state,
+            &db,
+            &shared,
+        )
+        .await;
+
+        assert!(matches!(state, ZoneScheduleState::Idle));
+        let st = shared.read().await;
+        assert!(st
+            .events
+            .back()
+            .unwrap()
+            .detail
+            .contains("soak ended early"));
+    }
+
+    #[tokio::test]
+    async fn soaking_early_exit_disabled_keeps_soaking() {
+        let db = seeded_db(&[0.6, 0.6, 0.6, 0.6, 0.6]).await;
+        let shared = test_shared();
+
+        let mut state = soaking_until(Instant::now() + Duration::from_secs(600));
+        handle_soaking(
+            "z1",
+            &ZoneConfig::test("z1"),
+            &SoakPolicy::default(),
+            &mut state,
+            &db,
+            &shared,
+        )
+        .await;
+
+        assert!(matches!(state, ZoneScheduleState::Soaking { .. }));
+    }
+
+    #[tokio::test]
+    async fn soaking_early_exit_respects_minimum_soak() {
+        let db = seeded_db(&[0.6, 0.6, 0.6, 0.6, 0.6]).await;
+        let shared = test_shared();
+
+        let now = Instant::now();
+        let mut state = ZoneScheduleState::Soaking {
+            started: now - Duration::from_secs(60), // only 1 min in
+            until: now + Duration::from_secs(600),
+            extended_sec: 0,
+        };
+        handle_soaking(
+            "z1",
+            &ZoneConfig::test("z1"),
+            &early_exit_policy(),
+            &mut state,
+            &db,
+            &shared,
+        )
+        .await;
+
+        assert!(matches!(state, ZoneScheduleState::Soaking { .. }));
+    }
+
+    #[tokio::test]
+    async fn soaking_early_exit_below_target_keeps_soaking() {
+        let db = seeded_db(&[0.4, 0.4, 0.4, 0.4, 0.4]).await;
+        let shared = test_shared();
+
+        let mut state = soaking_until(Instant::now() + Duration::from_secs(600));
+        handle_soaking(
+            "z1",
+            &ZoneConfig::test("z1"),
+            &early_exit_policy(),
+            &mut state,
+            &db,
+            &shared,
+        )
+        .await;
+
+        assert!(matches!(state, ZoneScheduleState::Soaking { .. }));
+    }
+
+    // -- Soaking: rise-based extension -----------------------------------
+
+    fn extend_policy() -> SoakPolicy {
+        SoakPolicy {
+            extend_on_rise: true,
+            rise_threshold_per_min: 0.002,
+            rise_window_min: 10,
+            max_extend_min: 10,
+            ..SoakPolicy::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn soaking_expired_rising_moisture_extends() {
+        // +0.01/min over the last 5 minutes, still below target.
+        let db = seeded_db_at(&[(300, 0.35), (180, 0.37), (60, 0.39), (0, 0.40)]).await;
+        let shared = test_shared();
+
+        let mut state = soaking_until(Instant::now() - Duration::from_secs(1));
+        handle_soaking(
+            "z1",
+            &ZoneConfig::test("z1"),
+            &extend_policy(),
+            &mut state,
+            &db,
+            &shared,
+        )
+        .await;
+
+        match state {
+            ZoneScheduleState::Soaking { extended_sec, .. } => {
+                assert_eq!(extended_sec, SOAK_EXTEND_STEP_SEC)
+            }
+            _ => panic!("expected soak to be extended"),
+        }
+    }
+
+    #[tokio::test]
+    async fn soaking_expired_flat_moisture_does_not_extend() {
+        let db = seeded_db_at(&[(300, 0.35), (180, 0.35), (60, 0.35), (0, 0.35)]).await;
+        let shared = test_shared();
+
+        let mut state = soaking_until(Instant::now() - Duration::from_secs(1));
+        handle_soaking(
+            "z1",
+            &ZoneConfig::test("z1"),
+            &extend_policy(),
+            &mut state,
+            &db,
+            &shared,
+        )
+        .await;
+
+        assert!(matches!(state, ZoneScheduleState::Idle));
+    }
+
+    #[tokio::test]
+    async fn soaking_extension_capped_at_max() {
+        let db = seeded_db_at(&[(300, 0.35), (180, 0.37), (60, 0.39), (0, 0.40)]).await;
+        let shared = test_shared();
+
+        let now = Instant::now();
+        let mut state = ZoneScheduleState::Soaking {
+            started: now - Duration::from_secs(30 * 60),
+            until: now - Duration::from_secs(1),
+            extended_sec: 600, // budget already used
+        };
+        handle_soaking(
+            "z1",
+            &ZoneConfig::test("z1"),
+            &extend_policy(),
+            &mut state,
+            &db,
+            &shared,
+        )
+        .await;
+
+        assert!(matches!(state, ZoneScheduleState::Idle));
+    }
+
+    // -- moisture_slope_per_min -------------------------------------------
+
+    #[test]
+    fn slope_of_rising_series() {
+        let slope = moisture_slope_per_min(&[(0, 0.30), (60, 0.31), (120, 0.32)]).unwrap();
+        assert!((slope - 0.01).abs() < 1e-5, "got {slope}");
+    }
+
+    #[test]
+    fn slope_of_falling_series_is_negative() {
+        let slope = moisture_slope_per_min(&[(0, 0.40), (120, 0.38)]).unwrap();
+        assert!(slope < 0.0);
+    }
+
+    #[test]
+    fn slope_needs_two_distinct_timestamps() {
+        assert_eq!(moisture_slope_per_min(&[]), None);
+        assert_eq!(moisture_slope_per_min(&[(5, 0.3)]), None);
+        assert_eq!(moisture_slope_per_min(&[(5, 0.3), (5, 0.4)]), None);
+    }
+
+    // -- Sensor aggregation ------------------------------------------------
+
+    #[tokio::test]
+    async fn zone_moisture_aggregates_and_skips_faulted_sensors() {
+        // s1 reads 0.2 (dry corner); s2 reads 0.5.
+        let db = seeded_db(&[0.2]).await;
+        db.upsert_sensor(&SensorConfig {
+            sensor_id: "s2".into(),
+            node_id: "n1".into(),
+            zone_id: "z1".into(),
+            raw_dry: 26000,
+            raw_wet: 12000,
+            archived_at: None,
+            channel: None,
+            weight: 3.0,
+            failure_margin: None,
+            failure_margin_pct: None,
+            curve: None,
+            enabled: true,
+        })
+        .await
+        .unwrap();
+        db.insert_reading(now_unix(), "s2", 19000, 0.5)
+            .await
+            .unwrap();
+        let shared = test_shared();
+
+        let mut cfg = ZoneConfig::test("z1");
+        let v = zone_moisture("z1", &cfg, &db, &shared)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!((v - 0.425).abs() < 1e-5, "weighted mean, got {v}");
+
+        cfg.aggregation = Aggregation::Min;
+        let v = zone_moisture("z1", &cfg, &db, &shared).await.unwrap();
+        assert_eq!(v, Some(0.2));
+
+        shared.write().await.set_sensor_faulted("s1", true);
+        let v = zone_moisture("z1", &cfg, &db, &shared).await.unwrap();
+        assert_eq!(v, Some(0.5));
+    }
+
+    // -- Zone dependencies -------------------------------------------------
+
+    #[test]
+    fn downstream_waits_until_upstream_settles_today() {
+        let upstream = ZoneConfig::test("z1");
+        let downstream = ZoneConfig {
+            after: vec!["z1".into(), "gone".into()],
+            ..ZoneConfig::test("z2")
+        };
+        let zones: HashMap<String, ZoneConfig> = [
+            ("z1".to_string(), upstream),
+            ("z2".to_string(), downstream.clone()),
+        ]
+        .into();
+
+        let mut settled = HashMap::new();
+        assert_eq!(
+            pending_upstream(&downstream, &zones, &settled, "2026-06-02"),
+            Some("z1")
+        );
+        settled.insert("z1".to_string(), "2026-06-01".to_string());
+        assert_eq!(
+            pending_upstream(&downstream, &zones, &settled, "2026-06-02"),
+            Some("z1")
+        );
+        // Unconfigured zones ("gone") never block.
+        settled.insert("z1".to_string(), "2026-06-02".to_string());
+        assert_eq!(
+            pending_upstream(&downstream, &zones, &settled, "2026-06-02"),
+            None
+        );
+    }
+
+    #[test]
+    fn idle_outcomes_that_settle_a_zone() {
+        assert!(Evaluation::action("wait", Some(0.4), "threshold").settles());
+        assert!(Evaluation::blocked("daily_limit", "").settles());
+        assert!(Evaluation::blocked("budget", "").settles());
+        assert!(!Evaluation::action("pulse", Some(0.2), "").settles());
+        assert!(!Evaluation::blocked("stale_readings", "").settles());
+        assert!(!Evaluation::blocked("dependency", "").settles());
+    }
 }
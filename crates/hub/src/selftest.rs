@@ -0,0 +1,573 @@
+//! Valve self-test: `POST /api/maintenance/valve-test` opens each zone in
+//! turn for a short blip and reports per zone whether it worked — the
+//! spring start-up check that otherwise means toggling every zone by hand.
+//!
+//! Each blip is a timed ON through the valve command topic, like a manual
+//! pulse, so the hub's own handler applies every guard: emergency stop,
+//! frost and pressure lockouts, interlocks, the concurrency limit and the
+//! zone's daily limits.  Zones are tested one at a time and a zone is
+//! skipped while another valve is open, so readings belong to the zone
+//! under test.
+//!
+//! A zone fails when its valve doesn't open (the refusal is reported), when
+//! it is still open well after the blip, when its flow meter reported less
+//! than `min_flow_lpm` during the blip, or — with `min_pressure_drop_kpa`
+//! set — when line pressure didn't drop by that much.  Without a flow meter
+//! or pressure readings only the relay round-trip is checked.
+//!
+//! ```toml
+//! [valve_test]
+//! blip_sec = 5
+//! min_flow_lpm = 0.2
+//! min_pressure_drop_kpa = 20
+//! ```
+
+use crate::db::Db;
+use crate::sessions;
+use crate::state::{EventKind, SharedState, SystemState};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use time::OffsetDateTime;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+/// Longest blip a test may use.
+pub const MAX_BLIP_SEC: i64 = 60;
+
+/// How long the hub gets to confirm a valve opened.
+const OPEN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long past the blip a valve may take to report closed.
+const CLOSE_GRACE: Duration = Duration::from_secs(5);
+
+/// Wait after closing for the last flow samples of the blip to arrive.
+const FLOW_SETTLE: Duration = Duration::from_secs(2);
+
+const POLL: Duration = Duration::from_millis(250);
+
+/// `[valve_test]` section.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ValveTestConfig {
+    /// How long each valve is opened, unless the request says otherwise.
+    pub blip_sec: i64,
+    /// Flow (L/min) a zone's meter must show during the blip.
+    pub min_flow_lpm: f64,
+    /// Drop in line pressure (kPa) an opening valve must cause.  Unset =
+    /// pressure is only reported.
+    pub min_pressure_drop_kpa: Option<f64>,
+}
+
+impl Default for ValveTestConfig {
+    fn default() -> Self {
+        Self {
+            blip_sec: 5,
+            min_flow_lpm: 0.2,
+            min_pressure_drop_kpa: None,
+        }
+    }
+}
+
+impl ValveTestConfig {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        if !(1..=MAX_BLIP_SEC).contains(&self.blip_sec) {
+            errors.push(format!(
+                "valve_test: blip_sec must be within 1..={MAX_BLIP_SEC}, got {}",
+                self.blip_sec
+            ));
+        }
+        if !(self.min_flow_lpm.is_finite() && self.min_flow_lpm >= 0.0) {
+            errors.push(format!(
+                "valve_test: min_flow_lpm must not be negative, got {}",
+                self.min_flow_lpm
+            ));
+        }
+        if let Some(v) = self
+            .min_pressure_drop_kpa
+            .filter(|v| !(v.is_finite() && *v > 0.0))
+        {
+            errors.push(format!(
+                "valve_test: min_pressure_drop_kpa must be positive, got {v}"
+            ));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// Not tested yet.
+    Pending,
+    Pass,
+    Fail,
+    /// Not tested: another valve was open, or the test was aborted.
+    Skipped,
+}
+
+/// What was seen while one zone was tested.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Observation {
+    pub opened: bool,
+    pub closed: bool,
+    /// Why the hub refused to open the valve, if it said.
+    pub refusal: Option<String>,
+    /// Flow samples for the zone during the blip, and the largest.
+    pub flow_samples: i64,
+    pub max_flow_lpm: Option<f64>,
+    /// Newest line pressure before opening, and the lowest during the blip.
+    pub pressure_before_kpa: Option<f64>,
+    pub pressure_min_kpa: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ZoneResult {
+    pub zone_id: String,
+    pub outcome: Outcome,
+    pub opened: bool,
+    pub closed: bool,
+    pub flow_samples: i64,
+    pub max_flow_lpm: Option<f64>,
+    pub pressure_before_kpa: Option<f64>,
+    pub pressure_min_kpa: Option<f64>,
+    /// Why the zone failed or was skipped, or what went unchecked.
+    pub detail: Option<String>,
+}
+
+impl ZoneResult {
+    fn pending(zone_id: &str) -> Self {
+        Self {
+            zone_id: zone_id.to_string(),
+            outcome: Outcome::Pending,
+            opened: false,
+            closed: false,
+            flow_samples: 0,
+            max_flow_lpm: None,
+            pressure_before_kpa: None,
+            pressure_min_kpa: None,
+            detail: None,
+        }
+    }
+
+    fn skipped(zone_id: &str, reason: String) -> Self {
+        Self {
+            outcome: Outcome::Skipped,
+            detail: Some(reason),
+            ..Self::pending(zone_id)
+        }
+    }
+}
+
+/// Judge one zone from what was observed during its blip.
+pub fn evaluate(cfg: &ValveTestConfig, zone_id: &str, obs: &Observation) -> ZoneResult {
+    let failure = if !obs.opened {
+        Some(
+            obs.refusal
+                .clone()
+                .unwrap_or_else(|| "valve did not open".to_string()),
+        )
+    } else if !obs.closed {
+        Some("valve did not close after the blip".to_string())
+    } else if obs.flow_samples > 0 && obs.max_flow_lpm.unwrap_or(0.0) < cfg.min_flow_lpm {
+        Some(format!(
+            "no flow: at most {:.2} L/min (expected {:.2})",
+            obs.max_flow_lpm.unwrap_or(0.0),
+            cfg.min_flow_lpm
+        ))
+    } else {
+        match (
+            cfg.min_pressure_drop_kpa,
+            obs.pressure_before_kpa,
+            obs.pressure_min_kpa,
+        ) {
+            (Some(want), Some(before), Some(min)) if before - min < want => Some(format!(
+                "line pressure only dropped {:.0} kPa (expected {want:.0})",
+                before - min
+            )),
+            _ => None,
+        }
+    };
+    let detail = failure.clone().or_else(|| {
+        (obs.flow_samples == 0 && obs.pressure_min_kpa.is_none())
+            .then(|| "no flow or pressure readings; only the relay was checked".to_string())
+    });
+    ZoneResult {
+        zone_id: zone_id.to_string(),
+        outcome: if failure.is_some() {
+            Outcome::Fail
+        } else {
+            Outcome::Pass
+        },
+        opened: obs.opened,
+        closed: obs.closed,
+        flow_samples: obs.flow_samples,
+        max_flow_lpm: obs.max_flow_lpm,
+        pressure_before_kpa: obs.pressure_before_kpa,
+        pressure_min_kpa: obs.pressure_min_kpa,
+        detail,
+    }
+}
+
+/// The latest test, served by `GET /api/maintenance/valve-test`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValveTestReport {
+    #[serde(with = "time::serde::rfc3339")]
+    pub started_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub finished_at: Option<OffsetDateTime>,
+    pub blip_sec: i64,
+    /// Why the test stopped early (emergency stop, a lockout, ...).
+    pub aborted: Option<String>,
+    pub zones: Vec<ZoneResult>,
+}
+
+impl ValveTestReport {
+    pub fn new(zone_ids: &[String], blip_sec: i64, now: OffsetDateTime) -> Self {
+        Self {
+            started_at: now,
+            finished_at: None,
+            blip_sec,
+            aborted: None,
+            zones: zone_ids.iter().map(|z| ZoneResult::pending(z)).collect(),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.finished_at.is_none()
+    }
+
+    fn count(&self, outcome: Outcome) -> usize {
+        self.zones.iter().filter(|z| z.outcome == outcome).count()
+    }
+
+    /// e.g. "valve test: 3 passed, 1 failed, 0 skipped".
+    pub fn summary(&self) -> String {
+        let mut s = format!(
+            "valve test: {} passed, {} failed, {} skipped",
+            self.count(Outcome::Pass),
+            self.count(Outcome::Fail),
+            self.count(Outcome::Skipped)
+        );
+        if let Some(reason) = &self.aborted {
+            s.push_str(&format!(" (aborted: {reason})"));
+        }
+        s
+    }
+}
+
+/// Self-test settings and the latest report.
+#[derive(Debug, Clone, Default)]
+pub struct ValveTest {
+    pub cfg: ValveTestConfig,
+    pub report: Option<ValveTestReport>,
+}
+
+impl ValveTest {
+    pub fn new(cfg: &ValveTestConfig) -> Self {
+        Self {
+            cfg: *cfg,
+            report: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.report.as_ref().is_some_and(|r| r.is_running())
+    }
+}
+
+/// Why no valve may be opened right now, if anything.
+pub fn refusal(st: &SystemState) -> Option<String> {
+    if !st.mqtt_connected {
+        Some("hub is not connected to MQTT".to_string())
+    } else if st.mode == "monitor" {
+        Some("system is in monitor mode".to_string())
+    } else {
+        st.valve_lockout()
+    }
+}
+
+/// Run the test started in `st.valve_test.report`, one zone at a time.
+/// Blips are published through `water_now`; a valve still open after its
+/// blip is closed through `session_cancels`.
+pub async fn run(
+    shared: SharedState,
+    db: Db,
+    water_now: mpsc::Sender<(String, i64)>,
+    session_cancels: mpsc::Sender<String>,
+) {
+    let (cfg, blip_sec, zone_ids) = {
+        let st = shared.read().await;
+        let Some(report) = &st.valve_test.report else {
+            return;
+        };
+        let zone_ids: Vec<String> = report.zones.iter().map(|z| z.zone_id.clone()).collect();
+        (st.valve_test.cfg, report.blip_sec, zone_ids)
+    };
+
+    for (i, zone_id) in zone_ids.iter().enumerate() {
+        let refused = refusal(&*shared.read().await);
+        if let Some(reason) = refused {
+            abort(&shared, i, reason).await;
+            return;
+        }
+        let busy = {
+            let st = shared.read().await;
+            st.zones
+                .iter()
+                .filter(|(_, z)| z.on)
+                .map(|(id, _)| id.clone())
+                .min()
+        };
+        let result = match busy {
+            Some(other) => ZoneResult::skipped(zone_id, format!("zone {other} was watering")),
+            None => match blip(
+                &shared,
+                &db,
+                &water_now,
+                &session_cancels,
+                zone_id,
+                blip_sec,
+            )
+            .await
+            {
+                Ok(obs) => evaluate(&cfg, zone_id, &obs),
+                Err(reason) => {
+                    abort(&shared, i, reason).await;
+                    return;
+                }
+            },
+        };
+        info!(zone = %zone_id, outcome = ?result.outcome, "valve test");
+        if let Some(report) = shared.write().await.valve_test.report.as_mut() {
+            report.zones[i] = result;
+        }
+    }
+    finish(&shared).await;
+}
+
+/// Open `zone_id` for `blip_sec` and watch it.  `Err` means the test can't
+/// go on.
+async fn blip(
+    shared: &SharedState,
+    db: &Db,
+    water_now: &mpsc::Sender<(String, i64)>,
+    session_cancels: &mpsc::Sender<String>,
+    zone_id: &str,
+    blip_sec: i64,
+) -> Result<Observation, String> {
+    let mut obs = Observation::default();
+    let sent_at = OffsetDateTime::now_utc();
+    let since_ts = sent_at.unix_timestamp();
+    obs.pressure_before_kpa = {
+        let mut st = shared.write().await;
+        st.sessions
+            .plan(zone_id, sessions::VALVE_TEST_REASON, Some(blip_sec));
+        st.pressure.latest().map(|r| r.kpa)
+    };
+    if water_now
+        .send((zone_id.to_string(), blip_sec))
+        .await
+        .is_err()
+    {
+        shared.write().await.sessions.unplan(zone_id);
+        return Err("valve command publisher is not running".to_string());
+    }
+
+    let deadline = tokio::time::Instant::now() + OPEN_TIMEOUT;
+    while tokio::time::Instant::now() < deadline {
+        if is_on(shared, zone_id, since_ts, &mut obs).await {
+            obs.opened = true;
+            break;
+        }
+        tokio::time::sleep(POLL).await;
+    }
+    if !obs.opened {
+        let mut st = shared.write().await;
+        st.sessions.unplan(zone_id);
+        obs.refusal = st
+            .events
+            .iter()
+            .rev()
+            .take_while(|e| e.ts >= sent_at)
+            .find(|e| {
+                matches!(e.kind, EventKind::Error)
+                    && e.detail.contains(zone_id)
+                    && (e.detail.contains("refused") || e.detail.contains("blocked"))
+            })
+            .map(|e| e.detail.clone());
+        return Ok(obs);
+    }
+
+    let deadline =
+        tokio::time::Instant::now() + Duration::from_secs(blip_sec.unsigned_abs()) + CLOSE_GRACE;
+    while tokio::time::Instant::now() < deadline {
+        if !is_on(shared, zone_id, since_ts, &mut obs).await {
+            obs.closed = true;
+            break;
+        }
+        tokio::time::sleep(POLL).await;
+    }
+    if !obs.closed {
+        warn!(zone = %zone_id, "valve test: valve still open after its blip — closing");
+        let _ = session_cancels.send(zone_id.to_string()).await;
+    }
+
+    tokio::time::sleep(FLOW_SETTLE).await;
+    match db
+        .flow_between(
+            zone_id,
+            since_ts,
+            OffsetDateTime::now_utc().unix_timestamp(),
+        )
+        .await
+    {
+        Ok((samples, max_lpm)) => {
+            obs.flow_samples = samples;
+            obs.max_flow_lpm = max_lpm;
+        }
+        Err(e) => warn!(zone = %zone_id, "valve test: flow query failed: {e:#}"),
+    }
+    Ok(obs)
+}
+
+/// Whether `zone_id` is open, noting the lowest line pressure since
+/// `since_ts`.
+async fn is_on(shared: &SharedState, zone_id: &str, since_ts: i64, obs: &mut Observation) -> bool {
+    let st = shared.read().await;
+    if let Some(r) = st.pressure.latest().filter(|r| r.ts >= since_ts) {
+        obs.pressure_min_kpa = Some(obs.pressure_min_kpa.map_or(r.kpa, |m| m.min(r.kpa)));
+    }
+    st.zones.get(zone_id).is_some_and(|z| z.on)
+}
+
+/// Stop the test before zone `from`; it and the rest are skipped.
+async fn abort(shared: &SharedState, from: usize, reason: String) {
+    warn!("valve test aborted: {reason}");
+    if let Some(report) = shared.write().await.valve_test.report.as_mut() {
+        for z in &mut report.zones[from..] {
+            *z = ZoneResult::skipped(&z.zone_id, "test aborted".to_string());
+        }
+        report.aborted = Some(reason);
+    }
+    finish(shared).await;
+}
+
+async fn finish(shared: &SharedState) {
+    let mut st = shared.write().await;
+    let Some(report) = st.valve_test.report.as_mut() else {
+        return;
+    };
+    report.finished_at = Some(OffsetDateTime::now_utc());
+    let summary = report.summary();
+    info!("{summary}");
+    st.record_system(summary);
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn observed() -> Observation {
+        Observation {
+            opened: true,
+            closed: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn a_zone_that_opens_and_closes_passes() {
+        let cfg = ValveTestConfig::default();
+        let r = evaluate(&cfg, "z1", &observed());
+        assert_eq!(r.outcome, Outcome::Pass);
+        assert_eq!(
+            r.detail.as_deref(),
+            Some("no flow or pressure readings; only the relay was checked")
+        );
+
+        let obs = Observation {
+            flow_samples: 2,
+            max_flow_lpm: Some(6.0),
+            ..observed()
+        };
+        let r = evaluate(&cfg, "z1", &obs);
+        assert_eq!(r.outcome, Outcome::Pass);
+        assert_eq!(r.detail, None);
+    }
+
+    #[test]
+    fn failures_are_explained() {
+        let cfg = ValveTestConfig {
+            min_pressure_drop_kpa: Some(20.0),
+            ..Default::default()
+        };
+        let refused = Observation {
+            refusal: Some("valve ON refused for z1 — frost".into()),
+            ..Default::default()
+        };
+        let r = evaluate(&cfg, "z1", &refused);
+        assert_eq!(r.outcome, Outcome::Fail);
+        assert_eq!(r.detail.as_deref(), Some("valve ON refused for z1 — frost"));
+        let r = evaluate(&cfg, "z1", &Observation::default());
+        assert_eq!(r.detail.as_deref(), Some("valve did not open"));
+
+        let stuck = Observation {
+            closed: false,
+            ..observed()
+        };
+        assert_eq!(
+            evaluate(&cfg, "z1", &stuck).detail.as_deref(),
+            Some("valve did not close after the blip")
+        );
+
+        let dry = Observation {
+            flow_samples: 3,
+            max_flow_lpm: Some(0.0),
+            ..observed()
+        };
+        let r = evaluate(&cfg, "z1", &dry);
+        assert_eq!(r.outcome, Outcome::Fail);
+        assert!(r.detail.unwrap().starts_with("no flow"));
+
+        let flat = Observation {
+            pressure_before_kpa: Some(400.0),
+            pressure_min_kpa: Some(390.0),
+            ..observed()
+        };
+        let r = evaluate(&cfg, "z1", &flat);
+        assert_eq!(
+            r.detail.as_deref(),
+            Some("line pressure only dropped 10 kPa (expected 20)")
+        );
+        let drop = Observation {
+            pressure_min_kpa: Some(300.0),
+            ..flat.clone()
+        };
+        assert_eq!(evaluate(&cfg, "z1", &drop).outcome, Outcome::Pass);
+        // Pressure is only reported without a threshold.
+        let r = evaluate(&ValveTestConfig::default(), "z1", &flat);
+        assert_eq!(r.outcome, Outcome::Pass);
+    }
+
+    #[test]
+    fn config_validation() {
+        assert!(ValveTestConfig::default().validate().is_ok());
+        let errs = ValveTestConfig {
+            blip_sec: 0,
+            min_flow_lpm: -1.0,
+            min_pressure_drop_kpa: Some(0.0),
+        }
+        .validate()
+        .unwrap_err();
+        assert_eq!(errs.len(), 3, "{errs:?}");
+    }
+}
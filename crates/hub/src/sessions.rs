@@ -0,0 +1,294 @@
+//! Watering sessions: one object per valve opening, from the command that
+//! asked for it to the close that ended it.
+//!
+//! Whoever wants a valve open plans a session first ([`Sessions::plan`]:
+//! trigger reason and planned duration, which reserves the session's id);
+//! the scheduler and `POST /api/zones/{id}/water-now` do this before
+//! publishing `ON`.  The hub takes the plan when the `ON` arrives and, if
+//! the valve actually opens, starts it, or an unplanned session
+//! (`mqtt_command`) for commands from elsewhere.  Closing the valve
+//! finishes it with a [`SessionResult`]: `completed` for a normal `OFF`, `cancelled` when `OFF` follows
+//! `POST /api/sessions/{id}/cancel`, `watchdog` when the watchdog closed
+//! it, and `forced_off` when every valve was shut (emergency stop, MQTT
+//! loss, task restart, restore).  Active sessions and the most recent
+//! finished ones are served by `GET /api/sessions`.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use serde::Serialize;
+use time::OffsetDateTime;
+
+/// Finished sessions kept for `GET /api/sessions`.
+const MAX_RECENT: usize = 50;
+
+/// Reason recorded for valves opened by a command nobody planned.
+pub const UNPLANNED_REASON: &str = "mqtt_command";
+
+/// Reason of the scheduler's watering pulses.
+pub const SCHEDULER_REASON: &str = "scheduler";
+
+/// Reason of pulses started with `POST /api/zones/{id}/water-now`.
+pub const MANUAL_REASON: &str = "manual";
+
+/// Reason of blips started by `POST /api/maintenance/valve-test`.
+pub const VALVE_TEST_REASON: &str = "valve_test";
+
+/// Who asked for a session with `reason`, as recorded in its watering
+/// event: `manual`, `mqtt` or (pulses and flushes) `scheduler`.
+pub fn triggered_by(reason: &str) -> &'static str {
+    match reason {
+        MANUAL_REASON | VALVE_TEST_REASON => "manual",
+        UNPLANNED_REASON => "mqtt",
+        _ => "scheduler",
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionResult {
+    Completed,
+    Cancelled,
+    Watchdog,
+    ForcedOff,
+}
+
+impl SessionResult {
+    /// `result` of the session's watering event.
+    pub fn event_result(self) -> &'static str {
+        match self {
+            Self::Completed => "ok",
+            Self::Cancelled => "cancelled",
+            Self::Watchdog => "watchdog",
+            Self::ForcedOff => "forced_off",
+        }
+    }
+
+    /// `ended_by` of the session's watering event.
+    pub fn ended_by(self) -> &'static str {
+        match self {
+            Self::Completed | Self::Cancelled => "off_command",
+            Self::Watchdog => "watchdog",
+            Self::ForcedOff => "emergency",
+        }
+    }
+}
+
+/// A session asked for but not started yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Planned {
+    /// Reserved by [`Sessions::plan`]; unplanned sessions get theirs when
+    /// they start.
+    pub id: Option<u64>,
+    pub reason: &'static str,
+    pub planned_sec: Option<i64>,
+}
+
+impl Planned {
+    /// What an `ON` nobody planned starts.
+    pub fn unplanned() -> Self {
+        Self {
+            id: None,
+            reason: UNPLANNED_REASON,
+            planned_sec: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Session {
+    pub id: u64,
+    pub zone_id: String,
+    pub reason: &'static str,
+    /// How long the valve was meant to stay open; unset for unplanned
+    /// commands.
+    pub planned_sec: Option<i64>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub started_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub ended_at: Option<OffsetDateTime>,
+    /// Unset while active.
+    pub result: Option<SessionResult>,
+    /// Cancellation was requested and the `OFF` is on its way.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub cancel_requested: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Sessions {
+    next_id: u64,
+    planned: HashMap<String, Planned>,
+    /// zone_id -> its open session.
+    active: BTreeMap<String, Session>,
+    /// Newest last.
+    recent: VecDeque<Session>,
+}
+
+impl Sessions {
+    /// Plan the next session for `zone_id`, replacing any earlier plan.
+    /// Returns the id the session will have once it starts.
+    pub fn plan(&mut self, zone_id: &str, reason: &'static str, planned_sec: Option<i64>) -> u64 {
+        self.next_id += 1;
+        self.planned.insert(
+            zone_id.to_string(),
+            Planned {
+                id: Some(self.next_id),
+                reason,
+                planned_sec,
+            },
+        );
+        self.next_id
+    }
+
+    /// Take the plan for `zone_id` (its `ON` arrived, or was never sent).
+    pub fn unplan(&mut self, zone_id: &str) -> Option<Planned> {
+        self.planned.remove(zone_id)
+    }
+
+    /// The valve opened: start `planned`, or an unplanned session.  A
+    /// repeated `ON` for an open valve keeps the running session.
+    pub fn start(
+        &mut self,
+        zone_id: &str,
+        planned: Option<Planned>,
+        now: OffsetDateTime,
+    ) -> &Session {
+        if !self.active.contains_key(zone_id) {
+            let planned = planned.unwrap_or_else(Planned::unplanned);
+            let id = planned.id.unwrap_or_else(|| {
+                self.next_id += 1;
+                self.next_id
+            });
+            self.active.insert(
+                zone_id.to_string(),
+                Session {
+                    id,
+                    zone_id: zone_id.to_string(),
+                    reason: planned.reason,
+                    planned_sec: planned.planned_sec,
+                    started_at: now,
+                    ended_at: None,
+                    result: None,
+                    cancel_requested: false,
+                },
+            );
+        }
+        &self.active[zone_id]
+    }
+
+    /// The valve closed.  `result` overrides the default (`cancelled` if a
+    /// cancellation was requested, else `completed`).
+    pub fn finish(
+        &mut self,
+        zone_id: &str,
+        result: Option<SessionResult>,
+        now: OffsetDateTime,
+    ) -> Option<Session> {
+        let mut session = self.active.remove(zone_id)?;
+        session.result = Some(result.unwrap_or(if session.cancel_requested {
+            SessionResult::Cancelled
+        } else {
+            SessionResult::Completed
+        }));
+        session.ended_at = Some(now);
+        if self.recent.len() >= MAX_RECENT {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(session.clone());
+        Some(session)
+    }
+
+    /// Every valve was shut: finish all sessions as `forced_off` and drop
+    /// plans.
+    pub fn finish_all(&mut self, now: OffsetDateTime) {
+        self.planned.clear();
+        let zones: Vec<String> = self.active.keys().cloned().collect();
+        for zone_id in zones {
+            self.finish(&zone_id, Some(SessionResult::ForcedOff), now);
+        }
+    }
+
+    /// Mark active session `id` for cancellation; returns its zone.
+    pub fn request_cancel(&mut self, id: u64) -> Option<String> {
+        let session = self.active.values_mut().find(|s| s.id == id)?;
+        session.cancel_requested = true;
+        Some(session.zone_id.clone())
+    }
+
+    /// Why `zone_id`'s open session was started, if it has one.
+    pub fn active_reason(&self, zone_id: &str) -> Option<&'static str> {
+        self.active.get(zone_id).map(|s| s.reason)
+    }
+
+    /// Active sessions, by zone.
+    pub fn active(&self) -> Vec<Session> {
+        self.active.values().cloned().collect()
+    }
+
+    /// Finished sessions, newest first.
+    pub fn recent(&self) -> Vec<Session> {
+        self.recent.iter().rev().cloned().collect()
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn planned_and_unplanned_sessions() {
+        let now = OffsetDateTime::now_utc();
+        let mut s = Sessions::default();
+        let reserved = s.plan("z1", SCHEDULER_REASON, Some(30));
+        let planned = s.unplan("z1");
+        assert!(s.unplan("z1").is_none());
+        let id = s.start("z1", planned, now).id;
+        assert_eq!(id, reserved);
+        // A repeated ON keeps the session.
+        assert_eq!(s.start("z1", None, now).id, id);
+        let z2 = s.start("z2", None, now);
+        assert_eq!(z2.reason, UNPLANNED_REASON);
+        assert_eq!(z2.planned_sec, None);
+        assert_ne!(z2.id, id);
+        assert_eq!(s.active().len(), 2);
+        assert_eq!(s.active_reason("z1"), Some(SCHEDULER_REASON));
+        assert_eq!(s.active_reason("z3"), None);
+
+        let done = s.finish("z1", None, now).unwrap();
+        assert_eq!(done.reason, SCHEDULER_REASON);
+        assert_eq!(done.planned_sec, Some(30));
+        assert_eq!(done.result, Some(SessionResult::Completed));
+        assert!(s.finish("z1", None, now).is_none());
+
+        s.finish_all(now);
+        assert!(s.active().is_empty());
+        let recent = s.recent();
+        assert_eq!(recent[0].zone_id, "z2");
+        assert_eq!(recent[0].result, Some(SessionResult::ForcedOff));
+    }
+
+    #[test]
+    fn cancel_marks_the_result() {
+        let now = OffsetDateTime::now_utc();
+        let mut s = Sessions::default();
+        let id = s.start("z1", None, now).id;
+        assert_eq!(s.request_cancel(id + 1), None);
+        assert_eq!(s.request_cancel(id).as_deref(), Some("z1"));
+        let done = s.finish("z1", None, now).unwrap();
+        assert_eq!(done.result, Some(SessionResult::Cancelled));
+        assert_eq!(done.result.unwrap().event_result(), "cancelled");
+
+        // The watchdog result wins over the default.
+        s.start("z1", None, now);
+        let done = s.finish("z1", Some(SessionResult::Watchdog), now).unwrap();
+        assert_eq!(done.result, Some(SessionResult::Watchdog));
+        assert_eq!(done.result.unwrap().ended_by(), "watchdog");
+        assert_eq!(triggered_by(done.reason), "mqtt");
+        assert_eq!(triggered_by(MANUAL_REASON), "manual");
+        assert_eq!(triggered_by(VALVE_TEST_REASON), "manual");
+        assert_eq!(triggered_by("flush"), "scheduler");
+    }
+}
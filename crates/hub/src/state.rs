@@ -1,22 +1,98 @@
 //! In-memory system state for the live web dashboard: node telemetry, zone
 //! valve status, and a capped event ring buffer.
-
-use serde::Serialize;
-use std::collections::{HashMap, VecDeque};
+//!
+//! The event ring is also saved to a small sidecar file (periodically and
+//! on shutdown) and reloaded at startup, so a restart keeps the recent
+//! operational context.
+
+use crate::alerts::{LowZone, MoistureAlerts};
+use crate::blackout::{Blackout, Blackouts};
+use crate::budget::BudgetUsage;
+use crate::db::ROLLING_WINDOW_SEC;
+use crate::estop::EmergencyStop;
+use crate::et::Weather;
+use crate::federation::Federation;
+use crate::frost::FrostLockout;
+use crate::interlock::Interlocks;
+use crate::limits::SafetyLimits;
+use crate::metrics::{Metrics, RejectCount};
+use crate::moisture::MoistureWindow;
+use crate::mqtt::Reject;
+use crate::ota::NodeOta;
+use crate::pressure::PressureMonitor;
+use crate::retention::RetentionPolicy;
+use crate::review::Finding;
+use crate::selftest::ValveTest;
+use crate::sessions::Sessions;
+use crate::strategy::Advice;
+use crate::sunlight::Sunlight;
+use anyhow::{Context, Result};
+use arc_swap::ArcSwap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use time::OffsetDateTime;
-use tokio::sync::RwLock;
+use tokio::sync::{watch, RwLock};
 
 /// Maximum number of events retained in the ring buffer.
 const MAX_EVENTS: usize = 200;
 
+/// Newest events carried by each `status/hub/heartbeat` message.
+pub const HEARTBEAT_EVENTS: usize = 10;
+
+/// How often the event ring is saved to its sidecar file (when changed).
+pub const EVENTS_SAVE_INTERVAL_SEC: u64 = 60;
+
+/// Default threshold (in minutes) after which a node is considered stale if no
+/// telemetry has been received.  Override with `NODE_STALE_TIMEOUT_MIN` env var.
+/// Should be roughly 2× the node sampling interval (default 300s = 5 min).
+pub const DEFAULT_NODE_STALE_TIMEOUT_MIN: i64 = 10;
+
+/// Consecutive implausible readings after which a sensor is quarantined.
+/// Override with `SENSOR_QUARANTINE_AFTER` env var.
+pub const DEFAULT_SENSOR_QUARANTINE_AFTER: i64 = 5;
+
+/// A background task (scheduler, valve watchdog) is reported dead by
+/// `/api/health` if it hasn't completed a loop iteration in this long.
+/// Comfortably above the scheduler's 30 s tick.
+pub const TASK_HEARTBEAT_TIMEOUT_SEC: i64 = 120;
+
+/// While the database is unwritable, manual valve commands are held to this
+/// fraction of each zone's configured daily limits (counted in memory).
+pub const DEGRADED_LIMIT_DIVISOR: i64 = 2;
+
+/// A daily limit reduced for degraded mode (never below 1).
+pub fn degraded_limit(limit: i64) -> i64 {
+    (limit / DEGRADED_LIMIT_DIVISOR).max(1)
+}
+
 // ---------------------------------------------------------------------------
 // Public type alias
 // ---------------------------------------------------------------------------
 
 pub type SharedState = Arc<RwLock<SystemState>>;
 
+/// The latest `/api/status` body.  The web layer serves this instead of
+/// locking [`SharedState`] per request, so dashboard polling never holds up
+/// the MQTT handler or scheduler waiting on a write lock.
+pub type StatusSnapshot = Arc<ArcSwap<StatusResponse>>;
+
+/// How often the status snapshot is republished from the live state.
+pub const STATUS_PUBLISH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Build a snapshot holding the current status.
+pub async fn status_snapshot(shared: &SharedState) -> StatusSnapshot {
+    Arc::new(ArcSwap::from_pointee(shared.read().await.to_status()))
+}
+
+/// Republish the snapshot from the live state.  One short read lock per
+/// call, however many clients are polling.
+pub async fn publish_status(shared: &SharedState, snapshot: &StatusSnapshot) {
+    let status = shared.read().await.to_status();
+    snapshot.store(Arc::new(status));
+}
+
 // ---------------------------------------------------------------------------
 // Core types
 // ---------------------------------------------------------------------------
@@ -27,14 +103,109 @@ pub struct SystemState {
     /// Operation mode label: "auto" or "monitor".
     pub mode: String,
     pub nodes: HashMap<String, NodeState>,
+    /// node_id -> last log lines the node sent in answer to `send-logs`.
+    pub node_logs: HashMap<String, NodeLogs>,
+    /// node_id -> latest answer to `ping`.
+    pub node_pongs: HashMap<String, NodePong>,
+    /// node_id -> pending update and last `ota/<node_id>/status` report.
+    pub node_ota: HashMap<String, NodeOta>,
     pub zones: HashMap<String, ZoneState>,
     pub events: VecDeque<SystemEvent>,
+    /// Bumped on every new event, so the sidecar file is only rewritten
+    /// when something changed.
+    pub events_seq: u64,
+    /// Sequence number of the newest event or zone/node change, watched by
+    /// `/api/status/changes` long-polls.
+    change_seq: watch::Sender<u64>,
+    /// Sequence number of the newest event dropped from the ring.
+    dropped_event_seq: u64,
     /// CPU usage as a percentage (0.0 - 100.0).
     pub cpu_usage_percent: f32,
     /// Memory currently used in bytes.
     pub memory_used_bytes: u64,
     /// Total system memory in bytes.
     pub memory_total_bytes: u64,
+    /// Hub-wide node staleness threshold (`NODE_STALE_TIMEOUT_MIN`); nodes
+    /// may override it via `/api/nodes/{node_id}`.
+    pub node_stale_timeout_min: i64,
+    /// Consecutive implausible readings before a sensor is quarantined
+    /// (`SENSOR_QUARANTINE_AFTER`).
+    pub sensor_quarantine_after: i64,
+    /// Latency histograms exposed at `GET /metrics`.
+    pub metrics: Metrics,
+    /// Last loop iteration of the scheduler task.
+    pub scheduler_heartbeat: Option<OffsetDateTime>,
+    /// Last loop iteration of the valve watchdog task.
+    pub watchdog_heartbeat: Option<OffsetDateTime>,
+    /// Completion time of the last successful database backup.
+    pub last_backup_at: Option<OffsetDateTime>,
+    /// Background tasks restarted after exiting or panicking.
+    pub tasks_restarted: u32,
+    /// Set while the database is unwritable (degraded mode): the scheduler
+    /// starts no new pulses and daily counters accumulate in memory.
+    pub db_degraded_since: Option<OffsetDateTime>,
+    /// (day, zone_id) -> counters accrued while degraded, flushed to the DB
+    /// on recovery.
+    pending_counters: HashMap<(String, String), PendingCounters>,
+    /// (day, zone_id) -> daily counters last read from the DB.  Degraded
+    /// mode adds `pending_counters` to these, so what a zone used before
+    /// the DB failed still counts.
+    db_counters: HashMap<(String, String), PendingCounters>,
+    /// (flush ts, zone_id, counters) flushed on leaving degraded mode.  No
+    /// watering events were written for those pulses, so the 24h window
+    /// counts them until a day after the flush.
+    flushed_counters: Vec<(i64, String, PendingCounters)>,
+    /// zone_id -> latest unconsumed advisor recommendation.
+    advice: HashMap<String, Advice>,
+    /// Planned, active and recently finished watering sessions.
+    pub sessions: Sessions,
+    /// Water budget usage today, refreshed every scheduler tick (empty
+    /// without a `[budget]`).
+    pub budget: Vec<BudgetUsage>,
+    /// Sensors whose latest reading was implausible; left out of zone
+    /// moisture until they report a plausible value again.
+    faulted_sensors: BTreeSet<String>,
+    /// Sensors quarantined after repeated implausible readings (mirrors
+    /// `sensor_health`); their readings no longer raise error events.
+    quarantined_sensors: BTreeSet<String>,
+    /// Cold-start safety review findings; zones with unacknowledged ones
+    /// aren't watered in auto mode.
+    safety_review: Vec<Finding>,
+    /// Low-temperature lockout of valve ON commands.
+    pub frost: FrostLockout,
+    /// Newest light reading per node, for the peak-sun deferral.
+    pub sunlight: Sunlight,
+    /// Low-pressure lockout and high-pressure cut-off.
+    pub pressure: PressureMonitor,
+    /// Emergency-stop button state and latched lockout.
+    pub estop: EmergencyStop,
+    /// Valve self-test settings and the latest report.
+    pub valve_test: ValveTest,
+    /// Recent readings per sensor, read by the scheduler instead of the
+    /// database.
+    pub moisture: MoistureWindow,
+    /// The safety envelope served by `/api/limits`.
+    pub limits: SafetyLimits,
+    /// Data retention policy, read by the pruner on every run.
+    pub retention: RetentionPolicy,
+    /// Sites mirrored from `[federation]`.
+    pub federation: Federation,
+    /// Low-moisture alert state from `[moisture_alerts]`.
+    pub moisture_alerts: MoistureAlerts,
+    /// Blackout calendar, replaced after every API change.
+    pub blackouts: Blackouts,
+    /// Weather samples for the current day's ET0.
+    pub weather: Weather,
+    /// Water-balance deficit (mm) of `et` zones, mirrored to
+    /// `zone_et_deficit`.
+    pub et_deficits: BTreeMap<String, f64>,
+}
+
+/// Daily safety counters held in memory while the database is unwritable.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PendingCounters {
+    pub pulses: i64,
+    pub open_sec: i64,
 }
 
 #[derive(Clone, Serialize)]
@@ -43,13 +214,67 @@ pub struct NodeState {
     pub last_seen: OffsetDateTime,
     /// Whether the node is connected to MQTT (tracked via LWT status messages).
     pub online: bool,
+    /// Change sequence number of the last update (see [`StatusChanges`]).
+    pub seq: u64,
     pub readings: Vec<SensorReading>,
+    /// Build the node last announced itself with; `None` for nodes that
+    /// predate version reporting (a bare `online`).
+    pub firmware: Option<Firmware>,
+}
+
+/// Crate version and git commit a hub or node reports in its retained
+/// status message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Firmware {
+    pub version: String,
+    pub git: String,
+}
+
+impl Firmware {
+    /// This hub's build.
+    pub fn hub() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git: env!("GIT_HASH").to_string(),
+        }
+    }
+}
+
+/// Log lines published by a node on `diag/<node_id>/logs`.
+#[derive(Clone, Serialize)]
+pub struct NodeLogs {
+    #[serde(with = "time::serde::rfc3339")]
+    pub received_at: OffsetDateTime,
+    /// The node's own timestamp (unix seconds).
+    pub ts: i64,
+    pub lines: Vec<String>,
+}
+
+/// A node's latest answer to a ping, from `diag/<node_id>/pong`.
+#[derive(Clone, Serialize)]
+pub struct NodePong {
+    #[serde(with = "time::serde::rfc3339")]
+    pub received_at: OffsetDateTime,
+    /// The ping this answers (hub time, unix milliseconds).
+    pub sent_ms: Option<i64>,
+    /// From the ping's publish to receipt of the answer.
+    pub round_trip_ms: Option<i64>,
+    /// The node's own timestamp (unix seconds).
+    pub ts: i64,
+    pub uptime_sec: u64,
+    pub sample_interval_sec: u64,
+    pub last_sample_ts: Option<i64>,
+    /// Readings waiting in the node's offline buffer.
+    pub buffered: u64,
 }
 
 #[derive(Clone, Serialize)]
 pub struct SensorReading {
     pub sensor_id: String,
     pub raw: i64,
+    /// Millivolts, when the node reports them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mv: Option<f32>,
 }
 
 #[derive(Clone, Serialize)]
@@ -58,17 +283,22 @@ pub struct ZoneState {
     pub gpio_pin: u8,
     #[serde(with = "time::serde::rfc3339::option")]
     pub last_changed: Option<OffsetDateTime>,
+    /// Change sequence number of the last valve change.
+    pub seq: u64,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SystemEvent {
+    /// Change sequence number; 0 for events restored from a previous run.
+    #[serde(default)]
+    pub seq: u64,
     #[serde(with = "time::serde::rfc3339")]
     pub ts: OffsetDateTime,
     pub kind: EventKind,
     pub detail: String,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum EventKind {
     Reading,
@@ -76,6 +306,10 @@ pub enum EventKind {
     Error,
     System,
     Scheduler,
+    /// Equipment due for service.
+    Maintenance,
+    /// A low-moisture alert or its recovery (see `alerts`).
+    Alert,
 }
 
 // ---------------------------------------------------------------------------
@@ -84,6 +318,8 @@ pub enum EventKind {
 
 #[derive(Serialize)]
 pub struct StatusResponse {
+    /// This hub's build.
+    pub hub_version: Firmware,
     pub uptime_secs: u64,
     pub mqtt_connected: bool,
     pub mode: String,
@@ -93,6 +329,114 @@ pub struct StatusResponse {
     pub cpu_usage_percent: f32,
     pub memory_used_bytes: u64,
     pub memory_total_bytes: u64,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub db_degraded_since: Option<OffsetDateTime>,
+    pub budget: Vec<BudgetUsage>,
+    pub faulted_sensors: Vec<String>,
+    pub quarantined_sensors: Vec<String>,
+    /// Safety review findings awaiting acknowledgement.
+    pub pending_safety_review: Vec<Finding>,
+    pub frost: FrostLockout,
+    pub pressure: PressureMonitor,
+    pub emergency_stop: EmergencyStop,
+    /// The blackout in effect today, if any.
+    pub blackout: Option<Blackout>,
+    pub mqtt_rejects: Vec<RejectCount>,
+    /// Zones below their low-moisture alert threshold.
+    pub moisture_alerts: Vec<LowZone>,
+}
+
+/// What changed after a sequence number, for `GET /api/status/changes`.
+#[derive(Serialize)]
+pub struct StatusChanges {
+    /// Newest sequence number: the next request's `since_seq`.
+    pub seq: u64,
+    /// Goes back to 0 when the hub restarts, as does `seq`.
+    pub uptime_secs: u64,
+    /// The changes since `since_seq` are incomplete; reload `/api/status`.
+    pub reset: bool,
+    /// New events, oldest first.
+    pub events: Vec<SystemEvent>,
+    pub zones: HashMap<String, ZoneState>,
+    pub nodes: HashMap<String, NodeState>,
+}
+
+/// Periodic hub health message on `status/hub/heartbeat`, for consumers
+/// (Node-RED, Home Assistant, a federating hub) that don't poll the REST
+/// API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HubHeartbeat {
+    pub ts: i64,
+    pub uptime_secs: u64,
+    pub mode: String,
+    /// Zones whose valve is open, sorted.
+    pub open_valves: Vec<String>,
+    pub zones: BTreeMap<String, ZoneHeartbeat>,
+    #[serde(default)]
+    pub nodes: BTreeMap<String, NodeHeartbeat>,
+    /// The newest [`HEARTBEAT_EVENTS`] events, newest first.
+    #[serde(default)]
+    pub events: Vec<SystemEvent>,
+    pub emergency_stop_latched: bool,
+    pub frost_locked: bool,
+    pub db_degraded: bool,
+}
+
+/// A zone's newest moisture reading; unset until one arrives.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ZoneHeartbeat {
+    pub moisture: Option<f32>,
+    pub moisture_ts: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NodeHeartbeat {
+    pub online: bool,
+    pub last_seen: i64,
+}
+
+/// Structured readiness report for `GET /api/health`.
+#[derive(Serialize)]
+pub struct HealthResponse {
+    /// "healthy" when every required component is OK, otherwise "degraded".
+    pub status: &'static str,
+    pub uptime_secs: u64,
+    pub mqtt_connected: bool,
+    pub db_connected: bool,
+    pub scheduler: TaskHealth,
+    pub watchdog: TaskHealth,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub last_backup_at: Option<OffsetDateTime>,
+    pub tasks_restarted: u32,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub db_degraded_since: Option<OffsetDateTime>,
+}
+
+#[derive(Serialize)]
+pub struct TaskHealth {
+    /// False when the task is intentionally not running (e.g. the watchdog
+    /// in monitor mode); a disabled task never degrades health.
+    pub enabled: bool,
+    pub alive: bool,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub last_heartbeat: Option<OffsetDateTime>,
+    pub heartbeat_age_sec: Option<i64>,
+}
+
+impl TaskHealth {
+    fn new(enabled: bool, last_heartbeat: Option<OffsetDateTime>, now: OffsetDateTime) -> Self {
+        let heartbeat_age_sec = last_heartbeat.map(|t| (now - t).whole_seconds());
+        Self {
+            enabled,
+            alive: heartbeat_age_sec.is_some_and(|age| age <= TASK_HEARTBEAT_TIMEOUT_SEC),
+            last_heartbeat,
+            heartbeat_age_sec,
+        }
+    }
+
+    fn ok(&self) -> bool {
+        !self.enabled || self.alive
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -109,6 +453,7 @@ impl SystemState {
                     on: false,
                     gpio_pin: *pin,
                     last_changed: None,
+                    seq: 0,
                 },
             );
         }
@@ -118,11 +463,47 @@ impl SystemState {
             mqtt_connected: false,
             mode: mode.to_string(),
             nodes: HashMap::new(),
+            node_logs: HashMap::new(),
+            node_pongs: HashMap::new(),
+            node_ota: HashMap::new(),
             zones,
             events: VecDeque::with_capacity(MAX_EVENTS),
+            events_seq: 0,
+            change_seq: watch::Sender::new(0),
+            dropped_event_seq: 0,
             cpu_usage_percent: 0.0,
             memory_used_bytes: 0,
             memory_total_bytes: 0,
+            node_stale_timeout_min: DEFAULT_NODE_STALE_TIMEOUT_MIN,
+            sensor_quarantine_after: DEFAULT_SENSOR_QUARANTINE_AFTER,
+            metrics: Metrics::default(),
+            scheduler_heartbeat: None,
+            watchdog_heartbeat: None,
+            last_backup_at: None,
+            tasks_restarted: 0,
+            db_degraded_since: None,
+            pending_counters: HashMap::new(),
+            db_counters: HashMap::new(),
+            flushed_counters: Vec::new(),
+            advice: HashMap::new(),
+            sessions: Sessions::default(),
+            budget: Vec::new(),
+            faulted_sensors: BTreeSet::new(),
+            quarantined_sensors: BTreeSet::new(),
+            safety_review: Vec::new(),
+            frost: FrostLockout::default(),
+            sunlight: Sunlight::default(),
+            pressure: PressureMonitor::default(),
+            estop: EmergencyStop::default(),
+            valve_test: ValveTest::default(),
+            moisture: MoistureWindow::default(),
+            limits: SafetyLimits::default(),
+            retention: RetentionPolicy::default(),
+            federation: Federation::default(),
+            moisture_alerts: MoistureAlerts::default(),
+            blackouts: Blackouts::default(),
+            weather: Weather::default(),
+            et_deficits: BTreeMap::new(),
         }
     }
 
@@ -139,12 +520,16 @@ impl SystemState {
                 .join(", ")
         );
 
+        let firmware = self.nodes.get(node_id).and_then(|n| n.firmware.clone());
+        let seq = self.next_seq();
         self.nodes.insert(
             node_id.to_string(),
             NodeState {
                 last_seen: now,
                 online: true, // receiving data proves the node is alive
+                seq,
                 readings,
+                firmware,
             },
         );
 
@@ -155,15 +540,19 @@ impl SystemState {
     /// announcement on `status/node/<node_id>`.
     pub fn record_node_status(&mut self, node_id: &str, online: bool) {
         let now = OffsetDateTime::now_utc();
+        let seq = self.next_seq();
         let entry = self
             .nodes
             .entry(node_id.to_string())
             .or_insert_with(|| NodeState {
                 last_seen: now,
                 online: false,
+                seq,
                 readings: Vec::new(),
+                firmware: None,
             });
         entry.online = online;
+        entry.seq = seq;
         if online {
             entry.last_seen = now;
         }
@@ -172,22 +561,90 @@ impl SystemState {
         self.push_event(EventKind::System, format!("node {node_id} {status_str}"));
     }
 
+    /// Record the build a node announced when it came online, noting when
+    /// it changed.
+    pub fn record_node_firmware(&mut self, node_id: &str, firmware: Option<Firmware>) {
+        if !self.nodes.contains_key(node_id) {
+            return;
+        }
+        let seq = self.next_seq();
+        let Some(node) = self.nodes.get_mut(node_id) else {
+            return;
+        };
+        node.seq = seq;
+        let previous = std::mem::replace(&mut node.firmware, firmware.clone());
+        if let (Some(old), Some(new)) = (previous, firmware) {
+            if old != new {
+                self.push_event(
+                    EventKind::System,
+                    format!(
+                        "node {node_id} now runs {} ({}), was {} ({})",
+                        new.version, new.git, old.version, old.git
+                    ),
+                );
+            }
+        }
+    }
+
     /// Record a valve state change.
     pub fn record_valve(&mut self, zone_id: &str, on: bool) {
-        if let Some(zone) = self.zones.get_mut(zone_id) {
-            zone.on = on;
-            zone.last_changed = Some(OffsetDateTime::now_utc());
+        if self.zones.contains_key(zone_id) {
+            let seq = self.next_seq();
+            if let Some(zone) = self.zones.get_mut(zone_id) {
+                zone.on = on;
+                zone.last_changed = Some(OffsetDateTime::now_utc());
+                zone.seq = seq;
+            }
         }
 
         let state_str = if on { "ON" } else { "OFF" };
         self.push_event(EventKind::Valve, format!("{zone_id} set {state_str}"));
     }
 
+    /// Why no valve may be opened at all right now: the emergency stop, the
+    /// frost lockout or low line pressure.
+    pub fn valve_lockout(&self) -> Option<String> {
+        if self.estop.is_latched() {
+            Some("emergency stop latched".to_string())
+        } else if self.frost.is_locked() {
+            Some(self.frost.reason())
+        } else if self.pressure.is_low() {
+            Some(self.pressure.reason())
+        } else {
+            None
+        }
+    }
+
+    /// Why an `ON` for `zone_id` would be refused given the valves already
+    /// open: `max_concurrent_valves` reached, or an interlocked zone open.
+    /// The same checks the valve command handler makes, for callers that
+    /// must answer before the command is published.
+    pub fn open_valve_conflict(&self, zone_id: &str) -> Option<String> {
+        if self.zones.get(zone_id).is_some_and(|z| z.on) {
+            return None;
+        }
+        let active = self.zones.values().filter(|z| z.on).count();
+        let limit = self.limits.max_concurrent_valves;
+        if active >= limit {
+            return Some(format!("{active}/{limit} valves already open"));
+        }
+        Interlocks::new(&self.limits.interlocks)
+            .conflict(zone_id, |z| self.zones.get(z).is_some_and(|s| s.on))
+            .map(|c| c.to_string())
+    }
+
     /// Record an error event.
     pub fn record_error(&mut self, detail: String) {
         self.push_event(EventKind::Error, detail);
     }
 
+    /// Count a rejected MQTT payload from `source` (a node or zone id) and
+    /// record it as an error event.
+    pub fn record_reject(&mut self, source: &str, reject: &Reject) {
+        self.metrics.count_mqtt_reject(reject.kind, reject.reason);
+        self.record_error(format!("payload from {source} rejected: {reject}"));
+    }
+
     /// Record a generic system event.
     pub fn record_system(&mut self, detail: String) {
         self.push_event(EventKind::System, detail);
@@ -198,15 +655,36 @@ impl SystemState {
         self.push_event(EventKind::Scheduler, detail);
     }
 
+    /// Record a maintenance reminder (e.g. a valve due for service).
+    pub fn record_maintenance(&mut self, detail: String) {
+        self.push_event(EventKind::Maintenance, detail);
+    }
+
+    /// Record a low-moisture alert or recovery.
+    pub fn record_alert(&mut self, detail: String) {
+        self.push_event(EventKind::Alert, detail);
+    }
+
     /// Force all zone states to OFF (used during emergency shutdowns / MQTT errors).
+    /// Open watering sessions finish as `forced_off`.
     pub fn set_all_zones_off(&mut self) {
         let now = OffsetDateTime::now_utc();
-        for zone in self.zones.values_mut() {
-            if zone.on {
+        let mut open: Vec<String> = self
+            .zones
+            .iter()
+            .filter(|(_, z)| z.on)
+            .map(|(id, _)| id.clone())
+            .collect();
+        open.sort();
+        for zone_id in open {
+            let seq = self.next_seq();
+            if let Some(zone) = self.zones.get_mut(&zone_id) {
                 zone.on = false;
                 zone.last_changed = Some(now);
+                zone.seq = seq;
             }
         }
+        self.sessions.finish_all(now);
     }
 
     /// Update system resource metrics (CPU, memory).
@@ -216,9 +694,273 @@ impl SystemState {
         self.memory_total_bytes = mem_total;
     }
 
+    /// Record that a critical background task was restarted by the
+    /// supervisor.
+    pub fn record_task_restart(&mut self, task: &str, attempt: u32, backoff: Duration) {
+        self.tasks_restarted += 1;
+        self.record_error(format!(
+            "{task} task failed — all valves off, restarting in {}s (attempt {attempt})",
+            backoff.as_secs()
+        ));
+    }
+
+    // ── Degraded DB mode ─────────────────────────────────────────
+
+    pub fn is_db_degraded(&self) -> bool {
+        self.db_degraded_since.is_some()
+    }
+
+    /// Enter degraded mode after a failed write.  Only the transition is
+    /// recorded, so a burst of failures raises a single alert.
+    pub fn mark_db_degraded(&mut self, reason: &str) {
+        if self.db_degraded_since.is_none() {
+            self.db_degraded_since = Some(OffsetDateTime::now_utc());
+            self.record_error(format!(
+                "database unwritable — degraded mode: scheduler paused, \
+                 manual commands capped at 1/{DEGRADED_LIMIT_DIVISOR} of daily limits ({reason})"
+            ));
+        }
+    }
+
+    /// Leave degraded mode.  Call only after `take_pending_counters` has
+    /// been flushed to the database.
+    pub fn mark_db_recovered(&mut self) {
+        if let Some(since) = self.db_degraded_since.take() {
+            let mins = (OffsetDateTime::now_utc() - since).whole_minutes();
+            self.record_system(format!(
+                "database writable again — leaving degraded mode after {mins} min"
+            ));
+        }
+    }
+
+    pub fn add_pending_pulse(&mut self, day: &str, zone_id: &str) {
+        self.pending_counters
+            .entry((day.to_string(), zone_id.to_string()))
+            .or_default()
+            .pulses += 1;
+    }
+
+    pub fn add_pending_open_sec(&mut self, day: &str, zone_id: &str, secs: i64) {
+        self.pending_counters
+            .entry((day.to_string(), zone_id.to_string()))
+            .or_default()
+            .open_sec += secs;
+    }
+
+    pub fn pending_counters(&self, day: &str, zone_id: &str) -> PendingCounters {
+        self.pending_counters
+            .get(&(day.to_string(), zone_id.to_string()))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// What `zone_id` used with no watering event to show for it, for the
+    /// 24h window at `now_ts`: everything accrued in memory while degraded
+    /// (over all days) plus what was flushed in the last 24 hours.
+    pub fn unlogged_counters(&self, zone_id: &str, now_ts: i64) -> PendingCounters {
+        let pending = self
+            .pending_counters
+            .iter()
+            .filter(|((_, z), _)| z == zone_id)
+            .map(|(_, c)| c);
+        let flushed = self
+            .flushed_counters
+            .iter()
+            .filter(|(ts, z, _)| z == zone_id && *ts > now_ts - ROLLING_WINDOW_SEC)
+            .map(|(_, _, c)| c);
+        pending
+            .chain(flushed)
+            .fold(PendingCounters::default(), |acc, c| PendingCounters {
+                pulses: acc.pulses + c.pulses,
+                open_sec: acc.open_sec + c.open_sec,
+            })
+    }
+
+    /// Remember the daily counters just read from the DB for `zone_id`.
+    /// Earlier days are dropped.
+    pub fn cache_db_counters(&mut self, day: &str, zone_id: &str, pulses: i64, open_sec: i64) {
+        self.db_counters.retain(|(d, _), _| d == day);
+        self.db_counters.insert(
+            (day.to_string(), zone_id.to_string()),
+            PendingCounters { pulses, open_sec },
+        );
+    }
+
+    /// Today's usage while degraded: the last DB counters plus those
+    /// accrued in memory since.  `None` when the zone's counters were never
+    /// read today, so its usage is unknown.
+    pub fn degraded_counters(&self, day: &str, zone_id: &str) -> Option<PendingCounters> {
+        let db = self
+            .db_counters
+            .get(&(day.to_string(), zone_id.to_string()))?;
+        let pending = self.pending_counters(day, zone_id);
+        Some(PendingCounters {
+            pulses: db.pulses + pending.pulses,
+            open_sec: db.open_sec + pending.open_sec,
+        })
+    }
+
+    /// Fold counters just flushed to the DB into the cached DB counters,
+    /// and keep them for the 24h window (see `unlogged_counters`).
+    pub fn note_flushed_counters(
+        &mut self,
+        day: &str,
+        zone_id: &str,
+        c: PendingCounters,
+        now_ts: i64,
+    ) {
+        self.flushed_counters
+            .retain(|(ts, _, _)| *ts > now_ts - ROLLING_WINDOW_SEC);
+        self.flushed_counters.push((now_ts, zone_id.to_string(), c));
+        if let Some(db) = self
+            .db_counters
+            .get_mut(&(day.to_string(), zone_id.to_string()))
+        {
+            db.pulses += c.pulses;
+            db.open_sec += c.open_sec;
+        }
+    }
+
+    /// Drain in-memory counters for flushing to the database.  On a failed
+    /// flush, put them back with `restore_pending_counters`.
+    pub fn take_pending_counters(&mut self) -> Vec<(String, String, PendingCounters)> {
+        self.pending_counters
+            .drain()
+            .map(|((day, zone_id), c)| (day, zone_id, c))
+            .collect()
+    }
+
+    pub fn restore_pending_counters(&mut self, counters: Vec<(String, String, PendingCounters)>) {
+        for (day, zone_id, c) in counters {
+            let e = self.pending_counters.entry((day, zone_id)).or_default();
+            e.pulses += c.pulses;
+            e.open_sec += c.open_sec;
+        }
+    }
+
+    /// Store an advisor recommendation for the scheduler to pick up (replaces
+    /// any earlier one not yet acted on).
+    pub fn record_advice(&mut self, zone_id: &str, advice: Advice) {
+        self.record_scheduler(format!(
+            "{zone_id}: advisor recommends {} pulse(s) ({})",
+            advice.pulses, advice.reason
+        ));
+        self.advice.insert(zone_id.to_string(), advice);
+    }
+
+    pub fn take_advice(&mut self, zone_id: &str) -> Option<Advice> {
+        self.advice.remove(zone_id)
+    }
+
+    /// Advice waiting for a zone, left in place.
+    pub fn peek_advice(&self, zone_id: &str) -> Option<&Advice> {
+        self.advice.get(zone_id)
+    }
+
+    /// Flag or clear a sensor fault.  Returns `true` if the state changed.
+    pub fn set_sensor_faulted(&mut self, sensor_id: &str, faulted: bool) -> bool {
+        if faulted {
+            self.faulted_sensors.insert(sensor_id.to_string())
+        } else {
+            self.faulted_sensors.remove(sensor_id)
+        }
+    }
+
+    pub fn is_sensor_faulted(&self, sensor_id: &str) -> bool {
+        self.faulted_sensors.contains(sensor_id)
+    }
+
+    /// Flag or clear a sensor quarantine.  Returns `true` if the state
+    /// changed.
+    pub fn set_sensor_quarantined(&mut self, sensor_id: &str, quarantined: bool) -> bool {
+        if quarantined {
+            self.quarantined_sensors.insert(sensor_id.to_string())
+        } else {
+            self.quarantined_sensors.remove(sensor_id)
+        }
+    }
+
+    pub fn is_sensor_quarantined(&self, sensor_id: &str) -> bool {
+        self.quarantined_sensors.contains(sensor_id)
+    }
+
+    /// Replace the safety review, raising an error event per finding still
+    /// awaiting acknowledgement.
+    pub fn set_safety_review(&mut self, findings: Vec<Finding>) {
+        for f in findings.iter().filter(|f| !f.acknowledged) {
+            self.record_error(format!(
+                "safety review: zone {} {}: {} — acknowledge before auto mode waters it",
+                f.zone_id, f.code, f.detail
+            ));
+        }
+        self.safety_review = findings;
+    }
+
+    pub fn safety_review(&self) -> &[Finding] {
+        &self.safety_review
+    }
+
+    /// Unacknowledged findings, for one zone or all.
+    pub fn pending_findings(&self, zone_id: Option<&str>) -> Vec<Finding> {
+        self.safety_review
+            .iter()
+            .filter(|f| !f.acknowledged && zone_id.is_none_or(|z| f.zone_id == z))
+            .cloned()
+            .collect()
+    }
+
+    /// Whether auto mode must leave `zone_id` alone pending review.
+    pub fn needs_safety_review(&self, zone_id: &str) -> bool {
+        self.safety_review
+            .iter()
+            .any(|f| !f.acknowledged && f.zone_id == zone_id)
+    }
+
+    /// Mark every finding for `zone_id` acknowledged.
+    pub fn acknowledge_findings(&mut self, zone_id: &str) {
+        for f in self
+            .safety_review
+            .iter_mut()
+            .filter(|f| f.zone_id == zone_id)
+        {
+            f.acknowledged = true;
+        }
+    }
+
+    /// Mark a successful database backup.
+    pub fn record_backup(&mut self) {
+        self.last_backup_at = Some(OffsetDateTime::now_utc());
+        self.record_system("database backup complete".to_string());
+    }
+
+    /// Build the health report.  `db_connected` comes from
+    /// `Db::health_check`, which needs an async round-trip to the pool.
+    pub fn to_health(&self, db_connected: bool) -> HealthResponse {
+        let now = OffsetDateTime::now_utc();
+        let scheduler = TaskHealth::new(true, self.scheduler_heartbeat, now);
+        let watchdog = TaskHealth::new(self.mode != "monitor", self.watchdog_heartbeat, now);
+        let healthy = self.mqtt_connected
+            && db_connected
+            && self.db_degraded_since.is_none()
+            && scheduler.ok()
+            && watchdog.ok();
+        HealthResponse {
+            status: if healthy { "healthy" } else { "degraded" },
+            uptime_secs: self.started_at.elapsed().as_secs(),
+            mqtt_connected: self.mqtt_connected,
+            db_connected,
+            scheduler,
+            watchdog,
+            last_backup_at: self.last_backup_at,
+            tasks_restarted: self.tasks_restarted,
+            db_degraded_since: self.db_degraded_since,
+        }
+    }
+
     /// Build the JSON-serialisable status snapshot.
     pub fn to_status(&self) -> StatusResponse {
         StatusResponse {
+            hub_version: Firmware::hub(),
             uptime_secs: self.started_at.elapsed().as_secs(),
             mqtt_connected: self.mqtt_connected,
             mode: self.mode.clone(),
@@ -228,21 +970,178 @@ impl SystemState {
             cpu_usage_percent: self.cpu_usage_percent,
             memory_used_bytes: self.memory_used_bytes,
             memory_total_bytes: self.memory_total_bytes,
+            db_degraded_since: self.db_degraded_since,
+            budget: self.budget.clone(),
+            faulted_sensors: self.faulted_sensors.iter().cloned().collect(),
+            quarantined_sensors: self.quarantined_sensors.iter().cloned().collect(),
+            pending_safety_review: self.pending_findings(None),
+            frost: self.frost.clone(),
+            pressure: self.pressure.clone(),
+            emergency_stop: self.estop.clone(),
+            blackout: self
+                .blackouts
+                .active(OffsetDateTime::now_utc().date())
+                .cloned(),
+            mqtt_rejects: self.metrics.mqtt_rejects(),
+            moisture_alerts: self.moisture_alerts.low_zones(),
         }
     }
 
+    /// Build the `status/hub/heartbeat` message.
+    pub fn to_heartbeat(&self) -> HubHeartbeat {
+        let mut open_valves: Vec<String> = self
+            .zones
+            .iter()
+            .filter(|(_, z)| z.on)
+            .map(|(id, _)| id.clone())
+            .collect();
+        open_valves.sort();
+        let zones = self
+            .zones
+            .keys()
+            .map(|zone_id| {
+                let latest = self.moisture.latest(zone_id);
+                let heartbeat = ZoneHeartbeat {
+                    moisture: latest.map(|(_, m)| m),
+                    moisture_ts: latest.map(|(ts, _)| ts),
+                };
+                (zone_id.clone(), heartbeat)
+            })
+            .collect();
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|(node_id, n)| {
+                let heartbeat = NodeHeartbeat {
+                    online: n.online,
+                    last_seen: n.last_seen.unix_timestamp(),
+                };
+                (node_id.clone(), heartbeat)
+            })
+            .collect();
+        HubHeartbeat {
+            ts: OffsetDateTime::now_utc().unix_timestamp(),
+            uptime_secs: self.started_at.elapsed().as_secs(),
+            mode: self.mode.clone(),
+            open_valves,
+            zones,
+            nodes,
+            events: self
+                .events
+                .iter()
+                .rev()
+                .take(HEARTBEAT_EVENTS)
+                .cloned()
+                .collect(),
+            emergency_stop_latched: self.estop.is_latched(),
+            frost_locked: self.frost.is_locked(),
+            db_degraded: self.db_degraded_since.is_some(),
+        }
+    }
+
+    /// Put events saved by a previous run ahead of this run's, keeping the
+    /// newest `MAX_EVENTS`.
+    pub fn restore_events(&mut self, saved: Vec<SystemEvent>) {
+        let saved = saved.into_iter().map(|e| SystemEvent { seq: 0, ..e });
+        let mut events: VecDeque<SystemEvent> = saved.chain(self.events.drain(..)).collect();
+        while events.len() > MAX_EVENTS {
+            events.pop_front();
+        }
+        self.events = events;
+        self.events_seq += 1;
+    }
+
     fn push_event(&mut self, kind: EventKind, detail: String) {
         if self.events.len() >= MAX_EVENTS {
-            self.events.pop_front();
+            if let Some(dropped) = self.events.pop_front() {
+                self.dropped_event_seq = self.dropped_event_seq.max(dropped.seq);
+            }
         }
+        let seq = self.next_seq();
         self.events.push_back(SystemEvent {
+            seq,
             ts: OffsetDateTime::now_utc(),
             kind,
             detail,
         });
+        self.events_seq += 1;
+    }
+
+    /// Take the next change sequence number, waking long-polls.
+    fn next_seq(&mut self) -> u64 {
+        self.change_seq.send_modify(|seq| *seq += 1);
+        *self.change_seq.borrow()
+    }
+
+    /// Sequence number of the newest change.
+    pub fn change_seq(&self) -> u64 {
+        *self.change_seq.borrow()
+    }
+
+    /// Watch the change sequence number, for a long-poll waiting on it.
+    pub fn watch_changes(&self) -> watch::Receiver<u64> {
+        self.change_seq.subscribe()
+    }
+
+    /// Events and zone/node states changed after `since_seq`.  `reset` is
+    /// set when that can't be answered (the hub restarted, or events were
+    /// dropped from the ring since); reload the full status then.
+    pub fn changes_since(&self, since_seq: u64) -> StatusChanges {
+        let seq = self.change_seq();
+        StatusChanges {
+            seq,
+            uptime_secs: self.started_at.elapsed().as_secs(),
+            reset: since_seq > seq || since_seq < self.dropped_event_seq,
+            events: self
+                .events
+                .iter()
+                .filter(|e| e.seq > since_seq)
+                .cloned()
+                .collect(),
+            zones: self
+                .zones
+                .iter()
+                .filter(|(_, z)| z.seq > since_seq)
+                .map(|(id, z)| (id.clone(), z.clone()))
+                .collect(),
+            nodes: self
+                .nodes
+                .iter()
+                .filter(|(_, n)| n.seq > since_seq)
+                .map(|(id, n)| (id.clone(), n.clone()))
+                .collect(),
+        }
     }
 }
 
+// ---------------------------------------------------------------------------
+// Event ring sidecar file
+// ---------------------------------------------------------------------------
+
+/// Write `events` (oldest first) to `path` as JSON.  Written to a temporary
+/// file and renamed, so a crash mid-write keeps the previous file.
+pub fn save_events<'a>(
+    path: &str,
+    events: impl IntoIterator<Item = &'a SystemEvent>,
+) -> Result<()> {
+    let events: Vec<&SystemEvent> = events.into_iter().collect();
+    let json = serde_json::to_vec(&events).context("serialize events")?;
+    let tmp = format!("{path}.tmp");
+    std::fs::write(&tmp, json).with_context(|| format!("write {tmp}"))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("rename {tmp} -> {path}"))?;
+    Ok(())
+}
+
+/// Events saved by `save_events`; none if the file doesn't exist yet.
+pub fn load_events(path: &str) -> Result<Vec<SystemEvent>> {
+    let json = match std::fs::read(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("read {path}")),
+    };
+    serde_json::from_slice(&json).with_context(|| format!("parse {path}"))
+}
+
 // ===========================================================================
 // Tests
 // ===========================================================================
@@ -265,10 +1164,12 @@ mod tests {
             SensorReading {
                 sensor_id: "s1".to_string(),
                 raw: 20000,
+                mv: None,
             },
             SensorReading {
                 sensor_id: "s2".to_string(),
                 raw: 21000,
+                mv: None,
             },
         ]
     }
@@ -298,6 +1199,51 @@ mod tests {
         assert!(st.nodes.is_empty());
     }
 
+    // -- Event persistence --------------------------------------------------
+
+    #[test]
+    fn saved_events_reload_ahead_of_new_ones() {
+        let path = std::env::temp_dir().join(format!(
+            "irrigation_events_test_{}.json",
+            std::process::id()
+        ));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+        assert!(load_events(path).unwrap().is_empty());
+
+        let mut old = two_zone_state();
+        old.record_system("old run".to_string());
+        old.record_valve("zone1", true);
+        save_events(path, &old.events).unwrap();
+
+        let mut st = two_zone_state();
+        st.record_system("hub started".to_string());
+        let seq = st.events_seq;
+        st.restore_events(load_events(path).unwrap());
+        assert!(st.events_seq > seq);
+        let details: Vec<&str> = st.events.iter().map(|e| e.detail.as_str()).collect();
+        assert_eq!(details, ["old run", "zone1 set ON", "hub started"]);
+        assert!(matches!(st.events[1].kind, EventKind::Valve));
+
+        std::fs::write(path, b"not json").unwrap();
+        assert!(load_events(path).is_err());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn restored_events_capped() {
+        let mut old = two_zone_state();
+        for i in 0..MAX_EVENTS {
+            old.record_system(format!("old {i}"));
+        }
+        let mut st = two_zone_state();
+        st.record_system("new".to_string());
+        st.restore_events(old.events.into_iter().collect());
+        assert_eq!(st.events.len(), MAX_EVENTS);
+        assert_eq!(st.events[0].detail, "old 1");
+        assert_eq!(st.events.back().unwrap().detail, "new");
+    }
+
     #[test]
     fn new_starts_with_empty_events() {
         let st = two_zone_state();
@@ -338,6 +1284,7 @@ mod tests {
         let new = vec![SensorReading {
             sensor_id: "s3".to_string(),
             raw: 99,
+            mv: None,
         }];
         st.record_reading("node-a", new);
 
@@ -438,6 +1385,31 @@ mod tests {
         assert_eq!(st.events[0].detail, "node node-a online");
     }
 
+    #[test]
+    fn node_firmware_survives_readings_and_changes_are_noted() {
+        let mut st = two_zone_state();
+        let build = |version: &str| Firmware {
+            version: version.to_string(),
+            git: "1a2b3c4d".to_string(),
+        };
+        st.record_node_status("node-a", true);
+        st.record_node_firmware("node-a", Some(build("0.1.0")));
+        st.record_reading("node-a", sample_readings());
+        assert_eq!(st.nodes["node-a"].firmware, Some(build("0.1.0")));
+        let events = st.events.len();
+
+        st.record_node_firmware("node-a", Some(build("0.1.0")));
+        assert_eq!(st.events.len(), events);
+        st.record_node_firmware("node-a", Some(build("0.2.0")));
+        assert_eq!(
+            st.events.back().unwrap().detail,
+            "node node-a now runs 0.2.0 (1a2b3c4d), was 0.1.0 (1a2b3c4d)"
+        );
+        // Rolled back to a build that doesn't report one.
+        st.record_node_firmware("node-a", None);
+        assert_eq!(st.nodes["node-a"].firmware, None);
+    }
+
     #[test]
     fn record_node_status_offline_event_detail() {
         let mut st = two_zone_state();
@@ -584,6 +1556,61 @@ mod tests {
 
     // -- to_status ----------------------------------------------------------
 
+    #[test]
+    fn changes_since_tracks_sequence_numbers() {
+        let mut st = two_zone_state();
+        st.record_reading("node-a", sample_readings());
+        let seq = st.change_seq();
+        st.record_node_status("node-b", true);
+        st.record_valve("zone1", true);
+
+        let changes = st.changes_since(seq);
+        assert!(!changes.reset);
+        assert_eq!(changes.seq, st.change_seq());
+        assert_eq!(changes.events.len(), 2);
+        assert!(changes.events[0].seq < changes.events[1].seq);
+        assert_eq!(changes.nodes.keys().collect::<Vec<_>>(), ["node-b"]);
+        assert_eq!(changes.zones.keys().collect::<Vec<_>>(), ["zone1"]);
+
+        st.set_all_zones_off();
+        let changes = st.changes_since(changes.seq);
+        assert!(changes.events.is_empty());
+        assert!(!changes.zones["zone1"].on);
+
+        // Events dropped from the ring can't be listed any more.
+        let seq = st.change_seq();
+        for i in 0..=MAX_EVENTS {
+            st.record_system(format!("event {i}"));
+        }
+        assert!(st.changes_since(seq).reset);
+        assert!(!st.changes_since(st.change_seq()).reset);
+        assert!(st.changes_since(st.change_seq() + 1).reset);
+    }
+
+    #[test]
+    fn heartbeat_lists_open_valves_and_moisture() {
+        let mut st = two_zone_state();
+        st.zones.get_mut("zone2").unwrap().on = true;
+        for i in 0..=HEARTBEAT_EVENTS {
+            st.record_system(format!("event {i}"));
+        }
+
+        let hb = st.to_heartbeat();
+        assert_eq!(hb.events.len(), HEARTBEAT_EVENTS);
+        assert_eq!(hb.events[0].detail, format!("event {HEARTBEAT_EVENTS}"));
+        assert_eq!(hb.mode, "auto");
+        assert_eq!(hb.open_valves, ["zone2"]);
+        assert_eq!(hb.zones.len(), 2);
+        assert_eq!(
+            hb.zones["zone1"],
+            ZoneHeartbeat {
+                moisture: None,
+                moisture_ts: None
+            }
+        );
+        assert!(!hb.emergency_stop_latched && !hb.frost_locked && !hb.db_degraded);
+    }
+
     #[test]
     fn to_status_returns_events_in_reverse_order() {
         let mut st = two_zone_state();
@@ -649,4 +1676,185 @@ mod tests {
         assert!(json["zones"].is_object());
         assert!(json["events"].is_array());
     }
+
+    #[test]
+    fn to_health_healthy_when_all_components_alive() {
+        let mut st = two_zone_state();
+        st.mqtt_connected = true;
+        st.scheduler_heartbeat = Some(OffsetDateTime::now_utc());
+        st.watchdog_heartbeat = Some(OffsetDateTime::now_utc());
+
+        let health = st.to_health(true);
+        assert_eq!(health.status, "healthy");
+        assert!(health.scheduler.alive);
+        assert!(health.watchdog.alive);
+        assert!(health.last_backup_at.is_none());
+
+        assert_eq!(st.to_health(false).status, "degraded");
+    }
+
+    #[test]
+    fn to_health_degraded_on_stale_task_heartbeat() {
+        let mut st = two_zone_state();
+        st.mqtt_connected = true;
+        st.scheduler_heartbeat = Some(OffsetDateTime::now_utc());
+        st.watchdog_heartbeat = Some(
+            OffsetDateTime::now_utc() - time::Duration::seconds(TASK_HEARTBEAT_TIMEOUT_SEC + 1),
+        );
+
+        let health = st.to_health(true);
+        assert_eq!(health.status, "degraded");
+        assert!(!health.watchdog.alive);
+        assert!(health.watchdog.heartbeat_age_sec.unwrap() > TASK_HEARTBEAT_TIMEOUT_SEC);
+
+        // No heartbeat at all is also dead.
+        st.watchdog_heartbeat = None;
+        assert!(!st.to_health(true).watchdog.alive);
+    }
+
+    #[test]
+    fn to_health_ignores_watchdog_in_monitor_mode() {
+        let mut st = SystemState::new(&[("z1".to_string(), 17)], "monitor");
+        st.mqtt_connected = true;
+        st.scheduler_heartbeat = Some(OffsetDateTime::now_utc());
+
+        let health = st.to_health(true);
+        assert!(!health.watchdog.enabled);
+        assert_eq!(health.status, "healthy");
+    }
+
+    #[test]
+    fn record_backup_sets_timestamp() {
+        let mut st = two_zone_state();
+        st.record_backup();
+        assert!(st.to_health(true).last_backup_at.is_some());
+        assert_eq!(st.events.back().unwrap().detail, "database backup complete");
+    }
+
+    #[test]
+    fn degraded_mode_transitions_alert_once() {
+        let mut st = two_zone_state();
+        st.mark_db_degraded("disk full");
+        st.mark_db_degraded("disk full");
+        assert!(st.is_db_degraded());
+        let errors = st
+            .events
+            .iter()
+            .filter(|e| matches!(e.kind, EventKind::Error))
+            .count();
+        assert_eq!(errors, 1);
+
+        st.mqtt_connected = true;
+        st.scheduler_heartbeat = Some(OffsetDateTime::now_utc());
+        st.watchdog_heartbeat = Some(OffsetDateTime::now_utc());
+        let health = st.to_health(true);
+        assert_eq!(health.status, "degraded");
+        assert!(health.db_degraded_since.is_some());
+
+        st.mark_db_recovered();
+        assert!(!st.is_db_degraded());
+        assert_eq!(st.to_health(true).status, "healthy");
+    }
+
+    #[test]
+    fn pending_counters_accumulate_and_drain() {
+        let mut st = two_zone_state();
+        st.add_pending_pulse("2026-01-01", "zone1");
+        st.add_pending_pulse("2026-01-01", "zone1");
+        st.add_pending_open_sec("2026-01-01", "zone1", 30);
+        assert_eq!(
+            st.pending_counters("2026-01-01", "zone1"),
+            PendingCounters {
+                pulses: 2,
+                open_sec: 30
+            }
+        );
+        assert_eq!(
+            st.pending_counters("2026-01-01", "zone2"),
+            PendingCounters::default()
+        );
+
+        let drained = st.take_pending_counters();
+        assert_eq!(drained.len(), 1);
+        assert_eq!(
+            st.pending_counters("2026-01-01", "zone1"),
+            PendingCounters::default()
+        );
+
+        // A failed flush puts them back, merging with anything new.
+        st.add_pending_pulse("2026-01-01", "zone1");
+        st.restore_pending_counters(drained);
+        assert_eq!(st.pending_counters("2026-01-01", "zone1").pulses, 3);
+    }
+
+    #[test]
+    fn degraded_counters_include_usage_before_degrading() {
+        let mut st = two_zone_state();
+        st.add_pending_pulse("2026-01-01", "zone1");
+        // Nothing read from the DB today: usage unknown.
+        assert_eq!(st.degraded_counters("2026-01-01", "zone1"), None);
+
+        st.cache_db_counters("2026-01-01", "zone1", 6, 120);
+        assert_eq!(
+            st.degraded_counters("2026-01-01", "zone1"),
+            Some(PendingCounters {
+                pulses: 7,
+                open_sec: 120
+            })
+        );
+
+        // Flushed counters move into the cache.
+        for (day, zone_id, c) in st.take_pending_counters() {
+            st.note_flushed_counters(&day, &zone_id, c, 1_000);
+        }
+        assert_eq!(
+            st.degraded_counters("2026-01-01", "zone1").unwrap().pulses,
+            7
+        );
+
+        st.add_pending_pulse("2026-01-02", "zone1");
+        st.add_pending_open_sec("2026-01-02", "zone1", 30);
+        st.add_pending_open_sec("2026-01-02", "zone2", 99);
+        assert_eq!(
+            st.unlogged_counters("zone1", 1_000),
+            PendingCounters {
+                pulses: 2,
+                open_sec: 30
+            }
+        );
+
+        // A new day starts unknown again.
+        st.cache_db_counters("2026-01-02", "zone2", 0, 0);
+        assert_eq!(st.degraded_counters("2026-01-01", "zone1"), None);
+    }
+
+    #[test]
+    fn flushed_counters_stay_in_the_24h_window() {
+        let mut st = two_zone_state();
+        st.add_pending_pulse("2026-01-01", "zone1");
+        st.add_pending_open_sec("2026-01-01", "zone1", 30);
+        let flushed_at = 100_000;
+        for (day, zone_id, c) in st.take_pending_counters() {
+            st.note_flushed_counters(&day, &zone_id, c, flushed_at);
+        }
+        // The pulse has no watering event, so the window keeps counting
+        // it after recovery...
+        let c = PendingCounters {
+            pulses: 1,
+            open_sec: 30,
+        };
+        assert_eq!(st.unlogged_counters("zone1", flushed_at + 3600), c);
+        assert_eq!(
+            st.unlogged_counters("zone2", flushed_at + 3600),
+            PendingCounters::default()
+        );
+        // ...until a day after the flush.
+        let day_later = flushed_at + ROLLING_WINDOW_SEC;
+        assert_eq!(
+            st.unlogged_counters("zone1", day_later),
+            PendingCounters::default()
+        );
+        st.note_flushed_counters("2026-01-02", "zone2", c, day_later);
+        assert_eq!(st.flushed_counters.len(), 1);
+    }
 }
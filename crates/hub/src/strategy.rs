@@ -0,0 +1,805 @@
+//! Per-zone watering strategies: the part of the scheduler that decides
+//! *whether* an idle zone should start a pulse.
+//!
+//! The scheduler owns everything around the decision — guards (MQTT,
+//! concurrency, daily limits, stale data), valve commands, and the
+//! Watering/Soaking timers — and hands each strategy a [`ZoneContext`] with
+//! the inputs it asked for.  Strategies are plain synchronous objects so new
+//! ones can be unit-tested without a database or broker.
+//!
+//! A zone selects its strategy via [`StrategyConfig`], stored as JSON on the
+//! zone (`strategy` in config.toml / the zones API).  Zones without one use
+//! the classic moisture threshold.
+//!
+//! The `advisor` strategy delegates the decision to an external service over
+//! MQTT: the scheduler publishes the zone's context to
+//! `advice/<zone_id>/request`, the service answers on
+//! `advice/<zone_id>/response` with a number of pulses, and the answer is
+//! clamped and run through the same guards as any other pulse.
+//!
+//! The `et` strategy waters from a water balance (see [`crate::et`]): once
+//! the zone's deficit reaches `allowed_depletion_mm` it runs enough pulses
+//! to replace it.  With `moisture_feedback` (the default) the sensors
+//! correct the bucket: a zone at `target_moisture` isn't watered (and its
+//! deficit is reset), and one below `min_moisture` is watered even if the
+//! bucket says it needn't be.
+
+use serde::{Deserialize, Serialize};
+use time::{OffsetDateTime, Time};
+
+use crate::db::ZoneConfig;
+use crate::et;
+
+/// A scheduled slot whose time passed longer ago than this is skipped
+/// rather than run late (e.g. after a hub restart mid-afternoon).
+const SCHEDULE_CATCH_UP_SEC: i64 = 3600;
+
+/// Advice older than this when the zone next goes idle is discarded.
+const ADVICE_TTL_SEC: i64 = 600;
+
+fn default_advice_interval_min() -> u32 {
+    30
+}
+
+fn default_crop_coefficient() -> f64 {
+    1.0
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// ---------------------------------------------------------------------------
+// Configuration
+// ---------------------------------------------------------------------------
+
+/// Strategy selection for a zone.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StrategyConfig {
+    /// Pulse when averaged moisture drops below `min_moisture`; adaptive
+    /// soak applies.  The default.
+    #[default]
+    Threshold,
+    /// Ignore moisture and run `pulses` pulse/soak cycles at each of
+    /// `times` ("HH:MM", UTC).
+    Schedule { times: Vec<String>, pulses: u32 },
+    /// Ask an external advisor over MQTT every `request_interval_min`
+    /// minutes (while idle with fresh sensor data) and run the pulses it
+    /// recommends.
+    Advisor {
+        #[serde(default = "default_advice_interval_min")]
+        request_interval_min: u32,
+    },
+    /// Keep a water-balance deficit from daily reference ET
+    /// (`ET0 × crop_coefficient`, less rain and watering) and water once
+    /// it reaches `allowed_depletion_mm`.
+    Et {
+        #[serde(default = "default_crop_coefficient")]
+        crop_coefficient: f64,
+        /// Depth the zone's emitters apply per hour of watering.
+        application_rate_mm_hr: f64,
+        allowed_depletion_mm: f64,
+        #[serde(default = "default_true")]
+        moisture_feedback: bool,
+    },
+}
+
+impl StrategyConfig {
+    /// Human-readable problems with this configuration (empty if valid).
+    pub fn validate(&self) -> Vec<String> {
+        let mut errs = Vec::new();
+        if let Self::Schedule { times, pulses } = self {
+            if times.is_empty() {
+                errs.push("schedule strategy needs at least one time".into());
+            }
+            for t in times {
+                if parse_hh_mm(t).is_none() {
+                    errs.push(format!("schedule time '{t}' must be HH:MM (UTC)"));
+                }
+            }
+            if *pulses == 0 {
+                errs.push("schedule pulses must be > 0".into());
+            }
+        }
+        if let Self::Advisor {
+            request_interval_min,
+        } = self
+        {
+            if *request_interval_min == 0 {
+                errs.push("advisor request_interval_min must be > 0".into());
+            }
+        }
+        if let Self::Et {
+            crop_coefficient,
+            application_rate_mm_hr,
+            allowed_depletion_mm,
+            ..
+        } = self
+        {
+            if !(*crop_coefficient > 0.0 && *crop_coefficient <= 3.0) {
+                errs.push(format!(
+                    "et crop_coefficient must be within 0..=3, got {crop_coefficient}"
+                ));
+            }
+            if !(application_rate_mm_hr.is_finite() && *application_rate_mm_hr > 0.0) {
+                errs.push("et application_rate_mm_hr must be > 0".into());
+            }
+            if !(allowed_depletion_mm.is_finite() && *allowed_depletion_mm > 0.0) {
+                errs.push("et allowed_depletion_mm must be > 0".into());
+            }
+        }
+        errs
+    }
+
+    /// Instantiate the strategy.  Invalid schedule times are dropped (they
+    /// are rejected by `validate` on every write path).
+    pub fn build(&self) -> Box<dyn WateringStrategy> {
+        match self {
+            Self::Threshold => Box::new(ThresholdStrategy),
+            Self::Schedule { times, pulses } => {
+                let mut slots: Vec<Time> = times.iter().filter_map(|t| parse_hh_mm(t)).collect();
+                slots.sort();
+                Box::new(ScheduleStrategy {
+                    slots,
+                    pulses: *pulses,
+                    remaining: 0,
+                    last_slot: None,
+                })
+            }
+            Self::Advisor {
+                request_interval_min,
+            } => Box::new(AdvisorStrategy {
+                interval_sec: i64::from(*request_interval_min) * 60,
+                last_request: None,
+                remaining: 0,
+                total: 0,
+                reason: String::new(),
+            }),
+            Self::Et {
+                application_rate_mm_hr,
+                allowed_depletion_mm,
+                moisture_feedback,
+                ..
+            } => Box::new(EtStrategy {
+                application_rate_mm_hr: *application_rate_mm_hr,
+                allowed_depletion_mm: *allowed_depletion_mm,
+                moisture_feedback: *moisture_feedback,
+                remaining: 0,
+                total: 0,
+                reason: String::new(),
+            }),
+        }
+    }
+
+    /// The crop coefficient of an `et` zone.
+    pub fn crop_coefficient(&self) -> Option<f64> {
+        match self {
+            Self::Et {
+                crop_coefficient, ..
+            } => Some(*crop_coefficient),
+            _ => None,
+        }
+    }
+
+    /// The application rate of an `et` zone, for debiting its deficit.
+    pub fn application_rate_mm_hr(&self) -> Option<f64> {
+        match self {
+            Self::Et {
+                application_rate_mm_hr,
+                ..
+            } => Some(*application_rate_mm_hr),
+            _ => None,
+        }
+    }
+}
+
+fn parse_hh_mm(s: &str) -> Option<Time> {
+    let (h, m) = s.trim().split_once(':')?;
+    Time::from_hms(h.parse().ok()?, m.parse().ok()?, 0).ok()
+}
+
+// ---------------------------------------------------------------------------
+// Strategy trait
+// ---------------------------------------------------------------------------
+
+/// Inputs available to a strategy when a zone is idle.
+pub struct ZoneContext<'a> {
+    pub cfg: &'a ZoneConfig,
+    /// Averaged recent moisture (disturbances excluded).  Only populated for
+    /// strategies that return `true` from `uses_moisture`.
+    pub avg_moisture: Option<f32>,
+    pub now: OffsetDateTime,
+    /// Advisor answer received since the zone was last idle, if any.
+    pub advice: Option<Advice>,
+    /// Water-balance deficit in mm (`et` zones; unset until the first
+    /// closed day).
+    pub deficit_mm: Option<f64>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum IdleDecision {
+    Wait,
+    /// Start a pulse of the zone's `pulse_sec`.  `reason` ends up in the
+    /// scheduler event log.
+    Pulse {
+        reason: String,
+    },
+    /// Publish the zone context to `advice/<zone_id>/request` and stay idle.
+    RequestAdvice,
+}
+
+/// A recommendation from an external advisor (`advice/<zone_id>/response`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Advice {
+    /// Pulse/soak cycles to run now (0 = don't water).
+    pub pulses: u32,
+    pub reason: String,
+    pub received_at: OffsetDateTime,
+}
+
+pub trait WateringStrategy: Send {
+    /// Short identifier used in logs and events.
+    fn name(&self) -> &'static str;
+
+    /// Whether decisions depend on moisture.  If so the scheduler skips
+    /// zones with stale sensor data, fills `avg_moisture`, and applies the
+    /// adaptive soak policy.
+    fn uses_moisture(&self) -> bool;
+
+    fn on_idle(&mut self, ctx: &ZoneContext) -> IdleDecision;
+
+    /// Called after a `Pulse` decision was acted on (or, in monitor mode,
+    /// would have been).
+    fn on_pulse(&mut self) {}
+}
+
+// ---------------------------------------------------------------------------
+// Threshold (moisture) strategy
+// ---------------------------------------------------------------------------
+
+pub struct ThresholdStrategy;
+
+impl WateringStrategy for ThresholdStrategy {
+    fn name(&self) -> &'static str {
+        "threshold"
+    }
+
+    fn uses_moisture(&self) -> bool {
+        true
+    }
+
+    fn on_idle(&mut self, ctx: &ZoneContext) -> IdleDecision {
+        match ctx.avg_moisture {
+            Some(avg) if avg < ctx.cfg.min_moisture => IdleDecision::Pulse {
+                reason: format!("moisture {avg:.3} < min {:.3}", ctx.cfg.min_moisture),
+            },
+            _ => IdleDecision::Wait,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Schedule-only strategy
+// ---------------------------------------------------------------------------
+
+pub struct ScheduleStrategy {
+    /// Sorted times of day (UTC).
+    slots: Vec<Time>,
+    pulses: u32,
+    /// Pulses left in the current cycle.
+    remaining: u32,
+    /// Most recent slot that started a cycle.
+    last_slot: Option<OffsetDateTime>,
+}
+
+impl ScheduleStrategy {
+    /// The latest slot at or before `now` that is still within the
+    /// catch-up window and hasn't run yet.
+    fn due_slot(&self, now: OffsetDateTime) -> Option<OffsetDateTime> {
+        let today = now.date();
+        let yesterday = today.previous_day();
+        [yesterday, Some(today)]
+            .into_iter()
+            .flatten()
+            .flat_map(|d| self.slots.iter().map(move |t| d.with_time(*t).assume_utc()))
+            .filter(|slot| *slot <= now && (now - *slot).whole_seconds() < SCHEDULE_CATCH_UP_SEC)
+            .filter(|slot| self.last_slot.is_none_or(|last| *slot > last))
+            .max()
+    }
+}
+
+impl WateringStrategy for ScheduleStrategy {
+    fn name(&self) -> &'static str {
+        "schedule"
+    }
+
+    fn uses_moisture(&self) -> bool {
+        false
+    }
+
+    fn on_idle(&mut self, ctx: &ZoneContext) -> IdleDecision {
+        if self.remaining == 0 {
+            let Some(slot) = self.due_slot(ctx.now) else {
+                return IdleDecision::Wait;
+            };
+            self.last_slot = Some(slot);
+            self.remaining = self.pulses;
+        }
+        IdleDecision::Pulse {
+            reason: format!(
+                "scheduled cycle, pulse {}/{}",
+                self.pulses - self.remaining + 1,
+                self.pulses
+            ),
+        }
+    }
+
+    fn on_pulse(&mut self) {
+        self.remaining = self.remaining.saturating_sub(1);
+    }
+}
+
+// ---------------------------------------------------------------------------
+// External advisor strategy
+// ---------------------------------------------------------------------------
+
+pub struct AdvisorStrategy {
+    interval_sec: i64,
+    last_request: Option<OffsetDateTime>,
+    /// Pulses left from the current advice.
+    remaining: u32,
+    /// Pulses granted by the current advice (after clamping).
+    total: u32,
+    reason: String,
+}
+
+impl WateringStrategy for AdvisorStrategy {
+    fn name(&self) -> &'static str {
+        "advisor"
+    }
+
+    fn uses_moisture(&self) -> bool {
+        true
+    }
+
+    fn on_idle(&mut self, ctx: &ZoneContext) -> IdleDecision {
+        if let Some(advice) = &ctx.advice {
+            if (ctx.now - advice.received_at).whole_seconds() <= ADVICE_TTL_SEC {
+                // Never more than the zone's daily pulse budget; the daily
+                // counters still cut the cycle short if it's partly used.
+                let cap = u32::try_from(ctx.cfg.max_pulses_per_day.max(0)).unwrap_or(u32::MAX);
+                self.total = advice.pulses.min(cap);
+                self.remaining = self.total;
+                self.reason = advice.reason.clone();
+            }
+        }
+
+        if self.remaining > 0 {
+            return IdleDecision::Pulse {
+                reason: format!(
+                    "{} (pulse {}/{})",
+                    self.reason,
+                    self.total - self.remaining + 1,
+                    self.total
+                ),
+            };
+        }
+
+        if self
+            .last_request
+            .is_none_or(|t| (ctx.now - t).whole_seconds() >= self.interval_sec)
+        {
+            self.last_request = Some(ctx.now);
+            return IdleDecision::RequestAdvice;
+        }
+        IdleDecision::Wait
+    }
+
+    fn on_pulse(&mut self) {
+        self.remaining = self.remaining.saturating_sub(1);
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Evapotranspiration strategy
+// ---------------------------------------------------------------------------
+
+pub struct EtStrategy {
+    application_rate_mm_hr: f64,
+    allowed_depletion_mm: f64,
+    moisture_feedback: bool,
+    /// Pulses left to replace the deficit.
+    remaining: u32,
+    total: u32,
+    reason: String,
+}
+
+impl WateringStrategy for EtStrategy {
+    fn name(&self) -> &'static str {
+        "et"
+    }
+
+    fn uses_moisture(&self) -> bool {
+        self.moisture_feedback
+    }
+
+    fn on_idle(&mut self, ctx: &ZoneContext) -> IdleDecision {
+        if self.remaining > 0 {
+            return IdleDecision::Pulse {
+                reason: format!(
+                    "{} (pulse {}/{})",
+                    self.reason,
+                    self.total - self.remaining + 1,
+                    self.total
+                ),
+            };
+        }
+
+        let deficit = ctx.deficit_mm.unwrap_or(0.0);
+        let avg = ctx.avg_moisture.filter(|_| self.moisture_feedback);
+        if avg.is_some_and(|avg| avg >= ctx.cfg.target_moisture) {
+            return IdleDecision::Wait;
+        }
+        self.reason = if deficit >= self.allowed_depletion_mm {
+            format!(
+                "deficit {deficit:.1} mm >= {:.1} mm",
+                self.allowed_depletion_mm
+            )
+        } else if let Some(avg) = avg.filter(|avg| *avg < ctx.cfg.min_moisture) {
+            format!(
+                "moisture {avg:.3} < min {:.3}, deficit {deficit:.1} mm",
+                ctx.cfg.min_moisture
+            )
+        } else {
+            return IdleDecision::Wait;
+        };
+
+        let per_pulse = et::applied_mm(self.application_rate_mm_hr, ctx.cfg.pulse_sec);
+        let needed = if per_pulse > 0.0 {
+            (deficit / per_pulse).ceil() as u32
+        } else {
+            1
+        };
+        let cap = u32::try_from(ctx.cfg.max_pulses_per_day.max(1)).unwrap_or(u32::MAX);
+        self.total = needed.clamp(1, cap);
+        self.remaining = self.total;
+        IdleDecision::Pulse {
+            reason: format!("{} (pulse 1/{})", self.reason, self.total),
+        }
+    }
+
+    fn on_pulse(&mut self) {
+        self.remaining = self.remaining.saturating_sub(1);
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    fn ctx(cfg: &ZoneConfig, avg_moisture: Option<f32>, now: OffsetDateTime) -> ZoneContext<'_> {
+        ZoneContext {
+            cfg,
+            avg_moisture,
+            now,
+            advice: None,
+            deficit_mm: None,
+        }
+    }
+
+    #[test]
+    fn threshold_pulses_below_min() {
+        let cfg = ZoneConfig::test("z1");
+        let now = OffsetDateTime::now_utc();
+        let mut s = StrategyConfig::Threshold.build();
+        assert!(s.uses_moisture());
+        assert!(matches!(
+            s.on_idle(&ctx(&cfg, Some(0.2), now)),
+            IdleDecision::Pulse { .. }
+        ));
+        assert_eq!(s.on_idle(&ctx(&cfg, Some(0.3), now)), IdleDecision::Wait);
+        assert_eq!(s.on_idle(&ctx(&cfg, None, now)), IdleDecision::Wait);
+    }
+
+    #[test]
+    fn schedule_runs_cycle_once_per_slot() {
+        let cfg = ZoneConfig::test("z1");
+        let mut s = StrategyConfig::Schedule {
+            times: vec!["06:00".into(), "18:30".into()],
+            pulses: 2,
+        }
+        .build();
+        assert!(!s.uses_moisture());
+
+        // Before the first slot: nothing.
+        let early = datetime!(2026-05-01 05:59 UTC);
+        assert_eq!(s.on_idle(&ctx(&cfg, None, early)), IdleDecision::Wait);
+
+        // Slot due: two pulses, then done until the next slot.
+        let t = datetime!(2026-05-01 06:01 UTC);
+        assert_eq!(
+            s.on_idle(&ctx(&cfg, None, t)),
+            IdleDecision::Pulse {
+                reason: "scheduled cycle, pulse 1/2".into()
+            }
+        );
+        s.on_pulse();
+        assert_eq!(
+            s.on_idle(&ctx(&cfg, None, t)),
+            IdleDecision::Pulse {
+                reason: "scheduled cycle, pulse 2/2".into()
+            }
+        );
+        s.on_pulse();
+        assert_eq!(s.on_idle(&ctx(&cfg, None, t)), IdleDecision::Wait);
+
+        let evening = datetime!(2026-05-01 18:45 UTC);
+        assert!(matches!(
+            s.on_idle(&ctx(&cfg, None, evening)),
+            IdleDecision::Pulse { .. }
+        ));
+    }
+
+    #[test]
+    fn schedule_skips_long_missed_slots() {
+        let cfg = ZoneConfig::test("z1");
+        let mut s = StrategyConfig::Schedule {
+            times: vec!["06:00".into()],
+            pulses: 1,
+        }
+        .build();
+        let afternoon = datetime!(2026-05-01 14:00 UTC);
+        assert_eq!(s.on_idle(&ctx(&cfg, None, afternoon)), IdleDecision::Wait);
+    }
+
+    #[test]
+    fn schedule_slot_just_before_midnight_is_caught_after() {
+        let cfg = ZoneConfig::test("z1");
+        let mut s = StrategyConfig::Schedule {
+            times: vec!["23:50".into()],
+            pulses: 1,
+        }
+        .build();
+        let after = datetime!(2026-05-02 00:05 UTC);
+        assert!(matches!(
+            s.on_idle(&ctx(&cfg, None, after)),
+            IdleDecision::Pulse { .. }
+        ));
+    }
+
+    #[test]
+    fn validate_rejects_bad_schedules() {
+        assert!(StrategyConfig::Threshold.validate().is_empty());
+        let errs = StrategyConfig::Schedule {
+            times: vec!["25:00".into(), "6am".into()],
+            pulses: 0,
+        }
+        .validate();
+        assert_eq!(errs.len(), 3);
+        assert_eq!(
+            StrategyConfig::Schedule {
+                times: vec![],
+                pulses: 1
+            }
+            .validate()
+            .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn config_json_round_trip() {
+        let json = r#"{"kind":"schedule","times":["06:00"],"pulses":3}"#;
+        let cfg: StrategyConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            cfg,
+            StrategyConfig::Schedule {
+                times: vec!["06:00".into()],
+                pulses: 3
+            }
+        );
+        assert_eq!(serde_json::to_string(&cfg).unwrap(), json);
+        let threshold: StrategyConfig = serde_json::from_str(r#"{"kind":"threshold"}"#).unwrap();
+        assert_eq!(threshold, StrategyConfig::Threshold);
+    }
+
+    #[test]
+    fn advisor_requests_then_runs_clamped_advice() {
+        let cfg = ZoneConfig::test("z1");
+        let mut s = StrategyConfig::Advisor {
+            request_interval_min: 30,
+        }
+        .build();
+        let t0 = datetime!(2026-05-01 06:00 UTC);
+
+        assert_eq!(
+            s.on_idle(&ctx(&cfg, Some(0.4), t0)),
+            IdleDecision::RequestAdvice
+        );
+        // Interval not yet elapsed.
+        let t1 = datetime!(2026-05-01 06:10 UTC);
+        assert_eq!(s.on_idle(&ctx(&cfg, Some(0.4), t1)), IdleDecision::Wait);
+
+        // Advisor asks for more pulses than the daily budget (6).
+        let mut c = ctx(&cfg, Some(0.4), t1);
+        c.advice = Some(Advice {
+            pulses: 50,
+            reason: "heat wave".into(),
+            received_at: t1,
+        });
+        assert_eq!(
+            s.on_idle(&c),
+            IdleDecision::Pulse {
+                reason: "heat wave (pulse 1/6)".into()
+            }
+        );
+        for _ in 0..6 {
+            s.on_pulse();
+        }
+        assert_eq!(s.on_idle(&ctx(&cfg, Some(0.4), t1)), IdleDecision::Wait);
+
+        let t2 = datetime!(2026-05-01 06:31 UTC);
+        assert_eq!(
+            s.on_idle(&ctx(&cfg, Some(0.4), t2)),
+            IdleDecision::RequestAdvice
+        );
+    }
+
+    #[test]
+    fn advisor_ignores_stale_and_zero_advice() {
+        let cfg = ZoneConfig::test("z1");
+        let mut s = StrategyConfig::Advisor {
+            request_interval_min: 30,
+        }
+        .build();
+        let now = datetime!(2026-05-01 12:00 UTC);
+        let _ = s.on_idle(&ctx(&cfg, None, now));
+
+        let mut c = ctx(&cfg, None, now);
+        c.advice = Some(Advice {
+            pulses: 2,
+            reason: "old".into(),
+            received_at: datetime!(2026-05-01 11:00 UTC),
+        });
+        assert_eq!(s.on_idle(&c), IdleDecision::Wait);
+
+        c.advice = Some(Advice {
+            pulses: 0,
+            reason: "wet enough".into(),
+            received_at: now,
+        });
+        assert_eq!(s.on_idle(&c), IdleDecision::Wait);
+    }
+
+    #[test]
+    fn advisor_config_defaults_interval() {
+        let cfg: StrategyConfig = serde_json::from_str(r#"{"kind":"advisor"}"#).unwrap();
+        assert_eq!(
+            cfg,
+            StrategyConfig::Advisor {
+                request_interval_min: 30
+            }
+        );
+        assert_eq!(
+            StrategyConfig::Advisor {
+                request_interval_min: 0
+            }
+            .validate()
+            .len(),
+            1
+        );
+    }
+
+    fn et_strategy() -> StrategyConfig {
+        StrategyConfig::Et {
+            crop_coefficient: 0.8,
+            application_rate_mm_hr: 12.0,
+            allowed_depletion_mm: 5.0,
+            moisture_feedback: true,
+        }
+    }
+
+    #[test]
+    fn et_replaces_the_deficit_once_depleted() {
+        let mut cfg = ZoneConfig::test("z1");
+        // 2 mm per pulse.
+        cfg.pulse_sec = 600;
+        let now = datetime!(2026-07-01 06:00 UTC);
+        let mut s = et_strategy().build();
+        assert_eq!(s.name(), "et");
+        assert!(s.uses_moisture());
+
+        let mut c = ctx(&cfg, Some(0.4), now);
+        c.deficit_mm = Some(4.9);
+        assert_eq!(s.on_idle(&c), IdleDecision::Wait);
+
+        c.deficit_mm = Some(5.5);
+        assert_eq!(
+            s.on_idle(&c),
+            IdleDecision::Pulse {
+                reason: "deficit 5.5 mm >= 5.0 mm (pulse 1/3)".into()
+            }
+        );
+        s.on_pulse();
+        assert!(
+            matches!(s.on_idle(&c), IdleDecision::Pulse { reason } if reason.ends_with("(pulse 2/3)"))
+        );
+        s.on_pulse();
+        s.on_pulse();
+        c.deficit_mm = Some(0.0);
+        assert_eq!(s.on_idle(&c), IdleDecision::Wait);
+
+        // Never more than the daily pulse budget.
+        c.deficit_mm = Some(100.0);
+        assert!(
+            matches!(s.on_idle(&c), IdleDecision::Pulse { reason } if reason.ends_with("(pulse 1/6)"))
+        );
+    }
+
+    #[test]
+    fn et_moisture_feedback_corrects_the_bucket() {
+        let cfg = ZoneConfig::test("z1");
+        let now = datetime!(2026-07-01 06:00 UTC);
+        let mut s = et_strategy().build();
+
+        // Sensors say the zone is at target: no water whatever the deficit.
+        let mut c = ctx(&cfg, Some(0.5), now);
+        c.deficit_mm = Some(20.0);
+        assert_eq!(s.on_idle(&c), IdleDecision::Wait);
+
+        // Sensors say it's dry before the bucket does.
+        let mut c = ctx(&cfg, Some(0.2), now);
+        c.deficit_mm = Some(1.0);
+        assert!(matches!(
+            s.on_idle(&c),
+            IdleDecision::Pulse { reason } if reason.starts_with("moisture 0.200 < min 0.300")
+        ));
+
+        // Without feedback only the bucket counts.
+        let mut s = StrategyConfig::Et {
+            crop_coefficient: 1.0,
+            application_rate_mm_hr: 12.0,
+            allowed_depletion_mm: 5.0,
+            moisture_feedback: false,
+        }
+        .build();
+        assert!(!s.uses_moisture());
+        let mut c = ctx(&cfg, None, now);
+        c.deficit_mm = Some(1.0);
+        assert_eq!(s.on_idle(&c), IdleDecision::Wait);
+    }
+
+    #[test]
+    fn et_config_defaults_and_validation() {
+        let cfg: StrategyConfig = serde_json::from_str(
+            r#"{"kind":"et","application_rate_mm_hr":12,"allowed_depletion_mm":5}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            cfg,
+            StrategyConfig::Et {
+                crop_coefficient: 1.0,
+                application_rate_mm_hr: 12.0,
+                allowed_depletion_mm: 5.0,
+                moisture_feedback: true,
+            }
+        );
+        assert!(cfg.validate().is_empty());
+        assert_eq!(cfg.crop_coefficient(), Some(1.0));
+        assert_eq!(StrategyConfig::Threshold.application_rate_mm_hr(), None);
+        let errs = StrategyConfig::Et {
+            crop_coefficient: 0.0,
+            application_rate_mm_hr: -1.0,
+            allowed_depletion_mm: f64::NAN,
+            moisture_feedback: true,
+        }
+        .validate();
+        assert_eq!(errs.len(), 3, "{errs:?}");
+    }
+}
@@ -0,0 +1,200 @@
+//! Peak-sun deferral: in auto mode a zone doesn't start a pulse while its
+//! light reading is above `avoid_above_lux`, since water put down in full
+//! sun partly evaporates before it soaks in.  Fixed watering windows don't
+//! follow cloudy days; a lux threshold does.  A zone whose moisture is
+//! `critical_margin` or more below its `min_moisture` waters anyway.
+//!
+//! ```toml
+//! [sunlight]
+//! avoid_above_lux = 40000
+//! critical_margin = 0.1   # default
+//! ```
+//!
+//! Lux arrives as `lux` in node telemetry (a BH1750 on the node).  A zone
+//! goes by the brightest reading from the nodes carrying its sensors or,
+//! when none of those has a light sensor, the brightest from any node: one
+//! sensor usually serves a whole greenhouse.  Readings older than the
+//! zone's stale timeout are ignored, so a light sensor that goes quiet
+//! never holds watering back.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SunlightConfig {
+    /// Defer pulses while the light is above this (lux).  Unset = off.
+    pub avoid_above_lux: Option<f64>,
+    /// How far below `min_moisture` a zone waters regardless of the sun.
+    pub critical_margin: f32,
+}
+
+impl Default for SunlightConfig {
+    fn default() -> Self {
+        Self {
+            avoid_above_lux: None,
+            critical_margin: 0.1,
+        }
+    }
+}
+
+impl SunlightConfig {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        if let Some(lux) = self.avoid_above_lux {
+            if !(lux.is_finite() && lux > 0.0) {
+                errors.push(format!(
+                    "sunlight: avoid_above_lux must be a positive number, got {lux}"
+                ));
+            }
+        }
+        if !(0.0..=1.0).contains(&self.critical_margin) {
+            errors.push(format!(
+                "sunlight: critical_margin must be between 0 and 1, got {}",
+                self.critical_margin
+            ));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// A node's newest light reading.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LuxReading {
+    lux: f64,
+    /// Unix seconds.
+    ts: i64,
+}
+
+/// Newest light reading per node.
+#[derive(Debug, Clone, Default)]
+pub struct Sunlight {
+    cfg: SunlightConfig,
+    latest: BTreeMap<String, LuxReading>,
+}
+
+impl Sunlight {
+    pub fn new(cfg: &SunlightConfig) -> Self {
+        Self {
+            cfg: *cfg,
+            latest: BTreeMap::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.cfg.avoid_above_lux.is_some()
+    }
+
+    /// Take a reading from `node_id`.  Readings older than the node's
+    /// latest are ignored.
+    pub fn record(&mut self, node_id: &str, lux: f64, ts: i64) {
+        if self.latest.get(node_id).is_some_and(|l| ts < l.ts) {
+            return;
+        }
+        self.latest
+            .insert(node_id.to_string(), LuxReading { lux, ts });
+    }
+
+    /// The brightest reading at or after `since_ts` for a zone whose
+    /// sensors are on `nodes`, with the node it came from.
+    fn brightest(&self, nodes: &[String], since_ts: i64) -> Option<(&str, f64)> {
+        let brightest_of = |own: bool| {
+            self.latest
+                .iter()
+                .filter(|(node, r)| r.ts >= since_ts && (!own || nodes.contains(node)))
+                .max_by(|a, b| a.1.lux.total_cmp(&b.1.lux))
+                .map(|(node, r)| (node.as_str(), r.lux))
+        };
+        brightest_of(true).or_else(|| brightest_of(false))
+    }
+
+    /// Why a zone on `nodes` at `moisture` (min `min_moisture`) should hold
+    /// off, or `None` when it may water.
+    pub fn defer(
+        &self,
+        nodes: &[String],
+        moisture: Option<f32>,
+        min_moisture: f32,
+        since_ts: i64,
+    ) -> Option<String> {
+        let limit = self.cfg.avoid_above_lux?;
+        let (node, lux) = self.brightest(nodes, since_ts)?;
+        if lux <= limit || moisture.is_some_and(|m| m <= min_moisture - self.cfg.critical_margin) {
+            return None;
+        }
+        Some(format!("{lux:.0} lux on {node} (limit {limit:.0})"))
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sunlight() -> Sunlight {
+        Sunlight::new(&SunlightConfig {
+            avoid_above_lux: Some(40_000.0),
+            critical_margin: 0.1,
+        })
+    }
+
+    #[test]
+    fn defers_in_peak_sun_unless_critically_dry() {
+        let mut s = sunlight();
+        let nodes = vec!["bed".to_string()];
+        assert_eq!(s.defer(&nodes, Some(0.25), 0.3, 0), None);
+        s.record("bed", 52_000.0, 100);
+        assert_eq!(
+            s.defer(&nodes, Some(0.25), 0.3, 0).as_deref(),
+            Some("52000 lux on bed (limit 40000)")
+        );
+        // Fixed-schedule zones have no moisture and wait too.
+        assert!(s.defer(&nodes, None, 0.3, 0).is_some());
+        // 0.1 or more below min: water anyway.
+        assert_eq!(s.defer(&nodes, Some(0.19), 0.3, 0), None);
+        // Stale reading: ignored.
+        assert_eq!(s.defer(&nodes, Some(0.25), 0.3, 101), None);
+        // Older readings don't replace newer ones.
+        s.record("bed", 1_000.0, 50);
+        assert!(s.defer(&nodes, Some(0.25), 0.3, 0).is_some());
+        s.record("bed", 30_000.0, 200);
+        assert_eq!(s.defer(&nodes, Some(0.25), 0.3, 0), None);
+    }
+
+    #[test]
+    fn zone_nodes_first_then_any_node() {
+        let mut s = sunlight();
+        s.record("roof", 60_000.0, 10);
+        let nodes = vec!["bed".to_string()];
+        // The zone's node has no light sensor: the roof sensor applies.
+        assert!(s.defer(&nodes, Some(0.25), 0.3, 0).is_some());
+        // Once it has one, its own reading decides.
+        s.record("bed", 20_000.0, 10);
+        assert_eq!(s.defer(&nodes, Some(0.25), 0.3, 0), None);
+    }
+
+    #[test]
+    fn disabled_and_validation() {
+        let mut s = Sunlight::new(&SunlightConfig::default());
+        s.record("bed", 100_000.0, 1);
+        assert!(!s.is_enabled());
+        assert_eq!(s.defer(&[], None, 0.3, 0), None);
+
+        let errs = SunlightConfig {
+            avoid_above_lux: Some(-1.0),
+            critical_margin: 2.0,
+        }
+        .validate()
+        .unwrap_err();
+        assert_eq!(errs.len(), 2, "{errs:?}");
+        assert!(SunlightConfig::default().validate().is_ok());
+    }
+}
@@ -0,0 +1,134 @@
+//! Restart policy for critical background tasks (valve watchdog, scheduler).
+//!
+//! The supervisor only decides *whether* and *when* to restart; `main` owns
+//! the task handles, forces valves off, and respawns.  A task that keeps
+//! failing is given up on so a persistent fault still stops the hub instead
+//! of looping forever.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Restarts allowed per task within `FAILURE_WINDOW` before giving up.
+const MAX_RESTARTS: usize = 5;
+
+/// Failures older than this no longer count towards `MAX_RESTARTS` or the
+/// backoff — a task that ran fine for a while starts fresh.
+const FAILURE_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+/// First restart delay; doubles with each recent failure.
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Upper bound on the restart delay.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Debug, PartialEq)]
+pub enum Decision {
+    /// Respawn the task after `backoff`.  `attempt` counts recent failures
+    /// (1 = first failure in the window).
+    Restart { attempt: u32, backoff: Duration },
+    /// Too many recent failures — shut down.
+    GiveUp { failures: u32 },
+}
+
+#[derive(Default)]
+pub struct Supervisor {
+    failures: HashMap<&'static str, VecDeque<Instant>>,
+}
+
+impl Supervisor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that `task` exited at `now` and decide what to do about it.
+    pub fn on_failure(&mut self, task: &'static str, now: Instant) -> Decision {
+        let recent = self.failures.entry(task).or_default();
+        while recent
+            .front()
+            .is_some_and(|t| now.duration_since(*t) > FAILURE_WINDOW)
+        {
+            recent.pop_front();
+        }
+        recent.push_back(now);
+
+        let failures = recent.len();
+        if failures > MAX_RESTARTS {
+            return Decision::GiveUp {
+                failures: failures as u32,
+            };
+        }
+
+        let backoff = BASE_BACKOFF
+            .saturating_mul(1 << (failures - 1))
+            .min(MAX_BACKOFF);
+        Decision::Restart {
+            attempt: failures as u32,
+            backoff,
+        }
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_then_gives_up() {
+        let mut sup = Supervisor::new();
+        let t0 = Instant::now();
+
+        let backoffs: Vec<Duration> = (0..MAX_RESTARTS as u64)
+            .map(
+                |i| match sup.on_failure("scheduler", t0 + Duration::from_secs(i)) {
+                    Decision::Restart { backoff, .. } => backoff,
+                    d => panic!("unexpected {d:?}"),
+                },
+            )
+            .collect();
+        assert_eq!(backoffs, [1, 2, 4, 8, 16].map(Duration::from_secs).to_vec());
+
+        assert_eq!(
+            sup.on_failure("scheduler", t0 + Duration::from_secs(10)),
+            Decision::GiveUp {
+                failures: MAX_RESTARTS as u32 + 1
+            }
+        );
+    }
+
+    #[test]
+    fn tasks_are_tracked_independently() {
+        let mut sup = Supervisor::new();
+        let now = Instant::now();
+        for _ in 0..MAX_RESTARTS {
+            sup.on_failure("scheduler", now);
+        }
+        assert_eq!(
+            sup.on_failure("watchdog", now),
+            Decision::Restart {
+                attempt: 1,
+                backoff: BASE_BACKOFF
+            }
+        );
+    }
+
+    #[test]
+    fn old_failures_expire() {
+        let mut sup = Supervisor::new();
+        let t0 = Instant::now();
+        for _ in 0..MAX_RESTARTS {
+            sup.on_failure("watchdog", t0);
+        }
+        let later = t0 + FAILURE_WINDOW + Duration::from_secs(1);
+        assert_eq!(
+            sup.on_failure("watchdog", later),
+            Decision::Restart {
+                attempt: 1,
+                backoff: BASE_BACKOFF
+            }
+        );
+    }
+}
@@ -10,6 +10,8 @@ const BORDER_COLOR: Record<EventKind, string> = {
   error: "border-l-red-500",
   system: "border-l-gray-500",
   scheduler: "border-l-purple-500",
+  maintenance: "border-l-amber-500",
+  alert: "border-l-orange-500",
 };
 
 const BADGE_CLASS: Record<EventKind, string> = {
@@ -19,6 +21,10 @@ const BADGE_CLASS: Record<EventKind, string> = {
   system: "bg-gray-100 text-gray-800 dark:bg-gray-900 dark:text-gray-200",
   scheduler:
     "bg-purple-100 text-purple-800 dark:bg-purple-900 dark:text-purple-200",
+  maintenance:
+    "bg-amber-100 text-amber-800 dark:bg-amber-900 dark:text-amber-200",
+  alert:
+    "bg-orange-100 text-orange-800 dark:bg-orange-900 dark:text-orange-200",
 };
 
 const MAX_EVENTS = 50;
@@ -21,6 +21,8 @@ export interface NodeState {
 export interface SensorReading {
   sensor_id: string;
   raw: number;
+  /** Millivolts, when the node reports them (ADC_REPORT_MV) */
+  mv?: number;
 }
 
 export interface ZoneState {
@@ -30,7 +32,14 @@ export interface ZoneState {
   last_changed: string | null;
 }
 
-export type EventKind = "reading" | "valve" | "error" | "system" | "scheduler";
+export type EventKind =
+  | "reading"
+  | "valve"
+  | "error"
+  | "system"
+  | "scheduler"
+  | "maintenance"
+  | "alert";
 
 export interface SystemEvent {
   /** ISO-8601 timestamp */
@@ -52,6 +61,24 @@ export interface ZoneConfig {
   max_pulses_per_day: number;
   stale_timeout_min: number;
   valve_gpio_pin: number;
+  /** Unix epoch seconds, set while the zone is archived */
+  archived_at?: number | null;
+}
+
+export interface ZoneOdometer {
+  actuations: number;
+  open_sec: number;
+  actuations_since_service: number;
+  open_sec_since_service: number;
+  /** Unix epoch seconds */
+  serviced_ts: number | null;
+  /** Unix epoch seconds */
+  service_due_ts: number | null;
+}
+
+/** A zone as returned by the zones API: config plus valve odometer. */
+export interface ZoneView extends ZoneConfig {
+  odometer: ZoneOdometer;
 }
 
 // ── Sensor config ───────────────────────────────────────────────
@@ -62,6 +89,10 @@ export interface SensorConfig {
   zone_id: string;
   raw_dry: number;
   raw_wet: number;
+  /** Plausibility margin in raw counts (default 3000) */
+  failure_margin?: number | null;
+  /** Plausibility margin as a percentage of the calibration range */
+  failure_margin_pct?: number | null;
 }
 
 // ── Readings ────────────────────────────────────────────────────
@@ -1,66 +1,277 @@
-//! Valve control via GPIO. The `gpio` feature gates the real rppal driver;
-//! without it, a mock implementation logs state changes.
+//! Valve control via GPIO. The `gpio` (rppal) and `gpiod` (GPIO character
+//! device) features gate the real drivers (see `gpio`); without either, a
+//! mock implementation logs state changes (and, in hardware-in-the-loop
+//! mode, mirrors them to the node simulator).
+//!
+//! Solenoid valves are open while their relay is energised.  Motorized
+//! ball valves instead need their motor driven open or closed for the
+//! valve's travel time, then left unpowered; the board tracks where each
+//! one is assumed to be (see `Motor`).
 
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use tracing::{info, warn};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
 
-#[cfg(feature = "gpio")]
-use rppal::gpio::{Gpio, OutputPin};
+use crate::gpio::GpioConfig;
+#[cfg(any(feature = "gpio", feature = "gpiod"))]
+use crate::gpio::OutputLine;
 
 // ---------------------------------------------------------------------------
-// Real GPIO valve board (production — requires rppal + Raspberry Pi hardware)
+// Valve type (per zone)
 // ---------------------------------------------------------------------------
-#[cfg(feature = "gpio")]
+
+/// Longest accepted motorized valve travel time.
+pub const MAX_TRAVEL_SEC: u64 = 60;
+
+/// The kind of valve a zone drives (stored as JSON; NULL = solenoid).
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ValveConfig {
+    /// Open while the relay on `valve_gpio_pin` is energised.  The default.
+    #[default]
+    Solenoid,
+    /// Motorized ball valve: `valve_gpio_pin` drives the motor open and
+    /// `close_gpio_pin` drives it closed, each for `travel_sec`.
+    Motorized {
+        close_gpio_pin: i64,
+        travel_sec: u64,
+    },
+}
+
+impl ValveConfig {
+    /// Human-readable problems with this configuration (empty if valid).
+    pub fn validate(&self, valve_gpio_pin: i64) -> Vec<String> {
+        let mut errs = Vec::new();
+        if let Self::Motorized {
+            close_gpio_pin,
+            travel_sec,
+        } = self
+        {
+            if *close_gpio_pin < 0 {
+                errs.push("valve close_gpio_pin must be >= 0".into());
+            } else if *close_gpio_pin == valve_gpio_pin {
+                errs.push("valve close_gpio_pin must differ from valve_gpio_pin".into());
+            }
+            if !(1..=MAX_TRAVEL_SEC).contains(travel_sec) {
+                errs.push(format!(
+                    "valve travel_sec must be 1–{MAX_TRAVEL_SEC}, got {travel_sec}"
+                ));
+            }
+        }
+        errs
+    }
+}
+
+/// A motorized valve as the board sees it: `(zone_id, close pin, travel)`.
+pub(crate) type MotorSpec = (String, u8, Duration);
+
+/// How often the hub checks for motor drives that have run their course.
+pub(crate) const MOTOR_SERVICE_INTERVAL: Duration = Duration::from_millis(250);
+
+// ---------------------------------------------------------------------------
+// Motorized valve position (shared by both implementations)
+// ---------------------------------------------------------------------------
+
+/// Assumed position of a motorized valve.  There is no feedback, so the
+/// estimate comes from how long the motor has been driven each way.
+///
+/// Closing always drives for the full travel time — running into the
+/// closed end stop is harmless and makes sure the valve shuts even if the
+/// estimate has drifted.  Opening only drives the remaining distance.  The
+/// position starts out assumed open, so a valve is never driven open
+/// before the startup close has run.
+#[derive(Debug, Clone)]
+struct Motor {
+    travel: Duration,
+    /// Estimated opening when the current drive started (0 = closed, 1 = open).
+    position: f32,
+    drive: Option<Drive>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Drive {
+    open: bool,
+    started: Instant,
+    duration: Duration,
+}
+
+impl Motor {
+    fn new(travel: Duration) -> Self {
+        Self {
+            travel,
+            position: 1.0,
+            drive: None,
+        }
+    }
+
+    fn position(&self, now: Instant) -> f32 {
+        let Some(d) = self.drive else {
+            return self.position;
+        };
+        let driven = now.saturating_duration_since(d.started).min(d.duration);
+        let moved = driven.as_secs_f32() / self.travel.as_secs_f32();
+        if d.open {
+            (self.position + moved).min(1.0)
+        } else {
+            (self.position - moved).max(0.0)
+        }
+    }
+
+    /// Start driving open or closed; returns how long the drive lasts.
+    fn start(&mut self, open: bool, now: Instant) -> Duration {
+        self.position = self.position(now);
+        let duration = if open {
+            self.travel.mul_f32(1.0 - self.position)
+        } else {
+            self.travel
+        };
+        self.drive = Some(Drive {
+            open,
+            started: now,
+            duration,
+        });
+        duration
+    }
+
+    /// End a drive that has run its course.  `true` if the motor should be
+    /// de-energised now.
+    fn finish(&mut self, now: Instant) -> bool {
+        match self.drive {
+            Some(d) if now.saturating_duration_since(d.started) >= d.duration => {
+                self.position = if d.open { self.position(now) } else { 0.0 };
+                self.drive = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn remaining(&self, now: Instant) -> Duration {
+        self.drive.map_or(Duration::ZERO, |d| {
+            d.duration
+                .saturating_sub(now.saturating_duration_since(d.started))
+        })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Activation stagger (shared by both implementations)
+// ---------------------------------------------------------------------------
+
+/// Minimum gap between two relay switches, so several valves opening or
+/// closing back-to-back don't load the 12 V supply all at once (solenoid
+/// inrush, or motors driving in either direction).
+#[derive(Default)]
+struct Stagger {
+    interval: Duration,
+    last_switch: Option<Instant>,
+}
+
+impl Stagger {
+    fn remaining(&self) -> Duration {
+        match self.last_switch {
+            Some(t) => self.interval.saturating_sub(t.elapsed()),
+            None => Duration::ZERO,
+        }
+    }
+}
+
+/// `(zone_id, on)` for every valve write, published to the simulator in
+/// hardware-in-the-loop mode (`SIM_HIL`).
+pub(crate) type GpioIntents = mpsc::UnboundedReceiver<(String, bool)>;
+
+// ---------------------------------------------------------------------------
+// Real GPIO valve board (production — requires rppal or a GPIO chardev)
+// ---------------------------------------------------------------------------
+#[cfg(any(feature = "gpio", feature = "gpiod"))]
 pub(crate) struct ValveBoard {
-    pins: HashMap<String, OutputPin>, // zone_id -> GPIO pin
-    active_low: bool,                 // many relay boards are active-low
+    gpio: GpioConfig,
+    pins: HashMap<String, OutputLine>, // zone_id -> GPIO pin
+    active_low: bool,                  // many relay boards are active-low
+    stagger: Stagger,
+    /// Motorized zones: close-direction pin and assumed position.  Their
+    /// open-direction pin is in `pins`.
+    motors: HashMap<String, (OutputLine, Motor)>,
+}
+
+#[cfg(any(feature = "gpio", feature = "gpiod"))]
+fn claim_off(gpio: &GpioConfig, pin_num: u8, active_low: bool) -> Result<OutputLine> {
+    // Atomic pin init: set the correct OFF level *during* the
+    // output-mode switch so the relay never sees a brief glitch.
+    // (A plain output defaults to LOW, which activates active-low relays.)
+    OutputLine::claim(gpio, pin_num, active_low)
+}
+
+#[cfg(any(feature = "gpio", feature = "gpiod"))]
+fn write_relay(pin: &mut OutputLine, active_low: bool, on: bool) {
+    pin.write(on != active_low)
 }
 
-#[cfg(feature = "gpio")]
+#[cfg(any(feature = "gpio", feature = "gpiod"))]
 impl ValveBoard {
-    pub(crate) fn new(zone_to_gpio: &[(String, u8)], active_low: bool) -> Result<Self> {
-        let gpio = Gpio::new()?;
+    pub(crate) fn new(
+        gpio: &GpioConfig,
+        zone_to_gpio: &[(String, u8)],
+        active_low: bool,
+    ) -> Result<Self> {
         let mut pins = HashMap::new();
 
         for (zone_id, pin_num) in zone_to_gpio {
-            // Atomic pin init: set the correct OFF level *during* the
-            // output-mode switch so the relay never sees a brief glitch.
-            // (into_output() defaults to LOW, which activates active-low relays.)
-            let mut pin = if active_low {
-                gpio.get(*pin_num)?.into_output_high() // active-low: OFF = HIGH
-            } else {
-                gpio.get(*pin_num)?.into_output_low() // active-high: OFF = LOW
-            };
+            pins.insert(zone_id.clone(), claim_off(gpio, *pin_num, active_low)?);
+        }
 
-            // Prevent rppal from resetting the pin to input mode (floating)
-            // when OutputPin is dropped.  Our Drop impl already calls all_off()
-            // to drive pins to the safe OFF level — floating after that would
-            // risk relay chatter on boards with weak pull-ups.
-            pin.set_reset_on_drop(false);
+        Ok(Self {
+            gpio: gpio.clone(),
+            pins,
+            active_low,
+            stagger: Stagger::default(),
+            motors: HashMap::new(),
+        })
+    }
 
-            pins.insert(zone_id.clone(), pin);
+    /// Drive these zones as motorized valves, claiming their close pins.
+    pub(crate) fn with_motorized(mut self, motors: &[MotorSpec]) -> Result<Self> {
+        for (zone_id, close_pin, travel) in motors {
+            let pin = claim_off(&self.gpio, *close_pin, self.active_low)?;
+            self.motors
+                .insert(zone_id.clone(), (pin, Motor::new(*travel)));
         }
+        Ok(self)
+    }
 
-        Ok(Self { pins, active_low })
+    /// Enforce a minimum gap between relay switches (see `stagger_wait`).
+    pub(crate) fn with_stagger(mut self, interval: Duration) -> Self {
+        self.stagger.interval = interval;
+        self
+    }
+
+    /// How long the caller should wait before switching another relay.
+    pub(crate) fn stagger_wait(&self) -> Duration {
+        self.stagger.remaining()
     }
 
     pub(crate) fn set(&mut self, zone_id: &str, on: bool) {
         if let Some(pin) = self.pins.get_mut(zone_id) {
-            if self.active_low {
-                if on {
-                    pin.set_low()
-                } else {
-                    pin.set_high()
-                }
+            if let Some((close_pin, motor)) = self.motors.get_mut(zone_id) {
+                // Never power both directions at once.
+                write_relay(pin, self.active_low, false);
+                write_relay(close_pin, self.active_low, false);
+                let travel = motor.start(on, Instant::now());
+                write_relay(if on { pin } else { close_pin }, self.active_low, true);
+                info!(
+                    zone = %zone_id,
+                    state = if on { "OPENING" } else { "CLOSING" },
+                    travel_ms = travel.as_millis() as u64,
+                    "motorized valve driving"
+                );
             } else {
-                if on {
-                    pin.set_high()
-                } else {
-                    pin.set_low()
-                }
+                write_relay(pin, self.active_low, on);
+                info!(zone = %zone_id, state = if on { "ON" } else { "OFF" }, "valve set");
             }
-            info!(zone = %zone_id, state = if on { "ON" } else { "OFF" }, "valve set");
+            self.stagger.last_switch = Some(Instant::now());
         } else {
             warn!(zone = %zone_id, "unknown zone_id");
         }
@@ -72,44 +283,127 @@ impl ValveBoard {
             self.set(&k, false);
         }
     }
+
+    /// Power down motors whose drive has run its course.  Called every
+    /// [`MOTOR_SERVICE_INTERVAL`] while any motorized zone exists.
+    pub(crate) fn service_motors(&mut self) {
+        let now = Instant::now();
+        for (zone_id, (close_pin, motor)) in &mut self.motors {
+            if motor.finish(now) {
+                if let Some(pin) = self.pins.get_mut(zone_id) {
+                    write_relay(pin, self.active_low, false);
+                }
+                write_relay(close_pin, self.active_low, false);
+                debug!(zone = %zone_id, position = motor.position(now), "motorized valve stopped");
+            }
+        }
+    }
+
+    /// Longest time any motor still has to drive.
+    pub(crate) fn motor_travel_remaining(&self) -> Duration {
+        let now = Instant::now();
+        self.motors
+            .values()
+            .map(|(_, m)| m.remaining(now))
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Hardware-in-the-loop simulation needs the mock board; real relays
+    /// are never mirrored to the simulator.
+    pub(crate) fn mirror_intents(&mut self) -> Option<GpioIntents> {
+        warn!("SIM_HIL ignored — real GPIO valve board in use");
+        None
+    }
 }
 
-#[cfg(feature = "gpio")]
+#[cfg(any(feature = "gpio", feature = "gpiod"))]
 impl Drop for ValveBoard {
     fn drop(&mut self) {
         // Safety net: ensure all relays are de-energized when dropped.
+        // Motorized valves are given their travel time to close first.
         self.all_off();
+        std::thread::sleep(self.motor_travel_remaining());
+        self.service_motors();
     }
 }
 
 // ---------------------------------------------------------------------------
 // Mock valve board (development — no hardware, logs state)
 // ---------------------------------------------------------------------------
-#[cfg(not(feature = "gpio"))]
+#[cfg(not(any(feature = "gpio", feature = "gpiod")))]
 pub(crate) struct ValveBoard {
     pub(super) zones: HashMap<String, bool>, // zone_id -> on/off state
+    stagger: Stagger,
+    intents: Option<mpsc::UnboundedSender<(String, bool)>>,
+    motors: HashMap<String, Motor>,
 }
 
-#[cfg(not(feature = "gpio"))]
+#[cfg(not(any(feature = "gpio", feature = "gpiod")))]
 impl ValveBoard {
-    pub(crate) fn new(zone_to_gpio: &[(String, u8)], _active_low: bool) -> Result<Self> {
+    pub(crate) fn new(
+        _gpio: &GpioConfig,
+        zone_to_gpio: &[(String, u8)],
+        _active_low: bool,
+    ) -> Result<Self> {
         let mut zones = HashMap::new();
         for (zone_id, pin_num) in zone_to_gpio {
             info!(zone = %zone_id, gpio = pin_num, "[mock] registered zone (not wired)");
             zones.insert(zone_id.clone(), false);
         }
         info!("[mock] valve board initialised (no hardware)");
-        Ok(Self { zones })
+        Ok(Self {
+            zones,
+            stagger: Stagger::default(),
+            intents: None,
+            motors: HashMap::new(),
+        })
     }
 
-    pub(crate) fn set(&mut self, zone_id: &str, on: bool) {
-        if let Some(state) = self.zones.get_mut(zone_id) {
-            *state = on;
+    pub(crate) fn with_motorized(mut self, motors: &[MotorSpec]) -> Result<Self> {
+        for (zone_id, close_pin, travel) in motors {
             info!(
                 zone = %zone_id,
-                state = if on { "ON" } else { "OFF" },
-                "[mock] valve set"
+                close_gpio = close_pin,
+                travel_ms = travel.as_millis() as u64,
+                "[mock] registered motorized valve"
             );
+            self.motors.insert(zone_id.clone(), Motor::new(*travel));
+        }
+        Ok(self)
+    }
+
+    pub(crate) fn with_stagger(mut self, interval: Duration) -> Self {
+        self.stagger.interval = interval;
+        self
+    }
+
+    pub(crate) fn stagger_wait(&self) -> Duration {
+        self.stagger.remaining()
+    }
+
+    pub(crate) fn set(&mut self, zone_id: &str, on: bool) {
+        if let Some(state) = self.zones.get_mut(zone_id) {
+            *state = on;
+            self.stagger.last_switch = Some(Instant::now());
+            if let Some(motor) = self.motors.get_mut(zone_id) {
+                let travel = motor.start(on, Instant::now());
+                info!(
+                    zone = %zone_id,
+                    state = if on { "OPENING" } else { "CLOSING" },
+                    travel_ms = travel.as_millis() as u64,
+                    "[mock] motorized valve driving"
+                );
+            } else {
+                info!(
+                    zone = %zone_id,
+                    state = if on { "ON" } else { "OFF" },
+                    "[mock] valve set"
+                );
+            }
+            if let Some(tx) = &self.intents {
+                let _ = tx.send((zone_id.to_string(), on));
+            }
         } else {
             warn!(zone = %zone_id, "[mock] unknown zone_id");
         }
@@ -121,12 +415,45 @@ impl ValveBoard {
             self.set(&k, false);
         }
     }
+
+    pub(crate) fn service_motors(&mut self) {
+        let now = Instant::now();
+        for (zone_id, motor) in &mut self.motors {
+            if motor.finish(now) {
+                debug!(zone = %zone_id, position = motor.position(now), "[mock] motorized valve stopped");
+            }
+        }
+    }
+
+    pub(crate) fn motor_travel_remaining(&self) -> Duration {
+        let now = Instant::now();
+        self.motors
+            .values()
+            .map(|m| m.remaining(now))
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Start mirroring every valve write (hardware-in-the-loop mode).  The
+    /// current state of each zone is queued first so the simulator starts
+    /// in sync.
+    pub(crate) fn mirror_intents(&mut self) -> Option<GpioIntents> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        for (zone_id, on) in &self.zones {
+            let _ = tx.send((zone_id.clone(), *on));
+        }
+        self.intents = Some(tx);
+        info!("[mock] mirroring valve writes to the simulator");
+        Some(rx)
+    }
 }
 
-#[cfg(not(feature = "gpio"))]
+#[cfg(not(any(feature = "gpio", feature = "gpiod")))]
 impl Drop for ValveBoard {
     fn drop(&mut self) {
         self.all_off();
+        std::thread::sleep(self.motor_travel_remaining());
+        self.service_motors();
     }
 }
 
@@ -140,41 +467,46 @@ mod tests {
 
     // -- ValveBoard (mock) --------------------------------------------------
 
+    #[cfg(not(any(feature = "gpio", feature = "gpiod")))]
     #[test]
     fn valve_board_new_registers_zones() {
         let zones = vec![("z1".to_string(), 17), ("z2".to_string(), 27)];
-        let board = ValveBoard::new(&zones, true).unwrap();
+        let board = ValveBoard::new(&GpioConfig::default(), &zones, true).unwrap();
         assert_eq!(board.zones.len(), 2);
     }
 
+    #[cfg(not(any(feature = "gpio", feature = "gpiod")))]
     #[test]
     fn valve_board_new_all_off() {
         let zones = vec![("z1".to_string(), 17)];
-        let board = ValveBoard::new(&zones, true).unwrap();
+        let board = ValveBoard::new(&GpioConfig::default(), &zones, true).unwrap();
         assert!(!board.zones["z1"]);
     }
 
+    #[cfg(not(any(feature = "gpio", feature = "gpiod")))]
     #[test]
     fn valve_board_set_on() {
         let zones = vec![("z1".to_string(), 17)];
-        let mut board = ValveBoard::new(&zones, true).unwrap();
+        let mut board = ValveBoard::new(&GpioConfig::default(), &zones, true).unwrap();
         board.set("z1", true);
         assert!(board.zones["z1"]);
     }
 
+    #[cfg(not(any(feature = "gpio", feature = "gpiod")))]
     #[test]
     fn valve_board_set_off() {
         let zones = vec![("z1".to_string(), 17)];
-        let mut board = ValveBoard::new(&zones, true).unwrap();
+        let mut board = ValveBoard::new(&GpioConfig::default(), &zones, true).unwrap();
         board.set("z1", true);
         board.set("z1", false);
         assert!(!board.zones["z1"]);
     }
 
+    #[cfg(not(any(feature = "gpio", feature = "gpiod")))]
     #[test]
     fn valve_board_all_off_resets_everything() {
         let zones = vec![("z1".to_string(), 17), ("z2".to_string(), 27)];
-        let mut board = ValveBoard::new(&zones, true).unwrap();
+        let mut board = ValveBoard::new(&GpioConfig::default(), &zones, true).unwrap();
         board.set("z1", true);
         board.set("z2", true);
         board.all_off();
@@ -182,21 +514,160 @@ mod tests {
         assert!(!board.zones["z2"]);
     }
 
+    #[cfg(not(any(feature = "gpio", feature = "gpiod")))]
     #[test]
     fn valve_board_set_unknown_zone_does_not_panic() {
         let zones = vec![("z1".to_string(), 17)];
-        let mut board = ValveBoard::new(&zones, true).unwrap();
+        let mut board = ValveBoard::new(&GpioConfig::default(), &zones, true).unwrap();
         board.set("nonexistent", true); // should not panic
         assert_eq!(board.zones.len(), 1); // no new entry created
     }
 
+    #[cfg(not(any(feature = "gpio", feature = "gpiod")))]
+    #[test]
+    fn valve_board_mirrors_intents() {
+        let zones = vec![("z1".to_string(), 17)];
+        let mut board = ValveBoard::new(&GpioConfig::default(), &zones, true).unwrap();
+        let mut rx = board.mirror_intents().unwrap();
+        board.set("z1", true);
+        board.set("nonexistent", true);
+        board.all_off();
+        let seen: Vec<(String, bool)> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert_eq!(
+            seen,
+            [
+                ("z1".to_string(), false),
+                ("z1".to_string(), true),
+                ("z1".to_string(), false)
+            ]
+        );
+    }
+
+    #[cfg(not(any(feature = "gpio", feature = "gpiod")))]
     #[test]
     fn valve_board_drop_turns_off() {
         let zones = vec![("z1".to_string(), 17)];
-        let mut board = ValveBoard::new(&zones, true).unwrap();
+        let mut board = ValveBoard::new(&GpioConfig::default(), &zones, true).unwrap();
         board.set("z1", true);
         assert!(board.zones["z1"]);
         drop(board);
         // Can't check state after drop, but at least it doesn't panic
     }
+
+    // -- Motorized valves ---------------------------------------------------
+
+    #[test]
+    fn motor_open_drives_remaining_distance() {
+        let t0 = Instant::now();
+        let travel = Duration::from_secs(10);
+        let mut m = Motor::new(travel);
+        // Closing always runs the full travel, whatever the estimate.
+        assert_eq!(m.start(false, t0), travel);
+        assert!(!m.finish(t0 + Duration::from_secs(9)));
+        assert!(m.finish(t0 + travel));
+        assert_eq!(m.position(t0 + travel), 0.0);
+
+        let t1 = t0 + Duration::from_secs(20);
+        assert_eq!(m.start(true, t1), travel);
+        // Reversed after 4 s: 40 % open.
+        let t2 = t1 + Duration::from_secs(4);
+        assert!((m.position(t2) - 0.4).abs() < 1e-6);
+        assert_eq!(m.start(false, t2), travel);
+        assert!((m.position(t2 + Duration::from_secs(2)) - 0.2).abs() < 1e-6);
+        // Reopening from 20 % only needs the remaining 80 %.
+        let t3 = t2 + Duration::from_secs(2);
+        let d = m.start(true, t3);
+        assert!((d.as_secs_f32() - 8.0).abs() < 1e-3);
+        assert_eq!(
+            m.remaining(t3 + Duration::from_secs(3)),
+            d - Duration::from_secs(3)
+        );
+    }
+
+    #[test]
+    fn motor_starts_assumed_open() {
+        let t0 = Instant::now();
+        let mut m = Motor::new(Duration::from_secs(10));
+        // Not driven open until a close has established the position.
+        assert_eq!(m.start(true, t0), Duration::ZERO);
+        assert!(m.finish(t0));
+    }
+
+    #[test]
+    fn valve_config_validation() {
+        assert!(ValveConfig::Solenoid.validate(17).is_empty());
+        let ok = ValveConfig::Motorized {
+            close_gpio_pin: 22,
+            travel_sec: 8,
+        };
+        assert!(ok.validate(17).is_empty());
+        let bad = ValveConfig::Motorized {
+            close_gpio_pin: 17,
+            travel_sec: 0,
+        };
+        assert_eq!(bad.validate(17).len(), 2);
+
+        let parsed: ValveConfig =
+            serde_json::from_str(r#"{"type": "motorized", "close_gpio_pin": 22, "travel_sec": 8}"#)
+                .unwrap();
+        assert_eq!(parsed, ok);
+    }
+
+    #[cfg(not(any(feature = "gpio", feature = "gpiod")))]
+    #[test]
+    fn motorized_board_stops_after_travel() {
+        let zones = vec![("z1".to_string(), 17), ("z2".to_string(), 27)];
+        let mut board = ValveBoard::new(&GpioConfig::default(), &zones, true)
+            .unwrap()
+            .with_motorized(&[("z1".to_string(), 22, Duration::from_millis(20))])
+            .unwrap();
+        board.all_off();
+        assert!(board.motor_travel_remaining() > Duration::ZERO);
+        board.service_motors();
+        assert!(board.motor_travel_remaining() > Duration::ZERO);
+
+        std::thread::sleep(Duration::from_millis(25));
+        board.service_motors();
+        assert_eq!(board.motor_travel_remaining(), Duration::ZERO);
+
+        board.set("z1", true);
+        assert!(board.motor_travel_remaining() > Duration::ZERO);
+    }
+
+    #[cfg(not(any(feature = "gpio", feature = "gpiod")))]
+    #[test]
+    fn stagger_wait_zero_without_stagger() {
+        let zones = vec![("z1".to_string(), 17)];
+        let mut board = ValveBoard::new(&GpioConfig::default(), &zones, true).unwrap();
+        board.set("z1", true);
+        assert_eq!(board.stagger_wait(), Duration::ZERO);
+    }
+
+    #[cfg(not(any(feature = "gpio", feature = "gpiod")))]
+    #[test]
+    fn stagger_wait_after_activation() {
+        let zones = vec![("z1".to_string(), 17), ("z2".to_string(), 27)];
+        let mut board = ValveBoard::new(&GpioConfig::default(), &zones, true)
+            .unwrap()
+            .with_stagger(Duration::from_secs(60));
+        assert_eq!(board.stagger_wait(), Duration::ZERO); // nothing opened yet
+        board.set("z1", true);
+        assert!(board.stagger_wait() > Duration::from_secs(59));
+        assert!(board.stagger_wait() <= Duration::from_secs(60));
+    }
+
+    #[cfg(not(any(feature = "gpio", feature = "gpiod")))]
+    #[test]
+    fn stagger_wait_after_close() {
+        let zones = vec![("z1".to_string(), 17)];
+        let mut board = ValveBoard::new(&GpioConfig::default(), &zones, true)
+            .unwrap()
+            .with_stagger(Duration::from_millis(50));
+        board.set("z1", true);
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(board.stagger_wait(), Duration::ZERO);
+        // Closing is a switch too.
+        board.set("z1", false);
+        assert!(board.stagger_wait() > Duration::ZERO);
+    }
 }
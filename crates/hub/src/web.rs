@@ -1,19 +1,55 @@
 //! Axum REST API and embedded single-page web dashboard.
 
-use axum::body::Body;
+use axum::body::{Body, Bytes};
 use axum::extract::{Path, Query, State};
-use axum::http::{header, Request, StatusCode};
+use axum::http::{header, HeaderMap, Request, StatusCode};
 use axum::middleware::{self, Next};
 use axum::response::{IntoResponse, Json};
-use axum::routing::get;
-use axum::Router;
-use serde::Deserialize;
+use axum::routing::{delete, get, post, put};
+use axum::{Extension, Router};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
+use time::OffsetDateTime;
 use tokio::net::TcpListener;
-
-use crate::db::{Db, SensorConfig, ZoneConfig};
-use crate::state::SharedState;
+use tokio::sync::{mpsc, oneshot, Notify};
+
+use crate::aggregation::Aggregation;
+use crate::audit::{AuditEntry, AuditSource};
+use crate::auth::{self, ApiTokens, Identity, NodeTokens};
+use crate::blackout::{self, Blackout, Blackouts};
+use crate::calibration::Curve;
+use crate::config::{self, Config, OperationMode, SensorEntry, ZoneEntry, ZoneExport};
+use crate::confirm::{self, ConfirmApi};
+use crate::db::{
+    default_sensor_enabled, default_sensor_weight, ClimateReading, ConfigVersion, Db, Disturbance,
+    MoistureBucket, NodeConfig, ReadingRow, SensorConfig, SensorHealth, StalePolicy, UsageBucket,
+    ZoneConfig, ZoneOdometer, ADS1115_MAX_CHANNEL,
+};
+use crate::efficiency::{self, PulseOutcome, ZoneEfficiency};
+use crate::et::EtDay;
+use crate::federation::FederationView;
+use crate::flow::{self, FlowTrend};
+use crate::history::{self, Comparison, PeriodSummary};
+use crate::kiosk::{self, KioskAccess};
+use crate::limits::SafetyLimits;
+use crate::metrics;
+use crate::mqtt::NodeCommand;
+use crate::ota::{self, NodeOta, OtaApi, OtaImage};
+use crate::restore::{self, BackupFile, RestoreApi, RestoreRequest};
+use crate::retention::{self, PruneReport, RetentionPolicy};
+use crate::review::Finding;
+use crate::scheduler;
+use crate::selftest::{self, ValveTestReport};
+use crate::sessions::{self, Session};
+use crate::state::{
+    self, Firmware, NodeLogs, NodePong, SharedState, StatusChanges, StatusSnapshot,
+};
+use crate::strategy::StrategyConfig;
+use crate::valve::ValveConfig;
 
 // this is built by the ui/package.json build script into the dist/index.html file
 const INDEX_HTML: &str = include_str!("ui/dist/index.html");
@@ -26,6 +62,33 @@ const INDEX_HTML: &str = include_str!("ui/dist/index.html");
 pub struct AppState {
     pub shared: SharedState,
     pub db: Db,
+    /// Signalled after sensor / node changes so `main` republishes the
+    /// retained `cfg/<node_id>/set` settings.
+    pub node_settings: Arc<Notify>,
+    /// Diagnostics commands for `main` to publish on `cmd/<node_id>/...`.
+    pub node_commands: mpsc::Sender<(String, NodeCommand)>,
+    /// Zones whose session was cancelled, for `main` to publish `OFF` to.
+    pub session_cancels: mpsc::Sender<String>,
+    /// Manual pulses (zone, seconds) for `main` to publish a timed `ON` for.
+    pub water_now: mpsc::Sender<(String, i64)>,
+    /// Backup listing and restore requests for the main loop.
+    pub restore: RestoreApi,
+    /// Confirmation tokens for restores and (optionally) deletes.
+    pub confirm: ConfirmApi,
+    /// Node images and update announcements for `main` to publish.
+    pub ota: OtaApi,
+    /// Served by `/api/status`; refreshed every
+    /// [`STATUS_PUBLISH_INTERVAL`](crate::state::STATUS_PUBLISH_INTERVAL).
+    pub status: StatusSnapshot,
+    /// `API_TOKEN` / `API_TOKENS`, checked by the auth middleware.
+    pub tokens: Arc<ApiTokens>,
+    /// Telemetry posted to `/api/telemetry`: (node, JSON payload) for
+    /// `main` to ingest like `tele/<node_id>/reading`.
+    pub telemetry: mpsc::Sender<(String, Vec<u8>)>,
+    /// `NODE_TOKENS`, checked by the telemetry handler.
+    pub node_tokens: Arc<NodeTokens>,
+    /// `KIOSK`: who may load `/kiosk`.
+    pub kiosk: KioskAccess,
 }
 
 // ---------------------------------------------------------------------------
@@ -92,6 +155,27 @@ struct ZonePayload {
     max_pulses_per_day: i64,
     stale_timeout_min: i64,
     valve_gpio_pin: i64,
+    #[serde(default)]
+    flow_lpm: Option<f32>,
+    #[serde(default)]
+    strategy: StrategyConfig,
+    #[serde(default)]
+    priority: i64,
+    /// Takes effect on the valve board at the next hub restart.
+    #[serde(default)]
+    valve: ValveConfig,
+    #[serde(default)]
+    after: Vec<String>,
+    #[serde(default)]
+    aggregation: Aggregation,
+    #[serde(default)]
+    notes: Option<String>,
+    #[serde(default)]
+    location: Option<String>,
+    #[serde(default)]
+    plant_type: Option<String>,
+    #[serde(default)]
+    emitter_flow_lph: Option<f32>,
 }
 
 #[derive(Deserialize)]
@@ -100,6 +184,90 @@ struct SensorPayload {
     zone_id: String,
     raw_dry: i64,
     raw_wet: i64,
+    channel: Option<i64>,
+    #[serde(default = "default_sensor_weight")]
+    weight: f64,
+    #[serde(default)]
+    failure_margin: Option<i64>,
+    #[serde(default)]
+    failure_margin_pct: Option<f64>,
+    #[serde(default)]
+    curve: Option<Curve>,
+    #[serde(default = "default_sensor_enabled")]
+    enabled: bool,
+}
+
+/// Body of `POST /api/zones/{zone_id}/clone`.
+#[derive(Deserialize)]
+struct ClonePayload {
+    zone_id: String,
+    /// Defaults to the source's name with " (copy)".
+    name: Option<String>,
+    /// Defaults to the source's pin, which only validates in monitor mode.
+    valve_gpio_pin: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct ExportQuery {
+    /// `json` (default) or `toml`.
+    format: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NodePayload {
+    name: String,
+    sample_interval_sec: Option<i64>,
+    stale_timeout_min: Option<i64>,
+    #[serde(default)]
+    battery_powered: bool,
+    wake_grace_factor: Option<f64>,
+}
+
+/// Create / update body for `/api/zones/{zone_id}/disturbances`.  Times
+/// are unix seconds, matching reading timestamps.
+#[derive(Deserialize)]
+struct DisturbancePayload {
+    /// Defaults to now on create, or the stored start on update.
+    start_ts: Option<i64>,
+    /// Omit (or null) for an ongoing disturbance.
+    end_ts: Option<i64>,
+    reason: String,
+}
+
+/// Create / update body for `/api/blackouts`.
+#[derive(Deserialize)]
+struct BlackoutPayload {
+    /// `YYYY-MM-DD`, or `MM-DD` for a range that recurs every year.
+    start: String,
+    /// Inclusive; same form as `start`.
+    end: String,
+    reason: String,
+}
+
+#[derive(Deserialize)]
+struct WaterNowQuery {
+    /// At most the zone's `pulse_sec`, which is the default.
+    duration_sec: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct ConfirmQuery {
+    /// Token from the first call, with `API_CONFIRM_DELETES` set.
+    confirm: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ValveTestQuery {
+    /// Defaults to `[valve_test] blip_sec`.
+    blip_sec: Option<i64>,
+    /// Test only this zone.
+    zone_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ZonesQuery {
+    #[serde(default)]
+    include_archived: bool,
 }
 
 #[derive(Deserialize)]
@@ -110,6 +278,42 @@ struct ReadingsQuery {
     offset: Option<i64>,
 }
 
+#[derive(Deserialize)]
+struct TrendQuery {
+    /// `hour` (default) or `day`.
+    resolution: Option<String>,
+    /// Unix-seconds range, `to` exclusive.
+    from: Option<i64>,
+    to: Option<i64>,
+}
+
+/// Longest `wait_sec` a `/api/status/changes` long-poll may ask for.
+const MAX_CHANGES_WAIT_SEC: u64 = 60;
+
+/// Longest lookback `GET /api/nodes/{node_id}/climate` serves (31 days).
+const MAX_CLIMATE_HOURS: i64 = 31 * 24;
+
+#[derive(Deserialize)]
+struct ChangesQuery {
+    #[serde(default)]
+    since_seq: u64,
+    /// Hold the request up to this long while nothing has changed.
+    #[serde(default)]
+    wait_sec: u64,
+}
+
+#[derive(Deserialize)]
+struct KioskQuery {
+    token: Option<String>,
+}
+
+/// Query for `POST /api/sensors/{sensor_id}/recompute`.
+#[derive(Deserialize)]
+struct RecomputeQuery {
+    /// First reading to rewrite, unix seconds (default: all of them).
+    from: Option<i64>,
+}
+
 #[derive(Deserialize)]
 struct EventsQuery {
     zone_id: Option<String>,
@@ -117,11 +321,162 @@ struct EventsQuery {
     offset: Option<i64>,
 }
 
+#[derive(Deserialize)]
+struct DecisionsQuery {
+    zone_id: Option<String>,
+    /// Inclusive unix-seconds range.
+    from: Option<i64>,
+    to: Option<i64>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct AuditQuery {
+    /// `zone` or `sensor`.
+    entity: Option<String>,
+    entity_id: Option<String>,
+    /// Inclusive unix-seconds range.
+    from: Option<i64>,
+    to: Option<i64>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct ClimateQuery {
+    /// Hours of samples to return (default 24, at most a month).
+    hours: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct EtQuery {
+    /// Closed days to return (default 14).
+    days: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct LogsQuery {
+    /// `warn` or `error`.
+    level: Option<String>,
+    /// Inclusive unix-seconds range.
+    from: Option<i64>,
+    to: Option<i64>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
 #[derive(Deserialize)]
 struct CountersQuery {
     day: Option<String>,
 }
 
+#[derive(Deserialize)]
+struct UsageQuery {
+    zone_id: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    #[serde(default)]
+    group_by: UsageBucket,
+}
+
+#[derive(Deserialize)]
+struct EfficiencyQuery {
+    zone_id: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SoakQuery {
+    zone_id: Option<String>,
+    events: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct CompareQuery {
+    from: Option<String>,
+    to: Option<String>,
+    baseline_from: Option<String>,
+}
+
+// ---------------------------------------------------------------------------
+// Response types
+// ---------------------------------------------------------------------------
+
+/// Everything needed to debug one node: its admin record, live MQTT status,
+/// staleness against the effective timeout, and each sensor's calibration
+/// alongside its newest stored reading.
+#[derive(Serialize)]
+struct NodeDiagnostics {
+    node_id: String,
+    config: Option<NodeConfig>,
+    online: bool,
+    #[serde(with = "time::serde::rfc3339::option")]
+    last_seen: Option<OffsetDateTime>,
+    last_seen_age_sec: Option<i64>,
+    stale_timeout_min: i64,
+    /// Effective silence allowed before `stale`: the timeout, or for a
+    /// battery node its wake interval times the grace factor.
+    stale_after_sec: i64,
+    stale: bool,
+    /// Build the node announced when it last came online.
+    firmware: Option<Firmware>,
+    sensors: Vec<SensorDiagnostics>,
+}
+
+/// A node as listed by `GET /api/nodes`: its admin record plus the build
+/// it last announced.
+#[derive(Serialize)]
+struct NodeView {
+    #[serde(flatten)]
+    config: NodeConfig,
+    firmware: Option<Firmware>,
+}
+
+/// A zone as served by the zones API: its config plus valve odometer.
+#[derive(Serialize)]
+struct ZoneView {
+    #[serde(flatten)]
+    config: ZoneConfig,
+    odometer: ZoneOdometer,
+}
+
+/// Reference ET history and the water balance of every `et` zone.
+#[derive(Serialize)]
+struct EtResponse {
+    /// The day still being gathered.
+    today: Option<WeatherToday>,
+    /// Newest first.
+    days: Vec<EtDay>,
+    zones: Vec<ZoneWaterBalance>,
+}
+
+#[derive(Serialize)]
+struct WeatherToday {
+    date: String,
+    temp_min_c: Option<f64>,
+    temp_max_c: Option<f64>,
+    rain_mm: f64,
+}
+
+#[derive(Serialize)]
+struct ZoneWaterBalance {
+    zone_id: String,
+    deficit_mm: f64,
+    allowed_depletion_mm: f64,
+}
+
+#[derive(Serialize)]
+struct SensorDiagnostics {
+    #[serde(flatten)]
+    sensor: SensorConfig,
+    latest: Option<ReadingRow>,
+    /// Whether `latest.raw` falls inside the calibration window (plus the
+    /// sensor-failure margin).  `None` when there is no reading yet.
+    plausible: Option<bool>,
+}
+
 // ---------------------------------------------------------------------------
 // Validation
 // ---------------------------------------------------------------------------
@@ -158,6 +513,60 @@ fn validate_zone(p: &ZonePayload) -> Result<(), ApiError> {
     if p.valve_gpio_pin < 0 {
         errs.push("valve_gpio_pin must be >= 0".into());
     }
+    if matches!(p.flow_lpm, Some(v) if v.is_nan() || v <= 0.0) {
+        errs.push("flow_lpm must be > 0".into());
+    }
+    if matches!(p.emitter_flow_lph, Some(v) if v.is_nan() || v <= 0.0) {
+        errs.push("emitter_flow_lph must be > 0".into());
+    }
+    for (field, text) in [
+        ("notes", &p.notes),
+        ("location", &p.location),
+        ("plant_type", &p.plant_type),
+    ] {
+        if text
+            .as_deref()
+            .is_some_and(|t| t.chars().count() > config::MAX_ZONE_TEXT_CHARS)
+        {
+            errs.push(format!(
+                "{field} must be at most {} characters",
+                config::MAX_ZONE_TEXT_CHARS
+            ));
+        }
+    }
+    errs.extend(p.strategy.validate());
+    errs.extend(p.valve.validate(p.valve_gpio_pin));
+    if errs.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::Validation(errs))
+    }
+}
+
+/// A zone's upstream zones must exist and must not lead back to it.
+async fn validate_dependencies(
+    state: &AppState,
+    zone_id: &str,
+    after: &[String],
+) -> Result<(), ApiError> {
+    if after.is_empty() {
+        return Ok(());
+    }
+    let zones = state.db.load_zones().await.map_err(internal)?;
+    let mut deps: BTreeMap<&str, &[String]> = zones
+        .iter()
+        .map(|z| (z.zone_id.as_str(), z.after.as_slice()))
+        .collect();
+    deps.insert(zone_id, after);
+
+    let mut errs: Vec<String> = after
+        .iter()
+        .filter(|up| !deps.contains_key(up.as_str()))
+        .map(|up| format!("after: unknown zone '{up}'"))
+        .collect();
+    if let Some(cycle) = config::dependency_cycle(&deps) {
+        errs.push(format!("after: dependency cycle: {}", cycle.join(" -> ")));
+    }
     if errs.is_empty() {
         Ok(())
     } else {
@@ -176,6 +585,83 @@ fn validate_sensor(p: &SensorPayload) -> Result<(), ApiError> {
     if p.raw_dry == p.raw_wet {
         errs.push("raw_dry and raw_wet must differ".into());
     }
+    if matches!(p.channel, Some(ch) if !(0..=ADS1115_MAX_CHANNEL).contains(&ch)) {
+        errs.push(format!("channel must be 0–{ADS1115_MAX_CHANNEL}"));
+    }
+    if !p.weight.is_finite() || p.weight < 0.0 {
+        errs.push("weight must be >= 0".into());
+    }
+    if p.failure_margin.is_some() && p.failure_margin_pct.is_some() {
+        errs.push("set failure_margin or failure_margin_pct, not both".into());
+    }
+    if matches!(p.failure_margin, Some(m) if m < 0) {
+        errs.push("failure_margin must be >= 0".into());
+    }
+    if matches!(p.failure_margin_pct, Some(p) if !(0.0..=100.0).contains(&p)) {
+        errs.push("failure_margin_pct must be 0–100".into());
+    }
+    if let Some(Err(curve_errs)) = p.curve.as_ref().map(Curve::validate) {
+        errs.extend(curve_errs);
+    }
+    if errs.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::Validation(errs))
+    }
+}
+
+fn validate_node(p: &NodePayload) -> Result<(), ApiError> {
+    let mut errs = Vec::new();
+    if p.name.trim().is_empty() {
+        errs.push("name must not be empty".into());
+    }
+    if matches!(p.sample_interval_sec, Some(v) if v <= 0) {
+        errs.push("sample_interval_sec must be > 0".into());
+    }
+    if matches!(p.stale_timeout_min, Some(v) if v <= 0) {
+        errs.push("stale_timeout_min must be > 0".into());
+    }
+    if p.battery_powered && p.sample_interval_sec.is_none() {
+        errs.push(
+            "battery_powered requires sample_interval_sec (the expected wake interval)".into(),
+        );
+    }
+    if matches!(p.wake_grace_factor, Some(v) if v < 1.0) {
+        errs.push("wake_grace_factor must be >= 1.0".into());
+    }
+    if errs.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::Validation(errs))
+    }
+}
+
+fn validate_blackout(p: &BlackoutPayload) -> Result<(), ApiError> {
+    let mut errs = Vec::new();
+    if let Err(e) = blackout::Span::parse(&p.start, &p.end) {
+        errs.push(e);
+    }
+    if p.reason.trim().is_empty() {
+        errs.push("reason must not be empty".into());
+    }
+    if errs.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::Validation(errs))
+    }
+}
+
+fn validate_disturbance(p: &DisturbancePayload, start_ts: i64) -> Result<(), ApiError> {
+    let mut errs = Vec::new();
+    if p.reason.trim().is_empty() {
+        errs.push("reason must not be empty".into());
+    }
+    if start_ts < 0 {
+        errs.push("start_ts must be >= 0".into());
+    }
+    if matches!(p.end_ts, Some(end) if end <= start_ts) {
+        errs.push("end_ts must be after start_ts".into());
+    }
     if errs.is_empty() {
         Ok(())
     } else {
@@ -187,37 +673,67 @@ fn validate_sensor(p: &SensorPayload) -> Result<(), ApiError> {
 // Auth middleware
 // ---------------------------------------------------------------------------
 
-/// Optional bearer-token gate. If API_TOKEN is set, every request to /api/*
-/// must carry `Authorization: Bearer <token>`. Requests to `/` (dashboard)
-/// and `/api/health` are exempt.
-async fn auth_layer(req: Request<Body>, next: Next) -> impl IntoResponse {
+/// Optional bearer-token gate. If API_TOKEN or API_TOKENS is set, every
+/// request to /api/* must carry `Authorization: Bearer <token>` for a token
+/// whose role covers the route (see [`auth::required_role`]); the caller's
+/// [`auth::Identity`] is added to the request extensions. Requests to `/`
+/// (dashboard), `/api/health` and `/ota/*` (signed node images) are exempt.
+async fn auth_layer(
+    State(state): State<AppState>,
+    mut req: Request<Body>,
+    next: Next,
+) -> impl IntoResponse {
     let path = req.uri().path().to_string();
 
-    // Always allow health check and dashboard
-    if path == "/" || path == "/api/health" {
+    // Always allow health check and dashboard; telemetry and the kiosk
+    // check their own tokens.
+    if path == "/"
+        || path == "/kiosk"
+        || path == "/api/health"
+        || path == "/api/telemetry"
+        || path.starts_with("/ota/")
+    {
         return next.run(req).await;
     }
 
     // If no token configured, allow everything (dev mode)
-    let expected = match env::var("API_TOKEN") {
-        Ok(t) if !t.is_empty() => t,
-        _ => return next.run(req).await,
-    };
+    if state.tokens.is_open() {
+        return next.run(req).await;
+    }
 
     // Check Authorization header
-    if let Some(auth) = req.headers().get("authorization") {
-        if let Ok(val) = auth.to_str() {
-            if val.strip_prefix("Bearer ").map(|t| t.trim()) == Some(expected.as_str()) {
-                return next.run(req).await;
-            }
-        }
-    }
+    let identity = req
+        .headers()
+        .get("authorization")
+        .and_then(|auth| auth.to_str().ok())
+        .and_then(|val| state.tokens.identify(val))
+        .cloned();
+    let Some(identity) = identity else {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({"error": "unauthorized", "message": "invalid or missing bearer token"})),
+        )
+            .into_response();
+    };
 
-    (
-        StatusCode::UNAUTHORIZED,
-        Json(serde_json::json!({"error": "unauthorized", "message": "invalid or missing bearer token"})),
-    )
-        .into_response()
+    let required = auth::required_role(req.method(), &path);
+    if identity.role < required {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "error": "forbidden",
+                "message": format!(
+                    "token '{}' has role {}; this needs {}",
+                    identity.name,
+                    identity.role.as_str(),
+                    required.as_str()
+                ),
+            })),
+        )
+            .into_response();
+    }
+    req.extensions_mut().insert(identity);
+    next.run(req).await
 }
 
 // ---------------------------------------------------------------------------
@@ -227,8 +743,13 @@ async fn auth_layer(req: Request<Body>, next: Next) -> impl IntoResponse {
 pub fn router(state: AppState) -> Router {
     Router::new()
         .route("/", get(index))
+        .route("/kiosk", get(kiosk_page))
         .route("/api/health", get(api_health))
         .route("/api/status", get(api_status))
+        .route("/api/status/changes", get(api_status_changes))
+        .route("/api/limits", get(api_limits))
+        .route("/metrics", get(metrics))
+        .route("/ota/{file}", get(ota_image))
         // Zones
         .route("/api/zones", get(api_zones))
         .route(
@@ -237,19 +758,117 @@ pub fn router(state: AppState) -> Router {
                 .put(api_upsert_zone)
                 .delete(api_delete_zone),
         )
+        .route(
+            "/api/zones/{zone_id}/disturbances",
+            get(api_disturbances).post(api_create_disturbance),
+        )
+        .route(
+            "/api/zones/{zone_id}/disturbances/{id}",
+            put(api_update_disturbance).delete(api_delete_disturbance),
+        )
+        .route("/api/zones/{zone_id}/clone", post(api_clone_zone))
+        .route("/api/zones/{zone_id}/water-now", post(api_water_now))
+        .route("/api/zones/{zone_id}/flow", get(api_zone_flow))
+        .route("/api/zones/{zone_id}/compare", get(api_zone_compare))
+        .route(
+            "/api/zones/{zone_id}/odometer/service",
+            post(api_record_valve_service),
+        )
+        .route(
+            "/api/zones/{zone_id}/archive",
+            post(api_archive_zone).delete(api_unarchive_zone),
+        )
         // Sensors
         .route("/api/sensors", get(api_sensors))
+        .route("/api/sensors/health", get(api_sensor_health))
         .route(
             "/api/sensors/{sensor_id}",
             get(api_get_sensor)
                 .put(api_upsert_sensor)
                 .delete(api_delete_sensor),
         )
+        .route(
+            "/api/sensors/{sensor_id}/quarantine",
+            delete(api_release_sensor),
+        )
+        .route("/api/sensors/{sensor_id}/trend", get(api_sensor_trend))
+        .route(
+            "/api/sensors/{sensor_id}/recompute",
+            post(api_recompute_moisture),
+        )
+        // Nodes
+        .route("/api/telemetry", post(api_telemetry))
+        .route("/api/nodes", get(api_nodes))
+        .route(
+            "/api/nodes/{node_id}",
+            get(api_get_node).put(api_upsert_node),
+        )
+        .route("/api/nodes/{node_id}/sensors", get(api_node_sensors))
+        .route(
+            "/api/nodes/{node_id}/decommission",
+            post(api_decommission_node),
+        )
+        .route("/api/nodes/{node_id}/restart", post(api_restart_node))
+        .route(
+            "/api/nodes/{node_id}/send-logs",
+            post(api_request_node_logs),
+        )
+        .route("/api/nodes/{node_id}/logs", get(api_node_logs))
+        .route("/api/nodes/{node_id}/climate", get(api_node_climate))
+        .route("/api/nodes/{node_id}/ping", post(api_ping_node))
+        .route(
+            "/api/nodes/{node_id}/ota",
+            post(api_update_node).delete(api_cancel_node_update),
+        )
+        .route("/api/ota", get(api_ota))
         // Readings / events / counters (read-only)
         .route("/api/readings", get(api_readings))
         .route("/api/watering-events", get(api_watering_events))
+        .route("/api/sessions", get(api_sessions))
+        .route("/api/sessions/{id}/cancel", post(api_cancel_session))
+        .route("/api/scheduler/decisions", get(api_scheduler_decisions))
+        .route("/api/scheduler/preview", get(api_scheduler_preview))
+        .route("/api/logs", get(api_logs))
         .route("/api/counters/{zone_id}", get(api_counters))
-        .layer(middleware::from_fn(auth_layer))
+        .route("/api/reports/usage", get(api_usage_report))
+        .route("/api/reports/efficiency", get(api_efficiency_report))
+        .route("/api/reports/soak", get(api_soak_report))
+        // Config versions
+        .route("/api/config/versions", get(api_config_versions))
+        .route("/api/config/versions/{version}", get(api_config_version))
+        .route("/api/config/rollback/{version}", post(api_config_rollback))
+        .route("/api/config/export", get(api_config_export))
+        .route("/api/config/import", put(api_config_import))
+        .route("/api/audit", get(api_audit))
+        .route("/api/federation", get(api_federation))
+        .route("/api/et", get(api_et))
+        // Backups
+        .route("/api/backups", get(api_backups))
+        .route("/api/backups/restore", post(api_restore_backup))
+        // Safety review
+        .route("/api/safety-review", get(api_safety_review))
+        .route(
+            "/api/safety-review/{zone_id}/ack",
+            post(api_acknowledge_review),
+        )
+        // Emergency stop
+        .route("/api/emergency-stop/clear", post(api_clear_emergency_stop))
+        // Data retention
+        .route("/api/retention", get(api_retention).put(api_put_retention))
+        .route(
+            "/api/blackouts",
+            get(api_blackouts).post(api_create_blackout),
+        )
+        .route(
+            "/api/blackouts/{id}",
+            put(api_update_blackout).delete(api_delete_blackout),
+        )
+        .route("/api/maintenance/prune", post(api_prune))
+        .route(
+            "/api/maintenance/valve-test",
+            get(api_valve_test).post(api_start_valve_test),
+        )
+        .layer(middleware::from_fn_with_state(state.clone(), auth_layer))
         .with_state(state)
 }
 
@@ -264,62 +883,178 @@ async fn index() -> impl IntoResponse {
     )
 }
 
+/// Read-only status page for wall displays; see [`kiosk`].
+async fn kiosk_page(
+    State(state): State<AppState>,
+    Query(q): Query<KioskQuery>,
+) -> Result<axum::response::Response, ApiError> {
+    match state.kiosk {
+        KioskAccess::Off => return Err(ApiError::NotFound("kiosk is off".into())),
+        KioskAccess::Token
+            if !state.tokens.is_open()
+                && q.token
+                    .as_deref()
+                    .and_then(|t| state.tokens.identify_token(t))
+                    .is_none() =>
+        {
+            return Ok((StatusCode::UNAUTHORIZED, "kiosk needs ?token=").into_response());
+        }
+        _ => {}
+    }
+    let zones = state.db.load_zones().await.map_err(internal)?;
+    let heartbeat = state.shared.read().await.to_heartbeat();
+    Ok((
+        [(header::CONTENT_TYPE, "text/html; charset=utf-8")],
+        kiosk::render(&zones, &heartbeat),
+    )
+        .into_response())
+}
+
+/// Prometheus text exposition of hub metrics (valve command latency).
+async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    let mut body = state.shared.read().await.metrics.render();
+    metrics::render_db_retries(&mut body, state.db.retried_statements());
+    metrics::render_duplicate_readings(&mut body, state.db.duplicate_readings());
+    (
+        [(
+            header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        body,
+    )
+}
+
 async fn api_status(State(state): State<AppState>) -> impl IntoResponse {
-    let st = state.shared.read().await;
-    Json(st.to_status())
+    let status = state.status.load_full();
+    Json(status.as_ref()).into_response()
 }
 
-async fn api_health(State(state): State<AppState>) -> impl IntoResponse {
-    let st = state.shared.read().await;
-    let mqtt_ok = st.mqtt_connected;
-    drop(st);
+/// Events and zone/node changes after `since_seq`.  With `wait_sec`, waits
+/// for the next change when there is none yet.
+async fn api_status_changes(
+    State(state): State<AppState>,
+    Query(q): Query<ChangesQuery>,
+) -> Result<Json<StatusChanges>, ApiError> {
+    if q.wait_sec > MAX_CHANGES_WAIT_SEC {
+        return Err(ApiError::Validation(vec![format!(
+            "wait_sec must be at most {MAX_CHANGES_WAIT_SEC}"
+        )]));
+    }
+    let mut changes = {
+        let st = state.shared.read().await;
+        if q.wait_sec == 0 || st.change_seq() != q.since_seq {
+            return Ok(Json(st.changes_since(q.since_seq)));
+        }
+        st.watch_changes()
+    };
+    let wait = std::time::Duration::from_secs(q.wait_sec);
+    let _ = tokio::time::timeout(wait, changes.wait_for(|seq| *seq > q.since_seq)).await;
+    Ok(Json(state.shared.read().await.changes_since(q.since_seq)))
+}
+
+/// The running safety envelope: watchdog and grace periods, concurrency,
+/// per-zone daily caps and ingest limits, as resolved at startup.
+async fn api_limits(State(state): State<AppState>) -> Json<SafetyLimits> {
+    Json(state.shared.read().await.limits.clone())
+}
 
+/// Readiness probe: DB, MQTT, scheduler / watchdog liveness, last backup and
+/// task restarts.  503 unless every required component is OK.
+async fn api_health(State(state): State<AppState>) -> impl IntoResponse {
     let db_ok = state.db.health_check().await.is_ok();
+    let health = state.shared.read().await.to_health(db_ok);
 
-    let healthy = mqtt_ok && db_ok;
-    let status = if healthy {
+    let status = if health.status == "healthy" {
         StatusCode::OK
     } else {
         StatusCode::SERVICE_UNAVAILABLE
     };
 
-    (
-        status,
-        Json(serde_json::json!({
-            "status": if healthy { "healthy" } else { "degraded" },
-            "mqtt_connected": mqtt_ok,
-            "db_connected": db_ok,
-        })),
-    )
+    (status, Json(health))
 }
 
 // ---------------------------------------------------------------------------
 // Handlers — zones
 // ---------------------------------------------------------------------------
 
-async fn api_zones(State(state): State<AppState>) -> Result<Json<Vec<ZoneConfig>>, ApiError> {
-    state.db.load_zones().await.map(Json).map_err(internal)
+async fn api_zones(
+    State(state): State<AppState>,
+    Query(q): Query<ZonesQuery>,
+) -> Result<Json<Vec<ZoneView>>, ApiError> {
+    let zones = if q.include_archived {
+        state.db.load_all_zones().await
+    } else {
+        state.db.load_zones().await
+    }
+    .map_err(internal)?;
+    let mut odometers = state.db.list_odometers().await.map_err(internal)?;
+    Ok(Json(
+        zones
+            .into_iter()
+            .map(|config| ZoneView {
+                odometer: odometers.remove(&config.zone_id).unwrap_or_default(),
+                config,
+            })
+            .collect(),
+    ))
 }
 
 async fn api_get_zone(
     State(state): State<AppState>,
     Path(zone_id): Path<String>,
-) -> Result<Json<ZoneConfig>, ApiError> {
-    state
+) -> Result<Json<ZoneView>, ApiError> {
+    let config = state
+        .db
+        .get_zone(&zone_id)
+        .await
+        .map_err(internal)?
+        .ok_or_else(|| ApiError::NotFound(format!("zone '{zone_id}' not found")))?;
+    let odometer = state.db.get_odometer(&zone_id).await.map_err(internal)?;
+    Ok(Json(ZoneView { config, odometer }))
+}
+
+/// Record that a zone's valve was serviced: restarts its since-service
+/// counts and clears a pending service-due flag.
+async fn api_record_valve_service(
+    State(state): State<AppState>,
+    Path(zone_id): Path<String>,
+) -> Result<Json<ZoneOdometer>, ApiError> {
+    if state
         .db
         .get_zone(&zone_id)
         .await
         .map_err(internal)?
+        .is_none()
+    {
+        return Err(ApiError::NotFound(format!("zone '{zone_id}' not found")));
+    }
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    state
+        .db
+        .record_valve_service(&zone_id, now)
+        .await
+        .map_err(internal)?;
+    state
+        .shared
+        .write()
+        .await
+        .record_maintenance(format!("zone {zone_id}: valve service recorded"));
+    state
+        .db
+        .get_odometer(&zone_id)
+        .await
         .map(Json)
-        .ok_or_else(|| ApiError::NotFound(format!("zone '{zone_id}' not found")))
+        .map_err(internal)
 }
 
 async fn api_upsert_zone(
     State(state): State<AppState>,
+    caller: Option<Extension<Identity>>,
     Path(zone_id): Path<String>,
     Json(payload): Json<ZonePayload>,
 ) -> Result<Json<ZoneConfig>, ApiError> {
     validate_zone(&payload)?;
+    validate_dependencies(&state, &zone_id, &payload.after).await?;
 
     let config = ZoneConfig {
         zone_id,
@@ -332,29 +1067,469 @@ async fn api_upsert_zone(
         max_pulses_per_day: payload.max_pulses_per_day,
         stale_timeout_min: payload.stale_timeout_min,
         valve_gpio_pin: payload.valve_gpio_pin,
+        flow_lpm: payload.flow_lpm,
+        strategy: payload.strategy,
+        priority: payload.priority,
+        valve: payload.valve,
+        after: payload.after,
+        aggregation: payload.aggregation,
+        archived_at: None,
+        notes: payload.notes,
+        location: payload.location,
+        plant_type: payload.plant_type,
+        emitter_flow_lph: payload.emitter_flow_lph,
     };
 
     state.db.upsert_zone(&config).await.map_err(internal)?;
-    Ok(Json(config))
+    record_config_version(
+        &state,
+        &caller,
+        &format!("zone '{}' updated", config.zone_id),
+    )
+    .await;
+    // Re-read so an archived zone reports its archive timestamp.
+    let stored = state
+        .db
+        .get_zone(&config.zone_id)
+        .await
+        .map_err(internal)?
+        .unwrap_or(config);
+    Ok(Json(stored))
+}
+
+/// Copy a zone's settings to a new zone.  Sensors are not copied.
+async fn api_clone_zone(
+    State(state): State<AppState>,
+    caller: Option<Extension<Identity>>,
+    Path(source_id): Path<String>,
+    Json(payload): Json<ClonePayload>,
+) -> Result<impl IntoResponse, ApiError> {
+    let source = state
+        .db
+        .get_zone(&source_id)
+        .await
+        .map_err(internal)?
+        .ok_or_else(|| ApiError::NotFound(format!("zone '{source_id}' not found")))?;
+    let zone_id = payload.zone_id.trim().to_string();
+    if zone_id.is_empty() {
+        return Err(ApiError::Validation(vec![
+            "zone_id must not be empty".into()
+        ]));
+    }
+    if state
+        .db
+        .get_zone(&zone_id)
+        .await
+        .map_err(internal)?
+        .is_some()
+    {
+        return Err(ApiError::Conflict(format!(
+            "zone '{zone_id}' already exists"
+        )));
+    }
+
+    let config = ZoneConfig {
+        zone_id: zone_id.clone(),
+        name: payload
+            .name
+            .unwrap_or_else(|| format!("{} (copy)", source.name)),
+        valve_gpio_pin: payload.valve_gpio_pin.unwrap_or(source.valve_gpio_pin),
+        archived_at: None,
+        ..source
+    };
+    validate_with_stored(&state, vec![ZoneEntry::from(&config)], Vec::new()).await?;
+
+    state.db.upsert_zone(&config).await.map_err(internal)?;
+    record_config_version(
+        &state,
+        &caller,
+        &format!("zone '{zone_id}' cloned from '{source_id}'"),
+    )
+    .await;
+    Ok((StatusCode::CREATED, Json(config)))
+}
+
+/// The stored zones and sensors with `zones` and `sensors` added (or
+/// replacing those with the same id), checked as `config.toml` would be.
+async fn validate_with_stored(
+    state: &AppState,
+    zones: Vec<ZoneEntry>,
+    sensors: Vec<SensorEntry>,
+) -> Result<Config, ApiError> {
+    let mut merged_zones: Vec<ZoneEntry> = state
+        .db
+        .load_zones()
+        .await
+        .map_err(internal)?
+        .iter()
+        .filter(|z| !zones.iter().any(|n| n.zone_id == z.zone_id))
+        .map(ZoneEntry::from)
+        .collect();
+    merged_zones.extend(zones);
+    let mut merged_sensors: Vec<SensorEntry> = state
+        .db
+        .load_sensors()
+        .await
+        .map_err(internal)?
+        .iter()
+        .filter(|s| !sensors.iter().any(|n| n.sensor_id == s.sensor_id))
+        .map(SensorEntry::from)
+        .collect();
+    merged_sensors.extend(sensors);
+
+    let (mode, gpio) = {
+        let st = state.shared.read().await;
+        let mode = if st.mode == "monitor" {
+            OperationMode::Monitor
+        } else {
+            OperationMode::Auto
+        };
+        (mode, st.limits.gpio.clone())
+    };
+    let config = Config {
+        mode,
+        gpio,
+        zones: merged_zones,
+        sensors: merged_sensors,
+        ..Config::default()
+    };
+    let errs = config.errors();
+    if errs.is_empty() {
+        Ok(config)
+    } else {
+        Err(ApiError::Validation(errs))
+    }
+}
+
+/// Archive a zone: it leaves scheduling, the valve board and the default
+/// zone listing on the next restart, while its readings and watering
+/// history stay queryable.
+async fn api_archive_zone(
+    State(state): State<AppState>,
+    caller: Option<Extension<Identity>>,
+    Path(zone_id): Path<String>,
+) -> Result<Json<ZoneConfig>, ApiError> {
+    let zone = state
+        .db
+        .get_zone(&zone_id)
+        .await
+        .map_err(internal)?
+        .ok_or_else(|| ApiError::NotFound(format!("zone '{zone_id}' not found")))?;
+    if zone.archived_at.is_some() {
+        return Ok(Json(zone));
+    }
+    if state
+        .shared
+        .read()
+        .await
+        .zones
+        .get(&zone_id)
+        .is_some_and(|z| z.on)
+    {
+        return Err(ApiError::Conflict(format!(
+            "zone '{zone_id}' is watering; close its valve first"
+        )));
+    }
+
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    state
+        .db
+        .set_zone_archived(&zone_id, Some(now))
+        .await
+        .map_err(internal)?;
+    record_config_version(&state, &caller, &format!("zone '{zone_id}' archived")).await;
+    state
+        .shared
+        .write()
+        .await
+        .record_system(format!("zone {zone_id} archived"));
+    Ok(Json(ZoneConfig {
+        archived_at: Some(now),
+        ..zone
+    }))
+}
+
+async fn api_unarchive_zone(
+    State(state): State<AppState>,
+    caller: Option<Extension<Identity>>,
+    Path(zone_id): Path<String>,
+) -> Result<Json<ZoneConfig>, ApiError> {
+    if !state
+        .db
+        .set_zone_archived(&zone_id, None)
+        .await
+        .map_err(internal)?
+    {
+        return Err(ApiError::NotFound(format!("zone '{zone_id}' not found")));
+    }
+    record_config_version(&state, &caller, &format!("zone '{zone_id}' unarchived")).await;
+    state
+        .shared
+        .write()
+        .await
+        .record_system(format!("zone {zone_id} unarchived"));
+    state
+        .db
+        .get_zone(&zone_id)
+        .await
+        .map_err(internal)?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("zone '{zone_id}' not found")))
 }
 
 async fn api_delete_zone(
     State(state): State<AppState>,
+    caller: Option<Extension<Identity>>,
     Path(zone_id): Path<String>,
-) -> Result<StatusCode, ApiError> {
+    Query(q): Query<ConfirmQuery>,
+) -> Result<axum::response::Response, ApiError> {
+    if state.confirm.deletes {
+        if state
+            .db
+            .get_zone(&zone_id)
+            .await
+            .map_err(internal)?
+            .is_none()
+        {
+            return Err(ApiError::NotFound(format!("zone '{zone_id}' not found")));
+        }
+        let action = format!("DELETE zone {zone_id}");
+        if let Some(token) = confirm_step(&state, &action, q.confirm.as_deref()).await? {
+            return Ok(confirm_response(
+                ("zone_id", &zone_id),
+                token,
+                "repeat with ?confirm=<token> to delete the zone",
+            ));
+        }
+    }
     let deleted = state
         .db
         .delete_zone(&zone_id)
         .await
-        .map_err(db_delete_err)?;
+        .map_err(|e| match db_delete_err(e) {
+            ApiError::Conflict(msg) => ApiError::Conflict(format!(
+                "{msg}; archive the zone instead (POST /api/zones/{zone_id}/archive)"
+            )),
+            other => other,
+        })?;
 
     if deleted {
-        Ok(StatusCode::NO_CONTENT)
+        record_config_version(&state, &caller, &format!("zone '{zone_id}' deleted")).await;
+        Ok(StatusCode::NO_CONTENT.into_response())
     } else {
         Err(ApiError::NotFound(format!("zone '{zone_id}' not found")))
     }
 }
 
+// ---------------------------------------------------------------------------
+// Handlers — zone disturbances
+// ---------------------------------------------------------------------------
+
+async fn require_zone(state: &AppState, zone_id: &str) -> Result<(), ApiError> {
+    match state.db.get_zone(zone_id).await.map_err(internal)? {
+        Some(_) => Ok(()),
+        None => Err(ApiError::NotFound(format!("zone '{zone_id}' not found"))),
+    }
+}
+
+async fn api_disturbances(
+    State(state): State<AppState>,
+    Path(zone_id): Path<String>,
+) -> Result<Json<Vec<Disturbance>>, ApiError> {
+    require_zone(&state, &zone_id).await?;
+    state
+        .db
+        .list_disturbances(&zone_id)
+        .await
+        .map(Json)
+        .map_err(internal)
+}
+
+/// Flow trend over the lookback window, as used by the clogged-emitter
+/// alert.  `null` until the zone has enough flow-meter history.
+async fn api_zone_flow(
+    State(state): State<AppState>,
+    Path(zone_id): Path<String>,
+) -> Result<Json<Option<FlowTrend>>, ApiError> {
+    require_zone(&state, &zone_id).await?;
+    let since = OffsetDateTime::now_utc().unix_timestamp() - flow::LOOKBACK_DAYS * 86400;
+    let days = state
+        .db
+        .daily_flow(&zone_id, since)
+        .await
+        .map_err(internal)?;
+    Ok(Json(flow::analyze(days)))
+}
+
+/// Mark a time range as disturbed.  Readings inside it stay stored but are
+/// ignored by the scheduler and moisture analytics.
+async fn api_create_disturbance(
+    State(state): State<AppState>,
+    Path(zone_id): Path<String>,
+    Json(payload): Json<DisturbancePayload>,
+) -> Result<impl IntoResponse, ApiError> {
+    let start_ts = payload
+        .start_ts
+        .unwrap_or_else(|| OffsetDateTime::now_utc().unix_timestamp());
+    validate_disturbance(&payload, start_ts)?;
+    require_zone(&state, &zone_id).await?;
+
+    let id = state
+        .db
+        .insert_disturbance(&zone_id, start_ts, payload.end_ts, payload.reason.trim())
+        .await
+        .map_err(internal)?;
+    {
+        let mut st = state.shared.write().await;
+        st.moisture.invalidate();
+        st.record_system(format!(
+            "zone {zone_id}: disturbance recorded ({})",
+            payload.reason.trim()
+        ));
+    }
+
+    let created = state
+        .db
+        .get_disturbance(&zone_id, id)
+        .await
+        .map_err(internal)?
+        .ok_or_else(|| ApiError::Internal("disturbance vanished after insert".into()))?;
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+async fn api_update_disturbance(
+    State(state): State<AppState>,
+    Path((zone_id, id)): Path<(String, i64)>,
+    Json(payload): Json<DisturbancePayload>,
+) -> Result<Json<Disturbance>, ApiError> {
+    let existing = state
+        .db
+        .get_disturbance(&zone_id, id)
+        .await
+        .map_err(internal)?
+        .ok_or_else(|| {
+            ApiError::NotFound(format!("disturbance {id} not found for zone '{zone_id}'"))
+        })?;
+
+    let start_ts = payload.start_ts.unwrap_or(existing.start_ts);
+    validate_disturbance(&payload, start_ts)?;
+
+    let updated = Disturbance {
+        start_ts,
+        end_ts: payload.end_ts,
+        reason: payload.reason.trim().to_string(),
+        ..existing
+    };
+    state
+        .db
+        .update_disturbance(&updated)
+        .await
+        .map_err(internal)?;
+    state.shared.write().await.moisture.invalidate();
+    Ok(Json(updated))
+}
+
+async fn api_delete_disturbance(
+    State(state): State<AppState>,
+    Path((zone_id, id)): Path<(String, i64)>,
+) -> Result<StatusCode, ApiError> {
+    if state
+        .db
+        .delete_disturbance(&zone_id, id)
+        .await
+        .map_err(internal)?
+    {
+        state.shared.write().await.moisture.invalidate();
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::NotFound(format!(
+            "disturbance {id} not found for zone '{zone_id}'"
+        )))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Handlers — blackout calendar
+// ---------------------------------------------------------------------------
+
+/// Hand the scheduler the calendar as stored now.
+async fn reload_blackouts(state: &AppState, event: String) -> Result<(), ApiError> {
+    let rows = state.db.list_blackouts().await.map_err(internal)?;
+    let mut st = state.shared.write().await;
+    st.blackouts = Blackouts::new(rows);
+    st.record_system(event);
+    Ok(())
+}
+
+async fn api_blackouts(State(state): State<AppState>) -> Result<Json<Vec<Blackout>>, ApiError> {
+    state.db.list_blackouts().await.map(Json).map_err(internal)
+}
+
+async fn api_create_blackout(
+    State(state): State<AppState>,
+    Json(payload): Json<BlackoutPayload>,
+) -> Result<impl IntoResponse, ApiError> {
+    validate_blackout(&payload)?;
+    let created = Blackout {
+        id: 0,
+        start: payload.start.trim().to_string(),
+        end: payload.end.trim().to_string(),
+        reason: payload.reason.trim().to_string(),
+    };
+    let id = state
+        .db
+        .insert_blackout(&created.start, &created.end, &created.reason)
+        .await
+        .map_err(internal)?;
+    let created = Blackout { id, ..created };
+    reload_blackouts(
+        &state,
+        format!(
+            "blackout added: {} to {} ({})",
+            created.start, created.end, created.reason
+        ),
+    )
+    .await?;
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+async fn api_update_blackout(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(payload): Json<BlackoutPayload>,
+) -> Result<Json<Blackout>, ApiError> {
+    validate_blackout(&payload)?;
+    let updated = Blackout {
+        id,
+        start: payload.start.trim().to_string(),
+        end: payload.end.trim().to_string(),
+        reason: payload.reason.trim().to_string(),
+    };
+    if !state.db.update_blackout(&updated).await.map_err(internal)? {
+        return Err(ApiError::NotFound(format!("blackout {id} not found")));
+    }
+    reload_blackouts(
+        &state,
+        format!(
+            "blackout {id} changed: {} to {} ({})",
+            updated.start, updated.end, updated.reason
+        ),
+    )
+    .await?;
+    Ok(Json(updated))
+}
+
+async fn api_delete_blackout(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, ApiError> {
+    if !state.db.delete_blackout(id).await.map_err(internal)? {
+        return Err(ApiError::NotFound(format!("blackout {id} not found")));
+    }
+    reload_blackouts(&state, format!("blackout {id} removed")).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 // ---------------------------------------------------------------------------
 // Handlers — sensors
 // ---------------------------------------------------------------------------
@@ -378,6 +1553,7 @@ async fn api_get_sensor(
 
 async fn api_upsert_sensor(
     State(state): State<AppState>,
+    caller: Option<Extension<Identity>>,
     Path(sensor_id): Path<String>,
     Json(payload): Json<SensorPayload>,
 ) -> Result<Json<SensorConfig>, ApiError> {
@@ -388,11 +1564,20 @@ async fn api_upsert_sensor(
         .get_zone(&payload.zone_id)
         .await
         .map_err(internal)?;
-    if zone.is_none() {
-        return Err(ApiError::Validation(vec![format!(
-            "zone '{}' does not exist",
-            payload.zone_id
-        )]));
+    match zone {
+        None => {
+            return Err(ApiError::Validation(vec![format!(
+                "zone '{}' does not exist",
+                payload.zone_id
+            )]));
+        }
+        Some(z) if z.archived_at.is_some() => {
+            return Err(ApiError::Validation(vec![format!(
+                "zone '{}' is archived",
+                payload.zone_id
+            )]));
+        }
+        Some(_) => {}
     }
 
     let config = SensorConfig {
@@ -401,16 +1586,60 @@ async fn api_upsert_sensor(
         zone_id: payload.zone_id,
         raw_dry: payload.raw_dry,
         raw_wet: payload.raw_wet,
+        channel: payload.channel,
+        archived_at: None,
+        weight: payload.weight,
+        failure_margin: payload.failure_margin,
+        failure_margin_pct: payload.failure_margin_pct,
+        curve: payload.curve,
+        enabled: payload.enabled,
     };
 
     state.db.upsert_sensor(&config).await.map_err(internal)?;
-    Ok(Json(config))
+    record_config_version(
+        &state,
+        &caller,
+        &format!("sensor '{}' updated", config.sensor_id),
+    )
+    .await;
+    state.node_settings.notify_one();
+    // Re-read so an archived sensor reports its archive timestamp.
+    let stored = state
+        .db
+        .get_sensor(&config.sensor_id)
+        .await
+        .map_err(internal)?
+        .unwrap_or(config);
+    Ok(Json(stored))
 }
 
 async fn api_delete_sensor(
     State(state): State<AppState>,
+    caller: Option<Extension<Identity>>,
     Path(sensor_id): Path<String>,
-) -> Result<StatusCode, ApiError> {
+    Query(q): Query<ConfirmQuery>,
+) -> Result<axum::response::Response, ApiError> {
+    if state.confirm.deletes {
+        if state
+            .db
+            .get_sensor(&sensor_id)
+            .await
+            .map_err(internal)?
+            .is_none()
+        {
+            return Err(ApiError::NotFound(format!(
+                "sensor '{sensor_id}' not found"
+            )));
+        }
+        let action = format!("DELETE sensor {sensor_id}");
+        if let Some(token) = confirm_step(&state, &action, q.confirm.as_deref()).await? {
+            return Ok(confirm_response(
+                ("sensor_id", &sensor_id),
+                token,
+                "repeat with ?confirm=<token> to delete the sensor",
+            ));
+        }
+    }
     let deleted = state
         .db
         .delete_sensor(&sensor_id)
@@ -418,7 +1647,9 @@ async fn api_delete_sensor(
         .map_err(db_delete_err)?;
 
     if deleted {
-        Ok(StatusCode::NO_CONTENT)
+        record_config_version(&state, &caller, &format!("sensor '{sensor_id}' deleted")).await;
+        state.node_settings.notify_one();
+        Ok(StatusCode::NO_CONTENT.into_response())
     } else {
         Err(ApiError::NotFound(format!(
             "sensor '{sensor_id}' not found"
@@ -426,530 +1657,5015 @@ async fn api_delete_sensor(
     }
 }
 
-// ---------------------------------------------------------------------------
-// Handlers — readings (read-only)
-// ---------------------------------------------------------------------------
-
-async fn api_readings(
+/// Implausible-reading counters and quarantine state per sensor.
+async fn api_sensor_health(
     State(state): State<AppState>,
-    Query(q): Query<ReadingsQuery>,
-) -> Result<impl IntoResponse, ApiError> {
-    let limit = q.limit.unwrap_or(100).clamp(1, 1000);
-    let offset = q.offset.unwrap_or(0).max(0);
-
-    let rows = state
+) -> Result<Json<Vec<SensorHealth>>, ApiError> {
+    state
         .db
-        .list_readings(q.sensor_id.as_deref(), q.zone_id.as_deref(), limit, offset)
+        .list_sensor_health()
         .await
-        .map_err(internal)?;
-
-    Ok(Json(rows))
+        .map(Json)
+        .map_err(internal)
 }
 
-// ---------------------------------------------------------------------------
-// Handlers — watering events (read-only)
-// ---------------------------------------------------------------------------
+/// Longest range, in days, of an hourly and of a daily trend.
+const MAX_HOURLY_TREND_DAYS: i64 = 92;
+const MAX_DAILY_TREND_DAYS: i64 = 3660;
 
-async fn api_watering_events(
+/// A sensor's moisture per hour or day, including periods whose raw
+/// readings have been pruned.  `from` defaults to a week (hourly) or a year
+/// (daily) before `to`, which defaults to now.
+async fn api_sensor_trend(
     State(state): State<AppState>,
-    Query(q): Query<EventsQuery>,
-) -> Result<impl IntoResponse, ApiError> {
-    let limit = q.limit.unwrap_or(100).clamp(1, 1000);
-    let offset = q.offset.unwrap_or(0).max(0);
+    Path(sensor_id): Path<String>,
+    Query(q): Query<TrendQuery>,
+) -> Result<Json<Vec<MoistureBucket>>, ApiError> {
+    let (bucket_sec, default_days, max_days) = match q.resolution.as_deref() {
+        None | Some("hour") => (3600, 7, MAX_HOURLY_TREND_DAYS),
+        Some("day") => (86400, 365, MAX_DAILY_TREND_DAYS),
+        Some(other) => {
+            return Err(ApiError::Validation(vec![format!(
+                "resolution must be 'hour' or 'day', got '{other}'"
+            )]))
+        }
+    };
+    let to =
+        q.to.unwrap_or_else(|| OffsetDateTime::now_utc().unix_timestamp());
+    let from = q.from.unwrap_or(to - default_days * 86400);
+    if from >= to || to - from > max_days * 86400 {
+        return Err(ApiError::Validation(vec![format!(
+            "from must be before to, at most {max_days} days apart"
+        )]));
+    }
+    state
+        .db
+        .sensor_moisture_trend(&sensor_id, bucket_sec, from, to)
+        .await
+        .map(Json)
+        .map_err(internal)
+}
 
-    let rows = state
+/// Rewrite a sensor's stored moisture from its raw readings with the
+/// current calibration, e.g. after correcting `raw_dry` / `raw_wet`.
+async fn api_recompute_moisture(
+    State(state): State<AppState>,
+    Path(sensor_id): Path<String>,
+    Query(q): Query<RecomputeQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let sensor = state
         .db
-        .list_watering_events(q.zone_id.as_deref(), limit, offset)
+        .get_sensor(&sensor_id)
+        .await
+        .map_err(internal)?
+        .ok_or_else(|| ApiError::NotFound(format!("sensor '{sensor_id}' not found")))?;
+    let from = q.from.unwrap_or(0);
+    let updated = state
+        .db
+        .recompute_moisture(&sensor, from)
         .await
         .map_err(internal)?;
-
-    Ok(Json(rows))
+    state.shared.write().await.record_system(format!(
+        "sensor {sensor_id}: moisture recomputed for {updated} readings"
+    ));
+    Ok(Json(serde_json::json!({
+        "sensor_id": sensor_id,
+        "from": from,
+        "updated": updated,
+    })))
 }
 
-// ---------------------------------------------------------------------------
-// Handlers — daily counters (read-only)
-// ---------------------------------------------------------------------------
-
-async fn api_counters(
+/// Release a quarantined sensor back into zone moisture.
+async fn api_release_sensor(
     State(state): State<AppState>,
-    Path(zone_id): Path<String>,
-    Query(q): Query<CountersQuery>,
-) -> Result<impl IntoResponse, ApiError> {
-    let day = q.day.unwrap_or_else(Db::today_yyyy_mm_dd);
-    let counters = state
+    Path(sensor_id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    if !state
         .db
-        .get_daily_counters(&day, &zone_id)
+        .release_sensor(&sensor_id)
         .await
-        .map_err(internal)?;
-    Ok(Json(counters))
+        .map_err(internal)?
+    {
+        return Err(ApiError::NotFound(format!(
+            "sensor '{sensor_id}' is not quarantined"
+        )));
+    }
+    let mut st = state.shared.write().await;
+    st.set_sensor_quarantined(&sensor_id, false);
+    st.record_system(format!("sensor {sensor_id} released from quarantine"));
+    Ok(StatusCode::NO_CONTENT)
 }
 
 // ---------------------------------------------------------------------------
-// Server entry-point
+// Handlers — nodes
 // ---------------------------------------------------------------------------
 
-pub async fn serve(shared: SharedState, db: Db) {
-    let port: u16 = env::var("WEB_PORT")
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(8080);
+/// A node's readings over HTTP, for nodes that can't speak MQTT.  The
+/// body is the `tele/<node_id>/reading` JSON; the node is the one whose
+/// `NODE_TOKENS` token is the bearer token.  Accepted readings go through
+/// the same ingest as MQTT telemetry.
+async fn api_telemetry(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> axum::response::Response {
+    let Some(node_id) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| state.node_tokens.node_for(v))
+        .map(str::to_string)
+    else {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({"error": "unauthorized", "message": "invalid or missing node token"})),
+        )
+            .into_response();
+    };
+    let msg = match crate::mqtt::parse_telemetry(&body) {
+        Ok(msg) => msg,
+        Err(reject) => {
+            tracing::warn!(node = %node_id, "http telemetry rejected: {reject}");
+            state.shared.write().await.record_reject(&node_id, &reject);
+            return ApiError::Validation(vec![reject.to_string()]).into_response();
+        }
+    };
+    if state
+        .telemetry
+        .send((node_id.clone(), body.to_vec()))
+        .await
+        .is_err()
+    {
+        return internal(anyhow::anyhow!("telemetry ingest is not running")).into_response();
+    }
+    (
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "node_id": node_id,
+            "readings": msg.readings.len(),
+        })),
+    )
+        .into_response()
+}
 
-    let bind: IpAddr = env::var("WEB_BIND")
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or_else(|| IpAddr::from([127, 0, 0, 1]));
+async fn api_nodes(State(state): State<AppState>) -> Result<Json<Vec<NodeView>>, ApiError> {
+    let nodes = state.db.load_nodes().await.map_err(internal)?;
+    let st = state.shared.read().await;
+    Ok(Json(
+        nodes
+            .into_iter()
+            .map(|config| NodeView {
+                firmware: st
+                    .nodes
+                    .get(&config.node_id)
+                    .and_then(|n| n.firmware.clone()),
+                config,
+            })
+            .collect(),
+    ))
+}
 
-    let addr = SocketAddr::new(bind, port);
-    let state = AppState { shared, db };
-    let app = router(state);
+async fn api_get_node(
+    State(state): State<AppState>,
+    Path(node_id): Path<String>,
+) -> Result<Json<NodeDiagnostics>, ApiError> {
+    let config = state.db.get_node(&node_id).await.map_err(internal)?;
+    let sensor_cfgs = state
+        .db
+        .sensors_for_node(&node_id)
+        .await
+        .map_err(internal)?;
 
-    let tls_cert = env::var("TLS_CERT").ok().filter(|s| !s.is_empty());
-    let tls_key = env::var("TLS_KEY").ok().filter(|s| !s.is_empty());
+    let (live, default_timeout) = {
+        let st = state.shared.read().await;
+        (st.nodes.get(&node_id).cloned(), st.node_stale_timeout_min)
+    };
 
-    match (&tls_cert, &tls_key) {
-        (Some(_), None) | (None, Some(_)) => {
-            tracing::error!(
-                "both TLS_CERT and TLS_KEY must be set for HTTPS (only one was provided)"
-            );
-        }
-        (Some(_), Some(_)) => {
-            #[cfg(feature = "tls")]
-            {
-                serve_https(
-                    addr,
-                    app,
-                    tls_cert.as_deref().unwrap(),
-                    tls_key.as_deref().unwrap(),
-                )
-                .await;
-            }
-            #[cfg(not(feature = "tls"))]
-            {
-                tracing::error!(
-                    "TLS_CERT and TLS_KEY are set but the 'tls' feature is not enabled — \
-                     rebuild with `--features tls` or remove TLS_CERT/TLS_KEY to use plain HTTP"
-                );
-            }
-        }
-        (None, None) => {
-            serve_http(addr, app).await;
-        }
+    if config.is_none() && sensor_cfgs.is_empty() && live.is_none() {
+        return Err(ApiError::NotFound(format!("node '{node_id}' not found")));
+    }
+
+    let mut sensors = Vec::with_capacity(sensor_cfgs.len());
+    for sensor in sensor_cfgs {
+        let latest = state
+            .db
+            .latest_sensor_reading(&sensor.sensor_id)
+            .await
+            .map_err(internal)?;
+        let plausible = latest.as_ref().map(|r| sensor.is_plausible(r.raw));
+        sensors.push(SensorDiagnostics {
+            sensor,
+            latest,
+            plausible,
+        });
     }
+
+    let stale_timeout_min = config
+        .as_ref()
+        .and_then(|c| c.stale_timeout_min)
+        .unwrap_or(default_timeout);
+    let stale_after_sec = StalePolicy::for_node(config.as_ref(), default_timeout).threshold_sec();
+    let last_seen = live.as_ref().map(|n| n.last_seen);
+    let last_seen_age_sec = last_seen.map(|t| (OffsetDateTime::now_utc() - t).whole_seconds());
+    let decommissioned = config
+        .as_ref()
+        .is_some_and(|c| c.decommissioned_at.is_some());
+    let stale = !decommissioned && last_seen_age_sec.is_none_or(|age| age > stale_after_sec);
+
+    Ok(Json(NodeDiagnostics {
+        node_id,
+        config,
+        online: live.as_ref().is_some_and(|n| n.online),
+        last_seen,
+        last_seen_age_sec,
+        stale_timeout_min,
+        stale_after_sec,
+        stale,
+        firmware: live.as_ref().and_then(|n| n.firmware.clone()),
+        sensors,
+    }))
 }
 
-/// Serve plain HTTP. Warns loudly if binding to a non-loopback address,
-/// because `API_TOKEN` would be sent in cleartext.
-async fn serve_http(addr: SocketAddr, app: Router) {
-    if !addr.ip().is_loopback() {
-        tracing::warn!(
-            %addr,
-            "binding WITHOUT TLS on a non-loopback address — API_TOKEN sent in cleartext! \
-             Set TLS_CERT + TLS_KEY for HTTPS, or bind to 127.0.0.1 behind a reverse proxy."
-        );
+async fn api_upsert_node(
+    State(state): State<AppState>,
+    caller: Option<Extension<Identity>>,
+    Path(node_id): Path<String>,
+    Json(payload): Json<NodePayload>,
+) -> Result<Json<NodeConfig>, ApiError> {
+    validate_node(&payload)?;
+
+    let config = NodeConfig {
+        node_id,
+        name: payload.name,
+        sample_interval_sec: payload.sample_interval_sec,
+        stale_timeout_min: payload.stale_timeout_min,
+        battery_powered: payload.battery_powered,
+        wake_grace_factor: payload.wake_grace_factor,
+        decommissioned_at: None,
+    };
+
+    state.db.upsert_node(&config).await.map_err(internal)?;
+    record_config_version(
+        &state,
+        &caller,
+        &format!("node '{}' updated", config.node_id),
+    )
+    .await;
+    state.node_settings.notify_one();
+    // Re-read so a decommissioned node keeps reporting its timestamp.
+    let stored = state
+        .db
+        .get_node(&config.node_id)
+        .await
+        .map_err(internal)?
+        .unwrap_or(config);
+    Ok(Json(stored))
+}
+
+async fn api_node_sensors(
+    State(state): State<AppState>,
+    Path(node_id): Path<String>,
+) -> Result<Json<Vec<SensorConfig>>, ApiError> {
+    state
+        .db
+        .sensors_for_node(&node_id)
+        .await
+        .map(Json)
+        .map_err(internal)
+}
+
+/// Retire a node: stamp it decommissioned, archive its sensors (history is
+/// kept), and drop it from the live dashboard.  Telemetry from archived
+/// sensors is ignored once the hub reloads its sensor map on restart.
+async fn api_decommission_node(
+    State(state): State<AppState>,
+    caller: Option<Extension<Identity>>,
+    Path(node_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let known = state
+        .db
+        .get_node(&node_id)
+        .await
+        .map_err(internal)?
+        .is_some()
+        || !state
+            .db
+            .sensors_for_node(&node_id)
+            .await
+            .map_err(internal)?
+            .is_empty();
+    if !known {
+        return Err(ApiError::NotFound(format!("node '{node_id}' not found")));
     }
 
-    let listener = match TcpListener::bind(addr).await {
-        Ok(l) => l,
-        Err(e) => {
-            tracing::error!("failed to bind web port {addr}: {e}");
-            return;
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let archived = state
+        .db
+        .decommission_node(&node_id, now)
+        .await
+        .map_err(internal)?;
+    record_config_version(&state, &caller, &format!("node '{node_id}' decommissioned")).await;
+    state.node_settings.notify_one();
+
+    {
+        let mut st = state.shared.write().await;
+        st.nodes.remove(&node_id);
+        st.record_system(format!(
+            "node {node_id} decommissioned ({archived} sensors archived)"
+        ));
+    }
+
+    let node = state.db.get_node(&node_id).await.map_err(internal)?;
+    Ok(Json(serde_json::json!({
+        "node": node,
+        "archived_sensors": archived,
+    })))
+}
+
+#[derive(Deserialize)]
+struct SendLogsQuery {
+    lines: Option<usize>,
+}
+
+/// Lines requested when `?lines` is omitted; the node caps requests at
+/// [`MAX_NODE_LOG_LINES`](crate::mqtt::MAX_NODE_LOG_LINES).
+const DEFAULT_NODE_LOG_LINES: usize = 50;
+
+/// Queue a diagnostics command for a node the hub knows about.  Commands
+/// aren't retained, so the node must be connected to receive them.
+async fn send_node_command(
+    state: &AppState,
+    node_id: &str,
+    command: NodeCommand,
+) -> Result<(), ApiError> {
+    ensure_node_reachable(state, node_id).await?;
+    state
+        .node_commands
+        .send((node_id.to_string(), command))
+        .await
+        .map_err(|_| internal(anyhow::anyhow!("node command publisher is not running")))?;
+    tracing::info!(node = %node_id, command = command.as_str(), "node command requested");
+    Ok(())
+}
+
+/// 404 for a node the hub has never heard of, 409 while MQTT is down.
+async fn ensure_node_reachable(state: &AppState, node_id: &str) -> Result<(), ApiError> {
+    let known = state.shared.read().await.nodes.contains_key(node_id)
+        || state
+            .db
+            .get_node(node_id)
+            .await
+            .map_err(internal)?
+            .is_some()
+        || !state
+            .db
+            .sensors_for_node(node_id)
+            .await
+            .map_err(internal)?
+            .is_empty();
+    if !known {
+        return Err(ApiError::NotFound(format!("node '{node_id}' not found")));
+    }
+    if !state.shared.read().await.mqtt_connected {
+        return Err(ApiError::Conflict(
+            "hub is not connected to MQTT".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Ask a node to restart (it exits and systemd starts it again).
+async fn api_restart_node(
+    State(state): State<AppState>,
+    Path(node_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    send_node_command(&state, &node_id, NodeCommand::Restart).await?;
+    state
+        .shared
+        .write()
+        .await
+        .record_system(format!("restart requested for node {node_id}"));
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({ "node_id": node_id, "command": "restart" })),
+    ))
+}
+
+/// Ask a node for its last `?lines` log lines; they appear at
+/// `GET /api/nodes/{node_id}/logs` once the node answers.
+async fn api_request_node_logs(
+    State(state): State<AppState>,
+    Path(node_id): Path<String>,
+    Query(q): Query<SendLogsQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let lines = q.lines.unwrap_or(DEFAULT_NODE_LOG_LINES);
+    if !(1..=crate::mqtt::MAX_NODE_LOG_LINES).contains(&lines) {
+        return Err(ApiError::Validation(vec![format!(
+            "lines must be between 1 and {}",
+            crate::mqtt::MAX_NODE_LOG_LINES
+        )]));
+    }
+    send_node_command(&state, &node_id, NodeCommand::SendLogs { lines }).await?;
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({ "node_id": node_id, "command": "send-logs", "lines": lines })),
+    ))
+}
+
+/// How long `POST /api/nodes/{node_id}/ping` waits for the answer.
+const PING_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[derive(Serialize)]
+struct PingResult {
+    node_id: String,
+    /// Whether the node answered within [`PING_TIMEOUT`].
+    answered: bool,
+    /// The answer, with the node's uptime and sampling state.
+    pong: Option<NodePong>,
+}
+
+/// Ping a node and wait briefly for its answer, telling a dead node from
+/// one that is alive but samples rarely.
+async fn api_ping_node(
+    State(state): State<AppState>,
+    Path(node_id): Path<String>,
+) -> Result<Json<PingResult>, ApiError> {
+    let sent_ms = (time::OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000) as i64;
+    send_node_command(&state, &node_id, NodeCommand::Ping { sent_ms }).await?;
+    let deadline = tokio::time::Instant::now() + PING_TIMEOUT;
+    let pong = loop {
+        let pong = state.shared.read().await.node_pongs.get(&node_id).cloned();
+        if let Some(pong) = pong.filter(|p| p.sent_ms == Some(sent_ms)) {
+            break Some(pong);
         }
+        if tokio::time::Instant::now() >= deadline {
+            break None;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
     };
+    Ok(Json(PingResult {
+        node_id,
+        answered: pong.is_some(),
+        pong,
+    }))
+}
 
-    tracing::info!("web ui listening on http://{addr}");
+/// The last log lines received from a node (kept in memory only).
+async fn api_node_logs(
+    State(state): State<AppState>,
+    Path(node_id): Path<String>,
+) -> Result<Json<NodeLogs>, ApiError> {
+    state
+        .shared
+        .read()
+        .await
+        .node_logs
+        .get(&node_id)
+        .cloned()
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("no logs received from node '{node_id}'")))
+}
 
-    if let Err(e) = axum::serve(listener, app).await {
-        tracing::error!("web server error: {e}");
+/// Air temperature and humidity from the node's climate sensor, oldest
+/// first.
+async fn api_node_climate(
+    State(state): State<AppState>,
+    Path(node_id): Path<String>,
+    Query(q): Query<ClimateQuery>,
+) -> Result<Json<Vec<ClimateReading>>, ApiError> {
+    let hours = q.hours.unwrap_or(24);
+    if !(1..=MAX_CLIMATE_HOURS).contains(&hours) {
+        return Err(ApiError::Validation(vec![format!(
+            "hours must be 1–{MAX_CLIMATE_HOURS}, got {hours}"
+        )]));
     }
+    let since = OffsetDateTime::now_utc().unix_timestamp() - hours * 3600;
+    state
+        .db
+        .climate_readings_since(&node_id, since)
+        .await
+        .map(Json)
+        .map_err(internal)
 }
 
-/// Serve HTTPS using `axum-server` with `rustls`.
-#[cfg(feature = "tls")]
-async fn serve_https(addr: SocketAddr, app: Router, cert_path: &str, key_path: &str) {
-    use axum_server::tls_rustls::RustlsConfig;
+// ---------------------------------------------------------------------------
+// Handlers — OTA
+// ---------------------------------------------------------------------------
 
-    let config = match RustlsConfig::from_pem_file(cert_path, key_path).await {
-        Ok(c) => c,
-        Err(e) => {
-            tracing::error!(cert = %cert_path, key = %key_path, "failed to load TLS cert/key: {e}");
-            return;
+#[derive(Serialize)]
+struct OtaOverview {
+    enabled: bool,
+    images: Vec<OtaImage>,
+    nodes: BTreeMap<String, NodeOta>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NodeUpdatePayload {
+    version: String,
+}
+
+/// Signed images on offer and each node's update progress.
+async fn api_ota(State(state): State<AppState>) -> Result<Json<OtaOverview>, ApiError> {
+    let images = match state.ota.config.resolved() {
+        Some((dir, _)) => ota::list_images(std::path::Path::new(dir)).map_err(internal)?,
+        None => Vec::new(),
+    };
+    let nodes = state
+        .shared
+        .read()
+        .await
+        .node_ota
+        .iter()
+        .map(|(id, node)| (id.clone(), node.clone()))
+        .collect();
+    Ok(Json(OtaOverview {
+        enabled: state.ota.config.resolved().is_some(),
+        images,
+        nodes,
+    }))
+}
+
+/// Announce a signed image to a node; it downloads, verifies and installs
+/// it, then restarts.  Progress shows up in `GET /api/ota`.
+async fn api_update_node(
+    State(state): State<AppState>,
+    Path(node_id): Path<String>,
+    Json(body): Json<NodeUpdatePayload>,
+) -> Result<impl IntoResponse, ApiError> {
+    let Some((dir, base_url)) = state.ota.config.resolved() else {
+        return Err(ApiError::Conflict(
+            "OTA updates are not configured ([ota] in config.toml)".to_string(),
+        ));
+    };
+    let image = ota::load_image(std::path::Path::new(dir), &body.version)
+        .map_err(|e| ApiError::Validation(vec![format!("{e:#}")]))?
+        .ok_or_else(|| {
+            ApiError::Validation(vec![format!(
+                "no image for version '{}' in {dir}",
+                body.version
+            )])
+        })?;
+    ensure_node_reachable(&state, &node_id).await?;
+    let announcement = ota::Announcement::new(&image, base_url);
+    state
+        .ota
+        .announcements
+        .send((node_id.clone(), Some(announcement.clone())))
+        .await
+        .map_err(|_| internal(anyhow::anyhow!("OTA announcer is not running")))?;
+    {
+        let mut st = state.shared.write().await;
+        st.node_ota.entry(node_id.clone()).or_default().pending = Some(image.version.clone());
+        st.record_system(format!(
+            "update to {} announced to node {node_id}",
+            image.version
+        ));
+    }
+    Ok((StatusCode::ACCEPTED, Json(announcement)))
+}
+
+/// Withdraw a node's pending update announcement.
+async fn api_cancel_node_update(
+    State(state): State<AppState>,
+    Path(node_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    ensure_node_reachable(&state, &node_id).await?;
+    state
+        .ota
+        .announcements
+        .send((node_id.clone(), None))
+        .await
+        .map_err(|_| internal(anyhow::anyhow!("OTA announcer is not running")))?;
+    let cancelled = {
+        let mut st = state.shared.write().await;
+        let cancelled = st
+            .node_ota
+            .get_mut(&node_id)
+            .and_then(|node| node.pending.take());
+        if let Some(version) = &cancelled {
+            st.record_system(format!("update to {version} withdrawn for node {node_id}"));
         }
+        cancelled
     };
+    Ok(Json(
+        serde_json::json!({ "node_id": node_id, "cancelled": cancelled }),
+    ))
+}
 
-    tracing::info!("web ui listening on https://{addr}");
+/// A node image, for the node that was told where to find it.  Served
+/// without auth: nodes hold no API token, and the signature vouches for
+/// the bytes.
+async fn ota_image(
+    State(state): State<AppState>,
+    Path(file): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let not_found = || ApiError::NotFound(format!("no OTA image '{file}'"));
+    let Some((dir, _)) = state.ota.config.resolved() else {
+        return Err(not_found());
+    };
+    if ota::image_version(&file).is_none() {
+        return Err(not_found());
+    }
+    match tokio::fs::read(std::path::Path::new(dir).join(&file)).await {
+        Ok(bytes) => Ok(([(header::CONTENT_TYPE, "application/octet-stream")], bytes)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(not_found()),
+        Err(e) => Err(internal(e.into())),
+    }
+}
 
-    if let Err(e) = axum_server::bind_rustls(addr, config)
-        .serve(app.into_make_service())
+// ---------------------------------------------------------------------------
+// Handlers — config versions
+// ---------------------------------------------------------------------------
+
+/// Snapshot the configuration after a successful change.  A failed snapshot
+/// is logged but doesn't fail the change itself.  Also has the scheduler
+/// reload its moisture window, which tracks sensor zones and weights.
+/// Name of the token a request was made with, for the audit log.
+fn actor(caller: &Option<Extension<Identity>>) -> Option<&str> {
+    caller.as_ref().map(|c| c.name.as_str())
+}
+
+async fn record_config_version(
+    state: &AppState,
+    caller: &Option<Extension<Identity>>,
+    reason: &str,
+) {
+    state.shared.write().await.moisture.invalidate();
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    if let Err(e) = state
+        .db
+        .record_config_version(now, reason, AuditSource::Api, actor(caller))
         .await
     {
-        tracing::error!("web server error: {e}");
+        tracing::warn!("config snapshot failed: {e:#}");
     }
 }
 
-// ===========================================================================
-// Tests
-// ===========================================================================
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::db::Db;
-    use crate::state::SystemState;
-    use axum::body::Body;
-    use axum::http::{Request, StatusCode};
-    use http_body_util::BodyExt;
-    use std::sync::Arc;
-    use tokio::sync::RwLock;
-    use tower::ServiceExt; // for `oneshot`
+async fn api_config_versions(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<ConfigVersion>>, ApiError> {
+    state
+        .db
+        .list_config_versions()
+        .await
+        .map(Json)
+        .map_err(internal)
+}
 
-    // -----------------------------------------------------------------------
-    // Helpers
-    // -----------------------------------------------------------------------
+async fn api_config_version(
+    State(state): State<AppState>,
+    Path(version): Path<i64>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (meta, snapshot) = state
+        .db
+        .get_config_version(version)
+        .await
+        .map_err(internal)?
+        .ok_or_else(|| ApiError::NotFound(format!("config version {version} not found")))?;
+    Ok(Json(serde_json::json!({
+        "version": meta.version,
+        "created_ts": meta.created_ts,
+        "reason": meta.reason,
+        "config": snapshot,
+    })))
+}
 
-    /// Build an AppState backed by an in-memory SQLite DB for testing.
-    async fn test_state() -> AppState {
-        let db = Db::connect("sqlite::memory:").await.unwrap();
-        db.migrate().await.unwrap();
+/// Restore a stored configuration version.  The restore itself becomes a
+/// new version, so a rollback can be undone the same way.  Like any other
+/// config change through the API, it takes effect on the next hub restart.
+async fn api_config_rollback(
+    State(state): State<AppState>,
+    caller: Option<Extension<Identity>>,
+    Path(version): Path<i64>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (_, snapshot) = state
+        .db
+        .get_config_version(version)
+        .await
+        .map_err(internal)?
+        .ok_or_else(|| ApiError::NotFound(format!("config version {version} not found")))?;
 
-        let zones = vec![("zone1".to_string(), 17), ("zone2".to_string(), 27)];
-        let shared = Arc::new(RwLock::new(SystemState::new(&zones, "auto")));
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let summary = state
+        .db
+        .restore_config(&snapshot, now)
+        .await
+        .map_err(internal)?;
+    let new_version = state
+        .db
+        .record_config_version(
+            now,
+            &format!("rollback to version {version}"),
+            AuditSource::Api,
+            actor(&caller),
+        )
+        .await
+        .map_err(internal)?;
 
-        AppState { shared, db }
+    {
+        let mut st = state.shared.write().await;
+        st.moisture.invalidate();
+        st.record_system(format!("configuration rolled back to version {version}"));
     }
+    state.node_settings.notify_one();
+
+    Ok(Json(serde_json::json!({
+        "restored_version": version,
+        "new_version": new_version,
+        "archived_sensors": summary.archived_sensors,
+        "kept_zones": summary.kept_zones,
+        "restart_required": true,
+    })))
+}
 
-    fn get_req(uri: &str) -> Request<Body> {
-        Request::builder().uri(uri).body(Body::empty()).unwrap()
+/// The active zones and sensors as the `zones` / `sensors` sections of
+/// `config.toml`, in JSON or TOML.
+async fn api_config_export(
+    State(state): State<AppState>,
+    Query(q): Query<ExportQuery>,
+) -> Result<axum::response::Response, ApiError> {
+    let zones = state.db.load_zones().await.map_err(internal)?;
+    let sensors = state.db.load_sensors().await.map_err(internal)?;
+    let export = ZoneExport {
+        zones: zones.iter().map(ZoneEntry::from).collect(),
+        sensors: sensors.iter().map(SensorEntry::from).collect(),
+    };
+    match q.format.as_deref().unwrap_or("json") {
+        "json" => Ok(Json(export).into_response()),
+        "toml" => {
+            let text = toml::to_string(&export).map_err(|e| internal(e.into()))?;
+            Ok(([(header::CONTENT_TYPE, "application/toml")], text).into_response())
+        }
+        other => Err(ApiError::Validation(vec![format!(
+            "format must be json or toml, got '{other}'"
+        )])),
     }
+}
 
-    fn put_json(uri: &str, body: serde_json::Value) -> Request<Body> {
-        Request::builder()
-            .method("PUT")
-            .uri(uri)
-            .header("content-type", "application/json")
-            .body(Body::from(serde_json::to_vec(&body).unwrap()))
-            .unwrap()
+/// Create or update every zone and sensor in an export (TOML when the
+/// content type says so, otherwise JSON).  Zones and sensors not in it are
+/// left alone; the result must pass the same checks as `config.toml`.
+async fn api_config_import(
+    State(state): State<AppState>,
+    caller: Option<Extension<Identity>>,
+    headers: axum::http::HeaderMap,
+    body: axum::body::Bytes,
+) -> Result<impl IntoResponse, ApiError> {
+    let is_toml = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("toml"));
+    let import: ZoneExport = if is_toml {
+        let text = std::str::from_utf8(&body)
+            .map_err(|e| ApiError::Validation(vec![format!("body is not UTF-8: {e}")]))?;
+        toml::from_str(text).map_err(|e| ApiError::Validation(vec![e.message().to_string()]))?
+    } else {
+        serde_json::from_slice(&body).map_err(|e| ApiError::Validation(vec![e.to_string()]))?
+    };
+    let (zones, sensors) = (import.zones.len(), import.sensors.len());
+    if zones + sensors == 0 {
+        return Err(ApiError::Validation(vec![
+            "import has no zones or sensors".into()
+        ]));
     }
 
-    fn delete_req(uri: &str) -> Request<Body> {
-        Request::builder()
-            .method("DELETE")
+    let config = validate_with_stored(&state, import.zones, import.sensors).await?;
+    config::apply(&config, &state.db).await.map_err(internal)?;
+    record_config_version(
+        &state,
+        &caller,
+        &format!("imported {zones} zone(s) and {sensors} sensor(s)"),
+    )
+    .await;
+    state.node_settings.notify_one();
+    Ok(Json(
+        serde_json::json!({ "zones": zones, "sensors": sensors }),
+    ))
+}
+
+/// Zones, nodes and events of every mirrored site.
+async fn api_federation(State(state): State<AppState>) -> Json<FederationView> {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    Json(state.shared.read().await.federation.view(now))
+}
+
+async fn api_et(
+    State(state): State<AppState>,
+    Query(q): Query<EtQuery>,
+) -> Result<Json<EtResponse>, ApiError> {
+    let days = state
+        .db
+        .list_et_days(q.days.unwrap_or(14).clamp(1, 366))
+        .await
+        .map_err(internal)?;
+    let configs = state.db.load_zones().await.map_err(internal)?;
+    let (today, deficits) = {
+        let st = state.shared.read().await;
+        let today = st.weather.today().map(|d| WeatherToday {
+            date: d.date.to_string(),
+            temp_min_c: d.temp_min_c,
+            temp_max_c: d.temp_max_c,
+            rain_mm: d.rain_mm,
+        });
+        (today, st.et_deficits.clone())
+    };
+    let zones = configs
+        .into_iter()
+        .filter_map(|z| match z.strategy {
+            StrategyConfig::Et {
+                allowed_depletion_mm,
+                ..
+            } => Some(ZoneWaterBalance {
+                deficit_mm: deficits.get(&z.zone_id).copied().unwrap_or(0.0),
+                zone_id: z.zone_id,
+                allowed_depletion_mm,
+            }),
+            _ => None,
+        })
+        .collect();
+    Ok(Json(EtResponse { today, days, zones }))
+}
+
+/// Zone and sensor changes, newest first.
+async fn api_audit(
+    State(state): State<AppState>,
+    Query(q): Query<AuditQuery>,
+) -> Result<Json<Vec<AuditEntry>>, ApiError> {
+    if let Some(entity) = q.entity.as_deref() {
+        if entity != "zone" && entity != "sensor" {
+            return Err(ApiError::Validation(vec![format!(
+                "entity must be 'zone' or 'sensor', got '{entity}'"
+            )]));
+        }
+    }
+    let limit = q.limit.unwrap_or(100).clamp(1, 1000);
+    let offset = q.offset.unwrap_or(0).max(0);
+
+    state
+        .db
+        .list_audit(
+            q.entity.as_deref(),
+            q.entity_id.as_deref(),
+            q.from,
+            q.to,
+            limit,
+            offset,
+        )
+        .await
+        .map(Json)
+        .map_err(internal)
+}
+
+// ---------------------------------------------------------------------------
+// Handlers — safety review
+// ---------------------------------------------------------------------------
+
+async fn api_safety_review(State(state): State<AppState>) -> Json<Vec<Finding>> {
+    Json(state.shared.read().await.safety_review().to_vec())
+}
+
+/// Acknowledge a zone's pending findings so auto mode may water it.
+async fn api_acknowledge_review(
+    State(state): State<AppState>,
+    Path(zone_id): Path<String>,
+) -> Result<Json<Vec<Finding>>, ApiError> {
+    let pending = state.shared.read().await.pending_findings(Some(&zone_id));
+    if pending.is_empty() {
+        return Err(ApiError::NotFound(format!(
+            "zone '{zone_id}' has no findings awaiting acknowledgement"
+        )));
+    }
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    state
+        .db
+        .acknowledge_findings(&pending, now)
+        .await
+        .map_err(internal)?;
+    let mut st = state.shared.write().await;
+    st.acknowledge_findings(&zone_id);
+    st.record_system(format!(
+        "safety review acknowledged for zone {zone_id} ({} finding(s))",
+        pending.len()
+    ));
+    Ok(Json(pending))
+}
+
+// ---------------------------------------------------------------------------
+// Handlers — emergency stop
+// ---------------------------------------------------------------------------
+
+/// Clear a latched emergency stop so valves may open again.  Refused while
+/// the button is still held down.
+async fn api_clear_emergency_stop(State(state): State<AppState>) -> Result<StatusCode, ApiError> {
+    // Held across the write so a press in between isn't cleared with it.
+    let mut st = state.shared.write().await;
+    let mut cleared = st.estop.clone();
+    cleared
+        .clear()
+        .map_err(|e| ApiError::Conflict(e.to_string()))?;
+    state.db.save_estop_latch(None).await.map_err(internal)?;
+    st.estop = cleared;
+    st.record_system("emergency stop cleared — valves may open again".to_string());
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ---------------------------------------------------------------------------
+// Handlers — data retention
+// ---------------------------------------------------------------------------
+
+async fn api_retention(State(state): State<AppState>) -> Json<RetentionPolicy> {
+    Json(state.shared.read().await.retention)
+}
+
+/// Replace the retention policy.  It is stored in the database and takes
+/// precedence over `[retention]` in config.toml from then on.
+async fn api_put_retention(
+    State(state): State<AppState>,
+    Json(policy): Json<RetentionPolicy>,
+) -> Result<Json<RetentionPolicy>, ApiError> {
+    policy.validate().map_err(ApiError::Validation)?;
+    state
+        .db
+        .save_retention_policy(&policy)
+        .await
+        .map_err(internal)?;
+    let mut st = state.shared.write().await;
+    if st.retention != policy {
+        st.retention = policy;
+        st.record_system(format!("retention policy changed: {policy:?}"));
+    }
+    Ok(Json(policy))
+}
+
+/// Prune now, outside the maintenance windows, vacuuming every free page.
+async fn api_prune(State(state): State<AppState>) -> Result<Json<PruneReport>, ApiError> {
+    let policy = state.shared.read().await.retention;
+    let report = retention::prune(&state.db, &policy, None)
+        .await
+        .map_err(internal)?;
+    state
+        .shared
+        .write()
+        .await
+        .record_system(format!("on-demand {}", report.summary()));
+    Ok(Json(report))
+}
+
+async fn api_valve_test(State(state): State<AppState>) -> Result<Json<ValveTestReport>, ApiError> {
+    state
+        .shared
+        .read()
+        .await
+        .valve_test
+        .report
+        .clone()
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound("no valve test has run".to_string()))
+}
+
+/// Start a valve self-test in the background; poll
+/// `GET /api/maintenance/valve-test` for the report.
+async fn api_start_valve_test(
+    State(state): State<AppState>,
+    Query(q): Query<ValveTestQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let blip_sec = match q.blip_sec {
+        Some(s) => s,
+        None => state.shared.read().await.valve_test.cfg.blip_sec,
+    };
+    if !(1..=selftest::MAX_BLIP_SEC).contains(&blip_sec) {
+        return Err(ApiError::Validation(vec![format!(
+            "blip_sec must be within 1..={}, got {blip_sec}",
+            selftest::MAX_BLIP_SEC
+        )]));
+    }
+    let mut zone_ids: Vec<String> = state
+        .db
+        .load_zones()
+        .await
+        .map_err(internal)?
+        .into_iter()
+        .map(|z| z.zone_id)
+        .collect();
+    if let Some(zone_id) = &q.zone_id {
+        if !zone_ids.contains(zone_id) {
+            return Err(ApiError::NotFound(format!("zone '{zone_id}' not found")));
+        }
+        zone_ids = vec![zone_id.clone()];
+    }
+    if zone_ids.is_empty() {
+        return Err(ApiError::Conflict("no zones to test".to_string()));
+    }
+    let refused = selftest::refusal(&*state.shared.read().await);
+    if let Some(reason) = refused {
+        return Err(ApiError::Conflict(reason));
+    }
+
+    let report = {
+        let mut st = state.shared.write().await;
+        if st.valve_test.is_running() {
+            return Err(ApiError::Conflict(
+                "a valve test is already running".to_string(),
+            ));
+        }
+        if let Some(zone_id) = st.zones.iter().find(|(_, z)| z.on).map(|(id, _)| id) {
+            return Err(ApiError::Conflict(format!("zone {zone_id} is watering")));
+        }
+        let report = ValveTestReport::new(&zone_ids, blip_sec, OffsetDateTime::now_utc());
+        st.valve_test.report = Some(report.clone());
+        st.record_system(format!(
+            "valve test started: {} zone(s), {blip_sec}s each",
+            zone_ids.len()
+        ));
+        report
+    };
+    tokio::spawn(selftest::run(
+        state.shared.clone(),
+        state.db.clone(),
+        state.water_now.clone(),
+        state.session_cancels.clone(),
+    ));
+    Ok((StatusCode::ACCEPTED, Json(report)))
+}
+
+// ---------------------------------------------------------------------------
+// Two-step confirmation
+// ---------------------------------------------------------------------------
+
+/// Without `token`, issue one for `action` (`Some`, for the caller to
+/// return); with it, check and consume it (`None`: go ahead).
+async fn confirm_step(
+    state: &AppState,
+    action: &str,
+    token: Option<&str>,
+) -> Result<Option<String>, ApiError> {
+    let now = std::time::Instant::now();
+    let mut pending = state.confirm.pending.lock().await;
+    match token {
+        None => Ok(Some(pending.issue(action, now))),
+        Some(token) if pending.take(action, token, now) => Ok(None),
+        Some(_) => Err(ApiError::Validation(vec![
+            "invalid or expired confirmation token".to_string(),
+        ])),
+    }
+}
+
+/// 202 carrying a confirmation token for the resource `subject`.
+fn confirm_response(
+    subject: (&str, &str),
+    token: String,
+    message: &str,
+) -> axum::response::Response {
+    let mut body = serde_json::Map::new();
+    body.insert(subject.0.to_string(), subject.1.into());
+    body.insert("confirm".to_string(), token.into());
+    body.insert(
+        "expires_in_sec".to_string(),
+        confirm::CONFIRM_TTL.as_secs().into(),
+    );
+    body.insert("message".to_string(), message.into());
+    (StatusCode::ACCEPTED, Json(body)).into_response()
+}
+
+// ---------------------------------------------------------------------------
+// Handlers — backups
+// ---------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+struct RestorePayload {
+    /// Backup file name, as listed by `GET /api/backups`.
+    file: String,
+    /// Token from the first call; omitted to request one.
+    #[serde(default)]
+    confirm: Option<String>,
+}
+
+async fn api_backups(State(state): State<AppState>) -> Result<Json<Vec<BackupFile>>, ApiError> {
+    let Some(dir) = &state.restore.dir else {
+        return Ok(Json(Vec::new()));
+    };
+    restore::list_backups(dir).map(Json).map_err(internal)
+}
+
+/// Two-step restore: without `confirm` this only returns a token; with it
+/// the main loop closes all valves and swaps the backup in.
+async fn api_restore_backup(
+    State(state): State<AppState>,
+    Json(body): Json<RestorePayload>,
+) -> Result<axum::response::Response, ApiError> {
+    let dir = state.restore.dir.as_ref().ok_or_else(|| {
+        ApiError::Validation(vec!["DB_BACKUP_PATH is not set on the hub".to_string()])
+    })?;
+    let path = restore::resolve(dir, &body.file).map_err(|e| ApiError::Validation(vec![e]))?;
+    if !path.is_file() {
+        return Err(ApiError::NotFound(format!(
+            "backup '{}' not found",
+            body.file
+        )));
+    }
+
+    let action = format!("restore {}", body.file);
+    if let Some(token) = confirm_step(&state, &action, body.confirm.as_deref()).await? {
+        return Ok(confirm_response(
+            ("file", &body.file),
+            token,
+            "repeat with this confirm token to restore; all valves will be turned off",
+        ));
+    }
+
+    let (reply, outcome) = oneshot::channel();
+    state
+        .restore
+        .requests
+        .send(RestoreRequest { path, reply })
+        .await
+        .map_err(|_| internal(anyhow::anyhow!("restore handler is not running")))?;
+    let outcome = outcome
+        .await
+        .map_err(|_| internal(anyhow::anyhow!("restore handler dropped the request")))?
+        .map_err(ApiError::Internal)?;
+
+    Ok(Json(serde_json::json!({
+        "restored": body.file,
+        "rows": outcome.rows,
+        "zones": outcome.zones,
+        "sensors": outcome.sensors,
+        "restart_required": outcome.restart_required,
+    }))
+    .into_response())
+}
+
+// ---------------------------------------------------------------------------
+// Handlers — readings (read-only)
+// ---------------------------------------------------------------------------
+
+async fn api_readings(
+    State(state): State<AppState>,
+    Query(q): Query<ReadingsQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let limit = q.limit.unwrap_or(100).clamp(1, 1000);
+    let offset = q.offset.unwrap_or(0).max(0);
+
+    let rows = state
+        .db
+        .list_readings(q.sensor_id.as_deref(), q.zone_id.as_deref(), limit, offset)
+        .await
+        .map_err(internal)?;
+
+    Ok(Json(rows))
+}
+
+// ---------------------------------------------------------------------------
+// Handlers — watering events (read-only)
+// ---------------------------------------------------------------------------
+
+async fn api_watering_events(
+    State(state): State<AppState>,
+    Query(q): Query<EventsQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let limit = q.limit.unwrap_or(100).clamp(1, 1000);
+    let offset = q.offset.unwrap_or(0).max(0);
+
+    let rows = state
+        .db
+        .list_watering_events(q.zone_id.as_deref(), limit, offset)
+        .await
+        .map_err(internal)?;
+
+    Ok(Json(rows))
+}
+
+// ---------------------------------------------------------------------------
+// Handlers — watering sessions
+// ---------------------------------------------------------------------------
+
+#[derive(Serialize)]
+struct SessionsResponse {
+    active: Vec<Session>,
+    /// Newest first.
+    recent: Vec<Session>,
+}
+
+/// Open watering sessions and the most recently finished ones (in memory
+/// only; the watering events table is the durable record).
+async fn api_sessions(State(state): State<AppState>) -> Json<SessionsResponse> {
+    let st = state.shared.read().await;
+    Json(SessionsResponse {
+        active: st.sessions.active(),
+        recent: st.sessions.recent(),
+    })
+}
+
+/// Cancel an active session: its valve is sent `OFF` through the normal
+/// command path and the session finishes as `cancelled`.
+async fn api_cancel_session(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Result<impl IntoResponse, ApiError> {
+    let zone_id = {
+        let mut st = state.shared.write().await;
+        if !st.mqtt_connected {
+            return Err(ApiError::Conflict(
+                "hub is not connected to MQTT".to_string(),
+            ));
+        }
+        st.sessions
+            .request_cancel(id)
+            .ok_or_else(|| ApiError::NotFound(format!("no active session {id}")))?
+    };
+    state
+        .session_cancels
+        .send(zone_id.clone())
+        .await
+        .map_err(|_| internal(anyhow::anyhow!("session cancel publisher is not running")))?;
+    state
+        .shared
+        .write()
+        .await
+        .record_system(format!("session {id} ({zone_id}) cancelled"));
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({ "id": id, "zone_id": zone_id })),
+    ))
+}
+
+/// Start one manual pulse: a timed `ON` through the normal command path,
+/// as a planned `manual` session.  The duration defaults to the zone's
+/// `pulse_sec` and may be shorter, and is cut to what is left of the
+/// zone's daily open-seconds limit.
+async fn api_water_now(
+    State(state): State<AppState>,
+    Path(zone_id): Path<String>,
+    Query(q): Query<WaterNowQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let zone = state
+        .db
+        .get_zone(&zone_id)
+        .await
+        .map_err(internal)?
+        .ok_or_else(|| ApiError::NotFound(format!("zone '{zone_id}' not found")))?;
+    if zone.archived_at.is_some() {
+        return Err(ApiError::Conflict(format!("zone '{zone_id}' is archived")));
+    }
+    let requested = q.duration_sec.unwrap_or(zone.pulse_sec);
+    if !(1..=zone.pulse_sec).contains(&requested) {
+        return Err(ApiError::Validation(vec![format!(
+            "duration_sec must be within 1..={} (the zone's pulse_sec), got {requested}",
+            zone.pulse_sec
+        )]));
+    }
+
+    // As for valve commands: counters that can't be read put the hub in
+    // degraded mode and the pulse is held to its reduced caps.
+    let today = Db::today_yyyy_mm_dd();
+    let mut daily = None;
+    if !state.shared.read().await.is_db_degraded() {
+        match state.db.get_daily_counters(&today, &zone_id).await {
+            Ok(c) => {
+                state
+                    .shared
+                    .write()
+                    .await
+                    .cache_db_counters(&today, &zone_id, c.pulses, c.open_sec);
+                daily = Some(c);
+            }
+            Err(e) => {
+                tracing::error!(zone = %zone_id, "water-now: {e:#} — using degraded-mode limits");
+                state
+                    .shared
+                    .write()
+                    .await
+                    .mark_db_degraded("daily counter read failed");
+            }
+        }
+    }
+    let rolling = match scheduler::rolling_usage(&state.db, &state.shared, &zone_id).await {
+        Ok(r) => Some(r),
+        Err(e) => {
+            tracing::error!(zone = %zone_id, "water-now: {e:#} — using degraded-mode limits");
+            state
+                .shared
+                .write()
+                .await
+                .mark_db_degraded("24h counter read failed");
+            None
+        }
+    };
+    let (pulses, open_sec, max_pulses, max_open_sec) = match daily.filter(|_| rolling.is_some()) {
+        Some(c) => (
+            c.pulses,
+            c.open_sec,
+            zone.max_pulses_per_day,
+            zone.max_open_sec_per_day,
+        ),
+        None => {
+            let c = state
+                .shared
+                .read()
+                .await
+                .degraded_counters(&today, &zone_id)
+                .ok_or_else(|| {
+                    ApiError::Conflict(format!(
+                        "zone {zone_id}: usage today unknown while the database is degraded"
+                    ))
+                })?;
+            (
+                c.pulses,
+                c.open_sec,
+                state::degraded_limit(zone.max_pulses_per_day),
+                state::degraded_limit(zone.max_open_sec_per_day),
+            )
+        }
+    };
+    if pulses >= max_pulses {
+        return Err(ApiError::Conflict(format!(
+            "zone {zone_id}: {pulses}/{max_pulses} pulses today"
+        )));
+    }
+    if open_sec >= max_open_sec {
+        return Err(ApiError::Conflict(format!(
+            "zone {zone_id}: {open_sec}s/{max_open_sec}s open today"
+        )));
+    }
+    let mut duration_sec = requested.min(max_open_sec - open_sec);
+    if let Some(r) = rolling {
+        if r.pulses >= max_pulses || r.open_sec >= max_open_sec {
+            return Err(ApiError::Conflict(format!(
+                "zone {zone_id}: {}/{max_pulses} pulses, {}s/{max_open_sec}s open in the last 24h",
+                r.pulses, r.open_sec
+            )));
+        }
+        duration_sec = duration_sec.min(max_open_sec - r.open_sec);
+    }
+
+    let session_id = {
+        let mut st = state.shared.write().await;
+        let refused = selftest::refusal(&st).or_else(|| {
+            if st.zones.get(&zone_id).is_some_and(|z| z.on) {
+                Some(format!("zone {zone_id} is already watering"))
+            } else {
+                st.open_valve_conflict(&zone_id)
+            }
+        });
+        if let Some(reason) = refused {
+            return Err(ApiError::Conflict(reason));
+        }
+        st.sessions
+            .plan(&zone_id, sessions::MANUAL_REASON, Some(duration_sec))
+    };
+    if state
+        .water_now
+        .send((zone_id.clone(), duration_sec))
+        .await
+        .is_err()
+    {
+        state.shared.write().await.sessions.unplan(&zone_id);
+        return Err(internal(anyhow::anyhow!(
+            "valve command publisher is not running"
+        )));
+    }
+    state.shared.write().await.record_system(format!(
+        "zone {zone_id}: manual pulse of {duration_sec}s requested"
+    ));
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "session_id": session_id,
+            "zone_id": zone_id,
+            "duration_sec": duration_sec,
+        })),
+    ))
+}
+
+// ---------------------------------------------------------------------------
+// Handlers — scheduler decisions (read-only)
+// ---------------------------------------------------------------------------
+
+async fn api_scheduler_decisions(
+    State(state): State<AppState>,
+    Query(q): Query<DecisionsQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let limit = q.limit.unwrap_or(100).clamp(1, 1000);
+    let offset = q.offset.unwrap_or(0).max(0);
+
+    let rows = state
+        .db
+        .list_scheduler_decisions(q.zone_id.as_deref(), q.from, q.to, limit, offset)
+        .await
+        .map_err(internal)?;
+
+    Ok(Json(rows))
+}
+
+/// What the next tick would do with each zone, without doing it.
+async fn api_scheduler_preview(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<scheduler::ZonePreview>>, ApiError> {
+    let zones = state.db.load_zones().await.map_err(internal)?;
+    scheduler::preview(&state.db, &zones, &state.shared)
+        .await
+        .map(Json)
+        .map_err(internal)
+}
+
+// ---------------------------------------------------------------------------
+// Handlers — stored logs (read-only)
+// ---------------------------------------------------------------------------
+
+/// WARN and ERROR records kept with `LOG_TO_DB` (see `logs`), newest first.
+async fn api_logs(
+    State(state): State<AppState>,
+    Query(q): Query<LogsQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    if let Some(level) = q.level.as_deref() {
+        if !level.eq_ignore_ascii_case("warn") && !level.eq_ignore_ascii_case("error") {
+            return Err(ApiError::Validation(vec![format!(
+                "level must be 'warn' or 'error', got '{level}'"
+            )]));
+        }
+    }
+    let limit = q.limit.unwrap_or(100).clamp(1, 1000);
+    let offset = q.offset.unwrap_or(0).max(0);
+
+    let rows = state
+        .db
+        .list_logs(q.level.as_deref(), q.from, q.to, limit, offset)
+        .await
+        .map_err(internal)?;
+
+    Ok(Json(rows))
+}
+
+// ---------------------------------------------------------------------------
+// Handlers — daily counters (read-only)
+// ---------------------------------------------------------------------------
+
+async fn api_counters(
+    State(state): State<AppState>,
+    Path(zone_id): Path<String>,
+    Query(q): Query<CountersQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let day = q.day.unwrap_or_else(Db::today_yyyy_mm_dd);
+    let counters = state
+        .db
+        .get_daily_counters(&day, &zone_id)
+        .await
+        .map_err(internal)?;
+    Ok(Json(counters))
+}
+
+// ---------------------------------------------------------------------------
+// Handlers — reports (read-only)
+// ---------------------------------------------------------------------------
+
+/// Default report window when `from` is omitted.
+const USAGE_REPORT_DEFAULT_DAYS: i64 = 30;
+
+/// Events per zone in the soak report when `events` is omitted, and the
+/// most it accepts.
+const SOAK_REPORT_DEFAULT_EVENTS: i64 = 20;
+const SOAK_REPORT_MAX_EVENTS: i64 = 500;
+
+/// Water usage per zone bucketed by day, week, or month.  `from`/`to` are
+/// inclusive `YYYY-MM-DD` dates; `to` defaults to today and `from` to 30
+/// days before `to`.
+async fn api_usage_report(
+    State(state): State<AppState>,
+    Query(q): Query<UsageQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let date_fmt = time::macros::format_description!("[year]-[month]-[day]");
+    let parse = |field: &str, v: &str| {
+        time::Date::parse(v, &date_fmt)
+            .map_err(|_| format!("{field} must be a YYYY-MM-DD date, got '{v}'"))
+    };
+
+    let mut errs = Vec::new();
+    let to = match q.to.as_deref() {
+        Some(v) => parse("to", v).map_err(|e| errs.push(e)).ok(),
+        None => Some(OffsetDateTime::now_utc().date()),
+    };
+    let from = match q.from.as_deref() {
+        Some(v) => parse("from", v).map_err(|e| errs.push(e)).ok(),
+        None => to.map(|t| t - time::Duration::days(USAGE_REPORT_DEFAULT_DAYS)),
+    };
+    if let (Some(f), Some(t)) = (from, to) {
+        if f > t {
+            errs.push("from must not be after to".into());
+        }
+    }
+    let (Some(from), Some(to)) = (from, to) else {
+        return Err(ApiError::Validation(errs));
+    };
+    if !errs.is_empty() {
+        return Err(ApiError::Validation(errs));
+    }
+
+    let from = from.format(&date_fmt).map_err(|e| internal(e.into()))?;
+    let to = to.format(&date_fmt).map_err(|e| internal(e.into()))?;
+    let rows = state
+        .db
+        .usage_report(q.zone_id.as_deref(), &from, &to, q.group_by)
+        .await
+        .map_err(internal)?;
+
+    Ok(Json(serde_json::json!({
+        "from": from,
+        "to": to,
+        "group_by": q.group_by,
+        "rows": rows,
+    })))
+}
+
+/// Per-zone watering efficiency for `from..=to` with a daily breakdown and
+/// trend (see `efficiency`).  Dates default as for the usage report.
+async fn api_efficiency_report(
+    State(state): State<AppState>,
+    Query(q): Query<EfficiencyQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let date_fmt = time::macros::format_description!("[year]-[month]-[day]");
+    let parse = |field: &str, v: &str| {
+        time::Date::parse(v, &date_fmt)
+            .map_err(|_| format!("{field} must be a YYYY-MM-DD date, got '{v}'"))
+    };
+
+    let mut errs = Vec::new();
+    let to = match q.to.as_deref() {
+        Some(v) => parse("to", v).map_err(|e| errs.push(e)).ok(),
+        None => Some(OffsetDateTime::now_utc().date()),
+    };
+    let from = match q.from.as_deref() {
+        Some(v) => parse("from", v).map_err(|e| errs.push(e)).ok(),
+        None => to.map(|t| t - time::Duration::days(USAGE_REPORT_DEFAULT_DAYS)),
+    };
+    if let (Some(f), Some(t)) = (from, to) {
+        if f > t {
+            errs.push("from must not be after to".into());
+        }
+    }
+    let (Some(from), Some(to)) = (from, to) else {
+        return Err(ApiError::Validation(errs));
+    };
+    if !errs.is_empty() {
+        return Err(ApiError::Validation(errs));
+    }
+    if let Some(zone_id) = q.zone_id.as_deref() {
+        require_zone(&state, zone_id).await?;
+    }
+
+    let from = from.format(&date_fmt).map_err(|e| internal(e.into()))?;
+    let to = to.format(&date_fmt).map_err(|e| internal(e.into()))?;
+    let mut outcomes: HashMap<String, Vec<PulseOutcome>> = HashMap::new();
+    for o in state
+        .db
+        .pulse_outcomes(q.zone_id.as_deref(), &from, &to)
+        .await
+        .map_err(internal)?
+    {
+        outcomes.entry(o.zone_id.clone()).or_default().push(o);
+    }
+    // Archived zones only when asked for by id.
+    let zones: Vec<ZoneEfficiency> = state
+        .db
+        .load_all_zones()
+        .await
+        .map_err(internal)?
+        .iter()
+        .filter(|z| match q.zone_id.as_deref() {
+            Some(id) => id == z.zone_id,
+            None => z.archived_at.is_none(),
+        })
+        .map(|z| {
+            let pulses = outcomes.remove(&z.zone_id).unwrap_or_default();
+            efficiency::summarize(z, &pulses)
+        })
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "from": from,
+        "to": to,
+        "zones": zones,
+    })))
+}
+
+/// Moisture rise per second of open valve over each zone's last `events`
+/// watering events, flagging zones whose newer events do markedly worse
+/// (see `efficiency::soak_effectiveness`).
+async fn api_soak_report(
+    State(state): State<AppState>,
+    Query(q): Query<SoakQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let events = q.events.unwrap_or(SOAK_REPORT_DEFAULT_EVENTS);
+    if !(2..=SOAK_REPORT_MAX_EVENTS).contains(&events) {
+        return Err(ApiError::Validation(vec![format!(
+            "events must be between 2 and {SOAK_REPORT_MAX_EVENTS}, got {events}"
+        )]));
+    }
+    if let Some(zone_id) = q.zone_id.as_deref() {
+        require_zone(&state, zone_id).await?;
+    }
+
+    // Archived zones only when asked for by id.
+    let mut zones = Vec::new();
+    for z in state.db.load_all_zones().await.map_err(internal)? {
+        let wanted = match q.zone_id.as_deref() {
+            Some(id) => id == z.zone_id,
+            None => z.archived_at.is_none(),
+        };
+        if !wanted {
+            continue;
+        }
+        let outcomes = state
+            .db
+            .recent_pulse_outcomes(&z.zone_id, events)
+            .await
+            .map_err(internal)?;
+        zones.push(efficiency::soak_effectiveness(&z.zone_id, &outcomes));
+    }
+
+    Ok(Json(serde_json::json!({
+        "events": events,
+        "zones": zones,
+    })))
+}
+
+/// A zone's watering totals and moisture profile for `from..=to` against a
+/// baseline of the same length (see `history`).  `to` defaults to today,
+/// `from` to the week ending `to`, and `baseline_from` to `from` last year.
+async fn api_zone_compare(
+    State(state): State<AppState>,
+    Path(zone_id): Path<String>,
+    Query(q): Query<CompareQuery>,
+) -> Result<Json<Comparison>, ApiError> {
+    let date_fmt = time::macros::format_description!("[year]-[month]-[day]");
+    let parse = |field: &str, v: &str| {
+        time::Date::parse(v, &date_fmt)
+            .map_err(|_| format!("{field} must be a YYYY-MM-DD date, got '{v}'"))
+    };
+
+    let mut errs = Vec::new();
+    let to = match q.to.as_deref() {
+        Some(v) => parse("to", v).map_err(|e| errs.push(e)).ok(),
+        None => Some(OffsetDateTime::now_utc().date()),
+    };
+    let from = match q.from.as_deref() {
+        Some(v) => parse("from", v).map_err(|e| errs.push(e)).ok(),
+        None => to.map(|t| t - time::Duration::days(history::DEFAULT_PERIOD_DAYS - 1)),
+    };
+    let baseline_from = q
+        .baseline_from
+        .as_deref()
+        .and_then(|v| parse("baseline_from", v).map_err(|e| errs.push(e)).ok());
+    if let (Some(f), Some(t)) = (from, to) {
+        if f > t {
+            errs.push("from must not be after to".into());
+        } else if (t - f).whole_days() >= history::MAX_PERIOD_DAYS {
+            errs.push(format!(
+                "period must not exceed {} days",
+                history::MAX_PERIOD_DAYS
+            ));
+        }
+    }
+    let (Some(from), Some(to)) = (from, to) else {
+        return Err(ApiError::Validation(errs));
+    };
+    if !errs.is_empty() {
+        return Err(ApiError::Validation(errs));
+    }
+    require_zone(&state, &zone_id).await?;
+
+    let (base_from, base_to) = history::baseline_range(from, to, baseline_from);
+    let period = period_summary(&state.db, &zone_id, from, to).await?;
+    let baseline = period_summary(&state.db, &zone_id, base_from, base_to).await?;
+    Ok(Json(Comparison::new(zone_id, period, baseline)))
+}
+
+async fn period_summary(
+    db: &Db,
+    zone_id: &str,
+    from: time::Date,
+    to: time::Date,
+) -> Result<PeriodSummary, ApiError> {
+    let date_fmt = time::macros::format_description!("[year]-[month]-[day]");
+    let from = from.format(&date_fmt).map_err(|e| internal(e.into()))?;
+    let to = to.format(&date_fmt).map_err(|e| internal(e.into()))?;
+    let usage = db
+        .usage_totals(zone_id, &from, &to)
+        .await
+        .map_err(internal)?;
+    let moisture = db
+        .daily_moisture(zone_id, &from, &to)
+        .await
+        .map_err(internal)?;
+    Ok(PeriodSummary::new(from, to, usage, moisture))
+}
+
+// ---------------------------------------------------------------------------
+// Server entry-point
+// ---------------------------------------------------------------------------
+
+/// One `WEB_BIND` listener.
+#[derive(Debug, Clone, PartialEq)]
+enum BindAddr {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+/// Parse `WEB_BIND`: a comma-separated list of IP addresses (served on
+/// `port`), socket addresses with their own port (`[::1]:8081`), or
+/// `unix:<path>` sockets for a reverse proxy on the same host.
+fn parse_bind_addrs(spec: &str, port: u16) -> Result<Vec<BindAddr>, Vec<String>> {
+    let mut addrs = Vec::new();
+    let mut errs = Vec::new();
+    for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        if let Some(path) = entry.strip_prefix("unix:") {
+            if path.is_empty() {
+                errs.push(format!("WEB_BIND: '{entry}' has no socket path"));
+            } else {
+                addrs.push(BindAddr::Unix(PathBuf::from(path)));
+            }
+        } else if let Ok(ip) = entry.parse::<IpAddr>() {
+            addrs.push(BindAddr::Tcp(SocketAddr::new(ip, port)));
+        } else if let Ok(addr) = entry.parse::<SocketAddr>() {
+            addrs.push(BindAddr::Tcp(addr));
+        } else {
+            errs.push(format!(
+                "WEB_BIND: '{entry}' is not an IP, IP:port or unix:<path>"
+            ));
+        }
+    }
+    if errs.is_empty() {
+        Ok(addrs)
+    } else {
+        Err(errs)
+    }
+}
+
+/// Parse `WEB_SOCKET_MODE`: octal permission bits (`660`, `0660`).
+fn parse_socket_mode(s: &str) -> Option<u32> {
+    u32::from_str_radix(s.trim(), 8)
+        .ok()
+        .filter(|m| *m <= 0o777)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn serve(
+    shared: SharedState,
+    db: Db,
+    node_settings: Arc<Notify>,
+    node_commands: mpsc::Sender<(String, NodeCommand)>,
+    session_cancels: mpsc::Sender<String>,
+    water_now: mpsc::Sender<(String, i64)>,
+    restore: RestoreApi,
+    ota: OtaApi,
+    telemetry: mpsc::Sender<(String, Vec<u8>)>,
+) {
+    let port: u16 = env::var("WEB_PORT")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(8080);
+
+    let loopback = BindAddr::Tcp(SocketAddr::new(IpAddr::from([127, 0, 0, 1]), port));
+    let binds = match env::var("WEB_BIND").map(|spec| parse_bind_addrs(&spec, port)) {
+        Ok(Ok(addrs)) if !addrs.is_empty() => addrs,
+        Ok(Err(errs)) => {
+            for e in errs {
+                tracing::error!("{e}");
+            }
+            tracing::error!("invalid WEB_BIND — web ui not started");
+            return;
+        }
+        _ => vec![loopback],
+    };
+    // Unix socket permissions, e.g. 660 so a proxy in the hub user's group
+    // can connect.  Unset leaves them to the umask.
+    let socket_mode = match env::var("WEB_SOCKET_MODE") {
+        Ok(s) if !s.is_empty() => match parse_socket_mode(&s) {
+            Some(mode) => Some(mode),
+            None => {
+                tracing::error!(
+                    "invalid WEB_SOCKET_MODE '{s}' (octal, e.g. 660) — web ui not started"
+                );
+                return;
+            }
+        },
+        _ => None,
+    };
+
+    let tokens = match ApiTokens::parse(
+        env::var("API_TOKEN").ok().as_deref(),
+        env::var("API_TOKENS").ok().as_deref(),
+    ) {
+        Ok(tokens) => tokens,
+        Err(errs) => {
+            for e in errs {
+                tracing::error!("{e}");
+            }
+            tracing::error!("invalid API_TOKENS — web ui not started");
+            return;
+        }
+    };
+
+    let node_tokens = match NodeTokens::parse(env::var("NODE_TOKENS").ok().as_deref()) {
+        Ok(tokens) => tokens,
+        Err(errs) => {
+            for e in errs {
+                tracing::error!("{e}");
+            }
+            tracing::error!("invalid NODE_TOKENS — web ui not started");
+            return;
+        }
+    };
+
+    let kiosk = match KioskAccess::parse(env::var("KIOSK").ok().as_deref()) {
+        Ok(kiosk) => kiosk,
+        Err(e) => {
+            tracing::error!("{e} — web ui not started");
+            return;
+        }
+    };
+
+    // Zone and sensor deletes need a confirmation token, like restores.
+    let confirm_deletes =
+        env::var("API_CONFIRM_DELETES").is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+
+    let status = state::status_snapshot(&shared).await;
+    let state = AppState {
+        shared: shared.clone(),
+        db,
+        node_settings,
+        node_commands,
+        session_cancels,
+        water_now,
+        restore,
+        confirm: ConfirmApi::new(confirm_deletes),
+        ota,
+        status: status.clone(),
+        tokens: Arc::new(tokens),
+        telemetry,
+        node_tokens: Arc::new(node_tokens),
+        kiosk,
+    };
+    let app = router(state);
+
+    let tls_cert = env::var("TLS_CERT").ok().filter(|s| !s.is_empty());
+    let tls_key = env::var("TLS_KEY").ok().filter(|s| !s.is_empty());
+
+    let tls = match (tls_cert, tls_key) {
+        (Some(cert), Some(key)) => Some((cert, key)),
+        (None, None) => None,
+        _ => {
+            tracing::error!(
+                "both TLS_CERT and TLS_KEY must be set for HTTPS (only one was provided)"
+            );
+            return;
+        }
+    };
+    #[cfg(not(feature = "tls"))]
+    if tls.is_some() {
+        tracing::error!(
+            "TLS_CERT and TLS_KEY are set but the 'tls' feature is not enabled — \
+             rebuild with `--features tls` or remove TLS_CERT/TLS_KEY to use plain HTTP"
+        );
+        return;
+    }
+
+    // One task per listener; unix sockets are always plain HTTP (the proxy
+    // on the same host terminates TLS).
+    let mut servers = tokio::task::JoinSet::new();
+    // HTTPS listeners share one certificate, reloaded when the files change.
+    #[cfg(feature = "tls")]
+    let rustls = match &tls {
+        Some((cert, key)) => {
+            match axum_server::tls_rustls::RustlsConfig::from_pem_file(cert, key).await {
+                Ok(config) => {
+                    servers.spawn(watch_tls_files(config.clone(), cert.clone(), key.clone()));
+                    Some(config)
+                }
+                Err(e) => {
+                    tracing::error!(cert = %cert, key = %key, "failed to load TLS cert/key: {e}");
+                    return;
+                }
+            }
+        }
+        None => None,
+    };
+    servers.spawn(async move {
+        let mut tick = tokio::time::interval(state::STATUS_PUBLISH_INTERVAL);
+        loop {
+            tick.tick().await;
+            state::publish_status(&shared, &status).await;
+        }
+    });
+    for bind in binds {
+        let app = app.clone();
+        match bind {
+            BindAddr::Tcp(addr) => {
+                #[cfg(feature = "tls")]
+                if let Some(config) = rustls.clone() {
+                    servers.spawn(serve_https(addr, app, config));
+                    continue;
+                }
+                servers.spawn(serve_http(addr, app));
+            }
+            BindAddr::Unix(path) => {
+                servers.spawn(serve_unix(path, app, socket_mode));
+            }
+        }
+    }
+    while servers.join_next().await.is_some() {}
+}
+
+/// Serve plain HTTP. Warns loudly if binding to a non-loopback address,
+/// because `API_TOKEN` would be sent in cleartext.
+async fn serve_http(addr: SocketAddr, app: Router) {
+    if !addr.ip().is_loopback() {
+        tracing::warn!(
+            %addr,
+            "binding WITHOUT TLS on a non-loopback address — API_TOKEN sent in cleartext! \
+             Set TLS_CERT + TLS_KEY for HTTPS, or bind to 127.0.0.1 behind a reverse proxy."
+        );
+    }
+
+    let listener = match TcpListener::bind(addr).await {
+        Ok(l) => l,
+        Err(e) => {
+            tracing::error!("failed to bind web port {addr}: {e}");
+            return;
+        }
+    };
+
+    tracing::info!("web ui listening on http://{addr}");
+
+    if let Err(e) = axum::serve(listener, app).await {
+        tracing::error!("web server error: {e}");
+    }
+}
+
+/// Serve plain HTTP on a unix domain socket, replacing a stale socket
+/// file left behind by a previous run.  `mode` sets the socket's
+/// permissions.
+#[cfg(unix)]
+async fn serve_unix(path: PathBuf, app: Router, mode: Option<u32>) {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    if std::fs::symlink_metadata(&path).is_ok_and(|m| m.file_type().is_socket()) {
+        let _ = std::fs::remove_file(&path);
+    }
+    let listener = match tokio::net::UnixListener::bind(&path) {
+        Ok(l) => l,
+        Err(e) => {
+            tracing::error!("failed to bind web socket {}: {e}", path.display());
+            return;
+        }
+    };
+    if let Some(mode) = mode {
+        if let Err(e) = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)) {
+            tracing::error!("failed to set mode {mode:o} on {}: {e}", path.display());
+            return;
+        }
+    }
+
+    tracing::info!("web ui listening on unix:{}", path.display());
+
+    if let Err(e) = axum::serve(listener, app).await {
+        tracing::error!("web server error: {e}");
+    }
+}
+
+#[cfg(not(unix))]
+async fn serve_unix(path: PathBuf, _app: Router, _mode: Option<u32>) {
+    tracing::error!(
+        "WEB_BIND unix:{} — unix sockets are not supported on this platform",
+        path.display()
+    );
+}
+
+/// Serve HTTPS using `axum-server` with `rustls`.
+#[cfg(feature = "tls")]
+async fn serve_https(addr: SocketAddr, app: Router, config: axum_server::tls_rustls::RustlsConfig) {
+    tracing::info!("web ui listening on https://{addr}");
+
+    if let Err(e) = axum_server::bind_rustls(addr, config)
+        .serve(app.into_make_service())
+        .await
+    {
+        tracing::error!("web server error: {e}");
+    }
+}
+
+/// How often the TLS cert and key files are checked for rotation.
+#[cfg(feature = "tls")]
+const TLS_RELOAD_CHECK: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Reload the certificate when either PEM file's modification time changes
+/// (e.g. a renewal cron job or `tailscale cert`).  New connections get the
+/// new certificate; open ones keep theirs.  A reload that fails (say the
+/// key isn't written yet) keeps the old certificate and is retried.
+#[cfg(feature = "tls")]
+async fn watch_tls_files(config: axum_server::tls_rustls::RustlsConfig, cert: String, key: String) {
+    let modified = || {
+        let mtime = |p: &str| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+        (mtime(&cert), mtime(&key))
+    };
+    let mut loaded = modified();
+    let mut tick = tokio::time::interval(TLS_RELOAD_CHECK);
+    loop {
+        tick.tick().await;
+        let current = modified();
+        if current == loaded {
+            continue;
+        }
+        match config.reload_from_pem_file(&cert, &key).await {
+            Ok(()) => {
+                tracing::info!(cert = %cert, "reloaded TLS certificate");
+                loaded = current;
+            }
+            Err(e) => {
+                tracing::warn!(cert = %cert, key = %key, "TLS certificate reload failed: {e}")
+            }
+        }
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Db, EventDetails, SchedulerDecision, SchedulerZoneState};
+    use crate::logs::LogRecord;
+    use crate::state::SystemState;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt;
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+    use tower::ServiceExt; // for `oneshot`
+
+    // -----------------------------------------------------------------------
+    // Helpers
+    // -----------------------------------------------------------------------
+
+    /// Build an AppState backed by an in-memory SQLite DB for testing.
+    async fn test_state() -> AppState {
+        let db = Db::connect("sqlite::memory:").await.unwrap();
+        db.migrate().await.unwrap();
+
+        let zones = vec![("zone1".to_string(), 17), ("zone2".to_string(), 27)];
+        let mut st = SystemState::new(&zones, "auto");
+        st.limits.max_concurrent_valves = 2;
+        let shared = Arc::new(RwLock::new(st));
+
+        AppState {
+            status: state::status_snapshot(&shared).await,
+            shared,
+            db,
+            node_settings: Arc::new(Notify::new()),
+            node_commands: mpsc::channel(1).0,
+            session_cancels: mpsc::channel(1).0,
+            water_now: mpsc::channel(1).0,
+            restore: RestoreApi::new(None, tokio::sync::mpsc::channel(1).0),
+            confirm: ConfirmApi::default(),
+            ota: OtaApi {
+                config: ota::OtaConfig::default(),
+                announcements: mpsc::channel(1).0,
+            },
+            tokens: Arc::new(ApiTokens::default()),
+            telemetry: mpsc::channel(1).0,
+            node_tokens: Arc::new(NodeTokens::default()),
+            kiosk: KioskAccess::default(),
+        }
+    }
+
+    fn get_req(uri: &str) -> Request<Body> {
+        Request::builder().uri(uri).body(Body::empty()).unwrap()
+    }
+
+    fn put_json(uri: &str, body: serde_json::Value) -> Request<Body> {
+        Request::builder()
+            .method("PUT")
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::to_vec(&body).unwrap()))
+            .unwrap()
+    }
+
+    fn post_req(uri: &str) -> Request<Body> {
+        Request::builder()
+            .method("POST")
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn post_json(uri: &str, body: serde_json::Value) -> Request<Body> {
+        Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::to_vec(&body).unwrap()))
+            .unwrap()
+    }
+
+    fn delete_req(uri: &str) -> Request<Body> {
+        Request::builder()
+            .method("DELETE")
             .uri(uri)
             .body(Body::empty())
             .unwrap()
     }
 
-    async fn body_json(resp: axum::response::Response) -> serde_json::Value {
-        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
-        serde_json::from_slice(&bytes).unwrap()
+    async fn body_json(resp: axum::response::Response) -> serde_json::Value {
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    fn sample_zone_json() -> serde_json::Value {
+        serde_json::json!({
+            "name": "Front Lawn",
+            "min_moisture": 0.3,
+            "target_moisture": 0.5,
+            "pulse_sec": 30,
+            "soak_min": 20,
+            "max_open_sec_per_day": 180,
+            "max_pulses_per_day": 6,
+            "stale_timeout_min": 30,
+            "valve_gpio_pin": 17
+        })
+    }
+
+    fn sample_sensor_json(zone_id: &str) -> serde_json::Value {
+        serde_json::json!({
+            "node_id": "node-a",
+            "zone_id": zone_id,
+            "raw_dry": 30000,
+            "raw_wet": 10000
+        })
+    }
+
+    // -----------------------------------------------------------------------
+    // Static routes
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn index_returns_html() {
+        let app = router(test_state().await);
+        let resp = app.oneshot(get_req("/")).await.unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        let ct = resp
+            .headers()
+            .get("content-type")
+            .unwrap()
+            .to_str()
+            .unwrap();
+        assert!(ct.contains("text/html"));
+    }
+
+    #[tokio::test]
+    async fn kiosk_access_modes() {
+        let mut state = test_state().await;
+        router(state.clone())
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .await
+            .unwrap();
+        state.tokens = Arc::new(ApiTokens::parse(Some("root"), Some("wall:viewer:wv")).unwrap());
+
+        // Public by default, even with tokens configured.
+        let resp = router(state.clone())
+            .oneshot(get_req("/kiosk"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        assert!(String::from_utf8_lossy(&bytes).contains("Front Lawn"));
+
+        state.kiosk = KioskAccess::Token;
+        for (uri, status) in [
+            ("/kiosk", StatusCode::UNAUTHORIZED),
+            ("/kiosk?token=nope", StatusCode::UNAUTHORIZED),
+            ("/kiosk?token=wv", StatusCode::OK),
+        ] {
+            let resp = router(state.clone()).oneshot(get_req(uri)).await.unwrap();
+            assert_eq!(resp.status(), status, "{uri}");
+        }
+
+        state.kiosk = KioskAccess::Off;
+        let resp = router(state).oneshot(get_req("/kiosk")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn status_changes_since_seq() {
+        let state = test_state().await;
+        let app = || router(state.clone());
+        state.shared.write().await.record_valve("zone1", true);
+        let json = body_json(app().oneshot(get_req("/api/status/changes")).await.unwrap()).await;
+        let seq = json["seq"].as_u64().unwrap();
+        assert_eq!(json["events"][0]["detail"], "zone1 set ON");
+        assert_eq!(json["zones"]["zone1"]["on"], true);
+        assert!(json["zones"].get("zone2").is_none());
+        assert_eq!(json["reset"], false);
+
+        // Nothing new: empty, unless the long-poll sees a change.
+        let uri = format!("/api/status/changes?since_seq={seq}");
+        let json = body_json(app().oneshot(get_req(&uri)).await.unwrap()).await;
+        assert_eq!(json["seq"], seq);
+        assert!(json["events"].as_array().unwrap().is_empty());
+        assert!(json["zones"].as_object().unwrap().is_empty());
+
+        let shared = state.shared.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            shared.write().await.record_valve("zone2", true);
+        });
+        let uri = format!("/api/status/changes?since_seq={seq}&wait_sec=10");
+        let json = body_json(app().oneshot(get_req(&uri)).await.unwrap()).await;
+        assert_eq!(json["events"].as_array().unwrap().len(), 1);
+        assert_eq!(json["zones"]["zone2"]["on"], true);
+
+        // A sequence number from before a restart.
+        let json = body_json(
+            app()
+                .oneshot(get_req("/api/status/changes?since_seq=1000"))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(json["reset"], true);
+        let resp = app()
+            .oneshot(get_req("/api/status/changes?wait_sec=61"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn api_status_returns_json_with_expected_fields() {
+        let app = router(test_state().await);
+        let resp = app.oneshot(get_req("/api/status")).await.unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp).await;
+
+        assert!(json["uptime_secs"].is_u64());
+        assert!(json["mqtt_connected"].is_boolean());
+        assert!(json["nodes"].is_object());
+        assert!(json["zones"].is_object());
+        assert!(json["events"].is_array());
+        assert!(json["zones"]["zone1"].is_object());
+        assert!(json["zones"]["zone2"].is_object());
+        assert_eq!(json["hub_version"]["version"], env!("CARGO_PKG_VERSION"));
+        assert!(json["hub_version"]["git"].is_string());
+    }
+
+    #[tokio::test]
+    async fn api_status_serves_published_snapshot() {
+        let state = test_state().await;
+        state.shared.write().await.mqtt_connected = true;
+
+        // Served from the snapshot, not the live state, until republished.
+        let app = router(state.clone());
+        let json = body_json(app.oneshot(get_req("/api/status")).await.unwrap()).await;
+        assert_eq!(json["mqtt_connected"], false);
+
+        // A held write lock doesn't block polling.
+        let guard = state.shared.write().await;
+        let app = router(state.clone());
+        let json = body_json(app.oneshot(get_req("/api/status")).await.unwrap()).await;
+        assert_eq!(json["mqtt_connected"], false);
+        drop(guard);
+
+        state::publish_status(&state.shared, &state.status).await;
+        let app = router(state);
+        let json = body_json(app.oneshot(get_req("/api/status")).await.unwrap()).await;
+        assert_eq!(json["mqtt_connected"], true);
+    }
+
+    #[tokio::test]
+    async fn api_limits_reports_running_envelope() {
+        let state = test_state().await;
+        let app = router(state.clone());
+        app.clone()
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .await
+            .unwrap();
+        let zone = state.db.get_zone("z1").await.unwrap().unwrap();
+        {
+            let mut st = state.shared.write().await;
+            st.limits.max_concurrent_valves = 2;
+            st.limits.watchdog.margin_sec = 30;
+            st.limits.zones = crate::limits::zone_limits(
+                &std::collections::HashMap::from([("z1".to_string(), zone)]),
+                &crate::flush::FlushPlan::default(),
+                |_| 60,
+            );
+        }
+        let resp = app.oneshot(get_req("/api/limits")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp).await;
+        assert_eq!(json["max_concurrent_valves"], 2);
+        assert_eq!(json["watchdog"]["margin_sec"], 30);
+        assert_eq!(json["zones"][0]["zone_id"], "z1");
+        assert_eq!(json["zones"][0]["watchdog_max_open_sec"], 60);
+        assert!(json["zones"][0]["flush_sec"].is_null());
+        assert!(json["ingest"]["max_json_payload_bytes"].is_number());
+    }
+
+    #[tokio::test]
+    async fn metrics_exposes_valve_latency_histogram() {
+        let state = test_state().await;
+        state.shared.write().await.metrics.observe_valve_latency(
+            crate::metrics::CommandSource::Scheduler,
+            crate::metrics::LatencyStage::Propagation,
+            std::time::Duration::from_millis(40),
+        );
+        let app = router(state);
+        let resp = app.oneshot(get_req("/metrics")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers()[header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain"));
+
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let text = String::from_utf8(bytes.to_vec()).unwrap();
+        assert!(text.contains(
+            "irrigation_valve_command_latency_seconds_count{source=\"scheduler\",stage=\"propagation\"} 1"
+        ));
+        assert!(text.contains("irrigation_db_statements_retried_total 0"));
+    }
+
+    #[tokio::test]
+    async fn api_health_returns_json() {
+        let app = router(test_state().await);
+        let resp = app.oneshot(get_req("/api/health")).await.unwrap();
+        // DB is connected but MQTT is not, so expect 503
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let json = body_json(resp).await;
+        assert_eq!(json["status"], "degraded");
+        assert_eq!(json["db_connected"], true);
+        assert_eq!(json["mqtt_connected"], false);
+        assert_eq!(json["scheduler"]["alive"], false);
+        assert_eq!(json["watchdog"]["enabled"], true);
+        assert!(json["last_backup_at"].is_null());
+        assert_eq!(json["tasks_restarted"], 0);
+    }
+
+    #[tokio::test]
+    async fn api_health_ok_when_all_components_alive() {
+        let state = test_state().await;
+        {
+            let mut st = state.shared.write().await;
+            st.mqtt_connected = true;
+            st.scheduler_heartbeat = Some(OffsetDateTime::now_utc());
+            st.watchdog_heartbeat = Some(OffsetDateTime::now_utc());
+        }
+        let app = router(state);
+        let resp = app.oneshot(get_req("/api/health")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let json = body_json(resp).await;
+        assert_eq!(json["status"], "healthy");
+        assert_eq!(json["scheduler"]["alive"], true);
+        assert!(json["scheduler"]["heartbeat_age_sec"].is_i64());
+    }
+
+    #[tokio::test]
+    async fn unknown_route_returns_404() {
+        let app = router(test_state().await);
+        let resp = app.oneshot(get_req("/nonexistent")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    // -----------------------------------------------------------------------
+    // Zones — list
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn api_zones_returns_empty_array_when_no_zones() {
+        let app = router(test_state().await);
+        let resp = app.oneshot(get_req("/api/zones")).await.unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp).await;
+        assert!(json.is_array());
+        assert!(json.as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn api_zones_returns_inserted_zone() {
+        let state = test_state().await;
+        state.db.upsert_zone(&ZoneConfig::test("z1")).await.unwrap();
+
+        let app = router(state);
+        let resp = app.oneshot(get_req("/api/zones")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let json = body_json(resp).await;
+        assert_eq!(json.as_array().unwrap().len(), 1);
+        assert_eq!(json[0]["zone_id"], "z1");
+    }
+
+    // -----------------------------------------------------------------------
+    // Zones — CRUD
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn put_zone_creates_and_returns_zone() {
+        let app = router(test_state().await);
+        let resp = app
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp).await;
+        assert_eq!(json["zone_id"], "z1");
+        assert_eq!(json["name"], "Front Lawn");
+    }
+
+    #[tokio::test]
+    async fn put_zone_then_get_returns_same() {
+        let app = router(test_state().await);
+        app.clone()
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .await
+            .unwrap();
+
+        let resp = app.oneshot(get_req("/api/zones/z1")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let json = body_json(resp).await;
+        assert_eq!(json["zone_id"], "z1");
+        assert_eq!(json["name"], "Front Lawn");
+        assert_eq!(json["pulse_sec"], 30);
+    }
+
+    #[tokio::test]
+    async fn put_zone_upsert_updates_existing() {
+        let app = router(test_state().await);
+        app.clone()
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .await
+            .unwrap();
+
+        let mut updated = sample_zone_json();
+        updated["name"] = serde_json::json!("Back Yard");
+        app.clone()
+            .oneshot(put_json("/api/zones/z1", updated))
+            .await
+            .unwrap();
+
+        let resp = app.oneshot(get_req("/api/zones/z1")).await.unwrap();
+        let json = body_json(resp).await;
+        assert_eq!(json["name"], "Back Yard");
+    }
+
+    #[tokio::test]
+    async fn get_zone_missing_returns_404() {
+        let app = router(test_state().await);
+        let resp = app.oneshot(get_req("/api/zones/nope")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let json = body_json(resp).await;
+        assert_eq!(json["error"], "not_found");
+    }
+
+    #[tokio::test]
+    async fn zones_include_odometer_and_service_resets_it() {
+        let state = test_state().await;
+        let app = router(state.clone());
+        app.clone()
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .await
+            .unwrap();
+        state.db.add_pulse("2025-06-01", "z1", 4).await.unwrap();
+        state
+            .db
+            .add_open_seconds("2025-06-01", "z1", 120)
+            .await
+            .unwrap();
+        state.db.mark_service_due("z1", 1000).await.unwrap();
+
+        let resp = app.clone().oneshot(get_req("/api/zones")).await.unwrap();
+        let json = body_json(resp).await;
+        assert_eq!(json[0]["odometer"]["actuations"], 4);
+        assert_eq!(json[0]["odometer"]["open_sec"], 120);
+        assert_eq!(json[0]["odometer"]["service_due_ts"], 1000);
+
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/zones/z1/odometer/service"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp).await;
+        assert_eq!(json["actuations"], 4);
+        assert_eq!(json["actuations_since_service"], 0);
+        assert!(json["service_due_ts"].is_null());
+
+        let resp = app.clone().oneshot(get_req("/api/zones/z1")).await.unwrap();
+        let json = body_json(resp).await;
+        assert_eq!(json["name"], "Front Lawn");
+        assert_eq!(json["odometer"]["open_sec_since_service"], 0);
+        assert!(state
+            .shared
+            .read()
+            .await
+            .events
+            .iter()
+            .any(|e| matches!(e.kind, crate::state::EventKind::Maintenance)));
+
+        let resp = app
+            .oneshot(post_req("/api/zones/nope/odometer/service"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn delete_zone_removes_it() {
+        let app = router(test_state().await);
+        app.clone()
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .await
+            .unwrap();
+
+        let resp = app
+            .clone()
+            .oneshot(delete_req("/api/zones/z1"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+        let resp = app.oneshot(get_req("/api/zones/z1")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn confirmed_deletes_need_a_token() {
+        let mut state = test_state().await;
+        state.confirm = ConfirmApi::new(true);
+        let app = router(state);
+        app.clone()
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .await
+            .unwrap();
+        app.clone()
+            .oneshot(put_json("/api/sensors/s1", sample_sensor_json("z1")))
+            .await
+            .unwrap();
+
+        let resp = app
+            .clone()
+            .oneshot(delete_req("/api/sensors/nope"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let resp = app
+            .clone()
+            .oneshot(delete_req("/api/sensors/s1"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        let json = body_json(resp).await;
+        assert_eq!(json["sensor_id"], "s1");
+        let token = json["confirm"].as_str().unwrap().to_string();
+        // The sensor is still there, and a zone token doesn't delete it.
+        let resp = app
+            .clone()
+            .oneshot(get_req("/api/sensors/s1"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = app
+            .clone()
+            .oneshot(delete_req("/api/zones/z1"))
+            .await
+            .unwrap();
+        let zone_token = body_json(resp).await["confirm"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let resp = app
+            .clone()
+            .oneshot(delete_req(&format!("/api/sensors/s1?confirm={zone_token}")))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let resp = app
+            .clone()
+            .oneshot(delete_req(&format!("/api/sensors/s1?confirm={token}")))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let resp = app
+            .clone()
+            .oneshot(delete_req(&format!("/api/zones/z1?confirm={zone_token}")))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let resp = app.oneshot(get_req("/api/zones/z1")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn delete_zone_missing_returns_404() {
+        let app = router(test_state().await);
+        let resp = app.oneshot(delete_req("/api/zones/nope")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn delete_zone_with_sensors_returns_409() {
+        let app = router(test_state().await);
+        app.clone()
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .await
+            .unwrap();
+        app.clone()
+            .oneshot(put_json("/api/sensors/s1", sample_sensor_json("z1")))
+            .await
+            .unwrap();
+
+        let resp = app.oneshot(delete_req("/api/zones/z1")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let json = body_json(resp).await;
+        assert!(json["message"]
+            .as_str()
+            .unwrap()
+            .contains("/api/zones/z1/archive"));
+    }
+
+    #[tokio::test]
+    async fn archived_zones_are_hidden_but_kept() {
+        let state = test_state().await;
+        let app = router(state.clone());
+        for zone_id in ["z1", "z2"] {
+            app.clone()
+                .oneshot(put_json(
+                    &format!("/api/zones/{zone_id}"),
+                    sample_zone_json(),
+                ))
+                .await
+                .unwrap();
+        }
+        app.clone()
+            .oneshot(put_json("/api/sensors/s1", sample_sensor_json("z1")))
+            .await
+            .unwrap();
+        state
+            .db
+            .insert_reading(1000, "s1", 20000, 0.4)
+            .await
+            .unwrap();
+
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/zones/z1/archive"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(body_json(resp).await["archived_at"].is_i64());
+
+        // Gone from scheduling and the default listing, still readable.
+        let ids = |json: serde_json::Value| -> Vec<String> {
+            json.as_array()
+                .unwrap()
+                .iter()
+                .map(|z| z["zone_id"].as_str().unwrap().to_string())
+                .collect()
+        };
+        let resp = app.clone().oneshot(get_req("/api/zones")).await.unwrap();
+        assert_eq!(ids(body_json(resp).await), ["z2"]);
+        let resp = app
+            .clone()
+            .oneshot(get_req("/api/zones?include_archived=true"))
+            .await
+            .unwrap();
+        assert_eq!(ids(body_json(resp).await), ["z1", "z2"]);
+        assert_eq!(state.db.load_zones().await.unwrap().len(), 1);
+        let resp = app.clone().oneshot(get_req("/api/zones/z1")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = app
+            .clone()
+            .oneshot(get_req("/api/readings?zone_id=z1"))
+            .await
+            .unwrap();
+        assert_eq!(body_json(resp).await.as_array().unwrap().len(), 1);
+
+        // Updates keep the archive; new sensors can't join it.
+        let resp = app
+            .clone()
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .await
+            .unwrap();
+        assert!(body_json(resp).await["archived_at"].is_i64());
+        let resp = app
+            .clone()
+            .oneshot(put_json("/api/sensors/s2", sample_sensor_json("z1")))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let resp = app
+            .clone()
+            .oneshot(delete_req("/api/zones/z1/archive"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(body_json(resp).await["archived_at"].is_null());
+        assert_eq!(state.db.load_zones().await.unwrap().len(), 2);
+
+        let resp = app
+            .oneshot(post_req("/api/zones/nope/archive"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn archive_refuses_an_open_valve() {
+        let state = test_state().await;
+        let app = router(state.clone());
+        app.clone()
+            .oneshot(put_json("/api/zones/zone1", sample_zone_json()))
+            .await
+            .unwrap();
+        state
+            .shared
+            .write()
+            .await
+            .zones
+            .get_mut("zone1")
+            .unwrap()
+            .on = true;
+        let resp = app
+            .oneshot(post_req("/api/zones/zone1/archive"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+    }
+
+    // -----------------------------------------------------------------------
+    // Zone validation
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn put_zone_bad_moisture_returns_422() {
+        let app = router(test_state().await);
+        let mut bad = sample_zone_json();
+        bad["min_moisture"] = serde_json::json!(1.5);
+        bad["target_moisture"] = serde_json::json!(-0.1);
+
+        let resp = app.oneshot(put_json("/api/zones/z1", bad)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let json = body_json(resp).await;
+        assert_eq!(json["error"], "validation");
+        let msgs = json["messages"].as_array().unwrap();
+        assert!(msgs.len() >= 2);
+    }
+
+    #[tokio::test]
+    async fn put_zone_target_below_min_returns_422() {
+        let app = router(test_state().await);
+        let mut bad = sample_zone_json();
+        bad["min_moisture"] = serde_json::json!(0.6);
+        bad["target_moisture"] = serde_json::json!(0.3);
+
+        let resp = app.oneshot(put_json("/api/zones/z1", bad)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn put_zone_empty_name_returns_422() {
+        let app = router(test_state().await);
+        let mut bad = sample_zone_json();
+        bad["name"] = serde_json::json!("");
+
+        let resp = app.oneshot(put_json("/api/zones/z1", bad)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn put_zone_negative_pulse_sec_returns_422() {
+        let app = router(test_state().await);
+        let mut bad = sample_zone_json();
+        bad["pulse_sec"] = serde_json::json!(-1);
+
+        let resp = app.oneshot(put_json("/api/zones/z1", bad)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    // -----------------------------------------------------------------------
+    // Zones — disturbances
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn disturbance_lifecycle() {
+        let state = test_state().await;
+        let app = router(state.clone());
+        app.clone()
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .await
+            .unwrap();
+        let window = crate::moisture::MoistureWindow::load(&state.db)
+            .await
+            .unwrap();
+        state.shared.write().await.moisture.replace(window);
+
+        let resp = app
+            .clone()
+            .oneshot(post_json(
+                "/api/zones/z1/disturbances",
+                serde_json::json!({"start_ts": 1000, "reason": "bed re-dug"}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        // The scheduler reloads its moisture window to pick it up.
+        assert!(!state.shared.read().await.moisture.is_loaded());
+        let created = body_json(resp).await;
+        assert_eq!(created["zone_id"], "z1");
+        assert!(created["end_ts"].is_null());
+        let id = created["id"].as_i64().unwrap();
+
+        // Close it.
+        let resp = app
+            .clone()
+            .oneshot(put_json(
+                &format!("/api/zones/z1/disturbances/{id}"),
+                serde_json::json!({"end_ts": 2000, "reason": "bed re-dug"}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let updated = body_json(resp).await;
+        assert_eq!(updated["start_ts"], 1000);
+        assert_eq!(updated["end_ts"], 2000);
+
+        let resp = app
+            .clone()
+            .oneshot(get_req("/api/zones/z1/disturbances"))
+            .await
+            .unwrap();
+        let list = body_json(resp).await;
+        assert_eq!(list.as_array().unwrap().len(), 1);
+
+        let uri = format!("/api/zones/z1/disturbances/{id}");
+        let resp = app.clone().oneshot(delete_req(&uri)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let resp = app.oneshot(delete_req(&uri)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn disturbance_validation_and_unknown_zone() {
+        let app = router(test_state().await);
+        let resp = app
+            .clone()
+            .oneshot(post_json(
+                "/api/zones/nope/disturbances",
+                serde_json::json!({"reason": "probe cleaning"}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let resp = app
+            .oneshot(post_json(
+                "/api/zones/z1/disturbances",
+                serde_json::json!({"start_ts": 2000, "end_ts": 1000, "reason": " "}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let json = body_json(resp).await;
+        assert_eq!(json["messages"].as_array().unwrap().len(), 2);
+    }
+
+    // -----------------------------------------------------------------------
+    // Blackout calendar
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn blackout_lifecycle() {
+        let state = test_state().await;
+        let app = router(state.clone());
+
+        let resp = app
+            .clone()
+            .oneshot(post_json(
+                "/api/blackouts",
+                serde_json::json!({"start": "11-01", "end": "03-15", "reason": "winter"}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let id = body_json(resp).await["id"].as_i64().unwrap();
+        assert!(state
+            .shared
+            .read()
+            .await
+            .blackouts
+            .active(time::macros::date!(2027 - 01 - 10))
+            .is_some());
+
+        let resp = app
+            .clone()
+            .oneshot(put_json(
+                &format!("/api/blackouts/{id}"),
+                serde_json::json!({"start": "2026-07-10", "end": "2026-07-24", "reason": "away"}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let list = body_json(
+            app.clone()
+                .oneshot(get_req("/api/blackouts"))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(list[0]["reason"], "away");
+        assert!(state
+            .shared
+            .read()
+            .await
+            .blackouts
+            .active(time::macros::date!(2027 - 01 - 10))
+            .is_none());
+
+        let resp = app
+            .clone()
+            .oneshot(post_json(
+                "/api/blackouts",
+                serde_json::json!({"start": "2026-07-10", "end": "07-24", "reason": ""}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            body_json(resp).await["messages"].as_array().unwrap().len(),
+            2
+        );
+
+        let resp = app
+            .clone()
+            .oneshot(delete_req(&format!("/api/blackouts/{id}")))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let resp = app
+            .oneshot(delete_req(&format!("/api/blackouts/{id}")))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert!(state.db.list_blackouts().await.unwrap().is_empty());
+    }
+
+    // -----------------------------------------------------------------------
+    // Evapotranspiration
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn et_shows_days_and_zone_deficits() {
+        let state = test_state().await;
+        let app = router(state.clone());
+
+        let mut zone = sample_zone_json();
+        zone["strategy"] = serde_json::json!({
+            "kind": "et",
+            "application_rate_mm_hr": 12.0,
+            "allowed_depletion_mm": 8.0
+        });
+        let resp = app
+            .clone()
+            .oneshot(put_json("/api/zones/lawn", zone))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = app
+            .clone()
+            .oneshot(put_json("/api/zones/beds", sample_zone_json()))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        for (date, et0_mm) in [("2026-07-05", 4.2), ("2026-07-06", 5.1)] {
+            state
+                .db
+                .upsert_et_day(&EtDay {
+                    date: date.into(),
+                    et0_mm,
+                    rain_mm: 0.0,
+                    method: crate::et::EtMethod::Hargreaves,
+                })
+                .await
+                .unwrap();
+        }
+        state
+            .shared
+            .write()
+            .await
+            .et_deficits
+            .insert("lawn".into(), 6.5);
+
+        let json = body_json(app.oneshot(get_req("/api/et?days=1")).await.unwrap()).await;
+        assert_eq!(json["today"], serde_json::Value::Null);
+        assert_eq!(json["days"].as_array().unwrap().len(), 1);
+        assert_eq!(json["days"][0]["date"], "2026-07-06");
+        assert_eq!(json["days"][0]["method"], "hargreaves");
+        assert_eq!(
+            json["zones"],
+            serde_json::json!([
+                {"zone_id": "lawn", "deficit_mm": 6.5, "allowed_depletion_mm": 8.0}
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn et_strategy_is_validated() {
+        let app = router(test_state().await);
+        let mut zone = sample_zone_json();
+        zone["strategy"] = serde_json::json!({
+            "kind": "et",
+            "application_rate_mm_hr": 0,
+            "allowed_depletion_mm": 8.0
+        });
+        let resp = app
+            .oneshot(put_json("/api/zones/lawn", zone))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    // -----------------------------------------------------------------------
+    // Zones — flow trend
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn zone_flow_trend() {
+        let state = test_state().await;
+        let app = router(state.clone());
+        app.clone()
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .await
+            .unwrap();
+
+        let resp = app
+            .clone()
+            .oneshot(get_req("/api/zones/z1/flow"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(body_json(resp).await.is_null());
+
+        // Two weeks of flow falling from 6 to ~4 L/min at steady pressure.
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        for n in 0..14 {
+            let ts = now - (13 - n) * 86400;
+            let lpm = 6.0 - 0.15 * n as f64;
+            state
+                .db
+                .insert_flow_reading(ts, "z1", lpm, Some(300.0))
+                .await
+                .unwrap();
+        }
+
+        let resp = app
+            .clone()
+            .oneshot(get_req("/api/zones/z1/flow"))
+            .await
+            .unwrap();
+        let json = body_json(resp).await;
+        assert_eq!(json["clog_suspected"], true);
+        assert_eq!(json["days"].as_array().unwrap().len(), 14);
+
+        let resp = app.oneshot(get_req("/api/zones/nope/flow")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    // -----------------------------------------------------------------------
+    // Sensors — CRUD
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn put_sensor_creates_and_returns_sensor() {
+        let app = router(test_state().await);
+        app.clone()
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .await
+            .unwrap();
+
+        let resp = app
+            .oneshot(put_json("/api/sensors/s1", sample_sensor_json("z1")))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let json = body_json(resp).await;
+        assert_eq!(json["sensor_id"], "s1");
+        assert_eq!(json["zone_id"], "z1");
+    }
+
+    #[tokio::test]
+    async fn get_sensors_list() {
+        let app = router(test_state().await);
+        app.clone()
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .await
+            .unwrap();
+        app.clone()
+            .oneshot(put_json("/api/sensors/s1", sample_sensor_json("z1")))
+            .await
+            .unwrap();
+        app.clone()
+            .oneshot(put_json("/api/sensors/s2", sample_sensor_json("z1")))
+            .await
+            .unwrap();
+
+        let resp = app.oneshot(get_req("/api/sensors")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let json = body_json(resp).await;
+        assert_eq!(json.as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn get_sensor_single() {
+        let app = router(test_state().await);
+        app.clone()
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .await
+            .unwrap();
+        app.clone()
+            .oneshot(put_json("/api/sensors/s1", sample_sensor_json("z1")))
+            .await
+            .unwrap();
+
+        let resp = app.oneshot(get_req("/api/sensors/s1")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let json = body_json(resp).await;
+        assert_eq!(json["sensor_id"], "s1");
+        assert_eq!(json["node_id"], "node-a");
+    }
+
+    #[tokio::test]
+    async fn get_sensor_missing_returns_404() {
+        let app = router(test_state().await);
+        let resp = app.oneshot(get_req("/api/sensors/nope")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn delete_sensor_removes_it() {
+        let app = router(test_state().await);
+        app.clone()
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .await
+            .unwrap();
+        app.clone()
+            .oneshot(put_json("/api/sensors/s1", sample_sensor_json("z1")))
+            .await
+            .unwrap();
+
+        let resp = app
+            .clone()
+            .oneshot(delete_req("/api/sensors/s1"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+        let resp = app.oneshot(get_req("/api/sensors/s1")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn delete_sensor_missing_returns_404() {
+        let app = router(test_state().await);
+        let resp = app.oneshot(delete_req("/api/sensors/nope")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn safety_review_acknowledged_per_zone() {
+        let state = test_state().await;
+        let finding = |zone_id: &str| Finding {
+            zone_id: zone_id.into(),
+            code: "no_sensors",
+            detail: "no active sensors: watering has no moisture feedback".into(),
+            acknowledged: false,
+        };
+        state
+            .shared
+            .write()
+            .await
+            .set_safety_review(vec![finding("zone1"), finding("zone2")]);
+        let app = router(state.clone());
+
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/safety-review/zone1/ack"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(body_json(resp).await.as_array().unwrap().len(), 1);
+        {
+            let st = state.shared.read().await;
+            assert!(!st.needs_safety_review("zone1"));
+            assert!(st.needs_safety_review("zone2"));
+        }
+
+        // Persisted: a fresh review of the same config comes back acknowledged.
+        let mut again = vec![finding("zone1"), finding("zone2")];
+        state.db.mark_acknowledged(&mut again).await.unwrap();
+        assert!(again[0].acknowledged);
+        assert!(!again[1].acknowledged);
+
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/safety-review/zone1/ack"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let json = body_json(app.oneshot(get_req("/api/safety-review")).await.unwrap()).await;
+        assert_eq!(json[0]["acknowledged"], true);
+        assert_eq!(json[1]["acknowledged"], false);
+    }
+
+    #[tokio::test]
+    async fn emergency_stop_cleared_once_released() {
+        let state = test_state().await;
+        let app = router(state.clone());
+        let clear = || post_req("/api/emergency-stop/clear");
+
+        let resp = app.clone().oneshot(clear()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        state.db.save_estop_latch(Some(1000)).await.unwrap();
+        {
+            let mut st = state.shared.write().await;
+            st.estop.latch(1000);
+            st.estop.pressed = true;
+        }
+        let resp = app.clone().oneshot(clear()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let status = serde_json::to_value(state.shared.read().await.to_status()).unwrap();
+        assert_eq!(status["emergency_stop"]["latched_since"], 1000);
+
+        state.shared.write().await.estop.pressed = false;
+        let resp = app.clone().oneshot(clear()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert!(!state.shared.read().await.estop.is_latched());
+        assert_eq!(state.db.load_estop_latch().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn retention_policy_changed_and_pruned_on_demand() {
+        let state = test_state().await;
+        let app = router(state.clone());
+
+        let json = body_json(
+            app.clone()
+                .oneshot(get_req("/api/retention"))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(json["readings_days"], 90);
+        assert_eq!(json["watering_events_days"], serde_json::Value::Null);
+
+        let resp = app
+            .clone()
+            .oneshot(put_json(
+                "/api/retention",
+                serde_json::json!({ "readings_days": 0 }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let resp = app
+            .clone()
+            .oneshot(put_json(
+                "/api/retention",
+                serde_json::json!({ "readings_days": 60, "watering_events_days": 30 }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let stored = state.db.load_retention_policy().await.unwrap().unwrap();
+        assert_eq!(stored.readings_days, 60);
+        assert_eq!(stored.decisions_days, 30);
+        assert_eq!(state.shared.read().await.retention, stored);
+
+        app.clone()
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .await
+            .unwrap();
+        let old = OffsetDateTime::now_utc().unix_timestamp() - 40 * 86400;
+        state
+            .db
+            .insert_watering_event(
+                old,
+                old + 30,
+                "z1",
+                "scheduler",
+                "ok",
+                EventDetails::default(),
+            )
+            .await
+            .unwrap();
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/maintenance/prune"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp).await;
+        assert_eq!(json["watering_events"], 1);
+        assert_eq!(json["readings"], 0);
+        assert!(json["bytes_reclaimed"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn sensor_trend_validates_range() {
+        let app = router(test_state().await);
+        let resp = app
+            .clone()
+            .oneshot(get_req("/api/sensors/s1/trend"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(body_json(resp).await, serde_json::json!([]));
+
+        for uri in [
+            "/api/sensors/s1/trend?resolution=minute",
+            "/api/sensors/s1/trend?from=100&to=100",
+            "/api/sensors/s1/trend?from=0&to=8000000",
+        ] {
+            let resp = app.clone().oneshot(get_req(uri)).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY, "{uri}");
+        }
+        let resp = app
+            .oneshot(get_req(
+                "/api/sensors/s1/trend?resolution=day&from=0&to=8000000",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn quarantined_sensor_listed_and_released() {
+        let state = test_state().await;
+        state
+            .db
+            .record_sensor_failure("n1/s1", 100, 1)
+            .await
+            .unwrap();
+        state
+            .shared
+            .write()
+            .await
+            .set_sensor_quarantined("n1/s1", true);
+        let app = router(state.clone());
+
+        let resp = app
+            .clone()
+            .oneshot(get_req("/api/sensors/health"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp).await;
+        assert_eq!(json[0]["sensor_id"], "n1/s1");
+        assert_eq!(json[0]["quarantined_ts"], 100);
+
+        let resp = app
+            .clone()
+            .oneshot(delete_req("/api/sensors/n1%2Fs1/quarantine"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert!(!state.shared.read().await.is_sensor_quarantined("n1/s1"));
+
+        // Not quarantined any more.
+        let resp = app
+            .oneshot(delete_req("/api/sensors/n1%2Fs1/quarantine"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    // -----------------------------------------------------------------------
+    // Sensor validation
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn put_sensor_nonexistent_zone_returns_422() {
+        let app = router(test_state().await);
+        let resp = app
+            .oneshot(put_json(
+                "/api/sensors/s1",
+                sample_sensor_json("no-such-zone"),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let json = body_json(resp).await;
+        assert!(json["messages"][0]
+            .as_str()
+            .unwrap()
+            .contains("no-such-zone"));
+    }
+
+    #[tokio::test]
+    async fn put_sensor_equal_dry_wet_returns_422() {
+        let app = router(test_state().await);
+        app.clone()
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .await
+            .unwrap();
+
+        let bad = serde_json::json!({
+            "node_id": "node-a",
+            "zone_id": "z1",
+            "raw_dry": 20000,
+            "raw_wet": 20000
+        });
+        let resp = app.oneshot(put_json("/api/sensors/s1", bad)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn put_sensor_empty_node_id_returns_422() {
+        let app = router(test_state().await);
+        app.clone()
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .await
+            .unwrap();
+
+        let bad = serde_json::json!({
+            "node_id": "",
+            "zone_id": "z1",
+            "raw_dry": 30000,
+            "raw_wet": 10000
+        });
+        let resp = app.oneshot(put_json("/api/sensors/s1", bad)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn put_sensor_failure_margin() {
+        let app = router(test_state().await);
+        app.clone()
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .await
+            .unwrap();
+
+        let mut body = sample_sensor_json("z1");
+        body["failure_margin_pct"] = serde_json::json!(10.0);
+        let resp = app
+            .clone()
+            .oneshot(put_json("/api/sensors/s1", body.clone()))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp).await;
+        assert_eq!(json["failure_margin_pct"], 10.0);
+        assert!(json["failure_margin"].is_null());
+
+        body["failure_margin"] = serde_json::json!(500);
+        let resp = app
+            .oneshot(put_json("/api/sensors/s1", body))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let json = body_json(resp).await;
+        assert_eq!(
+            json["messages"][0],
+            "set failure_margin or failure_margin_pct, not both"
+        );
+    }
+
+    #[tokio::test]
+    async fn put_sensor_curve() {
+        let app = router(test_state().await);
+        app.clone()
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .await
+            .unwrap();
+
+        let mut body = sample_sensor_json("z1");
+        body["curve"] = serde_json::json!({
+            "kind": "table",
+            "points": [[26000, 0.0], [21000, 0.2], [12000, 1.0]]
+        });
+        let resp = app
+            .clone()
+            .oneshot(put_json("/api/sensors/s1", body.clone()))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp).await;
+        assert_eq!(json["curve"]["kind"], "table");
+        assert_eq!(json["curve"]["points"][1][1], 0.2);
+
+        body["curve"] = serde_json::json!({"kind": "table", "points": [[26000, 0.0]]});
+        let resp = app
+            .oneshot(put_json("/api/sensors/s1", body))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let json = body_json(resp).await;
+        assert_eq!(
+            json["messages"][0],
+            "curve: a table needs at least 2 points"
+        );
+    }
+
+    #[tokio::test]
+    async fn recompute_sensor_moisture() {
+        let state = test_state().await;
+        let app = router(state.clone());
+        app.clone()
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .await
+            .unwrap();
+        let mut body = sample_sensor_json("z1");
+        body["raw_dry"] = serde_json::json!(20000);
+        app.clone()
+            .oneshot(put_json("/api/sensors/s1", body.clone()))
+            .await
+            .unwrap();
+        // Stored under the wrong calibration (20000 = 0.0).
+        state
+            .db
+            .insert_reading(1000, "s1", 20000, 0.0)
+            .await
+            .unwrap();
+        state
+            .db
+            .insert_reading(2000, "s1", 20000, 0.0)
+            .await
+            .unwrap();
+
+        body["raw_dry"] = serde_json::json!(30000);
+        app.clone()
+            .oneshot(put_json("/api/sensors/s1", body))
+            .await
+            .unwrap();
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/sensors/s1/recompute?from=1500"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp).await;
+        assert_eq!(json["updated"], 1);
+        assert_eq!(json["from"], 1500);
+
+        let rows = state
+            .db
+            .list_readings(Some("s1"), None, 10, 0)
+            .await
+            .unwrap();
+        let moisture: Vec<(i64, f64)> = rows.iter().map(|r| (r.ts, r.moisture)).collect();
+        assert!(moisture.contains(&(1000, 0.0)));
+        assert!(moisture.contains(&(2000, 0.5)));
+
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/sensors/s1/recompute"))
+            .await
+            .unwrap();
+        assert_eq!(body_json(resp).await["updated"], 2);
+        let resp = app
+            .oneshot(post_req("/api/sensors/nope/recompute"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    // -----------------------------------------------------------------------
+    // Readings (read-only)
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn readings_empty_returns_empty_array() {
+        let app = router(test_state().await);
+        let resp = app.oneshot(get_req("/api/readings")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let json = body_json(resp).await;
+        assert!(json.as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn readings_returns_inserted_data() {
+        let state = test_state().await;
+        state
+            .db
+            .upsert_zone(&ZoneConfig {
+                name: "Z".into(),
+                min_moisture: 0.2,
+                soak_min: 10,
+                max_open_sec_per_day: 120,
+                max_pulses_per_day: 4,
+                ..ZoneConfig::test("z1")
+            })
+            .await
+            .unwrap();
+        state
+            .db
+            .upsert_sensor(&SensorConfig {
+                sensor_id: "s1".into(),
+                node_id: "n1".into(),
+                zone_id: "z1".into(),
+                raw_dry: 30000,
+                raw_wet: 10000,
+                archived_at: None,
+                channel: None,
+                weight: 1.0,
+                failure_margin: None,
+                failure_margin_pct: None,
+                curve: None,
+                enabled: true,
+            })
+            .await
+            .unwrap();
+        state
+            .db
+            .insert_reading(1000, "s1", 20000, 0.5)
+            .await
+            .unwrap();
+        state
+            .db
+            .insert_reading(1001, "s1", 21000, 0.45)
+            .await
+            .unwrap();
+
+        let app = router(state);
+
+        // Filter by sensor_id
+        let resp = app
+            .clone()
+            .oneshot(get_req("/api/readings?sensor_id=s1"))
+            .await
+            .unwrap();
+        let json = body_json(resp).await;
+        assert_eq!(json.as_array().unwrap().len(), 2);
+
+        // Filter by zone_id
+        let resp = app
+            .clone()
+            .oneshot(get_req("/api/readings?zone_id=z1"))
+            .await
+            .unwrap();
+        let json = body_json(resp).await;
+        assert_eq!(json.as_array().unwrap().len(), 2);
+
+        // Limit
+        let resp = app
+            .clone()
+            .oneshot(get_req("/api/readings?limit=1"))
+            .await
+            .unwrap();
+        let json = body_json(resp).await;
+        assert_eq!(json.as_array().unwrap().len(), 1);
+
+        // Offset
+        let resp = app
+            .oneshot(get_req("/api/readings?limit=10&offset=1"))
+            .await
+            .unwrap();
+        let json = body_json(resp).await;
+        assert_eq!(json.as_array().unwrap().len(), 1);
+    }
+
+    // -----------------------------------------------------------------------
+    // Watering events (read-only)
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn watering_events_empty_returns_empty_array() {
+        let app = router(test_state().await);
+        let resp = app.oneshot(get_req("/api/watering-events")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let json = body_json(resp).await;
+        assert!(json.as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn watering_events_returns_inserted_data() {
+        let state = test_state().await;
+        state
+            .db
+            .upsert_zone(&ZoneConfig {
+                name: "Z".into(),
+                min_moisture: 0.2,
+                soak_min: 10,
+                max_open_sec_per_day: 120,
+                max_pulses_per_day: 4,
+                ..ZoneConfig::test("z1")
+            })
+            .await
+            .unwrap();
+        state
+            .db
+            .insert_watering_event(1000, 1030, "z1", "dry", "ok", EventDetails::default())
+            .await
+            .unwrap();
+        state
+            .db
+            .insert_watering_event(2000, 2030, "z1", "dry", "ok", EventDetails::default())
+            .await
+            .unwrap();
+
+        let app = router(state);
+
+        // All events
+        let resp = app
+            .clone()
+            .oneshot(get_req("/api/watering-events"))
+            .await
+            .unwrap();
+        let json = body_json(resp).await;
+        assert_eq!(json.as_array().unwrap().len(), 2);
+
+        // Filter by zone_id
+        let resp = app
+            .clone()
+            .oneshot(get_req("/api/watering-events?zone_id=z1"))
+            .await
+            .unwrap();
+        let json = body_json(resp).await;
+        assert_eq!(json.as_array().unwrap().len(), 2);
+
+        // Limit + offset
+        let resp = app
+            .oneshot(get_req("/api/watering-events?limit=1&offset=1"))
+            .await
+            .unwrap();
+        let json = body_json(resp).await;
+        assert_eq!(json.as_array().unwrap().len(), 1);
+    }
+
+    // -----------------------------------------------------------------------
+    // Scheduler decisions (read-only)
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn scheduler_decisions_filtered_by_zone_and_time() {
+        let state = test_state().await;
+        let decision = |ts: i64, zone_id: &str, blocked_by: Option<&str>| SchedulerDecision {
+            ts,
+            zone_id: zone_id.into(),
+            phase: "idle".into(),
+            avg_moisture: None,
+            blocked_by: blocked_by.map(Into::into),
+            action: if blocked_by.is_some() { "skip" } else { "wait" }.into(),
+            detail: String::new(),
+        };
+        state
+            .db
+            .insert_scheduler_decisions(&[
+                decision(1000, "z1", Some("stale_readings")),
+                decision(2000, "z1", None),
+                decision(2000, "z2", Some("daily_limit")),
+            ])
+            .await
+            .unwrap();
+        let app = router(state);
+
+        let resp = app
+            .clone()
+            .oneshot(get_req("/api/scheduler/decisions?zone_id=z1"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp).await;
+        assert_eq!(json.as_array().unwrap().len(), 2);
+        assert_eq!(json[0]["ts"], 2000);
+        assert_eq!(json[1]["blocked_by"], "stale_readings");
+
+        let resp = app
+            .oneshot(get_req("/api/scheduler/decisions?from=1500&to=2000"))
+            .await
+            .unwrap();
+        let json = body_json(resp).await;
+        assert_eq!(json.as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn scheduler_preview_reports_each_zone() {
+        let state = test_state().await;
+        let app = router(state.clone());
+        for zone_id in ["dry", "capped", "soaking"] {
+            let resp = app
+                .clone()
+                .oneshot(put_json(
+                    &format!("/api/zones/{zone_id}"),
+                    sample_zone_json(),
+                ))
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            state
+                .db
+                .upsert_sensor(&SensorConfig {
+                    sensor_id: format!("s-{zone_id}"),
+                    node_id: "n1".into(),
+                    zone_id: zone_id.into(),
+                    raw_dry: 26000,
+                    raw_wet: 12000,
+                    channel: None,
+                    archived_at: None,
+                    weight: 1.0,
+                    failure_margin: None,
+                    failure_margin_pct: None,
+                    curve: None,
+                    enabled: true,
+                })
+                .await
+                .unwrap();
+            let now = OffsetDateTime::now_utc().unix_timestamp();
+            state
+                .db
+                .insert_reading(now, &format!("s-{zone_id}"), 23000, 0.21)
+                .await
+                .unwrap();
+        }
+        state.db.flush_readings().await.unwrap();
+        state
+            .db
+            .add_pulse(&Db::today_yyyy_mm_dd(), "capped", 6)
+            .await
+            .unwrap();
+        state
+            .db
+            .save_scheduler_state(&SchedulerZoneState {
+                zone_id: "soaking".into(),
+                phase: "soaking".into(),
+                started_ts: 1,
+                until_ts: 2,
+                extended_sec: 0,
+            })
+            .await
+            .unwrap();
+        {
+            let mut st = state.shared.write().await;
+            st.mqtt_connected = true;
+            st.limits.max_concurrent_valves = 2;
+        }
+
+        let resp = app
+            .oneshot(get_req("/api/scheduler/preview"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp).await;
+        let by_zone = |id: &str| {
+            json.as_array()
+                .unwrap()
+                .iter()
+                .find(|z| z["zone_id"] == id)
+                .unwrap()
+                .clone()
+        };
+        let dry = by_zone("dry");
+        assert_eq!(dry["verdict"], "water");
+        assert_eq!(
+            dry["summary"],
+            "would water: threshold: moisture 0.210 < min 0.300"
+        );
+        let capped = by_zone("capped");
+        assert_eq!(capped["verdict"], "blocked");
+        assert_eq!(capped["blocked_by"], "daily_limit");
+        assert!(capped["summary"]
+            .as_str()
+            .unwrap()
+            .starts_with("blocked: daily_limit: 6/6 pulses"));
+        assert_eq!(by_zone("soaking")["verdict"], "busy");
+
+        // Nothing was published or recorded.
+        assert!(state.shared.read().await.sessions.active().is_empty());
+        assert!(state
+            .db
+            .list_scheduler_decisions(None, None, None, 10, 0)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    // -----------------------------------------------------------------------
+    // Daily counters (read-only)
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn counters_returns_zeros_for_no_data() {
+        let state = test_state().await;
+        state
+            .db
+            .upsert_zone(&ZoneConfig {
+                name: "Z".into(),
+                min_moisture: 0.2,
+                soak_min: 10,
+                max_open_sec_per_day: 120,
+                max_pulses_per_day: 4,
+                ..ZoneConfig::test("z1")
+            })
+            .await
+            .unwrap();
+
+        let app = router(state);
+        let resp = app
+            .oneshot(get_req("/api/counters/z1?day=2025-01-01"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let json = body_json(resp).await;
+        assert_eq!(json["open_sec"], 0);
+        assert_eq!(json["pulses"], 0);
     }
 
-    fn sample_zone_json() -> serde_json::Value {
-        serde_json::json!({
-            "name": "Front Lawn",
-            "min_moisture": 0.3,
-            "target_moisture": 0.5,
-            "pulse_sec": 30,
-            "soak_min": 20,
-            "max_open_sec_per_day": 180,
-            "max_pulses_per_day": 6,
-            "stale_timeout_min": 30,
-            "valve_gpio_pin": 17
-        })
+    #[tokio::test]
+    async fn counters_returns_accumulated_data() {
+        let state = test_state().await;
+        state
+            .db
+            .upsert_zone(&ZoneConfig {
+                name: "Z".into(),
+                min_moisture: 0.2,
+                soak_min: 10,
+                max_open_sec_per_day: 120,
+                max_pulses_per_day: 4,
+                ..ZoneConfig::test("z1")
+            })
+            .await
+            .unwrap();
+        state.db.add_pulse("2025-06-01", "z1", 1).await.unwrap();
+        state
+            .db
+            .add_open_seconds("2025-06-01", "z1", 30)
+            .await
+            .unwrap();
+
+        let app = router(state);
+        let resp = app
+            .oneshot(get_req("/api/counters/z1?day=2025-06-01"))
+            .await
+            .unwrap();
+        let json = body_json(resp).await;
+        assert_eq!(json["pulses"], 1);
+        assert_eq!(json["open_sec"], 30);
     }
 
-    fn sample_sensor_json(zone_id: &str) -> serde_json::Value {
-        serde_json::json!({
-            "node_id": "node-a",
-            "zone_id": zone_id,
-            "raw_dry": 30000,
-            "raw_wet": 10000
-        })
+    // -----------------------------------------------------------------------
+    // Nodes
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn put_node_then_get_returns_same() {
+        let app = router(test_state().await);
+        let body = serde_json::json!({
+            "name": "Greenhouse",
+            "sample_interval_sec": 300,
+            "stale_timeout_min": 20
+        });
+        let resp = app
+            .clone()
+            .oneshot(put_json("/api/nodes/node-a", body))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = app.oneshot(get_req("/api/nodes/node-a")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp).await;
+        assert_eq!(json["config"]["name"], "Greenhouse");
+        assert_eq!(json["config"]["sample_interval_sec"], 300);
+        assert!(json["config"]["decommissioned_at"].is_null());
+        // Per-node override wins over the hub-wide default.
+        assert_eq!(json["stale_timeout_min"], 20);
+        // Never heard from → stale, offline.
+        assert_eq!(json["stale"], true);
+        assert_eq!(json["online"], false);
+        assert!(json["firmware"].is_null());
+    }
+
+    #[tokio::test]
+    async fn node_list_shows_announced_firmware() {
+        let state = test_state().await;
+        let shared = state.shared.clone();
+        let app = router(state);
+        for node in ["node-a", "node-b"] {
+            app.clone()
+                .oneshot(put_json(
+                    &format!("/api/nodes/{node}"),
+                    serde_json::json!({ "name": node }),
+                ))
+                .await
+                .unwrap();
+        }
+        {
+            let mut st = shared.write().await;
+            st.record_node_status("node-a", true);
+            st.record_node_firmware(
+                "node-a",
+                Some(Firmware {
+                    version: "0.2.0".to_string(),
+                    git: "1a2b3c4d".to_string(),
+                }),
+            );
+        }
+
+        let json = body_json(app.oneshot(get_req("/api/nodes")).await.unwrap()).await;
+        assert_eq!(json[0]["node_id"], "node-a");
+        assert_eq!(json[0]["name"], "node-a");
+        assert_eq!(json[0]["firmware"]["version"], "0.2.0");
+        assert_eq!(json[0]["firmware"]["git"], "1a2b3c4d");
+        // Never announced a build (or predates version reporting).
+        assert!(json[1]["firmware"].is_null());
+    }
+
+    #[tokio::test]
+    async fn battery_node_stale_after_wake_interval() {
+        let app = router(test_state().await);
+        let body = serde_json::json!({"name": "Far bed", "battery_powered": true});
+        let resp = app
+            .clone()
+            .oneshot(put_json("/api/nodes/node-b", body))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let body = serde_json::json!({
+            "name": "Far bed",
+            "sample_interval_sec": 3600,
+            "battery_powered": true,
+            "wake_grace_factor": 2.0
+        });
+        let resp = app
+            .clone()
+            .oneshot(put_json("/api/nodes/node-b", body))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = app.oneshot(get_req("/api/nodes/node-b")).await.unwrap();
+        let json = body_json(resp).await;
+        assert_eq!(json["config"]["battery_powered"], true);
+        assert_eq!(json["stale_after_sec"], 7200);
+    }
+
+    #[tokio::test]
+    async fn get_node_missing_returns_404() {
+        let app = router(test_state().await);
+        let resp = app.oneshot(get_req("/api/nodes/nope")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn put_node_invalid_returns_422() {
+        let app = router(test_state().await);
+        let body = serde_json::json!({"name": " ", "sample_interval_sec": 0});
+        let resp = app
+            .oneshot(put_json("/api/nodes/node-a", body))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let json = body_json(resp).await;
+        assert_eq!(json["messages"].as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn decommission_node_archives_sensors_and_clears_live_state() {
+        let state = test_state().await;
+        let shared = state.shared.clone();
+        let app = router(state);
+
+        app.clone()
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .await
+            .unwrap();
+        app.clone()
+            .oneshot(put_json("/api/sensors/node-a-s1", sample_sensor_json("z1")))
+            .await
+            .unwrap();
+        shared.write().await.record_node_status("node-a", true);
+
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/nodes/node-a/decommission"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp).await;
+        assert_eq!(json["archived_sensors"], 1);
+        assert!(json["node"]["decommissioned_at"].is_i64());
+        assert!(!shared.read().await.nodes.contains_key("node-a"));
+
+        // Gone from the active sensor list, still listed under the node.
+        let resp = app.clone().oneshot(get_req("/api/sensors")).await.unwrap();
+        assert!(body_json(resp).await.as_array().unwrap().is_empty());
+        let resp = app
+            .oneshot(get_req("/api/nodes/node-a/sensors"))
+            .await
+            .unwrap();
+        let json = body_json(resp).await;
+        assert_eq!(json.as_array().unwrap().len(), 1);
+        assert!(json[0]["archived_at"].is_i64());
+    }
+
+    #[tokio::test]
+    async fn decommission_unknown_node_returns_404() {
+        let app = router(test_state().await);
+        let resp = app
+            .oneshot(post_req("/api/nodes/ghost/decommission"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn water_now_plans_a_capped_manual_pulse() {
+        let mut state = test_state().await;
+        let (tx, mut rx) = mpsc::channel(4);
+        state.water_now = tx;
+        let shared = state.shared.clone();
+        let db = state.db.clone();
+        let app = router(state);
+
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/zones/zone1/water-now"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        app.clone()
+            .oneshot(put_json("/api/zones/zone1", sample_zone_json()))
+            .await
+            .unwrap();
+
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/zones/zone1/water-now"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        shared.write().await.mqtt_connected = true;
+
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/zones/zone1/water-now?duration_sec=31"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        // 170 of 180 open seconds used: the pulse is cut to 10 s.
+        let today = Db::today_yyyy_mm_dd();
+        db.add_open_seconds(&today, "zone1", 170).await.unwrap();
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/zones/zone1/water-now"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        let json = body_json(resp).await;
+        assert_eq!(json["zone_id"], "zone1");
+        assert_eq!(json["duration_sec"], 10);
+        assert_eq!(rx.try_recv().unwrap(), ("zone1".to_string(), 10));
+
+        // The ON round-trip starts the reserved session.
+        {
+            let mut st = shared.write().await;
+            let planned = st.sessions.unplan("zone1");
+            let session = st
+                .sessions
+                .start("zone1", planned, OffsetDateTime::now_utc());
+            assert_eq!(json["session_id"], session.id);
+            assert_eq!(session.reason, "manual");
+            assert_eq!(session.planned_sec, Some(10));
+        }
+
+        db.add_open_seconds(&today, "zone1", 10).await.unwrap();
+        let resp = app
+            .oneshot(post_req("/api/zones/zone1/water-now"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let json = body_json(resp).await;
+        assert!(json["message"].as_str().unwrap().contains("180s/180s"));
     }
 
-    // -----------------------------------------------------------------------
-    // Static routes
-    // -----------------------------------------------------------------------
+    #[tokio::test]
+    async fn water_now_refuses_interlocked_and_over_concurrency() {
+        let mut state = test_state().await;
+        let (tx, mut rx) = mpsc::channel(4);
+        state.water_now = tx;
+        let shared = state.shared.clone();
+        let app = router(state);
+        app.clone()
+            .oneshot(put_json("/api/zones/zone1", sample_zone_json()))
+            .await
+            .unwrap();
+        {
+            let mut st = shared.write().await;
+            st.mqtt_connected = true;
+            st.limits.interlocks = BTreeMap::from([(
+                "north".to_string(),
+                vec!["zone1".to_string(), "zone2".to_string()],
+            )]);
+            st.zones.get_mut("zone2").unwrap().on = true;
+        }
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/zones/zone1/water-now"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let json = body_json(resp).await;
+        assert!(json["message"].as_str().unwrap().contains("interlocked"));
+
+        shared.write().await.limits.interlocks.clear();
+        shared.write().await.limits.max_concurrent_valves = 1;
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/zones/zone1/water-now"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let json = body_json(resp).await;
+        assert!(json["message"].as_str().unwrap().contains("1/1 valves"));
+        // Nothing was published and no session is left planned.
+        assert!(rx.try_recv().is_err());
+
+        shared.write().await.limits.max_concurrent_valves = 2;
+        let resp = app
+            .oneshot(post_req("/api/zones/zone1/water-now"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+    }
+
+    #[tokio::test]
+    async fn water_now_uses_degraded_caps_while_degraded() {
+        let mut state = test_state().await;
+        let (tx, mut rx) = mpsc::channel(4);
+        state.water_now = tx;
+        let shared = state.shared.clone();
+        let app = router(state);
+        app.clone()
+            .oneshot(put_json("/api/zones/zone1", sample_zone_json()))
+            .await
+            .unwrap();
+        let today = Db::today_yyyy_mm_dd();
+        {
+            let mut st = shared.write().await;
+            st.mqtt_connected = true;
+            st.mark_db_degraded("test");
+        }
+        // Usage today unknown: refused rather than guessed.
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/zones/zone1/water-now"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        // 80 of the degraded 90 open seconds used: cut to 10 s.
+        shared
+            .write()
+            .await
+            .cache_db_counters(&today, "zone1", 1, 80);
+        let resp = app
+            .oneshot(post_req("/api/zones/zone1/water-now"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        assert_eq!(body_json(resp).await["duration_sec"], 10);
+        assert_eq!(rx.try_recv().unwrap(), ("zone1".to_string(), 10));
+    }
 
     #[tokio::test]
-    async fn index_returns_html() {
-        let app = router(test_state().await);
-        let resp = app.oneshot(get_req("/")).await.unwrap();
+    async fn valve_test_blips_each_zone_and_reports() {
+        let mut state = test_state().await;
+        let (tx, mut rx) = mpsc::channel(4);
+        state.water_now = tx;
+        let shared = state.shared.clone();
+        let db = state.db.clone();
+        let app = router(state);
 
-        assert_eq!(resp.status(), StatusCode::OK);
-        let ct = resp
-            .headers()
-            .get("content-type")
-            .unwrap()
-            .to_str()
+        let resp = app
+            .clone()
+            .oneshot(get_req("/api/maintenance/valve-test"))
+            .await
             .unwrap();
-        assert!(ct.contains("text/html"));
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        app.clone()
+            .oneshot(put_json("/api/zones/zone1", sample_zone_json()))
+            .await
+            .unwrap();
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/maintenance/valve-test"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        shared.write().await.mqtt_connected = true;
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/maintenance/valve-test?blip_sec=61"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        // Stand in for the valve handler: open, report flow, close.
+        let hub = {
+            let shared = shared.clone();
+            tokio::spawn(async move {
+                let (zone_id, secs) = rx.recv().await.unwrap();
+                let now = OffsetDateTime::now_utc().unix_timestamp();
+                shared.write().await.zones.get_mut(&zone_id).unwrap().on = true;
+                db.insert_flow_reading(now, &zone_id, 5.5, None)
+                    .await
+                    .unwrap();
+                tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+                shared.write().await.zones.get_mut(&zone_id).unwrap().on = false;
+                (zone_id, secs)
+            })
+        };
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/maintenance/valve-test?blip_sec=1"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        let json = body_json(resp).await;
+        assert_eq!(json["zones"][0]["outcome"], "pending");
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/maintenance/valve-test"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        assert_eq!(hub.await.unwrap(), ("zone1".to_string(), 1));
+
+        let json = loop {
+            let resp = app
+                .clone()
+                .oneshot(get_req("/api/maintenance/valve-test"))
+                .await
+                .unwrap();
+            let json = body_json(resp).await;
+            if !json["finished_at"].is_null() {
+                break json;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        };
+        let zone = &json["zones"][0];
+        assert_eq!(zone["zone_id"], "zone1");
+        assert_eq!(zone["outcome"], "pass", "{zone}");
+        assert_eq!(zone["max_flow_lpm"], 5.5);
+        let mut st = shared.write().await;
+        assert!(st
+            .events
+            .iter()
+            .any(|e| e.detail == "valve test: 1 passed, 0 failed, 0 skipped"));
+        assert_eq!(st.sessions.unplan("zone1").unwrap().reason, "valve_test");
     }
 
     #[tokio::test]
-    async fn api_status_returns_json_with_expected_fields() {
-        let app = router(test_state().await);
-        let resp = app.oneshot(get_req("/api/status")).await.unwrap();
+    async fn sessions_are_listed_and_cancelled() {
+        let mut state = test_state().await;
+        let (tx, mut rx) = mpsc::channel(4);
+        state.session_cancels = tx;
+        let shared = state.shared.clone();
+        let app = router(state);
 
+        let id = {
+            let mut st = shared.write().await;
+            st.sessions.plan("zone1", "scheduler", Some(30));
+            let planned = st.sessions.unplan("zone1");
+            st.sessions
+                .start("zone1", planned, OffsetDateTime::now_utc())
+                .id
+        };
+        let resp = app.clone().oneshot(get_req("/api/sessions")).await.unwrap();
         assert_eq!(resp.status(), StatusCode::OK);
         let json = body_json(resp).await;
+        assert_eq!(json["active"][0]["id"], id);
+        assert_eq!(json["active"][0]["reason"], "scheduler");
+        assert_eq!(json["active"][0]["planned_sec"], 30);
+        assert!(json["active"][0]["result"].is_null());
 
-        assert!(json["uptime_secs"].is_u64());
-        assert!(json["mqtt_connected"].is_boolean());
-        assert!(json["nodes"].is_object());
-        assert!(json["zones"].is_object());
-        assert!(json["events"].is_array());
-        assert!(json["zones"]["zone1"].is_object());
-        assert!(json["zones"]["zone2"].is_object());
-    }
+        let cancel = format!("/api/sessions/{id}/cancel");
+        let resp = app.clone().oneshot(post_req(&cancel)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
 
-    #[tokio::test]
-    async fn api_health_returns_json() {
-        let app = router(test_state().await);
-        let resp = app.oneshot(get_req("/api/health")).await.unwrap();
-        // DB is connected but MQTT is not, so expect 503
-        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        shared.write().await.mqtt_connected = true;
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/sessions/999/cancel"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let resp = app.clone().oneshot(post_req(&cancel)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        assert_eq!(rx.try_recv().unwrap(), "zone1");
 
-        let json = body_json(resp).await;
-        assert_eq!(json["status"], "degraded");
-        assert_eq!(json["db_connected"], true);
-        assert_eq!(json["mqtt_connected"], false);
+        // The OFF round-trip finishes it as cancelled.
+        shared
+            .write()
+            .await
+            .sessions
+            .finish("zone1", None, OffsetDateTime::now_utc());
+        let json = body_json(app.oneshot(get_req("/api/sessions")).await.unwrap()).await;
+        assert_eq!(json["active"], serde_json::json!([]));
+        assert_eq!(json["recent"][0]["result"], "cancelled");
     }
 
     #[tokio::test]
-    async fn unknown_route_returns_404() {
-        let app = router(test_state().await);
-        let resp = app.oneshot(get_req("/nonexistent")).await.unwrap();
+    async fn node_commands_are_queued_for_known_connected_nodes() {
+        let mut state = test_state().await;
+        let (tx, mut rx) = mpsc::channel(4);
+        state.node_commands = tx;
+        let shared = state.shared.clone();
+        let app = router(state);
+
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/nodes/ghost/restart"))
+            .await
+            .unwrap();
         assert_eq!(resp.status(), StatusCode::NOT_FOUND);
-    }
 
-    // -----------------------------------------------------------------------
-    // Zones — list
-    // -----------------------------------------------------------------------
+        shared.write().await.record_node_status("node-a", true);
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/nodes/node-a/restart"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        shared.write().await.mqtt_connected = true;
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/nodes/node-a/restart"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            ("node-a".to_string(), NodeCommand::Restart)
+        );
+
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/nodes/node-a/send-logs?lines=500"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let resp = app
+            .clone()
+            .oneshot(post_req("/api/nodes/node-a/send-logs?lines=20"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            ("node-a".to_string(), NodeCommand::SendLogs { lines: 20 })
+        );
+        assert!(rx.try_recv().is_err());
+    }
 
     #[tokio::test]
-    async fn api_zones_returns_empty_array_when_no_zones() {
-        let app = router(test_state().await);
-        let resp = app.oneshot(get_req("/api/zones")).await.unwrap();
+    async fn ping_waits_for_the_node_to_answer() {
+        let mut state = test_state().await;
+        let (tx, mut rx) = mpsc::channel(4);
+        state.node_commands = tx;
+        let shared = state.shared.clone();
+        {
+            let mut st = shared.write().await;
+            st.record_node_status("node-a", true);
+            st.mqtt_connected = true;
+        }
+        // Stands in for the node and the hub's `diag/+/pong` handler.
+        tokio::spawn(async move {
+            let Some((node_id, NodeCommand::Ping { sent_ms })) = rx.recv().await else {
+                panic!("expected a ping");
+            };
+            shared.write().await.node_pongs.insert(
+                node_id,
+                NodePong {
+                    received_at: time::OffsetDateTime::now_utc(),
+                    sent_ms: Some(sent_ms),
+                    round_trip_ms: Some(12),
+                    ts: 1_700_000_000,
+                    uptime_sec: 3600,
+                    sample_interval_sec: 900,
+                    last_sample_ts: Some(1_699_999_500),
+                    buffered: 0,
+                },
+            );
+        });
 
+        let resp = router(state)
+            .oneshot(post_req("/api/nodes/node-a/ping"))
+            .await
+            .unwrap();
         assert_eq!(resp.status(), StatusCode::OK);
         let json = body_json(resp).await;
-        assert!(json.is_array());
-        assert!(json.as_array().unwrap().is_empty());
+        assert_eq!(json["answered"], true);
+        assert_eq!(json["pong"]["sample_interval_sec"], 900);
+        assert_eq!(json["pong"]["round_trip_ms"], 12);
     }
 
     #[tokio::test]
-    async fn api_zones_returns_inserted_zone() {
+    async fn node_climate_readings() {
         let state = test_state().await;
-        state
-            .db
-            .upsert_zone(&ZoneConfig {
-                zone_id: "z1".into(),
-                name: "Test".into(),
-                min_moisture: 0.3,
-                target_moisture: 0.5,
-                pulse_sec: 30,
-                soak_min: 20,
-                max_open_sec_per_day: 180,
-                max_pulses_per_day: 6,
-                stale_timeout_min: 30,
-                valve_gpio_pin: 17,
-            })
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let db = &state.db;
+        assert!(db
+            .insert_climate_reading(now - 7200, "node-a", Some(18.5), Some(60.0), None)
             .await
-            .unwrap();
-
+            .unwrap());
+        assert!(db
+            .insert_climate_reading(now - 60, "node-a", Some(21.0), None, Some(35_000.0))
+            .await
+            .unwrap());
+        // A replay of the same sample is ignored.
+        assert!(!db
+            .insert_climate_reading(now - 60, "node-a", Some(21.0), None, Some(35_000.0))
+            .await
+            .unwrap());
         let app = router(state);
-        let resp = app.oneshot(get_req("/api/zones")).await.unwrap();
-        assert_eq!(resp.status(), StatusCode::OK);
 
-        let json = body_json(resp).await;
+        let json = body_json(
+            app.clone()
+                .oneshot(get_req("/api/nodes/node-a/climate"))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(json.as_array().unwrap().len(), 2);
+        assert_eq!(json[0]["humidity_pct"], 60.0);
+        assert_eq!(json[1]["humidity_pct"], serde_json::Value::Null);
+        assert_eq!(json[1]["lux"], 35_000.0);
+
+        let json = body_json(
+            app.clone()
+                .oneshot(get_req("/api/nodes/node-a/climate?hours=1"))
+                .await
+                .unwrap(),
+        )
+        .await;
         assert_eq!(json.as_array().unwrap().len(), 1);
-        assert_eq!(json[0]["zone_id"], "z1");
-    }
-
-    // -----------------------------------------------------------------------
-    // Zones — CRUD
-    // -----------------------------------------------------------------------
+        assert_eq!(json[0]["air_temp_c"], 21.0);
 
-    #[tokio::test]
-    async fn put_zone_creates_and_returns_zone() {
-        let app = router(test_state().await);
         let resp = app
-            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .oneshot(get_req("/api/nodes/node-a/climate?hours=0"))
             .await
             .unwrap();
-
-        assert_eq!(resp.status(), StatusCode::OK);
-        let json = body_json(resp).await;
-        assert_eq!(json["zone_id"], "z1");
-        assert_eq!(json["name"], "Front Lawn");
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
     }
 
     #[tokio::test]
-    async fn put_zone_then_get_returns_same() {
-        let app = router(test_state().await);
-        app.clone()
-            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+    async fn node_logs_served_once_received() {
+        let state = test_state().await;
+        let shared = state.shared.clone();
+        let app = router(state);
+
+        let resp = app
+            .clone()
+            .oneshot(get_req("/api/nodes/node-a/logs"))
             .await
             .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
 
-        let resp = app.oneshot(get_req("/api/zones/z1")).await.unwrap();
+        shared.write().await.node_logs.insert(
+            "node-a".to_string(),
+            NodeLogs {
+                received_at: OffsetDateTime::now_utc(),
+                ts: 1_700_000_000,
+                lines: vec!["INFO sampling".to_string()],
+            },
+        );
+        let resp = app
+            .oneshot(get_req("/api/nodes/node-a/logs"))
+            .await
+            .unwrap();
         assert_eq!(resp.status(), StatusCode::OK);
-
         let json = body_json(resp).await;
-        assert_eq!(json["zone_id"], "z1");
-        assert_eq!(json["name"], "Front Lawn");
-        assert_eq!(json["pulse_sec"], 30);
+        assert_eq!(json["ts"], 1_700_000_000);
+        assert_eq!(json["lines"][0], "INFO sampling");
+        assert!(json["received_at"].is_string());
     }
 
     #[tokio::test]
-    async fn put_zone_upsert_updates_existing() {
-        let app = router(test_state().await);
-        app.clone()
-            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+    async fn telemetry_over_http_needs_a_node_token() {
+        let mut state = test_state().await;
+        let (tx, mut rx) = mpsc::channel(4);
+        state.telemetry = tx;
+        state.tokens = Arc::new(ApiTokens::parse(Some("secret"), None).unwrap());
+        state.node_tokens = Arc::new(NodeTokens::parse(Some("esp-bed:bed-key")).unwrap());
+        let app = router(state);
+        let with_token = |token: &str, body: serde_json::Value| {
+            let mut req = post_json("/api/telemetry", body);
+            req.headers_mut()
+                .insert("authorization", format!("Bearer {token}").parse().unwrap());
+            req
+        };
+        let reading = serde_json::json!({
+            "ts": 1_700_000_000,
+            "readings": [{ "sensor_id": "s1", "raw": 20000 }]
+        });
+
+        // API tokens don't work here, and node tokens don't work elsewhere.
+        let resp = app
+            .clone()
+            .oneshot(with_token("secret", reading.clone()))
             .await
             .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let mut req = get_req("/api/zones");
+        req.headers_mut()
+            .insert("authorization", "Bearer bed-key".parse().unwrap());
+        let resp = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
 
-        let mut updated = sample_zone_json();
-        updated["name"] = serde_json::json!("Back Yard");
-        app.clone()
-            .oneshot(put_json("/api/zones/z1", updated))
+        let resp = app
+            .clone()
+            .oneshot(with_token(
+                "bed-key",
+                serde_json::json!({ "ts": 0, "readings": [] }),
+            ))
             .await
             .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(rx.try_recv().is_err());
 
-        let resp = app.oneshot(get_req("/api/zones/z1")).await.unwrap();
-        let json = body_json(resp).await;
-        assert_eq!(json["name"], "Back Yard");
+        let resp = app
+            .oneshot(with_token("bed-key", reading.clone()))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        assert_eq!(body_json(resp).await["readings"], 1);
+        let (node_id, payload) = rx.try_recv().unwrap();
+        assert_eq!(node_id, "esp-bed");
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&payload).unwrap(),
+            reading
+        );
     }
 
     #[tokio::test]
-    async fn get_zone_missing_returns_404() {
-        let app = router(test_state().await);
-        let resp = app.oneshot(get_req("/api/zones/nope")).await.unwrap();
+    async fn node_updates_are_announced_and_images_served() {
+        let dir = std::env::temp_dir().join(format!("irrigation-web-ota-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("irrigation-node-0.2.0"), b"binary").unwrap();
+        std::fs::write(dir.join("irrigation-node-0.2.0.sig"), [0xab; 64]).unwrap();
+
+        let mut state = test_state().await;
+        let (tx, mut rx) = mpsc::channel(4);
+        state.tokens = Arc::new(ApiTokens::parse(Some("secret"), None).unwrap());
+        state.ota = OtaApi {
+            config: ota::OtaConfig {
+                dir: Some(dir.to_string_lossy().into_owned()),
+                base_url: Some("http://hub:8080".into()),
+            },
+            announcements: tx,
+        };
+        let shared = state.shared.clone();
+        let app = router(state);
+        let authed = |mut req: Request<Body>| {
+            req.headers_mut()
+                .insert("authorization", "Bearer secret".parse().unwrap());
+            req
+        };
+
+        let resp = app
+            .clone()
+            .oneshot(authed(post_json(
+                "/api/nodes/node-a/ota",
+                serde_json::json!({ "version": "0.2.0" }),
+            )))
+            .await
+            .unwrap();
         assert_eq!(resp.status(), StatusCode::NOT_FOUND);
 
+        {
+            let mut st = shared.write().await;
+            st.record_node_status("node-a", true);
+            st.mqtt_connected = true;
+        }
+        let resp = app
+            .clone()
+            .oneshot(authed(post_json(
+                "/api/nodes/node-a/ota",
+                serde_json::json!({ "version": "0.9.0" }),
+            )))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let resp = app
+            .clone()
+            .oneshot(authed(post_json(
+                "/api/nodes/node-a/ota",
+                serde_json::json!({ "version": "0.2.0" }),
+            )))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
         let json = body_json(resp).await;
-        assert_eq!(json["error"], "not_found");
-    }
+        assert_eq!(json["url"], "http://hub:8080/ota/irrigation-node-0.2.0");
+        assert_eq!(json["signature"], "ab".repeat(64));
+        let (node, announcement) = rx.try_recv().unwrap();
+        assert_eq!(node, "node-a");
+        assert_eq!(announcement.unwrap().version, "0.2.0");
 
-    #[tokio::test]
-    async fn delete_zone_removes_it() {
-        let app = router(test_state().await);
-        app.clone()
-            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+        let resp = app
+            .clone()
+            .oneshot(authed(get_req("/api/ota")))
             .await
             .unwrap();
+        let json = body_json(resp).await;
+        assert_eq!(json["enabled"], true);
+        assert_eq!(json["images"][0]["version"], "0.2.0");
+        assert_eq!(json["nodes"]["node-a"]["pending"], "0.2.0");
 
+        // The node fetches the image without a token.
         let resp = app
             .clone()
-            .oneshot(delete_req("/api/zones/z1"))
+            .oneshot(get_req("/ota/irrigation-node-0.2.0"))
             .await
             .unwrap();
-        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"binary");
+        for uri in [
+            "/ota/irrigation-node-0.2.0.sig",
+            "/ota/irrigation-node-0.3.0",
+        ] {
+            let resp = app.clone().oneshot(get_req(uri)).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{uri}");
+        }
 
-        let resp = app.oneshot(get_req("/api/zones/z1")).await.unwrap();
-        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let resp = app
+            .oneshot(authed(delete_req("/api/nodes/node-a/ota")))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(body_json(resp).await["cancelled"], "0.2.0");
+        assert_eq!(rx.try_recv().unwrap(), ("node-a".to_string(), None));
+        assert_eq!(shared.read().await.node_ota["node-a"].pending, None);
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 
     #[tokio::test]
-    async fn delete_zone_missing_returns_404() {
-        let app = router(test_state().await);
-        let resp = app.oneshot(delete_req("/api/zones/nope")).await.unwrap();
-        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
-    }
+    async fn node_diagnostics_reports_sensors_and_latest_readings() {
+        let state = test_state().await;
+        let shared = state.shared.clone();
+        let db = state.db.clone();
+        let app = router(state);
 
-    #[tokio::test]
-    async fn delete_zone_with_sensors_returns_409() {
-        let app = router(test_state().await);
         app.clone()
             .oneshot(put_json("/api/zones/z1", sample_zone_json()))
             .await
             .unwrap();
         app.clone()
-            .oneshot(put_json("/api/sensors/s1", sample_sensor_json("z1")))
+            .oneshot(put_json("/api/sensors/node-a-s1", sample_sensor_json("z1")))
+            .await
+            .unwrap();
+        app.clone()
+            .oneshot(put_json("/api/sensors/node-a-s2", sample_sensor_json("z1")))
             .await
             .unwrap();
+        db.insert_reading(1000, "node-a-s1", 20000, 0.5)
+            .await
+            .unwrap();
+        db.insert_reading(1060, "node-a-s1", 21000, 0.45)
+            .await
+            .unwrap();
+        shared.write().await.record_reading(
+            "node-a",
+            vec![crate::state::SensorReading {
+                sensor_id: "s1".into(),
+                raw: 21000,
+                mv: None,
+            }],
+        );
 
-        let resp = app.oneshot(delete_req("/api/zones/z1")).await.unwrap();
-        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let resp = app.oneshot(get_req("/api/nodes/node-a")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp).await;
+
+        assert!(json["config"].is_null());
+        assert_eq!(json["online"], true);
+        assert_eq!(json["stale"], false);
+        assert_eq!(json["stale_timeout_min"], 10);
+        assert!(json["last_seen_age_sec"].as_i64().unwrap() < 5);
+
+        let sensors = json["sensors"].as_array().unwrap();
+        assert_eq!(sensors.len(), 2);
+        assert_eq!(sensors[0]["sensor_id"], "node-a-s1");
+        assert_eq!(sensors[0]["raw_dry"], 30000);
+        assert_eq!(sensors[0]["latest"]["ts"], 1060);
+        assert_eq!(sensors[0]["plausible"], true);
+        assert!(sensors[1]["latest"].is_null());
+        assert!(sensors[1]["plausible"].is_null());
     }
 
     // -----------------------------------------------------------------------
-    // Zone validation
+    // Reports
     // -----------------------------------------------------------------------
 
     #[tokio::test]
-    async fn put_zone_bad_moisture_returns_422() {
-        let app = router(test_state().await);
-        let mut bad = sample_zone_json();
-        bad["min_moisture"] = serde_json::json!(1.5);
-        bad["target_moisture"] = serde_json::json!(-0.1);
-
-        let resp = app.oneshot(put_json("/api/zones/z1", bad)).await.unwrap();
-        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    async fn usage_report_groups_by_month() {
+        let state = test_state().await;
+        let db = state.db.clone();
+        let app = router(state);
+        let mut zone = sample_zone_json();
+        zone["flow_lpm"] = serde_json::json!(12.0);
+        app.clone()
+            .oneshot(put_json("/api/zones/z1", zone))
+            .await
+            .unwrap();
+        db.add_open_seconds("2025-06-01", "z1", 30).await.unwrap();
+        db.add_open_seconds("2025-06-15", "z1", 90).await.unwrap();
+        db.add_pulse("2025-06-15", "z1", 3).await.unwrap();
 
+        let resp = app
+            .oneshot(get_req(
+                "/api/reports/usage?zone_id=z1&from=2025-06-01&to=2025-06-30&group_by=month",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
         let json = body_json(resp).await;
-        assert_eq!(json["error"], "validation");
-        let msgs = json["messages"].as_array().unwrap();
-        assert!(msgs.len() >= 2);
+        assert_eq!(json["group_by"], "month");
+        let rows = json["rows"].as_array().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["period"], "2025-06");
+        assert_eq!(rows[0]["open_sec"], 120);
+        assert_eq!(rows[0]["pulses"], 3);
+        assert_eq!(rows[0]["litres"], 24.0);
     }
 
     #[tokio::test]
-    async fn put_zone_target_below_min_returns_422() {
+    async fn usage_report_rejects_bad_dates() {
         let app = router(test_state().await);
-        let mut bad = sample_zone_json();
-        bad["min_moisture"] = serde_json::json!(0.6);
-        bad["target_moisture"] = serde_json::json!(0.3);
+        let resp = app
+            .clone()
+            .oneshot(get_req("/api/reports/usage?from=June"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
 
-        let resp = app.oneshot(put_json("/api/zones/z1", bad)).await.unwrap();
+        let resp = app
+            .oneshot(get_req("/api/reports/usage?from=2025-07-01&to=2025-06-01"))
+            .await
+            .unwrap();
         assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
     }
 
     #[tokio::test]
-    async fn put_zone_empty_name_returns_422() {
-        let app = router(test_state().await);
-        let mut bad = sample_zone_json();
-        bad["name"] = serde_json::json!("");
+    async fn logs_filtered_by_level() {
+        let state = test_state().await;
+        let record = |ts, level: &str, message: &str| LogRecord {
+            ts,
+            level: level.into(),
+            target: "irrigation_hub::mqtt".into(),
+            message: message.into(),
+            fields: serde_json::Map::from_iter([("zone".to_string(), "z1".into())]),
+        };
+        state
+            .db
+            .insert_logs(&[
+                record(1000, "WARN", "broker slow"),
+                record(2000, "ERROR", "broker gone"),
+            ])
+            .await
+            .unwrap();
+        let app = router(state);
 
-        let resp = app.oneshot(put_json("/api/zones/z1", bad)).await.unwrap();
-        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
-    }
+        let resp = app.clone().oneshot(get_req("/api/logs")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp).await;
+        assert_eq!(json.as_array().unwrap().len(), 2);
+        assert_eq!(json[0]["message"], "broker gone");
+        assert_eq!(json[0]["fields"]["zone"], "z1");
 
-    #[tokio::test]
-    async fn put_zone_negative_pulse_sec_returns_422() {
-        let app = router(test_state().await);
-        let mut bad = sample_zone_json();
-        bad["pulse_sec"] = serde_json::json!(-1);
+        let resp = app
+            .clone()
+            .oneshot(get_req("/api/logs?level=warn"))
+            .await
+            .unwrap();
+        let json = body_json(resp).await;
+        assert_eq!(json.as_array().unwrap().len(), 1);
+        assert_eq!(json[0]["level"], "WARN");
 
-        let resp = app.oneshot(put_json("/api/zones/z1", bad)).await.unwrap();
+        let resp = app.oneshot(get_req("/api/logs?level=info")).await.unwrap();
         assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
     }
 
-    // -----------------------------------------------------------------------
-    // Sensors — CRUD
-    // -----------------------------------------------------------------------
-
     #[tokio::test]
-    async fn put_sensor_creates_and_returns_sensor() {
-        let app = router(test_state().await);
+    async fn efficiency_report_scores_pulses() {
+        let state = test_state().await;
+        let db = state.db.clone();
+        let app = router(state);
+        let mut zone = sample_zone_json();
+        zone["flow_lpm"] = serde_json::json!(6.0);
         app.clone()
-            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .oneshot(put_json("/api/zones/z1", zone))
+            .await
+            .unwrap();
+        app.clone()
+            .oneshot(put_json("/api/sensors/s1", sample_sensor_json("z1")))
+            .await
+            .unwrap();
+        let at = |h, m| {
+            time::Date::from_calendar_date(2025, time::Month::June, 2)
+                .unwrap()
+                .with_hms(h, m, 0)
+                .unwrap()
+                .assume_utc()
+                .unix_timestamp()
+        };
+        db.insert_watering_event(
+            at(6, 0),
+            at(6, 0) + 30,
+            "z1",
+            "mqtt_command",
+            "ok",
+            EventDetails::default(),
+        )
+        .await
+        .unwrap();
+        // Flushes don't count.
+        db.insert_watering_event(
+            at(12, 0),
+            at(12, 0) + 60,
+            "z1",
+            "flush",
+            "ok",
+            EventDetails::default(),
+        )
+        .await
+        .unwrap();
+        db.insert_reading(at(5, 50), "s1", 20000, 0.30)
+            .await
+            .unwrap();
+        db.insert_reading(at(6, 10), "s1", 19000, 0.34)
             .await
             .unwrap();
 
         let resp = app
-            .oneshot(put_json("/api/sensors/s1", sample_sensor_json("z1")))
+            .clone()
+            .oneshot(get_req(
+                "/api/reports/efficiency?from=2025-06-01&to=2025-06-30",
+            ))
             .await
             .unwrap();
         assert_eq!(resp.status(), StatusCode::OK);
-
         let json = body_json(resp).await;
-        assert_eq!(json["sensor_id"], "s1");
-        assert_eq!(json["zone_id"], "z1");
+        let zones = json["zones"].as_array().unwrap();
+        assert_eq!(zones.len(), 1);
+        assert_eq!(zones[0]["zone_id"], "z1");
+        assert_eq!(zones[0]["pulses"], 1);
+        assert_eq!(zones[0]["open_sec"], 30);
+        assert_eq!(zones[0]["litres"], 3.0);
+        assert_eq!(zones[0]["effective_pulses"], 1);
+        assert_eq!(zones[0]["score"], 100.0);
+        assert_eq!(zones[0]["days"][0]["day"], "2025-06-02");
+
+        let resp = app
+            .oneshot(get_req("/api/reports/efficiency?zone_id=nope"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
     }
 
     #[tokio::test]
-    async fn get_sensors_list() {
-        let app = router(test_state().await);
+    async fn soak_report_uses_the_last_events() {
+        let state = test_state().await;
+        let db = state.db.clone();
+        let app = router(state);
         app.clone()
             .oneshot(put_json("/api/zones/z1", sample_zone_json()))
             .await
@@ -958,369 +6674,736 @@ mod tests {
             .oneshot(put_json("/api/sensors/s1", sample_sensor_json("z1")))
             .await
             .unwrap();
-        app.clone()
-            .oneshot(put_json("/api/sensors/s2", sample_sensor_json("z1")))
+        // Four 60 s pulses a day apart, each raising moisture less.
+        let day = 86_400;
+        for (i, rise) in [0.06, 0.06, 0.02, 0.02].into_iter().enumerate() {
+            let ts = 1_750_000_000 + i as i64 * day;
+            db.insert_watering_event(
+                ts,
+                ts + 60,
+                "z1",
+                "mqtt_command",
+                "ok",
+                EventDetails::default(),
+            )
             .await
             .unwrap();
+            db.insert_reading(ts - 600, "s1", 20000, 0.30)
+                .await
+                .unwrap();
+            db.insert_reading(ts + 600, "s1", 19000, 0.30 + rise)
+                .await
+                .unwrap();
+        }
 
-        let resp = app.oneshot(get_req("/api/sensors")).await.unwrap();
+        let resp = app
+            .clone()
+            .oneshot(get_req("/api/reports/soak"))
+            .await
+            .unwrap();
         assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp).await;
+        assert_eq!(json["events"], 20);
+        let z = &json["zones"][0];
+        assert_eq!(z["zone_id"], "z1");
+        assert_eq!(z["measured_events"], 4);
+        assert_eq!(z["declining"], true);
 
+        // Only the last two: too few to flag.
+        let resp = app
+            .clone()
+            .oneshot(get_req("/api/reports/soak?zone_id=z1&events=2"))
+            .await
+            .unwrap();
         let json = body_json(resp).await;
-        assert_eq!(json.as_array().unwrap().len(), 2);
+        assert_eq!(json["zones"][0]["events"], 2);
+        assert_eq!(json["zones"][0]["declining"], false);
+
+        let resp = app
+            .clone()
+            .oneshot(get_req("/api/reports/soak?events=1"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let resp = app
+            .oneshot(get_req("/api/reports/soak?zone_id=nope"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
     }
 
     #[tokio::test]
-    async fn get_sensor_single() {
-        let app = router(test_state().await);
+    async fn zone_compare_against_last_year() {
+        let state = test_state().await;
+        let db = state.db.clone();
+        let app = router(state);
+        let mut zone = sample_zone_json();
+        zone["flow_lpm"] = serde_json::json!(6.0);
         app.clone()
-            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .oneshot(put_json("/api/zones/z1", zone))
             .await
             .unwrap();
-        app.clone()
-            .oneshot(put_json("/api/sensors/s1", sample_sensor_json("z1")))
+        db.upsert_sensor(&SensorConfig {
+            sensor_id: "node-a/s1".into(),
+            node_id: "node-a".into(),
+            zone_id: "z1".into(),
+            raw_dry: 26000,
+            raw_wet: 12000,
+            channel: None,
+            archived_at: None,
+            weight: 1.0,
+            failure_margin: None,
+            failure_margin_pct: None,
+            curve: None,
+            enabled: true,
+        })
+        .await
+        .unwrap();
+        db.add_open_seconds("2026-06-02", "z1", 60).await.unwrap();
+        db.add_pulse("2026-06-02", "z1", 2).await.unwrap();
+        db.add_open_seconds("2025-06-03", "z1", 180).await.unwrap();
+        db.add_pulse("2025-06-03", "z1", 6).await.unwrap();
+        // Outside both periods.
+        db.add_open_seconds("2025-06-08", "z1", 999).await.unwrap();
+        for (ts, m) in [
+            (time::macros::datetime!(2026-06-02 06:00 UTC), 0.4),
+            (time::macros::datetime!(2026-06-02 18:00 UTC), 0.2),
+            (time::macros::datetime!(2025-06-03 12:00 UTC), 0.5),
+        ] {
+            db.insert_reading(ts.unix_timestamp(), "node-a/s1", 20000, m)
+                .await
+                .unwrap();
+        }
+
+        let resp = app
+            .clone()
+            .oneshot(get_req(
+                "/api/zones/z1/compare?from=2026-06-01&to=2026-06-07",
+            ))
             .await
             .unwrap();
-
-        let resp = app.oneshot(get_req("/api/sensors/s1")).await.unwrap();
         assert_eq!(resp.status(), StatusCode::OK);
-
         let json = body_json(resp).await;
-        assert_eq!(json["sensor_id"], "s1");
-        assert_eq!(json["node_id"], "node-a");
+        assert_eq!(json["baseline"]["from"], "2025-06-01");
+        assert_eq!(json["baseline"]["to"], "2025-06-07");
+        assert_eq!(json["period"]["open_sec"], 60);
+        assert_eq!(json["period"]["litres"], 6.0);
+        assert_eq!(json["period"]["watering_days"], 1);
+        assert_eq!(json["baseline"]["open_sec"], 180);
+        assert_eq!(json["delta"]["open_sec"], -120);
+        assert_eq!(json["delta"]["litres"], -12.0);
+        let days = json["period"]["moisture"].as_array().unwrap();
+        assert_eq!(days.len(), 1);
+        assert_eq!(days[0]["samples"], 2);
+        let delta = json["delta"]["avg_moisture"].as_f64().unwrap();
+        assert!((delta - -0.2).abs() < 1e-6, "{delta}");
+
+        let resp = app
+            .clone()
+            .oneshot(get_req(
+                "/api/zones/z1/compare?from=2026-01-01&to=2027-06-01",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let resp = app
+            .oneshot(get_req("/api/zones/nope/compare"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
     }
 
     #[tokio::test]
-    async fn get_sensor_missing_returns_404() {
+    async fn put_zone_non_positive_flow_returns_422() {
         let app = router(test_state().await);
-        let resp = app.oneshot(get_req("/api/sensors/nope")).await.unwrap();
-        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let mut zone = sample_zone_json();
+        zone["flow_lpm"] = serde_json::json!(0.0);
+        let resp = app.oneshot(put_json("/api/zones/z1", zone)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
     }
 
     #[tokio::test]
-    async fn delete_sensor_removes_it() {
-        let app = router(test_state().await);
-        app.clone()
-            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+    async fn put_zone_metadata_round_trips() {
+        let state = test_state().await;
+        let mut zone = sample_zone_json();
+        zone["notes"] = serde_json::json!("Drip line replaced in May");
+        zone["location"] = serde_json::json!("South fence");
+        zone["plant_type"] = serde_json::json!("tomatoes");
+        zone["emitter_flow_lph"] = serde_json::json!(8.0);
+        let resp = router(state.clone())
+            .oneshot(put_json("/api/zones/z1", zone.clone()))
             .await
             .unwrap();
-        app.clone()
-            .oneshot(put_json("/api/sensors/s1", sample_sensor_json("z1")))
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(
+            router(state.clone())
+                .oneshot(get_req("/api/zones/z1"))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(json["notes"], "Drip line replaced in May");
+        assert_eq!(json["location"], "South fence");
+        assert_eq!(json["plant_type"], "tomatoes");
+        assert_eq!(json["emitter_flow_lph"], 8.0);
+
+        zone["emitter_flow_lph"] = serde_json::json!(-1.0);
+        zone["notes"] = serde_json::json!("x".repeat(config::MAX_ZONE_TEXT_CHARS + 1));
+        let resp = router(state)
+            .oneshot(put_json("/api/zones/z1", zone))
             .await
             .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let json = body_json(resp).await;
+        assert_eq!(json["messages"].as_array().unwrap().len(), 2, "{json}");
+    }
 
-        let resp = app
-            .clone()
-            .oneshot(delete_req("/api/sensors/s1"))
+    #[tokio::test]
+    async fn put_zone_with_schedule_strategy() {
+        let state = test_state().await;
+        let mut zone = sample_zone_json();
+        zone["strategy"] = serde_json::json!({"kind": "schedule", "times": ["06:00"], "pulses": 2});
+        let resp = router(state.clone())
+            .oneshot(put_json("/api/zones/z1", zone.clone()))
             .await
             .unwrap();
-        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp).await;
+        assert_eq!(json["strategy"]["kind"], "schedule");
 
-        let resp = app.oneshot(get_req("/api/sensors/s1")).await.unwrap();
-        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        zone["strategy"]["times"] = serde_json::json!(["6am"]);
+        let resp = router(state)
+            .oneshot(put_json("/api/zones/z1", zone))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
     }
 
     #[tokio::test]
-    async fn delete_sensor_missing_returns_404() {
-        let app = router(test_state().await);
-        let resp = app.oneshot(delete_req("/api/sensors/nope")).await.unwrap();
+    async fn clone_zone_copies_settings_on_a_new_pin() {
+        let state = test_state().await;
+        let app = || router(state.clone());
+        let mut zone = sample_zone_json();
+        zone["priority"] = serde_json::json!(3);
+        app()
+            .oneshot(put_json("/api/zones/z1", zone))
+            .await
+            .unwrap();
+
+        // Same pin as z1: rejected in auto mode.
+        let resp = app()
+            .oneshot(post_json(
+                "/api/zones/z1/clone",
+                serde_json::json!({"zone_id": "z2"}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let resp = app()
+            .oneshot(post_json(
+                "/api/zones/z1/clone",
+                serde_json::json!({"zone_id": "z2", "valve_gpio_pin": 27}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let json = body_json(resp).await;
+        assert_eq!(json["zone_id"], "z2");
+        assert_eq!(json["name"], "Front Lawn (copy)");
+        assert_eq!(json["valve_gpio_pin"], 27);
+        assert_eq!(json["priority"], 3);
+
+        let resp = app()
+            .oneshot(post_json(
+                "/api/zones/z1/clone",
+                serde_json::json!({"zone_id": "z2", "valve_gpio_pin": 22}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let resp = app()
+            .oneshot(post_json(
+                "/api/zones/nope/clone",
+                serde_json::json!({"zone_id": "z3"}),
+            ))
+            .await
+            .unwrap();
         assert_eq!(resp.status(), StatusCode::NOT_FOUND);
     }
 
-    // -----------------------------------------------------------------------
-    // Sensor validation
-    // -----------------------------------------------------------------------
-
     #[tokio::test]
-    async fn put_sensor_nonexistent_zone_returns_422() {
-        let app = router(test_state().await);
-        let resp = app
+    async fn config_export_and_import() {
+        let state = test_state().await;
+        let app = || router(state.clone());
+        app()
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+            .await
+            .unwrap();
+        app()
+            .oneshot(put_json("/api/sensors/s1", sample_sensor_json("z1")))
+            .await
+            .unwrap();
+
+        let resp = app()
+            .oneshot(get_req("/api/config/export?format=toml"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let text = String::from_utf8(bytes.to_vec()).unwrap();
+        assert!(
+            text.contains("[[zones]]") && text.contains("[[sensors]]"),
+            "{text}"
+        );
+
+        // Re-import the TOML with the zone and sensor renamed.
+        let renamed = text
+            .replace("\"z1\"", "\"z2\"")
+            .replace("\"s1\"", "\"s2\"")
+            .replace("valve_gpio_pin = 17", "valve_gpio_pin = 27");
+        let resp = app()
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/api/config/import")
+                    .header("content-type", "application/toml")
+                    .body(Body::from(renamed))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            body_json(resp).await,
+            serde_json::json!({"zones": 1, "sensors": 1})
+        );
+
+        let json = body_json(app().oneshot(get_req("/api/config/export")).await.unwrap()).await;
+        let ids: Vec<&str> = json["zones"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|z| z["zone_id"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids, ["z1", "z2"]);
+        assert_eq!(json["sensors"][1]["zone_id"], "z2");
+
+        // A sensor on an unknown zone fails validation and changes nothing.
+        let mut bad = sample_sensor_json("ghost");
+        bad["sensor_id"] = serde_json::json!("s3");
+        let resp = app()
             .oneshot(put_json(
-                "/api/sensors/s1",
-                sample_sensor_json("no-such-zone"),
+                "/api/config/import",
+                serde_json::json!({ "sensors": [bad] }),
             ))
             .await
             .unwrap();
         assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let json = body_json(app().oneshot(get_req("/api/config/export")).await.unwrap()).await;
+        assert_eq!(json["sensors"].as_array().unwrap().len(), 2);
 
-        let json = body_json(resp).await;
-        assert!(json["messages"][0]
-            .as_str()
-            .unwrap()
-            .contains("no-such-zone"));
+        let resp = app()
+            .oneshot(get_req("/api/config/export?format=yaml"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
     }
 
     #[tokio::test]
-    async fn put_sensor_equal_dry_wet_returns_422() {
-        let app = router(test_state().await);
-        app.clone()
-            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
+    async fn put_zone_with_motorized_valve() {
+        let state = test_state().await;
+        let mut zone = sample_zone_json();
+        zone["valve"] =
+            serde_json::json!({"type": "motorized", "close_gpio_pin": 22, "travel_sec": 8});
+        let resp = router(state.clone())
+            .oneshot(put_json("/api/zones/z1", zone.clone()))
             .await
             .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp).await;
+        assert_eq!(json["valve"]["type"], "motorized");
+        assert_eq!(json["valve"]["travel_sec"], 8);
 
-        let bad = serde_json::json!({
-            "node_id": "node-a",
-            "zone_id": "z1",
-            "raw_dry": 20000,
-            "raw_wet": 20000
-        });
-        let resp = app.oneshot(put_json("/api/sensors/s1", bad)).await.unwrap();
+        zone["valve"]["close_gpio_pin"] = zone["valve_gpio_pin"].clone();
+        let resp = router(state)
+            .oneshot(put_json("/api/zones/z1", zone))
+            .await
+            .unwrap();
         assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
     }
 
     #[tokio::test]
-    async fn put_sensor_empty_node_id_returns_422() {
-        let app = router(test_state().await);
-        app.clone()
+    async fn put_zone_dependencies_validated() {
+        let state = test_state().await;
+        let app = || router(state.clone());
+        let resp = app()
             .oneshot(put_json("/api/zones/z1", sample_zone_json()))
             .await
             .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
 
-        let bad = serde_json::json!({
-            "node_id": "",
-            "zone_id": "z1",
-            "raw_dry": 30000,
-            "raw_wet": 10000
-        });
-        let resp = app.oneshot(put_json("/api/sensors/s1", bad)).await.unwrap();
-        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
-    }
-
-    // -----------------------------------------------------------------------
-    // Readings (read-only)
-    // -----------------------------------------------------------------------
-
-    #[tokio::test]
-    async fn readings_empty_returns_empty_array() {
-        let app = router(test_state().await);
-        let resp = app.oneshot(get_req("/api/readings")).await.unwrap();
+        let mut z2 = sample_zone_json();
+        z2["after"] = serde_json::json!(["z1"]);
+        let resp = app().oneshot(put_json("/api/zones/z2", z2)).await.unwrap();
         assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(body_json(resp).await["after"], serde_json::json!(["z1"]));
 
+        let mut z1 = sample_zone_json();
+        z1["after"] = serde_json::json!(["z2", "nope"]);
+        let resp = app().oneshot(put_json("/api/zones/z1", z1)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
         let json = body_json(resp).await;
-        assert!(json.as_array().unwrap().is_empty());
+        let errs = json["messages"].to_string();
+        assert!(errs.contains("unknown zone 'nope'"), "{errs}");
+        assert!(errs.contains("z1 -> z2 -> z1"), "{errs}");
     }
 
+    // -- config versions ---------------------------------------------------
+
     #[tokio::test]
-    async fn readings_returns_inserted_data() {
+    async fn config_changes_are_versioned_and_rolled_back() {
         let state = test_state().await;
-        state
-            .db
-            .upsert_zone(&ZoneConfig {
-                zone_id: "z1".into(),
-                name: "Z".into(),
-                min_moisture: 0.2,
-                target_moisture: 0.5,
-                pulse_sec: 30,
-                soak_min: 10,
-                max_open_sec_per_day: 120,
-                max_pulses_per_day: 4,
-                stale_timeout_min: 30,
-                valve_gpio_pin: 17,
-            })
-            .await
-            .unwrap();
-        state
-            .db
-            .upsert_sensor(&SensorConfig {
-                sensor_id: "s1".into(),
-                node_id: "n1".into(),
-                zone_id: "z1".into(),
-                raw_dry: 30000,
-                raw_wet: 10000,
-            })
+        let app = || router(state.clone());
+
+        let resp = app()
+            .oneshot(put_json("/api/zones/z1", sample_zone_json()))
             .await
             .unwrap();
-        state
-            .db
-            .insert_reading(1000, "s1", 20000, 0.5)
+        assert_eq!(resp.status(), StatusCode::OK);
+        let mut bad = sample_zone_json();
+        bad["min_moisture"] = serde_json::json!(0.05);
+        app().oneshot(put_json("/api/zones/z1", bad)).await.unwrap();
+        app()
+            .oneshot(put_json("/api/zones/z2", sample_zone_json()))
             .await
             .unwrap();
-        state
-            .db
-            .insert_reading(1001, "s1", 21000, 0.45)
+
+        let versions = body_json(
+            app()
+                .oneshot(get_req("/api/config/versions"))
+                .await
+                .unwrap(),
+        )
+        .await;
+        let versions = versions.as_array().unwrap();
+        assert_eq!(versions.len(), 3);
+        // Newest first.
+        assert_eq!(versions[0]["reason"], "zone 'z2' updated");
+        let good = versions[2]["version"].as_i64().unwrap();
+
+        let resp = app()
+            .oneshot(post_req(&format!("/api/config/rollback/{good}")))
             .await
             .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp).await;
+        assert_eq!(json["restored_version"], good);
+        assert_eq!(json["new_version"], 4);
+        assert_eq!(json["kept_zones"], serde_json::json!([]));
 
-        let app = router(state);
+        let zones = state.db.load_zones().await.unwrap();
+        assert_eq!(zones.len(), 1);
+        assert_eq!(zones[0].min_moisture, 0.3);
 
-        // Filter by sensor_id
-        let resp = app
-            .clone()
-            .oneshot(get_req("/api/readings?sensor_id=s1"))
+        let resp = app()
+            .oneshot(get_req("/api/config/versions/4"))
             .await
             .unwrap();
         let json = body_json(resp).await;
-        assert_eq!(json.as_array().unwrap().len(), 2);
+        assert_eq!(json["reason"], format!("rollback to version {good}"));
+        assert_eq!(json["config"]["zones"].as_array().unwrap().len(), 1);
+    }
 
-        // Filter by zone_id
-        let resp = app
-            .clone()
-            .oneshot(get_req("/api/readings?zone_id=z1"))
+    #[tokio::test]
+    async fn zone_changes_are_audited_with_the_token_name() {
+        let mut state = test_state().await;
+        state.tokens = Arc::new(ApiTokens::parse(None, Some("alex:admin:a1")).unwrap());
+        let app = || router(state.clone());
+        let authed = |mut req: Request<Body>| {
+            req.headers_mut()
+                .insert(header::AUTHORIZATION, "Bearer a1".parse().unwrap());
+            req
+        };
+
+        app()
+            .oneshot(authed(put_json("/api/zones/z1", sample_zone_json())))
             .await
             .unwrap();
-        let json = body_json(resp).await;
-        assert_eq!(json.as_array().unwrap().len(), 2);
-
-        // Limit
-        let resp = app
-            .clone()
-            .oneshot(get_req("/api/readings?limit=1"))
+        let mut wetter = sample_zone_json();
+        wetter["max_open_sec_per_day"] = serde_json::json!(3600);
+        app()
+            .oneshot(authed(put_json("/api/zones/z1", wetter)))
             .await
             .unwrap();
-        let json = body_json(resp).await;
-        assert_eq!(json.as_array().unwrap().len(), 1);
 
-        // Offset
-        let resp = app
-            .oneshot(get_req("/api/readings?limit=10&offset=1"))
+        let resp = app()
+            .oneshot(authed(get_req("/api/audit?entity=zone&entity_id=z1")))
             .await
             .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
         let json = body_json(resp).await;
-        assert_eq!(json.as_array().unwrap().len(), 1);
+        let rows = json.as_array().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["action"], "update");
+        assert_eq!(rows[0]["source"], "api");
+        assert_eq!(rows[0]["actor"], "alex");
+        assert_eq!(rows[0]["old_value"]["max_open_sec_per_day"], 180);
+        assert_eq!(rows[0]["new_value"]["max_open_sec_per_day"], 3600);
+        assert_eq!(rows[1]["action"], "create");
+
+        let resp = app()
+            .oneshot(authed(get_req("/api/audit?entity=node")))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
     }
 
-    // -----------------------------------------------------------------------
-    // Watering events (read-only)
-    // -----------------------------------------------------------------------
-
     #[tokio::test]
-    async fn watering_events_empty_returns_empty_array() {
+    async fn config_rollback_unknown_version_returns_404() {
         let app = router(test_state().await);
-        let resp = app.oneshot(get_req("/api/watering-events")).await.unwrap();
-        assert_eq!(resp.status(), StatusCode::OK);
-
-        let json = body_json(resp).await;
-        assert!(json.as_array().unwrap().is_empty());
+        let resp = app
+            .oneshot(post_req("/api/config/rollback/99"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
     }
 
     #[tokio::test]
-    async fn watering_events_returns_inserted_data() {
-        let state = test_state().await;
-        state
-            .db
-            .upsert_zone(&ZoneConfig {
-                zone_id: "z1".into(),
-                name: "Z".into(),
-                min_moisture: 0.2,
-                target_moisture: 0.5,
-                pulse_sec: 30,
-                soak_min: 10,
-                max_open_sec_per_day: 120,
-                max_pulses_per_day: 4,
-                stale_timeout_min: 30,
-                valve_gpio_pin: 17,
+    async fn tokens_are_checked_against_route_roles() {
+        let mut state = test_state().await;
+        state.tokens = Arc::new(
+            ApiTokens::parse(Some("root"), Some("greenhouse:operator:gh,wall:viewer:wv")).unwrap(),
+        );
+        let app = router(state);
+        let send = |req: Request<Body>, token: Option<&str>| {
+            let mut req = req;
+            if let Some(token) = token {
+                req.headers_mut().insert(
+                    header::AUTHORIZATION,
+                    format!("Bearer {token}").parse().unwrap(),
+                );
+            }
+            let app = app.clone();
+            async move { app.oneshot(req).await.unwrap().status() }
+        };
+
+        // Exempt (503 here: no MQTT connection).
+        assert_ne!(
+            send(get_req("/api/health"), None).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            send(get_req("/api/zones"), None).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            send(get_req("/api/zones"), Some("nope")).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            send(get_req("/api/zones"), Some("wv")).await,
+            StatusCode::OK
+        );
+
+        // Viewers can't cancel sessions; operators get past auth (409: no
+        // MQTT here).
+        assert_eq!(
+            send(post_req("/api/sessions/1/cancel"), Some("wv")).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            send(post_req("/api/sessions/1/cancel"), Some("gh")).await,
+            StatusCode::CONFLICT
+        );
+
+        // Only admins edit zones.
+        let resp = app
+            .clone()
+            .oneshot({
+                let mut req = put_json("/api/zones/z1", sample_zone_json());
+                req.headers_mut()
+                    .insert(header::AUTHORIZATION, "Bearer gh".parse().unwrap());
+                req
             })
             .await
             .unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let body = body_json(resp).await;
+        assert_eq!(
+            body["message"],
+            "token 'greenhouse' has role operator; this needs admin"
+        );
+        assert_eq!(
+            send(put_json("/api/zones/z1", sample_zone_json()), Some("root")).await,
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn backup_restore_requires_confirmation() {
+        let dir =
+            std::env::temp_dir().join(format!("irrigation_web_restore_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // Backup and restore need a file-backed database.
+        let mut state = test_state().await;
+        state.db = Db::connect(&format!(
+            "sqlite:{}?mode=rwc",
+            dir.join("live.sqlite").display()
+        ))
+        .await
+        .unwrap();
+        state.db.migrate().await.unwrap();
         state
             .db
-            .insert_watering_event(1000, 1030, "z1", "dry", "ok")
-            .await
-            .unwrap();
-        state
-            .db
-            .insert_watering_event(2000, 2030, "z1", "dry", "ok")
+            .backup(dir.join("nightly.db").to_str().unwrap())
             .await
             .unwrap();
-
+        let (tx, mut rx) = tokio::sync::mpsc::channel::<RestoreRequest>(1);
+        state.restore = RestoreApi::new(Some(dir.clone()), tx);
+
+        // Stand-in for the main loop.
+        let db = state.db.clone();
+        tokio::spawn(async move {
+            while let Some(req) = rx.recv().await {
+                let result = db
+                    .restore_from(req.path.to_str().unwrap())
+                    .await
+                    .map(|rows| restore::RestoreOutcome {
+                        rows,
+                        zones: 0,
+                        sensors: 0,
+                        restart_required: false,
+                    })
+                    .map_err(|e| format!("{e:#}"));
+                let _ = req.reply.send(result);
+            }
+        });
         let app = router(state);
 
-        // All events
+        let resp = app.clone().oneshot(get_req("/api/backups")).await.unwrap();
+        let json = body_json(resp).await;
+        assert_eq!(json[0]["name"], "nightly.db");
+
+        let restore = |body: serde_json::Value| post_json("/api/backups/restore", body);
         let resp = app
             .clone()
-            .oneshot(get_req("/api/watering-events"))
+            .oneshot(restore(serde_json::json!({"file": "../nightly.db"})))
             .await
             .unwrap();
-        let json = body_json(resp).await;
-        assert_eq!(json.as_array().unwrap().len(), 2);
-
-        // Filter by zone_id
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
         let resp = app
             .clone()
-            .oneshot(get_req("/api/watering-events?zone_id=z1"))
+            .oneshot(restore(serde_json::json!({"file": "weekly.db"})))
             .await
             .unwrap();
-        let json = body_json(resp).await;
-        assert_eq!(json.as_array().unwrap().len(), 2);
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
 
-        // Limit + offset
+        // Step one only hands out a token.
         let resp = app
-            .oneshot(get_req("/api/watering-events?limit=1&offset=1"))
+            .clone()
+            .oneshot(restore(serde_json::json!({"file": "nightly.db"})))
             .await
             .unwrap();
-        let json = body_json(resp).await;
-        assert_eq!(json.as_array().unwrap().len(), 1);
-    }
-
-    // -----------------------------------------------------------------------
-    // Daily counters (read-only)
-    // -----------------------------------------------------------------------
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        let token = body_json(resp).await["confirm"]
+            .as_str()
+            .unwrap()
+            .to_string();
 
-    #[tokio::test]
-    async fn counters_returns_zeros_for_no_data() {
-        let state = test_state().await;
-        state
-            .db
-            .upsert_zone(&ZoneConfig {
-                zone_id: "z1".into(),
-                name: "Z".into(),
-                min_moisture: 0.2,
-                target_moisture: 0.5,
-                pulse_sec: 30,
-                soak_min: 10,
-                max_open_sec_per_day: 120,
-                max_pulses_per_day: 4,
-                stale_timeout_min: 30,
-                valve_gpio_pin: 17,
-            })
+        let resp = app
+            .clone()
+            .oneshot(restore(
+                serde_json::json!({"file": "nightly.db", "confirm": "wrong"}),
+            ))
             .await
             .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
 
-        let app = router(state);
+        let confirmed = serde_json::json!({"file": "nightly.db", "confirm": token});
         let resp = app
-            .oneshot(get_req("/api/counters/z1?day=2025-01-01"))
+            .clone()
+            .oneshot(restore(confirmed.clone()))
             .await
             .unwrap();
         assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(body_json(resp).await["restored"], "nightly.db");
 
-        let json = body_json(resp).await;
-        assert_eq!(json["open_sec"], 0);
-        assert_eq!(json["pulses"], 0);
+        // Tokens are single use.
+        let resp = app.oneshot(restore(confirmed)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    // -- listeners ---------------------------------------------------------
+
+    #[test]
+    fn web_bind_parses_ips_socket_addrs_and_unix_paths() {
+        let addrs =
+            parse_bind_addrs("127.0.0.1, ::1,[fe80::1]:8081,unix:/run/hub.sock", 8080).unwrap();
+        assert_eq!(
+            addrs,
+            vec![
+                BindAddr::Tcp("127.0.0.1:8080".parse().unwrap()),
+                BindAddr::Tcp("[::1]:8080".parse().unwrap()),
+                BindAddr::Tcp("[fe80::1]:8081".parse().unwrap()),
+                BindAddr::Unix(PathBuf::from("/run/hub.sock")),
+            ]
+        );
+        assert_eq!(parse_bind_addrs("", 8080).unwrap(), vec![]);
+
+        let errs = parse_bind_addrs("localhost,unix:,0.0.0.0", 8080).unwrap_err();
+        assert_eq!(errs.len(), 2);
+        assert!(errs[0].contains("'localhost'"), "{errs:?}");
+    }
+
+    #[test]
+    fn socket_mode_is_octal() {
+        assert_eq!(parse_socket_mode("660"), Some(0o660));
+        assert_eq!(parse_socket_mode(" 0770"), Some(0o770));
+        assert_eq!(parse_socket_mode("1777"), None);
+        assert_eq!(parse_socket_mode("rw-rw----"), None);
+        assert_eq!(parse_socket_mode("680"), None);
     }
 
+    #[cfg(unix)]
     #[tokio::test]
-    async fn counters_returns_accumulated_data() {
-        let state = test_state().await;
-        state
-            .db
-            .upsert_zone(&ZoneConfig {
-                zone_id: "z1".into(),
-                name: "Z".into(),
-                min_moisture: 0.2,
-                target_moisture: 0.5,
-                pulse_sec: 30,
-                soak_min: 10,
-                max_open_sec_per_day: 120,
-                max_pulses_per_day: 4,
-                stale_timeout_min: 30,
-                valve_gpio_pin: 17,
-            })
-            .await
-            .unwrap();
-        state.db.add_pulse("2025-06-01", "z1", 1).await.unwrap();
-        state
-            .db
-            .add_open_seconds("2025-06-01", "z1", 30)
+    async fn serves_on_unix_socket() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let path =
+            std::env::temp_dir().join(format!("irrigation_web_test_{}.sock", std::process::id()));
+        // A stale socket from an earlier run is replaced.
+        drop(std::os::unix::net::UnixListener::bind(&path));
+        tokio::spawn(serve_unix(
+            path.clone(),
+            router(test_state().await),
+            Some(0o660),
+        ));
+
+        let mut stream = loop {
+            if let Ok(s) = tokio::net::UnixStream::connect(&path).await {
+                break s;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        };
+        stream
+            .write_all(b"GET /api/zones HTTP/1.1\r\nHost: hub\r\nConnection: close\r\n\r\n")
             .await
             .unwrap();
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp).await.unwrap();
+        assert!(resp.starts_with("HTTP/1.1 200"), "{resp}");
+        let mode = std::os::unix::fs::PermissionsExt::mode(
+            &std::fs::metadata(&path).unwrap().permissions(),
+        );
+        assert_eq!(mode & 0o777, 0o660);
 
-        let app = router(state);
-        let resp = app
-            .oneshot(get_req("/api/counters/z1?day=2025-06-01"))
-            .await
-            .unwrap();
-        let json = body_json(resp).await;
-        assert_eq!(json["pulses"], 1);
-        assert_eq!(json["open_sec"], 30);
+        let _ = std::fs::remove_file(&path);
     }
 }
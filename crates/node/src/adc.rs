@@ -1,9 +1,17 @@
 //! ADS1115 16-bit ADC driver over I2C for soil moisture sensing.
 //!
-//! Reads single-ended channels at PGA ±4.096 V, 128 SPS, single-shot mode.
-//! This matches the calibration values in `config.toml` (`raw_dry ≈ 26000`,
-//! `raw_wet ≈ 12000`) for typical capacitive soil moisture sensors powered
-//! from 3.3 V.
+//! Reads single-ended channels in single-shot mode, by default at PGA
+//! ±4.096 V and 128 SPS.  This matches the calibration values in
+//! `config.toml` (`raw_dry ≈ 26000`, `raw_wet ≈ 12000`) for typical
+//! capacitive soil moisture sensors powered from 3.3 V.  `ADC_GAIN` and
+//! `ADC_DATA_RATE` change both (see [`Conversion`]); a different gain
+//! changes the raw scale, so recalibrate on the hub after changing it.
+//! With `ADC_REPORT_MV` each reading also carries the input voltage in
+//! millivolts, comparable across gains and sensor supply voltages.
+//!
+//! Each reported value is the median of `ADC_OVERSAMPLE` back-to-back
+//! conversions with outliers (relay switching spikes, I2C glitches) dropped,
+//! plus the spread of the kept conversions as `raw_stddev`.
 
 use rppal::i2c::I2c;
 use std::{thread, time::Duration};
@@ -30,25 +38,135 @@ const REG_CONFIG: u8 = 0x01;
 //   [2]     COMP_LAT
 //   [1:0]   COMP_QUE — 11 = disable comparator (default)
 
-/// Bits common to all channel reads:
+/// Bits common to all channel reads at the default gain and data rate:
 ///   OS=1 (start), PGA=001 (±4.096 V), MODE=1 (single-shot),
 ///   DR=100 (128 SPS), COMP_QUE=11 (comparator off).
 const CONFIG_BASE: u16 = 0b1_000_001_1_100_0_0_0_11;
 
+const PGA_SHIFT: u8 = 9;
+const DR_SHIFT: u8 = 5;
+const FIELD_MASK: u16 = 0b111;
+
 /// MUX values for single-ended reads (AINx vs GND).
 ///   AIN0: MUX=100, AIN1: MUX=101, AIN2: MUX=110, AIN3: MUX=111
 const MUX_SHIFT: u8 = 12;
 const MUX_SINGLE_ENDED: [u16; 4] = [0b100, 0b101, 0b110, 0b111];
 
 /// Maximum valid ADS1115 channel index (0–3 for single-ended).
-const MAX_CHANNEL: usize = 3;
+const MAX_CHANNEL: usize = crate::config::ADS1115_MAX_CHANNEL;
 
-/// Conversion time at 128 SPS is ~7.8 ms.  We wait 9 ms for margin.
-const CONVERSION_WAIT: Duration = Duration::from_millis(9);
+/// Extra wait on top of one conversion period, in percent (128 SPS:
+/// ~7.8 ms → 9 ms).
+const CONVERSION_MARGIN_PCT: u64 = 15;
 
 /// Bit 15 of the config register: conversion-ready flag when read.
 const OS_READY_BIT: u16 = 1 << 15;
 
+// ── Oversampling ────────────────────────────────────────────────────────────
+
+/// Conversions per reported sample when `ADC_OVERSAMPLE` is unset.
+/// 8 × ~9 ms keeps a full 4-channel read well under half a second.
+pub const DEFAULT_OVERSAMPLE: usize = 8;
+
+/// Upper bound for `ADC_OVERSAMPLE`.
+const MAX_OVERSAMPLE: usize = crate::config::MAX_OVERSAMPLE;
+
+/// Conversions further than this many median absolute deviations from the
+/// median are treated as outliers.
+const OUTLIER_MAD_FACTOR: f64 = 3.0;
+
+// ── Gain and data rate ──────────────────────────────────────────────────────
+
+/// Default PGA full-scale range (`ADC_GAIN`).
+pub const DEFAULT_GAIN_V: f64 = 4.096;
+
+/// Default data rate (`ADC_DATA_RATE`).
+pub const DEFAULT_DATA_RATE: u16 = 128;
+
+/// Gain and data rate of every conversion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conversion {
+    /// PGA field value (index into `ADS1115_GAINS_V`).
+    pga: u16,
+    /// DR field value (index into `ADS1115_DATA_RATES`).
+    dr: u16,
+}
+
+impl Default for Conversion {
+    fn default() -> Self {
+        Self::new(DEFAULT_GAIN_V, DEFAULT_DATA_RATE).expect("default gain and data rate")
+    }
+}
+
+impl Conversion {
+    /// `gain_v` is the full-scale range in volts (6.144 … 0.256), `sps` the
+    /// data rate (8 … 860).
+    pub fn new(gain_v: f64, sps: u16) -> anyhow::Result<Self> {
+        let pga = crate::config::ads1115_gain_index(gain_v).ok_or_else(|| {
+            anyhow::anyhow!(
+                "ADS1115 gain must be one of {:?} V, got {gain_v}",
+                crate::config::ADS1115_GAINS_V
+            )
+        })?;
+        let dr = crate::config::ads1115_rate_index(sps).ok_or_else(|| {
+            anyhow::anyhow!(
+                "ADS1115 data rate must be one of {:?} SPS, got {sps}",
+                crate::config::ADS1115_DATA_RATES
+            )
+        })?;
+        Ok(Self {
+            pga: pga as u16,
+            dr: dr as u16,
+        })
+    }
+
+    pub fn gain_v(self) -> f64 {
+        crate::config::ADS1115_GAINS_V[self.pga as usize]
+    }
+
+    pub fn sps(self) -> u16 {
+        crate::config::ADS1115_DATA_RATES[self.dr as usize]
+    }
+
+    /// Config register bits for a read, without the channel.
+    fn config_base(self) -> u16 {
+        CONFIG_BASE & !(FIELD_MASK << PGA_SHIFT) & !(FIELD_MASK << DR_SHIFT)
+            | self.pga << PGA_SHIFT
+            | self.dr << DR_SHIFT
+    }
+
+    /// Time to wait for one conversion.
+    fn wait(self) -> Duration {
+        Duration::from_micros(1_000_000 * (100 + CONVERSION_MARGIN_PCT) / 100 / self.sps() as u64)
+    }
+
+    /// Input voltage of a raw (possibly fractional) reading, in millivolts.
+    pub fn millivolts(self, raw: f64) -> f64 {
+        raw * self.gain_v() * 1000.0 / 32768.0
+    }
+}
+
+/// Parse `ADC_GAIN`: the PGA full-scale range in volts.
+pub fn parse_gain(env_val: &str) -> anyhow::Result<f64> {
+    let v: f64 = env_val
+        .trim()
+        .trim_end_matches(['v', 'V'])
+        .parse()
+        .map_err(|_| anyhow::anyhow!("invalid ADC_GAIN: {env_val:?}"))?;
+    Conversion::new(v, DEFAULT_DATA_RATE)?;
+    Ok(v)
+}
+
+/// Parse `ADC_DATA_RATE`: conversions per second.
+pub fn parse_data_rate(env_val: &str) -> anyhow::Result<u16> {
+    let sps: u16 = env_val
+        .trim()
+        .parse()
+        .map_err(|_| anyhow::anyhow!("invalid ADC_DATA_RATE: {env_val:?}"))?;
+    Conversion::new(DEFAULT_GAIN_V, sps)?;
+    Ok(sps)
+}
+
 // ── Channel configuration ───────────────────────────────────────────────────
 
 /// A mapping from an ADS1115 channel index (0–3) to a sensor ID string.
@@ -60,9 +178,21 @@ pub struct ChannelMap {
     pub sensor_id: String,
 }
 
-/// Build the config register value for a single-ended read on `channel`.
-fn config_for_channel(channel: usize) -> u16 {
-    CONFIG_BASE | (MUX_SINGLE_ENDED[channel] << MUX_SHIFT)
+fn check_channels(channels: &[ChannelMap]) -> anyhow::Result<()> {
+    for ch in channels {
+        anyhow::ensure!(
+            ch.channel <= MAX_CHANNEL,
+            "ADS1115 channel {} out of range (0–{MAX_CHANNEL})",
+            ch.channel,
+        );
+    }
+    Ok(())
+}
+
+/// Build the config register value for a single-ended read on `channel`,
+/// given the bits from [`Conversion::config_base`].
+fn config_for_channel(base: u16, channel: usize) -> u16 {
+    base | (MUX_SINGLE_ENDED[channel] << MUX_SHIFT)
 }
 
 // ── Driver ──────────────────────────────────────────────────────────────────
@@ -71,21 +201,27 @@ fn config_for_channel(channel: usize) -> u16 {
 pub struct Ads1115 {
     i2c: I2c,
     channels: Vec<ChannelMap>,
+    /// Conversions per reported sample (1 = no oversampling).
+    oversample: usize,
+    conversion: Conversion,
+    /// Add millivolts to each reading.
+    report_mv: bool,
 }
 
 impl Ads1115 {
     /// Open I2C bus 1 and configure for ADS1115 at `addr`.
     ///
-    /// `channels` defines which ADS1115 inputs to read and how to label them.
-    /// Panics if any channel index exceeds 3.
-    pub fn new(addr: u16, channels: Vec<ChannelMap>) -> anyhow::Result<Self> {
-        for ch in &channels {
-            anyhow::ensure!(
-                ch.channel <= MAX_CHANNEL,
-                "ADS1115 channel {} out of range (0–{MAX_CHANNEL})",
-                ch.channel,
-            );
-        }
+    /// `channels` defines which ADS1115 inputs to read and how to label them;
+    /// `oversample` is the number of conversions per reported sample.
+    /// Fails if any channel index exceeds 3.
+    pub fn new(
+        addr: u16,
+        channels: Vec<ChannelMap>,
+        oversample: usize,
+        conversion: Conversion,
+        report_mv: bool,
+    ) -> anyhow::Result<Self> {
+        check_channels(&channels)?;
 
         let mut i2c = I2c::new()?;
         i2c.set_slave_address(addr)?;
@@ -93,26 +229,44 @@ impl Ads1115 {
         tracing::info!(
             addr = format_args!("0x{addr:02x}"),
             channels = ?channels,
+            oversample,
+            gain_v = conversion.gain_v(),
+            sps = conversion.sps(),
+            report_mv,
             "ads1115 initialised"
         );
 
-        Ok(Self { i2c, channels })
+        Ok(Self {
+            i2c,
+            channels,
+            oversample: oversample.max(1),
+            conversion,
+            report_mv,
+        })
+    }
+
+    /// Replace the channel map (e.g. with one pushed by the hub).
+    pub fn set_channels(&mut self, channels: Vec<ChannelMap>) -> anyhow::Result<()> {
+        check_channels(&channels)?;
+        tracing::info!(channels = ?channels, "ads1115 channel map updated");
+        self.channels = channels;
+        Ok(())
     }
 
     /// Perform a single-shot read on `channel`, returning the raw 16-bit
     /// signed value (0–32767 for single-ended).
     fn read_channel(&mut self, channel: usize) -> anyhow::Result<i16> {
-        let config = config_for_channel(channel);
+        let config = config_for_channel(self.conversion.config_base(), channel);
         let config_bytes = config.to_be_bytes();
 
         // Write config register to start conversion.
         self.i2c.block_write(REG_CONFIG, &config_bytes)?;
 
         // Wait for conversion to complete.
-        thread::sleep(CONVERSION_WAIT);
+        thread::sleep(self.conversion.wait());
 
         // Poll the OS bit to confirm conversion is done.  Normally one wait
-        // is enough at 128 SPS; we retry briefly to be safe.
+        // is enough; we retry briefly to be safe.
         for _ in 0..3 {
             let mut buf = [0u8; 2];
             self.i2c.block_read(REG_CONFIG, &mut buf)?;
@@ -131,26 +285,51 @@ impl Ads1115 {
 
     /// Read all configured channels and return a `Vec<Reading>`.
     ///
-    /// On per-channel failure the channel is skipped (logged, not fatal).
+    /// Each channel is converted `oversample` times and reduced with
+    /// [`filter_samples`].  Individual failed conversions are dropped; a
+    /// channel with no successful conversion is skipped (logged, not fatal).
     pub fn read_all(&mut self) -> Vec<Reading> {
         let mut readings = Vec::with_capacity(self.channels.len());
 
         for ch in &self.channels.clone() {
-            match self.read_channel(ch.channel) {
-                Ok(raw) => {
+            let mut samples = Vec::with_capacity(self.oversample);
+            let mut last_err = None;
+            for _ in 0..self.oversample {
+                match self.read_channel(ch.channel) {
                     // Single-ended reads are non-negative; clamp defensively
                     // against bus corruption.
-                    let clamped = (raw as i32).clamp(0, 32767);
+                    Ok(raw) => samples.push((raw as i32).clamp(0, 32767)),
+                    Err(e) => last_err = Some(e),
+                }
+            }
+
+            match (filter_samples(&samples), last_err) {
+                (Some(f), err) => {
+                    if let Some(e) = err {
+                        tracing::warn!(
+                            channel = ch.channel,
+                            sensor_id = %ch.sensor_id,
+                            ok = samples.len(),
+                            of = self.oversample,
+                            "some adc conversions failed: {e}"
+                        );
+                    }
                     readings.push(Reading {
                         sensor_id: ch.sensor_id.clone(),
-                        raw: clamped,
+                        raw: f.median,
+                        raw_stddev: (self.oversample > 1).then_some(f.stddev),
+                        mv: self
+                            .report_mv
+                            .then(|| self.conversion.millivolts(f.median.into()) as f32),
+                        ts: None,
                     });
                 }
-                Err(e) => {
+                (None, err) => {
                     tracing::error!(
                         channel = ch.channel,
                         sensor_id = %ch.sensor_id,
-                        "adc read failed: {e}"
+                        "adc read failed: {}",
+                        err.map(|e| e.to_string()).unwrap_or_default()
                     );
                 }
             }
@@ -160,6 +339,60 @@ impl Ads1115 {
     }
 }
 
+// ── Filtering ───────────────────────────────────────────────────────────────
+
+/// Result of reducing a burst of conversions to one value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Filtered {
+    /// Median of the kept conversions.
+    pub median: i32,
+    /// Population standard deviation of the kept conversions.
+    pub stddev: f32,
+}
+
+/// Drop outliers (more than `OUTLIER_MAD_FACTOR` median absolute deviations
+/// from the median, with the MAD floored at 1 LSB) and summarise the rest.
+/// Returns `None` for an empty burst.
+pub fn filter_samples(samples: &[i32]) -> Option<Filtered> {
+    if samples.is_empty() {
+        return None;
+    }
+    let median = median_of(samples);
+    let deviations: Vec<f64> = samples.iter().map(|s| (*s as f64 - median).abs()).collect();
+    let mad = median_f64(&deviations).max(1.0);
+    let kept: Vec<i32> = samples
+        .iter()
+        .zip(&deviations)
+        .filter(|(_, d)| **d <= OUTLIER_MAD_FACTOR * mad)
+        .map(|(s, _)| *s)
+        .collect();
+
+    let n = kept.len() as f64;
+    let mean = kept.iter().map(|s| *s as f64).sum::<f64>() / n;
+    let var = kept.iter().map(|s| (*s as f64 - mean).powi(2)).sum::<f64>() / n;
+    Some(Filtered {
+        median: median_of(&kept).round() as i32,
+        stddev: var.sqrt() as f32,
+    })
+}
+
+fn median_of(samples: &[i32]) -> f64 {
+    let v: Vec<f64> = samples.iter().map(|s| *s as f64).collect();
+    median_f64(&v)
+}
+
+/// Median of a non-empty slice (mean of the middle two for even lengths).
+fn median_f64(values: &[f64]) -> f64 {
+    let mut v = values.to_vec();
+    v.sort_by(f64::total_cmp);
+    let mid = v.len() / 2;
+    if v.len().is_multiple_of(2) {
+        (v[mid - 1] + v[mid]) / 2.0
+    } else {
+        v[mid]
+    }
+}
+
 // ── Channel parsing ─────────────────────────────────────────────────────────
 
 /// Parse the `SENSOR_CHANNELS` environment variable into a channel map.
@@ -191,6 +424,25 @@ pub fn parse_channels(env_val: &str) -> anyhow::Result<Vec<ChannelMap>> {
     Ok(channels)
 }
 
+/// Parse the `ADC_OVERSAMPLE` environment variable: conversions per
+/// reported sample, `1` to disable oversampling.
+///
+/// Defaults to [`DEFAULT_OVERSAMPLE`] if the variable is unset or empty.
+pub fn parse_oversample(env_val: &str) -> anyhow::Result<usize> {
+    if env_val.trim().is_empty() {
+        return Ok(DEFAULT_OVERSAMPLE);
+    }
+    let n: usize = env_val
+        .trim()
+        .parse()
+        .map_err(|_| anyhow::anyhow!("invalid ADC_OVERSAMPLE: {env_val:?}"))?;
+    anyhow::ensure!(
+        (1..=MAX_OVERSAMPLE).contains(&n),
+        "ADC_OVERSAMPLE must be 1–{MAX_OVERSAMPLE}, got {n}"
+    );
+    Ok(n)
+}
+
 // ── Tests ───────────────────────────────────────────────────────────────────
 
 #[cfg(test)]
@@ -202,25 +454,25 @@ mod tests {
     #[test]
     fn config_register_channel_a0() {
         // AIN0 vs GND: MUX = 100 → bits [14:12] = 0b100
-        let cfg = config_for_channel(0);
+        let cfg = config_for_channel(CONFIG_BASE, 0);
         assert_eq!(cfg, 0xC383, "A0 config: {cfg:#06x}");
     }
 
     #[test]
     fn config_register_channel_a1() {
-        let cfg = config_for_channel(1);
+        let cfg = config_for_channel(CONFIG_BASE, 1);
         assert_eq!(cfg, 0xD383, "A1 config: {cfg:#06x}");
     }
 
     #[test]
     fn config_register_channel_a2() {
-        let cfg = config_for_channel(2);
+        let cfg = config_for_channel(CONFIG_BASE, 2);
         assert_eq!(cfg, 0xE383, "A2 config: {cfg:#06x}");
     }
 
     #[test]
     fn config_register_channel_a3() {
-        let cfg = config_for_channel(3);
+        let cfg = config_for_channel(CONFIG_BASE, 3);
         assert_eq!(cfg, 0xF383, "A3 config: {cfg:#06x}");
     }
 
@@ -252,6 +504,52 @@ mod tests {
         assert_eq!(os, 1, "OS should be set to start conversion");
     }
 
+    // -- Gain and data rate ---------------------------------------------------
+
+    #[test]
+    fn default_conversion_matches_config_base() {
+        let c = Conversion::default();
+        assert_eq!(c.config_base(), CONFIG_BASE);
+        assert_eq!(c.wait().as_micros(), 8984);
+    }
+
+    #[test]
+    fn conversion_sets_pga_and_data_rate() {
+        let c = Conversion::new(2.048, 860).unwrap();
+        let base = c.config_base();
+        assert_eq!((base >> PGA_SHIFT) & FIELD_MASK, 0b010);
+        assert_eq!((base >> DR_SHIFT) & FIELD_MASK, 0b111);
+        // Everything else as in the default.
+        let others = !(FIELD_MASK << PGA_SHIFT) & !(FIELD_MASK << DR_SHIFT);
+        assert_eq!(base & others, CONFIG_BASE & others);
+        assert_eq!(config_for_channel(base, 0), 0xC5E3);
+
+        let slow = Conversion::new(6.144, 8).unwrap();
+        assert_eq!((slow.config_base() >> PGA_SHIFT) & FIELD_MASK, 0b000);
+        assert_eq!(slow.wait(), Duration::from_micros(143_750));
+
+        assert!(Conversion::new(3.3, 128).is_err());
+        assert!(Conversion::new(4.096, 100).is_err());
+    }
+
+    #[test]
+    fn millivolts_scale_with_gain() {
+        assert_eq!(Conversion::default().millivolts(16384.0), 2048.0);
+        let fine = Conversion::new(0.256, 128).unwrap();
+        assert_eq!(fine.millivolts(32768.0), 256.0);
+    }
+
+    #[test]
+    fn parse_gain_and_data_rate() {
+        assert_eq!(parse_gain("2.048").unwrap(), 2.048);
+        assert_eq!(parse_gain(" 6.144V ").unwrap(), 6.144);
+        assert!(parse_gain("5").is_err());
+        assert!(parse_gain("high").is_err());
+        assert_eq!(parse_data_rate("860").unwrap(), 860);
+        assert!(parse_data_rate("1000").is_err());
+        assert!(parse_data_rate("fast").is_err());
+    }
+
     // -- Channel parsing ------------------------------------------------------
 
     #[test]
@@ -306,4 +604,49 @@ mod tests {
     fn parse_channels_negative() {
         assert!(parse_channels("-1").is_err());
     }
+
+    // -- Oversampling ---------------------------------------------------------
+
+    #[test]
+    fn filter_samples_drops_spike() {
+        let f = filter_samples(&[20000, 20010, 19990, 20005, 31000, 19995, 20000, 2]).unwrap();
+        assert_eq!(f.median, 20000);
+        assert!(
+            f.stddev < 10.0,
+            "outliers should not inflate stddev: {}",
+            f.stddev
+        );
+    }
+
+    #[test]
+    fn filter_samples_even_count_median() {
+        let f = filter_samples(&[100, 102, 104, 106]).unwrap();
+        assert_eq!(f.median, 103);
+        assert!((f.stddev - 2.236).abs() < 0.01);
+    }
+
+    #[test]
+    fn filter_samples_constant_input() {
+        let f = filter_samples(&[15000; 8]).unwrap();
+        assert_eq!(f.median, 15000);
+        assert_eq!(f.stddev, 0.0);
+    }
+
+    #[test]
+    fn filter_samples_single_and_empty() {
+        let f = filter_samples(&[12345]).unwrap();
+        assert_eq!(f.median, 12345);
+        assert_eq!(f.stddev, 0.0);
+        assert!(filter_samples(&[]).is_none());
+    }
+
+    #[test]
+    fn parse_oversample_values() {
+        assert_eq!(parse_oversample("").unwrap(), DEFAULT_OVERSAMPLE);
+        assert_eq!(parse_oversample(" 4 ").unwrap(), 4);
+        assert_eq!(parse_oversample("1").unwrap(), 1);
+        assert!(parse_oversample("0").is_err());
+        assert!(parse_oversample("65").is_err());
+        assert!(parse_oversample("many").is_err());
+    }
 }
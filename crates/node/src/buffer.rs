@@ -0,0 +1,130 @@
+//! Bounded queue of readings taken while the node is offline.
+//!
+//! Readings are replayed oldest-first with their original timestamps once
+//! MQTT reconnects.  When the buffer is full the oldest reading is dropped —
+//! recent data matters more to the hub's scheduler than old history.
+
+use std::collections::VecDeque;
+
+/// Default capacity: 24 h of readings at the default 5-minute interval.
+pub const DEFAULT_CAPACITY: usize = 288;
+
+pub struct OfflineBuffer<T> {
+    queue: VecDeque<T>,
+    capacity: usize,
+    /// Readings dropped because the buffer was full.
+    dropped: u64,
+}
+
+impl<T> OfflineBuffer<T> {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            queue: VecDeque::with_capacity(capacity.min(DEFAULT_CAPACITY)),
+            capacity,
+            dropped: 0,
+        }
+    }
+
+    /// Queue an item, dropping the oldest one if full.  Returns `true` if
+    /// something was dropped.
+    pub fn push(&mut self, item: T) -> bool {
+        let full = self.queue.len() >= self.capacity;
+        if full {
+            self.queue.pop_front();
+            self.dropped += 1;
+        }
+        self.queue.push_back(item);
+        full
+    }
+
+    /// Take the oldest item for sending.
+    pub fn pop(&mut self) -> Option<T> {
+        self.queue.pop_front()
+    }
+
+    /// Put back an item that failed to send, keeping it first in line.
+    pub fn unpop(&mut self, item: T) {
+        if self.queue.len() >= self.capacity {
+            // A newer reading arrived meanwhile and filled the buffer.
+            self.dropped += 1;
+            return;
+        }
+        self.queue.push_front(item);
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+/// Parse the `OFFLINE_BUFFER_MAX` environment variable (readings to keep
+/// while offline).  Falls back to [`DEFAULT_CAPACITY`] if unset or invalid.
+pub fn parse_capacity(env_val: Option<&str>) -> usize {
+    env_val
+        .and_then(|s| s.trim().parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_CAPACITY)
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replays_in_order() {
+        let mut b = OfflineBuffer::new(4);
+        for i in 0..3 {
+            assert!(!b.push(i));
+        }
+        assert_eq!(b.len(), 3);
+        assert_eq!(b.pop(), Some(0));
+        assert_eq!(b.pop(), Some(1));
+        assert_eq!(b.pop(), Some(2));
+        assert_eq!(b.pop(), None);
+    }
+
+    #[test]
+    fn full_buffer_drops_oldest() {
+        let mut b = OfflineBuffer::new(2);
+        b.push(1);
+        b.push(2);
+        assert!(b.push(3));
+        assert_eq!(b.dropped(), 1);
+        assert_eq!(b.pop(), Some(2));
+        assert_eq!(b.pop(), Some(3));
+    }
+
+    #[test]
+    fn unpop_keeps_failed_item_first() {
+        let mut b = OfflineBuffer::new(3);
+        b.push(1);
+        b.push(2);
+        let first = b.pop().unwrap();
+        b.unpop(first);
+        assert_eq!(b.pop(), Some(1));
+
+        // No room to put it back: counted as dropped.
+        let mut b = OfflineBuffer::new(1);
+        b.push(1);
+        b.unpop(0);
+        assert_eq!(b.dropped(), 1);
+        assert_eq!(b.pop(), Some(1));
+    }
+
+    #[test]
+    fn parse_capacity_values() {
+        assert_eq!(parse_capacity(None), DEFAULT_CAPACITY);
+        assert_eq!(parse_capacity(Some("50")), 50);
+        assert_eq!(parse_capacity(Some("0")), DEFAULT_CAPACITY);
+        assert_eq!(parse_capacity(Some("lots")), DEFAULT_CAPACITY);
+    }
+}
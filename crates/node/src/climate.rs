@@ -0,0 +1,294 @@
+//! Air temperature and humidity from a DHT22 or SHT31 (`climate` feature).
+//!
+//! The reading rides along with each soil sample as `air_temp_c` and
+//! `humidity_pct`; the hub stores it and counts it towards the day's ET0.
+//! `CLIMATE_SENSOR` (or `climate.sensor`) picks the part:
+//!
+//! - `sht31`: I2C on bus 1 at `SHT31_ADDR` (default 0x44), one
+//!   high-repeatability single-shot measurement per sample.
+//! - `dht22`: the single-wire protocol bit-banged on BCM pin `DHT22_GPIO`.
+//!   Its pulse timing is tight for a userspace process, so a failed read is
+//!   retried a couple of times (the part needs 2 s between reads).
+//!
+//! A failed read leaves the fields out of that sample rather than stopping
+//! the soil readings.
+
+use rppal::gpio::{Bias, Gpio, IoPin, Mode};
+use rppal::i2c::I2c;
+use std::env;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::{ClimateConfig, ClimateKind};
+
+/// SHT31 address with ADDR pulled low.
+pub const DEFAULT_SHT31_ADDR: u16 = 0x44;
+
+/// Single shot, high repeatability, no clock stretching.
+const SHT31_MEASURE: [u8; 2] = [0x24, 0x00];
+
+/// Longest high-repeatability measurement (15 ms) plus a margin.
+const SHT31_MEASURE_WAIT: Duration = Duration::from_millis(16);
+
+/// Attempts per DHT22 sample.
+const DHT22_ATTEMPTS: usize = 3;
+
+/// Minimum time between DHT22 reads.
+const DHT22_RETRY_WAIT: Duration = Duration::from_secs(2);
+
+/// Longest the DHT22 may take for any single edge.
+const DHT22_EDGE_TIMEOUT: Duration = Duration::from_micros(200);
+
+/// High pulses longer than this are 1 bits (26–28 µs is 0, 70 µs is 1).
+const DHT22_ONE_THRESHOLD: Duration = Duration::from_micros(48);
+
+/// One air sample.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Climate {
+    pub air_temp_c: f32,
+    pub humidity_pct: f32,
+}
+
+pub enum Sensor {
+    Sht31(I2c),
+    Dht22(IoPin),
+}
+
+/// Open the sensor named by `CLIMATE_SENSOR` (env) or `climate.sensor`
+/// (file).  `None` when neither is set.
+pub fn from_env(cfg: &ClimateConfig) -> anyhow::Result<Option<Sensor>> {
+    let kind = match env::var("CLIMATE_SENSOR") {
+        Ok(raw) if !raw.trim().is_empty() => raw.parse()?,
+        _ => match cfg.sensor {
+            Some(kind) => kind,
+            None => return Ok(None),
+        },
+    };
+    let sensor = match kind {
+        ClimateKind::Sht31 => {
+            let addr = env::var("SHT31_ADDR")
+                .ok()
+                .and_then(|s| u16::from_str_radix(s.trim_start_matches("0x"), 16).ok())
+                .or(cfg.address)
+                .unwrap_or(DEFAULT_SHT31_ADDR);
+            let mut i2c = I2c::new()?;
+            i2c.set_slave_address(addr)?;
+            tracing::info!(addr = format_args!("0x{addr:02x}"), "sht31 initialised");
+            Sensor::Sht31(i2c)
+        }
+        ClimateKind::Dht22 => {
+            let pin = match env::var("DHT22_GPIO") {
+                Ok(raw) if !raw.trim().is_empty() => raw
+                    .trim()
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("invalid DHT22_GPIO: {raw:?}"))?,
+                _ => cfg.gpio.ok_or_else(|| {
+                    anyhow::anyhow!("CLIMATE_SENSOR=dht22 needs DHT22_GPIO (or climate.gpio)")
+                })?,
+            };
+            let mut io = Gpio::new()?.get(pin)?.into_io(Mode::Input);
+            io.set_bias(Bias::PullUp);
+            tracing::info!(gpio = pin, "dht22 initialised");
+            Sensor::Dht22(io)
+        }
+    };
+    Ok(Some(sensor))
+}
+
+impl Sensor {
+    /// One measurement.
+    pub fn read(&mut self) -> anyhow::Result<Climate> {
+        match self {
+            Self::Sht31(i2c) => {
+                i2c.write(&SHT31_MEASURE)?;
+                thread::sleep(SHT31_MEASURE_WAIT);
+                let mut buf = [0u8; 6];
+                i2c.read(&mut buf)?;
+                decode_sht31(&buf)
+            }
+            Self::Dht22(pin) => {
+                let mut last_err = None;
+                for attempt in 0..DHT22_ATTEMPTS {
+                    if attempt > 0 {
+                        thread::sleep(DHT22_RETRY_WAIT);
+                    }
+                    match read_dht22(pin) {
+                        Ok(c) => return Ok(c),
+                        Err(e) => last_err = Some(e),
+                    }
+                }
+                Err(last_err.unwrap_or_else(|| anyhow::anyhow!("dht22: no attempts made")))
+            }
+        }
+    }
+
+    /// `(air_temp_c, humidity_pct)` for a telemetry message; both `None`
+    /// (logged) when the read fails.
+    pub fn sample(&mut self) -> (Option<f32>, Option<f32>) {
+        match self.read() {
+            Ok(c) => (Some(c.air_temp_c), Some(c.humidity_pct)),
+            Err(e) => {
+                tracing::warn!("climate read failed: {e:#}");
+                (None, None)
+            }
+        }
+    }
+}
+
+// ── SHT31 ───────────────────────────────────────────────────────────────────
+
+/// Sensirion CRC-8: polynomial 0x31, init 0xFF, over one 16-bit word.
+fn sht31_crc(data: &[u8]) -> u8 {
+    let mut crc: u8 = 0xFF;
+    for byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x31
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Temperature word, CRC, humidity word, CRC.
+fn decode_sht31(buf: &[u8; 6]) -> anyhow::Result<Climate> {
+    anyhow::ensure!(
+        sht31_crc(&buf[0..2]) == buf[2] && sht31_crc(&buf[3..5]) == buf[5],
+        "sht31: CRC mismatch"
+    );
+    let t = f32::from(u16::from_be_bytes([buf[0], buf[1]]));
+    let rh = f32::from(u16::from_be_bytes([buf[3], buf[4]]));
+    Ok(Climate {
+        air_temp_c: -45.0 + 175.0 * t / 65535.0,
+        humidity_pct: (100.0 * rh / 65535.0).clamp(0.0, 100.0),
+    })
+}
+
+// ── DHT22 ───────────────────────────────────────────────────────────────────
+
+fn read_dht22(pin: &mut IoPin) -> anyhow::Result<Climate> {
+    // Start signal: hold the line low for at least 1 ms, then release it.
+    pin.set_mode(Mode::Output);
+    pin.set_low();
+    thread::sleep(Duration::from_micros(1200));
+    pin.set_high();
+    pin.set_mode(Mode::Input);
+
+    // Response: ~80 µs low, ~80 µs high, then 40 bits, each ~50 µs low
+    // followed by a high pulse whose length is the bit.
+    wait_for(pin, false)?;
+    wait_for(pin, true)?;
+    wait_for(pin, false)?;
+    let mut highs = [Duration::ZERO; 40];
+    for high in &mut highs {
+        wait_for(pin, true)?;
+        let start = Instant::now();
+        wait_for(pin, false)?;
+        *high = start.elapsed();
+    }
+    decode_dht22(&dht22_bytes(&highs))
+}
+
+/// Busy-wait for the line to reach `high`.
+fn wait_for(pin: &IoPin, high: bool) -> anyhow::Result<()> {
+    let start = Instant::now();
+    while pin.is_high() != high {
+        anyhow::ensure!(
+            start.elapsed() < DHT22_EDGE_TIMEOUT,
+            "dht22: timed out waiting for the line to go {}",
+            if high { "high" } else { "low" }
+        );
+    }
+    Ok(())
+}
+
+/// Pack 40 high-pulse lengths into bytes, MSB first.
+fn dht22_bytes(highs: &[Duration; 40]) -> [u8; 5] {
+    let mut bytes = [0u8; 5];
+    for (i, high) in highs.iter().enumerate() {
+        if *high > DHT22_ONE_THRESHOLD {
+            bytes[i / 8] |= 0x80 >> (i % 8);
+        }
+    }
+    bytes
+}
+
+/// Humidity and temperature in tenths (temperature sign in the top bit),
+/// then a checksum of the four bytes.
+fn decode_dht22(bytes: &[u8; 5]) -> anyhow::Result<Climate> {
+    let sum = bytes[..4].iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+    anyhow::ensure!(sum == bytes[4], "dht22: checksum mismatch");
+    let humidity_pct = f32::from(u16::from_be_bytes([bytes[0], bytes[1]])) / 10.0;
+    let magnitude = f32::from(u16::from_be_bytes([bytes[2] & 0x7F, bytes[3]])) / 10.0;
+    let air_temp_c = if bytes[2] & 0x80 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    };
+    // Outside the part's range is a garbled read that happened to sum up.
+    anyhow::ensure!(
+        humidity_pct <= 100.0 && (-40.0..=80.0).contains(&air_temp_c),
+        "dht22: implausible reading ({air_temp_c} °C, {humidity_pct} %)"
+    );
+    Ok(Climate {
+        air_temp_c,
+        humidity_pct,
+    })
+}
+
+// ── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sht31_crc_matches_datasheet() {
+        assert_eq!(sht31_crc(&[0xBE, 0xEF]), 0x92);
+    }
+
+    #[test]
+    fn sht31_decodes() {
+        let t = 0x6666u16.to_be_bytes();
+        let rh = 0x8000u16.to_be_bytes();
+        let buf = [t[0], t[1], sht31_crc(&t), rh[0], rh[1], sht31_crc(&rh)];
+        let c = decode_sht31(&buf).unwrap();
+        assert!((c.air_temp_c - 25.0).abs() < 0.01, "{c:?}");
+        assert!((c.humidity_pct - 50.0).abs() < 0.01, "{c:?}");
+
+        let mut bad = buf;
+        bad[5] ^= 1;
+        assert!(decode_sht31(&bad).is_err());
+    }
+
+    #[test]
+    fn dht22_decodes() {
+        // Datasheet example: 65.2 %RH, 35.1 °C.
+        let c = decode_dht22(&[0x02, 0x8C, 0x01, 0x5F, 0xEE]).unwrap();
+        assert_eq!(c.humidity_pct, 65.2);
+        assert_eq!(c.air_temp_c, 35.1);
+        // Sign bit: -10.1 °C.
+        let c = decode_dht22(&[0x02, 0x8C, 0x80, 0x65, 0x73]).unwrap();
+        assert_eq!(c.air_temp_c, -10.1);
+
+        assert!(decode_dht22(&[0x02, 0x8C, 0x01, 0x5F, 0xEF]).is_err());
+        // 110 %RH with a valid checksum.
+        assert!(decode_dht22(&[0x04, 0x4C, 0x00, 0xC8, 0x18]).is_err());
+    }
+
+    #[test]
+    fn dht22_pulse_lengths_to_bytes() {
+        let zero = Duration::from_micros(27);
+        let one = Duration::from_micros(70);
+        let mut highs = [zero; 40];
+        // 0x02 in the first byte, 0xEE in the last.
+        highs[6] = one;
+        for i in [32, 33, 34, 36, 37, 38] {
+            highs[i] = one;
+        }
+        assert_eq!(dht22_bytes(&highs), [0x02, 0, 0, 0, 0xEE]);
+    }
+}
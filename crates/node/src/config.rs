@@ -0,0 +1,555 @@
+//! Optional node configuration file, loaded from `NODE_CONFIG_PATH`.
+//!
+//! Holds what is awkward to express in env vars — the channel map with
+//! sensor ids and calibration hints — alongside the usual settings.  Every
+//! field is optional: an env var still wins over the file, and the file
+//! wins over the built-in default.  Settings pushed by the hub on
+//! `cfg/<node_id>/set` override both at runtime.  On SIGHUP the node rereads
+//! the file's channel map, so probes can be remapped or disabled without a
+//! restart.
+//!
+//! ```toml
+//! node_id = "node-a"
+//! sample_every_s = 300
+//!
+//! [mqtt]
+//! host = "192.168.1.10"
+//! topic_prefix = "garden"
+//!
+//! [adc]
+//! address = 0x48
+//! oversample = 8
+//! gain = 4.096
+//! data_rate = 128
+//!
+//! [[channels]]
+//! channel = 0
+//! sensor_id = "s1"
+//! raw_dry = 26000
+//! raw_wet = 12000
+//! enabled = true
+//!
+//! [climate]
+//! sensor = "sht31"
+//!
+//! [light]
+//! sensor = "bh1750"
+//!
+//! [ota]
+//! public_key = "<64 hex digits>"
+//! ```
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::env;
+use std::str::FromStr;
+
+use crate::payload::PayloadFormat;
+
+/// Highest single-ended ADS1115 input (AIN0–AIN3).
+pub const ADS1115_MAX_CHANNEL: usize = 3;
+
+/// Upper bound for ADC oversampling (`ADC_OVERSAMPLE` / `adc.oversample`).
+pub const MAX_OVERSAMPLE: usize = 64;
+
+/// ADS1115 PGA full-scale ranges in volts, indexed by the PGA field value.
+pub const ADS1115_GAINS_V: [f64; 6] = [6.144, 4.096, 2.048, 1.024, 0.512, 0.256];
+
+/// ADS1115 data rates in samples per second, indexed by the DR field value.
+pub const ADS1115_DATA_RATES: [u16; 8] = [8, 16, 32, 64, 128, 250, 475, 860];
+
+/// PGA field value for a full-scale range of `volts`.
+pub fn ads1115_gain_index(volts: f64) -> Option<usize> {
+    ADS1115_GAINS_V
+        .iter()
+        .position(|g| (g - volts).abs() < 1e-6)
+}
+
+/// DR field value for `sps` samples per second.
+pub fn ads1115_rate_index(sps: u16) -> Option<usize> {
+    ADS1115_DATA_RATES.iter().position(|r| *r == sps)
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct NodeConfig {
+    pub node_id: Option<String>,
+    /// Seconds between readings (`SAMPLE_EVERY_S`).
+    pub sample_every_s: Option<u64>,
+    /// Readings kept while offline (`OFFLINE_BUFFER_MAX`).
+    pub offline_buffer_max: Option<usize>,
+    pub mqtt: MqttConfig,
+    pub adc: AdcConfig,
+    /// Channel → sensor map (`SENSOR_CHANNELS`).  The simulator uses the
+    /// sensor ids and calibration hints.
+    pub channels: Vec<ChannelEntry>,
+    pub ota: OtaConfig,
+    pub climate: ClimateConfig,
+    pub light: LightConfig,
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct MqttConfig {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub user: Option<String>,
+    pub pass: Option<String>,
+    pub topic_prefix: Option<String>,
+    /// Telemetry encoding (`PAYLOAD_FORMAT`): `json` or `cbor`.
+    pub payload_format: Option<PayloadFormat>,
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdcConfig {
+    /// I2C address (`ADS1115_ADDR`).
+    pub address: Option<u16>,
+    /// Conversions per reported sample (`ADC_OVERSAMPLE`).
+    pub oversample: Option<usize>,
+    /// PGA full-scale range in volts (`ADC_GAIN`).
+    pub gain: Option<f64>,
+    /// Conversions per second (`ADC_DATA_RATE`).
+    pub data_rate: Option<u16>,
+    /// Publish millivolts alongside `raw` (`ADC_REPORT_MV`).
+    pub report_mv: Option<bool>,
+}
+
+/// Over-the-air updates (see `updater`).
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct OtaConfig {
+    /// Ed25519 key updates must be signed with, as hex
+    /// (`OTA_PUBLIC_KEY`).  Unset: updates are refused.
+    pub public_key: Option<String>,
+    /// Binary to replace (`OTA_INSTALL_PATH`); defaults to the running
+    /// executable.
+    pub install_path: Option<String>,
+}
+
+/// Air temperature / humidity sensor (see `climate`, needs the `climate`
+/// feature).
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClimateConfig {
+    /// `sht31` or `dht22` (`CLIMATE_SENSOR`).  Unset: no climate readings.
+    pub sensor: Option<ClimateKind>,
+    /// SHT31 I2C address (`SHT31_ADDR`, default 0x44).
+    pub address: Option<u16>,
+    /// BCM pin of the DHT22 data line (`DHT22_GPIO`).
+    pub gpio: Option<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClimateKind {
+    Sht31,
+    Dht22,
+}
+
+impl FromStr for ClimateKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "sht31" => Ok(Self::Sht31),
+            "dht22" => Ok(Self::Dht22),
+            other => bail!("unknown climate sensor '{other}' (expected sht31 or dht22)"),
+        }
+    }
+}
+
+/// Light sensor (see `light`, needs the `light` feature).
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LightConfig {
+    /// `bh1750` (`LIGHT_SENSOR`).  Unset: no light readings.
+    pub sensor: Option<LightKind>,
+    /// BH1750 I2C address (`BH1750_ADDR`, default 0x23).
+    pub address: Option<u16>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LightKind {
+    Bh1750,
+}
+
+impl FromStr for LightKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "bh1750" => Ok(Self::Bh1750),
+            other => bail!("unknown light sensor '{other}' (expected bh1750)"),
+        }
+    }
+}
+
+/// Highest BCM GPIO on the Raspberry Pi header.
+pub const MAX_BCM_GPIO: u8 = 27;
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ChannelEntry {
+    pub channel: usize,
+    /// Local sensor id to report readings under (e.g. "s1").
+    pub sensor_id: String,
+    /// Calibration hints, matching the hub's sensor config.  Readings are
+    /// always sent raw.
+    #[serde(default)]
+    pub raw_dry: Option<i64>,
+    #[serde(default)]
+    pub raw_wet: Option<i64>,
+    /// `false` leaves the channel unsampled, e.g. while a probe awaits
+    /// replacement.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl NodeConfig {
+    /// Validate all entries, reporting every violation (not just the first).
+    pub fn validate(&self) -> Result<()> {
+        let mut errors: Vec<String> = Vec::new();
+
+        if self
+            .node_id
+            .as_deref()
+            .is_some_and(|id| id.trim().is_empty())
+        {
+            errors.push("node_id must not be empty".to_string());
+        }
+        if self.sample_every_s == Some(0) {
+            errors.push("sample_every_s must be > 0".to_string());
+        }
+        if self.offline_buffer_max == Some(0) {
+            errors.push("offline_buffer_max must be > 0".to_string());
+        }
+        if self.mqtt.port == Some(0) {
+            errors.push("mqtt.port must be > 0".to_string());
+        }
+        if self
+            .mqtt
+            .topic_prefix
+            .as_deref()
+            .is_some_and(|p| p.contains(['+', '#']))
+        {
+            errors.push("mqtt.topic_prefix must not contain wildcards".to_string());
+        }
+        if self.mqtt.user.is_some() != self.mqtt.pass.is_some() {
+            errors.push("mqtt.user and mqtt.pass must be set together".to_string());
+        }
+        if let Some(n) = self.adc.oversample {
+            if !(1..=MAX_OVERSAMPLE).contains(&n) {
+                errors.push(format!(
+                    "adc.oversample must be 1–{MAX_OVERSAMPLE}, got {n}"
+                ));
+            }
+        }
+        if let Some(g) = self.adc.gain.filter(|g| ads1115_gain_index(*g).is_none()) {
+            errors.push(format!(
+                "adc.gain must be one of {ADS1115_GAINS_V:?}, got {g}"
+            ));
+        }
+        if let Some(r) = self
+            .adc
+            .data_rate
+            .filter(|r| ads1115_rate_index(*r).is_none())
+        {
+            errors.push(format!(
+                "adc.data_rate must be one of {ADS1115_DATA_RATES:?}, got {r}"
+            ));
+        }
+        self.validate_channels(&mut errors);
+        if let Some(a) = self.climate.address.filter(|a| !matches!(a, 0x44 | 0x45)) {
+            errors.push(format!("climate.address must be 0x44 or 0x45, got {a:#x}"));
+        }
+        if let Some(g) = self.climate.gpio.filter(|g| *g > MAX_BCM_GPIO) {
+            errors.push(format!("climate.gpio must be 0–{MAX_BCM_GPIO}, got {g}"));
+        }
+        if let Some(a) = self.light.address.filter(|a| !matches!(a, 0x23 | 0x5C)) {
+            errors.push(format!("light.address must be 0x23 or 0x5c, got {a:#x}"));
+        }
+        if let Some(Err(e)) = self
+            .ota
+            .public_key
+            .as_deref()
+            .map(crate::updater::parse_public_key)
+        {
+            errors.push(format!("ota.public_key: {e}"));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            bail!(
+                "config validation failed ({} error{}):\n  - {}",
+                errors.len(),
+                if errors.len() == 1 { "" } else { "s" },
+                errors.join("\n  - ")
+            );
+        }
+    }
+
+    fn validate_channels(&self, errors: &mut Vec<String>) {
+        let mut seen_channels: HashSet<usize> = HashSet::new();
+        let mut seen_ids: HashSet<&str> = HashSet::new();
+
+        for (i, ch) in self.channels.iter().enumerate() {
+            let ctx = format!("channels[{i}]");
+            if ch.channel > ADS1115_MAX_CHANNEL {
+                errors.push(format!(
+                    "{ctx}: channel must be 0–{ADS1115_MAX_CHANNEL}, got {}",
+                    ch.channel
+                ));
+            }
+            if !seen_channels.insert(ch.channel) {
+                errors.push(format!(
+                    "{ctx}: channel {} assigned more than once",
+                    ch.channel
+                ));
+            }
+            if ch.sensor_id.trim().is_empty() {
+                errors.push(format!("{ctx}: sensor_id must not be empty"));
+            } else if ch.sensor_id.contains('/') {
+                // The hub qualifies ids as "<node_id>/<sensor_id>".
+                errors.push(format!(
+                    "{ctx}: sensor_id '{}' must be the local id (no '/')",
+                    ch.sensor_id
+                ));
+            } else if !seen_ids.insert(&ch.sensor_id) {
+                errors.push(format!("{ctx}: duplicate sensor_id '{}'", ch.sensor_id));
+            }
+            match (ch.raw_dry, ch.raw_wet) {
+                (Some(dry), Some(wet)) if dry <= wet => errors.push(format!(
+                    "{ctx}: raw_dry ({dry}) must be greater than raw_wet ({wet})"
+                )),
+                (Some(_), None) | (None, Some(_)) => {
+                    errors.push(format!("{ctx}: raw_dry and raw_wet must be set together"))
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Load and validate the config file.
+pub fn load(path: &str) -> Result<NodeConfig> {
+    let contents =
+        std::fs::read_to_string(path).with_context(|| format!("failed to read config: {path}"))?;
+    let config: NodeConfig =
+        toml::from_str(&contents).with_context(|| format!("failed to parse config: {path}"))?;
+    config
+        .validate()
+        .with_context(|| format!("invalid config: {path}"))?;
+    Ok(config)
+}
+
+/// The file named by `NODE_CONFIG_PATH`, or an empty config when unset.
+pub fn load_from_env() -> Result<NodeConfig> {
+    match env::var("NODE_CONFIG_PATH") {
+        Ok(path) => {
+            let config = load(&path)?;
+            tracing::info!(path = %path, "loaded node config file");
+            Ok(config)
+        }
+        Err(_) => Ok(NodeConfig::default()),
+    }
+}
+
+/// `key` from the environment if set and parseable, else the file value,
+/// else `default`.
+pub fn env_or<T: FromStr>(key: &str, file: Option<T>, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .or(file)
+        .unwrap_or(default)
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_validation_err(cfg: &NodeConfig, needle: &str) {
+        let msg = format!("{:#}", cfg.validate().unwrap_err());
+        assert!(
+            msg.contains(needle),
+            "expected error containing {needle:?}, got: {msg}"
+        );
+    }
+
+    #[test]
+    fn empty_file_is_valid() {
+        let cfg: NodeConfig = toml::from_str("").unwrap();
+        assert_eq!(cfg, NodeConfig::default());
+        cfg.validate().unwrap();
+    }
+
+    #[test]
+    fn parses_full_config() {
+        let cfg: NodeConfig = toml::from_str(
+            r#"
+node_id = "node-b"
+sample_every_s = 600
+
+[mqtt]
+host = "10.0.0.2"
+port = 8883
+topic_prefix = "garden"
+payload_format = "cbor"
+
+[adc]
+address = 0x49
+oversample = 16
+gain = 2.048
+data_rate = 250
+report_mv = true
+
+[climate]
+sensor = "dht22"
+gpio = 4
+
+[light]
+sensor = "bh1750"
+address = 0x5c
+
+[[channels]]
+channel = 2
+sensor_id = "s1"
+raw_dry = 26000
+raw_wet = 12000
+
+[[channels]]
+channel = 3
+sensor_id = "s2"
+enabled = false
+"#,
+        )
+        .unwrap();
+        cfg.validate().unwrap();
+        assert_eq!(cfg.node_id.as_deref(), Some("node-b"));
+        assert_eq!(cfg.mqtt.port, Some(8883));
+        assert_eq!(cfg.mqtt.payload_format, Some(PayloadFormat::Cbor));
+        assert_eq!(cfg.adc.address, Some(0x49));
+        assert_eq!(cfg.adc.gain, Some(2.048));
+        assert_eq!(cfg.adc.data_rate, Some(250));
+        assert_eq!(cfg.adc.report_mv, Some(true));
+        assert_eq!(cfg.climate.sensor, Some(ClimateKind::Dht22));
+        assert_eq!(cfg.climate.gpio, Some(4));
+        assert_eq!(" SHT31".parse::<ClimateKind>().unwrap(), ClimateKind::Sht31);
+        assert!("bme280".parse::<ClimateKind>().is_err());
+        assert_eq!(cfg.light.sensor, Some(LightKind::Bh1750));
+        assert_eq!(cfg.light.address, Some(0x5C));
+        assert_eq!(cfg.channels[0].channel, 2);
+        assert_eq!(cfg.channels[0].raw_wet, Some(12000));
+        assert!(cfg.channels[0].enabled);
+        assert!(!cfg.channels[1].enabled);
+    }
+
+    #[test]
+    fn example_file_is_valid() {
+        let cfg: NodeConfig = toml::from_str(include_str!("../node.example.toml")).unwrap();
+        cfg.validate().unwrap();
+        assert_eq!(cfg.channels.len(), 2);
+    }
+
+    #[test]
+    fn unknown_keys_rejected() {
+        assert!(toml::from_str::<NodeConfig>("sample_every = 10").is_err());
+    }
+
+    #[test]
+    fn channel_validation() {
+        let ch = |channel, sensor_id: &str| ChannelEntry {
+            channel,
+            sensor_id: sensor_id.into(),
+            raw_dry: None,
+            raw_wet: None,
+            enabled: true,
+        };
+        let cfg = NodeConfig {
+            channels: vec![
+                ch(0, "s1"),
+                ch(0, "s2"),
+                ch(4, "s1"),
+                ChannelEntry {
+                    raw_dry: Some(100),
+                    raw_wet: Some(200),
+                    ..ch(1, "node-a/s3")
+                },
+            ],
+            ..NodeConfig::default()
+        };
+        assert_validation_err(&cfg, "channels[1]: channel 0 assigned more than once");
+        assert_validation_err(&cfg, "channels[2]: channel must be 0–3, got 4");
+        assert_validation_err(&cfg, "channels[2]: duplicate sensor_id 's1'");
+        assert_validation_err(&cfg, "must be the local id");
+        assert_validation_err(&cfg, "raw_dry (100) must be greater than raw_wet (200)");
+    }
+
+    #[test]
+    fn settings_validation() {
+        let cfg = NodeConfig {
+            sample_every_s: Some(0),
+            mqtt: MqttConfig {
+                topic_prefix: Some("a/#".into()),
+                user: Some("node".into()),
+                ..MqttConfig::default()
+            },
+            adc: AdcConfig {
+                oversample: Some(0),
+                gain: Some(3.3),
+                data_rate: Some(100),
+                ..AdcConfig::default()
+            },
+            ota: OtaConfig {
+                public_key: Some("abcd".into()),
+                install_path: None,
+            },
+            climate: ClimateConfig {
+                sensor: Some(ClimateKind::Sht31),
+                address: Some(0x48),
+                gpio: Some(40),
+            },
+            light: LightConfig {
+                sensor: Some(LightKind::Bh1750),
+                address: Some(0x40),
+            },
+            ..NodeConfig::default()
+        };
+        assert_validation_err(&cfg, "10 errors");
+        assert_validation_err(&cfg, "light.address must be 0x23 or 0x5c, got 0x40");
+        assert_validation_err(&cfg, "climate.address must be 0x44 or 0x45, got 0x48");
+        assert_validation_err(&cfg, "climate.gpio must be 0–27, got 40");
+        assert_validation_err(&cfg, "ota.public_key: OTA public key must be 64 hex digits");
+        assert_validation_err(&cfg, "adc.gain must be one of");
+        assert_validation_err(
+            &cfg,
+            "adc.data_rate must be one of [8, 16, 32, 64, 128, 250, 475, 860], got 100",
+        );
+        assert_validation_err(&cfg, "mqtt.user and mqtt.pass must be set together");
+    }
+
+    #[test]
+    fn env_overrides_file() {
+        // Unique key so parallel tests don't interfere.
+        let key = "IRRIGATION_NODE_TEST_ENV_OR";
+        assert_eq!(env_or(key, Some(5u64), 1), 5);
+        assert_eq!(env_or::<u64>(key, None, 1), 1);
+        env::set_var(key, "9");
+        assert_eq!(env_or(key, Some(5u64), 1), 9);
+        env::set_var(key, "garbage");
+        assert_eq!(env_or(key, Some(5u64), 1), 5);
+        env::remove_var(key);
+    }
+}
@@ -0,0 +1,271 @@
+//! Remote diagnostics: commands the hub sends on `cmd/<node_id>/<command>`,
+//! so a node up a pole or in a hedge can be recovered without a ladder.
+//!
+//! - `restart`: announce offline and exit; systemd (`Restart=always`)
+//!   starts the node again.  Readings still in the offline buffer are lost.
+//! - `send-logs`: publish the last log lines (`{"lines": N}`, default 50) to
+//!   `diag/<node_id>/logs` as `{ "ts", "lines": [...] }`.
+//! - `ping`: answer at once on `diag/<node_id>/pong` with the node's
+//!   uptime and sampling state ([`PongMsg`]), echoing the request's
+//!   `sent_ms` so the hub can time the round trip.
+//!
+//! Commands published retained are ignored, so a stale `restart` can't
+//! put the node into a restart loop.  Log lines are kept in memory by a
+//! [`LogRing`] fed from the tracing subscriber.
+
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Log lines kept for `send-logs`.
+pub const LOG_RING_CAPACITY: usize = 500;
+
+/// Lines sent when the request doesn't say.
+pub const DEFAULT_LOG_LINES: usize = 50;
+
+/// Most lines sent per request.
+pub const MAX_LOG_LINES: usize = 200;
+
+/// Budget for the joined lines, keeping the message within the hub's
+/// diagnostics payload limit.
+pub const MAX_LOG_BYTES: usize = 48 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Restart,
+    SendLogs { lines: usize },
+    Ping { sent_ms: Option<i64> },
+}
+
+#[derive(Deserialize)]
+struct SendLogsArgs {
+    lines: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct PingArgs {
+    sent_ms: Option<i64>,
+}
+
+/// Topic prefix of this node's commands; subscribe to `<base>+`.
+pub fn command_base(prefix: &str, node_id: &str) -> String {
+    crate::prefixed(prefix, &format!("cmd/{node_id}/"))
+}
+
+/// Topic `send-logs` answers on.
+pub fn logs_topic(prefix: &str, node_id: &str) -> String {
+    crate::prefixed(prefix, &format!("diag/{node_id}/logs"))
+}
+
+/// Topic `ping` answers on.
+pub fn pong_topic(prefix: &str, node_id: &str) -> String {
+    crate::prefixed(prefix, &format!("diag/{node_id}/pong"))
+}
+
+/// Parse a message on `topic`; `None` if it isn't under `base` (see
+/// [`command_base`]).
+pub fn parse_command(base: &str, topic: &str, payload: &[u8]) -> Option<anyhow::Result<Command>> {
+    let name = topic.strip_prefix(base)?;
+    Some(match name {
+        "restart" => Ok(Command::Restart),
+        "send-logs" => parse_send_logs(payload),
+        "ping" => parse_ping(payload),
+        other => Err(anyhow::anyhow!("unknown command '{other}'")),
+    })
+}
+
+fn parse_send_logs(payload: &[u8]) -> anyhow::Result<Command> {
+    let lines = if payload.iter().all(u8::is_ascii_whitespace) {
+        None
+    } else {
+        serde_json::from_slice::<SendLogsArgs>(payload)?.lines
+    };
+    Ok(Command::SendLogs {
+        lines: lines.unwrap_or(DEFAULT_LOG_LINES).clamp(1, MAX_LOG_LINES),
+    })
+}
+
+fn parse_ping(payload: &[u8]) -> anyhow::Result<Command> {
+    let sent_ms = if payload.iter().all(u8::is_ascii_whitespace) {
+        None
+    } else {
+        serde_json::from_slice::<PingArgs>(payload)?.sent_ms
+    };
+    Ok(Command::Ping { sent_ms })
+}
+
+/// Sampling state the `ping` answer reports, kept current by the sampling
+/// loop.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Vitals {
+    pub sample_interval_sec: u64,
+    /// When the sampling loop last ran (unix seconds).
+    pub last_sample_ts: Option<i64>,
+    /// Readings waiting in the offline buffer.
+    pub buffered: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PongMsg {
+    pub ts: i64,
+    /// Echoed from the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sent_ms: Option<i64>,
+    pub uptime_sec: u64,
+    pub sample_interval_sec: u64,
+    pub last_sample_ts: Option<i64>,
+    pub buffered: usize,
+}
+
+impl PongMsg {
+    pub fn new(ts: i64, sent_ms: Option<i64>, uptime_sec: u64, vitals: Vitals) -> Self {
+        Self {
+            ts,
+            sent_ms,
+            uptime_sec,
+            sample_interval_sec: vitals.sample_interval_sec,
+            last_sample_ts: vitals.last_sample_ts,
+            buffered: vitals.buffered,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogsMsg {
+    pub ts: i64,
+    pub lines: Vec<String>,
+}
+
+/// The last [`LOG_RING_CAPACITY`] formatted log lines.  Cloning shares the
+/// buffer; use it as a tracing `MakeWriter`.
+#[derive(Clone, Default)]
+pub struct LogRing {
+    lines: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl LogRing {
+    /// Up to `n` of the newest lines, oldest first, within
+    /// [`MAX_LOG_BYTES`].
+    pub fn tail(&self, n: usize) -> Vec<String> {
+        let lines = self.lines.lock().unwrap_or_else(|e| e.into_inner());
+        let mut bytes = 0;
+        let mut out: Vec<String> = lines
+            .iter()
+            .rev()
+            .take(n)
+            .take_while(|l| {
+                bytes += l.len() + 1;
+                bytes <= MAX_LOG_BYTES
+            })
+            .cloned()
+            .collect();
+        out.reverse();
+        out
+    }
+
+    fn push(&self, line: &str) {
+        let mut lines = self.lines.lock().unwrap_or_else(|e| e.into_inner());
+        if lines.len() >= LOG_RING_CAPACITY {
+            lines.pop_front();
+        }
+        lines.push_back(line.to_string());
+    }
+}
+
+/// Collects one formatted record and stores it when dropped.
+pub struct LineWriter {
+    ring: LogRing,
+    buf: Vec<u8>,
+}
+
+impl io::Write for LineWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for LineWriter {
+    fn drop(&mut self) {
+        let text = String::from_utf8_lossy(&self.buf);
+        for line in text.lines().filter(|l| !l.is_empty()) {
+            self.ring.push(line);
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for LogRing {
+    type Writer = LineWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        LineWriter {
+            ring: self.clone(),
+            buf: Vec::new(),
+        }
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn parses_commands() {
+        let base = command_base("garden", "node-a");
+        let parse =
+            |topic: &str, payload: &[u8]| parse_command(&base, topic, payload).map(|r| r.ok());
+        assert_eq!(
+            parse("garden/cmd/node-a/restart", b""),
+            Some(Some(Command::Restart))
+        );
+        assert_eq!(
+            parse("garden/cmd/node-a/send-logs", b""),
+            Some(Some(Command::SendLogs { lines: 50 }))
+        );
+        assert_eq!(
+            parse("garden/cmd/node-a/send-logs", br#"{"lines":1000}"#),
+            Some(Some(Command::SendLogs { lines: 200 }))
+        );
+        assert_eq!(parse("garden/cmd/node-a/send-logs", b"lots"), Some(None));
+        assert_eq!(
+            parse("garden/cmd/node-a/ping", br#"{"sent_ms":1700000000123}"#),
+            Some(Some(Command::Ping {
+                sent_ms: Some(1_700_000_000_123)
+            }))
+        );
+        assert_eq!(
+            parse("garden/cmd/node-a/ping", b""),
+            Some(Some(Command::Ping { sent_ms: None }))
+        );
+        assert_eq!(parse("garden/cmd/node-a/reboot", b""), Some(None));
+        assert_eq!(parse("garden/cmd/node-b/restart", b""), None);
+        assert_eq!(parse("garden/cfg/node-a/set", b""), None);
+        assert_eq!(command_base("", "node-a"), "cmd/node-a/");
+        assert_eq!(logs_topic("garden", "node-a"), "garden/diag/node-a/logs");
+        assert_eq!(pong_topic("", "node-a"), "diag/node-a/pong");
+    }
+
+    #[test]
+    fn ring_keeps_newest_lines() {
+        let ring = LogRing::default();
+        for i in 0..LOG_RING_CAPACITY + 5 {
+            writeln!(ring.make_writer(), "line {i}").unwrap();
+        }
+        let tail = ring.tail(3);
+        assert_eq!(tail, ["line 502", "line 503", "line 504"]);
+        assert_eq!(ring.tail(1000).len(), LOG_RING_CAPACITY);
+        assert_eq!(ring.tail(1000)[0], "line 5");
+    }
+}
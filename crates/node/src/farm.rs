@@ -0,0 +1,251 @@
+//! Simulation farm (`--farm N`): N virtual nodes in one process, for load
+//! testing the hub's telemetry path and node heartbeat monitor.
+//!
+//! Each virtual node has its own MQTT connection, client id and last will,
+//! so the hub sees N independent nodes.  Node `i` (1-based) is
+//! `<NODE_ID>-<i>`; zones (`SIM_ZONE_ID`) and scenarios (`SIM_SCENARIO`)
+//! may be comma-separated lists, handed out round-robin.  With `SIM_SEED`
+//! node `i` is seeded with `SIM_SEED + i`.  Nodes answer `sim/valve/<zone>`
+//! and `sim/scenario/<node_id>` like a single simulated node, but don't
+//! buffer readings while offline or take hub settings and commands.
+
+use std::time::Duration;
+
+use rumqttc::{AsyncClient, Event, LastWill, MqttOptions, Packet, QoS};
+use tokio::task::JoinSet;
+use tokio::time::{interval, MissedTickBehavior};
+
+use crate::payload::PayloadFormat;
+use crate::sim::{self, Scenario, SoilMoistureSim};
+use crate::{now_unix, prefixed, Reading, ReadingMsg};
+
+/// Sensors each virtual node reports.
+const FARM_SENSOR_IDS: [&str; 2] = ["s1", "s2"];
+
+/// Settings shared by every virtual node.
+#[derive(Debug, Clone)]
+pub struct FarmOptions {
+    pub broker: String,
+    pub port: u16,
+    pub credentials: Option<(String, String)>,
+    pub topic_prefix: String,
+    pub payload_format: PayloadFormat,
+    pub sample_every_s: u64,
+    pub raw_dry: f64,
+    pub raw_wet: f64,
+    pub diurnal_period_s: f64,
+}
+
+/// One virtual node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FarmNode {
+    pub node_id: String,
+    pub zone_id: Option<String>,
+    pub scenario: Scenario,
+    pub seed: Option<u64>,
+}
+
+/// Split a comma-separated env value into its non-empty items.
+pub fn split_list(raw: &str) -> Vec<String> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// The `count` virtual nodes, zones and scenarios handed out round-robin.
+pub fn plan(
+    base_id: &str,
+    count: usize,
+    zones: &[String],
+    scenarios: &[Scenario],
+    seed: Option<u64>,
+) -> Vec<FarmNode> {
+    (1..=count)
+        .map(|i| FarmNode {
+            node_id: format!("{base_id}-{i}"),
+            zone_id: (!zones.is_empty()).then(|| zones[(i - 1) % zones.len()].clone()),
+            scenario: scenarios
+                .get((i - 1) % scenarios.len().max(1))
+                .copied()
+                .unwrap_or(Scenario::Drying),
+            seed: seed.map(|s| s.wrapping_add(i as u64)),
+        })
+        .collect()
+}
+
+/// Run every node until they all stop (they only stop on a bug).
+pub async fn run(nodes: Vec<FarmNode>, opts: FarmOptions) -> anyhow::Result<()> {
+    tracing::info!(
+        nodes = nodes.len(),
+        sample_every_s = opts.sample_every_s,
+        "starting simulation farm"
+    );
+    let mut tasks = JoinSet::new();
+    for node in nodes {
+        tasks.spawn(run_node(node, opts.clone()));
+    }
+    while let Some(result) = tasks.join_next().await {
+        if let Err(e) = result {
+            tracing::error!("farm node task failed: {e}");
+        }
+    }
+    Ok(())
+}
+
+async fn run_node(node: FarmNode, opts: FarmOptions) {
+    let id = &node.node_id;
+    let status_topic = prefixed(&opts.topic_prefix, &format!("status/node/{id}"));
+    let telemetry_topic = opts.payload_format.telemetry_topic(&opts.topic_prefix, id);
+    let scenario_topic = sim::scenario_topic(&opts.topic_prefix, id);
+    let valve_topic = node
+        .zone_id
+        .as_ref()
+        .map(|z| prefixed(&opts.topic_prefix, &format!("sim/valve/{z}")));
+
+    let mut mqttoptions =
+        MqttOptions::new(format!("irrigation-node-{id}"), &opts.broker, opts.port);
+    mqttoptions.set_keep_alive(Duration::from_secs(30));
+    mqttoptions.set_last_will(LastWill::new(
+        &status_topic,
+        b"offline".to_vec(),
+        QoS::AtLeastOnce,
+        true,
+    ));
+    if let Some((user, pass)) = &opts.credentials {
+        mqttoptions.set_credentials(user, pass);
+    }
+    let (client, mut eventloop) = AsyncClient::new(mqttoptions, 10);
+
+    let rng = node
+        .seed
+        .map_or_else(fastrand::Rng::new, fastrand::Rng::with_seed);
+    let mut sim = SoilMoistureSim::new(
+        rng,
+        node.scenario,
+        FARM_SENSOR_IDS.len(),
+        opts.raw_dry,
+        opts.raw_wet,
+        opts.diurnal_period_s,
+    );
+    let mut connected = false;
+    let mut ticker = interval(Duration::from_secs(opts.sample_every_s.max(1)));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    // The event loop is polled in the same task, so requests use the
+    // non-blocking `try_*` calls: a full request queue drops the message
+    // instead of stalling the poll that would drain it.
+    loop {
+        tokio::select! {
+            event = eventloop.poll() => match event {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    connected = true;
+                    tracing::info!(node = %id, "farm node connected");
+                    let mut topics = vec![scenario_topic.clone()];
+                    topics.extend(valve_topic.clone());
+                    let requests = [client.try_publish(
+                        &status_topic,
+                        QoS::AtLeastOnce,
+                        true,
+                        crate::online_payload(),
+                    )]
+                    .into_iter()
+                    .chain(topics.iter().map(|t| client.try_subscribe(t, QoS::AtLeastOnce)));
+                    for result in requests {
+                        if let Err(e) = result {
+                            tracing::error!(node = %id, "farm node setup failed: {e}");
+                        }
+                    }
+                }
+                Ok(Event::Incoming(Packet::Publish(msg))) => {
+                    let payload = String::from_utf8_lossy(&msg.payload);
+                    if msg.topic == scenario_topic {
+                        match Scenario::parse(&payload) {
+                            Some(s) => {
+                                tracing::info!(node = %id, scenario = %s, "sim: switching scenario");
+                                sim.set_scenario(s);
+                            }
+                            None => tracing::warn!(node = %id, "sim: ignoring unknown scenario"),
+                        }
+                    } else if valve_topic.as_deref() == Some(msg.topic.as_str()) {
+                        match payload.trim() {
+                            "open" => sim.set_watering(true),
+                            "close" => sim.set_watering(false),
+                            _ => {}
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    if connected {
+                        tracing::error!(node = %id, "mqtt error: {e} — retrying");
+                    }
+                    connected = false;
+                    tokio::time::sleep(Duration::from_secs(2)).await;
+                }
+            },
+            _ = ticker.tick() => {
+                if !connected {
+                    continue;
+                }
+                let msg = ReadingMsg {
+                    ts: now_unix(),
+                    readings: FARM_SENSOR_IDS
+                        .iter()
+                        .enumerate()
+                        .map(|(i, sensor_id)| Reading {
+                            sensor_id: sensor_id.to_string(),
+                            raw: sim.sample(i),
+                            raw_stddev: None,
+                            mv: None,
+                            ts: None,
+                        })
+                        .collect(),
+                    air_temp_c: None,
+                    humidity_pct: None,
+                    lux: None,
+                };
+                let payload = opts.payload_format.encode(&msg);
+                if let Err(e) = client.try_publish(&telemetry_topic, QoS::AtLeastOnce, false, payload) {
+                    tracing::warn!(node = %id, "dropped farm reading: {e}");
+                }
+            }
+        }
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plan_hands_out_zones_and_scenarios_round_robin() {
+        let zones = split_list("front, back,");
+        let scenarios = [Scenario::Drying, Scenario::Flaky, Scenario::Rain];
+        let nodes = plan("sim", 4, &zones, &scenarios, Some(100));
+        let summary: Vec<(&str, Option<&str>, Scenario, Option<u64>)> = nodes
+            .iter()
+            .map(|n| (n.node_id.as_str(), n.zone_id.as_deref(), n.scenario, n.seed))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("sim-1", Some("front"), Scenario::Drying, Some(101)),
+                ("sim-2", Some("back"), Scenario::Flaky, Some(102)),
+                ("sim-3", Some("front"), Scenario::Rain, Some(103)),
+                ("sim-4", Some("back"), Scenario::Drying, Some(104)),
+            ]
+        );
+
+        let bare = plan("node", 2, &[], &[], None);
+        assert_eq!(bare[1].node_id, "node-2");
+        assert_eq!(bare[1].zone_id, None);
+        assert_eq!(bare[1].scenario, Scenario::Drying);
+        assert_eq!(bare[1].seed, None);
+    }
+}
@@ -0,0 +1,113 @@
+//! Light level from a BH1750 on I2C (`light` feature).
+//!
+//! The reading rides along with each soil sample as `lux`; the hub holds
+//! back pulses while it is above `[sunlight] avoid_above_lux`.
+//! `LIGHT_SENSOR=bh1750` (or `light.sensor`) turns it on, at `BH1750_ADDR`
+//! (default 0x23, 0x5C with ADDR high) on bus 1.
+//!
+//! The part's default sensitivity saturates at about 54 600 lux, below
+//! full sun, so the measurement time is set to its minimum: that stretches
+//! the range to about 121 000 lux at 2 lux resolution, plenty for telling
+//! bright from overcast.  A failed read leaves `lux` out of that sample.
+
+use rppal::i2c::I2c;
+use std::env;
+use std::thread;
+use std::time::Duration;
+
+use crate::config::{LightConfig, LightKind};
+
+/// BH1750 address with ADDR pulled low.
+pub const DEFAULT_BH1750_ADDR: u16 = 0x23;
+
+const POWER_ON: u8 = 0x01;
+
+/// One-time high-resolution measurement; powers down afterwards.
+const ONE_TIME_H_RES: u8 = 0x20;
+
+/// Measurement time register: 31 (minimum) against the default 69.
+const MTREG: u8 = 31;
+const MTREG_DEFAULT: u8 = 69;
+
+/// Longest high-resolution measurement (180 ms at the default MTreg).
+const MEASURE_WAIT: Duration = Duration::from_millis(180);
+
+pub struct Sensor {
+    i2c: I2c,
+}
+
+/// Open the sensor named by `LIGHT_SENSOR` (env) or `light.sensor` (file).
+/// `None` when neither is set.
+pub fn from_env(cfg: &LightConfig) -> anyhow::Result<Option<Sensor>> {
+    let kind = match env::var("LIGHT_SENSOR") {
+        Ok(raw) if !raw.trim().is_empty() => raw.parse()?,
+        _ => match cfg.sensor {
+            Some(kind) => kind,
+            None => return Ok(None),
+        },
+    };
+    let LightKind::Bh1750 = kind;
+    let addr = env::var("BH1750_ADDR")
+        .ok()
+        .and_then(|s| u16::from_str_radix(s.trim_start_matches("0x"), 16).ok())
+        .or(cfg.address)
+        .unwrap_or(DEFAULT_BH1750_ADDR);
+    let mut i2c = I2c::new()?;
+    i2c.set_slave_address(addr)?;
+    i2c.write(&[POWER_ON])?;
+    i2c.write(&mtreg_commands(MTREG))?;
+    tracing::info!(addr = format_args!("0x{addr:02x}"), "bh1750 initialised");
+    Ok(Some(Sensor { i2c }))
+}
+
+impl Sensor {
+    /// One measurement in lux.
+    pub fn read(&mut self) -> anyhow::Result<f32> {
+        self.i2c.write(&[POWER_ON])?;
+        self.i2c.write(&[ONE_TIME_H_RES])?;
+        thread::sleep(MEASURE_WAIT);
+        let mut buf = [0u8; 2];
+        self.i2c.read(&mut buf)?;
+        Ok(counts_to_lux(u16::from_be_bytes(buf), MTREG))
+    }
+
+    /// `lux` for a telemetry message; `None` (logged) when the read fails.
+    pub fn sample(&mut self) -> Option<f32> {
+        self.read()
+            .inspect_err(|e| tracing::warn!("light read failed: {e:#}"))
+            .ok()
+    }
+}
+
+/// The two writes that set the measurement time register: its top three
+/// bits (`01000_hhh`), then its low five (`011_lllll`).
+fn mtreg_commands(mt: u8) -> [u8; 2] {
+    [0x40 | (mt >> 5), 0x60 | (mt & 0x1F)]
+}
+
+/// Counts at measurement time `mt` to lux (datasheet: counts / 1.2 at the
+/// default time, scaled inversely with it).
+fn counts_to_lux(counts: u16, mt: u8) -> f32 {
+    f32::from(counts) / 1.2 * f32::from(MTREG_DEFAULT) / f32::from(mt)
+}
+
+// ── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mtreg_split_into_commands() {
+        assert_eq!(mtreg_commands(69), [0x42, 0x65]);
+        assert_eq!(mtreg_commands(31), [0x40, 0x7F]);
+    }
+
+    #[test]
+    fn counts_scale_with_measurement_time() {
+        // Datasheet example: 0x83 0x90 at the default time is 28067 lux.
+        assert_eq!(counts_to_lux(0x8390, MTREG_DEFAULT).round(), 28_067.0);
+        // Full scale at the minimum time covers full sun.
+        assert!(counts_to_lux(u16::MAX, MTREG) > 120_000.0);
+    }
+}
@@ -4,12 +4,27 @@
 //! data for local development.  With the `adc` feature, reads a real ADS1115
 //! ADC over I2C (Pi Zero W production).
 
+mod buffer;
+mod config;
+mod diag;
+#[cfg(feature = "sim")]
+mod farm;
+mod payload;
+mod settings;
+mod updater;
+
 #[cfg(feature = "sim")]
 mod sim;
 
 #[cfg(feature = "adc")]
 mod adc;
 
+#[cfg(feature = "climate")]
+mod climate;
+
+#[cfg(feature = "light")]
+mod light;
+
 // Fail at compile time if no sensor backend is enabled.
 #[cfg(not(any(feature = "sim", feature = "adc")))]
 compile_error!("Enable either `sim` (fake data) or `adc` (real ADS1115) feature");
@@ -21,19 +36,211 @@ compile_error!("Features `sim` and `adc` are mutually exclusive");
 
 use rumqttc::{AsyncClient, Event, LastWill, MqttOptions, Packet, QoS};
 use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::{env, time::Duration};
-use tokio::time::sleep;
+use tokio::sync::{watch, Notify};
+use tokio::time::{sleep, sleep_until, Instant};
+use tracing_subscriber::prelude::*;
+
+use buffer::OfflineBuffer;
+use payload::PayloadFormat;
+use settings::NodeSettings;
+use updater::{Announcement, Report, Updater};
 
 #[derive(Debug, Serialize)]
 struct Reading {
     sensor_id: String,
     raw: i32,
+    /// Spread of the oversampled conversions behind `raw` (ADC backend only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    raw_stddev: Option<f32>,
+    /// Input voltage behind `raw` in millivolts (ADC backend with
+    /// `ADC_REPORT_MV`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mv: Option<f32>,
+    /// When the reading was taken, set when buffered messages are batched
+    /// on replay; the message's `ts` otherwise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ts: Option<i64>,
 }
 
 #[derive(Debug, Serialize)]
 struct ReadingMsg {
     ts: i64,
     readings: Vec<Reading>,
+    /// Air temperature and humidity (`climate` feature).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    air_temp_c: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    humidity_pct: Option<f32>,
+    /// Light level (`light` feature).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lux: Option<f32>,
+}
+
+/// Validate `MQTT_TOPIC_PREFIX` (must match the hub's): surrounding slashes
+/// are trimmed and wildcards rejected.  Empty means no prefix.
+fn parse_topic_prefix(raw: &str) -> anyhow::Result<String> {
+    let prefix = raw.trim().trim_matches('/');
+    anyhow::ensure!(
+        !prefix.contains(['+', '#']),
+        "MQTT_TOPIC_PREFIX must not contain wildcards, got {raw:?}"
+    );
+    Ok(prefix.to_string())
+}
+
+/// Apply the topic namespace to `path`.
+fn prefixed(prefix: &str, path: &str) -> String {
+    if prefix.is_empty() {
+        path.to_string()
+    } else {
+        format!("{prefix}/{path}")
+    }
+}
+
+/// Samples `--dump` prints when no count is given: a day at the default
+/// 5 minute interval.
+#[cfg(feature = "sim")]
+const DEFAULT_DUMP_SAMPLES: usize = 288;
+
+/// What the process does, from the command line.
+#[derive(Debug, PartialEq, Eq)]
+enum Mode {
+    /// No arguments: run as a sensor node.
+    Node,
+    /// `--dump [N]`: print N simulated readings and exit.
+    #[cfg(feature = "sim")]
+    Dump(usize),
+    /// `--farm N`: run N simulated nodes (see `farm`).
+    #[cfg(feature = "sim")]
+    Farm(usize),
+}
+
+impl Mode {
+    /// `--dump` writes readings to stdout, so logs go to stderr.
+    fn owns_stdout(&self) -> bool {
+        match self {
+            #[cfg(feature = "sim")]
+            Self::Dump(_) => true,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(feature = "sim")]
+fn parse_count(flag: &str, n: &str) -> anyhow::Result<usize> {
+    n.parse()
+        .map_err(|_| anyhow::anyhow!("{flag} expects a count, got {n:?}"))
+}
+
+/// Parse the command line (see [`Mode`]).
+fn parse_args(args: &[String]) -> anyhow::Result<Mode> {
+    match args {
+        [] => Ok(Mode::Node),
+        #[cfg(feature = "sim")]
+        [flag] if flag == "--dump" => Ok(Mode::Dump(DEFAULT_DUMP_SAMPLES)),
+        #[cfg(feature = "sim")]
+        [flag, n] if flag == "--dump" => Ok(Mode::Dump(parse_count(flag, n)?)),
+        #[cfg(feature = "sim")]
+        [flag, n] if flag == "--farm" => match parse_count(flag, n)? {
+            0 => anyhow::bail!("--farm needs at least one node"),
+            n => Ok(Mode::Farm(n)),
+        },
+        #[cfg(not(feature = "sim"))]
+        [flag, ..] if flag == "--dump" || flag == "--farm" => {
+            anyhow::bail!("{flag} needs the `sim` feature")
+        }
+        _ => anyhow::bail!("usage: irrigation-node [--dump [SAMPLES] | --farm NODES]"),
+    }
+}
+
+/// Print `samples` ticks of simulated readings as telemetry JSON, one
+/// message per line.  Timestamps count seconds from the start of the trace,
+/// which runs on its own clock so a seeded simulator repeats it exactly.
+#[cfg(feature = "sim")]
+fn dump_readings(
+    sim: &mut sim::SoilMoistureSim,
+    sensor_ids: &[String],
+    samples: usize,
+    every_s: u64,
+    mut out: impl std::io::Write,
+) -> anyhow::Result<()> {
+    for i in 0..samples {
+        let ts = i as u64 * every_s;
+        let readings = sensor_ids
+            .iter()
+            .enumerate()
+            .map(|(idx, sensor_id)| Reading {
+                sensor_id: sensor_id.clone(),
+                raw: sim.sample_at(idx, ts as f64),
+                raw_stddev: None,
+                mv: None,
+                ts: None,
+            })
+            .collect();
+        let msg = ReadingMsg {
+            ts: ts as i64,
+            readings,
+            air_temp_c: None,
+            humidity_pct: None,
+            lux: None,
+        };
+        serde_json::to_writer(&mut out, &msg)?;
+        writeln!(out)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Git commit this node was built from (see `build.rs`).
+const GIT_HASH: &str = env!("GIT_HASH");
+
+/// Retained `status/node/<node_id>` payload while connected, carrying the
+/// build so the hub can tell which nodes run old code.  The last will stays
+/// a bare `offline`.
+fn online_payload() -> Vec<u8> {
+    serde_json::json!({
+        "status": "online",
+        "version": updater::VERSION,
+        "git": GIT_HASH,
+    })
+    .to_string()
+    .into_bytes()
+}
+
+/// Sensor ids to simulate: the config file's enabled channels, or s1 and
+/// s2 when it has none.
+#[cfg(feature = "sim")]
+fn sim_channel_ids(channels: &[config::ChannelEntry]) -> Vec<String> {
+    if channels.is_empty() {
+        vec!["s1".into(), "s2".into()]
+    } else {
+        channels
+            .iter()
+            .filter(|ch| ch.enabled)
+            .map(|ch| ch.sensor_id.clone())
+            .collect()
+    }
+}
+
+/// The node's own channel map: `SENSOR_CHANNELS` if set, else the config
+/// file's enabled channels, else channels 0 and 1.
+#[cfg(feature = "adc")]
+fn local_adc_channels(channels: &[config::ChannelEntry]) -> anyhow::Result<Vec<adc::ChannelMap>> {
+    let raw = env::var("SENSOR_CHANNELS").unwrap_or_default();
+    if raw.is_empty() && !channels.is_empty() {
+        Ok(channels
+            .iter()
+            .filter(|ch| ch.enabled)
+            .map(|ch| adc::ChannelMap {
+                channel: ch.channel,
+                sensor_id: ch.sensor_id.clone(),
+            })
+            .collect())
+    } else {
+        adc::parse_channels(&raw)
+    }
 }
 
 fn now_unix() -> i64 {
@@ -53,42 +260,77 @@ fn now_unix() -> i64 {
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> anyhow::Result<()> {
-    // Structured logging
-    tracing_subscriber::fmt()
-        .with_env_filter(
+    let mode = parse_args(&env::args().skip(1).collect::<Vec<_>>())?;
+
+    // Structured logging, with the recent lines kept for `send-logs`.
+    // `--dump` owns stdout, so it logs to stderr.
+    let log_ring = diag::LogRing::default();
+    tracing_subscriber::registry()
+        .with(
             tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
         )
+        .with((!mode.owns_stdout()).then(tracing_subscriber::fmt::layer))
+        .with(
+            mode.owns_stdout()
+                .then(|| tracing_subscriber::fmt::layer().with_writer(std::io::stderr)),
+        )
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(log_ring.clone()),
+        )
         .init();
 
     // ── Env config ───────────────────────────────────────────────────
-    let broker = env::var("MQTT_HOST").unwrap_or_else(|_| "192.168.1.10".to_string());
-    let port: u16 = env::var("MQTT_PORT")
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(1883);
-    let node_id = env::var("NODE_ID").unwrap_or_else(|_| "node-a".to_string());
+    // Env vars take precedence over the optional NODE_CONFIG_PATH file.
+    let file_cfg = config::load_from_env()?;
 
-    let sample_every_s: u64 = env::var("SAMPLE_EVERY_S")
+    let broker = env::var("MQTT_HOST")
         .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(300);
+        .or(file_cfg.mqtt.host.clone())
+        .unwrap_or_else(|| "192.168.1.10".to_string());
+    let port: u16 = config::env_or("MQTT_PORT", file_cfg.mqtt.port, 1883);
+    let node_id = env::var("NODE_ID")
+        .ok()
+        .or(file_cfg.node_id.clone())
+        .unwrap_or_else(|| "node-a".to_string());
+    let topic_prefix = parse_topic_prefix(
+        &env::var("MQTT_TOPIC_PREFIX")
+            .ok()
+            .or(file_cfg.mqtt.topic_prefix.clone())
+            .unwrap_or_default(),
+    )?;
+
+    let payload_format = match env::var("PAYLOAD_FORMAT") {
+        Ok(raw) if !raw.trim().is_empty() => raw.parse()?,
+        _ => file_cfg.mqtt.payload_format.unwrap_or_default(),
+    };
+
+    let env_sample_every_s: u64 = config::env_or("SAMPLE_EVERY_S", file_cfg.sample_every_s, 300);
+
+    let buffer_capacity = buffer::parse_capacity(
+        env::var("OFFLINE_BUFFER_MAX")
+            .ok()
+            .or(file_cfg.offline_buffer_max.map(|n| n.to_string()))
+            .as_deref(),
+    );
 
     // ── Simulation config (only when `sim` feature is enabled) ───────
     #[cfg(feature = "sim")]
-    let scenario = {
+    let mut scenario = {
         let s = env::var("SIM_SCENARIO").unwrap_or_else(|_| "drying".to_string());
         sim::Scenario::from_str_lossy(&s)
     };
+    // Calibration hints from the first configured channel that has them.
     #[cfg(feature = "sim")]
-    let sim_raw_dry: f64 = env::var("SIM_RAW_DRY")
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(26000.0);
+    let file_calibration = file_cfg
+        .channels
+        .iter()
+        .find_map(|ch| Some((ch.raw_dry? as f64, ch.raw_wet? as f64)));
     #[cfg(feature = "sim")]
-    let sim_raw_wet: f64 = env::var("SIM_RAW_WET")
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(12000.0);
+    let sim_raw_dry: f64 = config::env_or("SIM_RAW_DRY", file_calibration.map(|c| c.0), 26000.0);
+    #[cfg(feature = "sim")]
+    let sim_raw_wet: f64 = config::env_or("SIM_RAW_WET", file_calibration.map(|c| c.1), 12000.0);
     #[cfg(feature = "sim")]
     let sim_diurnal_period_s: f64 = env::var("SIM_DIURNAL_PERIOD_S")
         .ok()
@@ -96,11 +338,29 @@ async fn main() -> anyhow::Result<()> {
         .unwrap_or(600.0);
     #[cfg(feature = "sim")]
     let sim_zone_id: Option<String> = env::var("SIM_ZONE_ID").ok();
+    // Fixed seed for a reproducible simulation (see `sim`).
+    #[cfg(feature = "sim")]
+    let sim_seed: Option<u64> =
+        match env::var("SIM_SEED") {
+            Ok(raw) if !raw.trim().is_empty() => Some(raw.trim().parse().map_err(|_| {
+                anyhow::anyhow!("SIM_SEED must be an unsigned integer, got {raw:?}")
+            })?),
+            _ => None,
+        };
+    #[cfg(feature = "sim")]
+    let sim_rng = || sim_seed.map_or_else(fastrand::Rng::new, fastrand::Rng::with_seed);
 
+    // Two sensor channels (s1, s2) unless the config file or the hub
+    // provides a channel map.
+    #[cfg(feature = "sim")]
+    let mut env_sim_sensor_ids = sim_channel_ids(&file_cfg.channels);
+    #[cfg(feature = "sim")]
+    let mut sim_sensor_ids = env_sim_sensor_ids.clone();
     #[cfg(feature = "sim")]
     let mut sim = sim::SoilMoistureSim::new(
+        sim_rng(),
         scenario,
-        2, // two sensor channels: s1, s2
+        sim_sensor_ids.len(),
         sim_raw_dry,
         sim_raw_wet,
         sim_diurnal_period_s,
@@ -112,9 +372,21 @@ async fn main() -> anyhow::Result<()> {
         raw_wet = sim_raw_wet,
         diurnal_period_s = sim_diurnal_period_s,
         zone_id = ?sim_zone_id,
+        seed = ?sim_seed,
         "simulation initialised"
     );
 
+    #[cfg(feature = "sim")]
+    if let Mode::Dump(samples) = mode {
+        return dump_readings(
+            &mut sim,
+            &sim_sensor_ids,
+            samples,
+            env_sample_every_s,
+            std::io::stdout().lock(),
+        );
+    }
+
     // Watering flag shared between MQTT event loop and sampling loop.
     // The event loop sets it; the sampling loop reads it.  AtomicBool is
     // sufficient — no mutex needed.
@@ -126,20 +398,123 @@ async fn main() -> anyhow::Result<()> {
     let adc_addr: u16 = env::var("ADS1115_ADDR")
         .ok()
         .and_then(|s| u16::from_str_radix(s.trim_start_matches("0x"), 16).ok())
+        .or(file_cfg.adc.address)
         .unwrap_or(0x48);
 
     #[cfg(feature = "adc")]
-    let adc_channels = {
-        let raw = env::var("SENSOR_CHANNELS").unwrap_or_default();
-        adc::parse_channels(&raw)?
+    let adc_channels = local_adc_channels(&file_cfg.channels)?;
+
+    #[cfg(feature = "adc")]
+    let adc_oversample = match env::var("ADC_OVERSAMPLE") {
+        Ok(raw) if !raw.trim().is_empty() => adc::parse_oversample(&raw)?,
+        _ => file_cfg.adc.oversample.unwrap_or(adc::DEFAULT_OVERSAMPLE),
+    };
+
+    #[cfg(feature = "adc")]
+    let adc_conversion = adc::Conversion::new(
+        match env::var("ADC_GAIN") {
+            Ok(raw) if !raw.trim().is_empty() => adc::parse_gain(&raw)?,
+            _ => file_cfg.adc.gain.unwrap_or(adc::DEFAULT_GAIN_V),
+        },
+        match env::var("ADC_DATA_RATE") {
+            Ok(raw) if !raw.trim().is_empty() => adc::parse_data_rate(&raw)?,
+            _ => file_cfg.adc.data_rate.unwrap_or(adc::DEFAULT_DATA_RATE),
+        },
+    )?;
+
+    #[cfg(feature = "adc")]
+    let adc_report_mv = match env::var("ADC_REPORT_MV") {
+        Ok(raw) if !raw.trim().is_empty() => {
+            raw.trim() == "1" || raw.trim().eq_ignore_ascii_case("true")
+        }
+        _ => file_cfg.adc.report_mv.unwrap_or(false),
     };
 
+    // ── Climate sensor (only when `climate` feature is enabled) ──────
+    #[cfg(feature = "climate")]
+    let mut climate_sensor = climate::from_env(&file_cfg.climate)?;
+    #[cfg(not(feature = "climate"))]
+    if env::var("CLIMATE_SENSOR").is_ok_and(|s| !s.trim().is_empty())
+        || file_cfg.climate.sensor.is_some()
+    {
+        tracing::warn!("climate sensor configured but this build lacks the `climate` feature");
+    }
+
+    // ── Light sensor (only when `light` feature is enabled) ──────────
+    #[cfg(feature = "light")]
+    let mut light_sensor = light::from_env(&file_cfg.light)?;
+    #[cfg(not(feature = "light"))]
+    if env::var("LIGHT_SENSOR").is_ok_and(|s| !s.trim().is_empty())
+        || file_cfg.light.sensor.is_some()
+    {
+        tracing::warn!("light sensor configured but this build lacks the `light` feature");
+    }
+
+    #[cfg(feature = "adc")]
+    let mut env_adc_channels = adc_channels.clone();
     #[cfg(feature = "adc")]
-    let mut adc_device = adc::Ads1115::new(adc_addr, adc_channels)?;
+    let mut adc_device = adc::Ads1115::new(
+        adc_addr,
+        adc_channels,
+        adc_oversample,
+        adc_conversion,
+        adc_report_mv,
+    )?;
 
     // ── MQTT setup ───────────────────────────────────────────────────
+    // MQTT authentication — required for production (see deploy/mosquitto-production.conf).
+    let credentials = match (env::var("MQTT_USER"), env::var("MQTT_PASS")) {
+        (Ok(user), Ok(pass)) => Some((user, pass)),
+        _ => file_cfg.mqtt.user.clone().zip(file_cfg.mqtt.pass.clone()),
+    };
+
+    #[cfg(feature = "sim")]
+    if let Mode::Farm(count) = mode {
+        let list = |var: &str| farm::split_list(&env::var(var).unwrap_or_default());
+        let scenarios: Vec<sim::Scenario> = list("SIM_SCENARIO")
+            .iter()
+            .map(|s| sim::Scenario::from_str_lossy(s))
+            .collect();
+        let nodes = farm::plan(&node_id, count, &list("SIM_ZONE_ID"), &scenarios, sim_seed);
+        return farm::run(
+            nodes,
+            farm::FarmOptions {
+                broker,
+                port,
+                credentials,
+                topic_prefix,
+                payload_format,
+                sample_every_s: env_sample_every_s,
+                raw_dry: sim_raw_dry,
+                raw_wet: sim_raw_wet,
+                diurnal_period_s: sim_diurnal_period_s,
+            },
+        )
+        .await;
+    }
+
+    // ── OTA updates (see `updater`) ──────────────────────────────────
+    let updater = Updater {
+        key: env::var("OTA_PUBLIC_KEY")
+            .ok()
+            .filter(|k| !k.trim().is_empty())
+            .or(file_cfg.ota.public_key.clone())
+            .map(|raw| updater::parse_public_key(&raw))
+            .transpose()?,
+        install_path: match env::var("OTA_INSTALL_PATH")
+            .ok()
+            .or(file_cfg.ota.install_path.clone())
+        {
+            Some(path) => path.into(),
+            None => env::current_exe()?,
+        },
+    };
+    if updater.key.is_none() {
+        tracing::info!("OTA_PUBLIC_KEY not set — over-the-air updates are refused");
+    }
+
     let client_id = format!("irrigation-node-{node_id}");
-    let status_topic = format!("status/node/{node_id}");
+    let status_topic = prefixed(&topic_prefix, &format!("status/node/{node_id}"));
 
     let mut mqttoptions = MqttOptions::new(client_id, broker, port);
     mqttoptions.set_keep_alive(Duration::from_secs(30));
@@ -154,8 +529,7 @@ async fn main() -> anyhow::Result<()> {
         true,
     ));
 
-    // MQTT authentication — required for production (see deploy/mosquitto-production.conf).
-    if let (Ok(user), Ok(pass)) = (env::var("MQTT_USER"), env::var("MQTT_PASS")) {
+    if let Some((user, pass)) = credentials {
         mqttoptions.set_credentials(user, pass);
         tracing::info!("mqtt: using password authentication");
     } else {
@@ -168,43 +542,214 @@ async fn main() -> anyhow::Result<()> {
     let status_client = client.clone();
     let el_status_topic = status_topic.clone();
 
-    // Build the valve subscription topic if SIM_ZONE_ID is set.
+    // Connection state for the sampling loop: readings are buffered while
+    // disconnected and replayed as soon as the event loop signals ConnAck.
+    let connected = Arc::new(AtomicBool::new(false));
+    let reconnected = Arc::new(Notify::new());
+    let el_connected = connected.clone();
+    let el_reconnected = reconnected.clone();
+
+    // Hub-pushed settings: the event loop parses them, the sampling loop
+    // applies them.  Only actual changes wake the receiver, so the retained
+    // copy re-delivered on every reconnect is a no-op.
+    let el_settings_topic = settings::topic(&topic_prefix, &node_id);
+    let (settings_tx, mut settings_rx) = watch::channel::<Option<NodeSettings>>(None);
+
+    // Remote diagnostics (see `diag`): `send-logs` is answered from the
+    // event loop; `restart` wakes the sampling loop to shut down cleanly.
+    let el_cmd_base = diag::command_base(&topic_prefix, &node_id);
+    let el_logs_topic = diag::logs_topic(&topic_prefix, &node_id);
+    let el_pong_topic = diag::pong_topic(&topic_prefix, &node_id);
+    let started = Instant::now();
+    let (vitals_tx, vitals_rx) = watch::channel(diag::Vitals {
+        sample_interval_sec: env_sample_every_s,
+        ..Default::default()
+    });
+    let restart = Arc::new(Notify::new());
+    let el_restart = restart.clone();
+
+    // Update announcements: the event loop parses them, the updater task
+    // installs them.  Like settings, only a changed announcement wakes it.
+    let el_ota_topic = updater::announce_topic(&topic_prefix, &node_id);
+    let ota_status_topic = updater::status_topic(&topic_prefix, &node_id);
+    let el_ota_status_topic = ota_status_topic.clone();
+    let (ota_tx, mut ota_rx) = watch::channel::<Option<Announcement>>(None);
+
+    // Build the valve subscription topic if SIM_ZONE_ID is set.  The hub
+    // mirrors its (mock) valve writes there when run with SIM_HIL=1.
     #[cfg(feature = "sim")]
-    let valve_topic: Option<String> = sim_zone_id.as_ref().map(|z| format!("valve/{z}/set"));
+    let valve_topic: Option<String> = sim_zone_id
+        .as_ref()
+        .map(|z| prefixed(&topic_prefix, &format!("sim/valve/{z}")));
 
     #[cfg(feature = "sim")]
     let el_valve_topic = valve_topic.clone();
     #[cfg(feature = "sim")]
     let el_watering_flag = watering_flag.clone();
 
+    // Scenario switches at runtime: the event loop parses them, the
+    // sampling loop applies them to the simulator.
+    #[cfg(feature = "sim")]
+    let el_scenario_topic = sim::scenario_topic(&topic_prefix, &node_id);
+    #[cfg(feature = "sim")]
+    let (scenario_tx, mut scenario_rx) = watch::channel(scenario);
+
     tokio::spawn(async move {
         loop {
             match eventloop.poll().await {
                 Ok(Event::Incoming(Packet::ConnAck(_))) => {
                     tracing::info!("node connected to mqtt");
+                    el_connected.store(true, Ordering::Relaxed);
+                    el_reconnected.notify_one();
 
-                    // Announce online (retained) — mirrors the LWT "offline".
+                    // Announce online with our build (retained) — mirrors the
+                    // LWT "offline".
                     if let Err(e) = status_client
-                        .publish(&el_status_topic, QoS::AtLeastOnce, true, b"online".to_vec())
+                        .publish(&el_status_topic, QoS::AtLeastOnce, true, online_payload())
                         .await
                     {
                         tracing::error!("failed to publish online status: {e}");
                     }
 
-                    // Subscribe to valve commands for watering response.
+                    if let Err(e) = status_client
+                        .subscribe(&el_settings_topic, QoS::AtLeastOnce)
+                        .await
+                    {
+                        tracing::error!("failed to subscribe to {el_settings_topic}: {e}");
+                    }
+                    if let Err(e) = status_client
+                        .subscribe(&el_ota_topic, QoS::AtLeastOnce)
+                        .await
+                    {
+                        tracing::error!("failed to subscribe to {el_ota_topic}: {e}");
+                    }
+                    publish_ota_report(
+                        &status_client,
+                        &el_ota_status_topic,
+                        &Report::new(updater::State::Running, updater::VERSION),
+                    )
+                    .await;
+                    let cmd_filter = format!("{el_cmd_base}+");
+                    if let Err(e) = status_client.subscribe(&cmd_filter, QoS::AtLeastOnce).await {
+                        tracing::error!("failed to subscribe to {cmd_filter}: {e}");
+                    }
+
+                    #[cfg(feature = "sim")]
+                    if let Err(e) = status_client
+                        .subscribe(&el_scenario_topic, QoS::AtLeastOnce)
+                        .await
+                    {
+                        tracing::error!("failed to subscribe to {el_scenario_topic}: {e}");
+                    }
+
+                    // Subscribe to valve state for watering response.
                     #[cfg(feature = "sim")]
                     if let Some(ref vt) = el_valve_topic {
                         if let Err(e) = status_client.subscribe(vt, QoS::AtLeastOnce).await {
                             tracing::error!("failed to subscribe to {vt}: {e}");
                         } else {
-                            tracing::info!(topic = %vt, "subscribed to valve commands");
+                            tracing::info!(topic = %vt, "subscribed to simulated valve state");
                         }
                     }
                 }
 
-                // Handle incoming valve commands (sim only).
+                Ok(Event::Incoming(Packet::Publish(pub_msg)))
+                    if pub_msg.topic == el_settings_topic =>
+                {
+                    match settings::parse(&pub_msg.payload) {
+                        Ok(new) => {
+                            settings_tx.send_if_modified(|cur| {
+                                let changed = *cur != new;
+                                *cur = new;
+                                changed
+                            });
+                        }
+                        Err(e) => tracing::warn!("ignoring invalid hub settings: {e}"),
+                    }
+                }
+
+                Ok(Event::Incoming(Packet::Publish(pub_msg))) if pub_msg.topic == el_ota_topic => {
+                    match updater::parse_announcement(&pub_msg.payload) {
+                        Ok(new) => {
+                            ota_tx.send_if_modified(|cur| {
+                                let changed = *cur != new;
+                                *cur = new;
+                                changed
+                            });
+                        }
+                        Err(e) => tracing::warn!("ignoring invalid update announcement: {e}"),
+                    }
+                }
+
+                Ok(Event::Incoming(Packet::Publish(pub_msg)))
+                    if pub_msg.topic.starts_with(&el_cmd_base) =>
+                {
+                    if pub_msg.retain {
+                        tracing::warn!(topic = %pub_msg.topic, "ignoring retained command");
+                        continue;
+                    }
+                    match diag::parse_command(&el_cmd_base, &pub_msg.topic, &pub_msg.payload) {
+                        Some(Ok(diag::Command::Restart)) => {
+                            tracing::warn!("restart requested by the hub");
+                            el_restart.notify_one();
+                        }
+                        Some(Ok(diag::Command::SendLogs { lines })) => {
+                            let msg = diag::LogsMsg {
+                                ts: now_unix(),
+                                lines: log_ring.tail(lines),
+                            };
+                            let payload =
+                                serde_json::to_vec(&msg).expect("logs serialization failed");
+                            if let Err(e) = status_client
+                                .publish(&el_logs_topic, QoS::AtLeastOnce, false, payload)
+                                .await
+                            {
+                                tracing::error!("failed to publish logs: {e}");
+                            } else {
+                                tracing::info!(lines = msg.lines.len(), "sent logs to the hub");
+                            }
+                        }
+                        Some(Ok(diag::Command::Ping { sent_ms })) => {
+                            let msg = diag::PongMsg::new(
+                                now_unix(),
+                                sent_ms,
+                                started.elapsed().as_secs(),
+                                *vitals_rx.borrow(),
+                            );
+                            let payload =
+                                serde_json::to_vec(&msg).expect("pong serialization failed");
+                            if let Err(e) = status_client
+                                .publish(&el_pong_topic, QoS::AtLeastOnce, false, payload)
+                                .await
+                            {
+                                tracing::error!("failed to answer ping: {e}");
+                            } else {
+                                tracing::debug!("answered ping from the hub");
+                            }
+                        }
+                        Some(Err(e)) => {
+                            tracing::warn!(topic = %pub_msg.topic, "ignoring invalid command: {e}")
+                        }
+                        None => {}
+                    }
+                }
+
+                // Handle simulated valve state from the hub (sim only).
                 #[cfg(feature = "sim")]
                 Ok(Event::Incoming(Packet::Publish(pub_msg))) => {
+                    if pub_msg.topic == el_scenario_topic {
+                        let payload = String::from_utf8_lossy(&pub_msg.payload);
+                        match sim::Scenario::parse(&payload) {
+                            Some(s) => {
+                                tracing::info!(scenario = %s, "sim: switching scenario");
+                                scenario_tx.send_replace(s);
+                            }
+                            None => tracing::warn!(
+                                payload = %payload.trim(),
+                                "sim: ignoring unknown scenario"
+                            ),
+                        }
+                    }
                     if let Some(ref vt) = el_valve_topic {
                         if pub_msg.topic == *vt {
                             let payload =
@@ -234,17 +779,153 @@ async fn main() -> anyhow::Result<()> {
                 Ok(_) => {}
                 Err(e) => {
                     tracing::error!("mqtt error: {e} — retrying");
+                    el_connected.store(false, Ordering::Relaxed);
                     sleep(Duration::from_secs(2)).await;
                 }
             }
         }
     });
 
+    // ── OTA updater task ─────────────────────────────────────────────
+    // Downloads and installs on a blocking thread, then restarts the node
+    // through the sampling loop's restart path.
+    {
+        let client = client.clone();
+        let restart = restart.clone();
+        tokio::spawn(async move {
+            while ota_rx.changed().await.is_ok() {
+                let Some(ann) = ota_rx.borrow_and_update().clone() else {
+                    continue;
+                };
+                if ann.version == updater::VERSION {
+                    tracing::info!(version = %ann.version, "update announced for the running version — ignoring");
+                    continue;
+                }
+                tracing::info!(version = %ann.version, url = %ann.url, "installing update");
+                publish_ota_report(
+                    &client,
+                    &ota_status_topic,
+                    &Report::new(updater::State::Downloading, &ann.version),
+                )
+                .await;
+                let job = updater.clone();
+                let job_ann = ann.clone();
+                let result = tokio::task::spawn_blocking(move || job.apply(&job_ann))
+                    .await
+                    .unwrap_or_else(|e| Err(anyhow::anyhow!("updater panicked: {e}")));
+                match result {
+                    Ok(()) => {
+                        tracing::warn!(version = %ann.version, "update installed — restarting");
+                        publish_ota_report(
+                            &client,
+                            &ota_status_topic,
+                            &Report::new(updater::State::Installed, &ann.version),
+                        )
+                        .await;
+                        restart.notify_one();
+                    }
+                    Err(e) => {
+                        tracing::error!(version = %ann.version, "update failed: {e:#}");
+                        publish_ota_report(
+                            &client,
+                            &ota_status_topic,
+                            &Report::failed(&ann.version, &e),
+                        )
+                        .await;
+                    }
+                }
+            }
+        });
+    }
+
     // ── Sampling loop ────────────────────────────────────────────────
-    let topic = format!("tele/{node_id}/reading");
-    tracing::info!(topic = %topic, "publishing sensor readings");
+    let topic = payload_format.telemetry_topic(&topic_prefix, &node_id);
+    tracing::info!(
+        topic = %topic,
+        format = ?payload_format,
+        offline_buffer = buffer_capacity,
+        "publishing sensor readings"
+    );
+    let mut backlog: OfflineBuffer<ReadingMsg> = OfflineBuffer::new(buffer_capacity);
+    let mut sample_every_s = env_sample_every_s;
+    // SIGHUP (`systemctl reload`) rereads the config file's channel map, so
+    // a probe can be remapped or disabled without a restart.
+    let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
 
     loop {
+        // Apply hub-pushed settings; anything unset keeps the env value.
+        if settings_rx.has_changed().unwrap_or(false) {
+            let pushed = settings_rx.borrow_and_update().clone();
+            let channels = pushed
+                .as_ref()
+                .map(|s| s.channels.as_slice())
+                .filter(|c| !c.is_empty());
+
+            sample_every_s = pushed
+                .as_ref()
+                .and_then(|s| s.sample_interval_sec)
+                .unwrap_or(env_sample_every_s);
+
+            #[cfg(feature = "sim")]
+            {
+                // Rebuild with the pushed sensors, centred on the hub's
+                // calibration so readings land in the expected range.
+                let (ids, raw_dry, raw_wet) = match channels {
+                    Some(chs) => {
+                        let chs: Vec<_> = chs.iter().filter(|c| c.enabled).collect();
+                        let n = chs.len().max(1) as f64;
+                        (
+                            chs.iter().map(|c| c.sensor_id.clone()).collect(),
+                            chs.iter().map(|c| c.raw_dry as f64).sum::<f64>() / n,
+                            chs.iter().map(|c| c.raw_wet as f64).sum::<f64>() / n,
+                        )
+                    }
+                    None => (env_sim_sensor_ids.clone(), sim_raw_dry, sim_raw_wet),
+                };
+                sim_sensor_ids = ids;
+                sim = sim::SoilMoistureSim::new(
+                    sim_rng(),
+                    scenario,
+                    sim_sensor_ids.len(),
+                    raw_dry,
+                    raw_wet,
+                    sim_diurnal_period_s,
+                );
+            }
+
+            #[cfg(feature = "adc")]
+            {
+                let map = match channels {
+                    Some(chs) => chs
+                        .iter()
+                        .filter(|c| c.enabled)
+                        .map(|c| adc::ChannelMap {
+                            channel: c.channel,
+                            sensor_id: c.sensor_id.clone(),
+                        })
+                        .collect(),
+                    None => env_adc_channels.clone(),
+                };
+                if let Err(e) = adc_device.set_channels(map) {
+                    tracing::warn!("keeping current channel map: {e}");
+                }
+            }
+
+            tracing::info!(
+                sample_every_s,
+                channels = channels.map_or(0, |c| c.len()),
+                disabled = channels.map_or(0, |c| c.iter().filter(|c| !c.enabled).count()),
+                from_hub = pushed.is_some(),
+                "applied node settings"
+            );
+        }
+
+        #[cfg(feature = "sim")]
+        if scenario_rx.has_changed().unwrap_or(false) {
+            scenario = *scenario_rx.borrow_and_update();
+            sim.set_scenario(scenario);
+        }
+
         // Produce readings from the active sensor backend.
         #[cfg(feature = "sim")]
         let readings: Vec<Reading> = {
@@ -252,10 +933,13 @@ async fn main() -> anyhow::Result<()> {
             sim.set_watering(watering);
 
             let mut out = Vec::with_capacity(sim.sensor_count());
-            for i in 0..sim.sensor_count() {
+            for (i, sensor_id) in sim_sensor_ids.iter().enumerate() {
                 out.push(Reading {
-                    sensor_id: format!("s{}", i + 1),
+                    sensor_id: sensor_id.clone(),
                     raw: sim.sample(i),
+                    raw_stddev: None,
+                    mv: None,
+                    ts: None,
                 });
             }
             out
@@ -264,28 +948,176 @@ async fn main() -> anyhow::Result<()> {
         #[cfg(feature = "adc")]
         let readings: Vec<Reading> = adc_device.read_all();
 
-        // Publish if we got at least one reading from whichever backend.
-        if !readings.is_empty() {
-            let msg = ReadingMsg {
-                ts: now_unix(),
-                readings,
-            };
+        #[cfg(feature = "climate")]
+        let (air_temp_c, humidity_pct) = climate_sensor
+            .as_mut()
+            .map_or((None, None), climate::Sensor::sample);
+        #[cfg(not(feature = "climate"))]
+        let (air_temp_c, humidity_pct) = (None, None);
 
-            let payload = serde_json::to_vec(&msg).expect("reading serialization failed");
+        #[cfg(feature = "light")]
+        let lux = light_sensor.as_mut().and_then(light::Sensor::sample);
+        #[cfg(not(feature = "light"))]
+        let lux = None;
 
-            if let Err(e) = client
-                .publish(&topic, QoS::AtLeastOnce, false, payload)
-                .await
-            {
-                tracing::error!("publish error: {e}");
-            } else {
-                tracing::info!(ts = msg.ts, "published readings");
+        let next_sample = Instant::now() + Duration::from_secs(sample_every_s);
+
+        // Queue, then send everything queued if connected.  While offline
+        // readings accumulate (with their original timestamps) and are
+        // replayed on reconnect; the hub ignores any it already has.
+        if !readings.is_empty() || air_temp_c.is_some() || humidity_pct.is_some() || lux.is_some() {
+            if backlog.push(ReadingMsg {
+                ts: now_unix(),
+                readings,
+                air_temp_c,
+                humidity_pct,
+                lux,
+            }) {
+                tracing::warn!(
+                    dropped = backlog.dropped(),
+                    capacity = buffer_capacity,
+                    "offline buffer full — dropped oldest reading"
+                );
             }
         } else {
             tracing::warn!("no readings produced — skipping publish");
         }
 
-        sleep(Duration::from_secs(sample_every_s)).await;
+        if connected.load(Ordering::Relaxed) {
+            flush_backlog(&client, &topic, payload_format, &mut backlog).await;
+        } else if backlog.len() > 0 {
+            tracing::info!(queued = backlog.len(), "mqtt offline — buffering readings");
+        }
+        vitals_tx.send_replace(diag::Vitals {
+            sample_interval_sec: sample_every_s,
+            last_sample_ts: Some(now_unix()),
+            buffered: backlog.len(),
+        });
+
+        // Sleep until the next sample, replaying the backlog if the
+        // connection comes back in the meantime.  New hub settings cut the
+        // wait short so they take effect (and are sampled) right away.
+        loop {
+            tokio::select! {
+                _ = sleep_until(next_sample) => break,
+                _ = reconnected.notified() => {
+                    flush_backlog(&client, &topic, payload_format, &mut backlog).await;
+                }
+                Ok(()) = settings_rx.changed() => {
+                    // `changed` marks the value seen; flag it again so the
+                    // top of the loop applies it.
+                    settings_rx.mark_changed();
+                    break;
+                }
+                _ = hangup.recv() => {
+                    // Reread the config file's channel map and reapply it
+                    // (hub-pushed channels still take precedence).
+                    match config::load_from_env() {
+                        Ok(cfg) => {
+                            #[cfg(feature = "sim")]
+                            {
+                                env_sim_sensor_ids = sim_channel_ids(&cfg.channels);
+                            }
+                            #[cfg(feature = "adc")]
+                            match local_adc_channels(&cfg.channels) {
+                                Ok(map) => env_adc_channels = map,
+                                Err(e) => tracing::warn!("SIGHUP: keeping channel map: {e:#}"),
+                            }
+                            tracing::info!("SIGHUP: reloaded channel map");
+                            settings_rx.mark_changed();
+                            break;
+                        }
+                        Err(e) => tracing::warn!("SIGHUP: keeping channel map: {e:#}"),
+                    }
+                }
+                _ = restart.notified() => {
+                    // Announce offline ourselves (the LWT only fires on an
+                    // unclean disconnect) and exit; systemd restarts us.
+                    let _ = client
+                        .publish(&status_topic, QoS::AtLeastOnce, true, b"offline".to_vec())
+                        .await;
+                    let _ = client.disconnect().await;
+                    sleep(Duration::from_secs(1)).await;
+                    tracing::info!("exiting for restart");
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Publish update progress (retained, so the hub sees the latest after a
+/// restart).
+async fn publish_ota_report(client: &AsyncClient, topic: &str, report: &Report<'_>) {
+    if let Err(e) = client
+        .publish(topic, QoS::AtLeastOnce, true, report.to_json())
+        .await
+    {
+        tracing::error!("failed to publish update status: {e}");
+    }
+}
+
+/// Publish queued readings oldest-first.  Stops at the first failure and
+/// keeps the rest for the next attempt.
+async fn flush_backlog(
+    client: &AsyncClient,
+    topic: &str,
+    format: PayloadFormat,
+    backlog: &mut OfflineBuffer<ReadingMsg>,
+) {
+    let replaying = backlog.len() > 1;
+    let mut sent = 0;
+    while let Some(mut msg) = backlog.pop() {
+        if replaying {
+            batch(&mut msg, backlog);
+        }
+        let payload = format.encode(&msg);
+        if let Err(e) = client
+            .publish(topic, QoS::AtLeastOnce, false, payload)
+            .await
+        {
+            tracing::error!("publish error: {e}");
+            backlog.unpop(msg);
+            break;
+        }
+        tracing::info!(ts = msg.ts, "published readings");
+        sent += 1;
+    }
+    if replaying && sent > 0 {
+        tracing::info!(
+            sent,
+            remaining = backlog.len(),
+            "replayed buffered readings"
+        );
+    }
+}
+
+/// Most readings batched into one replayed message (the hub's limit).
+const MAX_BATCH_READINGS: usize = 32;
+
+/// Fold the next buffered messages into `msg` for replay, each reading
+/// keeping its own `ts`, up to [`MAX_BATCH_READINGS`].  Messages with
+/// climate or light values are sent on their own: those fields belong to
+/// the message, not a reading.
+fn batch(msg: &mut ReadingMsg, backlog: &mut OfflineBuffer<ReadingMsg>) {
+    let standalone =
+        |m: &ReadingMsg| m.air_temp_c.is_some() || m.humidity_pct.is_some() || m.lux.is_some();
+    if standalone(msg) {
+        return;
+    }
+    while let Some(next) = backlog.pop() {
+        if standalone(&next) || msg.readings.len() + next.readings.len() > MAX_BATCH_READINGS {
+            backlog.unpop(next);
+            break;
+        }
+        for r in &mut msg.readings {
+            r.ts.get_or_insert(msg.ts);
+        }
+        msg.readings.extend(next.readings.into_iter().map(|mut r| {
+            r.ts.get_or_insert(next.ts);
+            r
+        }));
+        msg.ts = msg.ts.max(next.ts);
     }
 }
 
@@ -297,6 +1129,76 @@ async fn main() -> anyhow::Result<()> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn topic_prefix_parsing() {
+        assert_eq!(parse_topic_prefix("").unwrap(), "");
+        assert_eq!(parse_topic_prefix("/garden/").unwrap(), "garden");
+        assert!(parse_topic_prefix("garden/#").is_err());
+        assert_eq!(prefixed("", "tele/n/reading"), "tele/n/reading");
+        assert_eq!(
+            prefixed("garden", "tele/n/reading"),
+            "garden/tele/n/reading"
+        );
+    }
+
+    #[test]
+    fn args_parsing() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(parse_args(&[]).unwrap(), Mode::Node);
+        assert!(parse_args(&args(&["--verbose"])).is_err());
+        #[cfg(feature = "sim")]
+        {
+            assert_eq!(parse_args(&args(&["--dump"])).unwrap(), Mode::Dump(288));
+            assert_eq!(
+                parse_args(&args(&["--dump", "10"])).unwrap(),
+                Mode::Dump(10)
+            );
+            assert!(parse_args(&args(&["--dump", "ten"])).is_err());
+            assert_eq!(
+                parse_args(&args(&["--farm", "25"])).unwrap(),
+                Mode::Farm(25)
+            );
+            assert!(parse_args(&args(&["--farm", "0"])).is_err());
+            assert!(parse_args(&args(&["--farm"])).is_err());
+            assert!(Mode::Dump(1).owns_stdout());
+        }
+        assert!(!Mode::Node.owns_stdout());
+    }
+
+    #[cfg(feature = "sim")]
+    #[test]
+    fn dump_is_reproducible_with_a_seed() {
+        let ids = vec!["s1".to_string(), "s2".to_string()];
+        let dump = || {
+            let mut sim = sim::SoilMoistureSim::new(
+                fastrand::Rng::with_seed(42),
+                sim::Scenario::Drying,
+                ids.len(),
+                26000.0,
+                12000.0,
+                600.0,
+            );
+            let mut out = Vec::new();
+            dump_readings(&mut sim, &ids, 3, 300, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let text = dump();
+        assert_eq!(text, dump());
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        let last: serde_json::Value = serde_json::from_str(lines[2]).unwrap();
+        assert_eq!(last["ts"], 600);
+        assert_eq!(last["readings"][1]["sensor_id"], "s2");
+    }
+
+    #[test]
+    fn online_payload_carries_the_build() {
+        let json: serde_json::Value = serde_json::from_slice(&online_payload()).unwrap();
+        assert_eq!(json["status"], "online");
+        assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+        assert!(!json["git"].as_str().unwrap().is_empty());
+    }
+
     #[test]
     fn now_unix_returns_positive() {
         assert!(now_unix() > 0);
@@ -318,18 +1220,74 @@ mod tests {
                 Reading {
                     sensor_id: "s1".to_string(),
                     raw: 20000,
+                    raw_stddev: None,
+                    mv: None,
+                    ts: None,
                 },
                 Reading {
                     sensor_id: "s2".to_string(),
                     raw: 21000,
+                    raw_stddev: Some(3.5),
+                    mv: Some(2625.0),
+                    ts: None,
                 },
             ],
+            air_temp_c: None,
+            humidity_pct: None,
+            lux: None,
         };
         let json = serde_json::to_value(&msg).unwrap();
 
         assert_eq!(json["ts"], 1_700_000_000);
         assert!(json["readings"].is_array());
         assert_eq!(json["readings"].as_array().unwrap().len(), 2);
+        assert!(json["readings"][0].get("raw_stddev").is_none());
+        assert_eq!(json["readings"][1]["raw_stddev"], 3.5);
+        assert!(json["readings"][0].get("mv").is_none());
+        assert_eq!(json["readings"][1]["mv"], 2625.0);
+        assert!(json.get("air_temp_c").is_none());
+        assert!(json.get("humidity_pct").is_none());
+        assert!(json.get("lux").is_none());
+    }
+
+    #[test]
+    fn replay_batches_buffered_messages() {
+        let msg = |ts: i64, n: usize, lux: Option<f32>| ReadingMsg {
+            ts,
+            readings: (0..n)
+                .map(|i| Reading {
+                    sensor_id: format!("s{i}"),
+                    raw: 20000,
+                    raw_stddev: None,
+                    mv: None,
+                    ts: None,
+                })
+                .collect(),
+            air_temp_c: None,
+            humidity_pct: None,
+            lux,
+        };
+        let mut backlog = OfflineBuffer::new(10);
+        backlog.push(msg(200, 2, None));
+        backlog.push(msg(500, 20, Some(1200.0)));
+        backlog.push(msg(800, 20, None));
+        backlog.push(msg(900, 20, None));
+
+        let mut first = msg(100, 2, None);
+        batch(&mut first, &mut backlog);
+        assert_eq!(first.ts, 200);
+        let ts: Vec<Option<i64>> = first.readings.iter().map(|r| r.ts).collect();
+        assert_eq!(ts, vec![Some(100), Some(100), Some(200), Some(200)]);
+        // Stopped at the message with a light value, which goes alone.
+        assert_eq!(backlog.len(), 3);
+        let mut lit = backlog.pop().unwrap();
+        batch(&mut lit, &mut backlog);
+        assert_eq!((lit.ts, lit.readings.len()), (500, 20));
+        // 20 + 20 readings is over the limit: not merged.
+        let mut next = backlog.pop().unwrap();
+        batch(&mut next, &mut backlog);
+        assert_eq!((next.readings.len(), backlog.len()), (20, 1));
+        assert!(next.readings[0].ts.is_none());
     }
 
     #[test]
@@ -337,6 +1295,9 @@ mod tests {
         let r = Reading {
             sensor_id: "adc0".to_string(),
             raw: 12345,
+            raw_stddev: None,
+            mv: None,
+            ts: None,
         };
         let json = serde_json::to_value(&r).unwrap();
 
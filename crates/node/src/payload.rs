@@ -0,0 +1,89 @@
+//! Telemetry payload encoding (`PAYLOAD_FORMAT` / `mqtt.payload_format`).
+//!
+//! JSON goes to `tele/<node_id>/reading`; CBOR, for LoRa bridges and other
+//! links with tight payload budgets, goes to `tele/<node_id>/reading/cbor`.
+//! The hub decodes either, so the topic is all it needs to tell them apart.
+
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadFormat {
+    #[default]
+    Json,
+    Cbor,
+}
+
+impl FromStr for PayloadFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "cbor" => Ok(Self::Cbor),
+            other => anyhow::bail!("unknown payload format '{other}' (expected json or cbor)"),
+        }
+    }
+}
+
+impl PayloadFormat {
+    /// Topic this node's readings are published to.
+    pub fn telemetry_topic(self, prefix: &str, node_id: &str) -> String {
+        let path = match self {
+            Self::Json => format!("tele/{node_id}/reading"),
+            Self::Cbor => format!("tele/{node_id}/reading/cbor"),
+        };
+        crate::prefixed(prefix, &path)
+    }
+
+    pub fn encode<T: Serialize>(self, msg: &T) -> Vec<u8> {
+        match self {
+            Self::Json => serde_json::to_vec(msg).expect("reading serialization failed"),
+            Self::Cbor => {
+                let mut buf = Vec::new();
+                ciborium::into_writer(msg, &mut buf).expect("reading serialization failed");
+                buf
+            }
+        }
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn topics_by_format() {
+        assert_eq!(
+            PayloadFormat::Json.telemetry_topic("garden", "node-a"),
+            "garden/tele/node-a/reading"
+        );
+        assert_eq!(
+            PayloadFormat::Cbor.telemetry_topic("", "node-a"),
+            "tele/node-a/reading/cbor"
+        );
+        assert_eq!(
+            "CBOR".parse::<PayloadFormat>().unwrap(),
+            PayloadFormat::Cbor
+        );
+        assert!("msgpack".parse::<PayloadFormat>().is_err());
+    }
+
+    #[test]
+    fn cbor_is_smaller_and_round_trips() {
+        let msg = serde_json::json!({
+            "ts": 1700000000,
+            "readings": [{ "sensor_id": "s1", "raw": 23110 }, { "sensor_id": "s2", "raw": 18000 }],
+        });
+        let json = PayloadFormat::Json.encode(&msg);
+        let cbor = PayloadFormat::Cbor.encode(&msg);
+        assert!(cbor.len() < json.len());
+        let decoded: serde_json::Value = ciborium::from_reader(cbor.as_slice()).unwrap();
+        assert_eq!(decoded, msg);
+    }
+}
@@ -0,0 +1,125 @@
+//! Settings pushed by the hub as retained JSON on `cfg/<node_id>/set`.
+//!
+//! The hub derives them from its database (node admin record + sensor
+//! table), so sampling interval and channel wiring are managed in one place
+//! instead of per-node env files.  Anything the hub leaves out falls back to
+//! the node's own env configuration; an empty (cleared) retained message
+//! reverts to it entirely.
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct NodeSettings {
+    /// Seconds between readings (overrides `SAMPLE_EVERY_S`).
+    #[serde(default)]
+    pub sample_interval_sec: Option<u64>,
+    /// Channel → sensor map (overrides `SENSOR_CHANNELS` when non-empty).
+    #[serde(default)]
+    pub channels: Vec<ChannelSettings>,
+}
+
+/// One ADS1115 input and the sensor wired to it.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ChannelSettings {
+    pub channel: usize,
+    /// Local sensor id to report readings under (e.g. "s1").
+    pub sensor_id: String,
+    /// The hub's calibration for this sensor.  Readings are always sent
+    /// raw; the simulator uses these to produce values in the right range.
+    pub raw_dry: i64,
+    pub raw_wet: i64,
+    /// `false` while the sensor is out of service: the channel stays in the
+    /// map but isn't sampled.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Topic the hub publishes this node's settings to.
+pub fn topic(prefix: &str, node_id: &str) -> String {
+    crate::prefixed(prefix, &format!("cfg/{node_id}/set"))
+}
+
+/// Parse a settings payload.  An empty payload means the hub cleared the
+/// retained message (e.g. the node was decommissioned) and yields `None`.
+pub fn parse(payload: &[u8]) -> anyhow::Result<Option<NodeSettings>> {
+    if payload.iter().all(u8::is_ascii_whitespace) {
+        return Ok(None);
+    }
+    let settings: NodeSettings = serde_json::from_slice(payload)?;
+    anyhow::ensure!(
+        settings.sample_interval_sec != Some(0),
+        "sample_interval_sec must be > 0"
+    );
+    for (i, ch) in settings.channels.iter().enumerate() {
+        anyhow::ensure!(
+            !settings.channels[..i]
+                .iter()
+                .any(|other| other.channel == ch.channel),
+            "channel {} assigned more than once",
+            ch.channel
+        );
+    }
+    Ok(Some(settings))
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hub_payload() {
+        let json = br#"{"sample_interval_sec":600,"channels":[
+            {"channel":0,"sensor_id":"s1","raw_dry":26000,"raw_wet":12000},
+            {"channel":2,"sensor_id":"s2","raw_dry":25000,"raw_wet":11000}]}"#;
+        let s = parse(json).unwrap().unwrap();
+        assert_eq!(s.sample_interval_sec, Some(600));
+        assert_eq!(s.channels.len(), 2);
+        assert_eq!(s.channels[1].channel, 2);
+        assert_eq!(s.channels[1].sensor_id, "s2");
+        assert!(s.channels[1].enabled);
+    }
+
+    #[test]
+    fn disabled_channel() {
+        let json = br#"{"channels":[
+            {"channel":0,"sensor_id":"s1","raw_dry":26000,"raw_wet":12000,"enabled":false}]}"#;
+        let s = parse(json).unwrap().unwrap();
+        assert!(!s.channels[0].enabled);
+    }
+
+    #[test]
+    fn missing_fields_fall_back() {
+        let s = parse(br#"{"channels":[]}"#).unwrap().unwrap();
+        assert_eq!(s.sample_interval_sec, None);
+        assert!(s.channels.is_empty());
+    }
+
+    #[test]
+    fn empty_payload_clears() {
+        assert_eq!(parse(b"").unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_invalid() {
+        assert!(parse(b"not json").is_err());
+        assert!(parse(br#"{"sample_interval_sec":0}"#).is_err());
+        let dup = br#"{"channels":[
+            {"channel":1,"sensor_id":"s1","raw_dry":1,"raw_wet":0},
+            {"channel":1,"sensor_id":"s2","raw_dry":1,"raw_wet":0}]}"#;
+        assert!(parse(dup).is_err());
+    }
+
+    #[test]
+    fn topic_format() {
+        assert_eq!(topic("", "node-a"), "cfg/node-a/set");
+        assert_eq!(topic("garden", "node-a"), "garden/cfg/node-a/set");
+    }
+}
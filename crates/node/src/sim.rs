@@ -8,8 +8,31 @@
 //! - Diurnal (day/night) cycle
 //! - Per-sensor calibration offsets
 //! - Closed-loop watering response (moisture increases when valve is open)
+//! - Rain events and failing sensors (`rain` / `dying` scenarios)
+//!
+//! The scenario can be switched while running (see [`scenario_topic`]);
+//! sensors keep their current moisture, so e.g. `rain` starts from
+//! wherever the soil is.
+//!
+//! All randomness comes from the simulator's own [`fastrand::Rng`]: built
+//! from a seeded generator and sampled on a fixed clock
+//! ([`sample_at`](SoilMoistureSim::sample_at)), it produces the same trace
+//! every run.
 
 use std::fmt;
+use std::time::Duration;
+
+/// Samples a rain event keeps wetting the soil for.
+const RAIN_SAMPLES: u32 = 8;
+
+/// ADC change per sample while it rains (negative = wetter).
+const RAIN_RATE: f64 = -400.0;
+
+/// Time a `dying` sensor takes to drift all the way to full scale.
+const DYING_DURATION: Duration = Duration::from_secs(3600);
+
+/// Full-scale ADS1115 reading a dying sensor drifts toward.
+const ADC_MAX: f64 = 32767.0;
 
 // ---------------------------------------------------------------------------
 // Gaussian approximation (no extra dependency)
@@ -17,17 +40,17 @@ use std::fmt;
 
 /// Approximate a sample from N(0,1) using the Irwin-Hall method:
 /// sum of 12 uniform [0,1) values minus 6.
-fn approx_std_normal() -> f64 {
+fn approx_std_normal(rng: &mut fastrand::Rng) -> f64 {
     let mut sum: f64 = 0.0;
     for _ in 0..12 {
-        sum += fastrand::f64();
+        sum += rng.f64();
     }
     sum - 6.0
 }
 
 /// Sample from N(mean, sigma).
-fn gaussian(mean: f64, sigma: f64) -> f64 {
-    mean + sigma * approx_std_normal()
+fn gaussian(rng: &mut fastrand::Rng, mean: f64, sigma: f64) -> f64 {
+    mean + sigma * approx_std_normal(rng)
 }
 
 // ---------------------------------------------------------------------------
@@ -49,17 +72,37 @@ pub enum Scenario {
     /// Starts near wet end.  Very slow drying.  Tests that scheduler
     /// correctly does nothing when moisture is adequate.
     Wet,
+    /// A downpour: moisture rises sharply for [`RAIN_SAMPLES`] samples,
+    /// then decays slowly.  Tests rain-skip logic.
+    Rain,
+    /// A failing sensor: readings drift toward full scale (32767) over
+    /// [`DYING_DURATION`].  Tests plausibility checks and quarantine.
+    Dying,
 }
 
 impl Scenario {
-    pub fn from_str_lossy(s: &str) -> Self {
-        match s.to_ascii_lowercase().as_str() {
-            "stable" => Self::Stable,
-            "flaky" => Self::Flaky,
-            "wet" => Self::Wet,
-            _ => Self::Drying, // default
+    /// Parse a scenario name (case-insensitive).
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "drying" => Some(Self::Drying),
+            "stable" => Some(Self::Stable),
+            "flaky" => Some(Self::Flaky),
+            "wet" => Some(Self::Wet),
+            "rain" => Some(Self::Rain),
+            "dying" => Some(Self::Dying),
+            _ => None,
         }
     }
+
+    pub fn from_str_lossy(s: &str) -> Self {
+        Self::parse(s).unwrap_or(Self::Drying)
+    }
+}
+
+/// Topic a running simulated node takes scenario changes on (payload: the
+/// scenario name, e.g. `rain`).
+pub fn scenario_topic(prefix: &str, node_id: &str) -> String {
+    crate::prefixed(prefix, &format!("sim/scenario/{node_id}"))
 }
 
 impl fmt::Display for Scenario {
@@ -69,6 +112,8 @@ impl fmt::Display for Scenario {
             Self::Stable => write!(f, "stable"),
             Self::Flaky => write!(f, "flaky"),
             Self::Wet => write!(f, "wet"),
+            Self::Rain => write!(f, "rain"),
+            Self::Dying => write!(f, "dying"),
         }
     }
 }
@@ -84,8 +129,47 @@ struct SensorState {
     /// Permanent per-sensor calibration offset (ADC units).  Models the fact
     /// that two sensors in the same soil will not read identically.
     offset: f64,
-    /// Per-sensor noise sigma (ADC units).
+    /// Per-sensor scale of the scenario's noise sigma.
+    noise_factor: f64,
+    /// Samples of the current rain event still to come.
+    rain_left: u32,
+}
+
+/// Random walk, noise and spike parameters of a scenario.
+struct Params {
+    drift: f64,
+    walk_sigma: f64,
+    mean_reversion: f64,
     noise_sigma: f64,
+    spike_prob: f32,
+    spike_sigma: f64,
+    /// Where sensors start: 0.0 = at raw_wet (wettest), 1.0 = at raw_dry
+    /// (driest).
+    start_frac: f64,
+}
+
+impl Params {
+    fn of(scenario: Scenario) -> Self {
+        let (drift, walk_sigma, mean_reversion, noise_sigma, spike_prob, spike_sigma, start_frac) =
+            match scenario {
+                Scenario::Drying => (15.0, 150.0, 0.02, 80.0, 0.03_f32, 2000.0, 0.5),
+                Scenario::Stable => (2.0, 60.0, 0.05, 40.0, 0.005, 1000.0, 0.5),
+                Scenario::Flaky => (10.0, 250.0, 0.02, 200.0, 0.10, 3000.0, 0.5),
+                Scenario::Wet => (3.0, 80.0, 0.02, 60.0, 0.02, 1500.0, 0.2),
+                // Weak mean reversion so the soil stays wet after the rain.
+                Scenario::Rain => (4.0, 80.0, 0.002, 60.0, 0.01, 1500.0, 0.5),
+                Scenario::Dying => (10.0, 150.0, 0.02, 80.0, 0.03, 2000.0, 0.5),
+            };
+        Self {
+            drift,
+            walk_sigma,
+            mean_reversion,
+            noise_sigma,
+            spike_prob,
+            spike_sigma,
+            start_frac,
+        }
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -95,6 +179,7 @@ struct SensorState {
 /// Stateful simulator producing realistic soil moisture ADC readings.
 pub struct SoilMoistureSim {
     sensors: Vec<SensorState>,
+    rng: fastrand::Rng,
 
     // Calibration endpoints (from config.toml)
     raw_dry: f64,
@@ -106,6 +191,9 @@ pub struct SoilMoistureSim {
     mean_reversion: f64,
     center: f64,
 
+    // Electronic noise
+    noise_sigma: f64,
+
     // Spike parameters
     spike_prob: f32,
     spike_sigma: f64,
@@ -117,6 +205,11 @@ pub struct SoilMoistureSim {
     // Watering response
     watering: bool,
     wet_rate: f64,
+
+    // Failing sensor, and when (clock seconds) the drift toward full scale
+    // started; set by the first sample after switching to `dying`.
+    dying: bool,
+    dying_since: Option<f64>,
 }
 
 impl SoilMoistureSim {
@@ -127,7 +220,11 @@ impl SoilMoistureSim {
     ///
     /// `diurnal_period_s` controls the day/night cycle length.  Use 600
     /// (10 min) for fast dev iteration or 86400 for real-time.
+    ///
+    /// All randomness is drawn from `rng`; pass
+    /// `fastrand::Rng::with_seed(..)` for a reproducible trace.
     pub fn new(
+        mut rng: fastrand::Rng,
         scenario: Scenario,
         sensor_count: usize,
         raw_dry: f64,
@@ -136,48 +233,69 @@ impl SoilMoistureSim {
     ) -> Self {
         let range = raw_dry - raw_wet; // typically 14000
         let center = (raw_dry + raw_wet) / 2.0; // ~19000
-
-        let (drift, walk_sigma, mean_rev, noise_sigma, spike_prob, spike_sigma, start_frac) =
-            match scenario {
-                // start_frac: 0.0 = at raw_wet (wettest), 1.0 = at raw_dry (driest)
-                Scenario::Drying => (15.0, 150.0, 0.02, 80.0, 0.03_f32, 2000.0, 0.5),
-                Scenario::Stable => (2.0, 60.0, 0.05, 40.0, 0.005, 1000.0, 0.5),
-                Scenario::Flaky => (10.0, 250.0, 0.02, 200.0, 0.10, 3000.0, 0.5),
-                Scenario::Wet => (3.0, 80.0, 0.02, 60.0, 0.02, 1500.0, 0.2),
-            };
+        let params = Params::of(scenario);
 
         // Starting base in ADC units.  raw_wet + frac * range.
-        let start_base = raw_wet + start_frac * range;
+        let start_base = raw_wet + params.start_frac * range;
 
         // Per-sensor: randomise initial base slightly and assign a permanent
         // calibration offset so sensors diverge naturally.
         let sensors = (0..sensor_count)
             .map(|_| {
-                let jitter = gaussian(0.0, range * 0.03); // +-~3% of range
-                let offset = gaussian(0.0, range * 0.02); // permanent shift
-                let sensor_noise = noise_sigma * (1.0 + 0.2 * approx_std_normal()).max(0.3);
+                let jitter = gaussian(&mut rng, 0.0, range * 0.03); // +-~3% of range
+                let offset = gaussian(&mut rng, 0.0, range * 0.02); // permanent shift
                 SensorState {
                     base: (start_base + jitter).clamp(raw_wet, raw_dry),
                     offset,
-                    noise_sigma: sensor_noise,
+                    noise_factor: (1.0 + 0.2 * approx_std_normal(&mut rng)).max(0.3),
+                    rain_left: 0,
                 }
             })
             .collect();
 
-        Self {
+        let mut sim = Self {
             sensors,
+            rng,
             raw_dry,
             raw_wet,
-            drift_per_sample: drift,
-            walk_sigma,
-            mean_reversion: mean_rev,
+            drift_per_sample: 0.0,
+            walk_sigma: 0.0,
+            mean_reversion: 0.0,
             center,
-            spike_prob,
-            spike_sigma,
+            noise_sigma: 0.0,
+            spike_prob: 0.0,
+            spike_sigma: 0.0,
             diurnal_amplitude: range * 0.06, // ~6% of range (~840 for 14000)
             diurnal_period_s,
             watering: false,
             wet_rate: -300.0,
+            dying: false,
+            dying_since: None,
+        };
+        sim.set_scenario(scenario);
+        sim
+    }
+
+    /// Switch to `scenario`, keeping each sensor's current moisture.
+    /// `rain` starts a new rain event and `dying` restarts the drift to
+    /// full scale; any other scenario ends a running one (a "replaced"
+    /// sensor).
+    pub fn set_scenario(&mut self, scenario: Scenario) {
+        let params = Params::of(scenario);
+        self.drift_per_sample = params.drift;
+        self.walk_sigma = params.walk_sigma;
+        self.mean_reversion = params.mean_reversion;
+        self.noise_sigma = params.noise_sigma;
+        self.spike_prob = params.spike_prob;
+        self.spike_sigma = params.spike_sigma;
+        self.dying = scenario == Scenario::Dying;
+        self.dying_since = None;
+        for sensor in &mut self.sensors {
+            sensor.rain_left = if scenario == Scenario::Rain {
+                RAIN_SAMPLES
+            } else {
+                0
+            };
         }
     }
 
@@ -191,6 +309,17 @@ impl SoilMoistureSim {
     /// Call this once per sensor per sampling tick.  The internal base value
     /// evolves with each call, so the order and frequency of calls matters.
     pub fn sample(&mut self, index: usize) -> i32 {
+        let now_s = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        self.sample_at(index, now_s)
+    }
+
+    /// [`sample`](Self::sample) at clock time `now_s` (seconds) instead of
+    /// the wall clock.
+    pub fn sample_at(&mut self, index: usize, now_s: f64) -> i32 {
+        let rng = &mut self.rng;
         let sensor = &mut self.sensors[index];
 
         // -- Evolve the base value ----------------------------------------
@@ -199,7 +328,7 @@ impl SoilMoistureSim {
         let pull = self.mean_reversion * (self.center - sensor.base);
 
         // Random walk step
-        let walk = gaussian(0.0, self.walk_sigma);
+        let walk = gaussian(rng, 0.0, self.walk_sigma);
 
         // Drying drift (positive = toward raw_dry = drier)
         let drift = self.drift_per_sample;
@@ -207,34 +336,45 @@ impl SoilMoistureSim {
         // Watering effect (negative = toward raw_wet = wetter)
         let wet = if self.watering { self.wet_rate } else { 0.0 };
 
-        sensor.base = (sensor.base + drift + pull + walk + wet)
+        // Rain event
+        let rain = if sensor.rain_left > 0 {
+            sensor.rain_left -= 1;
+            RAIN_RATE
+        } else {
+            0.0
+        };
+
+        sensor.base = (sensor.base + drift + pull + walk + wet + rain)
             .clamp(self.raw_wet - 500.0, self.raw_dry + 500.0);
 
         // -- Build the instantaneous reading ------------------------------
 
         // Diurnal offset: sinusoidal, peaks at "afternoon" (period/2).
-        let now_s = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs_f64();
         let phase = 2.0 * std::f64::consts::PI * now_s / self.diurnal_period_s;
         let diurnal = self.diurnal_amplitude * phase.sin();
 
         // Electronic noise
-        let noise = gaussian(0.0, sensor.noise_sigma);
+        let noise = gaussian(rng, 0.0, self.noise_sigma * sensor.noise_factor);
 
         // Occasional spike (sensor flakiness)
-        let spike = if fastrand::f32() < self.spike_prob {
-            gaussian(0.0, self.spike_sigma)
+        let spike = if rng.f32() < self.spike_prob {
+            gaussian(rng, 0.0, self.spike_sigma)
         } else {
             0.0
         };
 
-        let reading = sensor.base + sensor.offset + diurnal + noise + spike;
+        let mut reading = sensor.base + sensor.offset + diurnal + noise + spike;
+
+        // Failing sensor: blend toward full scale as it dies.
+        if self.dying {
+            let since = *self.dying_since.get_or_insert(now_s);
+            let frac = ((now_s - since) / DYING_DURATION.as_secs_f64()).clamp(0.0, 1.0);
+            reading += (ADC_MAX - reading) * frac;
+        }
 
         // Clamp to physically possible ADC range (ADS1115: 0..32767) and
         // round to integer.
-        reading.round().clamp(0.0, 32767.0) as i32
+        reading.round().clamp(0.0, ADC_MAX) as i32
     }
 
     /// Number of sensor channels in this simulator.
@@ -258,7 +398,14 @@ mod tests {
 
     #[test]
     fn readings_within_adc_range() {
-        let mut sim = SoilMoistureSim::new(Scenario::Drying, 2, 26000.0, 12000.0, 600.0);
+        let mut sim = SoilMoistureSim::new(
+            fastrand::Rng::new(),
+            Scenario::Drying,
+            2,
+            26000.0,
+            12000.0,
+            600.0,
+        );
         for _ in 0..500 {
             for i in 0..2 {
                 let v = sim.sample(i);
@@ -270,7 +417,14 @@ mod tests {
     #[test]
     fn temporal_coherence() {
         // Consecutive readings should be much closer than the full range.
-        let mut sim = SoilMoistureSim::new(Scenario::Stable, 1, 26000.0, 12000.0, 600.0);
+        let mut sim = SoilMoistureSim::new(
+            fastrand::Rng::new(),
+            Scenario::Stable,
+            1,
+            26000.0,
+            12000.0,
+            600.0,
+        );
         let samples = collect_samples(&mut sim, 100);
         let max_jump: i32 = samples
             .windows(2)
@@ -288,7 +442,14 @@ mod tests {
     #[test]
     fn per_sensor_variation() {
         // Two sensors should produce different readings.
-        let mut sim = SoilMoistureSim::new(Scenario::Drying, 2, 26000.0, 12000.0, 600.0);
+        let mut sim = SoilMoistureSim::new(
+            fastrand::Rng::new(),
+            Scenario::Drying,
+            2,
+            26000.0,
+            12000.0,
+            600.0,
+        );
         let mut diffs = 0_u32;
         for _ in 0..50 {
             let a = sim.sample(0);
@@ -305,7 +466,14 @@ mod tests {
     fn watering_decreases_readings() {
         // When watering is active, readings should trend downward (wetter =
         // lower ADC).
-        let mut sim = SoilMoistureSim::new(Scenario::Drying, 1, 26000.0, 12000.0, 600.0);
+        let mut sim = SoilMoistureSim::new(
+            fastrand::Rng::new(),
+            Scenario::Drying,
+            1,
+            26000.0,
+            12000.0,
+            600.0,
+        );
 
         // Warm up and record baseline.
         for _ in 0..20 {
@@ -339,8 +507,22 @@ mod tests {
                 / n as f64
         }
 
-        let mut stable = SoilMoistureSim::new(Scenario::Stable, 1, 26000.0, 12000.0, 600.0);
-        let mut flaky = SoilMoistureSim::new(Scenario::Flaky, 1, 26000.0, 12000.0, 600.0);
+        let mut stable = SoilMoistureSim::new(
+            fastrand::Rng::new(),
+            Scenario::Stable,
+            1,
+            26000.0,
+            12000.0,
+            600.0,
+        );
+        let mut flaky = SoilMoistureSim::new(
+            fastrand::Rng::new(),
+            Scenario::Flaky,
+            1,
+            26000.0,
+            12000.0,
+            600.0,
+        );
 
         let var_stable = variance(&mut stable, 200);
         let var_flaky = variance(&mut flaky, 200);
@@ -367,12 +549,113 @@ mod tests {
         assert_eq!(Scenario::Stable.to_string(), "stable");
         assert_eq!(Scenario::Flaky.to_string(), "flaky");
         assert_eq!(Scenario::Wet.to_string(), "wet");
+        assert_eq!(Scenario::Rain.to_string(), "rain");
+        assert_eq!(Scenario::Dying.to_string(), "dying");
+    }
+
+    #[test]
+    fn scenario_parse_is_strict() {
+        assert_eq!(Scenario::parse(" Rain\n"), Some(Scenario::Rain));
+        assert_eq!(Scenario::parse("dying"), Some(Scenario::Dying));
+        assert_eq!(Scenario::parse("hail"), None);
+        assert_eq!(scenario_topic("", "node-a"), "sim/scenario/node-a");
+    }
+
+    #[test]
+    fn rain_raises_moisture_then_decays_slowly() {
+        let mut sim = SoilMoistureSim::new(
+            fastrand::Rng::new(),
+            Scenario::Stable,
+            1,
+            26000.0,
+            12000.0,
+            600.0,
+        );
+        let before: f64 = (0..10).map(|_| sim.sample(0) as f64).sum::<f64>() / 10.0;
+
+        sim.set_scenario(Scenario::Rain);
+        for _ in 0..RAIN_SAMPLES {
+            sim.sample(0);
+        }
+        let after_rain: f64 = (0..10).map(|_| sim.sample(0) as f64).sum::<f64>() / 10.0;
+        assert!(
+            after_rain < before - 2000.0,
+            "rain should wet the soil: before={before:.0} after={after_rain:.0}"
+        );
+
+        // Still wetter than before well after the rain stopped.
+        for _ in 0..50 {
+            sim.sample(0);
+        }
+        let later: f64 = (0..10).map(|_| sim.sample(0) as f64).sum::<f64>() / 10.0;
+        assert!(
+            later < before,
+            "rain should decay slowly: before={before:.0} later={later:.0}"
+        );
+    }
+
+    #[test]
+    fn dying_sensor_drifts_to_full_scale() {
+        let mut sim = SoilMoistureSim::new(
+            fastrand::Rng::new(),
+            Scenario::Dying,
+            1,
+            26000.0,
+            12000.0,
+            600.0,
+        );
+        let fresh = sim.sample_at(0, 1000.0);
+        assert!(
+            fresh < 30000,
+            "a new dying sensor still reads normally: {fresh}"
+        );
+        let half = sim.sample_at(0, 1000.0 + DYING_DURATION.as_secs_f64() / 2.0);
+        assert!(half > fresh + 3000, "half dead: {half} vs {fresh}");
+        assert_eq!(
+            sim.sample_at(0, 1000.0 + DYING_DURATION.as_secs_f64()),
+            32767
+        );
+
+        // Switching away "replaces" the sensor.
+        sim.set_scenario(Scenario::Stable);
+        assert!(sim.sample_at(0, 10_000.0) < 30000);
+    }
+
+    #[test]
+    fn seeded_sims_repeat_their_trace() {
+        let trace = |seed: u64| {
+            let mut sim = SoilMoistureSim::new(
+                fastrand::Rng::with_seed(seed),
+                Scenario::Flaky,
+                2,
+                26000.0,
+                12000.0,
+                600.0,
+            );
+            (0..50)
+                .flat_map(|t| {
+                    [
+                        sim.sample_at(0, t as f64 * 5.0),
+                        sim.sample_at(1, t as f64 * 5.0),
+                    ]
+                })
+                .collect::<Vec<i32>>()
+        };
+        assert_eq!(trace(7), trace(7));
+        assert_ne!(trace(7), trace(8));
     }
 
     #[test]
     fn wet_scenario_starts_low() {
         // Wet scenario should start near the wet end (lower ADC values).
-        let mut sim = SoilMoistureSim::new(Scenario::Wet, 1, 26000.0, 12000.0, 600.0);
+        let mut sim = SoilMoistureSim::new(
+            fastrand::Rng::new(),
+            Scenario::Wet,
+            1,
+            26000.0,
+            12000.0,
+            600.0,
+        );
         let avg: f64 = (0..10).map(|_| sim.sample(0) as f64).sum::<f64>() / 10.0;
         let midpoint = (26000.0 + 12000.0) / 2.0;
         assert!(
@@ -384,7 +667,8 @@ mod tests {
     #[test]
     fn approx_std_normal_has_zero_mean() {
         let n = 5000;
-        let sum: f64 = (0..n).map(|_| approx_std_normal()).sum();
+        let mut rng = fastrand::Rng::new();
+        let sum: f64 = (0..n).map(|_| approx_std_normal(&mut rng)).sum();
         let mean = sum / n as f64;
         // Mean should be close to zero.  With n=5000 the std error is
         // 1/sqrt(5000) ≈ 0.014, so ±0.1 is generous.
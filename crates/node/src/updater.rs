@@ -0,0 +1,474 @@
+//! Over-the-air updates.  The hub announces a signed node binary on the
+//! retained `cfg/<node_id>/ota` and serves it over HTTP:
+//!
+//! ```json
+//! { "version": "0.2.0", "url": "http://hub:8080/ota/irrigation-node-0.2.0",
+//!   "size": 4194304, "sha256": "<hex>", "signature": "<hex>" }
+//! ```
+//!
+//! The updater task downloads it, checks its size, SHA-256 and Ed25519
+//! signature against `OTA_PUBLIC_KEY`, writes it over the running binary
+//! (the old one is kept as `<binary>.prev`) and restarts the node the same
+//! way the `restart` command does; systemd starts the new version.  The
+//! signature covers the version as well as the image (see
+//! [`signed_message`]), and only versions newer than the running one are
+//! installed, so an old signed build can't be announced to roll a node
+//! back.  An announcement of the version already running is ignored, and a
+//! failed one isn't retried until the hub announces again or the node
+//! restarts.
+//!
+//! Progress goes to `ota/<node_id>/status` (retained) as
+//! `{ "ts", "state", "version", "error" }`, `state` being `running` (sent
+//! on every connect, with the running version), `downloading`,
+//! `installed` or `failed`.  Without a public key every announcement
+//! fails.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Context, Result};
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// The running version.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Largest binary accepted.
+pub const MAX_IMAGE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Give up on a download after this long.
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Topic the hub announces updates for `node_id` on.
+pub fn announce_topic(prefix: &str, node_id: &str) -> String {
+    crate::prefixed(prefix, &format!("cfg/{node_id}/ota"))
+}
+
+/// Topic update progress is reported on.
+pub fn status_topic(prefix: &str, node_id: &str) -> String {
+    crate::prefixed(prefix, &format!("ota/{node_id}/status"))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Announcement {
+    pub version: String,
+    /// Plain `http://`: the signature, not the transport, vouches for the
+    /// binary.
+    pub url: String,
+    pub size: u64,
+    pub sha256: String,
+    pub signature: String,
+}
+
+/// Parse an announcement.  An empty payload means the hub cleared it and
+/// yields `None`.
+pub fn parse_announcement(payload: &[u8]) -> Result<Option<Announcement>> {
+    if payload.iter().all(u8::is_ascii_whitespace) {
+        return Ok(None);
+    }
+    let ann: Announcement = serde_json::from_slice(payload)?;
+    ensure!(!ann.version.trim().is_empty(), "version must not be empty");
+    ensure!(
+        ann.url.starts_with("http://"),
+        "url must be http://, got {:?}",
+        ann.url
+    );
+    ensure!(
+        (1..=MAX_IMAGE_BYTES).contains(&ann.size),
+        "size must be 1..={MAX_IMAGE_BYTES} bytes, got {}",
+        ann.size
+    );
+    ensure!(
+        decode_hex(&ann.sha256).is_some_and(|d| d.len() == 32),
+        "sha256 must be 64 hex digits"
+    );
+    ensure!(
+        decode_hex(&ann.signature).is_some_and(|s| s.len() == 64),
+        "signature must be 128 hex digits"
+    );
+    Ok(Some(ann))
+}
+
+/// Parse `OTA_PUBLIC_KEY`: a raw Ed25519 public key as 64 hex digits.
+pub fn parse_public_key(raw: &str) -> Result<VerifyingKey> {
+    let bytes: [u8; 32] = decode_hex(raw)
+        .and_then(|b| b.try_into().ok())
+        .context("OTA public key must be 64 hex digits")?;
+    VerifyingKey::from_bytes(&bytes).context("OTA public key is not a valid Ed25519 key")
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    let s = s.trim();
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// What a release is signed over: its version and the image's SHA-256 (hex),
+/// as `irrigation-node <version> <sha256>`.
+pub fn signed_message(version: &str, sha256: &[u8]) -> String {
+    let hex: String = sha256.iter().map(|b| format!("{b:02x}")).collect();
+    format!("irrigation-node {version} {hex}")
+}
+
+/// Whether `candidate` is a newer version than `running`.  Versions are
+/// dot-separated numbers with an optional `-pre-release` (and `+build`,
+/// ignored); a release is newer than its pre-releases, and pre-releases
+/// compare identifier by identifier as semver does (`rc.10` > `rc.9`).
+/// Anything else is never newer.
+pub fn is_newer(candidate: &str, running: &str) -> bool {
+    fn parse(v: &str) -> Option<(Vec<u64>, Option<&str>)> {
+        let v = v.split('+').next()?;
+        let (numbers, pre) = match v.split_once('-') {
+            Some((n, pre)) => (n, Some(pre)),
+            None => (v, None),
+        };
+        let numbers = numbers
+            .split('.')
+            .map(|n| n.parse().ok())
+            .collect::<Option<Vec<u64>>>()?;
+        Some((numbers, pre))
+    }
+    let (Some((cand, cand_pre)), Some((run, run_pre))) = (parse(candidate), parse(running)) else {
+        return false;
+    };
+    match cand.cmp(&run) {
+        std::cmp::Ordering::Equal => match (cand_pre, run_pre) {
+            (None, Some(_)) => true,
+            (Some(c), Some(r)) => cmp_pre_release(c, r).is_gt(),
+            _ => false,
+        },
+        order => order.is_gt(),
+    }
+}
+
+/// Semver §11 pre-release precedence: identifiers are compared in turn,
+/// numerically when both are numeric, otherwise as ASCII, with numeric
+/// below alphanumeric; a longer list wins when all shared ones are equal.
+fn cmp_pre_release(a: &str, b: &str) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+    let numeric = |id: &str| -> Option<u64> {
+        if id.is_empty() || !id.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        id.parse().ok()
+    };
+    let (mut a, mut b) = (a.split('.'), b.split('.'));
+    loop {
+        let order = match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => match (numeric(x), numeric(y)) {
+                (Some(x), Some(y)) => x.cmp(&y),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => x.cmp(y),
+            },
+        };
+        if order.is_ne() {
+            return order;
+        }
+    }
+}
+
+/// Check a downloaded binary against its announcement.
+pub fn verify(image: &[u8], ann: &Announcement, key: &VerifyingKey) -> Result<()> {
+    ensure!(
+        image.len() as u64 == ann.size,
+        "size mismatch: got {} bytes, announced {}",
+        image.len(),
+        ann.size
+    );
+    let digest = Sha256::digest(image);
+    ensure!(
+        decode_hex(&ann.sha256).as_deref() == Some(digest.as_slice()),
+        "sha256 mismatch"
+    );
+    let signature: [u8; 64] = decode_hex(&ann.signature)
+        .and_then(|s| s.try_into().ok())
+        .context("malformed signature")?;
+    let message = signed_message(&ann.version, &digest);
+    key.verify_strict(message.as_bytes(), &Signature::from_bytes(&signature))
+        .context("signature does not verify against OTA_PUBLIC_KEY")
+}
+
+/// Write `image` over the binary at `path`, keeping the current one as
+/// `<path>.prev`.  The new binary is written beside it and renamed into
+/// place, so an interrupted install leaves the old one intact.
+pub fn install(path: &Path, image: &[u8]) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let with_suffix = |suffix: &str| {
+        let mut p = path.as_os_str().to_owned();
+        p.push(suffix);
+        PathBuf::from(p)
+    };
+    let staged = with_suffix(".new");
+    std::fs::write(&staged, image)
+        .with_context(|| format!("failed to write {}", staged.display()))?;
+    std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o755))?;
+    std::fs::File::open(&staged)?.sync_all()?;
+    if path.exists() {
+        std::fs::copy(path, with_suffix(".prev"))
+            .with_context(|| format!("failed to keep a copy of {}", path.display()))?;
+    }
+    std::fs::rename(&staged, path)
+        .with_context(|| format!("failed to replace {}", path.display()))?;
+    Ok(())
+}
+
+fn download(url: &str, size: u64) -> Result<Vec<u8>> {
+    let response = ureq::AgentBuilder::new()
+        .timeout(DOWNLOAD_TIMEOUT)
+        .build()
+        .get(url)
+        .call()
+        .with_context(|| format!("download from {url} failed"))?;
+    let mut image = Vec::with_capacity(size as usize);
+    // One byte over the announced size is enough to tell it's wrong.
+    response
+        .into_reader()
+        .take(size + 1)
+        .read_to_end(&mut image)
+        .context("download interrupted")?;
+    Ok(image)
+}
+
+/// Where updates are installed and the key they must be signed with.
+#[derive(Debug, Clone)]
+pub struct Updater {
+    /// Unset: updates are refused.
+    pub key: Option<VerifyingKey>,
+    pub install_path: PathBuf,
+}
+
+impl Updater {
+    /// Download, verify and install `ann`.  Blocks; run it off the async
+    /// runtime.
+    pub fn apply(&self, ann: &Announcement) -> Result<()> {
+        let Some(key) = &self.key else {
+            bail!("updates are disabled: OTA_PUBLIC_KEY is not set");
+        };
+        ensure!(
+            is_newer(&ann.version, VERSION),
+            "{} is not newer than the running {VERSION}",
+            ann.version
+        );
+        let image = download(&ann.url, ann.size)?;
+        verify(&image, ann, key)?;
+        install(&self.install_path, &image)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum State {
+    Running,
+    Downloading,
+    Installed,
+    Failed,
+}
+
+/// A progress message for [`status_topic`].
+#[derive(Debug, Serialize)]
+pub struct Report<'a> {
+    pub ts: i64,
+    pub state: State,
+    pub version: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl<'a> Report<'a> {
+    pub fn new(state: State, version: &'a str) -> Self {
+        Self {
+            ts: crate::now_unix(),
+            state,
+            version,
+            error: None,
+        }
+    }
+
+    pub fn failed(version: &'a str, error: &anyhow::Error) -> Self {
+        Self {
+            error: Some(format!("{error:#}")),
+            ..Self::new(State::Failed, version)
+        }
+    }
+
+    pub fn to_json(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("ota report serialization failed")
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    fn signed(image: &[u8]) -> (Announcement, VerifyingKey) {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let digest = Sha256::digest(image);
+        let message = signed_message("0.2.0", &digest);
+        let ann = Announcement {
+            version: "0.2.0".into(),
+            url: "http://hub:8080/ota/irrigation-node-0.2.0".into(),
+            size: image.len() as u64,
+            sha256: hex(&digest),
+            signature: hex(&key.sign(message.as_bytes()).to_bytes()),
+        };
+        (ann, key.verifying_key())
+    }
+
+    #[test]
+    fn parse_announcements() {
+        let (ann, _) = signed(b"binary");
+        let json = serde_json::json!({
+            "version": ann.version,
+            "url": ann.url,
+            "size": ann.size,
+            "sha256": ann.sha256,
+            "signature": ann.signature,
+        });
+        let payload = json.to_string();
+        assert_eq!(
+            parse_announcement(payload.as_bytes()).unwrap(),
+            Some(ann.clone())
+        );
+        assert_eq!(parse_announcement(b"").unwrap(), None);
+
+        let with = |field: &str, value: serde_json::Value| {
+            let mut bad = json.clone();
+            bad[field] = value;
+            parse_announcement(bad.to_string().as_bytes())
+        };
+        assert!(with("url", "https://hub/ota/x".into()).is_err());
+        assert!(with("size", 0.into()).is_err());
+        assert!(with("sha256", "abc".into()).is_err());
+        assert!(with("signature", "zz".repeat(64).into()).is_err());
+        assert!(with("version", " ".into()).is_err());
+        assert!(with("extra", true.into()).is_err());
+    }
+
+    #[test]
+    fn verify_checks_size_digest_and_signature() {
+        let image = b"new node binary".to_vec();
+        let (ann, key) = signed(&image);
+        verify(&image, &ann, &key).unwrap();
+
+        let mut tampered = image.clone();
+        tampered[0] ^= 1;
+        let err = verify(&tampered, &ann, &key).unwrap_err();
+        assert!(err.to_string().contains("sha256"), "{err}");
+
+        assert!(verify(&image[1..], &ann, &key).is_err());
+
+        // Right digest, signed by someone else.
+        let other = SigningKey::from_bytes(&[9; 32]).verifying_key();
+        let err = verify(&image, &ann, &other).unwrap_err();
+        assert!(err.to_string().contains("signature"), "{err}");
+
+        // The same signed image announced as another version.
+        let relabelled = Announcement {
+            version: "0.3.0".into(),
+            ..ann.clone()
+        };
+        let err = verify(&image, &relabelled, &key).unwrap_err();
+        assert!(err.to_string().contains("signature"), "{err}");
+
+        // An image signed directly, without its version.
+        let bare = Announcement {
+            signature: hex(&SigningKey::from_bytes(&[7; 32]).sign(&image).to_bytes()),
+            ..ann
+        };
+        assert!(verify(&image, &bare, &key).is_err());
+    }
+
+    #[test]
+    fn only_newer_versions_install() {
+        assert!(is_newer("0.2.0", "0.1.0"));
+        assert!(is_newer("0.10.0", "0.9.3"));
+        assert!(is_newer("1.0", "0.9.9"));
+        assert!(is_newer("0.2.0", "0.2.0-rc1"));
+        assert!(is_newer("0.2.0-rc2", "0.2.0-rc1"));
+        assert!(is_newer("0.2.0-rc.10", "0.2.0-rc.9"));
+        assert!(is_newer("0.2.0-rc.1", "0.2.0-rc"));
+        assert!(is_newer("0.2.0-rc.1", "0.2.0-beta.11"));
+        assert!(is_newer("0.2.0-alpha.beta", "0.2.0-alpha.1"));
+        assert!(is_newer("0.2.1+g1a2b3c", "0.2.0"));
+        for (candidate, running) in [
+            ("0.1.0", "0.2.0"),
+            ("0.2.0", "0.2.0"),
+            ("0.2.0+other", "0.2.0"),
+            ("0.2.0-rc1", "0.2.0"),
+            ("0.2.0-rc.9", "0.2.0-rc.10"),
+            ("0.2.0-rc.2", "0.2.0-rc.2"),
+            ("latest", "0.2.0"),
+            ("0.3.0", "dev"),
+        ] {
+            assert!(!is_newer(candidate, running), "{candidate} vs {running}");
+        }
+
+        let updater = Updater {
+            key: Some(SigningKey::from_bytes(&[7; 32]).verifying_key()),
+            install_path: PathBuf::from("/nonexistent/irrigation-node"),
+        };
+        let (mut ann, _) = signed(b"old build");
+        ann.version = "0.0.1".into();
+        let err = updater.apply(&ann).unwrap_err();
+        assert!(err.to_string().contains("not newer"), "{err}");
+    }
+
+    #[test]
+    fn public_key_parsing() {
+        let key = SigningKey::from_bytes(&[7; 32]).verifying_key();
+        assert_eq!(parse_public_key(&hex(key.as_bytes())).unwrap(), key);
+        assert!(parse_public_key("abcd").is_err());
+        assert!(parse_public_key(&"g".repeat(64)).is_err());
+    }
+
+    #[test]
+    fn install_swaps_the_binary_and_keeps_the_old_one() {
+        let dir = std::env::temp_dir().join(format!("irrigation-ota-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let bin = dir.join("irrigation-node");
+        std::fs::write(&bin, b"old").unwrap();
+
+        install(&bin, b"new").unwrap();
+        assert_eq!(std::fs::read(&bin).unwrap(), b"new");
+        assert_eq!(
+            std::fs::read(dir.join("irrigation-node.prev")).unwrap(),
+            b"old"
+        );
+        assert!(!dir.join("irrigation-node.new").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn report_json() {
+        let report = Report::failed("0.2.0", &anyhow::anyhow!("sha256 mismatch"));
+        let json: serde_json::Value = serde_json::from_slice(&report.to_json()).unwrap();
+        assert_eq!(json["state"], "failed");
+        assert_eq!(json["error"], "sha256 mismatch");
+        let running: serde_json::Value =
+            serde_json::from_slice(&Report::new(State::Running, VERSION).to_json()).unwrap();
+        assert_eq!(running["version"], VERSION);
+        assert!(running.get("error").is_none());
+    }
+}